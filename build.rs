@@ -0,0 +1,18 @@
+//! Build script - compiles the gRPC API definition when the `grpc` feature is enabled
+
+fn main() {
+    println!("cargo:rerun-if-changed=proto/leann.proto");
+
+    #[cfg(feature = "grpc")]
+    {
+        // Use the vendored protoc unless one is given explicitly
+        if std::env::var_os("PROTOC").is_none() {
+            let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this platform");
+            std::env::set_var("PROTOC", protoc);
+        }
+        // The client is only used by the end-to-end tests
+        tonic_build::configure()
+            .compile_protos(&["proto/leann.proto"], &["proto"])
+            .expect("failed to compile proto/leann.proto");
+    }
+}
@@ -0,0 +1,169 @@
+//! Archive ingestion (.zip, .tar, .tar.gz)
+//!
+//! Archives given with `--docs` are read in memory, entry by entry, without
+//! extracting them to disk. Each supported entry is indexed as a document
+//! whose `source` is `archive.zip!path/inside.md`.
+
+use std::path::Path;
+#[cfg(feature = "archives")]
+use std::{io::Read, path::PathBuf};
+
+/// Archive file types expanded during build
+const ARCHIVE_TYPES: &[&str] = &[".zip", ".tar", ".tar.gz", ".tgz"];
+
+/// Whether a path names a supported archive
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    ARCHIVE_TYPES.iter().any(|ext| name.ends_with(ext))
+}
+
+/// The archive an `archive!entry` source points into, if any
+pub fn archive_of(source: &str) -> Option<&str> {
+    let (archive, entry) = source.split_once('!')?;
+    (!entry.is_empty() && is_archive(Path::new(archive))).then_some(archive)
+}
+
+/// Read the entries of an archive that `accept` keeps (by entry path and size)
+///
+/// Returns `(source, content)` pairs, where `source` is `archive!entry`.
+/// Hidden entries, macOS resource forks, and entries that aren't text (or,
+/// with the `pdf` feature, PDFs) are skipped.
+#[cfg(feature = "archives")]
+pub fn read_entries(path: &Path, accept: impl Fn(&Path, u64) -> bool) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let file = std::fs::File::open(path)?;
+    let name = path.to_string_lossy().to_lowercase();
+    let mut entries = Vec::new();
+    let mut add = |entry_name: &str, bytes: Vec<u8>| {
+        let entry_path = Path::new(entry_name);
+        if let Some(content) = entry_text(entry_path, bytes) {
+            entries.push((PathBuf::from(format!("{}!{}", path.to_string_lossy(), entry_name)), content));
+        }
+    };
+
+    if name.ends_with(".zip") {
+        let mut archive = zip::ZipArchive::new(std::io::BufReader::new(file))
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let entry_name = entry.name().to_string();
+            if entry.is_dir() || !is_indexable(&entry_name) || !accept(Path::new(&entry_name), entry.size()) {
+                continue;
+            }
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut bytes)?;
+            add(&entry_name, bytes);
+        }
+    } else {
+        let reader: Box<dyn Read> = if name.ends_with(".tar") {
+            Box::new(std::io::BufReader::new(file))
+        } else {
+            Box::new(flate2::read::GzDecoder::new(std::io::BufReader::new(file)))
+        };
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_name = entry.path()?.to_string_lossy().to_string();
+            if !entry.header().entry_type().is_file()
+                || !is_indexable(&entry_name)
+                || !accept(Path::new(&entry_name), entry.size())
+            {
+                continue;
+            }
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut bytes)?;
+            add(&entry_name, bytes);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Skip hidden files and folders and `__MACOSX` resource forks
+#[cfg(feature = "archives")]
+fn is_indexable(entry_name: &str) -> bool {
+    entry_name
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .all(|c| !c.starts_with('.') && c != "__MACOSX")
+}
+
+/// Text of an archive entry: UTF-8 text, or extracted PDF text
+#[cfg(feature = "archives")]
+fn entry_text(entry_path: &Path, bytes: Vec<u8>) -> Option<String> {
+    #[cfg(feature = "pdf")]
+    if entry_path.extension().is_some_and(|e| e.eq_ignore_ascii_case("pdf")) {
+        return match pdf_extract::extract_text_from_mem(&bytes) {
+            Ok(text) if !text.trim().is_empty() => Some(text.trim().to_string()),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Failed to extract text from {}: {}", entry_path.display(), e);
+                None
+            }
+        };
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        Err(_) => {
+            tracing::debug!("Skipping non-UTF-8 archive entry {}", entry_path.display());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_paths() {
+        assert!(is_archive(Path::new("docs/bundle.zip")));
+        assert!(is_archive(Path::new("export.TAR.GZ")));
+        assert!(is_archive(Path::new("export.tgz")));
+        assert!(!is_archive(Path::new("notes.gz")));
+        assert_eq!(archive_of("docs/bundle.zip!guide/intro.md"), Some("docs/bundle.zip"));
+        assert_eq!(archive_of("notes/Hello!.md"), None);
+        assert_eq!(archive_of("bundle.zip!"), None);
+    }
+
+    #[cfg(feature = "archives")]
+    #[test]
+    fn test_read_zip_entries() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("leann-archive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bundle.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, content) in [
+            ("guide/intro.md", "# Intro\nHello".as_bytes()),
+            ("guide/.hidden.md", b"secret"),
+            ("__MACOSX/guide/._intro.md", b"fork"),
+            ("logo.png", b"\x89PNG"),
+            ("data/bad.md", b"\xff\xfe"),
+        ] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let tar_path = dir.join("bundle.tar.gz");
+        let gz = flate2::write::GzEncoder::new(std::fs::File::create(&tar_path).unwrap(), Default::default());
+        let mut builder = tar::Builder::new(gz);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "notes/a.md", "Hello".as_bytes()).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let is_md = |p: &Path, _| p.extension().is_some_and(|e| e == "md");
+        let entries = read_entries(&path, is_md).unwrap();
+        let tar_entries = read_entries(&tar_path, is_md).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, PathBuf::from(format!("{}!guide/intro.md", path.display())));
+        assert_eq!(entries[0].1, "# Intro\nHello");
+        assert_eq!(tar_entries, vec![(PathBuf::from(format!("{}!notes/a.md", tar_path.display())), "Hello".to_string())]);
+    }
+}
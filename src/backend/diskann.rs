@@ -1,45 +1,518 @@
-//! DiskANN backend using diskann-rs crate
+//! DiskANN backend - a Vamana graph searched from disk
 //!
-//! Provides disk-based approximate nearest neighbor search using the Vamana graph algorithm.
-//! Memory-efficient for larger-than-RAM datasets.
+//! Every passage is a node with at most `max_degree` out-links, built with
+//! Vamana's alpha-pruning so that a greedy walk from the medoid reaches any
+//! neighborhood in few hops. Each node's vector and neighbor list share one
+//! fixed-size record in `documents.vamana`:
+//!
+//! ```text
+//! header (64 bytes): magic, version, dims, max_degree, build_list_size,
+//!                    alpha, beam_width, count, medoid
+//! record i:          dims x f32 | degree: u32 | max_degree x u32 neighbors
+//! ```
+//!
+//! The file is memory-mapped, and a beam search reads only the records on
+//! its path, so resident memory is bounded by the pages a query touches
+//! rather than the index size. Updates insert new nodes into the graph
+//! (loaded into memory for the update) and rewrite the file.
 
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 
-use anndists::dist::distances::DistDot;
-use diskann_rs::{DiskANN, DiskAnnParams};
+use memmap2::Mmap;
 use tracing::info;
 
-use super::traits::BackendSearcher;
+use super::kmeans::XorShift;
+use super::traits::{Backend, BackendError, BackendInfo, BackendSearcher, BuildParams};
 
-/// DiskANN searcher using diskann-rs
-pub struct DiskAnnSearcher {
-    index: DiskANN<DistDot>,
+const MAGIC: &[u8; 8] = b"LEANNVAM";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 64;
+
+/// Nodes inserted per round of parallel neighbor searches during a build
+const BUILD_BATCH: usize = 1024;
+
+/// Graph parameters that aren't shared with the other backends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiskAnnParams {
+    /// Pruning factor: a neighbor is dropped when a kept one is `alpha`
+    /// times closer to it (larger keeps more long links; 1.0 keeps fewest)
+    pub alpha: f32,
+    /// Nodes expanded per search step
+    pub beam_width: usize,
 }
 
-impl DiskAnnSearcher {
-    /// Load a DiskANN index from disk
-    pub fn load(index_path: &Path, _dimensions: usize) -> anyhow::Result<Self> {
-        let index_file = index_path.with_extension("diskann");
+impl Default for DiskAnnParams {
+    fn default() -> Self {
+        Self {
+            alpha: 1.2,
+            beam_width: 4,
+        }
+    }
+}
+
+/// Fixed header of a `.vamana` file
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Header {
+    dimensions: usize,
+    max_degree: usize,
+    build_list_size: usize,
+    params: DiskAnnParams,
+    count: usize,
+    medoid: u32,
+}
+
+impl Header {
+    fn record_size(&self) -> usize {
+        self.dimensions * 4 + 4 + self.max_degree * 4
+    }
+
+    fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[0..8].copy_from_slice(MAGIC);
+        bytes[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[12..16].copy_from_slice(&(self.dimensions as u32).to_le_bytes());
+        bytes[16..20].copy_from_slice(&(self.max_degree as u32).to_le_bytes());
+        bytes[20..24].copy_from_slice(&(self.build_list_size as u32).to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.params.alpha.to_le_bytes());
+        bytes[28..32].copy_from_slice(&(self.params.beam_width as u32).to_le_bytes());
+        bytes[32..40].copy_from_slice(&(self.count as u64).to_le_bytes());
+        bytes[40..44].copy_from_slice(&self.medoid.to_le_bytes());
+        bytes
+    }
+
+    fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.len() < HEADER_SIZE || &bytes[0..8] != MAGIC {
+            anyhow::bail!("Not a LEANN DiskANN graph file");
+        }
+        let version = read_u32(bytes, 8);
+        if version != VERSION {
+            anyhow::bail!("Unsupported DiskANN graph version {}", version);
+        }
+        Ok(Self {
+            dimensions: read_u32(bytes, 12) as usize,
+            max_degree: read_u32(bytes, 16) as usize,
+            build_list_size: read_u32(bytes, 20) as usize,
+            params: DiskAnnParams {
+                alpha: f32::from_le_bytes(bytes[24..28].try_into()?),
+                beam_width: read_u32(bytes, 28) as usize,
+            },
+            count: u64::from_le_bytes(bytes[32..40].try_into()?) as usize,
+            medoid: read_u32(bytes, 40),
+        })
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// Inner-product distance (1 - dot), like the other backends
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+/// Read access to a graph, in memory or on disk
+trait Graph: Sync {
+    fn node_count(&self) -> usize;
+    fn medoid(&self) -> u32;
+    fn distance_to(&self, query: &[f32], node: u32) -> f32;
+    fn neighbors_into(&self, node: u32, out: &mut Vec<u32>);
+}
+
+/// Outcome of a beam search, as (node, distance) pairs
+struct Walk {
+    /// The `list_size` nearest nodes found, nearest first
+    nearest: Vec<(u32, f32)>,
+    /// Nodes whose neighbors were read, in order (Vamana's visited set)
+    expanded: Vec<(u32, f32)>,
+    /// Every node whose distance was computed
+    scored: Vec<(u32, f32)>,
+}
+
+/// Best-first search from the medoid, keeping the `list_size` nearest nodes
+/// and reading the neighbors of up to `beam_width` of them per step
+fn beam_search(graph: &dyn Graph, query: &[f32], list_size: usize, beam_width: usize) -> Walk {
+    let mut walk = Walk {
+        nearest: Vec::new(),
+        expanded: Vec::new(),
+        scored: Vec::new(),
+    };
+    if graph.node_count() == 0 {
+        return walk;
+    }
+
+    let list_size = list_size.max(1);
+    let start = graph.medoid();
+    let start_distance = graph.distance_to(query, start);
+    let mut seen = HashSet::from([start]);
+    // (node, distance, expanded), sorted by distance
+    let mut list = vec![(start, start_distance, false)];
+    walk.scored.push((start, start_distance));
+
+    let mut neighbors = Vec::new();
+    loop {
+        let beam: Vec<u32> = list
+            .iter_mut()
+            .filter(|(_, _, expanded)| !expanded)
+            .take(beam_width.max(1))
+            .map(|(node, distance, expanded)| {
+                *expanded = true;
+                walk.expanded.push((*node, *distance));
+                *node
+            })
+            .collect();
+        if beam.is_empty() {
+            break;
+        }
+
+        for node in beam {
+            graph.neighbors_into(node, &mut neighbors);
+            for &neighbor in &neighbors {
+                if !seen.insert(neighbor) {
+                    continue;
+                }
+                let d = graph.distance_to(query, neighbor);
+                walk.scored.push((neighbor, d));
+                if list.len() >= list_size && d >= list[list.len() - 1].1 {
+                    continue;
+                }
+                let at = list.partition_point(|entry| entry.1 <= d);
+                list.insert(at, (neighbor, d, false));
+                list.truncate(list_size);
+            }
+        }
+    }
+
+    walk.nearest = list.into_iter().map(|(node, d, _)| (node, d)).collect();
+    walk
+}
+
+/// A graph held in memory while it is built or updated
+struct MemoryGraph<'a> {
+    dimensions: usize,
+    vectors: Cow<'a, [Vec<f32>]>,
+    neighbors: Vec<Vec<u32>>,
+    medoid: u32,
+    max_degree: usize,
+    build_list_size: usize,
+    params: DiskAnnParams,
+}
+
+impl Graph for MemoryGraph<'_> {
+    fn node_count(&self) -> usize {
+        self.vectors.len()
+    }
+
+    fn medoid(&self) -> u32 {
+        self.medoid
+    }
+
+    fn distance_to(&self, query: &[f32], node: u32) -> f32 {
+        distance(query, &self.vectors[node as usize])
+    }
+
+    fn neighbors_into(&self, node: u32, out: &mut Vec<u32>) {
+        out.clear();
+        out.extend_from_slice(&self.neighbors[node as usize]);
+    }
+}
+
+impl<'a> MemoryGraph<'a> {
+    /// Build a graph over `vectors` with Vamana: a random regular graph,
+    /// refined by one pass with alpha = 1 and one with `params.alpha`
+    fn build(
+        vectors: &'a [Vec<f32>],
+        dimensions: usize,
+        max_degree: usize,
+        build_list_size: usize,
+        params: DiskAnnParams,
+    ) -> Self {
+        let n = vectors.len();
+        let mut rng = XorShift::new(0x5eed);
+        let degree = max_degree.min(n.saturating_sub(1));
+        let neighbors = (0..n)
+            .map(|node| {
+                let mut links = Vec::with_capacity(degree);
+                while links.len() < degree {
+                    let other = rng.below(n) as u32;
+                    if other as usize != node && !links.contains(&other) {
+                        links.push(other);
+                    }
+                }
+                links
+            })
+            .collect();
+
+        let mut graph = Self {
+            dimensions,
+            vectors: Cow::Borrowed(vectors),
+            neighbors,
+            medoid: medoid(vectors),
+            max_degree,
+            build_list_size,
+            params,
+        };
+
+        let mut order: Vec<u32> = (0..n as u32).collect();
+        for i in (1..n).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+        graph.insert(&order, 1.0);
+        graph.insert(&order, params.alpha);
+        graph
+    }
+
+    /// Load the graph of a `.vamana` file
+    fn load(bytes: &[u8]) -> anyhow::Result<Self> {
+        let header = Header::parse(bytes)?;
+        let record_size = header.record_size();
+        if bytes.len() < HEADER_SIZE + header.count * record_size {
+            anyhow::bail!("DiskANN graph file is truncated");
+        }
+
+        let mut vectors = Vec::with_capacity(header.count);
+        let mut neighbors = Vec::with_capacity(header.count);
+        for node in 0..header.count {
+            let record = &bytes[HEADER_SIZE + node * record_size..][..record_size];
+            vectors.push(decode_vector(record, header.dimensions));
+            neighbors.push(decode_neighbors(record, header.dimensions).collect());
+        }
+
+        Ok(Self {
+            dimensions: header.dimensions,
+            vectors: Cow::Owned(vectors),
+            neighbors,
+            medoid: header.medoid,
+            max_degree: header.max_degree,
+            build_list_size: header.build_list_size,
+            params: header.params,
+        })
+    }
+
+    /// (Re)link `nodes` into the graph, in batches whose neighbor searches
+    /// run in parallel against the graph as it was before the batch
+    fn insert(&mut self, nodes: &[u32], alpha: f32) {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        for batch in nodes.chunks(BUILD_BATCH) {
+            let graph = &*self;
+            let links: Vec<Vec<u32>> = std::thread::scope(|scope| {
+                let handles: Vec<_> = batch
+                    .chunks(batch.len().div_ceil(threads))
+                    .map(|part| {
+                        scope.spawn(move || {
+                            part.iter().map(|&node| graph.candidate_links(node, alpha)).collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().expect("graph build thread panicked"))
+                    .collect()
+            });
+
+            for (&node, links) in batch.iter().zip(&links) {
+                self.neighbors[node as usize].clone_from(links);
+            }
+            // Back-links go in after all of the batch's own links, so they aren't overwritten
+            for (&node, links) in batch.iter().zip(links) {
+                for neighbor in links {
+                    let back = &mut self.neighbors[neighbor as usize];
+                    if back.contains(&node) {
+                        continue;
+                    }
+                    if back.len() < self.max_degree {
+                        back.push(node);
+                    } else {
+                        let candidates = back.iter().copied().chain([node]).collect::<Vec<_>>();
+                        self.neighbors[neighbor as usize] = self.prune(neighbor, candidates, alpha);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Out-links for `node`: the nodes a search for it expands, plus its
+    /// current links, pruned to `max_degree`
+    fn candidate_links(&self, node: u32, alpha: f32) -> Vec<u32> {
+        let walk = beam_search(self, &self.vectors[node as usize], self.build_list_size, 1);
+        let candidates = walk
+            .expanded
+            .into_iter()
+            .map(|(candidate, _)| candidate)
+            .chain(self.neighbors[node as usize].iter().copied())
+            .collect();
+        self.prune(node, candidates, alpha)
+    }
+
+    /// Vamana's robust prune: take candidates nearest first, dropping those
+    /// `alpha` times closer to an already kept neighbor than to `node`
+    fn prune(&self, node: u32, candidates: Vec<u32>, alpha: f32) -> Vec<u32> {
+        let vector = &self.vectors[node as usize];
+        let mut candidates: Vec<(u32, f32)> = candidates
+            .into_iter()
+            .filter(|&candidate| candidate != node)
+            .map(|candidate| (candidate, distance(vector, &self.vectors[candidate as usize])))
+            .collect();
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        candidates.dedup_by_key(|(candidate, _)| *candidate);
+
+        let mut kept = Vec::with_capacity(self.max_degree);
+        let mut remaining = candidates;
+        while !remaining.is_empty() && kept.len() < self.max_degree {
+            let (nearest, _) = remaining.remove(0);
+            kept.push(nearest);
+            let nearest_vector = &self.vectors[nearest as usize];
+            remaining.retain(|&(candidate, d)| {
+                alpha * distance(nearest_vector, &self.vectors[candidate as usize]) > d
+            });
+        }
+        kept
+    }
+
+    fn header(&self) -> Header {
+        Header {
+            dimensions: self.dimensions,
+            max_degree: self.max_degree,
+            build_list_size: self.build_list_size,
+            params: self.params,
+            count: self.vectors.len(),
+            medoid: self.medoid,
+        }
+    }
+
+    /// Write the graph as a `.vamana` file
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        w.write_all(&self.header().to_bytes())?;
+        for (vector, links) in self.vectors.iter().zip(&self.neighbors) {
+            for value in vector {
+                w.write_all(&value.to_le_bytes())?;
+            }
+            w.write_all(&(links.len() as u32).to_le_bytes())?;
+            for slot in 0..self.max_degree {
+                w.write_all(&links.get(slot).copied().unwrap_or(0).to_le_bytes())?;
+            }
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// The node nearest the centroid of `vectors`, where searches start
+fn medoid(vectors: &[Vec<f32>]) -> u32 {
+    let Some(first) = vectors.first() else {
+        return 0;
+    };
+    let mut centroid = vec![0f32; first.len()];
+    for vector in vectors {
+        for (sum, value) in centroid.iter_mut().zip(vector) {
+            *sum += value;
+        }
+    }
+    for sum in &mut centroid {
+        *sum /= vectors.len() as f32;
+    }
+    (0..vectors.len())
+        .min_by(|&a, &b| distance(&centroid, &vectors[a]).total_cmp(&distance(&centroid, &vectors[b])))
+        .unwrap_or(0) as u32
+}
+
+fn decode_vector(record: &[u8], dimensions: usize) -> Vec<f32> {
+    record[..dimensions * 4]
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn decode_neighbors(record: &[u8], dimensions: usize) -> impl Iterator<Item = u32> + '_ {
+    let degree = read_u32(record, dimensions * 4) as usize;
+    record[dimensions * 4 + 4..]
+        .chunks_exact(4)
+        .take(degree)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
 
-        info!("Loading DiskANN index from {:?}", index_file);
+/// Searches a memory-mapped `.vamana` graph
+pub struct DiskAnnSearcher {
+    mmap: Mmap,
+    header: Header,
+}
 
-        if !index_file.exists() {
+impl DiskAnnSearcher {
+    pub fn load(index_path: &Path, dimensions: usize) -> anyhow::Result<Self> {
+        let graph_file = index_path.with_extension("vamana");
+        if !graph_file.exists() {
             anyhow::bail!(
                 "DiskANN index not found: {:?}\n\
                 Run 'leann build' with --backend-name diskann to create an index first.",
-                index_file
+                graph_file
             );
         }
 
-        let index = DiskANN::<DistDot>::open_index_with(
-            index_file.to_string_lossy().as_ref(),
-            DistDot {},
-        )
-        .map_err(|e| anyhow::anyhow!("Failed to load DiskANN index: {}", e))?;
+        info!("Loading DiskANN graph from {:?}", graph_file);
+        let file = File::open(&graph_file)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        let header = Header::parse(&mmap)?;
+        if header.count > 0 && header.dimensions != dimensions {
+            anyhow::bail!(
+                "DiskANN graph has {} dimensions, expected {}",
+                header.dimensions,
+                dimensions
+            );
+        }
+        if mmap.len() < HEADER_SIZE + header.count * header.record_size() {
+            anyhow::bail!("DiskANN graph file is truncated: {:?}", graph_file);
+        }
 
-        info!("Loaded DiskANN index with {} vectors", index.num_vectors);
+        info!(
+            "Loaded DiskANN graph with {} vectors (degree {}, beam width {})",
+            header.count, header.max_degree, header.params.beam_width
+        );
+        Ok(Self { mmap, header })
+    }
 
-        Ok(Self { index })
+    fn record(&self, node: u32) -> &[u8] {
+        let size = self.header.record_size();
+        &self.mmap[HEADER_SIZE + node as usize * size..][..size]
+    }
+
+    fn walk(&self, query: &[f32], list_size: usize) -> anyhow::Result<Walk> {
+        if self.header.count > 0 && query.len() != self.header.dimensions {
+            anyhow::bail!(
+                "Query has {} dimensions, index has {}",
+                query.len(),
+                self.header.dimensions
+            );
+        }
+        Ok(beam_search(self, query, list_size, self.header.params.beam_width))
+    }
+}
+
+impl Graph for DiskAnnSearcher {
+    fn node_count(&self) -> usize {
+        self.header.count
+    }
+
+    fn medoid(&self) -> u32 {
+        self.header.medoid
+    }
+
+    fn distance_to(&self, query: &[f32], node: u32) -> f32 {
+        let record = self.record(node);
+        1.0 - query
+            .iter()
+            .zip(record.chunks_exact(4))
+            .map(|(q, b)| q * f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .sum::<f32>()
+    }
+
+    fn neighbors_into(&self, node: u32, out: &mut Vec<u32>) {
+        out.clear();
+        out.extend(decode_neighbors(self.record(node), self.header.dimensions));
     }
 }
 
@@ -50,56 +523,304 @@ impl BackendSearcher for DiskAnnSearcher {
         top_k: usize,
         complexity: usize,
     ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
-        // Use complexity as beam_width for search
-        let beam_width = complexity.max(top_k);
-
-        let results = self.index.search_with_dists(query, top_k, beam_width);
+        // Complexity is the search list size
+        let mut walk = self.walk(query, complexity.max(top_k))?;
+        walk.nearest.truncate(top_k);
+        Ok(walk.nearest.into_iter().map(|(node, d)| (node as u64, d)).unzip())
+    }
 
-        let indices: Vec<u64> = results.iter().map(|(id, _)| *id as u64).collect();
-        let distances: Vec<f32> = results.iter().map(|(_, d)| *d).collect();
+    /// Walks the whole graph but keeps only allowed nodes, doubling the list
+    /// size while fewer than `top_k` of the scored nodes are allowed
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        complexity: usize,
+        allowed: &dyn Fn(u64) -> bool,
+    ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
+        let mut list_size = complexity.max(top_k);
+        loop {
+            let walk = self.walk(query, list_size)?;
+            let mut kept: Vec<(u32, f32)> = walk
+                .scored
+                .into_iter()
+                .filter(|&(node, _)| allowed(node as u64))
+                .collect();
+            if kept.len() >= top_k || list_size >= self.header.count {
+                kept.sort_by(|a, b| a.1.total_cmp(&b.1));
+                kept.truncate(top_k);
+                return Ok(kept.into_iter().map(|(node, d)| (node as u64, d)).unzip());
+            }
+            list_size *= 2;
+        }
+    }
 
-        Ok((indices, distances))
+    fn vector(&self, idx: u64) -> Option<Vec<f32>> {
+        (idx < self.header.count as u64).then(|| decode_vector(self.record(idx as u32), self.header.dimensions))
     }
 
     fn len(&self) -> usize {
-        self.index.num_vectors
+        self.header.count
     }
 }
 
-/// Build a DiskANN index
+/// Build a DiskANN graph with `graph_degree` links per node, using search
+/// lists of `complexity` nodes to find them
 pub fn build_index(
     embeddings: &[Vec<f32>],
-    _ids: &[String],
     index_path: &Path,
     dimensions: usize,
     graph_degree: usize,
     complexity: usize,
+    params: DiskAnnParams,
 ) -> anyhow::Result<()> {
     info!(
-        "Building DiskANN index: {} vectors, {} dims, degree={}, complexity={}",
+        "Building DiskANN graph: {} vectors, {} dims, degree={}, complexity={}, alpha={}",
         embeddings.len(),
         dimensions,
         graph_degree,
-        complexity
+        complexity,
+        params.alpha
     );
+    if embeddings.len() > u32::MAX as usize {
+        anyhow::bail!("DiskANN graphs hold at most {} vectors", u32::MAX);
+    }
 
-    let index_file = index_path.with_extension("diskann");
+    let graph = MemoryGraph::build(embeddings, dimensions, graph_degree.max(1), complexity.max(1), params);
+    let graph_file = index_path.with_extension("vamana");
+    graph.save(&graph_file)?;
 
-    let params = DiskAnnParams {
-        max_degree: graph_degree,
-        build_beam_width: complexity,
-        alpha: 1.2,
-    };
+    info!("DiskANN graph saved to {:?}", graph_file);
+    Ok(())
+}
 
-    let _index = DiskANN::<DistDot>::build_index_with_params(
-        embeddings,
-        DistDot {},
-        index_file.to_string_lossy().as_ref(),
-        params,
-    )
-    .map_err(|e| anyhow::anyhow!("Failed to build DiskANN index: {}", e))?;
+/// Insert vectors into an existing DiskANN graph, with the parameters it
+/// was built with
+pub fn add_to_index(
+    embeddings: &[Vec<f32>],
+    index_path: &Path,
+    dimensions: usize,
+    start_id: usize,
+) -> anyhow::Result<()> {
+    let graph_file = index_path.with_extension("vamana");
+    if !graph_file.exists() {
+        anyhow::bail!(
+            "This DiskANN index was built with diskann-rs, which doesn't support incremental updates. \
+            Use 'leann build --force' to rebuild it."
+        );
+    }
 
-    info!("DiskANN index saved to {:?}", index_file);
+    let mut graph = MemoryGraph::load(&std::fs::read(&graph_file)?)?;
+    if graph.node_count() > 0 && graph.dimensions != dimensions {
+        anyhow::bail!(
+            "DiskANN graph has {} dimensions, expected {}",
+            graph.dimensions,
+            dimensions
+        );
+    }
+    graph.dimensions = dimensions;
+    if graph.node_count() != start_id {
+        anyhow::bail!(
+            "DiskANN graph holds {} vectors, but new passages start at {}",
+            graph.node_count(),
+            start_id
+        );
+    }
 
+    let new_nodes: Vec<u32> = (start_id as u32..(start_id + embeddings.len()) as u32).collect();
+    let was_empty = graph.node_count() == 0;
+    graph.vectors.to_mut().extend_from_slice(embeddings);
+    graph.neighbors.resize(graph.vectors.len(), Vec::new());
+    if was_empty {
+        graph.medoid = medoid(&graph.vectors);
+    }
+    graph.insert(&new_nodes, graph.params.alpha);
+
+    // Write next to the graph and swap it in, so a failed update keeps the old one
+    let staged = graph_file.with_extension("vamana.tmp");
+    graph.save(&staged)?;
+    std::fs::rename(&staged, &graph_file)?;
+
+    info!("Updated DiskANN graph saved with {} total vectors", graph.node_count());
     Ok(())
 }
+
+/// Vamana graphs searched from disk
+pub struct DiskAnnBackend;
+
+static DISKANN_INFO: BackendInfo = BackendInfo {
+    name: "diskann",
+    description: "Vamana graph memory-mapped from disk, for indexes larger than RAM",
+    supports_update: true,
+    supports_replace: false,
+    supports_filtering: true,
+    supports_sharding: false,
+    supports_pruning: true,
+    quantizations: &["f32"],
+};
+
+impl Backend for DiskAnnBackend {
+    fn info(&self) -> &BackendInfo {
+        &DISKANN_INFO
+    }
+
+    fn load_searcher(&self, index_path: &Path, dimensions: usize) -> Result<Box<dyn BackendSearcher>, BackendError> {
+        let graph_file = index_path.with_extension("vamana");
+        if graph_file.exists() {
+            return Ok(Box::new(DiskAnnSearcher::load(index_path, dimensions)?));
+        }
+
+        // Indexes built by earlier versions hold a diskann-rs `.diskann` file instead
+        let legacy_file = index_path.with_extension("diskann");
+        if !legacy_file.exists() {
+            return Err(BackendError::NotFound {
+                backend: DISKANN_INFO.name,
+                path: graph_file,
+            });
+        }
+        #[cfg(feature = "diskann-backend")]
+        {
+            let searcher = super::diskann_legacy::LegacyDiskAnnSearcher::load(index_path, dimensions)?;
+            Ok(Box::new(searcher))
+        }
+        #[cfg(not(feature = "diskann-backend"))]
+        Err(anyhow::anyhow!(
+            "This DiskANN index was built with diskann-rs; rebuild it with 'leann build --force', \
+            or use a binary built with --features diskann-backend to read it"
+        )
+        .into())
+    }
+
+    fn build(
+        &self,
+        embeddings: &[Vec<f32>],
+        _ids: &[String],
+        index_path: &Path,
+        dimensions: usize,
+        params: &BuildParams,
+    ) -> Result<(), BackendError> {
+        Ok(build_index(
+            embeddings,
+            index_path,
+            dimensions,
+            params.graph_degree,
+            params.complexity,
+            params.diskann,
+        )?)
+    }
+
+    fn add_to_index(
+        &self,
+        embeddings: &[Vec<f32>],
+        index_path: &Path,
+        dimensions: usize,
+        start_id: usize,
+    ) -> Result<(), BackendError> {
+        Ok(add_to_index(embeddings, index_path, dimensions, start_id)?)
+    }
+
+    fn build_kwargs(&self, params: &BuildParams) -> serde_json::Value {
+        serde_json::json!({
+            "graph_degree": params.graph_degree,
+            "complexity": params.complexity,
+            "prune_alpha": params.diskann.alpha,
+            "beam_width": params.diskann.beam_width,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic unit vectors
+    fn vectors(n: usize, dim: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut rng = XorShift::new(seed);
+        (0..n)
+            .map(|_| {
+                let v: Vec<f32> = (0..dim).map(|_| rng.below(2001) as f32 / 1000.0 - 1.0).collect();
+                let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+                v.into_iter().map(|x| x / norm).collect()
+            })
+            .collect()
+    }
+
+    fn temp_index(name: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("leann-diskann-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let index_path = dir.join("documents.leann");
+        (dir, index_path)
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = Header {
+            dimensions: 384,
+            max_degree: 32,
+            build_list_size: 64,
+            params: DiskAnnParams::default(),
+            count: 1_000_000,
+            medoid: 17,
+        };
+        assert_eq!(Header::parse(&header.to_bytes()).unwrap(), header);
+        assert!(Header::parse(b"LEANNIVF").is_err());
+    }
+
+    #[test]
+    fn test_prune_bounds_degree_and_keeps_nearest() {
+        let data = vectors(50, 8, 7);
+        let graph = MemoryGraph::build(&data, 8, 6, 32, DiskAnnParams::default());
+        assert!(graph.neighbors.iter().all(|links| links.len() <= 6 && !links.is_empty()));
+
+        let candidates: Vec<u32> = (1..50).collect();
+        let kept = graph.prune(0, candidates.clone(), 1.2);
+        let nearest = candidates
+            .iter()
+            .copied()
+            .min_by(|&a, &b| distance(&data[0], &data[a as usize]).total_cmp(&distance(&data[0], &data[b as usize])))
+            .unwrap();
+        assert_eq!(kept[0], nearest);
+        assert!(kept.len() <= 6);
+    }
+
+    #[test]
+    fn test_search_recall_and_vectors() {
+        let (dir, index_path) = temp_index("search");
+        let data = vectors(600, 16, 42);
+        build_index(&data, &index_path, 16, 16, 48, DiskAnnParams::default()).unwrap();
+
+        let searcher = DiskAnnSearcher::load(&index_path, 16).unwrap();
+        assert_eq!(searcher.len(), 600);
+        assert_eq!(searcher.vector(123), Some(data[123].clone()));
+
+        // Every stored vector is its own nearest neighbor
+        let found = (0..600).filter(|&i| searcher.search(&data[i], 1, 48).unwrap().0 == vec![i as u64]).count();
+        assert!(found >= 590, "only {} of 600 exact matches found", found);
+
+        let (indices, distances) = searcher.search(&data[5], 10, 48).unwrap();
+        assert_eq!(indices.len(), 10);
+        assert!(distances.windows(2).all(|w| w[0] <= w[1]));
+
+        let (indices, _) = searcher.search_filtered(&data[5], 3, 48, &|idx| idx % 2 == 1).unwrap();
+        assert_eq!(indices.len(), 3);
+        assert!(indices.iter().all(|idx| idx % 2 == 1));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_to_index() {
+        let (dir, index_path) = temp_index("update");
+        let data = vectors(300, 16, 9);
+        build_index(&data[..200], &index_path, 16, 12, 32, DiskAnnParams::default()).unwrap();
+        add_to_index(&data[200..], &index_path, 16, 200).unwrap();
+        assert!(add_to_index(&data[..1], &index_path, 16, 10).is_err());
+
+        let searcher = DiskAnnSearcher::load(&index_path, 16).unwrap();
+        assert_eq!(searcher.len(), 300);
+        let found = (200..300).filter(|&i| searcher.search(&data[i], 1, 32).unwrap().0 == vec![i as u64]).count();
+        assert!(found >= 95, "only {} of 100 new passages found", found);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
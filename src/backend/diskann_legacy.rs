@@ -0,0 +1,68 @@
+//! Reader for DiskANN indexes built with the diskann-rs crate
+//!
+//! Earlier versions built `--backend-name diskann` indexes as a diskann-rs
+//! `.diskann` file; they stay searchable with the `diskann-backend` feature.
+//! New builds use the native graph in `diskann.rs`.
+
+use std::path::Path;
+
+use anndists::dist::distances::DistDot;
+use diskann_rs::DiskANN;
+use tracing::info;
+
+use super::traits::BackendSearcher;
+
+/// Searcher for a diskann-rs `.diskann` index
+pub struct LegacyDiskAnnSearcher {
+    index: DiskANN<DistDot>,
+}
+
+impl LegacyDiskAnnSearcher {
+    /// Load a DiskANN index from disk
+    pub fn load(index_path: &Path, _dimensions: usize) -> anyhow::Result<Self> {
+        let index_file = index_path.with_extension("diskann");
+
+        info!("Loading DiskANN index from {:?}", index_file);
+
+        if !index_file.exists() {
+            anyhow::bail!(
+                "DiskANN index not found: {:?}\n\
+                Run 'leann build' with --backend-name diskann to create an index first.",
+                index_file
+            );
+        }
+
+        let index = DiskANN::<DistDot>::open_index_with(
+            index_file.to_string_lossy().as_ref(),
+            DistDot {},
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to load DiskANN index: {}", e))?;
+
+        info!("Loaded DiskANN index with {} vectors", index.num_vectors);
+
+        Ok(Self { index })
+    }
+}
+
+impl BackendSearcher for LegacyDiskAnnSearcher {
+    fn search(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        complexity: usize,
+    ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
+        // Use complexity as beam_width for search
+        let beam_width = complexity.max(top_k);
+
+        let results = self.index.search_with_dists(query, top_k, beam_width);
+
+        let indices: Vec<u64> = results.iter().map(|(id, _)| *id as u64).collect();
+        let distances: Vec<f32> = results.iter().map(|(_, d)| *d).collect();
+
+        Ok((indices, distances))
+    }
+
+    fn len(&self) -> usize {
+        self.index.num_vectors
+    }
+}
@@ -1,12 +1,15 @@
 //! HNSW backend using usearch crate
 
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
 use tracing::info;
 use usearch::{Index, IndexOptions, MetricKind, ScalarKind};
 
 use super::compat::is_faiss_index;
-use super::traits::BackendSearcher;
+use super::sharded::{self, ShardInfo, ShardManifest};
+use super::traits::{Backend, BackendError, BackendInfo, BackendSearcher, BuildParams};
 
 /// HNSW searcher using usearch
 pub struct HnswSearcher {
@@ -87,6 +90,27 @@ impl BackendSearcher for HnswSearcher {
         Ok((results.keys.to_vec(), results.distances.to_vec()))
     }
 
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        _complexity: usize,
+        allowed: &dyn Fn(u64) -> bool,
+    ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
+        // usearch checks the predicate during graph traversal
+        let results = self.index.filtered_search(query, top_k, allowed)?;
+
+        Ok((results.keys.to_vec(), results.distances.to_vec()))
+    }
+
+    fn vector(&self, idx: u64) -> Option<Vec<f32>> {
+        let mut vector = vec![0.0f32; self.index.dimensions()];
+        match self.index.get(idx, &mut vector) {
+            Ok(found) if found > 0 => Some(vector),
+            _ => None,
+        }
+    }
+
     fn len(&self) -> usize {
         self.index.size()
     }
@@ -100,6 +124,19 @@ pub fn build_index(
     dimensions: usize,
     graph_degree: usize,
     complexity: usize,
+) -> anyhow::Result<()> {
+    build_index_from(embeddings, index_path, dimensions, graph_degree, complexity, 0)
+}
+
+/// Build an HNSW index whose vectors are keyed from `start_id` (e.g. a shard
+/// keyed by the global offsets of its passages)
+pub fn build_index_from(
+    embeddings: &[Vec<f32>],
+    index_path: &Path,
+    dimensions: usize,
+    graph_degree: usize,
+    complexity: usize,
+    start_id: usize,
 ) -> anyhow::Result<()> {
     info!(
         "Building HNSW index: {} vectors, {} dims, degree={}, complexity={}",
@@ -126,7 +163,7 @@ pub fn build_index(
 
     // Add vectors
     for (i, embedding) in embeddings.iter().enumerate() {
-        index.add(i as u64, embedding)?;
+        index.add((start_id + i) as u64, embedding)?;
     }
 
     // Save to disk
@@ -189,3 +226,232 @@ pub fn add_to_index(
 
     Ok(())
 }
+
+/// Replace the vectors under `keys` and remove those under `removed` in an
+/// existing HNSW index
+pub fn replace_in_index(
+    keys: &[u64],
+    embeddings: &[Vec<f32>],
+    removed: &[u64],
+    index_path: &Path,
+    dimensions: usize,
+) -> anyhow::Result<()> {
+    let index_file = index_path.with_extension("index");
+
+    // Same options `add_to_index` loads with
+    let options = IndexOptions {
+        dimensions,
+        metric: MetricKind::IP,
+        quantization: ScalarKind::F32,
+        connectivity: 32,
+        expansion_add: 64,
+        expansion_search: 64,
+        multi: false,
+    };
+
+    let index = Index::new(&options)?;
+    index.load(index_file.to_string_lossy().as_ref())?;
+
+    for &key in keys.iter().chain(removed) {
+        index.remove(key)?;
+    }
+    index.reserve(index.size() + keys.len())?;
+    for (&key, embedding) in keys.iter().zip(embeddings) {
+        index.add(key, embedding)?;
+    }
+
+    index.save(index_file.to_string_lossy().as_ref())?;
+
+    info!(
+        "Replaced {} and removed {} vectors in HNSW index ({} total)",
+        keys.len(),
+        removed.len(),
+        index.size()
+    );
+
+    Ok(())
+}
+
+/// Remove the vectors under `keys` from an existing HNSW index
+pub fn remove_from_index(keys: &[u64], index_path: &Path, dimensions: usize) -> anyhow::Result<()> {
+    replace_in_index(&[], &[], keys, index_path, dimensions)
+}
+
+/// Keys whose vectors were removed from an index, so later vectors can take
+/// them over instead of growing the key space
+///
+/// Stored as `documents.free.json` next to the index; sharded indexes keep one
+/// list for all their shards.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FreeKeys {
+    pub keys: BTreeSet<u64>,
+}
+
+impl FreeKeys {
+    fn path(index_path: &Path) -> PathBuf {
+        index_path.with_extension("free.json")
+    }
+
+    /// The free keys of the index at `index_path` (none if it has no list)
+    pub fn load(index_path: &Path) -> anyhow::Result<Self> {
+        let path = Self::path(index_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, index_path: &Path) -> anyhow::Result<()> {
+        let path = Self::path(index_path);
+        if self.keys.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Take `reused` off the list and add `removed` to it
+    pub fn record(index_path: &Path, reused: &[u64], removed: &[u64]) -> anyhow::Result<()> {
+        let mut free = Self::load(index_path)?;
+        for key in reused {
+            free.keys.remove(key);
+        }
+        free.keys.extend(removed);
+        free.save(index_path)
+    }
+}
+
+/// HNSW graphs in usearch format, optionally split into shards
+pub struct HnswBackend;
+
+static HNSW_INFO: BackendInfo = BackendInfo {
+    name: "hnsw",
+    description: "In-memory HNSW graph (usearch), optionally sharded",
+    supports_update: true,
+    supports_replace: true,
+    supports_filtering: true,
+    supports_sharding: true,
+    supports_pruning: true,
+    quantizations: &["f32"],
+};
+
+impl Backend for HnswBackend {
+    fn info(&self) -> &BackendInfo {
+        &HNSW_INFO
+    }
+
+    fn load_searcher(&self, index_path: &Path, dimensions: usize) -> Result<Box<dyn BackendSearcher>, BackendError> {
+        if ShardManifest::exists(index_path) {
+            return Ok(Box::new(sharded::ShardedSearcher::load(index_path, dimensions)?));
+        }
+        Ok(Box::new(HnswSearcher::load(index_path, dimensions)?))
+    }
+
+    fn build(
+        &self,
+        embeddings: &[Vec<f32>],
+        ids: &[String],
+        index_path: &Path,
+        dimensions: usize,
+        params: &BuildParams,
+    ) -> Result<(), BackendError> {
+        Ok(build_index(embeddings, ids, index_path, dimensions, params.graph_degree, params.complexity)?)
+    }
+
+    fn build_shard(
+        &self,
+        embeddings: &[Vec<f32>],
+        index_path: &Path,
+        shard: usize,
+        start: usize,
+        dimensions: usize,
+        params: &BuildParams,
+    ) -> Result<ShardInfo, BackendError> {
+        Ok(sharded::build_shard(
+            embeddings,
+            index_path,
+            shard,
+            start,
+            dimensions,
+            params.graph_degree,
+            params.complexity,
+        )?)
+    }
+
+    fn add_to_index(
+        &self,
+        embeddings: &[Vec<f32>],
+        index_path: &Path,
+        dimensions: usize,
+        start_id: usize,
+    ) -> Result<(), BackendError> {
+        if ShardManifest::exists(index_path) {
+            return Ok(sharded::add_to_index(embeddings, index_path, dimensions, start_id)?);
+        }
+        Ok(add_to_index(embeddings, index_path, dimensions, start_id)?)
+    }
+
+    fn replace_in_index(
+        &self,
+        keys: &[u64],
+        embeddings: &[Vec<f32>],
+        removed: &[u64],
+        index_path: &Path,
+        dimensions: usize,
+    ) -> Result<(), BackendError> {
+        if ShardManifest::exists(index_path) {
+            sharded::replace_in_index(keys, embeddings, removed, index_path, dimensions)?;
+        } else {
+            replace_in_index(keys, embeddings, removed, index_path, dimensions)?;
+        }
+        Ok(FreeKeys::record(index_path, keys, removed)?)
+    }
+
+    fn remove_from_index(&self, keys: &[u64], index_path: &Path, dimensions: usize) -> Result<(), BackendError> {
+        if ShardManifest::exists(index_path) {
+            sharded::replace_in_index(&[], &[], keys, index_path, dimensions)?;
+        } else {
+            remove_from_index(keys, index_path, dimensions)?;
+        }
+        Ok(FreeKeys::record(index_path, &[], keys)?)
+    }
+
+    fn free_keys(&self, index_path: &Path) -> Result<Vec<u64>, BackendError> {
+        Ok(FreeKeys::load(index_path)?.keys.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_frees_keys_for_reuse() {
+        let dir = std::env::temp_dir().join(format!("leann-hnsw-free-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let index_path = dir.join("documents.leann");
+        let axis = |i: usize| -> Vec<f32> { (0..8).map(|d| if d == i { 1.0 } else { 0.0 }).collect() };
+        build_index_from(&(0..4).map(axis).collect::<Vec<_>>(), &index_path, 8, 16, 64, 0).unwrap();
+
+        let backend = HnswBackend;
+        backend.remove_from_index(&[1, 3], &index_path, 8).unwrap();
+        assert_eq!(backend.free_keys(&index_path).unwrap(), vec![1, 3]);
+        let searcher = HnswSearcher::load(&index_path, 8).unwrap();
+        assert_eq!(searcher.len(), 2);
+        assert_eq!(searcher.vector(1), None);
+
+        // Key 1 takes a new vector and leaves the free list
+        backend.replace_in_index(&[1], &[axis(5)], &[], &index_path, 8).unwrap();
+        assert_eq!(backend.free_keys(&index_path).unwrap(), vec![3]);
+        let searcher = HnswSearcher::load(&index_path, 8).unwrap();
+        assert_eq!(searcher.search(&axis(5), 1, 64).unwrap().0, vec![1]);
+
+        backend.replace_in_index(&[3], &[axis(6)], &[], &index_path, 8).unwrap();
+        assert!(!index_path.with_extension("free.json").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
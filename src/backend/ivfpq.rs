@@ -0,0 +1,576 @@
+//! IVF-PQ backend - inverted lists of product-quantized vectors
+//!
+//! Vectors are assigned to the nearest of `nlist` coarse centroids (k-means),
+//! and the residual from that centroid is compressed to `m` one-byte codes
+//! (product quantization, 256 centroids per subspace). A search scores the
+//! vectors of the lists whose centroids best match the query from
+//! precomputed lookup tables, then reranks the best candidates with the
+//! full-precision vectors in the `.embeddings` file.
+//!
+//! Memory per vector is about `m + 8` bytes plus the memory-mapped full
+//! vectors, against the full vectors plus graph links for HNSW.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use memmap2::Mmap;
+use tracing::info;
+
+use super::kmeans::{assign, kmeans, nearest, XorShift};
+use super::traits::{Backend, BackendError, BackendInfo, BackendSearcher, BuildParams};
+
+const MAGIC: &[u8; 8] = b"LEANNIVF";
+const VERSION: u32 = 1;
+
+/// Subquantizers per vector are at most this many (one code byte each)
+const MAX_SUBQUANTIZERS: usize = 64;
+
+/// Centroids per subquantizer (codes are one byte)
+const MAX_SUB_CENTROIDS: usize = 256;
+
+/// Training sample size per k-means centroid
+const TRAIN_POINTS_PER_CENTROID: usize = 64;
+
+/// Candidates reranked with full-precision vectors, per requested result
+const RERANK_FACTOR: usize = 4;
+
+/// Minimum number of candidates reranked with full-precision vectors
+const MIN_RERANK: usize = 32;
+
+/// The vectors assigned to one coarse centroid
+#[derive(Default)]
+struct InvertedList {
+    ids: Vec<u64>,
+    /// `m` codes per vector, in `ids` order
+    codes: Vec<u8>,
+}
+
+/// Trained coarse quantizer and product quantizer with their inverted lists
+struct IvfPqIndex {
+    dimensions: usize,
+    /// Subquantizers (code bytes) per vector
+    m: usize,
+    /// Centroids per subquantizer
+    ksub: usize,
+    /// Coarse centroids, `nlist * dimensions`
+    centroids: Vec<f32>,
+    /// Subquantizer centroids, `m * ksub * (dimensions / m)`
+    codebooks: Vec<f32>,
+    lists: Vec<InvertedList>,
+}
+
+impl IvfPqIndex {
+    /// Train the quantizers on (a sample of) `embeddings`
+    fn train(embeddings: &[Vec<f32>], dimensions: usize) -> Self {
+        let n = embeddings.len();
+        let nlist = ((n as f64).sqrt() as usize).clamp(1, 65_536);
+        let m = subquantizers(dimensions);
+        let ksub = n.clamp(1, MAX_SUB_CENTROIDS);
+        let dsub = dimensions / m;
+        let mut rng = XorShift::new(0x5eed);
+
+        let sample = rng.sample(n, (nlist * TRAIN_POINTS_PER_CENTROID).max(MAX_SUB_CENTROIDS * 64));
+        let points: Vec<f32> = sample.iter().flat_map(|&i| embeddings[i].iter().copied()).collect();
+        let centroids = kmeans(&points, dimensions, nlist, &mut rng);
+
+        // Subquantizers are trained on residuals from the coarse centroids
+        let assignments = assign(&points, dimensions, &centroids);
+        let residuals: Vec<f32> = points
+            .chunks(dimensions)
+            .zip(&assignments)
+            .flat_map(|(point, &list)| {
+                let centroid = &centroids[list as usize * dimensions..][..dimensions];
+                point.iter().zip(centroid).map(|(p, c)| p - c)
+            })
+            .collect();
+        let mut codebooks = Vec::with_capacity(m * ksub * dsub);
+        for j in 0..m {
+            let sub: Vec<f32> = residuals
+                .chunks(dimensions)
+                .flat_map(|r| r[j * dsub..(j + 1) * dsub].iter().copied())
+                .collect();
+            codebooks.extend(kmeans(&sub, dsub, ksub, &mut rng));
+        }
+
+        Self {
+            dimensions,
+            m,
+            ksub,
+            centroids,
+            codebooks,
+            lists: (0..nlist).map(|_| InvertedList::default()).collect(),
+        }
+    }
+
+    fn nlist(&self) -> usize {
+        self.lists.len()
+    }
+
+    fn len(&self) -> usize {
+        self.lists.iter().map(|list| list.ids.len()).sum()
+    }
+
+    /// Encode and add vectors keyed from `start_id`
+    fn add(&mut self, embeddings: &[Vec<f32>], start_id: usize) {
+        let d = self.dimensions;
+        let dsub = d / self.m;
+        let points: Vec<f32> = embeddings.iter().flat_map(|e| e.iter().copied()).collect();
+        let assignments = assign(&points, d, &self.centroids);
+
+        for (i, (point, &list)) in points.chunks(d).zip(&assignments).enumerate() {
+            let centroid = &self.centroids[list as usize * d..][..d];
+            let residual: Vec<f32> = point.iter().zip(centroid).map(|(p, c)| p - c).collect();
+            let list = &mut self.lists[list as usize];
+            list.ids.push((start_id + i) as u64);
+            for j in 0..self.m {
+                let codebook = &self.codebooks[j * self.ksub * dsub..][..self.ksub * dsub];
+                list.codes.push(nearest(&residual[j * dsub..(j + 1) * dsub], codebook, dsub) as u8);
+            }
+        }
+    }
+
+    /// Approximate inner products of the vectors in the `nprobe` lists whose
+    /// centroids best match the query, for vectors accepted by `allowed`
+    fn scan(&self, query: &[f32], nprobe: usize, allowed: Option<&dyn Fn(u64) -> bool>) -> Vec<(u64, f32)> {
+        let d = self.dimensions;
+        let dsub = d / self.m;
+
+        let mut lists: Vec<(usize, f32)> = self
+            .centroids
+            .chunks(d)
+            .map(|centroid| dot(query, centroid))
+            .enumerate()
+            .collect();
+        let nprobe = nprobe.clamp(1, lists.len());
+        lists.select_nth_unstable_by(nprobe - 1, |a, b| b.1.total_cmp(&a.1));
+        lists.truncate(nprobe);
+
+        // Inner product of each query subvector with each subquantizer centroid
+        let table: Vec<f32> = (0..self.m)
+            .flat_map(|j| {
+                let sub = &query[j * dsub..(j + 1) * dsub];
+                self.codebooks[j * self.ksub * dsub..][..self.ksub * dsub]
+                    .chunks(dsub)
+                    .map(move |centroid| dot(sub, centroid))
+            })
+            .collect();
+
+        let mut scored = Vec::new();
+        for (list, base) in lists {
+            let list = &self.lists[list];
+            for (id, codes) in list.ids.iter().zip(list.codes.chunks(self.m)) {
+                if allowed.is_some_and(|allowed| !allowed(*id)) {
+                    continue;
+                }
+                let score = base
+                    + codes
+                        .iter()
+                        .enumerate()
+                        .map(|(j, &code)| table[j * self.ksub + code as usize])
+                        .sum::<f32>();
+                scored.push((*id, score));
+            }
+        }
+        scored
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        w.write_all(MAGIC)?;
+        for value in [VERSION, self.dimensions as u32, self.m as u32, self.ksub as u32, self.nlist() as u32] {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        for value in self.centroids.iter().chain(&self.codebooks) {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        for list in &self.lists {
+            w.write_all(&(list.ids.len() as u64).to_le_bytes())?;
+            for id in &list.ids {
+                w.write_all(&id.to_le_bytes())?;
+            }
+            w.write_all(&list.codes)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut r = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            anyhow::bail!("Not an IVF-PQ index: {}", path.display());
+        }
+        let read_u32 = |r: &mut BufReader<File>| -> anyhow::Result<usize> {
+            let mut buf = [0u8; 4];
+            r.read_exact(&mut buf)?;
+            Ok(u32::from_le_bytes(buf) as usize)
+        };
+        let version = read_u32(&mut r)?;
+        if version != VERSION as usize {
+            anyhow::bail!("Unsupported IVF-PQ index version {} in {}", version, path.display());
+        }
+        let (dimensions, m, ksub, nlist) = (read_u32(&mut r)?, read_u32(&mut r)?, read_u32(&mut r)?, read_u32(&mut r)?);
+        let dsub = dimensions / m.max(1);
+
+        let read_f32s = |r: &mut BufReader<File>, count: usize| -> anyhow::Result<Vec<f32>> {
+            let mut bytes = vec![0u8; count * 4];
+            r.read_exact(&mut bytes)?;
+            Ok(bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+        };
+        let centroids = read_f32s(&mut r, nlist * dimensions)?;
+        let codebooks = read_f32s(&mut r, m * ksub * dsub)?;
+
+        let mut lists = Vec::with_capacity(nlist);
+        for _ in 0..nlist {
+            let mut len = [0u8; 8];
+            r.read_exact(&mut len)?;
+            let len = u64::from_le_bytes(len) as usize;
+            let mut ids = vec![0u8; len * 8];
+            r.read_exact(&mut ids)?;
+            let mut codes = vec![0u8; len * m];
+            r.read_exact(&mut codes)?;
+            lists.push(InvertedList {
+                ids: ids.chunks_exact(8).map(|b| u64::from_le_bytes(b.try_into().unwrap())).collect(),
+                codes,
+            });
+        }
+
+        Ok(Self {
+            dimensions,
+            m,
+            ksub,
+            centroids,
+            codebooks,
+            lists,
+        })
+    }
+}
+
+/// IVF-PQ searcher, reranking with full vectors when the `.embeddings` file exists
+pub struct IvfPqSearcher {
+    index: IvfPqIndex,
+    /// Memory-mapped full-precision vectors, in passage order
+    vectors: Option<Mmap>,
+}
+
+impl IvfPqSearcher {
+    pub fn load(index_path: &Path, dimensions: usize) -> anyhow::Result<Self> {
+        let index_file = index_path.with_extension("ivfpq");
+        if !index_file.exists() {
+            anyhow::bail!(
+                "Index file not found: {:?}\n\
+                Run 'leann build' to create an index first.",
+                index_file
+            );
+        }
+        let index = IvfPqIndex::load(&index_file)?;
+        if index.dimensions != dimensions {
+            anyhow::bail!(
+                "IVF-PQ index has {} dimensions, expected {}",
+                index.dimensions,
+                dimensions
+            );
+        }
+
+        let embeddings_file = index_path.with_extension("embeddings");
+        let vectors = if embeddings_file.exists() {
+            let file = File::open(&embeddings_file)?;
+            Some(unsafe { Mmap::map(&file)? })
+        } else {
+            tracing::warn!("No embeddings file; IVF-PQ results are not reranked with full vectors");
+            None
+        };
+
+        info!(
+            "Loaded IVF-PQ index with {} vectors ({} lists, {} codes per vector)",
+            index.len(),
+            index.nlist(),
+            index.m
+        );
+        Ok(Self { index, vectors })
+    }
+
+    /// Nearest `top_k` by approximate score, reranked with full vectors
+    ///
+    /// Probes `complexity` lists, doubling the count while fewer than `top_k`
+    /// vectors are found.
+    fn search_lists(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        complexity: usize,
+        allowed: Option<&dyn Fn(u64) -> bool>,
+    ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
+        if query.len() != self.index.dimensions {
+            anyhow::bail!(
+                "Query has {} dimensions, index has {}",
+                query.len(),
+                self.index.dimensions
+            );
+        }
+
+        let mut nprobe = complexity.max(1);
+        let mut scored = loop {
+            let scored = self.index.scan(query, nprobe, allowed);
+            if scored.len() >= top_k || nprobe >= self.index.nlist() {
+                break scored;
+            }
+            nprobe *= 2;
+        };
+
+        if self.vectors.is_some() {
+            let rerank = (top_k * RERANK_FACTOR).max(MIN_RERANK);
+            if scored.len() > rerank {
+                scored.select_nth_unstable_by(rerank - 1, |a, b| b.1.total_cmp(&a.1));
+                scored.truncate(rerank);
+            }
+            for (id, score) in scored.iter_mut() {
+                if let Some(vector) = self.vector(*id) {
+                    *score = dot(query, &vector);
+                }
+            }
+        }
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(top_k);
+        // Report inner-product distances (1 - dot), like the other backends
+        Ok(scored.into_iter().map(|(id, score)| (id, 1.0 - score)).unzip())
+    }
+}
+
+impl BackendSearcher for IvfPqSearcher {
+    fn search(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        complexity: usize,
+    ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
+        self.search_lists(query, top_k, complexity, None)
+    }
+
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        complexity: usize,
+        allowed: &dyn Fn(u64) -> bool,
+    ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
+        self.search_lists(query, top_k, complexity, Some(allowed))
+    }
+
+    fn vector(&self, idx: u64) -> Option<Vec<f32>> {
+        let bytes_per_vector = self.index.dimensions * 4;
+        let start = idx as usize * bytes_per_vector;
+        let bytes = self.vectors.as_ref()?.get(start..start + bytes_per_vector)?;
+        Some(bytes.chunks_exact(4).map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]])).collect())
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+}
+
+/// Build an IVF-PQ index, writing the full vectors to `.embeddings` for
+/// reranking unless the build already did (recompute mode)
+pub fn build_index(embeddings: &[Vec<f32>], index_path: &Path, dimensions: usize) -> anyhow::Result<()> {
+    let mut index = IvfPqIndex::train(embeddings, dimensions);
+    info!(
+        "Building IVF-PQ index: {} vectors, {} dims, {} lists, {} codes per vector",
+        embeddings.len(),
+        dimensions,
+        index.nlist(),
+        index.m
+    );
+    index.add(embeddings, 0);
+
+    let index_file = index_path.with_extension("ivfpq");
+    index.save(&index_file)?;
+
+    let embeddings_file = index_path.with_extension("embeddings");
+    if !embeddings_file.exists() {
+        write_vectors(&embeddings_file, embeddings, false)?;
+    }
+
+    info!("IVF-PQ index saved to {:?}", index_file);
+    Ok(())
+}
+
+/// Add vectors to an existing IVF-PQ index with its trained quantizers
+pub fn add_to_index(
+    embeddings: &[Vec<f32>],
+    index_path: &Path,
+    dimensions: usize,
+    start_id: usize,
+) -> anyhow::Result<()> {
+    let index_file = index_path.with_extension("ivfpq");
+    let mut index = IvfPqIndex::load(&index_file)?;
+    if index.dimensions != dimensions {
+        anyhow::bail!("IVF-PQ index has {} dimensions, expected {}", index.dimensions, dimensions);
+    }
+    index.add(embeddings, start_id);
+    index.save(&index_file)?;
+
+    // Keep the rerank vectors in step with the index
+    let embeddings_file = index_path.with_extension("embeddings");
+    if embeddings_file.exists() {
+        let stored = std::fs::metadata(&embeddings_file)?.len() as usize / (dimensions * 4);
+        if stored == start_id {
+            write_vectors(&embeddings_file, embeddings, true)?;
+        } else {
+            tracing::warn!(
+                "Embeddings file holds {} vectors, expected {}; new passages won't be reranked",
+                stored,
+                start_id
+            );
+        }
+    }
+
+    info!("Updated IVF-PQ index saved with {} total vectors", index.len());
+    Ok(())
+}
+
+/// Inverted lists of product-quantized vectors, reranked with full vectors
+pub struct IvfPqBackend;
+
+static IVFPQ_INFO: BackendInfo = BackendInfo {
+    name: "ivfpq",
+    description: "IVF-PQ codes in memory, reranked with full vectors from disk",
+    supports_update: true,
+    supports_replace: false,
+    supports_filtering: true,
+    supports_sharding: false,
+    supports_pruning: false,
+    quantizations: &["pq8"],
+};
+
+impl Backend for IvfPqBackend {
+    fn info(&self) -> &BackendInfo {
+        &IVFPQ_INFO
+    }
+
+    fn load_searcher(&self, index_path: &Path, dimensions: usize) -> Result<Box<dyn BackendSearcher>, BackendError> {
+        let index_file = index_path.with_extension("ivfpq");
+        if !index_file.exists() {
+            return Err(BackendError::NotFound {
+                backend: IVFPQ_INFO.name,
+                path: index_file,
+            });
+        }
+        Ok(Box::new(IvfPqSearcher::load(index_path, dimensions)?))
+    }
+
+    /// IVF-PQ sizes its lists and codebooks from the data, so the graph
+    /// parameters don't apply
+    fn build(
+        &self,
+        embeddings: &[Vec<f32>],
+        _ids: &[String],
+        index_path: &Path,
+        dimensions: usize,
+        _params: &BuildParams,
+    ) -> Result<(), BackendError> {
+        Ok(build_index(embeddings, index_path, dimensions)?)
+    }
+
+    fn add_to_index(
+        &self,
+        embeddings: &[Vec<f32>],
+        index_path: &Path,
+        dimensions: usize,
+        start_id: usize,
+    ) -> Result<(), BackendError> {
+        Ok(add_to_index(embeddings, index_path, dimensions, start_id)?)
+    }
+}
+
+/// Write vectors in the `.embeddings` layout (native-endian f32, in order)
+fn write_vectors(path: &Path, embeddings: &[Vec<f32>], append: bool) -> anyhow::Result<()> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)?;
+    let mut w = BufWriter::new(file);
+    for embedding in embeddings {
+        for value in embedding {
+            w.write_all(&value.to_ne_bytes())?;
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Number of subquantizers for `dimensions`: the largest divisor of it up to
+/// `MAX_SUBQUANTIZERS`
+fn subquantizers(dimensions: usize) -> usize {
+    (1..=MAX_SUBQUANTIZERS.min(dimensions.max(1)))
+        .rev()
+        .find(|m| dimensions.is_multiple_of(*m))
+        .unwrap_or(1)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic unit vectors
+    fn vectors(n: usize, dim: usize) -> Vec<Vec<f32>> {
+        let mut rng = XorShift::new(42);
+        (0..n)
+            .map(|_| {
+                let v: Vec<f32> = (0..dim).map(|_| rng.below(2001) as f32 / 1000.0 - 1.0).collect();
+                let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+                v.into_iter().map(|x| x / norm).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_subquantizers() {
+        assert_eq!(subquantizers(1536), 64);
+        assert_eq!(subquantizers(384), 64);
+        assert_eq!(subquantizers(100), 50);
+        assert_eq!(subquantizers(7), 7);
+    }
+
+    #[test]
+    fn test_search_finds_exact_match_and_round_trips() {
+        let dir = std::env::temp_dir().join(format!("leann-ivfpq-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let index_path = dir.join("documents.leann");
+        let data = vectors(500, 16);
+
+        build_index(&data, &index_path, 16).unwrap();
+        let searcher = IvfPqSearcher::load(&index_path, 16).unwrap();
+        assert_eq!(searcher.len(), 500);
+
+        // Probing every list and reranking finds each vector itself
+        for i in [0, 123, 499] {
+            let (ids, distances) = searcher.search(&data[i], 3, usize::MAX).unwrap();
+            assert_eq!(ids[0], i as u64);
+            assert!(distances[0].abs() < 1e-4);
+        }
+
+        let (ids, _) = searcher.search_filtered(&data[7], 5, 4, &|id| id % 2 == 0).unwrap();
+        assert_eq!(ids.len(), 5);
+        assert!(ids.iter().all(|id| id % 2 == 0));
+        assert_eq!(searcher.vector(7), Some(data[7].clone()));
+
+        // Added vectors are searchable and reranked
+        let more = vectors(510, 16).split_off(500);
+        add_to_index(&more, &index_path, 16, 500).unwrap();
+        let searcher = IvfPqSearcher::load(&index_path, 16).unwrap();
+        assert_eq!(searcher.len(), 510);
+        let (ids, _) = searcher.search(&more[3], 1, usize::MAX).unwrap();
+        assert_eq!(ids, vec![503]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+//! k-means clustering shared by the IVF-PQ backend and `leann topics`
+
+const KMEANS_ITERATIONS: usize = 10;
+
+/// Index of the centroid nearest to `point` by L2 distance
+pub(crate) fn nearest(point: &[f32], centroids: &[f32], dim: usize) -> usize {
+    centroids
+        .chunks(dim)
+        .map(|c| point.iter().zip(c).map(|(p, c)| (p - c) * (p - c)).sum::<f32>())
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(i, _)| i)
+}
+
+/// Nearest centroid of each point (rows of `dim`), computed in parallel
+pub(crate) fn assign(points: &[f32], dim: usize, centroids: &[f32]) -> Vec<u32> {
+    let rows = points.len() / dim;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let rows_per_thread = rows.div_ceil(threads).max(1);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = points
+            .chunks(rows_per_thread * dim)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .chunks(dim)
+                        .map(|point| nearest(point, centroids, dim) as u32)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("k-means assignment panicked"))
+            .collect()
+    })
+}
+
+/// Lloyd's k-means over `points` (rows of `dim`), returning `k` centroids
+///
+/// Clusters that end up empty are restarted from a random point.
+pub(crate) fn kmeans(points: &[f32], dim: usize, k: usize, rng: &mut XorShift) -> Vec<f32> {
+    let rows = points.len() / dim;
+    if rows == 0 {
+        return vec![0.0; dim];
+    }
+    let k = k.min(rows);
+    let mut centroids: Vec<f32> = rng
+        .sample(rows, k)
+        .into_iter()
+        .flat_map(|i| points[i * dim..(i + 1) * dim].iter().copied())
+        .collect();
+
+    for _ in 0..KMEANS_ITERATIONS {
+        let assignments = assign(points, dim, &centroids);
+        let mut sums = vec![0.0f32; k * dim];
+        let mut counts = vec![0usize; k];
+        for (point, &cluster) in points.chunks(dim).zip(&assignments) {
+            let cluster = cluster as usize;
+            counts[cluster] += 1;
+            for (sum, value) in sums[cluster * dim..(cluster + 1) * dim].iter_mut().zip(point) {
+                *sum += value;
+            }
+        }
+        for cluster in 0..k {
+            let centroid = &mut centroids[cluster * dim..(cluster + 1) * dim];
+            if counts[cluster] == 0 {
+                let row = rng.below(rows);
+                centroid.copy_from_slice(&points[row * dim..(row + 1) * dim]);
+            } else {
+                for (c, sum) in centroid.iter_mut().zip(&sums[cluster * dim..(cluster + 1) * dim]) {
+                    *c = sum / counts[cluster] as f32;
+                }
+            }
+        }
+    }
+    centroids
+}
+
+/// Small deterministic PRNG for sampling, so builds are reproducible
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n.max(1) as u64) as usize
+    }
+
+    /// Up to `count` distinct indices below `n`
+    pub(crate) fn sample(&mut self, n: usize, count: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..n).collect();
+        let count = count.min(n);
+        for i in 0..count {
+            let j = i + self.below(n - i);
+            indices.swap(i, j);
+        }
+        indices.truncate(count);
+        indices
+    }
+}
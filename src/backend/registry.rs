@@ -0,0 +1,90 @@
+//! Backend registry - backends looked up by the name stored in index metadata
+
+use std::sync::{Arc, LazyLock, RwLock};
+
+use super::diskann::DiskAnnBackend;
+use super::hnsw::HnswBackend;
+use super::ivfpq::IvfPqBackend;
+use super::traits::{Backend, BackendError};
+
+/// Backends by name, in registration order
+pub struct BackendRegistry {
+    backends: Vec<Arc<dyn Backend>>,
+}
+
+impl BackendRegistry {
+    /// A registry of the built-in backends
+    pub fn with_builtins() -> Self {
+        let mut registry = Self { backends: Vec::new() };
+        registry.register(Arc::new(HnswBackend));
+        registry.register(Arc::new(DiskAnnBackend));
+        registry.register(Arc::new(IvfPqBackend));
+        registry
+    }
+
+    /// Add a backend, replacing any registered under the same name
+    pub fn register(&mut self, backend: Arc<dyn Backend>) {
+        let name = backend.info().name;
+        match self.backends.iter_mut().find(|b| b.info().name == name) {
+            Some(existing) => *existing = backend,
+            None => self.backends.push(backend),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Arc<dyn Backend>, BackendError> {
+        self.backends
+            .iter()
+            .find(|b| b.info().name == name)
+            .cloned()
+            .ok_or_else(|| BackendError::Unknown {
+                name: name.to_string(),
+                available: self.names().join(", "),
+            })
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.backends.iter().map(|b| b.info().name).collect()
+    }
+}
+
+static REGISTRY: LazyLock<RwLock<BackendRegistry>> = LazyLock::new(|| RwLock::new(BackendRegistry::with_builtins()));
+
+/// The process-wide registry, starting with the built-in backends
+pub fn registry() -> &'static RwLock<BackendRegistry> {
+    &REGISTRY
+}
+
+/// The registered backend called `name`
+pub fn resolve(name: &str) -> Result<Arc<dyn Backend>, BackendError> {
+    registry().read().expect("backend registry poisoned").get(name)
+}
+
+/// Names of the registered backends (for `--backend-name`)
+pub fn names() -> Vec<&'static str> {
+    registry().read().expect("backend registry poisoned").names()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtins_resolve_by_name() {
+        assert_eq!(names(), vec!["hnsw", "diskann", "ivfpq"]);
+        assert!(resolve("ivfpq").unwrap().info().supports_update);
+        assert!(!resolve("ivfpq").unwrap().info().supports_pruning);
+        assert!(resolve("hnsw").unwrap().info().supports_sharding);
+
+        let err = resolve("faiss").err().unwrap();
+        assert!(matches!(err, BackendError::Unknown { .. }));
+        assert_eq!(err.to_string(), "Unknown backend: faiss (available: hnsw, diskann, ivfpq)");
+    }
+
+    #[test]
+    fn test_register_replaces_same_name() {
+        let mut registry = BackendRegistry::with_builtins();
+        registry.register(Arc::new(IvfPqBackend));
+        assert_eq!(registry.names(), vec!["hnsw", "diskann", "ivfpq"]);
+        assert_eq!(registry.get("ivfpq").unwrap().info().quantizations, ["pq8"]);
+    }
+}
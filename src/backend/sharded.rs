@@ -0,0 +1,326 @@
+//! Sharded HNSW indexes - one logical index split into several graphs
+//!
+//! Each shard is an HNSW index over a contiguous range of passages, keyed by
+//! the passages' global offsets, so shard results merge without remapping.
+//! `documents.shards.json` lists the shards; searches fan out to all of them
+//! and keep the nearest results overall.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::hnsw::{self, HnswSearcher};
+use super::traits::BackendSearcher;
+
+/// How a build splits its passages into shards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShardPlan {
+    /// This many shards of about equal size (`--shards`)
+    Count(usize),
+    /// Shards of at most this many passages (`--shard-size`), each built as
+    /// soon as it fills
+    MaxPassages(usize),
+}
+
+impl ShardPlan {
+    /// Passage ranges (start, count) for `total` passages, for a plan of `Count` shards
+    pub fn split(self, total: usize) -> Vec<(usize, usize)> {
+        let size = match self {
+            ShardPlan::Count(shards) => total.div_ceil(shards.max(1)),
+            ShardPlan::MaxPassages(max) => max,
+        }
+        .max(1);
+        (0..total)
+            .step_by(size)
+            .map(|start| (start, size.min(total - start)))
+            .collect()
+    }
+}
+
+/// A shard's range of passage offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardInfo {
+    pub start: usize,
+    pub count: usize,
+}
+
+/// The shards of an index, in passage order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShardManifest {
+    /// Passages per shard for indexes sharded by size; updates start a new
+    /// shard when the last one is full
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_passages: Option<usize>,
+    pub shards: Vec<ShardInfo>,
+}
+
+impl ShardManifest {
+    fn path(index_path: &Path) -> PathBuf {
+        index_path.with_extension("shards.json")
+    }
+
+    /// Whether the index at `index_path` is sharded
+    pub fn exists(index_path: &Path) -> bool {
+        Self::path(index_path).exists()
+    }
+
+    pub fn load(index_path: &Path) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(Self::path(index_path))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&self, index_path: &Path) -> anyhow::Result<()> {
+        std::fs::write(Self::path(index_path), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Base path of a shard's graph (`documents.shard<N>.index` next to the index)
+fn shard_path(index_path: &Path, shard: usize) -> PathBuf {
+    index_path.with_extension(format!("shard{}.leann", shard))
+}
+
+/// Build shard `shard` from the embeddings of the passages starting at `start`
+pub fn build_shard(
+    embeddings: &[Vec<f32>],
+    index_path: &Path,
+    shard: usize,
+    start: usize,
+    dimensions: usize,
+    graph_degree: usize,
+    complexity: usize,
+) -> anyhow::Result<ShardInfo> {
+    info!("Building shard {} ({} passages from {})", shard, embeddings.len(), start);
+    hnsw::build_index_from(
+        embeddings,
+        &shard_path(index_path, shard),
+        dimensions,
+        graph_degree,
+        complexity,
+        start,
+    )?;
+    Ok(ShardInfo {
+        start,
+        count: embeddings.len(),
+    })
+}
+
+/// Add vectors to a sharded index: fill the last shard (up to the manifest's
+/// size limit), then build new shards for the rest
+pub fn add_to_index(
+    embeddings: &[Vec<f32>],
+    index_path: &Path,
+    dimensions: usize,
+    start_id: usize,
+) -> anyhow::Result<()> {
+    let mut manifest = ShardManifest::load(index_path)?;
+    let mut remaining = embeddings;
+    let mut next_id = start_id;
+
+    if let Some(last) = manifest.shards.len().checked_sub(1) {
+        let room = match manifest.max_passages {
+            Some(max) => max.saturating_sub(manifest.shards[last].count),
+            None => remaining.len(),
+        };
+        let (head, tail) = remaining.split_at(room.min(remaining.len()));
+        if !head.is_empty() {
+            hnsw::add_to_index(head, &shard_path(index_path, last), dimensions, next_id)?;
+            manifest.shards[last].count += head.len();
+            next_id += head.len();
+        }
+        remaining = tail;
+    }
+
+    let shard_size = manifest.max_passages.unwrap_or(remaining.len()).max(1);
+    for chunk in remaining.chunks(shard_size) {
+        // Same graph parameters `hnsw::add_to_index` loads existing graphs with
+        let shard = build_shard(chunk, index_path, manifest.shards.len(), next_id, dimensions, 32, 64)?;
+        manifest.shards.push(shard);
+        next_id += chunk.len();
+    }
+
+    manifest.save(index_path)
+}
+
+/// Replace and remove vectors in whichever shards hold their keys
+pub fn replace_in_index(
+    keys: &[u64],
+    embeddings: &[Vec<f32>],
+    removed: &[u64],
+    index_path: &Path,
+    dimensions: usize,
+) -> anyhow::Result<()> {
+    let manifest = ShardManifest::load(index_path)?;
+    let shard_of = |key: u64| {
+        manifest
+            .shards
+            .iter()
+            .position(|s| (s.start..s.start + s.count).contains(&(key as usize)))
+            .ok_or_else(|| anyhow::anyhow!("No shard holds vector {}", key))
+    };
+
+    let mut replaced: Vec<(Vec<u64>, Vec<Vec<f32>>)> = vec![Default::default(); manifest.shards.len()];
+    let mut dropped: Vec<Vec<u64>> = vec![Vec::new(); manifest.shards.len()];
+    for (&key, embedding) in keys.iter().zip(embeddings) {
+        let (keys, embeddings) = &mut replaced[shard_of(key)?];
+        keys.push(key);
+        embeddings.push(embedding.clone());
+    }
+    for &key in removed {
+        dropped[shard_of(key)?].push(key);
+    }
+
+    for (shard, ((keys, embeddings), removed)) in replaced.iter().zip(&dropped).enumerate() {
+        if !keys.is_empty() || !removed.is_empty() {
+            hnsw::replace_in_index(keys, embeddings, removed, &shard_path(index_path, shard), dimensions)?;
+        }
+    }
+    Ok(())
+}
+
+/// Searches every shard of an index and merges their results
+pub struct ShardedSearcher {
+    shards: Vec<HnswSearcher>,
+    /// Where each shard's passage range starts, for vector lookups
+    starts: Vec<usize>,
+}
+
+impl ShardedSearcher {
+    pub fn load(index_path: &Path, dimensions: usize) -> anyhow::Result<Self> {
+        let manifest = ShardManifest::load(index_path)?;
+        let shards = (0..manifest.shards.len())
+            .map(|shard| HnswSearcher::load(&shard_path(index_path, shard), dimensions))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        info!("Loaded {} shards", shards.len());
+
+        Ok(Self {
+            shards,
+            starts: manifest.shards.iter().map(|s| s.start).collect(),
+        })
+    }
+}
+
+/// Nearest `top_k` of several shards' (indices, distances) results
+fn merge(results: Vec<(Vec<u64>, Vec<f32>)>, top_k: usize) -> (Vec<u64>, Vec<f32>) {
+    let mut merged: Vec<(u64, f32)> = results
+        .into_iter()
+        .flat_map(|(indices, distances)| indices.into_iter().zip(distances))
+        .collect();
+    merged.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    merged.truncate(top_k);
+    merged.into_iter().unzip()
+}
+
+impl BackendSearcher for ShardedSearcher {
+    fn search(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        complexity: usize,
+    ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
+        // Search the shards in parallel
+        let results = std::thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .shards
+                .iter()
+                .map(|shard| scope.spawn(move || shard.search(query, top_k, complexity)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("shard search panicked"))
+                .collect::<anyhow::Result<Vec<_>>>()
+        })?;
+        Ok(merge(results, top_k))
+    }
+
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        complexity: usize,
+        allowed: &dyn Fn(u64) -> bool,
+    ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
+        let results = self
+            .shards
+            .iter()
+            .map(|shard| shard.search_filtered(query, top_k, complexity, allowed))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(merge(results, top_k))
+    }
+
+    fn vector(&self, idx: u64) -> Option<Vec<f32>> {
+        let shard = self.starts.partition_point(|&start| start as u64 <= idx).checked_sub(1)?;
+        self.shards[shard].vector(idx)
+    }
+
+    fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(ShardPlan::Count(3).split(10), vec![(0, 4), (4, 4), (8, 2)]);
+        assert_eq!(ShardPlan::Count(4).split(2), vec![(0, 1), (1, 1)]);
+        assert_eq!(ShardPlan::MaxPassages(5).split(10), vec![(0, 5), (5, 5)]);
+        assert!(ShardPlan::Count(2).split(0).is_empty());
+    }
+
+    #[test]
+    fn test_merge_keeps_nearest() {
+        let merged = merge(vec![(vec![0, 1], vec![0.1, 0.5]), (vec![7, 8], vec![0.2, 0.3])], 3);
+        assert_eq!(merged, (vec![0, 7, 8], vec![0.1, 0.2, 0.3]));
+    }
+
+    #[test]
+    fn test_sharded_search_and_update() {
+        let dir = std::env::temp_dir().join(format!("leann-shards-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let index_path = dir.join("documents.leann");
+
+        // Unit vectors along each of 8 axes, one per passage
+        let axis = |i: usize| -> Vec<f32> { (0..8).map(|d| if d == i { 1.0 } else { 0.0 }).collect() };
+        let embeddings: Vec<Vec<f32>> = (0..6).map(axis).collect();
+
+        let mut manifest = ShardManifest {
+            max_passages: Some(3),
+            shards: Vec::new(),
+        };
+        for (shard, (start, count)) in ShardPlan::MaxPassages(3).split(6).into_iter().enumerate() {
+            let info = build_shard(&embeddings[start..start + count], &index_path, shard, start, 8, 16, 64).unwrap();
+            manifest.shards.push(info);
+        }
+        manifest.save(&index_path).unwrap();
+
+        let searcher = ShardedSearcher::load(&index_path, 8).unwrap();
+        assert_eq!(searcher.len(), 6);
+        let (indices, _) = searcher.search(&axis(4), 1, 64).unwrap();
+        assert_eq!(indices, vec![4]);
+        let (indices, _) = searcher.search_filtered(&axis(4), 1, 64, &|idx| idx < 3).unwrap();
+        assert_eq!(indices.len(), 1);
+        assert!(indices[0] < 3);
+        assert_eq!(searcher.vector(5), Some(axis(5)));
+
+        // Two more passages start a third shard
+        add_to_index(&[axis(6), axis(7)], &index_path, 8, 6).unwrap();
+        let manifest = ShardManifest::load(&index_path).unwrap();
+        assert_eq!(manifest.shards.last(), Some(&ShardInfo { start: 6, count: 2 }));
+        let searcher = ShardedSearcher::load(&index_path, 8).unwrap();
+        assert_eq!(searcher.search(&axis(7), 1, 64).unwrap().0, vec![7]);
+
+        // Key 1 (first shard) now holds axis 7's vector and key 4 (second shard) is gone
+        replace_in_index(&[1], &[axis(7)], &[4], &index_path, 8).unwrap();
+        let searcher = ShardedSearcher::load(&index_path, 8).unwrap();
+        assert_eq!(searcher.len(), 7);
+        assert_eq!(searcher.vector(1), Some(axis(7)));
+        assert_eq!(searcher.vector(4), None);
+        assert!(!searcher.search(&axis(4), 7, 64).unwrap().0.contains(&4));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,10 +1,155 @@
 //! Backend traits for vector search
 
-use super::BackendType;
+use std::path::{Path, PathBuf};
 
-/// Builder for creating vector indexes
-pub struct BackendBuilder {
-    pub(crate) backend_type: BackendType,
+use serde::Serialize;
+
+use super::{DiskAnnParams, ShardInfo};
+
+/// What a backend is and what it can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BackendInfo {
+    /// Name passed to `--backend-name` and stored in the index metadata
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Whether `add_to_index` extends a built index (`leann update`)
+    pub supports_update: bool,
+    /// Whether `replace_in_index` swaps vectors under existing keys and
+    /// `remove_from_index` drops them, so updated and removed passages don't
+    /// leave outdated vectors behind
+    pub supports_replace: bool,
+    /// Whether searchers filter during traversal rather than over-fetching
+    pub supports_filtering: bool,
+    /// Whether `build_shard` splits the index into shards
+    pub supports_sharding: bool,
+    /// Whether the index still searches once `leann prune` deletes the embeddings file
+    pub supports_pruning: bool,
+    /// Vector encodings the index stores
+    pub quantizations: &'static [&'static str],
+}
+
+/// Errors from resolving, building, or loading a backend
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    #[error("Unknown backend: {name} (available: {available})")]
+    Unknown { name: String, available: String },
+
+    #[error("The {backend} backend does not support {operation}")]
+    Unsupported {
+        backend: &'static str,
+        operation: &'static str,
+    },
+
+    #[error("{backend} index not found: {path:?}\nRun 'leann build' to create an index first.")]
+    NotFound { backend: &'static str, path: PathBuf },
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl BackendError {
+    pub fn unsupported(info: &BackendInfo, operation: &'static str) -> Self {
+        Self::Unsupported {
+            backend: info.name,
+            operation,
+        }
+    }
+}
+
+/// Graph parameters for building an index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BuildParams {
+    pub graph_degree: usize,
+    pub complexity: usize,
+    /// Pruning and beam parameters (DiskANN only)
+    pub diskann: DiskAnnParams,
+}
+
+impl Default for BuildParams {
+    fn default() -> Self {
+        Self {
+            graph_degree: 32,
+            complexity: 64,
+            diskann: DiskAnnParams::default(),
+        }
+    }
+}
+
+/// A vector search backend: builds, extends, and opens one kind of index
+///
+/// Backends are looked up by name in the registry (see `backend::resolve`).
+pub trait Backend: Send + Sync {
+    /// Name and capabilities
+    fn info(&self) -> &BackendInfo;
+
+    /// Open the index at `index_path` for searching
+    fn load_searcher(&self, index_path: &Path, dimensions: usize) -> Result<Box<dyn BackendSearcher>, BackendError>;
+
+    /// Build an index over `embeddings`, keyed by their offsets
+    fn build(
+        &self,
+        embeddings: &[Vec<f32>],
+        ids: &[String],
+        index_path: &Path,
+        dimensions: usize,
+        params: &BuildParams,
+    ) -> Result<(), BackendError>;
+
+    /// Build one shard of a sharded index, over the passages starting at `start`
+    fn build_shard(
+        &self,
+        _embeddings: &[Vec<f32>],
+        _index_path: &Path,
+        _shard: usize,
+        _start: usize,
+        _dimensions: usize,
+        _params: &BuildParams,
+    ) -> Result<ShardInfo, BackendError> {
+        Err(BackendError::unsupported(self.info(), "sharding"))
+    }
+
+    /// Add vectors to a built index, keyed from `start_id`
+    fn add_to_index(
+        &self,
+        _embeddings: &[Vec<f32>],
+        _index_path: &Path,
+        _dimensions: usize,
+        _start_id: usize,
+    ) -> Result<(), BackendError> {
+        Err(BackendError::unsupported(self.info(), "incremental updates"))
+    }
+
+    /// Replace the vectors under `keys` with `embeddings` and remove the
+    /// vectors under `removed` from a built index
+    fn replace_in_index(
+        &self,
+        _keys: &[u64],
+        _embeddings: &[Vec<f32>],
+        _removed: &[u64],
+        _index_path: &Path,
+        _dimensions: usize,
+    ) -> Result<(), BackendError> {
+        Err(BackendError::unsupported(self.info(), "replacing vectors"))
+    }
+
+    /// Remove the vectors under `keys` from a built index, freeing the keys
+    /// for later vectors (see `free_keys`)
+    fn remove_from_index(&self, _keys: &[u64], _index_path: &Path, _dimensions: usize) -> Result<(), BackendError> {
+        Err(BackendError::unsupported(self.info(), "removing vectors"))
+    }
+
+    /// Keys whose vectors were removed and not yet reused, lowest first
+    fn free_keys(&self, _index_path: &Path) -> Result<Vec<u64>, BackendError> {
+        Ok(Vec::new())
+    }
+
+    /// Build parameters recorded in the index metadata
+    fn build_kwargs(&self, params: &BuildParams) -> serde_json::Value {
+        serde_json::json!({
+            "graph_degree": params.graph_degree,
+            "complexity": params.complexity,
+        })
+    }
 }
 
 /// Trait for searching a vector index
@@ -20,6 +165,45 @@ pub trait BackendSearcher: Send + Sync {
         complexity: usize,
     ) -> anyhow::Result<(Vec<u64>, Vec<f32>)>;
 
+    /// Search for nearest neighbors among the indices accepted by `allowed`
+    ///
+    /// The default implementation searches with an increasing candidate count
+    /// until `top_k` allowed results are found or the whole index was fetched.
+    /// Backends with native filtered search should override it.
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        complexity: usize,
+        allowed: &dyn Fn(u64) -> bool,
+    ) -> anyhow::Result<(Vec<u64>, Vec<f32>)> {
+        let total = self.len();
+        let mut fetch_k = (top_k * 4).max(16);
+
+        loop {
+            let fetch_k_capped = fetch_k.min(total);
+            let (indices, distances) = self.search(query, fetch_k_capped, complexity)?;
+            let exhausted = fetch_k_capped >= total || indices.len() < fetch_k_capped;
+
+            let (kept_indices, kept_distances): (Vec<u64>, Vec<f32>) = indices
+                .into_iter()
+                .zip(distances)
+                .filter(|(idx, _)| allowed(*idx))
+                .take(top_k)
+                .unzip();
+
+            if kept_indices.len() >= top_k || exhausted {
+                return Ok((kept_indices, kept_distances));
+            }
+            fetch_k *= 4;
+        }
+    }
+
+    /// Get the stored vector at an index, if the backend can return it
+    fn vector(&self, _idx: u64) -> Option<Vec<f32>> {
+        None
+    }
+
     /// Get the number of vectors in the index
     fn len(&self) -> usize;
 
@@ -0,0 +1,83 @@
+//! Cancellation of long operations - Ctrl-C and `leann cancel`
+//!
+//! Builds, updates, and re-embeds check a `Cancellation` between embedding
+//! batches. Once it is cancelled they stop there, keep the embeddings computed
+//! so far in a checkpoint (see `index::EmbeddingCheckpoint`), and leave the
+//! index as it was, so running the same command again picks up where it
+//! stopped instead of paying for those embeddings twice.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::LeannError;
+
+/// Exit status of an operation stopped by Ctrl-C or `leann cancel` (128 + SIGINT)
+pub const EXIT_CANCELLED: i32 = 130;
+
+/// Shared flag asking an operation to stop at its next checkpoint
+#[derive(Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operation to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// `LeannError::Cancelled` for `operation` on `index` if cancelled
+    pub fn check(&self, operation: &'static str, index: &str) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(LeannError::Cancelled { operation, index: index.to_string() }.into());
+        }
+        Ok(())
+    }
+
+    /// Whether `other` is a handle to the same flag
+    #[cfg(feature = "server")]
+    pub fn same(&self, other: &Cancellation) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Cancellation set by Ctrl-C: the first stops the operation after the
+    /// current batch, a second exits right away
+    pub fn on_ctrl_c() -> Self {
+        let cancel = Self::new();
+        let flag = cancel.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            flag.cancel();
+            eprintln!("\nStopping after the current batch (press Ctrl-C again to exit now)...");
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(EXIT_CANCELLED);
+            }
+        });
+        cancel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_is_shared() {
+        let cancel = Cancellation::new();
+        let handle = cancel.clone();
+        assert!(!handle.is_cancelled());
+        assert!(handle.check("Build", "docs").is_ok());
+        cancel.cancel();
+        let err = handle.check("Build", "docs").unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), EXIT_CANCELLED);
+        assert!(handle.is_cancelled());
+    }
+}
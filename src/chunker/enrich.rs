@@ -0,0 +1,446 @@
+//! Metadata enrichment - attach per-file metadata to chunks at build time
+//!
+//! Enrichers compute metadata once per source file; the pipeline copies it
+//! into every chunk of that file. Enrichers may also add fields computed from
+//! each chunk's own text. Fields already set by a chunker are kept.
+
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::{Map, Value};
+
+use super::Chunk;
+use crate::index::{extract_calls, extract_definitions, extract_imports};
+
+/// Names of the built-in enrichers "all" stands for, in the order they run
+///
+/// `git` isn't among them: it runs `git log` once per file, which is slow on
+/// large trees and needs git installed, so it's only run when named.
+pub const ALL_ENRICHERS: &[&str] = &["size", "lines", "language", "lang", "flags", "code"];
+
+/// Computes extra metadata for a source file
+pub trait Enricher: Send + Sync {
+    /// Short name used on the command line (e.g. "git")
+    fn name(&self) -> &'static str;
+
+    /// Add fields for the file at `path` with the given content
+    fn enrich(&self, path: &Path, content: &str, fields: &mut Map<String, Value>);
+
+    /// Add fields for one chunk of the file at `path` (default: none)
+    fn enrich_chunk(&self, _path: &Path, _text: &str, _fields: &mut Map<String, Value>) {}
+}
+
+/// File size in bytes (`file_size`)
+pub struct FileSizeEnricher;
+
+impl Enricher for FileSizeEnricher {
+    fn name(&self) -> &'static str {
+        "size"
+    }
+
+    fn enrich(&self, path: &Path, content: &str, fields: &mut Map<String, Value>) {
+        let size = std::fs::metadata(path)
+            .map(|m| m.len())
+            .unwrap_or(content.len() as u64);
+        fields.insert("file_size".to_string(), size.into());
+    }
+}
+
+/// Number of lines in the file (`line_count`)
+pub struct LineCountEnricher;
+
+impl Enricher for LineCountEnricher {
+    fn name(&self) -> &'static str {
+        "lines"
+    }
+
+    fn enrich(&self, _path: &Path, content: &str, fields: &mut Map<String, Value>) {
+        fields.insert("line_count".to_string(), content.lines().count().into());
+    }
+}
+
+/// Language detected from the file extension (`language`)
+pub struct LanguageEnricher;
+
+impl Enricher for LanguageEnricher {
+    fn name(&self) -> &'static str {
+        "language"
+    }
+
+    fn enrich(&self, path: &Path, _content: &str, fields: &mut Map<String, Value>) {
+        if let Some(language) = detect_language(path) {
+            fields.insert("language".to_string(), language.into());
+        }
+    }
+}
+
+/// Natural language detected per chunk from its text (`lang`, an ISO 639-3
+/// code such as "eng" or "cmn"); code files are skipped
+pub struct TextLanguageEnricher;
+
+impl Enricher for TextLanguageEnricher {
+    fn name(&self) -> &'static str {
+        "lang"
+    }
+
+    fn enrich(&self, _path: &Path, _content: &str, _fields: &mut Map<String, Value>) {}
+
+    fn enrich_chunk(&self, path: &Path, text: &str, fields: &mut Map<String, Value>) {
+        if !matches!(detect_language(path), None | Some("markdown" | "text" | "pdf")) {
+            return;
+        }
+        if let Some(lang) = detect_text_language(text) {
+            fields.insert("lang".to_string(), lang.into());
+        }
+    }
+}
+
+/// Heuristic `is_test` and `is_generated` flags
+pub struct FlagsEnricher;
+
+impl Enricher for FlagsEnricher {
+    fn name(&self) -> &'static str {
+        "flags"
+    }
+
+    fn enrich(&self, path: &Path, content: &str, fields: &mut Map<String, Value>) {
+        fields.insert("is_test".to_string(), is_test_file(path, content).into());
+        fields.insert("is_generated".to_string(), is_generated_file(path, content).into());
+    }
+}
+
+/// Imports of code files (`imports`) and the symbols each chunk defines and
+/// calls (`defines`, `calls`), for following references between chunks
+pub struct CodeReferencesEnricher;
+
+impl Enricher for CodeReferencesEnricher {
+    fn name(&self) -> &'static str {
+        "code"
+    }
+
+    fn enrich(&self, path: &Path, content: &str, fields: &mut Map<String, Value>) {
+        if is_code_file(path) {
+            insert_list(fields, "imports", extract_imports(content));
+        }
+    }
+
+    fn enrich_chunk(&self, path: &Path, text: &str, fields: &mut Map<String, Value>) {
+        if is_code_file(path) {
+            insert_list(fields, "defines", extract_definitions(text));
+            insert_list(fields, "calls", extract_calls(text));
+        }
+    }
+}
+
+fn insert_list(fields: &mut Map<String, Value>, key: &str, values: Vec<String>) {
+    if !values.is_empty() {
+        fields.insert(key.to_string(), values.into());
+    }
+}
+
+/// Last commit time and author from git (`git_modified`, `git_author`)
+///
+/// `git_modified` is a Unix timestamp so it works with numeric filters.
+/// Files outside a git repository are left untouched.
+pub struct GitEnricher;
+
+impl Enricher for GitEnricher {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn enrich(&self, path: &Path, _content: &str, fields: &mut Map<String, Value>) {
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        let Some(file_name) = path.file_name() else {
+            return;
+        };
+
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["log", "-1", "--format=%ct%x09%an", "--"])
+            .arg(file_name)
+            .output();
+
+        let Ok(output) = output else {
+            return;
+        };
+        if !output.status.success() {
+            return;
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if let Some((timestamp, author)) = stdout.trim().split_once('\t') {
+            if let Ok(timestamp) = timestamp.parse::<u64>() {
+                fields.insert("git_modified".to_string(), timestamp.into());
+            }
+            fields.insert("git_author".to_string(), author.into());
+        }
+    }
+}
+
+/// Ordered set of enrichers applied to every file during a build
+#[derive(Default)]
+pub struct EnrichmentPipeline {
+    enrichers: Vec<Box<dyn Enricher>>,
+}
+
+impl EnrichmentPipeline {
+    /// Pipeline with the enrichers "all" stands for
+    pub fn all() -> Self {
+        Self::from_names(ALL_ENRICHERS).expect("built-in enricher names are valid")
+    }
+
+    /// Build a pipeline from enricher names ("all" and "none" are accepted,
+    /// e.g. "all,git")
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> anyhow::Result<Self> {
+        let mut pipeline = Self::default();
+
+        for name in names {
+            match name.as_ref().trim() {
+                "" | "none" => {}
+                "all" => pipeline.enrichers.extend(Self::all().enrichers),
+                "size" => pipeline.enrichers.push(Box::new(FileSizeEnricher)),
+                "lines" => pipeline.enrichers.push(Box::new(LineCountEnricher)),
+                "language" => pipeline.enrichers.push(Box::new(LanguageEnricher)),
+                "lang" => pipeline.enrichers.push(Box::new(TextLanguageEnricher)),
+                "flags" => pipeline.enrichers.push(Box::new(FlagsEnricher)),
+                "code" => pipeline.enrichers.push(Box::new(CodeReferencesEnricher)),
+                "git" => pipeline.enrichers.push(Box::new(GitEnricher)),
+                other => anyhow::bail!(
+                    "Unknown enricher: {} (available: {}, git, all, none)",
+                    other,
+                    ALL_ENRICHERS.join(", ")
+                ),
+            }
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Names of the enrichers in this pipeline
+    pub fn names(&self) -> Vec<String> {
+        self.enrichers.iter().map(|e| e.name().to_string()).collect()
+    }
+
+    /// Compute file-level metadata and copy it into each chunk of the file
+    pub fn enrich(&self, path: &Path, content: &str, chunks: &mut [Chunk]) {
+        if self.enrichers.is_empty() || chunks.is_empty() {
+            return;
+        }
+
+        let mut fields = Map::new();
+        for enricher in &self.enrichers {
+            enricher.enrich(path, content, &mut fields);
+        }
+
+        for chunk in chunks.iter_mut() {
+            let mut chunk_fields = Map::new();
+            for enricher in &self.enrichers {
+                enricher.enrich_chunk(path, &chunk.text, &mut chunk_fields);
+            }
+            if let Some(obj) = chunk.metadata.as_object_mut() {
+                for (key, value) in fields.iter().chain(&chunk_fields) {
+                    obj.entry(key.clone()).or_insert_with(|| value.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Whether a file is source code (by extension), as opposed to prose or data
+pub fn is_code_file(path: &Path) -> bool {
+    !matches!(detect_language(path), None | Some("markdown" | "text" | "pdf" | "json" | "yaml" | "toml"))
+}
+
+/// Detect the natural language of a text as an ISO 639-3 code ("eng", "deu",
+/// "cmn", ...), if the detection is reliable
+pub fn detect_text_language(text: &str) -> Option<&'static str> {
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code())
+}
+
+/// ISO 639-3 code for a language given by code or English name ("deu", "German")
+pub fn language_code(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_lowercase();
+    whatlang::Lang::all()
+        .iter()
+        .find(|lang| lang.code() == name || lang.eng_name().to_lowercase() == name)
+        .map(|lang| lang.code())
+}
+
+/// Detect a language name from a file extension
+pub fn detect_language(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let language = match ext.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "jsx" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "kt" => "kotlin",
+        "scala" => "scala",
+        "cs" => "csharp",
+        "md" => "markdown",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "txt" => "text",
+        "pdf" => "pdf",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Heuristic: test directories, test file naming conventions, or Rust test modules
+fn is_test_file(path: &Path, content: &str) -> bool {
+    let in_test_dir = path.components().any(|c| {
+        matches!(
+            c.as_os_str().to_str(),
+            Some("test" | "tests" | "spec" | "specs" | "__tests__" | "testdata")
+        )
+    });
+    if in_test_dir {
+        return true;
+    }
+
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let named_like_test = stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_tests")
+        || stem.ends_with(".test")
+        || stem.ends_with(".spec")
+        || stem.ends_with("_spec");
+    if named_like_test {
+        return true;
+    }
+
+    // Rust files that consist mostly of a test module
+    path.extension().and_then(|e| e.to_str()) == Some("rs")
+        && content.trim_start().starts_with("#[cfg(test)]")
+}
+
+/// Heuristic: generated-code markers near the top of the file, or well-known generated names
+fn is_generated_file(path: &Path, content: &str) -> bool {
+    let file_name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let generated_name = file_name.ends_with(".pb.go")
+        || file_name.ends_with("_pb2.py")
+        || file_name.ends_with(".g.dart")
+        || file_name.ends_with(".min.js")
+        || file_name.ends_with(".generated.cs")
+        || matches!(
+            file_name.as_str(),
+            "package-lock.json" | "cargo.lock" | "yarn.lock" | "pnpm-lock.yaml"
+        );
+    if generated_name {
+        return true;
+    }
+
+    content.lines().take(10).any(|line| {
+        let line = line.to_lowercase();
+        line.contains("@generated")
+            || line.contains("do not edit")
+            || line.contains("code generated")
+            || line.contains("autogenerated")
+            || line.contains("auto-generated")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_heuristics() {
+        assert!(is_test_file(Path::new("src/tests/foo.rs"), ""));
+        assert!(is_test_file(Path::new("pkg/foo_test.go"), ""));
+        assert!(is_test_file(Path::new("web/button.spec.ts"), ""));
+        assert!(!is_test_file(Path::new("src/main.rs"), "fn main() {}"));
+
+        assert!(is_generated_file(Path::new("api.pb.go"), ""));
+        assert!(is_generated_file(Path::new("x.rs"), "// @generated by build.rs\n"));
+        assert!(!is_generated_file(Path::new("x.rs"), "fn main() {}"));
+    }
+
+    #[test]
+    fn test_pipeline_keeps_chunker_fields() {
+        let pipeline = EnrichmentPipeline::from_names(&["lines", "language"]).unwrap();
+        let mut chunks = vec![Chunk {
+            id: "1".to_string(),
+            text: "fn a() {}".to_string(),
+            metadata: serde_json::json!({ "language": "custom" }),
+        }];
+
+        pipeline.enrich(Path::new("lib.rs"), "fn a() {}\nfn b() {}\n", &mut chunks);
+
+        assert_eq!(chunks[0].metadata["line_count"], 2);
+        assert_eq!(chunks[0].metadata["language"], "custom");
+        assert!(EnrichmentPipeline::from_names(&["bogus"]).is_err());
+        // git only runs when named
+        assert!(!EnrichmentPipeline::all().names().contains(&"git".to_string()));
+        assert_eq!(EnrichmentPipeline::from_names(&["all", "git"]).unwrap().names().last().map(String::as_str), Some("git"));
+    }
+
+    #[test]
+    fn test_code_references() {
+        let pipeline = EnrichmentPipeline::from_names(&["code"]).unwrap();
+        let content = "use crate::tokenizer::tokenize_expression;\n\npub fn evaluate(input: &str) -> usize {\n    \
+                       tokenize_expression(input).len()\n}\n";
+        let mut chunks = vec![Chunk { id: "1".to_string(), text: content.to_string(), metadata: serde_json::json!({}) }];
+
+        pipeline.enrich(Path::new("calc.rs"), content, &mut chunks);
+        assert_eq!(chunks[0].metadata["imports"], serde_json::json!(["crate::tokenizer::tokenize_expression"]));
+        assert_eq!(chunks[0].metadata["defines"], serde_json::json!(["evaluate"]));
+        assert_eq!(chunks[0].metadata["calls"], serde_json::json!(["tokenize_expression", "len"]));
+
+        // Prose gets no code references
+        let mut prose = vec![Chunk { id: "1".to_string(), text: content.to_string(), metadata: serde_json::json!({}) }];
+        pipeline.enrich(Path::new("notes.md"), content, &mut prose);
+        assert_eq!(prose[0].metadata, serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_text_language_per_chunk() {
+        let pipeline = EnrichmentPipeline::from_names(&["lang"]).unwrap();
+        let chunk = |text: &str| Chunk {
+            id: "1".to_string(),
+            text: text.to_string(),
+            metadata: serde_json::json!({}),
+        };
+        let mut chunks = vec![
+            chunk("The quarterly report shows that revenue grew faster than expected this year."),
+            chunk("Der Quartalsbericht zeigt, dass der Umsatz in diesem Jahr schneller gewachsen ist als erwartet."),
+        ];
+
+        pipeline.enrich(Path::new("notes.md"), "", &mut chunks);
+        assert_eq!(chunks[0].metadata["lang"], "eng");
+        assert_eq!(chunks[1].metadata["lang"], "deu");
+
+        // Code isn't prose
+        let mut code = vec![chunk("The quarterly report shows that revenue grew faster than expected this year.")];
+        pipeline.enrich(Path::new("report.rs"), "", &mut code);
+        assert!(code[0].metadata.get("lang").is_none());
+
+        assert_eq!(language_code("German"), Some("deu"));
+        assert_eq!(language_code("cmn"), Some("cmn"));
+        assert_eq!(language_code("klingon"), None);
+    }
+}
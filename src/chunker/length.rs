@@ -0,0 +1,225 @@
+//! Chunk length policy - what to do with chunks longer than the embedding model accepts
+//!
+//! Chunkers target `--doc-chunk-size`, but code blocks, data sections, and
+//! large chunk sizes can still exceed the model's input limit. The policy
+//! decides whether such chunks fail the build, are cut, or are split.
+
+use super::Chunk;
+
+/// How to handle chunks over the token limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkLengthPolicy {
+    /// Fail the build, naming the offending source
+    Error,
+    /// Keep the start of the chunk and drop the rest
+    Truncate,
+    /// Split into several chunks that each fit
+    #[default]
+    Split,
+}
+
+impl std::str::FromStr for ChunkLengthPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "truncate" => Ok(Self::Truncate),
+            "split" => Ok(Self::Split),
+            _ => Err(format!("Unknown chunk length policy: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for ChunkLengthPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Error => "error",
+            Self::Truncate => "truncate",
+            Self::Split => "split",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Counters for chunks affected by the length policy
+#[derive(Debug, Clone, Default)]
+pub struct LengthStats {
+    /// Chunks checked against the limit
+    pub checked: usize,
+    /// Chunks cut by `Truncate`
+    pub truncated: usize,
+    /// Chunks split by `Split`
+    pub split: usize,
+    /// Chunks produced by splitting (replacing the `split` originals)
+    pub split_pieces: usize,
+    /// Estimated tokens dropped by truncation
+    pub tokens_dropped: usize,
+}
+
+impl LengthStats {
+    /// Whether any chunk was changed
+    pub fn any(&self) -> bool {
+        self.truncated > 0 || self.split > 0
+    }
+}
+
+/// A token limit together with the policy for chunks that exceed it
+#[derive(Debug, Clone)]
+pub struct ChunkLengthLimit {
+    pub policy: ChunkLengthPolicy,
+    pub max_tokens: usize,
+}
+
+impl ChunkLengthLimit {
+    pub fn new(policy: ChunkLengthPolicy, max_tokens: usize) -> Self {
+        Self {
+            policy,
+            max_tokens: max_tokens.max(1),
+        }
+    }
+
+    /// Apply the policy to one file's chunks
+    ///
+    /// Split pieces keep their chunk's ID; `assign_stable_ids` gives every
+    /// chunk of the file its own afterwards.
+    pub fn apply(&self, chunks: Vec<Chunk>, stats: &mut LengthStats) -> anyhow::Result<Vec<Chunk>> {
+        let mut output = Vec::with_capacity(chunks.len());
+
+        for chunk in chunks {
+            stats.checked += 1;
+            let tokens = crate::embedding::estimate_tokens(&chunk.text);
+
+            if tokens <= self.max_tokens {
+                output.push(chunk);
+                continue;
+            }
+
+            match self.policy {
+                ChunkLengthPolicy::Error => {
+                    let source = chunk
+                        .metadata
+                        .get("source")
+                        .and_then(|s| s.as_str())
+                        .unwrap_or("unknown source");
+                    anyhow::bail!(
+                        "Chunk from {} is ~{} tokens, over the {}-token limit. \
+                        Use --chunk-length-policy truncate|split or a smaller --doc-chunk-size.",
+                        source,
+                        tokens,
+                        self.max_tokens
+                    );
+                }
+                ChunkLengthPolicy::Truncate => {
+                    let kept = truncate_to_tokens(&chunk.text, self.max_tokens);
+                    stats.truncated += 1;
+                    stats.tokens_dropped += tokens - crate::embedding::estimate_tokens(kept);
+
+                    let mut metadata = chunk.metadata;
+                    if let Some(obj) = metadata.as_object_mut() {
+                        obj.insert("truncated".to_string(), true.into());
+                    }
+                    output.push(Chunk {
+                        id: chunk.id,
+                        text: kept.to_string(),
+                        metadata,
+                    });
+                }
+                ChunkLengthPolicy::Split => {
+                    let pieces = split_to_tokens(&chunk.text, self.max_tokens);
+                    stats.split += 1;
+                    stats.split_pieces += pieces.len();
+
+                    for (part, piece) in pieces.iter().enumerate() {
+                        let mut metadata = chunk.metadata.clone();
+                        if let Some(obj) = metadata.as_object_mut() {
+                            obj.insert("split_part".to_string(), part.into());
+                        }
+                        output.push(Chunk {
+                            id: chunk.id.clone(),
+                            text: piece.to_string(),
+                            metadata,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Longest prefix of `text` within `max_tokens`, cut at a whitespace boundary when possible
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> &str {
+    // Same estimate as `embedding::estimate_tokens` (~4 chars per token)
+    let max_chars = max_tokens * 4;
+    let end = match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => idx,
+        None => return text,
+    };
+
+    let cut = &text[..end];
+    match cut.rfind(char::is_whitespace) {
+        // Don't back off more than half the budget looking for a boundary
+        Some(ws) if ws >= end / 2 => cut[..ws].trim_end(),
+        _ => cut,
+    }
+}
+
+/// Split `text` into consecutive pieces that each fit within `max_tokens`
+fn split_to_tokens(text: &str, max_tokens: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let piece = truncate_to_tokens(rest, max_tokens);
+        let piece = if piece.is_empty() { rest } else { piece };
+        pieces.push(piece);
+        rest = rest[piece.len()..].trim_start();
+    }
+
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(text: &str) -> Chunk {
+        Chunk {
+            id: "0".to_string(),
+            text: text.to_string(),
+            metadata: serde_json::json!({ "source": "a.txt" }),
+        }
+    }
+
+    #[test]
+    fn test_truncate_to_tokens() {
+        assert_eq!(truncate_to_tokens("short", 10), "short");
+        assert_eq!(truncate_to_tokens("aaaa bbbb cccc", 2), "aaaa");
+        // Multi-byte characters are never split
+        assert_eq!(truncate_to_tokens("ééééé", 1), "éééé");
+    }
+
+    #[test]
+    fn test_policies() {
+        let long = "word ".repeat(40);
+        let mut stats = LengthStats::default();
+
+        let limit = ChunkLengthLimit::new(ChunkLengthPolicy::Split, 10);
+        let out = limit.apply(vec![chunk("ok"), chunk(&long)], &mut stats).unwrap();
+        assert!(out.len() > 2);
+        assert_eq!(out[0].text, "ok");
+        assert!(out.iter().all(|c| crate::embedding::estimate_tokens(&c.text) <= 10));
+        assert_eq!(stats.split, 1);
+
+        let limit = ChunkLengthLimit::new(ChunkLengthPolicy::Truncate, 10);
+        let out = limit.apply(vec![chunk(&long)], &mut stats).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].metadata["truncated"], true);
+        assert_eq!(stats.truncated, 1);
+
+        let limit = ChunkLengthLimit::new(ChunkLengthPolicy::Error, 10);
+        assert!(limit.apply(vec![chunk(&long)], &mut stats).is_err());
+    }
+}
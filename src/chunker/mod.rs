@@ -3,10 +3,20 @@
 //! Provides both simple character-based chunking and AST-aware code chunking.
 
 mod ast;
+mod enrich;
+mod length;
+mod overrides;
+mod secrets;
 mod simple;
+mod structured;
 
 pub use ast::CodeChunker;
+pub use enrich::{detect_language, detect_text_language, is_code_file, language_code, EnrichmentPipeline};
+pub use length::{truncate_to_tokens, ChunkLengthLimit, ChunkLengthPolicy, LengthStats};
+pub use overrides::{is_excluded_dir, DirOverrides};
+pub use secrets::{SecretScanner, SecretStats, SecretsPolicy};
 pub use simple::SimpleChunker;
+pub use structured::StructuredChunker;
 
 use std::path::Path;
 
@@ -60,10 +70,18 @@ pub trait Chunker {
 }
 
 /// Unified chunker that selects strategy based on file type
+///
+/// JSON, YAML, and TOML files use the structured-data chunker unless it is
+/// turned off for text-free indexes.
 pub struct SmartChunker {
     strategy: ChunkingStrategy,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    /// Chunk data files by key path
+    structured_data: bool,
     simple: SimpleChunker,
     ast: CodeChunker,
+    structured: StructuredChunker,
 }
 
 impl SmartChunker {
@@ -74,11 +92,25 @@ impl SmartChunker {
     ) -> Self {
         Self {
             strategy,
+            chunk_size,
+            chunk_overlap,
+            structured_data: true,
             simple: SimpleChunker::new(chunk_size, chunk_overlap),
             ast: CodeChunker::new(chunk_size, chunk_overlap),
+            structured: StructuredChunker::new(chunk_size, chunk_overlap),
         }
     }
 
+    /// Turn key-path chunking of data files on or off
+    ///
+    /// Structured chunks are prefixed with their key path and have no byte
+    /// range in the source, so a text-free index couldn't re-read their text;
+    /// text-free builds chunk data files like any other text instead.
+    pub fn with_structured_data(mut self, enabled: bool) -> Self {
+        self.structured_data = enabled;
+        self
+    }
+
     /// Check if a file should use AST chunking
     fn should_use_ast(&self, path: &Path) -> bool {
         let ext = path
@@ -102,7 +134,11 @@ impl Chunker for SmartChunker {
         source_path: &Path,
         chunk_id: &mut u64,
     ) -> Vec<Chunk> {
-        match self.strategy {
+        if self.structured_data && StructuredChunker::format_for(source_path).is_some() {
+            return self.structured.chunk(text, source_path, chunk_id);
+        }
+
+        let mut chunks = match self.strategy {
             ChunkingStrategy::Simple => self.simple.chunk(text, source_path, chunk_id),
             ChunkingStrategy::Ast => self.ast.chunk(text, source_path, chunk_id),
             ChunkingStrategy::Auto => {
@@ -112,6 +148,163 @@ impl Chunker for SmartChunker {
                     self.simple.chunk(text, source_path, chunk_id)
                 }
             }
+        };
+        annotate_locations(text, &mut chunks);
+        chunks
+    }
+}
+
+/// Record where each chunk lives in its source text
+///
+/// Adds `byte_start`/`byte_end` for chunks whose text appears verbatim in the
+/// source, and `start_line`/`end_line` (1-indexed) when the chunker didn't
+/// set them. These let text be re-read from disk when it isn't stored.
+fn annotate_locations(text: &str, chunks: &mut [Chunk]) {
+    let mut cursor = 0usize;
+    // (byte position, line number at that position) for incremental line counting
+    let mut line_mark = (0usize, 1usize);
+
+    for chunk in chunks.iter_mut() {
+        if chunk.text.is_empty() {
+            continue;
         }
+
+        let found = text[cursor..]
+            .find(&chunk.text)
+            .map(|pos| cursor + pos)
+            .or_else(|| text.find(&chunk.text));
+
+        let Some(start) = found else {
+            continue;
+        };
+        let end = start + chunk.text.len();
+        cursor = start;
+
+        if start < line_mark.0 {
+            line_mark = (0, 1);
+        }
+        let start_line = line_mark.1 + text[line_mark.0..start].matches('\n').count();
+        line_mark = (start, start_line);
+        let end_line = start_line + chunk.text.matches('\n').count();
+
+        if let Some(obj) = chunk.metadata.as_object_mut() {
+            obj.insert("byte_start".to_string(), start.into());
+            obj.insert("byte_end".to_string(), end.into());
+            obj.entry("start_line").or_insert(start_line.into());
+            obj.entry("end_line").or_insert(end_line.into());
+        }
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of text, as a hex string
+///
+/// Used to verify re-read source text matches what was indexed.
+pub fn content_hash(text: &str) -> String {
+    format!("{:016x}", fnv1a(&[text.as_bytes()]))
+}
+
+/// Deterministic id of a chunk from its source, position in the source, and content
+///
+/// Rebuilding an unchanged file yields the same ids, so passages can be
+/// matched across builds and updates.
+pub fn stable_chunk_id(source: &str, index: usize, content: &[u8]) -> String {
+    let index = index.to_string();
+    format!("{:016x}", fnv1a(&[source.as_bytes(), b"\0", index.as_bytes(), b"\0", content]))
+}
+
+/// Replace the sequential ids chunkers assign with stable ids
+///
+/// `chunks` are all the chunks of the file at `source`, in order. The path is
+/// canonicalized when possible so ids don't depend on how `--docs` was given.
+pub fn assign_stable_ids(chunks: &mut [Chunk], source: &Path) {
+    let source = source.canonicalize().unwrap_or_else(|_| source.to_path_buf());
+    let source = source.to_string_lossy();
+    for (index, chunk) in chunks.iter_mut().enumerate() {
+        chunk.id = stable_chunk_id(&source, index, chunk.text.as_bytes());
+    }
+}
+
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_locations() {
+        let text = "first line\nsecond line\nthird line";
+        let mut chunks = vec![
+            Chunk {
+                id: "1".to_string(),
+                text: "second line".to_string(),
+                metadata: serde_json::json!({}),
+            },
+            Chunk {
+                id: "2".to_string(),
+                text: "second line\nthird line".to_string(),
+                metadata: serde_json::json!({}),
+            },
+        ];
+
+        annotate_locations(text, &mut chunks);
+
+        assert_eq!(chunks[0].metadata["byte_start"], 11);
+        assert_eq!(chunks[0].metadata["byte_end"], 22);
+        assert_eq!(chunks[0].metadata["start_line"], 2);
+        assert_eq!(chunks[1].metadata["start_line"], 2);
+        assert_eq!(chunks[1].metadata["end_line"], 3);
+    }
+
+    #[test]
+    fn test_text_free_data_files_have_locations() {
+        let text = "{\n  \"name\": \"leann\",\n  \"tags\": [\"rag\", \"rust\"]\n}\n";
+        let path = Path::new("package.json");
+        let mut chunk_id = 0;
+
+        let structured = SmartChunker::new(ChunkingStrategy::Auto, 1000, 100).chunk(text, path, &mut chunk_id);
+        assert!(structured.iter().all(|c| c.metadata.get("byte_start").is_none()));
+
+        // Without key-path chunking, every chunk can be re-read from the source
+        let chunks = SmartChunker::new(ChunkingStrategy::Auto, 1000, 100)
+            .with_structured_data(false)
+            .chunk(text, path, &mut chunk_id);
+        assert!(!chunks.is_empty());
+        for chunk in &chunks {
+            let start = chunk.metadata["byte_start"].as_u64().unwrap() as usize;
+            let end = chunk.metadata["byte_end"].as_u64().unwrap() as usize;
+            assert_eq!(&text[start..end], chunk.text);
+        }
+    }
+
+    #[test]
+    fn test_content_hash_stable() {
+        assert_eq!(content_hash(""), "cbf29ce484222325");
+        assert_eq!(content_hash("hello"), content_hash("hello"));
+        assert_ne!(content_hash("hello"), content_hash("hello "));
+    }
+
+    #[test]
+    fn test_stable_chunk_ids() {
+        let chunk = |text: &str| Chunk { id: "7".to_string(), text: text.to_string(), metadata: serde_json::json!({}) };
+        let mut first = vec![chunk("intro"), chunk("body")];
+        let mut again = vec![chunk("intro"), chunk("body")];
+        assign_stable_ids(&mut first, Path::new("notes/a.md"));
+        assign_stable_ids(&mut again, Path::new("notes/a.md"));
+
+        assert_eq!(first[0].id, again[0].id);
+        assert_eq!(first[0].id.len(), 16);
+        assert_ne!(first[0].id, first[1].id);
+        assert_ne!(first[0].id, stable_chunk_id("notes/b.md", 0, b"intro"));
+        assert_ne!(first[1].id, stable_chunk_id("notes/a.md", 1, b"body!"));
     }
 }
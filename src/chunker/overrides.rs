@@ -0,0 +1,248 @@
+//! Per-directory overrides - `.leann.toml` files inside the documents tree
+//!
+//! A `.leann.toml` in any directory under a documents root applies to the
+//! files below it:
+//!
+//! ```toml
+//! chunk_size = 512
+//! chunk_overlap = 64
+//! chunking_strategy = "ast"
+//! exclude = false          # true skips the whole subtree
+//!
+//! [metadata]
+//! project = "frontend"
+//! ```
+//!
+//! Files are merged from the documents root down to the file's directory;
+//! deeper files override shallower ones, and metadata tables are combined.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Deserialize;
+
+use super::{Chunk, ChunkingStrategy, SmartChunker};
+
+/// Name of the per-directory override file
+pub const DIR_CONFIG_FILE: &str = ".leann.toml";
+
+/// Contents of one `.leann.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DirConfig {
+    chunk_size: Option<usize>,
+    chunk_overlap: Option<usize>,
+    chunking_strategy: Option<String>,
+    #[serde(default)]
+    exclude: bool,
+    #[serde(default)]
+    metadata: BTreeMap<String, toml::Value>,
+}
+
+fn read_dir_config(dir: &Path) -> anyhow::Result<Option<DirConfig>> {
+    let path = dir.join(DIR_CONFIG_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)?;
+    let config: DirConfig = toml::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))?;
+    if let Some(strategy) = &config.chunking_strategy {
+        strategy
+            .parse::<ChunkingStrategy>()
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))?;
+    }
+    Ok(Some(config))
+}
+
+/// Whether `dir` has a `.leann.toml` with `exclude = true`
+///
+/// Unreadable files are reported and don't exclude anything; the build fails
+/// on them later, when their settings are looked up.
+pub fn is_excluded_dir(dir: &Path) -> bool {
+    match read_dir_config(dir) {
+        Ok(config) => config.is_some_and(|c| c.exclude),
+        Err(e) => {
+            tracing::warn!("{}", e);
+            false
+        }
+    }
+}
+
+/// Settings merged from the `.leann.toml` files above a source file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirSettings {
+    pub chunk_size: Option<usize>,
+    pub chunk_overlap: Option<usize>,
+    pub chunking_strategy: Option<ChunkingStrategy>,
+    /// Static tags added to every chunk
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+impl DirSettings {
+    fn merge(&mut self, config: &DirConfig) {
+        self.chunk_size = config.chunk_size.or(self.chunk_size);
+        self.chunk_overlap = config.chunk_overlap.or(self.chunk_overlap);
+        if let Some(strategy) = config.chunking_strategy.as_deref().and_then(|s| s.parse().ok()) {
+            self.chunking_strategy = Some(strategy);
+        }
+        for (key, value) in &config.metadata {
+            if let Ok(value) = serde_json::to_value(value) {
+                self.metadata.insert(key.clone(), value);
+            }
+        }
+    }
+
+    /// Whether any setting changes how chunks are split
+    fn changes_chunking(&self) -> bool {
+        self.chunk_size.is_some() || self.chunk_overlap.is_some() || self.chunking_strategy.is_some()
+    }
+
+    /// Add the metadata tags to chunks, replacing fields of the same name
+    pub fn tag(&self, chunks: &mut [Chunk]) {
+        if self.metadata.is_empty() {
+            return;
+        }
+        for chunk in chunks.iter_mut() {
+            if let Some(obj) = chunk.metadata.as_object_mut() {
+                for (key, value) in &self.metadata {
+                    obj.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Looks up `.leann.toml` settings for source files, reading each directory once
+pub struct DirOverrides {
+    roots: Vec<PathBuf>,
+    /// Tags every file gets, whatever its `.leann.toml` files set
+    metadata: serde_json::Map<String, serde_json::Value>,
+    cache: Mutex<HashMap<PathBuf, Option<DirConfig>>>,
+}
+
+impl DirOverrides {
+    /// Overrides for files under the documents roots `roots`
+    pub fn new(roots: &[PathBuf]) -> Self {
+        Self {
+            roots: roots.iter().filter(|r| r.is_dir()).cloned().collect(),
+            metadata: serde_json::Map::new(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Tag every file with `key`, overriding any `.leann.toml` (e.g. the
+    /// namespace given on the command line, which a directory can't escape)
+    pub fn with_metadata(mut self, key: &str, value: serde_json::Value) -> Self {
+        self.metadata.insert(key.to_string(), value);
+        self
+    }
+
+    /// Settings for `file`, merged from its documents root down to its directory
+    ///
+    /// A file given directly (outside any root directory) only picks up the
+    /// `.leann.toml` next to it.
+    pub fn settings_for(&self, file: &Path) -> anyhow::Result<DirSettings> {
+        let mut settings = DirSettings::default();
+        let Some(parent) = file.parent() else {
+            settings.metadata = self.metadata.clone();
+            return Ok(settings);
+        };
+        let root = self
+            .roots
+            .iter()
+            .filter(|root| parent.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .map_or(parent, |root| root.as_path());
+
+        // Directories from the file's up to the root, applied root first
+        let dirs: Vec<&Path> = parent.ancestors().take_while(|dir| dir.starts_with(root)).collect();
+
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        for dir in dirs.into_iter().rev() {
+            let config = match cache.get(dir) {
+                Some(config) => config,
+                None => {
+                    let config = read_dir_config(dir)?;
+                    cache.entry(dir.to_path_buf()).or_insert(config)
+                }
+            };
+            if let Some(config) = config {
+                settings.merge(config);
+            }
+        }
+        settings.metadata.extend(self.metadata.clone());
+        Ok(settings)
+    }
+}
+
+impl SmartChunker {
+    /// Chunker with a directory's chunking overrides, if it has any
+    pub fn with_settings(&self, settings: &DirSettings) -> Option<SmartChunker> {
+        settings.changes_chunking().then(|| {
+            SmartChunker::new(
+                settings.chunking_strategy.unwrap_or(self.strategy),
+                settings.chunk_size.unwrap_or(self.chunk_size),
+                settings.chunk_overlap.unwrap_or(self.chunk_overlap),
+            )
+            .with_structured_data(self.structured_data)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hierarchical_settings() {
+        let root = std::env::temp_dir().join(format!("leann-dir-overrides-{}", std::process::id()));
+        let web = root.join("web");
+        let components = web.join("components");
+        std::fs::create_dir_all(&components).unwrap();
+        std::fs::write(root.join(DIR_CONFIG_FILE), "chunk_size = 800\n[metadata]\nteam = \"core\"\n").unwrap();
+        std::fs::write(
+            web.join(DIR_CONFIG_FILE),
+            "chunk_size = 300\nchunking_strategy = \"ast\"\n[metadata]\nproject = \"frontend\"\n",
+        )
+        .unwrap();
+        std::fs::write(components.join(DIR_CONFIG_FILE), "exclude = true\n").unwrap();
+
+        let overrides = DirOverrides::new(std::slice::from_ref(&root));
+        let settings = overrides.settings_for(&web.join("app.ts")).unwrap();
+        assert_eq!(settings.chunk_size, Some(300));
+        assert_eq!(settings.chunking_strategy, Some(ChunkingStrategy::Ast));
+        assert_eq!(settings.metadata["team"], "core");
+        assert_eq!(settings.metadata["project"], "frontend");
+
+        let top = overrides.settings_for(&root.join("README.md")).unwrap();
+        assert_eq!(top.chunk_size, Some(800));
+        assert!(!top.metadata.contains_key("project"));
+
+        let base = SmartChunker::new(ChunkingStrategy::Auto, 1000, 100);
+        let chunker = base.with_settings(&settings).unwrap();
+        assert_eq!((chunker.chunk_size, chunker.chunk_overlap), (300, 100));
+
+        let mut chunks = vec![Chunk { id: "0".into(), text: "x".into(), metadata: serde_json::json!({}) }];
+        settings.tag(&mut chunks);
+        assert_eq!(chunks[0].metadata["project"], "frontend");
+
+        assert!(is_excluded_dir(&components));
+        assert!(!is_excluded_dir(&web));
+
+        // A tag given on the command line wins over .leann.toml, which only fills it in otherwise
+        std::fs::write(web.join(DIR_CONFIG_FILE), "[metadata]\nnamespace = \"web\"\n").unwrap();
+        let overrides = DirOverrides::new(std::slice::from_ref(&root)).with_metadata("namespace", "shared".into());
+        assert_eq!(overrides.settings_for(&root.join("README.md")).unwrap().metadata["namespace"], "shared");
+        assert_eq!(overrides.settings_for(&web.join("app.ts")).unwrap().metadata["namespace"], "shared");
+        let overrides = DirOverrides::new(std::slice::from_ref(&root));
+        assert_eq!(overrides.settings_for(&web.join("app.ts")).unwrap().metadata["namespace"], "web");
+
+        std::fs::write(web.join(DIR_CONFIG_FILE), "chunk_sise = 300\n").unwrap();
+        let overrides = DirOverrides::new(std::slice::from_ref(&root));
+        assert!(overrides.settings_for(&web.join("app.ts")).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
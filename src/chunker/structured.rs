@@ -0,0 +1,362 @@
+//! Structured-data chunking for JSON, YAML, and TOML files
+//!
+//! Splits data files along their key structure instead of by character count,
+//! so each chunk covers a coherent subtree. Every chunk records its location as
+//! a `json_path` (e.g. `$.paths./users.get`), which is also prefixed to the text
+//! so the embedding sees the context.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::{Chunk, Chunker, SimpleChunker};
+
+/// Chunker for JSON, YAML, and TOML files
+pub struct StructuredChunker {
+    /// Approximate chunk size in tokens (1 token ~= 4 chars)
+    chunk_size: usize,
+    /// Fallback for unparseable files and oversized leaf values
+    fallback: SimpleChunker,
+}
+
+/// A piece of a data file: its path and rendered text
+struct Section {
+    path: String,
+    text: String,
+}
+
+impl StructuredChunker {
+    pub fn new(chunk_size: usize, chunk_overlap: usize) -> Self {
+        Self {
+            chunk_size,
+            fallback: SimpleChunker::new(chunk_size, chunk_overlap),
+        }
+    }
+
+    /// Data format handled for a path, if any
+    pub fn format_for(path: &Path) -> Option<&'static str> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some("json"),
+            "yaml" | "yml" => Some("yaml"),
+            "toml" => Some("toml"),
+            _ => None,
+        }
+    }
+
+    fn char_limit(&self) -> usize {
+        self.chunk_size * 4
+    }
+
+    /// Split a JSON value into sections, descending into subtrees that are too large
+    fn json_sections(&self, path: &str, value: &Value, sections: &mut Vec<Section>) {
+        let rendered = serde_json::to_string_pretty(value).unwrap_or_default();
+        if rendered.len() <= self.char_limit() {
+            sections.push(Section {
+                path: path.to_string(),
+                text: rendered,
+            });
+            return;
+        }
+
+        let children: Vec<(String, &Value)> = match value {
+            Value::Object(map) => map
+                .iter()
+                .map(|(k, v)| (format!("{}.{}", path, k), v))
+                .collect(),
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (format!("{}[{}]", path, i), v))
+                .collect(),
+            // Oversized scalar (e.g. a long description string); split later
+            _ => {
+                sections.push(Section {
+                    path: path.to_string(),
+                    text: rendered,
+                });
+                return;
+            }
+        };
+
+        // Group small siblings so a wide object doesn't become hundreds of tiny chunks
+        let mut group: Vec<String> = Vec::new();
+        let mut group_len = 0;
+        for (child_path, child) in children {
+            let child_rendered = serde_json::to_string_pretty(child).unwrap_or_default();
+            let key = child_path[path.len()..].trim_start_matches('.').to_string();
+            let entry = format!("{}: {}", key, child_rendered);
+
+            if entry.len() > self.char_limit() {
+                self.flush_group(path, &mut group, &mut group_len, sections);
+                self.json_sections(&child_path, child, sections);
+                continue;
+            }
+
+            if group_len + entry.len() > self.char_limit() {
+                self.flush_group(path, &mut group, &mut group_len, sections);
+            }
+            group_len += entry.len() + 1;
+            group.push(entry);
+        }
+        self.flush_group(path, &mut group, &mut group_len, sections);
+    }
+
+    fn flush_group(
+        &self,
+        path: &str,
+        group: &mut Vec<String>,
+        group_len: &mut usize,
+        sections: &mut Vec<Section>,
+    ) {
+        if group.is_empty() {
+            return;
+        }
+        sections.push(Section {
+            path: path.to_string(),
+            text: group.join("\n"),
+        });
+        group.clear();
+        *group_len = 0;
+    }
+
+    /// Split YAML by key, descending by indentation into sections that are too large
+    fn yaml_sections(&self, path: &str, lines: &[&str], sections: &mut Vec<Section>) {
+        let text = lines.join("\n");
+        if text.trim().is_empty() {
+            return;
+        }
+        if text.len() <= self.char_limit() {
+            sections.push(Section {
+                path: path.to_string(),
+                text,
+            });
+            return;
+        }
+
+        // Keys at the shallowest indentation in this block start new sections
+        let indent = lines
+            .iter()
+            .filter(|l| is_yaml_key_line(l))
+            .map(|l| leading_spaces(l))
+            .min();
+        let starts: Vec<(usize, String)> = match indent {
+            Some(indent) => lines
+                .iter()
+                .enumerate()
+                .filter(|(_, l)| leading_spaces(l) == indent && is_yaml_key_line(l))
+                .map(|(i, l)| (i, format!("{}.{}", path, yaml_key(l))))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        match starts.as_slice() {
+            // No keys (long list or block string): split by size later
+            [] => sections.push(Section {
+                path: path.to_string(),
+                text,
+            }),
+            // This block is a single key: descend into its body
+            [(0, _)] => self.yaml_sections(path, &lines[1..], sections),
+            _ => {
+                // Preamble before the first key (comments, document markers)
+                self.yaml_sections(path, &lines[..starts[0].0], sections);
+                for (n, (start, key_path)) in starts.iter().enumerate() {
+                    let end = starts.get(n + 1).map_or(lines.len(), |(i, _)| *i);
+                    self.yaml_sections(key_path, &lines[*start..end], sections);
+                }
+            }
+        }
+    }
+
+    /// Split TOML by table headers (`[table]`, `[[array]]`)
+    fn toml_sections(&self, text: &str) -> Vec<Section> {
+        let mut sections = Vec::new();
+        let mut path = "$".to_string();
+        let mut current: Vec<&str> = Vec::new();
+
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                if current.iter().any(|l| !l.trim().is_empty()) {
+                    sections.push(Section {
+                        path: path.clone(),
+                        text: current.join("\n"),
+                    });
+                }
+                current.clear();
+                let table = trimmed.trim_start_matches('[').trim_end_matches(']').trim();
+                path = format!("$.{}", table);
+            }
+            current.push(line);
+        }
+        if current.iter().any(|l| !l.trim().is_empty()) {
+            sections.push(Section {
+                path,
+                text: current.join("\n"),
+            });
+        }
+
+        sections
+    }
+}
+
+impl Chunker for StructuredChunker {
+    fn chunk(
+        &self,
+        text: &str,
+        source_path: &Path,
+        chunk_id: &mut u64,
+    ) -> Vec<Chunk> {
+        let Some(format) = Self::format_for(source_path) else {
+            return self.fallback.chunk(text, source_path, chunk_id);
+        };
+
+        let sections = match format {
+            "json" => match serde_json::from_str::<Value>(text) {
+                Ok(value) => {
+                    let mut sections = Vec::new();
+                    self.json_sections("$", &value, &mut sections);
+                    sections
+                }
+                Err(e) => {
+                    tracing::debug!("Invalid JSON in {}: {}", source_path.display(), e);
+                    return self.fallback.chunk(text, source_path, chunk_id);
+                }
+            },
+            "yaml" => {
+                let lines: Vec<&str> = text.lines().collect();
+                let mut sections = Vec::new();
+                self.yaml_sections("$", &lines, &mut sections);
+                sections
+            }
+            _ => self.toml_sections(text),
+        };
+
+        let mut chunks = Vec::new();
+        for section in sections {
+            // Oversized leaves fall back to character chunking, keeping the path
+            let pieces = if section.text.len() > self.char_limit() {
+                let mut scratch_id = 0u64;
+                self.fallback
+                    .chunk(&section.text, source_path, &mut scratch_id)
+                    .into_iter()
+                    .map(|c| c.text)
+                    .collect()
+            } else {
+                vec![section.text]
+            };
+
+            for piece in pieces {
+                *chunk_id += 1;
+                chunks.push(Chunk {
+                    id: chunk_id.to_string(),
+                    text: format!("{}\n{}", section.path, piece),
+                    metadata: serde_json::json!({
+                        "source": source_path.to_string_lossy(),
+                        "chunk_type": "structured",
+                        "format": format,
+                        "json_path": section.path,
+                    }),
+                });
+            }
+        }
+
+        chunks
+    }
+}
+
+fn leading_spaces(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Key name of a YAML key line, without quotes or list marker
+fn yaml_key(line: &str) -> &str {
+    let candidate = line.trim().trim_start_matches("- ");
+    candidate
+        .split(':')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// Whether a YAML line starts a mapping key (`key:` or `- key:`)
+fn is_yaml_key_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed == "---" {
+        return false;
+    }
+    let candidate = trimmed.trim_start_matches("- ");
+    match candidate.find(':') {
+        Some(idx) => {
+            let after = &candidate[idx + 1..];
+            idx > 0 && (after.is_empty() || after.starts_with(' '))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_paths() {
+        let chunker = StructuredChunker::new(16, 0);
+        let text = serde_json::json!({
+            "openapi": "3.0.0",
+            "paths": {
+                "/users": {
+                    "get": { "summary": "List all users in the system with pagination" },
+                    "post": { "summary": "Create a new user account from the request body" }
+                }
+            }
+        })
+        .to_string();
+
+        let mut id = 0;
+        let chunks = chunker.chunk(&text, Path::new("api.json"), &mut id);
+        let paths: Vec<&str> = chunks
+            .iter()
+            .map(|c| c.metadata["json_path"].as_str().unwrap())
+            .collect();
+
+        assert!(paths.iter().any(|p| p.starts_with("$.paths./users.get")));
+        assert!(chunks.iter().all(|c| c.metadata["chunk_type"] == "structured"));
+    }
+
+    #[test]
+    fn test_small_json_single_chunk() {
+        let chunker = StructuredChunker::new(256, 0);
+        let mut id = 0;
+        let chunks = chunker.chunk(r#"{"a": 1}"#, Path::new("x.json"), &mut id);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].metadata["json_path"], "$");
+    }
+
+    #[test]
+    fn test_yaml_and_toml_sections() {
+        let chunker = StructuredChunker::new(8, 0);
+        let yaml = "server:\n  host: localhost\n  port: 8080\ndatabase:\n  url: postgres://localhost/app\n";
+        let mut id = 0;
+        let chunks = chunker.chunk(yaml, Path::new("config.yaml"), &mut id);
+        assert!(chunks.iter().any(|c| c.metadata["json_path"] == "$.database"));
+
+        let toml = "title = \"app\"\n\n[server]\nhost = \"localhost\"\n\n[database]\nurl = \"x\"\n";
+        let chunks = chunker.chunk(toml, Path::new("config.toml"), &mut id);
+        let paths: Vec<&str> = chunks
+            .iter()
+            .map(|c| c.metadata["json_path"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, vec!["$", "$.server", "$.database"]);
+    }
+
+    #[test]
+    fn test_invalid_json_falls_back() {
+        let chunker = StructuredChunker::new(256, 0);
+        let mut id = 0;
+        let chunks = chunker.chunk("{not json", Path::new("x.json"), &mut id);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].metadata.get("json_path").is_none());
+    }
+}
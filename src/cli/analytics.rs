@@ -0,0 +1,75 @@
+//! Analytics command - summarize an index's query log
+
+use clap::Args;
+
+use crate::index::{find_index, query_log_path, read_query_log, QueryAnalytics};
+
+#[derive(Args)]
+pub struct AnalyticsArgs {
+    /// Index name to summarize queries for
+    pub index_name: String,
+
+    /// Number of queries to list per ranking
+    #[arg(long, default_value = "10")]
+    pub top: usize,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+}
+
+pub async fn run(args: AnalyticsArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let entries = read_query_log(&index_dir)?;
+
+    if entries.is_empty() {
+        println!("No queries logged for index '{}'.", args.index_name);
+        println!("Enable query logging with 'log_queries = true' under [analytics] in the config file.");
+        return Ok(());
+    }
+
+    let analytics = QueryAnalytics::from_entries(&entries, args.top);
+
+    if args.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&analytics)?);
+        return Ok(());
+    }
+
+    let first = entries.iter().map(|e| e.timestamp).min().unwrap_or_default();
+    let last = entries.iter().map(|e| e.timestamp).max().unwrap_or_default();
+
+    println!("Query analytics for '{}'", args.index_name);
+    println!("{}", "=".repeat(50));
+    println!("  Log:              {}", query_log_path(&index_dir).display());
+    println!(
+        "  Period:           {} to {}",
+        crate::time::format_rfc3339(first),
+        crate::time::format_rfc3339(last)
+    );
+    println!("  Queries:          {} ({} distinct)", analytics.queries, analytics.distinct_queries);
+    println!(
+        "  Zero results:     {} ({:.1}%)",
+        analytics.zero_result_count,
+        analytics.zero_result_count as f64 * 100.0 / analytics.queries as f64
+    );
+    println!("  Results used:     {:.1}% of queries", analytics.used_rate * 100.0);
+
+    println!("\nLatency");
+    println!("  p50:              {} ms", analytics.latency_p50_ms);
+    println!("  p90:              {} ms", analytics.latency_p90_ms);
+    println!("  p99:              {} ms", analytics.latency_p99_ms);
+
+    println!("\nTop queries");
+    for (query, count) in &analytics.top_queries {
+        println!("  {:>5}  {}", count, query);
+    }
+
+    if !analytics.zero_result_queries.is_empty() {
+        println!("\nQueries with no results");
+        for (query, count) in &analytics.zero_result_queries {
+            println!("  {:>5}  {}", count, query);
+        }
+    }
+
+    Ok(())
+}
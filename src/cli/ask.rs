@@ -1,28 +1,56 @@
 //! Ask command - RAG question answering
 
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+
 use clap::Args;
+use regex::Regex;
 use tracing::info;
 
-use crate::embedding::{EmbeddingMode, EmbeddingProvider};
-use crate::index::{find_index, IndexMeta, IndexSearcher};
-use crate::llm::{LlmProvider, LlmType};
+use crate::config::Config;
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::index::{
+    log_query, log_used, resolve_index, IndexMeta, IndexSearcher, KnowledgeGraph, QueryLogEntry, SearchOptions, SearchResult,
+    Synonyms,
+};
+use crate::llm::{
+    context_window, count_tokens, generate_structured, pack_context, reformulate_query, GenerationOptions, LlmOverrides,
+    LlmProvider, LlmType, OutputSchema, PackedContext, PiiRedactor, PromptTemplate, ReformulationCache, TokenUsage, DEFAULT_ASK_TEMPLATE, DEFAULT_MAX_TOKENS,
+};
+use crate::usage::{format_cost, record_usage, UsageKind, UsageRecord};
+
+use super::history::{QuestionHistory, HISTORY_INDEX};
+use super::repl::{print_commands, Repl};
 
 #[derive(Args)]
 pub struct AskArgs {
     /// Question to ask (omit for interactive mode)
     pub query: Option<String>,
 
-    /// Index name to query (defaults to current directory name)
+    /// Index name or s3://bucket/prefix URL to query (defaults to current directory name)
     #[arg(short, long)]
     pub index: Option<String>,
 
-    /// LLM provider
-    #[arg(long, default_value = "ollama", value_parser = ["ollama", "openai", "anthropic", "simulated"])]
-    pub llm: String,
+    /// LLM provider (default: `[llm] provider` in config.toml, else ollama)
+    #[cfg(feature = "local-llm")]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated", "local"])]
+    pub llm: Option<String>,
+
+    /// LLM provider (default: `[llm] provider` in config.toml, else ollama)
+    #[cfg(not(feature = "local-llm"))]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated"])]
+    pub llm: Option<String>,
 
-    /// LLM model name
-    #[arg(long, default_value = "qwen3:8b")]
-    pub model: String,
+    /// LLM model name (default: `[llm] model` in config.toml, else qwen3:8b)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Tokenizer file for local GGUF models (default: tokenizer.json next to the model)
+    #[cfg(feature = "local-llm")]
+    #[arg(long)]
+    pub tokenizer: Option<String>,
 
     /// Ollama host
     #[arg(long, env = "OLLAMA_HOST")]
@@ -40,13 +68,72 @@ pub struct AskArgs {
     #[arg(long)]
     pub interactive: bool,
 
-    /// Number of passages to retrieve
-    #[arg(long, default_value = "5")]
-    pub top_k: usize,
+    /// Number of passages to retrieve (default: the index's default, then
+    /// `[search] top_k` in config.toml, else 5)
+    #[arg(long)]
+    pub top_k: Option<usize>,
+
+    /// Search complexity (default: the index's default, then `[search] complexity`
+    /// in config.toml, else 64)
+    #[arg(long)]
+    pub complexity: Option<usize>,
+
+    /// Retrieve with hybrid search (vector + BM25) (default: the index's default)
+    #[arg(long)]
+    pub hybrid: bool,
+
+    /// Retrieve with vector search only, even if the index's default is hybrid search
+    #[arg(long, conflicts_with = "hybrid")]
+    pub no_hybrid: bool,
+
+    /// Retrieve with HyDE: search near an LLM-written hypothetical answer
+    /// (blended with the question embedding) instead of the question alone
+    #[arg(long)]
+    pub hyde: bool,
+
+    /// Weight of the hypothetical answer in the HyDE blend: 1.0 = hypothetical answer only
+    #[arg(long, default_value = "0.5")]
+    pub hyde_weight: f32,
+
+    /// Also retrieve with N LLM-written reformulations of the question and fuse
+    /// the rankings with reciprocal rank fusion (reformulations are cached)
+    #[arg(long, value_name = "N")]
+    pub multi_query: Option<usize>,
+
+    /// Prompt template file (Jinja-style; variables: context, question, history)
+    #[arg(long)]
+    pub prompt_template: Option<PathBuf>,
+
+    /// Token budget for retrieved passages; lower-ranked passages are truncated or
+    /// dropped to fit (default: the model's context window less the prompt and answer)
+    #[arg(long)]
+    pub context_budget: Option<usize>,
+
+    /// Also add passages about knowledge-graph neighbors of the entities in
+    /// the question and the retrieved passages (indexes built with --graph)
+    #[arg(long)]
+    pub graph: bool,
+
+    /// Most passages --graph adds
+    #[arg(long, default_value = "3")]
+    pub graph_passages: usize,
+
+    /// Don't add synonyms from the index's synonym file (<name>.synonyms.toml)
+    #[arg(long)]
+    pub no_synonyms: bool,
+
+    /// Retrieve with the question as typed, without correcting words missing
+    /// from the index's vocabulary
+    #[arg(long)]
+    pub no_correct: bool,
+
+    /// Answer with JSON conforming to this JSON Schema file, for extraction
+    /// (replies that don't validate are retried with the errors)
+    #[arg(long, value_name = "FILE")]
+    pub schema: Option<PathBuf>,
 
-    /// Search complexity
-    #[arg(long, default_value = "64")]
-    pub complexity: usize,
+    #[command(flatten)]
+    pub generation: GenerationArgs,
 
     /// API key for embedding service
     #[arg(long, env = "OPENAI_API_KEY")]
@@ -61,6 +148,54 @@ pub struct AskArgs {
     pub embedding_host: Option<String>,
 }
 
+/// LLM generation flags shared by `ask`, `react`, and `serve`
+#[derive(Args, Clone, Default)]
+pub struct GenerationArgs {
+    /// System prompt sent to the LLM
+    #[arg(long)]
+    pub system_prompt: Option<String>,
+
+    /// Sampling temperature (0-2)
+    #[arg(long)]
+    pub temperature: Option<f32>,
+
+    /// Nucleus sampling probability mass (0-1)
+    #[arg(long)]
+    pub top_p: Option<f32>,
+
+    /// Maximum tokens to generate (default: 1000 for OpenAI/Anthropic, model default for Ollama)
+    #[arg(long)]
+    pub max_tokens: Option<u32>,
+
+    /// Stop generation at this sequence (repeatable)
+    #[arg(long)]
+    pub stop: Vec<String>,
+
+    /// Replace personal information in prompts with placeholders before they
+    /// are sent to the LLM (comma-separated: emails, phones, names); answers
+    /// are shown with the original values restored
+    #[arg(long, value_name = "KINDS")]
+    pub redact: Option<String>,
+}
+
+impl GenerationArgs {
+    /// Generation options from the flags (unset flags use provider defaults)
+    pub fn to_options(&self) -> GenerationOptions {
+        GenerationOptions {
+            system: self.system_prompt.clone(),
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            stop: self.stop.clone(),
+        }
+    }
+
+    /// Prompt redactor from `--redact`, if given
+    pub fn redactor(&self) -> anyhow::Result<Option<PiiRedactor>> {
+        self.redact.as_deref().map(PiiRedactor::from_names).transpose()
+    }
+}
+
 pub async fn run(args: AskArgs, _verbose: bool) -> anyhow::Result<()> {
     // Default to current directory name if no index specified
     let index_name = args.index.unwrap_or_else(|| {
@@ -71,7 +206,7 @@ pub async fn run(args: AskArgs, _verbose: bool) -> anyhow::Result<()> {
     });
 
     // Find index
-    let index_dir = find_index(&index_name)?;
+    let index_dir = resolve_index(&index_name).await?;
     let meta_path = index_dir.join("documents.leann.meta.json");
     let index_path = index_dir.join("documents.leann");
 
@@ -83,236 +218,460 @@ pub async fn run(args: AskArgs, _verbose: bool) -> anyhow::Result<()> {
         index_name, meta.passage_count
     );
 
+    let schema = args.schema.as_deref().map(OutputSchema::load).transpose()?;
+
+    // Resolve the prompt template: CLI, then index default, then config file
+    let config = Config::load();
+    let template = PromptTemplate::resolve(
+        args.prompt_template.as_deref(),
+        meta.prompt_templates.as_ref().and_then(|t| t.ask.as_deref()),
+        config.prompts.ask_path().as_deref(),
+        DEFAULT_ASK_TEMPLATE,
+    )?;
+
     // Create embedding provider
-    let embedding_mode = match meta.embedding_mode.as_str() {
-        "openai" => EmbeddingMode::OpenAI {
-            api_key: args.embedding_api_key.clone(),
-            base_url: args.embedding_api_base.clone(),
-        },
-        "ollama" => EmbeddingMode::Ollama {
-            host: args.embedding_host.clone(),
-        },
-        "gemini" => EmbeddingMode::Gemini {
-            api_key: std::env::var("GOOGLE_API_KEY").ok(),
-        },
-        _ => anyhow::bail!("Unknown embedding mode: {}", meta.embedding_mode),
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        ..Default::default()
     };
-
-    let embedding_provider = EmbeddingProvider::new(
-        meta.embedding_model.clone(),
-        embedding_mode,
-    ).await?;
+    let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &config.embedding).await?;
 
     // Load index
     let searcher = IndexSearcher::load(&index_path, &meta)?;
+    let graph = if args.graph {
+        Some(super::graph::load_graph(&index_name, &index_path)?)
+    } else {
+        None
+    };
 
-    // Create LLM provider
-    let llm_type = match args.llm.as_str() {
-        "ollama" => LlmType::Ollama {
-            host: args.host.clone(),
-        },
-        "openai" => LlmType::OpenAI {
-            api_key: args.api_key.clone(),
-            base_url: args.api_base.clone(),
-        },
-        "anthropic" => LlmType::Anthropic {
-            api_key: args.api_key.clone(),
-            base_url: args.api_base.clone(),
-        },
-        "simulated" => LlmType::Simulated,
-        _ => anyhow::bail!("Unknown LLM provider: {}", args.llm),
+    // Create LLM provider: flags, then config.toml
+    let provider = args.llm.clone().unwrap_or_else(|| config.llm.provider.clone());
+    let model = args.model.clone().unwrap_or_else(|| config.llm.model.clone());
+    let llm_overrides = LlmOverrides {
+        host: args.host.clone(),
+        api_key: args.api_key.clone(),
+        api_base: args.api_base.clone(),
+        #[cfg(feature = "local-llm")]
+        tokenizer: args.tokenizer.clone(),
     };
+    let llm_type = LlmType::from_name(&provider, &llm_overrides, &config.llm)?;
+
+    let llm = LlmProvider::new(model.clone(), llm_type)?
+        .with_options(args.generation.to_options())?
+        .with_redactor(args.generation.redactor()?);
 
-    let llm = LlmProvider::new(args.model.clone(), llm_type)?;
+    println!("Using {} with model {}", provider, model);
 
-    println!("Using {} with model {}", args.llm, args.model);
+    // Prompt tokens available before reserving room for the answer
+    let prompt_budget = context_window(&provider, &model).map(|window| {
+        let reserve = args.generation.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS) as usize;
+        window.saturating_sub(reserve)
+    });
+
+    // Search settings: flags, then the index's defaults, then config.toml
+    let search_config = meta.search_config(&config.search);
+    let hybrid = args.hybrid || (meta.default_hybrid() == Some(true) && !args.no_hybrid);
+
+    let asker = Asker {
+        embedding_provider: &embedding_provider,
+        searcher: &searcher,
+        llm: &llm,
+        template: &template,
+        top_k: args.top_k.unwrap_or(search_config.top_k),
+        complexity: args.complexity.unwrap_or(search_config.complexity),
+        hybrid_alpha: hybrid.then_some(search_config.hybrid_alpha),
+        context_budget: args.context_budget,
+        prompt_budget,
+        hyde_weight: args.hyde.then_some(args.hyde_weight),
+        query_template: meta.embedding_template(EmbeddingTask::Query),
+        document_template: meta.embedding_template(EmbeddingTask::Document),
+        multi_query: args.multi_query,
+        reformulation_cache: Mutex::new(ReformulationCache::load()),
+        query_log: config.analytics.log_queries.then(|| index_dir.clone()),
+        index_name: index_name.clone(),
+        graph,
+        graph_passages: args.graph_passages,
+        synonyms: if args.no_synonyms { None } else { Synonyms::load(&index_dir)? },
+        correct: !args.no_correct,
+        schema,
+    };
 
     if args.interactive {
-        run_interactive(
-            &embedding_provider,
-            &searcher,
-            &llm,
-            args.top_k,
-            args.complexity,
-        ).await
+        // Answers go to the question history, except those from the history itself
+        let history = if config.analytics.ask_history && index_name != HISTORY_INDEX {
+            Some(QuestionHistory::open(&meta, &overrides, &config.embedding).await?)
+        } else {
+            None
+        };
+        run_interactive(&asker, history).await
     } else {
         let query = args.query.ok_or_else(|| {
             anyhow::anyhow!("Query required in non-interactive mode. Use -i for interactive mode.")
         })?;
 
-        let answer = ask_question(
-            &query,
-            &embedding_provider,
-            &searcher,
-            &llm,
-            args.top_k,
-            args.complexity,
-        ).await?;
-
-        println!("\nAnswer:\n{}", answer);
+        asker.ask(&query, &[], "\nAnswer:\n").await?;
+        println!();
         Ok(())
     }
 }
 
-async fn ask_question(
-    query: &str,
-    embedding_provider: &EmbeddingProvider,
-    searcher: &IndexSearcher,
-    llm: &LlmProvider,
+/// Answers questions against one index
+struct Asker<'a> {
+    embedding_provider: &'a EmbeddingProvider,
+    searcher: &'a IndexSearcher,
+    llm: &'a LlmProvider,
+    template: &'a PromptTemplate,
     top_k: usize,
     complexity: usize,
-) -> anyhow::Result<String> {
-    // Compute query embedding
-    let query_embedding = embedding_provider.embed(&[query]).await?;
-    let query_embedding = &query_embedding[0];
-
-    // Search for relevant passages
-    let results = searcher.search(query_embedding, top_k, complexity)?;
+    /// Vector weight of hybrid search, if retrieving with it
+    hybrid_alpha: Option<f32>,
+    /// Explicit token budget for the retrieved passages
+    context_budget: Option<usize>,
+    /// Token budget for the whole prompt, used when no context budget is given
+    prompt_budget: Option<usize>,
+    /// HyDE blend weight, if retrieving with a hypothetical answer
+    hyde_weight: Option<f32>,
+    /// Embedding prompt template for questions
+    query_template: String,
+    /// Embedding prompt template the index's passages were embedded with (for HyDE)
+    document_template: String,
+    /// Number of LLM reformulations to also retrieve with, if any
+    multi_query: Option<usize>,
+    reformulation_cache: Mutex<ReformulationCache>,
+    /// Index directory to log questions for, if query logging is enabled
+    query_log: Option<PathBuf>,
+    /// Index name, for the usage ledger
+    index_name: String,
+    /// Knowledge graph whose neighbors add passages, with --graph
+    graph: Option<KnowledgeGraph>,
+    graph_passages: usize,
+    /// The index's synonyms, added to questions before retrieval
+    synonyms: Option<Synonyms>,
+    /// Fix typos in questions before retrieval
+    correct: bool,
+    /// Schema of JSON answers, with --schema
+    schema: Option<OutputSchema>,
+}
 
-    if results.is_empty() {
-        return Ok("No relevant passages found.".to_string());
+impl Asker<'_> {
+    /// Answer a question, printing `header`, the answer as it is generated,
+    /// and the tokens and cost it took
+    ///
+    /// Returns the full answer.
+    async fn ask(&self, query: &str, history: &[(String, String)], header: &str) -> anyhow::Result<String> {
+        let llm_before = self.llm.usage();
+        let embedded_before = self.embedding_provider.tokens_used();
+        let answer = self.answer(query, history, header).await?;
+        self.report_usage(&llm_before, embedded_before);
+        Ok(answer)
     }
 
-    // Build context from results
-    let context: String = results
-        .iter()
-        .enumerate()
-        .map(|(i, r)| format!("[{}] {}", i + 1, r.text))
-        .collect::<Vec<_>>()
-        .join("\n\n");
+    /// Print and record the tokens used since the given counter snapshots
+    fn report_usage(&self, llm_before: &TokenUsage, embedded_before: usize) {
+        let generation = self.llm.usage().since(llm_before);
+        let embedded = self.embedding_provider.tokens_used().saturating_sub(embedded_before);
+        let generation_cost = self.llm.cost(&generation);
+        let embedding_cost = self.embedding_provider.cost(embedded);
+        let total_cost = generation_cost.zip(embedding_cost).map(|(g, e)| g + e);
+
+        print!(
+            "\n\nUsage: ~{} input / ~{} output tokens, {}",
+            generation.input_tokens,
+            generation.output_tokens,
+            format_cost(total_cost)
+        );
+        let _ = std::io::stdout().flush();
+
+        record_usage(&[
+            UsageRecord::new(
+                "ask",
+                Some(&self.index_name),
+                UsageKind::Generation,
+                self.llm.provider_name(),
+                self.llm.model_name(),
+            )
+            .with_tokens(generation.input_tokens, generation.output_tokens, generation_cost),
+            UsageRecord::new(
+                "ask",
+                Some(&self.index_name),
+                UsageKind::Embedding,
+                self.embedding_provider.mode_name(),
+                self.embedding_provider.model_name(),
+            )
+            .with_tokens(embedded, 0, embedding_cost),
+        ]);
+    }
 
-    // Build prompt
-    let prompt = format!(
-        r#"Here is some retrieved context that might help answer your question:
+    /// Retrieve passages and generate the answer, streaming it after `header`
+    async fn answer(&self, query: &str, history: &[(String, String)], header: &str) -> anyhow::Result<String> {
+        let start = std::time::Instant::now();
+        let reformulations = match self.multi_query {
+            Some(n) => reformulate_query(self.llm, query, n, &self.reformulation_cache).await?,
+            None => Vec::new(),
+        };
+
+        // Compute query embeddings (the question with typos fixed and any
+        // synonyms added, then any reformulations)
+        let corrected = if self.correct {
+            self.searcher
+                .correct_query(query, |word| self.synonyms.as_ref().is_some_and(|s| s.defines(word)))?
+        } else {
+            None
+        };
+        if let Some(corrected) = &corrected {
+            info!("Retrieving for '{}' instead of '{}' (--no-correct retrieves as typed)", corrected, query);
+        }
+        let typed = corrected.as_deref().unwrap_or(query);
+        let expanded = match &self.synonyms {
+            Some(synonyms) => synonyms.expand(typed),
+            None => typed.to_string(),
+        };
+        if expanded != typed {
+            info!("Added synonyms: '{}' → '{}'", typed, expanded);
+        }
+        let mut queries = vec![expanded.as_str()];
+        queries.extend(reformulations.iter().map(|q| q.as_str()));
+        let query_embeddings = self
+            .embedding_provider
+            .embed_for_task(&queries, EmbeddingTask::Query, &self.query_template)
+            .await?;
+
+        let mut opts = SearchOptions::new(self.top_k, self.complexity);
+        if let Some(alpha) = self.hybrid_alpha {
+            opts = opts.with_hybrid(expanded.clone(), alpha);
+        }
+        if let Some(weight) = self.hyde_weight {
+            let embedding = super::search::hyde_embedding(
+                self.llm,
+                self.embedding_provider,
+                &self.document_template,
+                query,
+            )
+            .await?;
+            opts = opts.with_hyde(embedding, weight);
+        }
 
-{}
+        // Search for relevant passages
+        let mut results = if queries.len() == 1 {
+            self.searcher.search_with_options(&query_embeddings[0], &opts)?
+        } else {
+            let fused: Vec<(&[f32], &str)> = query_embeddings
+                .iter()
+                .map(|e| e.as_slice())
+                .zip(queries.iter().copied())
+                .collect();
+            self.searcher.search_fused(&fused, &opts)?
+        };
+
+        let logged = self.query_log.as_ref().and_then(|index_dir| {
+            let entry = QueryLogEntry::new(
+                "ask",
+                query,
+                None,
+                start.elapsed().as_millis() as u64,
+                results.iter().map(|r| r.id.clone()).collect(),
+            );
+            match log_query(index_dir, &entry) {
+                Ok(()) => Some((index_dir, entry.id)),
+                Err(e) => {
+                    tracing::warn!("Failed to log query: {}", e);
+                    None
+                }
+            }
+        });
 
-Question: {}
+        if let Some(graph) = &self.graph {
+            let added = super::graph::neighbor_passages(graph, self.searcher, query, &results, self.graph_passages);
+            if !added.is_empty() {
+                info!("Added {} passages from the knowledge graph", added.len());
+                results.extend(added);
+            }
+        }
 
-Please provide the best answer you can based on this context and your knowledge."#,
-        context, query
-    );
+        if results.is_empty() {
+            let answer = "No relevant passages found.".to_string();
+            print!("{}{}", header, answer);
+            return Ok(answer);
+        }
 
-    // Generate answer
-    llm.generate(&prompt).await
+        let mut vars = HashMap::from([
+            ("question", query.to_string()),
+            ("history", format_history(history)),
+        ]);
+
+        // Fit passages into what the rest of the prompt leaves over
+        let budget = self.context_budget.or_else(|| {
+            let overhead = count_tokens(self.llm.model_name(), &self.template.render(&vars));
+            self.prompt_budget.map(|b| b.saturating_sub(overhead))
+        });
+        let packed = pack_results(&results, budget, self.llm.model_name());
+        println!("Context: {}", packed.summary());
+
+        // Build prompt
+        vars.insert("context", packed.text);
+        let prompt = self.template.render(&vars);
+
+        // Generate answer
+        let answer = match &self.schema {
+            Some(schema) => {
+                let value = generate_structured(self.llm, &prompt, schema).await?;
+                let answer = serde_json::to_string_pretty(&value)?;
+                print!("{}{}", header, answer);
+                answer
+            }
+            None => {
+                print!("{}", header);
+                self.llm
+                    .generate_stream(&prompt, &mut |chunk| {
+                        print!("{}", chunk);
+                        let _ = std::io::stdout().flush();
+                    })
+                    .await?
+            }
+        };
+
+        // Passages the answer cites count as used
+        if let Some((index_dir, query_id)) = logged {
+            let used = cited_ids(&answer, &results[..packed.included]);
+            if !used.is_empty() {
+                if let Err(e) = log_used(index_dir, &query_id, used) {
+                    tracing::warn!("Failed to log used passages: {}", e);
+                }
+            }
+        }
+        Ok(answer)
+    }
 }
 
-async fn run_interactive(
-    embedding_provider: &EmbeddingProvider,
-    searcher: &IndexSearcher,
-    llm: &LlmProvider,
-    top_k: usize,
-    complexity: usize,
-) -> anyhow::Result<()> {
-    use rustyline::error::ReadlineError;
-    use rustyline::DefaultEditor;
-
-    println!("\n🔍 LEANN Interactive Mode");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!("Commands:");
-    println!("  /help     - Show this help message");
-    println!("  /clear    - Clear conversation history");
-    println!("  /history  - Show command history");
-    println!("  /quit     - Exit interactive mode");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
-
-    // Setup readline with history
-    let mut rl = DefaultEditor::new()?;
-
-    // Load history from file if it exists
-    let history_path = dirs::home_dir()
-        .map(|h| h.join(".leann").join("history.txt"))
-        .unwrap_or_else(|| std::path::PathBuf::from(".leann_history"));
-
-    if history_path.exists() {
-        let _ = rl.load_history(&history_path);
+/// Ids of the passages an answer cites as `[n]`, in order of first citation
+pub(crate) fn cited_ids(answer: &str, results: &[SearchResult]) -> Vec<String> {
+    static CITATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[(\d+)\]").unwrap());
+    let mut ids: Vec<String> = Vec::new();
+    for capture in CITATION.captures_iter(answer) {
+        let Ok(n) = capture[1].parse::<usize>() else {
+            continue;
+        };
+        if let Some(result) = n.checked_sub(1).and_then(|i| results.get(i)) {
+            if !ids.contains(&result.id) {
+                ids.push(result.id.clone());
+            }
+        }
     }
+    ids
+}
 
-    let mut conversation_history: Vec<(String, String)> = Vec::new();
+/// Retrieved passages numbered for citation and packed into `budget` tokens,
+/// for the `{{context}}` template variable
+pub(crate) fn pack_results(results: &[SearchResult], budget: Option<usize>, model_name: &str) -> PackedContext {
+    let passages: Vec<&str> = results.iter().map(|r| r.text.as_str()).collect();
+    pack_context(&passages, budget, model_name)
+}
 
-    loop {
-        let readline = rl.readline("You: ");
+/// Earlier turns of an interactive session, for the `{{history}}` template variable
+pub(super) fn format_history(history: &[(String, String)]) -> String {
+    history
+        .iter()
+        .map(|(q, a)| format!("User: {}\nAssistant: {}", q, a))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
 
-        match readline {
-            Ok(line) => {
-                let input = line.trim();
+/// Commands of the interactive mode
+const COMMANDS: &[(&str, &str)] = &[
+    ("/help", "Show this help message"),
+    ("/clear", "Clear conversation history"),
+    ("/history", "Show command history"),
+    ("/quit", "Exit interactive mode"),
+];
 
-                if input.is_empty() {
-                    continue;
-                }
+async fn run_interactive(asker: &Asker<'_>, mut history: Option<QuestionHistory>) -> anyhow::Result<()> {
+    let mut repl = Repl::start("LEANN Interactive Mode", COMMANDS)?;
+    let mut conversation_history: Vec<(String, String)> = Vec::new();
 
-                // Add to readline history
-                let _ = rl.add_history_entry(input);
-
-                // Handle commands
-                if input.starts_with('/') {
-                    match input {
-                        "/help" | "/h" | "/?" => {
-                            println!("\nCommands:");
-                            println!("  /help     - Show this help message");
-                            println!("  /clear    - Clear conversation history");
-                            println!("  /history  - Show command history");
-                            println!("  /quit     - Exit interactive mode\n");
-                            continue;
-                        }
-                        "/clear" | "/c" => {
-                            conversation_history.clear();
-                            println!("\nConversation history cleared.\n");
-                            continue;
-                        }
-                        "/history" | "/hist" => {
-                            println!("\nConversation history:");
-                            if conversation_history.is_empty() {
-                                println!("  (empty)");
-                            } else {
-                                for (i, (q, _)) in conversation_history.iter().enumerate() {
-                                    println!("  {}. {}", i + 1, q);
-                                }
-                            }
-                            println!();
-                            continue;
-                        }
-                        "/quit" | "/q" | "/exit" => {
-                            println!("\nGoodbye!");
-                            break;
-                        }
-                        _ => {
-                            println!("\nUnknown command: {}. Type /help for available commands.\n", input);
-                            continue;
+    while let Some(input) = repl.read_line("You: ") {
+        // Handle commands
+        if input.starts_with('/') {
+            match input.as_str() {
+                "/help" | "/h" | "/?" => {
+                    println!("\nCommands:");
+                    print_commands(COMMANDS);
+                    println!();
+                }
+                "/clear" | "/c" => {
+                    conversation_history.clear();
+                    println!("\nConversation history cleared.\n");
+                }
+                "/history" | "/hist" => {
+                    println!("\nConversation history:");
+                    if conversation_history.is_empty() {
+                        println!("  (empty)");
+                    } else {
+                        for (i, (q, _)) in conversation_history.iter().enumerate() {
+                            println!("  {}. {}", i + 1, q);
                         }
                     }
+                    println!();
                 }
+                "/quit" | "/q" | "/exit" => {
+                    println!("\nGoodbye!");
+                    break;
+                }
+                _ => println!("\nUnknown command: {}. Type /help for available commands.\n", input),
+            }
+            continue;
+        }
 
-                // Regular question
-                match ask_question(input, embedding_provider, searcher, llm, top_k, complexity).await {
-                    Ok(answer) => {
-                        println!("\nLEANN: {}\n", answer);
-                        conversation_history.push((input.to_string(), answer));
+        // Regular question
+        match asker.ask(&input, &conversation_history, "\nLEANN: ").await {
+            Ok(answer) => {
+                println!("\n");
+                if let Some(history) = history.as_mut() {
+                    if let Err(e) = history.record(&asker.index_name, &input, &answer).await {
+                        tracing::warn!("Couldn't add the answer to the question history: {}", e);
                     }
-                    Err(e) => eprintln!("\nError: {}\n", e),
                 }
+                conversation_history.push((input, answer));
             }
-            Err(ReadlineError::Interrupted) => {
-                println!("\nInterrupted. Type /quit to exit.");
-                continue;
-            }
-            Err(ReadlineError::Eof) => {
-                println!("\nGoodbye!");
-                break;
-            }
-            Err(err) => {
-                eprintln!("Error: {:?}", err);
-                break;
-            }
+            Err(e) => eprintln!("\nError: {}\n", e),
         }
     }
 
-    // Save history
-    if let Some(parent) = history_path.parent() {
-        let _ = std::fs::create_dir_all(parent);
-    }
-    let _ = rl.save_history(&history_path);
-
     Ok(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_history() {
+        assert_eq!(format_history(&[]), "");
+        let history = vec![
+            ("What is X?".to_string(), "X is a thing.".to_string()),
+            ("And Y?".to_string(), "Y too.".to_string()),
+        ];
+        assert_eq!(
+            format_history(&history),
+            "User: What is X?\nAssistant: X is a thing.\n\nUser: And Y?\nAssistant: Y too."
+        );
+    }
+
+    #[test]
+    fn test_cited_ids() {
+        let results: Vec<SearchResult> = ["a", "b", "c"]
+            .iter()
+            .map(|id| SearchResult {
+                id: id.to_string(),
+                score: 1.0,
+                text: String::new(),
+                metadata: serde_json::Value::Null,
+            })
+            .collect();
+        let answer = "Use tokens [3], refreshed hourly [1][3]. See also [7] and [0].";
+        assert_eq!(cited_ids(answer, &results), vec!["c".to_string(), "a".to_string()]);
+        assert!(cited_ids("No citations here.", &results).is_empty());
+    }
+}
@@ -3,14 +3,34 @@
 use std::path::PathBuf;
 
 use clap::Args;
-use indicatif::{ProgressBar, ProgressStyle};
 use tracing::info;
 
-use crate::backend::BackendType;
-use crate::chunker::{Chunk, Chunker, ChunkingStrategy, SmartChunker};
+use crate::backend::{BuildParams, DiskAnnParams, ShardPlan};
+use crate::cancel::Cancellation;
+use crate::chunker::{
+    assign_stable_ids, detect_text_language, is_code_file, is_excluded_dir, Chunk, ChunkLengthLimit, ChunkLengthPolicy,
+    Chunker, ChunkingStrategy, DirOverrides, EnrichmentPipeline, LengthStats, SecretScanner, SecretStats, SecretsPolicy,
+    SmartChunker, StructuredChunker,
+};
 use crate::config::Config;
-use crate::embedding::{get_model_config, EmbeddingMode, EmbeddingProvider};
-use crate::index::{IndexMeta, StreamingIndexBuilder};
+use crate::embedding::{
+    document_template, get_model_config, max_input_tokens, multilingual_alternative, template_tokens, DocumentInstructions,
+    EmbeddingMode, EmbeddingProvider, EmbeddingTask, MultiVectorEmbedding, PostProcessing, ProviderOverrides, SparseEmbedding,
+};
+use crate::globs::PathFilter;
+use crate::index::{
+    check_index_name, commit_staged_build, has_stemmer, parse_bm25_fields, staging_dir, Bm25Analyzer, Bm25Tokenizer,
+    BuildProvenance, CheckpointHeader, ChunkingInfo, EmbeddingCheckpoint, FileManifestEntry, IndexMeta, MultiVectorInfo,
+    PassageFormat, PromptTemplates, SearchDefaults, SparseModelInfo, StreamingIndexBuilder, META_VERSION, NAMESPACE_FIELD,
+};
+use crate::llm::{LlmOverrides, LlmProvider, LlmType, PromptTemplate, DEFAULT_MAX_TOKENS};
+use crate::progress::{ProgressEvent, ProgressRecord, ProgressReporter};
+use crate::transcribe::{chunk_segments, Transcriber, AUDIO_TYPES, MAX_AUDIO_FILE_SIZE_KB};
+use crate::usage::{format_cost, record_usage, UsageKind, UsageRecord};
+
+use super::graph::GraphExtractor;
+use super::raptor::RaptorTree;
+use super::summarize::Summarizer;
 
 #[derive(Args)]
 pub struct BuildArgs {
@@ -18,12 +38,35 @@ pub struct BuildArgs {
     #[arg()]
     pub index_name: Option<String>,
 
-    /// Document directories and/or files
+    /// Document directories and/or files (and .zip/.tar/.tar.gz archives with
+    /// the archives feature)
     #[arg(long, default_value = ".")]
     pub docs: Vec<PathBuf>,
 
-    /// Backend to use
-    #[arg(long, default_value = "hnsw", value_parser = ["hnsw", "diskann"])]
+    /// Kind of source under --docs: "files" (documents and code), "audio"
+    /// (.mp3, .wav, .m4a, ... recordings transcribed with Whisper), "mail"
+    /// (mbox, Maildir, and .eml messages), or "obsidian" (an Obsidian vault or
+    /// Notion markdown export, chunked by heading with links resolved)
+    #[arg(long, default_value = "files", value_parser = ["files", "audio", "mail", "obsidian"])]
+    pub source: String,
+
+    /// Whisper model for --source audio
+    #[arg(long, default_value = "whisper-1")]
+    pub transcription_model: String,
+
+    /// OpenAI-compatible transcription API base URL for --source audio, e.g. a local
+    /// faster-whisper-server (default: LEANN_TRANSCRIPTION_API_BASE, then OpenAI)
+    #[arg(long)]
+    pub transcription_api_base: Option<String>,
+
+    /// Spoken language of the recordings (ISO-639-1, e.g. "en"; default: detected)
+    #[arg(long)]
+    pub transcription_language: Option<String>,
+
+    /// Backend to use ("diskann" searches a memory-mapped graph from disk and
+    /// "ivfpq" keeps compressed codes in memory and reranks with full vectors
+    /// from disk, both for very large indexes)
+    #[arg(long, default_value = "hnsw", value_parser = clap::builder::PossibleValuesParser::new(crate::backend::names()))]
     pub backend_name: String,
 
     /// Embedding model name
@@ -32,12 +75,12 @@ pub struct BuildArgs {
 
     /// Embedding mode
     #[cfg(feature = "local-embeddings")]
-    #[arg(long, default_value = "openai", value_parser = ["openai", "ollama", "gemini", "local"])]
+    #[arg(long, default_value = "openai", value_parser = ["openai", "ollama", "gemini", "jina", "mock", "local"])]
     pub embedding_mode: String,
 
     /// Embedding mode
     #[cfg(not(feature = "local-embeddings"))]
-    #[arg(long, default_value = "openai", value_parser = ["openai", "ollama", "gemini"])]
+    #[arg(long, default_value = "openai", value_parser = ["openai", "ollama", "gemini", "jina", "mock"])]
     pub embedding_mode: String,
 
     /// Ollama host for embeddings
@@ -56,17 +99,67 @@ pub struct BuildArgs {
     #[arg(long, env = "GOOGLE_API_KEY")]
     pub google_api_key: Option<String>,
 
-    /// Prompt template prefix for document embeddings
-    /// Used for asymmetric models (e.g., "passage: " for E5, BGE models)
+    /// Prompt template for document embeddings: a prefix, or an instruction with
+    /// a `{text}` placeholder. Used for asymmetric models (e.g., "passage: " for E5)
     #[arg(long)]
     pub embedding_prompt_template: Option<String>,
 
+    /// Prompt template for query embeddings, stored as the index default for
+    /// search (a prefix or a `{text}` template; defaults to the model's query prefix)
+    #[arg(long)]
+    pub query_prompt_template: Option<String>,
+
+    /// L2-normalize embeddings (default: only for models whose vectors are meant
+    /// to be unit length, e.g. nomic-embed-text, E5, BGE)
+    #[arg(long, conflicts_with = "no_normalize")]
+    pub normalize: bool,
+
+    /// Don't L2-normalize embeddings
+    #[arg(long)]
+    pub no_normalize: bool,
+
+    /// Keep only the first N embedding dimensions (Matryoshka models such as
+    /// text-embedding-3 and nomic-embed-text v1.5)
+    #[arg(long)]
+    pub embedding_dims: Option<usize>,
+
+    /// Round embeddings to a lower precision (f32, f16, bf16)
+    #[arg(long, default_value = "f32", value_parser = ["f32", "f16", "bf16"])]
+    pub embedding_precision: String,
+
+    /// Also store learned sparse vectors (SPLADE, BM42) from this model, served by a
+    /// text-embeddings-inference server, for `leann search --sparse`
+    #[arg(long)]
+    pub sparse_model: Option<String>,
+
+    /// text-embeddings-inference server for --sparse-model (default: http://localhost:8080)
+    #[arg(long, env = "LEANN_SPARSE_HOST")]
+    pub sparse_host: Option<String>,
+
+    /// Also store per-token (ColBERT-style) vectors for late-interaction
+    /// reranking with `leann search --multi-vector` (experimental)
+    #[arg(long)]
+    pub multi_vector: bool,
+
+    /// Multi-vector model for --multi-vector
+    #[arg(long, default_value = "jina-colbert-v2")]
+    pub multi_vector_model: String,
+
+    /// Dimensions of each token vector for --multi-vector
+    #[arg(long, default_value = "128")]
+    pub multi_vector_dims: usize,
+
+    /// Jina AI API key (for --multi-vector and the jina embedding mode)
+    #[arg(long, env = "JINA_API_KEY")]
+    pub jina_api_key: Option<String>,
+
     /// Local model path (for local embedding mode)
     #[cfg(feature = "local-embeddings")]
     #[arg(long)]
     pub embedding_model_path: Option<String>,
 
-    /// Force rebuild existing index
+    /// Force rebuild existing index (the current build is kept until the new
+    /// one completes, and can be restored with `leann rollback`)
     #[arg(short, long)]
     pub force: bool,
 
@@ -74,7 +167,18 @@ pub struct BuildArgs {
     #[arg(long)]
     pub recompute: bool,
 
-    /// Graph degree for HNSW
+    /// Don't persist passage text (only metadata, hashes, and vectors);
+    /// text is re-read from source files at query time when available
+    #[arg(long)]
+    pub no_store_text: bool,
+
+    /// Passage storage: jsonl (one JSON line per passage) or compact
+    /// (zstd-compressed records with a binary offset table, smaller and
+    /// faster to open for very large indexes)
+    #[arg(long, default_value = "jsonl", value_parser = ["jsonl", "compact"])]
+    pub passage_store: String,
+
+    /// Graph degree for HNSW and DiskANN
     #[arg(long, default_value = "32")]
     pub graph_degree: usize,
 
@@ -82,7 +186,25 @@ pub struct BuildArgs {
     #[arg(long, default_value = "64")]
     pub complexity: usize,
 
-    /// Document chunk size in tokens
+    /// DiskANN pruning factor; larger values keep more long-range links
+    /// (denser graph, better recall, bigger file)
+    #[arg(long, default_value = "1.2")]
+    pub prune_alpha: f32,
+
+    /// DiskANN nodes expanded per search step (more reads per step, fewer steps)
+    #[arg(long, default_value = "4")]
+    pub beam_width: usize,
+
+    /// Split the HNSW graph into this many shards, searched in parallel
+    #[arg(long, conflicts_with = "shard_size")]
+    pub shards: Option<usize>,
+
+    /// Split the HNSW graph into shards of at most this many passages, each built
+    /// as soon as it fills (bounds memory for corpora too large for one graph)
+    #[arg(long)]
+    pub shard_size: Option<usize>,
+
+    /// Document chunk size in tokens (estimated at ~4 characters each)
     #[arg(long, default_value = "256")]
     pub doc_chunk_size: usize,
 
@@ -99,10 +221,25 @@ pub struct BuildArgs {
     #[arg(long)]
     pub exclude_types: Option<String>,
 
+    /// Only index files matching this glob, relative to the --docs directory
+    /// (repeatable, e.g. --include 'src/**/*.rs'; replaces `include` in config.toml)
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Skip files matching this glob (repeatable, e.g. --exclude '**/generated/**';
+    /// added to `exclude` in config.toml)
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
     /// Include hidden files
     #[arg(long)]
     pub include_hidden: bool,
 
+    /// Follow symbolic links to directories and files (each real directory
+    /// and file is indexed once, and link cycles are skipped)
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
     /// Chunking strategy: simple, ast, or auto (default: simple)
     /// - simple: character-based chunking with word boundary awareness (recommended)
     /// - ast: AST-aware chunking for code files (functions, classes, etc.) - experimental
@@ -110,6 +247,58 @@ pub struct BuildArgs {
     #[arg(long, default_value = "simple", value_parser = ["simple", "ast", "auto"])]
     pub chunking_strategy: String,
 
+    /// What to do with chunks longer than the embedding model accepts: error, truncate, or split
+    #[arg(long, default_value = "split", value_parser = ["error", "truncate", "split"])]
+    pub chunk_length_policy: String,
+
+    /// Token limit for the chunk length policy (default: the model's input limit,
+    /// if known, less the tokens of the document prompt template)
+    #[arg(long)]
+    pub max_chunk_tokens: Option<usize>,
+
+    /// Reduce --doc-chunk-size (and the overlap) to the chunk token limit when
+    /// it is larger, instead of warning and leaving long chunks to the length policy
+    #[arg(long)]
+    pub auto_chunk_size: bool,
+
+    /// What to do with chunks containing API keys, tokens, or private keys:
+    /// skip (leave them out), redact (replace each secret with
+    /// `[REDACTED:kind]`), or allow (index them unchanged, flagged with `secret_kinds`)
+    #[arg(long, default_value = "redact", value_parser = ["skip", "redact", "allow"])]
+    pub secrets: String,
+
+    /// Metadata enrichers to apply per chunk (comma-separated: size, lines, language, lang, flags, code, git; or "all"/"none").
+    /// "all" leaves out git, which runs `git log` per file; add it with "all,git"
+    #[arg(long, default_value = "all")]
+    pub enrichers: String,
+
+    /// Namespace for the indexed chunks (the `namespace` metadata field), so
+    /// searches can be restricted to it; a `.leann.toml` may set its own
+    #[arg(long)]
+    pub namespace: Option<String>,
+
+    /// BM25 tokenizer for hybrid search: words, ngram (character bigrams for
+    /// Chinese, Japanese, and Korean), code (also splits snake_case and camelCase
+    /// identifiers), or auto (ngram when CJK text dominates, code when most
+    /// chunks are source code)
+    #[arg(long, default_value = "auto", value_parser = ["auto", "words", "ngram", "code"])]
+    pub bm25_tokenizer: String,
+
+    /// BM25 stemmer language (e.g. "english", "german"), "none", or "auto" (the
+    /// most common detected language, with the words tokenizer)
+    #[arg(long, default_value = "auto")]
+    pub bm25_stemmer: String,
+
+    /// Keep English stopwords ("the", "and", ...) in BM25 terms
+    #[arg(long)]
+    pub no_bm25_stopwords: bool,
+
+    /// Metadata fields BM25 scores next to the text, as field:weight pairs
+    /// (filename, symbols), or "none"; a weight of 3 makes a match in the
+    /// field count like three matches in the text
+    #[arg(long, default_value = "filename:2,symbols:3")]
+    pub bm25_fields: String,
+
     /// Batch size for embedding API calls (default: provider-specific)
     #[arg(long)]
     pub embedding_batch_size: Option<usize>,
@@ -121,39 +310,227 @@ pub struct BuildArgs {
     /// Skip files larger than this size in KB (default: 1024 = 1MB)
     #[arg(long, default_value = "1024")]
     pub max_file_size_kb: usize,
+
+    /// Stop collecting files once their total size reaches this many MB
+    #[arg(long)]
+    pub max_total_size_mb: Option<usize>,
+
+    /// Size limit in KB for data files (JSON, YAML, TOML); default: same as --max-file-size-kb
+    #[arg(long)]
+    pub data_max_file_size_kb: Option<usize>,
+
+    /// Prompt template file stored as this index's default for `leann ask`
+    #[arg(long)]
+    pub ask_template: Option<PathBuf>,
+
+    /// Prompt template file stored as this index's default for `leann react`
+    #[arg(long)]
+    pub react_template: Option<PathBuf>,
+
+    /// Results per search stored as this index's default (over `[search] top_k`)
+    #[arg(long)]
+    pub default_top_k: Option<usize>,
+
+    /// Search complexity stored as this index's default (over `[search] complexity`)
+    #[arg(long)]
+    pub default_complexity: Option<usize>,
+
+    /// Hybrid search stored as this index's default: true for every query,
+    /// false for none (default: automatic for short queries)
+    #[arg(long)]
+    pub default_hybrid: Option<bool>,
+
+    /// Hybrid vector weight stored as this index's default (over `[search] hybrid_alpha`)
+    #[arg(long)]
+    pub default_hybrid_alpha: Option<f32>,
+
+    /// Also index a tree of LLM summaries (RAPTOR): chunks are clustered by
+    /// embedding and each cluster summarized, then the summaries clustered and
+    /// summarized again, so broad questions can match a summary while
+    /// specific ones match chunks
+    #[arg(long)]
+    pub raptor: bool,
+
+    /// LLM provider writing the --raptor summaries (ollama, openai, anthropic,
+    /// gemini; default: `[llm] provider` in config.toml)
+    #[arg(long, requires = "raptor")]
+    pub raptor_llm: Option<String>,
+
+    /// LLM model for --raptor (default: `[llm] model` in config.toml)
+    #[arg(long, requires = "raptor")]
+    pub raptor_model: Option<String>,
+
+    /// Average number of passages per --raptor cluster
+    #[arg(long, default_value = "8")]
+    pub raptor_cluster_size: usize,
+
+    /// Most --raptor summary levels above the chunks
+    #[arg(long, default_value = "3")]
+    pub raptor_levels: usize,
+
+    /// Also extract a knowledge graph: code (functions linked to the
+    /// functions they call) or llm (code, plus LLM-extracted relations between
+    /// entities in other chunks); see `leann graph query` and `leann ask --graph`
+    #[arg(long, value_parser = ["code", "llm"])]
+    pub graph: Option<String>,
+
+    /// LLM provider for --graph llm (default: `[llm] provider` in config.toml)
+    #[arg(long, requires = "graph")]
+    pub graph_llm: Option<String>,
+
+    /// LLM model for --graph llm (default: `[llm] model` in config.toml)
+    #[arg(long, requires = "graph")]
+    pub graph_model: Option<String>,
+
+    /// Also index images (.png, .jpg, .jpeg, .webp) with a multimodal model
+    /// (e.g. --embedding-mode jina --embedding-model jina-clip-v2)
+    #[cfg(feature = "images")]
+    #[arg(long)]
+    pub images: bool,
+
+    /// Progress output: bar (console progress bars) or json (one JSON event
+    /// per line on stdout: scanned files, embedded batches with latency and
+    /// ETA, and the finished or failed build)
+    #[arg(long, default_value = "bar", value_parser = ["bar", "json"])]
+    pub progress: String,
+
+    /// Also append JSON progress events to this file
+    #[arg(long)]
+    pub progress_file: Option<PathBuf>,
+
+    /// Print the estimated embedding tokens and cost, then stop without building
+    #[arg(long)]
+    pub estimate_only: bool,
+
+    /// Receives progress events when the build runs inside `leann serve`
+    #[arg(skip)]
+    pub progress_events: Option<tokio::sync::mpsc::UnboundedSender<ProgressRecord>>,
+
+    /// Stops the build when it runs inside `leann serve` (default: Ctrl-C)
+    #[arg(skip)]
+    pub cancel: Option<Cancellation>,
 }
 
+/// `leann build` arguments parsed on their own, for builds requested over the API
+#[cfg(feature = "server")]
+#[derive(clap::Parser)]
+#[command(name = "leann build", no_binary_name = true)]
+struct BuildCommand {
+    #[command(flatten)]
+    args: BuildArgs,
+}
+
+/// Parse `leann build` arguments (e.g. `["my-docs", "--docs", "./docs"]`)
+#[cfg(feature = "server")]
+pub fn parse_args(args: &[String]) -> anyhow::Result<BuildArgs> {
+    use clap::Parser;
+
+    Ok(BuildCommand::try_parse_from(args)?.args)
+}
+
+/// Image file types indexed with --images
+#[cfg(feature = "images")]
+const IMAGE_TYPES: &[&str] = &[".png", ".jpg", ".jpeg", ".webp"];
+
+/// Images embedded per request
+#[cfg(feature = "images")]
+const IMAGE_BATCH_SIZE: usize = 16;
+
+/// Read and validate `--ask-template` / `--react-template` for storing in the index metadata
+fn read_prompt_templates(
+    ask: Option<&PathBuf>,
+    react: Option<&PathBuf>,
+) -> anyhow::Result<Option<PromptTemplates>> {
+    let read = |path: Option<&PathBuf>| -> anyhow::Result<Option<String>> {
+        let Some(path) = path else {
+            return Ok(None);
+        };
+        PromptTemplate::from_file(path)?.check_variables()?;
+        Ok(Some(std::fs::read_to_string(path)?))
+    };
+
+    let templates = PromptTemplates {
+        ask: read(ask)?,
+        react: read(react)?,
+    };
+    Ok((templates.ask.is_some() || templates.react.is_some()).then_some(templates))
+}
+
+#[tracing::instrument(name = "build", skip_all, fields(index = args.index_name.as_deref()))]
 pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
+    let progress = ProgressReporter::new(args.progress == "json", args.progress_file.as_deref())?
+        .with_channel(args.progress_events.clone());
+    let result = build_index(args, &progress).await;
+    match &result {
+        Err(e) if crate::error::is_cancelled(e) => progress.emit(ProgressEvent::Cancelled { message: e.to_string() }),
+        Err(e) => progress.emit(ProgressEvent::Failed { error: e.to_string() }),
+        Ok(()) => {}
+    }
+    result
+}
+
+async fn build_index(mut args: BuildArgs, reporter: &ProgressReporter) -> anyhow::Result<()> {
     // Load config file for defaults
     let config = Config::load();
 
-    let index_name = args.index_name.unwrap_or_else(|| {
+    // `leann build --source obsidian ~/vault`: a vault directory given in place
+    // of the index name is indexed, and names the index
+    if args.source == "obsidian" && args.docs == [PathBuf::from(".")] {
+        if let Some(vault) = args.index_name.as_ref().map(PathBuf::from).filter(|p| p.is_dir()) {
+            args.index_name = vault
+                .canonicalize()
+                .ok()
+                .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()));
+            args.docs = vec![vault];
+        }
+    }
+
+    let index_name = args.index_name.take().unwrap_or_else(|| {
         std::env::current_dir()
             .ok()
             .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
             .unwrap_or_else(|| "index".to_string())
     });
 
+    check_index_name(&index_name)?;
     info!("Building index '{}'", index_name);
+    reporter.emit(ProgressEvent::Started { index: index_name.clone() });
+
+    // Validate prompt templates and BM25 settings before spending time on embeddings
+    let prompt_templates = read_prompt_templates(args.ask_template.as_ref(), args.react_template.as_ref())?;
+    if !matches!(args.bm25_stemmer.as_str(), "auto" | "none") {
+        Bm25Analyzer::default().with_stemmer(&args.bm25_stemmer)?;
+    }
+    let bm25_fields = parse_bm25_fields(&args.bm25_fields)?;
 
     // Determine index directory
     let index_dir = PathBuf::from(".leann").join("indexes").join(&index_name);
 
-    if index_dir.exists() && !args.force {
+    if index_dir.exists() && !args.force && !args.estimate_only {
         anyhow::bail!(
             "Index '{}' already exists. Use --force to rebuild.",
             index_name
         );
     }
 
-    // Create index directory
-    std::fs::create_dir_all(&index_dir)?;
-
-    // Parse backend type
-    let backend_type = match args.backend_name.as_str() {
-        "hnsw" => BackendType::Hnsw,
-        "diskann" => BackendType::DiskAnn,
-        _ => anyhow::bail!("Unknown backend: {}", args.backend_name),
+    // Glob patterns: --include replaces the config file's, --exclude adds to them
+    let include = if args.include.is_empty() {
+        config.build.include.clone().unwrap_or_default()
+    } else {
+        args.include.clone()
+    };
+    let mut exclude = config.build.exclude.clone().unwrap_or_default();
+    exclude.extend(args.exclude.iter().cloned());
+    let path_filter = PathFilter::new(&include, &exclude)?;
+
+    let backend = crate::backend::resolve(&args.backend_name)?;
+    let build_params = BuildParams {
+        graph_degree: args.graph_degree,
+        complexity: args.complexity,
+        diskann: DiskAnnParams {
+            alpha: args.prune_alpha,
+            beam_width: args.beam_width,
+        },
     };
 
     // Use config defaults if CLI args not provided
@@ -165,30 +542,47 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
     };
 
     // Use config model if CLI uses default
-    let embedding_model = if args.embedding_model == "text-embedding-3-small" {
+    let model_is_default = args.embedding_model == "text-embedding-3-small";
+    let embedding_model = if model_is_default {
         config.embedding.model.clone()
     } else {
         args.embedding_model.clone()
     };
 
-    // Parse embedding mode, using config for host/base_url if not specified in CLI
-    let embedding_mode = match embedding_mode_str {
-        "openai" | "lmstudio" => EmbeddingMode::OpenAI {
-            api_key: args.embedding_api_key.clone().or(config.embedding.api_key.clone()),
-            base_url: args.embedding_api_base.clone().or(config.embedding.base_url.clone()),
-        },
-        "ollama" => EmbeddingMode::Ollama {
-            host: args.embedding_host.clone().or(config.embedding.host.clone()),
-        },
-        "gemini" => EmbeddingMode::Gemini {
-            api_key: args.google_api_key.clone().or(config.embedding.api_key.clone()),
+    // English-only default models give way to a multilingual one when most
+    // documents aren't English; a model passed on the command line is kept
+    let embedding_model = match multilingual_alternative(&embedding_model) {
+        Some(alternative) if args.source != "audio" => match sample_language(&args.docs, args.max_file_size_kb, args.follow_symlinks, &path_filter) {
+            Some(lang) if lang != "eng" && model_is_default => {
+                reporter.message(format!(
+                    "Documents are mostly '{}'; using multilingual model {} instead of {}",
+                    lang, alternative, embedding_model
+                ));
+                alternative.to_string()
+            }
+            Some(lang) if lang != "eng" => {
+                tracing::warn!(
+                    "Documents are mostly '{}' but {} is English-only; consider --embedding-model {}",
+                    lang, embedding_model, alternative
+                );
+                embedding_model
+            }
+            _ => embedding_model,
         },
+        _ => embedding_model,
+    };
+
+    // Parse embedding mode, using config for host/base_url if not specified in CLI
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        google_api_key: args.google_api_key.clone(),
+        jina_api_key: args.jina_api_key.clone(),
         #[cfg(feature = "local-embeddings")]
-        "local" => EmbeddingMode::Local {
-            model_path: args.embedding_model_path.clone(),
-        },
-        _ => anyhow::bail!("Unknown embedding mode: {}", embedding_mode_str),
+        model_path: args.embedding_model_path.clone(),
     };
+    let embedding_mode = EmbeddingMode::from_name(embedding_mode_str, &overrides, &config.embedding)?;
 
     // Get model-specific configuration (prefixes, normalization)
     let model_config = get_model_config(&embedding_model);
@@ -200,25 +594,85 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
     if !document_prefix.is_empty() {
         info!("Document prefix: {:?}", document_prefix);
     }
+    let instructions = DocumentInstructions::new(config.embedding.instructions.clone());
+    if !instructions.is_empty() {
+        info!("Document instructions by file type: {}", instructions.to_json());
+    }
+
+    // Chunk text must leave room in the model's input for the longest template
+    let prefix_tokens = instructions
+        .templates()
+        .chain([document_prefix.as_str()])
+        .map(template_tokens)
+        .max()
+        .unwrap_or(0);
+    let max_chunk_tokens = args
+        .max_chunk_tokens
+        .or_else(|| max_input_tokens(&embedding_model).map(|max| max.saturating_sub(prefix_tokens)));
+    let (chunk_size, chunk_overlap) = match fit_chunk_size(args.doc_chunk_size, args.doc_chunk_overlap, max_chunk_tokens, args.auto_chunk_size) {
+        ChunkFit::Fits => (args.doc_chunk_size, args.doc_chunk_overlap),
+        ChunkFit::Reduced { chunk_size, chunk_overlap } => {
+            reporter.message(format!(
+                "Reducing chunk size from {} to {} tokens (overlap {}) to fit the embedding model's input",
+                args.doc_chunk_size, chunk_size, chunk_overlap
+            ));
+            (chunk_size, chunk_overlap)
+        }
+        ChunkFit::Over { max } => {
+            tracing::warn!(
+                "--doc-chunk-size {} is over the {}-token chunk limit of the embedding model (its input limit less \
+                the prompt template); longer chunks are handled by the '{}' length policy. Use --auto-chunk-size to \
+                fit chunks to the model.",
+                args.doc_chunk_size,
+                max,
+                args.chunk_length_policy
+            );
+            (args.doc_chunk_size, args.doc_chunk_overlap)
+        }
+    };
+
+    // Post-processing: model defaults, overridden by flags
+    let mut post_processing = PostProcessing::for_model(&embedding_model);
+    if args.normalize || args.no_normalize {
+        post_processing.normalize = args.normalize;
+    }
+    post_processing.truncate_dims = args.embedding_dims;
+    post_processing.precision = args.embedding_precision.parse()
+        .map_err(|e: String| anyhow::anyhow!(e))?;
+    if !post_processing.is_identity() {
+        info!("Embedding post-processing: {:?}", post_processing);
+    }
 
     // Create embedding provider
     let embedding_provider = EmbeddingProvider::new(
         embedding_model.clone(),
         embedding_mode.clone(),
-    ).await?;
+    ).await?
+    .with_post_processing(post_processing.clone())?;
 
     // Get embedding dimensions
     let dimensions = embedding_provider.dimensions();
     info!("Embedding dimensions: {}", dimensions);
 
+    // Learned sparse vectors, stored alongside the dense index
+    let sparse_model = args.sparse_model.as_ref().map(|model| SparseModelInfo {
+        model: model.clone(),
+        host: args.sparse_host.clone(),
+    });
+    let sparse_provider = sparse_model.as_ref().map(|info| info.provider(None));
+
+    // Per-token vectors for late-interaction reranking
+    let multi_vector = args.multi_vector.then(|| MultiVectorInfo {
+        model: args.multi_vector_model.clone(),
+        dimensions: args.multi_vector_dims,
+    });
+    let multi_vector_provider = match &multi_vector {
+        Some(info) => Some(info.provider(args.jina_api_key.clone())?),
+        None => None,
+    };
+
     // Load documents
-    let progress = ProgressBar::new_spinner();
-    progress.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} {msg}")
-            .unwrap(),
-    );
-    progress.set_message("Loading documents...");
+    let progress = reporter.spinner("Loading documents...");
 
     let file_types: Option<Vec<String>> = args.file_types.map(|ft| {
         ft.split(',')
@@ -226,9 +680,33 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
             .collect()
     });
 
+    // Recordings are transcribed instead of read as text
+    let audio = args.source == "audio";
+    let transcriber = audio
+        .then(|| {
+            Transcriber::new(
+                args.transcription_model.clone(),
+                None,
+                args.transcription_api_base.clone(),
+                args.transcription_language.clone(),
+            )
+        })
+        .transpose()?;
+    let obsidian = args.source == "obsidian";
+    let (collect_types, max_file_size_kb) = if audio {
+        let audio_types = AUDIO_TYPES.iter().map(|t| t.to_string()).collect();
+        (Some(file_types.clone().unwrap_or(audio_types)), args.max_file_size_kb.max(MAX_AUDIO_FILE_SIZE_KB))
+    } else if obsidian {
+        (Some(file_types.clone().unwrap_or_else(|| vec![".md".to_string()])), args.max_file_size_kb)
+    } else {
+        (file_types.clone(), args.max_file_size_kb)
+    };
+
     let chunking_strategy: ChunkingStrategy = args.chunking_strategy.parse()
         .unwrap_or(ChunkingStrategy::Auto);
 
+    let enrichers = EnrichmentPipeline::from_names(&args.enrichers.split(',').collect::<Vec<_>>())?;
+
     // Collect file paths first (low memory), then process in batches
     let exclude_types: Option<Vec<String>> = args.exclude_types.map(|ft| {
         ft.split(',')
@@ -236,33 +714,125 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
             .collect()
     });
 
-    let file_paths = collect_file_paths(
-        &args.docs,
-        file_types.as_deref(),
-        exclude_types.as_deref(),
-        args.include_hidden,
-        args.max_files,
-        args.max_file_size_kb,
-    )?;
+    // Mail files have no size limit: an mbox archive holds many messages
+    let mail = args.source == "mail";
+    let walk = WalkOptions {
+        file_types: collect_types.as_deref(),
+        exclude_types: exclude_types.as_deref(),
+        include_hidden: args.include_hidden,
+        follow_symlinks: args.follow_symlinks,
+        max_files: args.max_files,
+        max_total_size_mb: args.max_total_size_mb,
+        max_file_size_kb,
+        data_max_file_size_kb: args.data_max_file_size_kb,
+        path_filter: &path_filter,
+    };
+    let file_paths = if mail {
+        crate::mail::collect_mail_files(&args.docs, args.include_hidden, args.max_files)
+    } else {
+        collect_file_paths(&args.docs, &walk)?
+    };
 
-    progress.finish_with_message(format!("Found {} files", file_paths.len()));
+    // Notes are read once up front to count backlinks
+    let vault = obsidian.then(|| crate::vault::Vault::scan(&file_paths));
+
+    #[cfg(feature = "images")]
+    let image_paths = if args.images {
+        let image_types: Vec<String> = IMAGE_TYPES.iter().map(|t| t.to_string()).collect();
+        let walk = WalkOptions {
+            file_types: Some(&image_types),
+            max_file_size_kb: args.max_file_size_kb,
+            data_max_file_size_kb: None,
+            ..walk
+        };
+        collect_file_paths(&args.docs, &walk)?
+    } else {
+        Vec::new()
+    };
+    #[cfg(not(feature = "images"))]
+    let image_paths: Vec<PathBuf> = Vec::new();
+
+    progress.finish_with_message(format!("Found {} files", file_paths.len() + image_paths.len()));
+    reporter.emit(ProgressEvent::Scanned { files: file_paths.len(), images: image_paths.len() });
 
-    if file_paths.is_empty() {
+    if file_paths.is_empty() && image_paths.is_empty() {
         anyhow::bail!("No documents found to index");
     }
 
+    // Upfront cost estimate (transcripts are much smaller than the recordings)
+    let estimated_tokens = (!audio).then(|| estimate_build_tokens(&file_paths, chunk_size, chunk_overlap));
+    let estimated_cost = estimated_tokens.and_then(|tokens| embedding_provider.cost(tokens));
+    reporter.emit(ProgressEvent::Estimate { tokens: estimated_tokens, cost_usd: estimated_cost });
+    if !reporter.is_json() {
+        match estimated_tokens {
+            Some(tokens) => println!(
+                "Estimated embedding: ~{} tokens, {} ({})",
+                tokens,
+                format_cost(estimated_cost),
+                embedding_model
+            ),
+            None => println!("Estimated embedding: unknown until recordings are transcribed ({})", embedding_model),
+        }
+    }
+    if args.estimate_only {
+        if !reporter.is_json() {
+            println!("Nothing was embedded (--estimate-only).");
+        }
+        return Ok(());
+    }
+
+    // Build into a staging directory so a failed build leaves the current index intact
+    let staging = StagingDir::create(staging_dir(&index_dir))?;
+
+    // Embeddings are checkpointed as they're computed, so a cancelled or failed
+    // build resumes without embedding the same chunks again
+    let cancel = args.cancel.clone().unwrap_or_else(Cancellation::on_ctrl_c);
+    let mut checkpoint = EmbeddingCheckpoint::open(
+        &index_dir,
+        checkpoint_header(&embedding_provider, Some(&post_processing), &document_prefix, Some(&instructions)),
+    )?;
+    if checkpoint.len() > 0 {
+        reporter.message(format!("Resuming: {} embeddings reused from an interrupted build", checkpoint.len()));
+    }
 
     // Build index using streaming builder to minimize memory usage
-    let index_path = index_dir.join("documents.leann");
+    let index_path = staging.path.join("documents.leann");
+    let passage_format: PassageFormat = args.passage_store.parse()?;
+    let shard_plan = args
+        .shards
+        .map(ShardPlan::Count)
+        .or(args.shard_size.map(ShardPlan::MaxPassages));
     let mut builder = StreamingIndexBuilder::new(
-        backend_type,
+        backend.clone(),
         dimensions,
-        args.graph_degree,
-        args.complexity,
+        build_params.graph_degree,
+        build_params.complexity,
         args.recompute,
         &index_path,
-    )?;
-
+    )?
+    .with_store_text(!args.no_store_text)
+    .with_passage_format(passage_format)?
+    .with_sparse_index(sparse_provider.is_some())
+    .with_multi_vector(multi_vector.as_ref().map(|info| info.dimensions))?
+    .with_shard_plan(shard_plan)?
+    .with_diskann_params(build_params.diskann);
+
+    // The summary tree keeps chunk embeddings in memory until it is built
+    let raptor_llm = if args.raptor {
+        Some(build_llm(&config, args.raptor_llm.as_deref(), args.raptor_model.as_deref())?)
+    } else {
+        None
+    };
+    let mut raptor = match &raptor_llm {
+        Some(_) => Some(RaptorTree::new(dimensions, args.raptor_cluster_size, args.raptor_levels)?),
+        None => None,
+    };
+    let graph_llm = if args.graph.as_deref() == Some("llm") {
+        Some(build_llm(&config, args.graph_llm.as_deref(), args.graph_model.as_deref())?)
+    } else {
+        None
+    };
+    let mut graph = args.graph.is_some().then(|| GraphExtractor::new(graph_llm.as_ref()));
 
     // Process files in streaming fashion to avoid memory explosion
     let batch_size = args.embedding_batch_size.unwrap_or_else(|| {
@@ -272,15 +842,24 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
         }
     });
 
-    let chunker = SmartChunker::new(chunking_strategy, args.doc_chunk_size, args.doc_chunk_overlap);
+    let chunker = SmartChunker::new(chunking_strategy, chunk_size, chunk_overlap).with_structured_data(!args.no_store_text);
+    // Directories can override chunking and add tags with a .leann.toml
+    let mut dir_overrides = DirOverrides::new(&args.docs);
+    if let Some(namespace) = &args.namespace {
+        dir_overrides = dir_overrides.with_metadata(NAMESPACE_FIELD, namespace.as_str().into());
+    }
 
-    let progress = ProgressBar::new(file_paths.len() as u64);
-    progress.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} files ({eta})")
-            .unwrap()
-            .progress_chars("#>-"),
-    );
+    // Chunks over the model's input limit are handled by the length policy
+    let length_policy: ChunkLengthPolicy = args.chunk_length_policy.parse()
+        .map_err(|e: String| anyhow::anyhow!(e))?;
+    let length_limit = max_chunk_tokens.map(|max_tokens| ChunkLengthLimit::new(length_policy, max_tokens));
+
+    // Secrets are caught before chunks are split, so no key is cut in half
+    let secrets_policy: SecretsPolicy = args.secrets.parse()
+        .map_err(|e: String| anyhow::anyhow!(e))?;
+    let secret_scanner = SecretScanner::new(secrets_policy);
+
+    let progress = reporter.file_bar(file_paths.len() as u64);
 
     let mut total_chunks = 0usize;
     let mut chunk_id = 0u64;
@@ -291,11 +870,95 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
     let mut embed_time_total = std::time::Duration::ZERO;
     let mut embed_batches = 0usize;
     let build_start = std::time::Instant::now();
-
-    for file_path in &file_paths {
-        // Load and chunk one file at a time
-        if let Some(content) = load_file_content(file_path) {
-            let file_chunks = chunker.chunk(&content, file_path, &mut chunk_id);
+    let mut manifest: Vec<FileManifestEntry> = Vec::with_capacity(file_paths.len());
+    let mut length_stats = LengthStats::default();
+    let mut secret_stats = SecretStats::default();
+    let mut lang_counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    let mut code_chunks = 0usize;
+    // Stable ids of chunks seen so far, to drop files reached twice (e.g. overlapping --docs)
+    let mut seen_ids: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut duplicate_chunks = 0usize;
+
+    for (files_done, file_path) in file_paths.iter().enumerate() {
+        cancel.check("Build", &index_name)?;
+        let dir_settings = dir_overrides.settings_for(file_path)?;
+        let dir_chunker = chunker.with_settings(&dir_settings);
+        let chunker = dir_chunker.as_ref().unwrap_or(&chunker);
+
+        // Load and chunk one file at a time (archives yield one document per entry)
+        let loaded: Vec<(PathBuf, String, Vec<Chunk>)> = match &transcriber {
+            Some(transcriber) => match transcriber.transcribe(file_path).await {
+                Ok(segments) => {
+                    let chunks = chunk_segments(&segments, file_path, chunk_size, &mut chunk_id);
+                    let transcript = segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join("\n");
+                    vec![(file_path.clone(), transcript, chunks)]
+                }
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    Vec::new()
+                }
+            },
+            #[cfg(feature = "archives")]
+            None if crate::archive::is_archive(file_path) => {
+                let accept = |entry: &std::path::Path, size: u64| {
+                    accepts_archive_entry(entry, size, collect_types.as_deref(), exclude_types.as_deref(), max_file_size_kb, args.data_max_file_size_kb)
+                };
+                match crate::archive::read_entries(file_path, accept) {
+                    Ok(entries) => entries
+                        .into_iter()
+                        .map(|(path, content)| {
+                            let chunks = match &vault {
+                                Some(vault) => vault.chunk_note(&path, &content, &mut chunk_id),
+                                None => chunker.chunk(&content, &path, &mut chunk_id),
+                            };
+                            (path, content, chunks)
+                        })
+                        .collect(),
+                    Err(e) => {
+                        tracing::warn!("Failed to read archive {}: {}", file_path.display(), e);
+                        Vec::new()
+                    }
+                }
+            }
+            // Messages may have 8-bit bodies in other charsets
+            None if mail => std::fs::read(file_path).ok().map(|bytes| {
+                let content = String::from_utf8_lossy(&bytes).into_owned();
+                let chunks = crate::mail::chunk_file(file_path, &content, &mut chunk_id);
+                (file_path.clone(), content, chunks)
+            }).into_iter().collect(),
+            None => load_file_content(file_path)
+                .map(|content| {
+                    let chunks = match &vault {
+                        Some(vault) => vault.chunk_note(file_path, &content, &mut chunk_id),
+                        None => chunker.chunk(&content, file_path, &mut chunk_id),
+                    };
+                    (file_path.clone(), content, chunks)
+                })
+                .into_iter()
+                .collect(),
+        };
+        for (file_path, content, mut file_chunks) in loaded {
+            file_chunks = secret_scanner.apply(file_chunks, &mut secret_stats);
+            if let Some(limit) = &length_limit {
+                file_chunks = limit.apply(file_chunks, &mut length_stats)?;
+            }
+            enrichers.enrich(&file_path, &content, &mut file_chunks);
+            dir_settings.tag(&mut file_chunks);
+            instructions.tag(&file_path, &mut file_chunks);
+            assign_stable_ids(&mut file_chunks, &file_path);
+            let before = file_chunks.len();
+            file_chunks.retain(|c| seen_ids.insert(c.id.clone()));
+            duplicate_chunks += before - file_chunks.len();
+            for lang in file_chunks.iter().filter_map(|c| c.metadata.get("lang").and_then(|l| l.as_str())) {
+                *lang_counts.entry(lang.to_string()).or_insert(0) += 1;
+            }
+            if is_code_file(&file_path) {
+                code_chunks += file_chunks.len();
+            }
+            manifest.push(FileManifestEntry::for_file(&file_path, &content, file_chunks.len()));
+            if let Some(graph) = graph.as_mut() {
+                graph.add_chunks(&file_path, &file_chunks).await;
+            }
 
             // Track stats by extension
             let ext = file_path.extension()
@@ -310,12 +973,17 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
 
             // Process batch when full
             while pending_chunks.len() >= batch_size {
+                cancel.check("Build", &index_name)?;
                 let batch: Vec<Chunk> = pending_chunks.drain(..batch_size).collect();
                 let batch_start = std::time::Instant::now();
-                process_chunk_batch(&batch, &embedding_provider, &document_prefix, &mut builder).await?;
+                let embeddings = process_chunk_batch(&batch, false, &embedding_provider, sparse_provider.as_ref(), multi_vector_provider.as_ref(), &document_prefix, &mut checkpoint, &mut builder).await?;
+                if let Some(raptor) = raptor.as_mut() {
+                    raptor.add_leaves(&batch, embeddings);
+                }
                 embed_time_total += batch_start.elapsed();
                 embed_batches += 1;
                 total_chunks += batch.len();
+                reporter.batch(files_done, file_paths.len(), total_chunks, batch.len(), batch_start.elapsed().as_millis() as u64);
                 // Log progress every 500 chunks
                 if total_chunks % 500 == 0 {
                     let elapsed = build_start.elapsed().as_secs();
@@ -331,41 +999,219 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
 
     // Process remaining chunks
     if !pending_chunks.is_empty() {
-        process_chunk_batch(&pending_chunks, &embedding_provider, &document_prefix, &mut builder).await?;
+        cancel.check("Build", &index_name)?;
+        let batch_start = std::time::Instant::now();
+        let embeddings = process_chunk_batch(&pending_chunks, false, &embedding_provider, sparse_provider.as_ref(), multi_vector_provider.as_ref(), &document_prefix, &mut checkpoint, &mut builder).await?;
+        if let Some(raptor) = raptor.as_mut() {
+            raptor.add_leaves(&pending_chunks, embeddings);
+        }
         total_chunks += pending_chunks.len();
+        reporter.batch(file_paths.len(), file_paths.len(), total_chunks, pending_chunks.len(), batch_start.elapsed().as_millis() as u64);
     }
 
     progress.finish_with_message(format!("Indexed {} chunks from {} files", total_chunks, file_paths.len()));
+    if duplicate_chunks > 0 {
+        info!("Skipped {} duplicate chunks (same source and content)", duplicate_chunks);
+    }
+
+    // Images are embedded whole, one passage each
+    #[cfg(feature = "images")]
+    for batch in image_paths.chunks(IMAGE_BATCH_SIZE) {
+        cancel.check("Build", &index_name)?;
+        let batch_start = std::time::Instant::now();
+        process_image_batch(
+            batch,
+            &embedding_provider,
+            sparse_provider.is_some(),
+            multi_vector_provider.is_some(),
+            &mut builder,
+        )
+        .await?;
+        embed_time_total += batch_start.elapsed();
+        embed_batches += 1;
+        total_chunks += batch.len();
+        reporter.batch(file_paths.len(), file_paths.len(), total_chunks, batch.len(), batch_start.elapsed().as_millis() as u64);
+    }
+    if !image_paths.is_empty() {
+        reporter.message(format!("Indexed {} images", image_paths.len()));
+    }
+
+    // Summarize clusters level by level; summary nodes are indexed with the chunks
+    let mut raptor_summaries = 0usize;
+    if let (Some(raptor), Some(llm)) = (raptor.as_mut(), raptor_llm.as_ref()) {
+        let summarizer = Summarizer::new(llm, None, DEFAULT_MAX_TOKENS as usize);
+        let mut nodes = raptor.take_leaves();
+        for level in 1..=raptor.max_levels {
+            if nodes.len() < 2 {
+                break;
+            }
+            cancel.check("Build", &index_name)?;
+            let (summaries, carried) = raptor.summarize_level(&nodes, level, &summarizer).await?;
+            if summaries.is_empty() {
+                break;
+            }
+            let batch_start = std::time::Instant::now();
+            let embeddings = process_chunk_batch(&summaries, true, &embedding_provider, sparse_provider.as_ref(), multi_vector_provider.as_ref(), &document_prefix, &mut checkpoint, &mut builder).await?;
+            embed_time_total += batch_start.elapsed();
+            embed_batches += 1;
+            raptor_summaries += summaries.len();
+            reporter.message(format!("Summarized {} nodes into {} level-{} summaries", nodes.len(), summaries.len(), level));
+            nodes = super::raptor::nodes(&summaries, embeddings).chain(carried).collect();
+        }
+        total_chunks += raptor_summaries;
+    }
 
     // Build the vector index
     builder.build()?;
 
+    let graph_size = match graph {
+        Some(graph) => {
+            let graph = graph.finish();
+            graph.save(&index_path)?;
+            Some((graph.nodes().len(), graph.edges().len()))
+        }
+        None => None,
+    };
+
     // Save metadata with embedding options (including query prefix for search)
-    let query_prefix = model_config.query_prefix.to_string();
-    let embedding_options = if !query_prefix.is_empty() || !document_prefix.is_empty() {
-        Some(serde_json::json!({
-            "query_prompt_template": query_prefix,
-            "build_prompt_template": document_prefix,
-        }))
-    } else {
-        None
+    let query_prefix = args.query_prompt_template
+        .clone()
+        .unwrap_or_else(|| model_config.query_prefix.to_string());
+    let embedding_options = embedding_options(&query_prefix, &document_prefix, &instructions);
+
+    // Languages by chunk count, most common first
+    let mut languages: Vec<(String, usize)> = lang_counts.into_iter().collect();
+    languages.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let dominant_language = languages.first().map(|(lang, _)| lang.as_str());
+
+    // BM25 analysis follows the corpus unless set explicitly
+    let bm25_tokenizer = match args.bm25_tokenizer.as_str() {
+        "auto" => match dominant_language.map(Bm25Tokenizer::for_language) {
+            Some(Bm25Tokenizer::Ngram) => Bm25Tokenizer::Ngram,
+            _ if code_chunks * 2 > total_chunks => Bm25Tokenizer::Code,
+            _ => Bm25Tokenizer::Words,
+        },
+        other => other.parse().map_err(|e: String| anyhow::anyhow!(e))?,
     };
+    let mut bm25_analyzer = Bm25Analyzer::new(bm25_tokenizer)
+        .with_stopwords(!args.no_bm25_stopwords)
+        .with_fields(bm25_fields);
+    match args.bm25_stemmer.as_str() {
+        "none" => {}
+        "auto" => {
+            if let Some(lang) = dominant_language.filter(|l| bm25_tokenizer == Bm25Tokenizer::Words && has_stemmer(l)) {
+                bm25_analyzer = bm25_analyzer.with_stemmer(lang)?;
+            }
+        }
+        language => bm25_analyzer = bm25_analyzer.with_stemmer(language)?,
+    }
+
+    let mut backend_kwargs = backend.build_kwargs(&build_params);
+    if let Some(plan) = shard_plan {
+        backend_kwargs["shards"] = plan.split(total_chunks).len().into();
+    }
 
     let meta = IndexMeta {
-        version: "1.0".to_string(),
+        version: META_VERSION.to_string(),
         backend_name: args.backend_name,
         embedding_model,
         embedding_mode: embedding_mode_str.to_string(),
         dimensions,
         passage_count: total_chunks,
-        backend_kwargs: None,
+        backend_kwargs: Some(backend_kwargs),
         embedding_options,
+        post_processing: Some(post_processing),
+        sparse_model,
+        multi_vector,
         is_recompute: args.recompute,
         is_pruned: false,
+        created_at: Some(crate::time::now_unix()),
+        chunking: Some(ChunkingInfo {
+            strategy: args.chunking_strategy.clone(),
+            chunk_size,
+            chunk_overlap,
+            length_policy: Some(args.chunk_length_policy.clone()),
+            max_chunk_tokens: length_limit.as_ref().map(|l| l.max_tokens),
+            secrets: Some(args.secrets.clone()),
+        }),
+        text_stored: !args.no_store_text,
+        passage_store: (passage_format != PassageFormat::Jsonl).then(|| passage_format.to_string()),
+        provenance: Some(BuildProvenance {
+            docs: args
+                .docs
+                .iter()
+                .map(|p| {
+                    p.canonicalize()
+                        .unwrap_or_else(|_| p.clone())
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect(),
+            file_types,
+            exclude_types,
+            include: (!include.is_empty()).then_some(include),
+            exclude: (!exclude.is_empty()).then_some(exclude),
+            include_hidden: args.include_hidden,
+            follow_symlinks: args.follow_symlinks,
+            max_file_size_kb: Some(max_file_size_kb),
+            max_files: args.max_files,
+            max_total_size_mb: args.max_total_size_mb,
+            data_max_file_size_kb: args.data_max_file_size_kb,
+            enrichers: Some(enrichers.names()),
+            source: (audio || mail || obsidian).then(|| args.source.clone()),
+            namespace: args.namespace.clone(),
+            files: manifest,
+        }),
+        prompt_templates,
+        bm25_analyzer: Some(bm25_analyzer.clone()),
+        search_defaults: Some(SearchDefaults {
+            top_k: args.default_top_k,
+            complexity: args.default_complexity,
+            hybrid: args.default_hybrid,
+            hybrid_alpha: args.default_hybrid_alpha,
+        })
+        .filter(|defaults| !defaults.is_empty()),
     };
-    meta.save(&index_dir.join("documents.leann.meta.json"))?;
+    meta.save(&staging.path.join("documents.leann.meta.json"))?;
+    staging.commit(&index_dir)?;
+    checkpoint.remove()?;
 
     let total_time = build_start.elapsed();
+    let embedding_tokens = embedding_provider.tokens_used();
+    let embedding_cost = embedding_provider.cost(embedding_tokens);
+    let truncated_inputs = embedding_provider.truncated_inputs();
+    let mut usage = vec![UsageRecord::new(
+        "build",
+        Some(&index_name),
+        UsageKind::Embedding,
+        embedding_provider.mode_name(),
+        embedding_provider.model_name(),
+    )
+    .with_tokens(embedding_tokens, 0, embedding_cost)];
+    for llm in raptor_llm.iter().chain(&graph_llm) {
+        let generation = llm.usage();
+        usage.push(
+            UsageRecord::new("build", Some(&index_name), UsageKind::Generation, llm.provider_name(), llm.model_name())
+                .with_tokens(generation.input_tokens, generation.output_tokens, llm.cost(&generation)),
+        );
+    }
+    record_usage(&usage);
+
+    reporter.emit(ProgressEvent::Finished {
+        index: index_name.clone(),
+        passages: total_chunks,
+        dimensions,
+        elapsed_secs: total_time.as_secs_f64(),
+        embedding_secs: embed_time_total.as_secs_f64(),
+        batches: embed_batches,
+        embedding_tokens,
+        truncated_inputs,
+        cost_usd: embedding_cost,
+    });
+    if reporter.is_json() {
+        return Ok(());
+    }
+
     println!("Index '{}' built successfully at {:?}", index_name, index_dir);
     println!("  Passages: {}", total_chunks);
     println!("  Dimensions: {}", dimensions);
@@ -376,6 +1222,7 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
     println!("  Avg batch time: {:.0}ms ({} batches)",
              embed_time_total.as_millis() as f64 / embed_batches.max(1) as f64,
              embed_batches);
+    println!("  Embedding tokens: ~{} ({})", embedding_tokens, format_cost(embedding_cost));
 
     // Show top file types by chunk count
     let mut stats_vec: Vec<_> = stats.into_iter().collect();
@@ -386,6 +1233,81 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
         println!("    .{}: {} files, {} chunks (avg {}/file)", ext, files, chunks, avg);
     }
 
+    if !languages.is_empty() {
+        let detected: usize = languages.iter().map(|(_, count)| count).sum();
+        let shares: Vec<String> = languages
+            .iter()
+            .take(5)
+            .map(|(lang, count)| format!("{} {:.0}%", lang, 100.0 * *count as f64 / detected as f64))
+            .collect();
+        println!("\n  Languages: {}", shares.join(", "));
+    }
+    println!(
+        "  BM25 analyzer: {}{}{}{}",
+        bm25_analyzer.tokenizer,
+        bm25_analyzer.stemmer.as_ref().map(|s| format!(", {} stemming", s)).unwrap_or_default(),
+        if bm25_analyzer.stopwords { ", stopwords removed" } else { "" },
+        if bm25_analyzer.fields.is_empty() {
+            String::new()
+        } else {
+            let fields: Vec<String> = bm25_analyzer.fields.iter().map(|f| format!("{} x{}", f.field, f.weight)).collect();
+            format!(", fields {}", fields.join(", "))
+        }
+    );
+
+    if length_stats.any() {
+        println!("\n  Over-length chunks ({} policy, {} tokens):", args.chunk_length_policy,
+                 length_limit.as_ref().map_or(0, |l| l.max_tokens));
+        if length_stats.truncated > 0 {
+            println!("    Truncated: {} chunks (~{} tokens dropped)",
+                     length_stats.truncated, length_stats.tokens_dropped);
+        }
+        if length_stats.split > 0 {
+            println!("    Split: {} chunks into {} pieces", length_stats.split, length_stats.split_pieces);
+        }
+    }
+    if truncated_inputs > 0 {
+        println!("\n  Truncated by the embedding model's input limit: {} inputs (lower --doc-chunk-size or use \
+                  --auto-chunk-size)", truncated_inputs);
+    }
+
+    if secret_stats.any() {
+        let kinds: Vec<String> = secret_stats.by_kind.iter().map(|(kind, n)| format!("{} {}", kind, n)).collect();
+        let action = match secrets_policy {
+            SecretsPolicy::Skip => "skipped",
+            SecretsPolicy::Redact => "redacted",
+            SecretsPolicy::Allow => "indexed unchanged",
+        };
+        println!("\n  Secrets: {} found in {} chunks ({}), {}", secret_stats.total(), secret_stats.chunks,
+                 kinds.join(", "), action);
+        for source in secret_stats.sources.iter().take(10) {
+            println!("    {}", source);
+        }
+        if secret_stats.sources.len() > 10 {
+            println!("    ... and {} more files", secret_stats.sources.len() - 10);
+        }
+    }
+
+    if args.no_store_text {
+        println!("\n  Text-free mode: passage text not stored (re-read from source files)");
+    }
+
+    if let Some(model) = &args.sparse_model {
+        println!("\n  Sparse vectors: {} (search with --sparse)", model);
+    }
+
+    if args.multi_vector {
+        println!("\n  Token vectors: {} (search with --multi-vector)", args.multi_vector_model);
+    }
+
+    if let Some((entities, relations)) = graph_size {
+        println!("\n  Knowledge graph: {} entities, {} relations (see 'leann graph query {}')", entities, relations, index_name);
+    }
+
+    if raptor_summaries > 0 {
+        println!("\n  Summary tree: {} summaries (search them with --filter type=summary)", raptor_summaries);
+    }
+
     if args.recompute {
         println!("\n  Recompute mode: enabled (run 'leann prune {}' to save space)", index_name);
     }
@@ -393,74 +1315,334 @@ pub async fn run(args: BuildArgs, _verbose: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Process a batch of chunks: compute embeddings and add to builder
-async fn process_chunk_batch(
+/// How the chunk size relates to the chunk token limit
+#[derive(Debug, PartialEq)]
+enum ChunkFit {
+    /// Within the limit, or no limit is known
+    Fits,
+    /// Reduced to the limit (`--auto-chunk-size`)
+    Reduced { chunk_size: usize, chunk_overlap: usize },
+    /// Over the `max`-token limit; the length policy handles chunks over it
+    Over { max: usize },
+}
+
+/// Fit a chunk size and overlap to `max_chunk_tokens`: with `auto`, a larger
+/// size is reduced to it and the overlap to at most half of it
+///
+/// Chunkers cut chunks at `size * 4` bytes, so a chunk is at most `size`
+/// tokens by `estimate_tokens`, the estimate the length limit checks chunks
+/// against as well.
+fn fit_chunk_size(size: usize, overlap: usize, max_chunk_tokens: Option<usize>, auto: bool) -> ChunkFit {
+    match max_chunk_tokens.filter(|max| size > *max) {
+        None => ChunkFit::Fits,
+        Some(max) if auto => {
+            let max = max.max(1);
+            ChunkFit::Reduced { chunk_size: max, chunk_overlap: overlap.min(max / 2) }
+        }
+        Some(max) => ChunkFit::Over { max },
+    }
+}
+
+/// LLM for generation during a build: flags, then config.toml
+fn build_llm(config: &Config, provider: Option<&str>, model: Option<&str>) -> anyhow::Result<LlmProvider> {
+    let provider = provider.unwrap_or(&config.llm.provider);
+    let model = model.map(String::from).unwrap_or_else(|| config.llm.model.clone());
+    let llm_type = LlmType::from_name(provider, &LlmOverrides::default(), &config.llm)?;
+    LlmProvider::new(model, llm_type)
+}
+
+/// Prompt templates recorded in the index metadata, if any
+pub(super) fn embedding_options(
+    query_prefix: &str,
+    document_prefix: &str,
+    instructions: &DocumentInstructions,
+) -> Option<serde_json::Value> {
+    if query_prefix.is_empty() && document_prefix.is_empty() && instructions.is_empty() {
+        return None;
+    }
+    let mut options = serde_json::json!({
+        "query_prompt_template": query_prefix,
+        "build_prompt_template": document_prefix,
+    });
+    if !instructions.is_empty() {
+        options["document_instructions"] = instructions.to_json();
+    }
+    Some(options)
+}
+
+/// Checkpoint header for passages embedded by `provider` with these settings
+pub(super) fn checkpoint_header(
+    provider: &EmbeddingProvider,
+    post_processing: Option<&PostProcessing>,
+    document_prefix: &str,
+    instructions: Option<&DocumentInstructions>,
+) -> CheckpointHeader {
+    CheckpointHeader {
+        model: format!("{}/{}", provider.mode_name(), provider.model_name()),
+        dimensions: provider.dimensions(),
+        settings: serde_json::json!({
+            "post_processing": post_processing,
+            "document_prefix": document_prefix,
+            "document_instructions": instructions.map(DocumentInstructions::to_json),
+        }),
+    }
+}
+
+/// Embed chunks, reusing the vectors `checkpoint` has for them and appending
+/// the new ones to it
+pub(super) async fn embed_with_checkpoint(
+    chunks: &[Chunk],
+    embedding_provider: &EmbeddingProvider,
+    embed_template: &str,
+    checkpoint: &mut EmbeddingCheckpoint,
+) -> anyhow::Result<Vec<Vec<f32>>> {
+    let missing: Vec<&Chunk> = chunks.iter().filter(|c| checkpoint.get(&c.id).is_none()).collect();
+    let mut new_embeddings = Vec::new();
+    if !missing.is_empty() {
+        let texts: Vec<&str> = missing.iter().map(|c| c.text.as_str()).collect();
+        let templates: Vec<&str> = missing.iter().map(|c| document_template(&c.metadata, embed_template)).collect();
+        new_embeddings = embedding_provider.embed_documents(&texts, &templates).await?;
+        checkpoint.add(missing.iter().zip(&new_embeddings).map(|(c, v)| (c.id.as_str(), v.as_slice())))?;
+    }
+
+    let mut new_embeddings = new_embeddings.into_iter();
+    chunks
+        .iter()
+        .map(|c| match checkpoint.get(&c.id) {
+            Some(vector) => Ok(vector.to_vec()),
+            None => new_embeddings.next().ok_or_else(|| anyhow::anyhow!("Missing embedding for chunk {}", c.id)),
+        })
+        .collect()
+}
+
+/// Process a batch of chunks: compute embeddings (and sparse or per-token
+/// vectors, if enabled) and add to builder; returns the embeddings
+///
+/// Chunks with an `embedding_instruction` are embedded with it instead of
+/// `embed_template`. Embeddings in `checkpoint` are reused, and new ones
+/// are added to it. `generated` chunks (e.g. summaries) keep their text in
+/// text-free indexes.
+#[allow(clippy::too_many_arguments)]
+pub async fn process_chunk_batch(
     chunks: &[Chunk],
+    generated: bool,
     embedding_provider: &EmbeddingProvider,
+    sparse_provider: Option<&SparseEmbedding>,
+    multi_vector_provider: Option<&MultiVectorEmbedding>,
     embed_template: &str,
+    checkpoint: &mut EmbeddingCheckpoint,
     builder: &mut StreamingIndexBuilder,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Vec<Vec<f32>>> {
     let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
-    let embeddings = embedding_provider.embed_with_template(&texts, embed_template).await?;
+    let embeddings = embed_with_checkpoint(chunks, embedding_provider, embed_template, checkpoint).await?;
+    let sparse_vectors = match sparse_provider {
+        Some(provider) => provider.embed(&texts).await?,
+        None => Vec::new(),
+    };
+    let token_vectors = match multi_vector_provider {
+        Some(provider) => provider.embed(&texts, EmbeddingTask::Document).await?,
+        None => Vec::new(),
+    };
 
-    for (chunk, embedding) in chunks.iter().zip(embeddings.iter()) {
-        builder.add_passage(&chunk.id, &chunk.text, embedding, chunk.metadata.clone())?;
+    for (i, (chunk, embedding)) in chunks.iter().zip(embeddings.iter()).enumerate() {
+        if generated {
+            builder.add_generated_passage(&chunk.id, &chunk.text, embedding, chunk.metadata.clone())?;
+        } else {
+            builder.add_passage(&chunk.id, &chunk.text, embedding, chunk.metadata.clone())?;
+        }
+        if let Some(vector) = sparse_vectors.get(i) {
+            builder.add_sparse_vector(vector)?;
+        }
+        if let Some(tokens) = token_vectors.get(i) {
+            builder.add_token_vectors(tokens)?;
+        }
     }
 
-    Ok(())
+    Ok(embeddings)
 }
 
-/// Collect file paths without loading content (memory efficient)
-fn collect_file_paths(
+/// Embed a batch of image files and add them to the builder as passages whose
+/// text is the file path, tagged `modality: image`
+#[cfg(feature = "images")]
+async fn process_image_batch(
     paths: &[PathBuf],
+    embedding_provider: &EmbeddingProvider,
+    sparse: bool,
+    multi_vector: bool,
+    builder: &mut StreamingIndexBuilder,
+) -> anyhow::Result<()> {
+    let images = paths
+        .iter()
+        .map(|path| std::fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", path, e)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let embeddings = embedding_provider.embed_images(&images).await?;
+
+    for ((path, image), embedding) in paths.iter().zip(&images).zip(&embeddings) {
+        let source = path.to_string_lossy();
+        let id = crate::chunker::stable_chunk_id(&source, 0, image);
+        let metadata = serde_json::json!({
+            "source": source,
+            "modality": "image",
+            "size": image.len(),
+        });
+        builder.add_passage(&id, &source, embedding, metadata)?;
+        // Images have no sparse or per-token vectors; keep the side indexes aligned
+        if sparse {
+            builder.add_sparse_vector(&[])?;
+        }
+        if multi_vector {
+            builder.add_token_vectors(&[])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether an archive entry passes the same type and size filters as files on disk
+#[cfg(feature = "archives")]
+fn accepts_archive_entry(
+    entry: &std::path::Path,
+    size: u64,
     file_types: Option<&[String]>,
     exclude_types: Option<&[String]>,
-    include_hidden: bool,
-    max_files: Option<usize>,
     max_file_size_kb: usize,
-) -> anyhow::Result<Vec<PathBuf>> {
-    use ignore::WalkBuilder;
+    data_max_file_size_kb: Option<usize>,
+) -> bool {
+    let Some(ext) = entry.extension() else {
+        return false;
+    };
+    let ext_str = format!(".{}", ext.to_string_lossy());
+    if exclude_types.is_some_and(|ft| ft.contains(&ext_str)) {
+        return false;
+    }
+    let allowed = match file_types {
+        Some(ft) => ft.contains(&ext_str),
+        None => default_file_types().contains(&ext_str.as_str()),
+    };
+    let size_limit_kb = match StructuredChunker::format_for(entry) {
+        Some(_) => data_max_file_size_kb.unwrap_or(max_file_size_kb),
+        None => max_file_size_kb,
+    };
+    allowed && size <= size_limit_kb as u64 * 1024
+}
 
-    let max_file_bytes = max_file_size_kb as u64 * 1024;
-    let mut file_paths = Vec::new();
+/// Rough embedding tokens for a build, from file sizes
+///
+/// About 4 bytes per token, scaled up for chunk overlap. Formats whose text is
+/// much smaller than the file (PDF, Office documents) are overestimated.
+fn estimate_build_tokens(file_paths: &[PathBuf], chunk_size: usize, chunk_overlap: usize) -> usize {
+    let bytes: u64 = file_paths
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    let stride = chunk_size.saturating_sub(chunk_overlap).max(1);
+    (bytes as f64 / 4.0 * chunk_size as f64 / stride as f64).ceil() as usize
+}
 
-    // Default file types
-    #[cfg(feature = "pdf")]
-    let default_types = vec![
-        ".txt", ".md", ".py", ".js", ".ts", ".tsx", ".jsx", ".rs", ".go", ".java",
-        ".c", ".cpp", ".cc", ".h", ".hpp", ".json", ".yaml", ".yml", ".toml",
-        ".rb", ".php", ".swift", ".kt", ".scala", ".cs",
-        ".pdf",
-    ];
-    #[cfg(not(feature = "pdf"))]
-    let default_types = vec![
+/// Number of prose files sampled to pick an embedding model by language
+const LANGUAGE_SAMPLE_FILES: usize = 50;
+
+/// Most common natural language in a sample of the prose documents under
+/// `docs`, if it covers more than half of the files detected reliably
+fn sample_language(docs: &[PathBuf], max_file_size_kb: usize, follow_symlinks: bool, path_filter: &PathFilter) -> Option<&'static str> {
+    let prose_types: Vec<String> = [".md", ".txt", ".pdf"].iter().map(|t| t.to_string()).collect();
+    let walk = WalkOptions {
+        file_types: Some(&prose_types),
+        exclude_types: None,
+        include_hidden: false,
+        follow_symlinks,
+        max_files: Some(LANGUAGE_SAMPLE_FILES),
+        max_total_size_mb: None,
+        max_file_size_kb,
+        data_max_file_size_kb: None,
+        path_filter,
+    };
+    let paths = collect_file_paths(docs, &walk).ok()?;
+
+    let mut counts: std::collections::HashMap<&'static str, usize> = std::collections::HashMap::new();
+    for path in paths.iter().filter(|p| !crate::archive::is_archive(p)) {
+        let sample: Option<String> = load_file_content(path).map(|content| content.chars().take(2000).collect());
+        if let Some(lang) = sample.as_deref().and_then(detect_text_language) {
+            *counts.entry(lang).or_insert(0) += 1;
+        }
+    }
+
+    let detected: usize = counts.values().sum();
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .filter(|(_, count)| *count * 2 > detected)
+        .map(|(lang, _)| lang)
+}
+
+/// File types indexed when --file-types isn't given
+fn default_file_types() -> Vec<&'static str> {
+    let mut types = vec![
         ".txt", ".md", ".py", ".js", ".ts", ".tsx", ".jsx", ".rs", ".go", ".java",
         ".c", ".cpp", ".cc", ".h", ".hpp", ".json", ".yaml", ".yml", ".toml",
         ".rb", ".php", ".swift", ".kt", ".scala", ".cs",
     ];
+    if cfg!(feature = "pdf") {
+        types.push(".pdf");
+    }
+    types
+}
+
+/// Which files `collect_file_paths` picks from the documents roots
+#[derive(Clone, Copy)]
+pub struct WalkOptions<'a> {
+    /// Extensions to index (default: `default_file_types`)
+    pub file_types: Option<&'a [String]>,
+    pub exclude_types: Option<&'a [String]>,
+    pub include_hidden: bool,
+    pub follow_symlinks: bool,
+    pub max_files: Option<usize>,
+    pub max_total_size_mb: Option<usize>,
+    pub max_file_size_kb: usize,
+    /// Size limit of data files (JSON/YAML/TOML), if not `max_file_size_kb`
+    pub data_max_file_size_kb: Option<usize>,
+    pub path_filter: &'a PathFilter,
+}
 
-    // File types with stricter size limits (JSON often contains data, not semantic content)
-    let strict_size_extensions: std::collections::HashSet<&str> = [".json"].into_iter().collect();
-    let strict_size_limit: u64 = 10 * 1024; // 10KB for JSON files
+/// Collect file paths without loading content (memory efficient)
+pub fn collect_file_paths(paths: &[PathBuf], walk: &WalkOptions) -> anyhow::Result<Vec<PathBuf>> {
+    let path_filter = walk.path_filter;
+    let max_file_bytes = walk.max_file_size_kb as u64 * 1024;
+    // Data files (JSON/YAML/TOML) can have their own limit; they are chunked by structure
+    let data_max_file_bytes = walk.data_max_file_size_kb.map_or(max_file_bytes, |kb| kb as u64 * 1024);
+    let mut budget = WalkBudget::new(walk.max_files, walk.max_total_size_mb);
+    let mut file_paths = Vec::new();
 
-    let allowed_extensions: Vec<&str> = file_types
-        .map(|ft| ft.iter().map(|s| s.as_str()).collect())
-        .unwrap_or(default_types);
+    let allowed_extensions: Vec<&str> = match walk.file_types {
+        Some(ft) => ft.iter().map(|s| s.as_str()).collect(),
+        None => default_file_types(),
+    };
 
     // Extensions to exclude (user can override with --exclude-types)
-    let excluded_extensions: Vec<&str> = exclude_types
+    let excluded_extensions: Vec<&str> = walk.exclude_types
         .map(|ft| ft.iter().map(|s| s.as_str()).collect())
         .unwrap_or_default();
 
     for path in paths {
-        if let Some(max) = max_files {
-            if file_paths.len() >= max {
-                tracing::info!("Reached max files limit ({})", max);
-                break;
-            }
+        if budget.exhausted() {
+            break;
         }
 
         if path.is_file() {
+            if !path_filter.allows(path, path) {
+                continue;
+            }
+            // Archives are expanded entry by entry when loading
+            if crate::archive::is_archive(path) {
+                if cfg!(feature = "archives") {
+                    budget.add(path.metadata().map_or(0, |m| m.len()));
+                    file_paths.push(path.clone());
+                } else {
+                    tracing::warn!("Skipping archive {} (rebuild with --features archives)", path.display());
+                }
+                continue;
+            }
             if let Some(ext) = path.extension() {
                 let ext_str = format!(".{}", ext.to_string_lossy());
 
@@ -473,51 +1655,29 @@ fn collect_file_paths(
                     continue;
                 }
 
-                // Check file size (stricter limit for JSON-like files)
-                if let Ok(metadata) = path.metadata() {
-                    let size_limit = if strict_size_extensions.contains(ext_str.as_str()) {
-                        strict_size_limit
-                    } else {
-                        max_file_bytes
-                    };
-                    if metadata.len() > size_limit {
-                        tracing::debug!("Skipping large file: {} ({}KB, limit {}KB)",
-                            path.display(), metadata.len() / 1024, size_limit / 1024);
-                        continue;
-                    }
+                // Check file size (data files have their own limit)
+                let size = path.metadata().map_or(0, |m| m.len());
+                let size_limit = if StructuredChunker::format_for(path).is_some() {
+                    data_max_file_bytes
+                } else {
+                    max_file_bytes
+                };
+                if size > size_limit {
+                    tracing::debug!("Skipping large file: {} ({}KB, limit {}KB)",
+                        path.display(), size / 1024, size_limit / 1024);
+                    continue;
                 }
+                budget.add(size);
                 file_paths.push(path.clone());
             }
         } else if path.is_dir() {
-            let walker = WalkBuilder::new(path)
-                .hidden(!include_hidden)
-                .git_ignore(true)
-                .git_global(true)
-                .git_exclude(true)
-                // Add common build/dependency directories to ignore
-                .add_custom_ignore_filename(".leannignore")
-                .filter_entry(|entry| {
-                    let name = entry.file_name().to_string_lossy();
-                    // Skip common build/dependency directories
-                    !matches!(name.as_ref(),
-                        "target" | "node_modules" | ".git" | "__pycache__" |
-                        "venv" | ".venv" | "dist" | "build" | ".next" |
-                        ".nuxt" | "vendor" | "Pods" | ".gradle" | ".cache" |
-                        "deps" | "_build" | ".elixir_ls" | ".hex" | "priv"
-                    )
-                })
-                .build();
-
-            for entry in walker.flatten() {
-                if let Some(max) = max_files {
-                    if file_paths.len() >= max {
-                        tracing::info!("Reached max files limit ({})", max);
-                        break;
-                    }
+            for entry in walk_dir(path, walk.include_hidden, walk.follow_symlinks) {
+                if budget.exhausted() {
+                    break;
                 }
 
                 let entry_path = entry.path();
-                if entry_path.is_file() {
+                if entry_path.is_file() && path_filter.allows(entry_path, path) {
                     if let Some(ext) = entry_path.extension() {
                         let ext_str = format!(".{}", ext.to_string_lossy());
 
@@ -530,19 +1690,19 @@ fn collect_file_paths(
                             continue;
                         }
 
-                        // Check file size (stricter limit for JSON-like files)
-                        if let Ok(metadata) = entry_path.metadata() {
-                            let size_limit = if strict_size_extensions.contains(ext_str.as_str()) {
-                                strict_size_limit
-                            } else {
-                                max_file_bytes
-                            };
-                            if metadata.len() > size_limit {
-                                tracing::debug!("Skipping large file: {} ({}KB, limit {}KB)",
-                                    entry_path.display(), metadata.len() / 1024, size_limit / 1024);
-                                continue;
-                            }
+                        // Check file size (data files have their own limit)
+                        let size = entry_path.metadata().map_or(0, |m| m.len());
+                        let size_limit = if StructuredChunker::format_for(entry_path).is_some() {
+                            data_max_file_bytes
+                        } else {
+                            max_file_bytes
+                        };
+                        if size > size_limit {
+                            tracing::debug!("Skipping large file: {} ({}KB, limit {}KB)",
+                                entry_path.display(), size / 1024, size_limit / 1024);
+                            continue;
                         }
+                        budget.add(size);
                         file_paths.push(entry_path.to_path_buf());
                     }
                 }
@@ -553,70 +1713,216 @@ fn collect_file_paths(
     Ok(file_paths)
 }
 
-/// Load documents from paths and chunk them
-pub fn load_documents(
-    paths: &[PathBuf],
-    chunk_size: usize,
-    chunk_overlap: usize,
-    file_types: Option<&[String]>,
-    include_hidden: bool,
-    chunking_strategy: ChunkingStrategy,
-) -> anyhow::Result<Vec<Chunk>> {
-    use ignore::WalkBuilder;
+/// Walk a documents directory, honoring .gitignore and .leannignore files and
+/// skipping hidden files (unless `include_hidden`), common build and
+/// dependency directories, and directories excluded by their `.leann.toml`
+///
+/// With `follow_symlinks`, linked directories are walked too; entries are
+/// tracked by canonical path so each real directory and file is visited once,
+/// which also stops link cycles.
+fn walk_dir(path: &std::path::Path, include_hidden: bool, follow_symlinks: bool) -> impl Iterator<Item = ignore::DirEntry> {
+    let visited: std::sync::Mutex<std::collections::HashSet<PathBuf>> = Default::default();
+    ignore::WalkBuilder::new(path)
+        .hidden(!include_hidden)
+        .follow_links(follow_symlinks)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .add_custom_ignore_filename(".leannignore")
+        .filter_entry(move |entry| {
+            let name = entry.file_name().to_string_lossy();
+            // Skip common build/dependency directories
+            if matches!(name.as_ref(),
+                "target" | "node_modules" | ".git" | "__pycache__" |
+                "venv" | ".venv" | "dist" | "build" | ".next" |
+                ".nuxt" | "vendor" | "Pods" | ".gradle" | ".cache" |
+                "deps" | "_build" | ".elixir_ls" | ".hex" | "priv"
+            ) {
+                return false;
+            }
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            if is_dir && is_excluded_dir(entry.path()) {
+                return false;
+            }
+            if follow_symlinks {
+                let Ok(real) = entry.path().canonicalize() else {
+                    return false;
+                };
+                let mut visited = visited.lock().unwrap_or_else(|e| e.into_inner());
+                if !visited.insert(real) {
+                    tracing::debug!("Skipping already visited {}", entry.path().display());
+                    return false;
+                }
+            }
+            true
+        })
+        .build()
+        .filter_map(|entry| entry.map_err(|e| tracing::debug!("Skipping walk entry: {}", e)).ok())
+}
+
+/// Caps on how much a file walk collects, so builds on huge or messy trees stay bounded
+struct WalkBudget {
+    max_files: Option<usize>,
+    max_bytes: Option<u64>,
+    files: usize,
+    bytes: u64,
+    reported: bool,
+}
+
+impl WalkBudget {
+    fn new(max_files: Option<usize>, max_total_size_mb: Option<usize>) -> Self {
+        Self {
+            max_files,
+            max_bytes: max_total_size_mb.map(|mb| mb as u64 * 1024 * 1024),
+            files: 0,
+            bytes: 0,
+            reported: false,
+        }
+    }
+
+    /// Count a collected file of `size` bytes
+    fn add(&mut self, size: u64) {
+        self.files += 1;
+        self.bytes += size;
+    }
+
+    /// Whether no more files should be collected (logged the first time a limit is hit)
+    fn exhausted(&mut self) -> bool {
+        let files_full = self.max_files.is_some_and(|max| self.files >= max);
+        let bytes_full = self.max_bytes.is_some_and(|max| self.bytes >= max);
+        if (files_full || bytes_full) && !self.reported {
+            self.reported = true;
+            if files_full {
+                tracing::info!("Reached max files limit ({})", self.files);
+            } else {
+                tracing::warn!(
+                    "Reached total size limit ({} MB) after {} files; remaining files are skipped",
+                    self.max_bytes.unwrap_or_default() / (1024 * 1024),
+                    self.files
+                );
+            }
+        }
+        files_full || bytes_full
+    }
+}
+
+/// Directory a build is written to, removed unless the build completes
+pub struct StagingDir {
+    pub path: PathBuf,
+    committed: bool,
+}
+
+impl StagingDir {
+    /// Create an empty staging directory, clearing one left by an interrupted build
+    pub fn create(path: PathBuf) -> anyhow::Result<Self> {
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        std::fs::create_dir_all(&path)?;
+        Ok(Self { path, committed: false })
+    }
+
+    /// Move the finished build into place at `index_dir`
+    pub fn commit(mut self, index_dir: &std::path::Path) -> anyhow::Result<()> {
+        commit_staged_build(index_dir)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// How `load_documents` walks the documents roots and chunks their files
+pub struct LoadOptions<'a> {
+    pub chunker: &'a SmartChunker,
+    /// Extensions to load (default: `default_file_types`)
+    pub file_types: Option<&'a [String]>,
+    pub include_hidden: bool,
+    pub follow_symlinks: bool,
+    pub enrichers: &'a EnrichmentPipeline,
+    pub instructions: &'a DocumentInstructions,
+    pub length_limit: Option<&'a ChunkLengthLimit>,
+    pub secret_scanner: &'a SecretScanner,
+    pub path_filter: &'a PathFilter,
+    /// Namespace every chunk is tagged with
+    pub namespace: Option<&'a str>,
+}
 
-    let chunker = SmartChunker::new(chunking_strategy, chunk_size, chunk_overlap);
+/// Load documents from paths and chunk them
+pub fn load_documents(paths: &[PathBuf], options: &LoadOptions) -> anyhow::Result<Vec<Chunk>> {
+    let LoadOptions {
+        chunker,
+        file_types,
+        include_hidden,
+        follow_symlinks,
+        enrichers,
+        instructions,
+        length_limit,
+        secret_scanner,
+        path_filter,
+        namespace,
+    } = *options;
     let mut chunks = Vec::new();
+    let mut length_stats = LengthStats::default();
+    let mut secret_stats = SecretStats::default();
     let mut chunk_id = 0u64;
+    let mut dir_overrides = DirOverrides::new(paths);
+    if let Some(namespace) = namespace {
+        dir_overrides = dir_overrides.with_metadata(NAMESPACE_FIELD, namespace.into());
+    }
 
-    // Default file types
-    #[cfg(feature = "pdf")]
-    let default_types = vec![
-        ".txt", ".md", ".py", ".js", ".ts", ".tsx", ".jsx", ".rs", ".go", ".java",
-        ".c", ".cpp", ".cc", ".h", ".hpp", ".json", ".yaml", ".yml", ".toml",
-        ".rb", ".php", ".swift", ".kt", ".scala", ".cs",
-        ".pdf",
-    ];
-    #[cfg(not(feature = "pdf"))]
-    let default_types = vec![
-        ".txt", ".md", ".py", ".js", ".ts", ".tsx", ".jsx", ".rs", ".go", ".java",
-        ".c", ".cpp", ".cc", ".h", ".hpp", ".json", ".yaml", ".yml", ".toml",
-        ".rb", ".php", ".swift", ".kt", ".scala", ".cs",
-    ];
+    let allowed_extensions: Vec<&str> = match file_types {
+        Some(ft) => ft.iter().map(|s| s.as_str()).collect(),
+        None => default_file_types(),
+    };
 
-    let allowed_extensions: Vec<&str> = file_types
-        .map(|ft| ft.iter().map(|s| s.as_str()).collect())
-        .unwrap_or(default_types);
+    // Chunk one file with its directory's .leann.toml settings
+    let mut load_file = |path: &std::path::Path, chunks: &mut Vec<Chunk>| -> anyhow::Result<()> {
+        let Some(content) = load_file_content(path) else {
+            return Ok(());
+        };
+        let dir_settings = dir_overrides.settings_for(path)?;
+        let dir_chunker = chunker.with_settings(&dir_settings);
+        let mut file_chunks = dir_chunker.as_ref().unwrap_or(chunker).chunk(&content, path, &mut chunk_id);
+        file_chunks = secret_scanner.apply(file_chunks, &mut secret_stats);
+        if let Some(limit) = length_limit {
+            file_chunks = limit.apply(file_chunks, &mut length_stats)?;
+        }
+        enrichers.enrich(path, &content, &mut file_chunks);
+        dir_settings.tag(&mut file_chunks);
+        instructions.tag(path, &mut file_chunks);
+        assign_stable_ids(&mut file_chunks, path);
+        chunks.extend(file_chunks);
+        Ok(())
+    };
 
     for path in paths {
         if path.is_file() {
             // Single file
+            if !path_filter.allows(path, path) {
+                continue;
+            }
             if let Some(ext) = path.extension() {
                 let ext_str = format!(".{}", ext.to_string_lossy());
                 if allowed_extensions.iter().any(|e| *e == ext_str) {
-                    if let Some(content) = load_file_content(path) {
-                        let file_chunks = chunker.chunk(&content, path, &mut chunk_id);
-                        chunks.extend(file_chunks);
-                    }
+                    load_file(path, &mut chunks)?;
                 }
             }
         } else if path.is_dir() {
-            // Directory - walk with gitignore support
-            let walker = WalkBuilder::new(path)
-                .hidden(!include_hidden)
-                .git_ignore(true)
-                .git_global(true)
-                .build();
-
-            for entry in walker.flatten() {
+            // Directory - walk the same files a build would
+            for entry in walk_dir(path, include_hidden, follow_symlinks) {
                 let entry_path = entry.path();
-                if entry_path.is_file() {
+                if entry_path.is_file() && path_filter.allows(entry_path, path) {
                     if let Some(ext) = entry_path.extension() {
                         let ext_str = format!(".{}", ext.to_string_lossy());
                         if allowed_extensions.iter().any(|e| *e == ext_str) {
-                            if let Some(content) = load_file_content(entry_path) {
-                                let file_chunks = chunker.chunk(&content, entry_path, &mut chunk_id);
-                                chunks.extend(file_chunks);
-                            }
+                            load_file(entry_path, &mut chunks)?;
                         }
                     }
                 }
@@ -624,11 +1930,24 @@ pub fn load_documents(
         }
     }
 
+    if length_stats.any() {
+        info!(
+            "Over-length chunks: {} truncated, {} split into {} pieces",
+            length_stats.truncated, length_stats.split, length_stats.split_pieces
+        );
+    }
+    if secret_stats.any() {
+        info!(
+            "Secrets: {} found in {} chunks from {} files ({} policy)",
+            secret_stats.total(), secret_stats.chunks, secret_stats.sources.len(), secret_scanner.policy
+        );
+    }
+
     Ok(chunks)
 }
 
 /// Load file content, handling different file types
-fn load_file_content(path: &std::path::Path) -> Option<String> {
+pub fn load_file_content(path: &std::path::Path) -> Option<String> {
     let ext = path.extension()
         .and_then(|e| e.to_str())
         .unwrap_or("")
@@ -659,3 +1978,66 @@ fn load_file_content(path: &std::path::Path) -> Option<String> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_chunk_size() {
+        // No known limit, or a size within it
+        assert_eq!(fit_chunk_size(256, 128, None, true), ChunkFit::Fits);
+        assert_eq!(fit_chunk_size(256, 128, Some(256), true), ChunkFit::Fits);
+        assert_eq!(fit_chunk_size(256, 128, Some(500), false), ChunkFit::Fits);
+
+        // Reduced to the limit, with the overlap at most half of it
+        assert_eq!(fit_chunk_size(512, 128, Some(200), true), ChunkFit::Reduced { chunk_size: 200, chunk_overlap: 100 });
+        assert_eq!(fit_chunk_size(512, 32, Some(200), true), ChunkFit::Reduced { chunk_size: 200, chunk_overlap: 32 });
+        assert_eq!(fit_chunk_size(512, 128, Some(0), true), ChunkFit::Reduced { chunk_size: 1, chunk_overlap: 0 });
+
+        // Without --auto-chunk-size the size is kept and warned about
+        assert_eq!(fit_chunk_size(512, 128, Some(200), false), ChunkFit::Over { max: 200 });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_follows_symlinks_once() {
+        let root = std::env::temp_dir().join(format!("leann-walk-symlinks-{}", std::process::id()));
+        let docs = root.join("docs");
+        std::fs::create_dir_all(docs.join("notes")).unwrap();
+        std::fs::create_dir_all(root.join("shared")).unwrap();
+        std::fs::write(docs.join("notes/a.md"), "a").unwrap();
+        std::fs::write(root.join("shared/b.md"), "b").unwrap();
+        // A linked tree outside the docs, a second link to it, and a cycle back to the root
+        std::os::unix::fs::symlink(root.join("shared"), docs.join("shared")).unwrap();
+        std::os::unix::fs::symlink(root.join("shared"), docs.join("notes/shared-again")).unwrap();
+        std::os::unix::fs::symlink(&docs, docs.join("notes/loop")).unwrap();
+
+        let files = |follow_symlinks: bool| {
+            let mut names: Vec<String> = walk_dir(&docs, false, follow_symlinks)
+                .filter(|e| e.path().is_file())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(files(false), vec!["a.md"]);
+        assert_eq!(files(true), vec!["a.md", "b.md"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_walk_budget() {
+        let mut budget = WalkBudget::new(None, Some(1));
+        budget.add(600 * 1024);
+        assert!(!budget.exhausted());
+        budget.add(600 * 1024);
+        assert!(budget.exhausted());
+
+        let mut budget = WalkBudget::new(Some(2), None);
+        budget.add(10);
+        budget.add(10);
+        assert!(budget.exhausted());
+    }
+}
@@ -0,0 +1,51 @@
+//! Cancel command - stop a build running inside `leann serve`
+//!
+//! Builds and updates started over the server's API stop after their current
+//! batch, leaving the index as it was and keeping the embeddings computed so
+//! far; running the same build again resumes from them. A build running in a
+//! terminal is stopped the same way with Ctrl-C.
+
+use anyhow::Context;
+use clap::Args;
+
+use crate::http::{check_network, create_client};
+
+#[derive(Args)]
+pub struct CancelArgs {
+    /// Index whose build to stop (default: every running build)
+    pub index_name: Option<String>,
+
+    /// URL of the `leann serve --allow-build` server running the build
+    #[arg(long, default_value = "http://127.0.0.1:8080")]
+    pub server: String,
+}
+
+pub async fn run(args: CancelArgs) -> anyhow::Result<()> {
+    let url = format!("{}/build/cancel", args.server.trim_end_matches('/'));
+    check_network("leann server", &url)?;
+    let response = create_client()
+        .post(&url)
+        .json(&serde_json::json!({ "index": args.index_name }))
+        .send()
+        .await
+        .with_context(|| format!("No leann server at {}; stop a build running in a terminal with Ctrl-C", args.server))?;
+    // The server answers 404 when no build is running and 403 without --allow-build
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("{} (HTTP {})", response.text().await.unwrap_or_default(), status.as_u16());
+    }
+    let body: serde_json::Value = response.json().await?;
+
+    let cancelled: Vec<&str> = body["cancelled"]
+        .as_array()
+        .map(|names| names.iter().filter_map(|n| n.as_str()).collect())
+        .unwrap_or_default();
+    for name in cancelled {
+        match name {
+            "" => println!("Cancelling a build started without an index name"),
+            name => println!("Cancelling build of '{}'", name),
+        }
+    }
+    println!("Builds stop after their current batch; run them again to resume.");
+    Ok(())
+}
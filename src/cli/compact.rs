@@ -0,0 +1,68 @@
+//! Compact command - drop deleted passages from an index's passage file
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use clap::Args;
+
+use crate::index::{find_index, IndexMeta, PassageStore};
+
+#[derive(Args)]
+pub struct CompactArgs {
+    /// Index name to compact
+    pub index_name: String,
+}
+
+pub async fn run(args: CompactArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let index_path = index_dir.join("documents.leann");
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+
+    let stats = PassageStore::compact(&index_path)?;
+    let removed_vectors = remove_orphaned_vectors(&index_path, &meta)?;
+    if stats.removed == 0 && removed_vectors == 0 {
+        println!("Index '{}' has no deleted passages to compact.", args.index_name);
+        return Ok(());
+    }
+
+    let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    println!(
+        "Compacted index '{}': removed {} passages, {:.2} MB → {:.2} MB",
+        args.index_name,
+        stats.removed,
+        mb(stats.bytes_before),
+        mb(stats.bytes_after)
+    );
+    if removed_vectors > 0 {
+        println!("  Removed {} vectors without a passage from the graph; updates reuse their keys.", removed_vectors);
+    } else if !meta.backend()?.info().supports_replace {
+        println!("  Their vectors stay in the graph (never returned) until the next 'leann build --force'.");
+    }
+
+    Ok(())
+}
+
+/// Remove vectors whose passages are gone (deleted before the backend could
+/// remove vectors) and that aren't free already; returns how many
+fn remove_orphaned_vectors(index_path: &Path, meta: &IndexMeta) -> anyhow::Result<usize> {
+    let backend = meta.backend()?;
+    let ids_path = index_path.with_extension("ids.txt");
+    if !backend.info().supports_replace || !ids_path.exists() {
+        return Ok(0);
+    }
+
+    let store = PassageStore::open(index_path)?;
+    let free: HashSet<u64> = backend.free_keys(index_path)?.into_iter().collect();
+    let keys: Vec<u64> = std::fs::read_to_string(&ids_path)?
+        .lines()
+        .enumerate()
+        .map(|(k, id)| (k as u64, id))
+        .filter(|(k, id)| !free.contains(k) && !store.contains(id))
+        .map(|(k, _)| k)
+        .collect();
+    drop(store);
+    if !keys.is_empty() {
+        backend.remove_from_index(&keys, index_path, meta.dimensions)?;
+    }
+    Ok(keys.len())
+}
@@ -2,7 +2,7 @@
 
 use clap::{Args, Subcommand};
 
-use crate::config::Config;
+use crate::config::{selected_profile, Config};
 
 #[derive(Args)]
 pub struct ConfigArgs {
@@ -37,6 +37,16 @@ pub async fn run(args: ConfigArgs) -> anyhow::Result<()> {
             } else {
                 println!("Config file: {} (not found, using defaults)", path.display());
             }
+            if let Some(profile) = selected_profile() {
+                println!("Profile: {}", profile);
+            }
+            if !config.profile.is_empty() {
+                let names: Vec<&str> = config.profile.keys().map(String::as_str).collect();
+                println!("Profiles: {}", names.join(", "));
+            }
+            if config.offline {
+                println!("offline = true");
+            }
             println!();
             println!("[embedding]");
             println!("provider = \"{}\"", config.embedding.provider);
@@ -54,10 +64,80 @@ pub async fn run(args: ConfigArgs) -> anyhow::Result<()> {
                 println!("batch_size = {}", batch_size);
             }
             println!();
+            println!("[llm]");
+            println!("provider = \"{}\"", config.llm.provider);
+            println!("model = \"{}\"", config.llm.model);
+            if let Some(host) = &config.llm.host {
+                println!("host = \"{}\"", host);
+            }
+            if let Some(base_url) = &config.llm.base_url {
+                println!("base_url = \"{}\"", base_url);
+            }
+            if config.llm.api_key.is_some() {
+                println!("api_key = \"***\"");
+            }
+            println!();
+            println!("[search]");
+            println!("top_k = {}", config.search.top_k);
+            println!("complexity = {}", config.search.complexity);
+            println!("hybrid_alpha = {}", config.search.hybrid_alpha);
+            println!();
             println!("[build]");
             println!("chunk_size = {}", config.build.chunk_size);
             println!("chunk_overlap = {}", config.build.chunk_overlap);
             println!("max_file_size_kb = {}", config.build.max_file_size_kb);
+            if let Some(include) = &config.build.include {
+                println!("include = {:?}", include);
+            }
+            if let Some(exclude) = &config.build.exclude {
+                println!("exclude = {:?}", exclude);
+            }
+            if config.prompts.ask.is_some() || config.prompts.react.is_some() {
+                println!();
+                println!("[prompts]");
+                if let Some(ask) = &config.prompts.ask {
+                    println!("ask = \"{}\"", ask.display());
+                }
+                if let Some(react) = &config.prompts.react {
+                    println!("react = \"{}\"", react.display());
+                }
+            }
+            println!();
+            println!("[analytics]");
+            println!("log_queries = {}", config.analytics.log_queries);
+            let http = &config.http;
+            if http.timeout.is_some()
+                || http.connect_timeout.is_some()
+                || http.proxy.is_some()
+                || http.ca_cert.is_some()
+                || http.max_idle_per_host.is_some()
+            {
+                println!();
+                println!("[http]");
+                if let Some(timeout) = http.timeout {
+                    println!("timeout = {}", timeout);
+                }
+                if let Some(connect_timeout) = http.connect_timeout {
+                    println!("connect_timeout = {}", connect_timeout);
+                }
+                if let Some(proxy) = &http.proxy {
+                    // Hide proxy passwords
+                    let shown = match reqwest::Url::parse(proxy) {
+                        Ok(mut url) if url.password().is_some() => {
+                            let _ = url.set_password(Some("***"));
+                            url.to_string()
+                        }
+                        _ => proxy.clone(),
+                    };
+                    println!("proxy = \"{}\"", shown);
+                }
+                if let Some(ca_cert) = &http.ca_cert {
+                    println!("ca_cert = \"{}\"", ca_cert.display());
+                }
+                if let Some(max_idle) = http.max_idle_per_host {
+                    println!("max_idle_per_host = {}", max_idle);
+                }
+            }
         }
 
         ConfigCommands::Init { force } => {
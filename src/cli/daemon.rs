@@ -0,0 +1,303 @@
+//! Daemon command - keep indexes loaded for fast CLI searches
+//!
+//! `leann daemon` listens on a unix socket (`~/.leann/daemon.sock` by
+//! default). While it runs, `leann search` sends its arguments there instead
+//! of loading the index, embedding provider, and BM25 statistics itself; the
+//! daemon keeps those loaded between searches and reloads an index when it is
+//! updated or rebuilt. Without a daemon, searches run in-process as before.
+//!
+//! Requests and responses are single lines of JSON.
+
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::sync::Arc;
+
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use crate::index::SearchResult;
+
+use super::search::{SearchArgs, SearchOutput};
+
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Socket to listen on (default: ~/.leann/daemon.sock)
+    #[arg(long, env = "LEANN_DAEMON_SOCKET")]
+    pub socket: Option<PathBuf>,
+
+    /// Stop the running daemon
+    #[arg(long)]
+    pub stop: bool,
+}
+
+/// A request to the daemon
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+enum DaemonRequest {
+    /// Run `leann search` on an index
+    Search {
+        /// Absolute path of the index directory
+        index_dir: PathBuf,
+        index_name: String,
+        args: Box<SearchArgs>,
+    },
+    /// Exit after answering
+    Shutdown,
+}
+
+/// The daemon's answer to a request
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum DaemonResponse {
+    Results {
+        results: Vec<SearchResult>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        corrected_query: Option<String>,
+    },
+    Error { message: String },
+    Ok,
+}
+
+/// Socket the daemon listens on: `LEANN_DAEMON_SOCKET`, else `~/.leann/daemon.sock`
+fn socket_path(socket: Option<PathBuf>) -> Option<PathBuf> {
+    socket
+        .or_else(|| std::env::var_os("LEANN_DAEMON_SOCKET").map(PathBuf::from))
+        .or_else(|| dirs::home_dir().map(|h| h.join(".leann").join("daemon.sock")))
+}
+
+/// Run a search through the daemon, if one is running
+///
+/// Returns `None` when no daemon answers (or `LEANN_NO_DAEMON` is set), so
+/// the caller searches in-process instead; errors reported by the daemon
+/// (e.g. an invalid filter) are returned as they are.
+pub(crate) async fn proxy_search(
+    index_dir: &Path,
+    index_name: &str,
+    args: &SearchArgs,
+) -> Option<anyhow::Result<SearchOutput>> {
+    if std::env::var_os("LEANN_NO_DAEMON").is_some() {
+        return None;
+    }
+    let socket = socket_path(None).filter(|p| p.exists())?;
+    // The daemon has its own working directory
+    let index_dir = index_dir.canonicalize().ok()?;
+
+    let request = DaemonRequest::Search {
+        index_dir,
+        index_name: index_name.to_string(),
+        args: Box::new(args.clone()),
+    };
+    match send(&socket, &request).await {
+        Ok(DaemonResponse::Results { results, corrected_query }) => {
+            tracing::debug!("Searched through the daemon at {}", socket.display());
+            Some(Ok(SearchOutput { results, corrected_query, explanation: None }))
+        }
+        Ok(DaemonResponse::Error { message }) => Some(Err(anyhow::anyhow!(message))),
+        Ok(DaemonResponse::Ok) => None,
+        Err(e) => {
+            tracing::debug!("Daemon at {} unavailable ({}); searching in-process", socket.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn send(socket: &Path, request: &DaemonRequest) -> anyhow::Result<DaemonResponse> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let stream = tokio::net::UnixStream::connect(socket).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+
+    let mut response = String::new();
+    BufReader::new(reader).read_line(&mut response).await?;
+    if response.is_empty() {
+        anyhow::bail!("connection closed without a response");
+    }
+    Ok(serde_json::from_str(&response)?)
+}
+
+#[cfg(not(unix))]
+async fn send(_socket: &Path, _request: &DaemonRequest) -> anyhow::Result<DaemonResponse> {
+    anyhow::bail!("the daemon needs unix sockets")
+}
+
+#[cfg(unix)]
+pub async fn run(args: DaemonArgs) -> anyhow::Result<()> {
+    use std::collections::HashMap;
+    use std::os::unix::fs::PermissionsExt;
+
+    use tokio::net::UnixListener;
+    use tokio::sync::{Mutex, Notify};
+
+    let socket = socket_path(args.socket).ok_or_else(|| anyhow::anyhow!("No home directory; pass --socket"))?;
+
+    if args.stop {
+        return match send(&socket, &DaemonRequest::Shutdown).await {
+            Ok(_) => {
+                println!("Stopped the daemon at {}", socket.display());
+                Ok(())
+            }
+            Err(_) => anyhow::bail!("No daemon is running at {}", socket.display()),
+        };
+    }
+
+    if socket.exists() {
+        if tokio::net::UnixStream::connect(&socket).await.is_ok() {
+            anyhow::bail!("A daemon is already running at {}", socket.display());
+        }
+        // Left behind by a daemon that didn't shut down cleanly
+        std::fs::remove_file(&socket)?;
+    }
+    if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(&socket)?;
+    // Requests carry API keys from the client's environment
+    std::fs::set_permissions(&socket, std::fs::Permissions::from_mode(0o600))?;
+
+    println!("LEANN daemon listening on {}", socket.display());
+    println!("  'leann search' uses it while it runs; stop it with 'leann daemon --stop' or Ctrl-C");
+
+    let state = Arc::new(DaemonState {
+        indexes: Mutex::new(HashMap::new()),
+        shutdown: Notify::new(),
+    });
+
+    // Connections are served concurrently on this thread; search futures
+    // borrow the loaded index, which keeps them from moving between threads
+    let connections = tokio::task::LocalSet::new();
+    connections
+        .run_until(async {
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => {
+                        let (stream, _) = match accepted {
+                            Ok(accepted) => accepted,
+                            Err(e) => {
+                                tracing::warn!("Failed to accept a connection: {}", e);
+                                continue;
+                            }
+                        };
+                        let state = state.clone();
+                        tokio::task::spawn_local(async move {
+                            if let Err(e) = handle_connection(stream, &state).await {
+                                tracing::warn!("Daemon connection failed: {}", e);
+                            }
+                        });
+                    }
+                    _ = state.shutdown.notified() => break,
+                    _ = tokio::signal::ctrl_c() => break,
+                }
+            }
+        })
+        .await;
+
+    let _ = std::fs::remove_file(&socket);
+    println!("Daemon stopped");
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn run(_args: DaemonArgs) -> anyhow::Result<()> {
+    anyhow::bail!("leann daemon needs unix sockets and isn't available on this platform")
+}
+
+/// Indexes kept loaded by the daemon
+#[cfg(unix)]
+struct DaemonState {
+    /// Loaded indexes by directory and embedding settings
+    indexes: tokio::sync::Mutex<std::collections::HashMap<String, Arc<HotIndex>>>,
+    shutdown: tokio::sync::Notify,
+}
+
+/// A loaded index and the modification time of its metadata when loaded
+#[cfg(unix)]
+struct HotIndex {
+    index: super::search::LoadedIndex,
+    /// Version of the index (and its feedback) when it was loaded
+    version: crate::index::IndexVersion,
+}
+
+#[cfg(unix)]
+async fn handle_connection(stream: tokio::net::UnixStream, state: &DaemonState) -> anyhow::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let (response, shutdown) = match serde_json::from_str::<DaemonRequest>(&line) {
+            Ok(DaemonRequest::Search { index_dir, index_name, args }) => {
+                let response = match search(state, &index_dir, &index_name, &args).await {
+                    Ok(output) => DaemonResponse::Results { results: output.results, corrected_query: output.corrected_query },
+                    Err(e) => DaemonResponse::Error { message: e.to_string() },
+                };
+                (response, false)
+            }
+            Ok(DaemonRequest::Shutdown) => (DaemonResponse::Ok, true),
+            Err(e) => (DaemonResponse::Error { message: format!("Invalid request: {}", e) }, false),
+        };
+        let mut line = serde_json::to_string(&response)?;
+        line.push('\n');
+        writer.write_all(line.as_bytes()).await?;
+        if shutdown {
+            state.shutdown.notify_one();
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Search an index, loading it (again, if it changed) when needed
+#[cfg(unix)]
+async fn search(
+    state: &DaemonState,
+    index_dir: &Path,
+    index_name: &str,
+    args: &SearchArgs,
+) -> anyhow::Result<SearchOutput> {
+    use super::search::{search_loaded, LoadedIndex};
+
+    // The embedding provider depends on the client's endpoint settings
+    let key = format!(
+        "{}\n{:?}\n{:?}\n{:?}\n{:?}",
+        index_dir.display(),
+        args.embedding_api_key,
+        args.embedding_api_base,
+        args.embedding_host,
+        args.jina_api_key
+    );
+    let version = crate::index::index_version(index_dir);
+
+    let cached = state.indexes.lock().await.get(&key).cloned();
+    let hot = match cached {
+        Some(hot) if hot.version == version => hot,
+        _ => {
+            let hot = Arc::new(HotIndex {
+                index: LoadedIndex::load(index_dir, index_name, args).await?,
+                version,
+            });
+            state.indexes.lock().await.insert(key, hot.clone());
+            hot
+        }
+    };
+    search_loaded(&hot.index, index_name, args).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_roundtrip() {
+        let request = DaemonRequest::Shutdown;
+        assert_eq!(serde_json::to_string(&request).unwrap(), r#"{"request":"shutdown"}"#);
+
+        let response: DaemonResponse = serde_json::from_str(r#"{"status":"error","message":"bad filter"}"#).unwrap();
+        assert!(matches!(response, DaemonResponse::Error { message } if message == "bad filter"));
+        let response: DaemonResponse = serde_json::from_str(r#"{"status":"results","results":[]}"#).unwrap();
+        assert!(matches!(response, DaemonResponse::Results { results, corrected_query: None } if results.is_empty()));
+    }
+}
@@ -0,0 +1,309 @@
+//! Dedup report command - find near-duplicate passages from stored embeddings
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use clap::Args;
+use indicatif::{ProgressBar, ProgressStyle};
+use tracing::info;
+
+use super::highlight;
+use crate::index::{find_index, IndexMeta, IndexSearcher, Passage};
+
+#[derive(Args)]
+pub struct DedupReportArgs {
+    /// Index name
+    pub index_name: String,
+
+    /// Minimum cosine similarity for a pair to count as a near-duplicate
+    #[arg(long, default_value = "0.95")]
+    pub threshold: f32,
+
+    /// Nearest neighbors checked per passage
+    #[arg(long, default_value = "10")]
+    pub neighbors: usize,
+
+    /// Search complexity for the neighbor searches
+    #[arg(long, default_value = "64")]
+    pub complexity: usize,
+
+    /// Compare every pair of passages instead of searching for neighbors
+    /// (exhaustive, quadratic in the passage count)
+    #[arg(long)]
+    pub exact: bool,
+
+    /// Only report pairs whose passages come from different files
+    #[arg(long)]
+    pub cross_file: bool,
+
+    /// Maximum number of file groups shown
+    #[arg(long, default_value = "20")]
+    pub limit: usize,
+
+    /// Example pairs shown per file group
+    #[arg(long, default_value = "3")]
+    pub examples: usize,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+}
+
+/// Two passages (indexes into the passage list) and their cosine similarity
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pair {
+    a: usize,
+    b: usize,
+    similarity: f32,
+}
+
+/// Near-duplicate pairs between two files (the same file for duplicates within it)
+#[derive(Debug)]
+struct FileGroup {
+    files: (String, String),
+    /// Pairs, most similar first
+    pairs: Vec<Pair>,
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// All pairs at or above `threshold`, comparing every pair of vectors
+fn exact_pairs(vectors: &[Vec<f32>], threshold: f32) -> Vec<Pair> {
+    let mut pairs = Vec::new();
+    for a in 0..vectors.len() {
+        for b in a + 1..vectors.len() {
+            let similarity = cosine(&vectors[a], &vectors[b]);
+            if similarity >= threshold {
+                pairs.push(Pair { a, b, similarity });
+            }
+        }
+    }
+    pairs
+}
+
+/// Pairs at or above `threshold` among each passage's nearest neighbors
+///
+/// Neighbors come from the index's own search, so pairs the graph misses are
+/// not reported; similarities are recomputed exactly from the stored vectors.
+fn ann_pairs(
+    searcher: &IndexSearcher,
+    passages: &[Passage],
+    vectors: &[Vec<f32>],
+    args: &DedupReportArgs,
+) -> anyhow::Result<Vec<Pair>> {
+    let positions: HashMap<&str, usize> = passages.iter().enumerate().map(|(i, p)| (p.id.as_str(), i)).collect();
+
+    let progress = ProgressBar::new(vectors.len() as u64);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} Comparing passages...")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+
+    let mut seen = HashSet::new();
+    let mut pairs = Vec::new();
+    for (i, vector) in vectors.iter().enumerate() {
+        for result in searcher.search(vector, args.neighbors + 1, args.complexity)? {
+            let Some(&j) = positions.get(result.id.as_str()) else { continue };
+            let (a, b) = (i.min(j), i.max(j));
+            if a == b || !seen.insert((a, b)) {
+                continue;
+            }
+            let similarity = cosine(&vectors[a], &vectors[b]);
+            if similarity >= args.threshold {
+                pairs.push(Pair { a, b, similarity });
+            }
+        }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+    Ok(pairs)
+}
+
+fn source(passage: &Passage) -> &str {
+    passage.metadata.get("source").and_then(|s| s.as_str()).unwrap_or("(no source)")
+}
+
+/// Group pairs by the (unordered) pair of files they come from, groups with
+/// the most pairs first
+fn group_by_files(pairs: &[Pair], passages: &[Passage], cross_file: bool) -> Vec<FileGroup> {
+    let mut groups: BTreeMap<(String, String), Vec<Pair>> = BTreeMap::new();
+    for pair in pairs {
+        let (a, b) = (source(&passages[pair.a]), source(&passages[pair.b]));
+        if cross_file && a == b {
+            continue;
+        }
+        let (pair, files) = if a <= b {
+            (*pair, (a.to_string(), b.to_string()))
+        } else {
+            (Pair { a: pair.b, b: pair.a, similarity: pair.similarity }, (b.to_string(), a.to_string()))
+        };
+        groups.entry(files).or_default().push(pair);
+    }
+
+    let mut groups: Vec<FileGroup> = groups
+        .into_iter()
+        .map(|(files, mut pairs)| {
+            pairs.sort_by(|x, y| y.similarity.total_cmp(&x.similarity));
+            FileGroup { files, pairs }
+        })
+        .collect();
+    groups.sort_by(|x, y| {
+        y.pairs.len().cmp(&x.pairs.len()).then_with(|| y.pairs[0].similarity.total_cmp(&x.pairs[0].similarity))
+    });
+    groups
+}
+
+pub async fn run(args: DedupReportArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let index_path = index_dir.join("documents.leann");
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    if meta.is_pruned {
+        anyhow::bail!(
+            "Index '{}' is pruned; duplicates are found from stored embeddings. Rebuild it without pruning.",
+            args.index_name
+        );
+    }
+
+    let searcher = IndexSearcher::load(&index_path, &meta)?;
+    let (passages, vectors): (Vec<Passage>, Vec<Vec<f32>>) =
+        searcher.passage_vectors().collect::<anyhow::Result<Vec<_>>>()?.into_iter().unzip();
+
+    info!(
+        "Comparing {} passages ({})",
+        passages.len(),
+        if args.exact { "all pairs" } else { "nearest neighbors" }
+    );
+    let pairs = if args.exact {
+        exact_pairs(&vectors, args.threshold)
+    } else {
+        ann_pairs(&searcher, &passages, &vectors, &args)?
+    };
+    let groups = group_by_files(&pairs, &passages, args.cross_file);
+
+    print_report(&args, &groups, &passages)
+}
+
+fn print_report(args: &DedupReportArgs, groups: &[FileGroup], passages: &[Passage]) -> anyhow::Result<()> {
+    let total: usize = groups.iter().map(|g| g.pairs.len()).sum();
+
+    if args.format == "json" {
+        let passage_json = |i: usize| {
+            let p = &passages[i];
+            serde_json::json!({ "id": p.id, "text": p.text, "metadata": p.metadata })
+        };
+        let json: Vec<serde_json::Value> = groups
+            .iter()
+            .take(args.limit)
+            .map(|g| {
+                serde_json::json!({
+                    "files": [g.files.0, g.files.1],
+                    "pair_count": g.pairs.len(),
+                    "max_similarity": g.pairs[0].similarity,
+                    "pairs": g.pairs.iter().take(args.examples).map(|p| serde_json::json!({
+                        "similarity": p.similarity,
+                        "a": passage_json(p.a),
+                        "b": passage_json(p.b),
+                    })).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "threshold": args.threshold,
+                "pair_count": total,
+                "groups": json,
+            }))?
+        );
+        return Ok(());
+    }
+
+    if groups.is_empty() {
+        println!(
+            "No near-duplicate passages (cosine >= {}) in index '{}'",
+            args.threshold, args.index_name
+        );
+        return Ok(());
+    }
+
+    println!(
+        "\n{} near-duplicate pairs (cosine >= {}) in {} file groups of index '{}':\n",
+        total,
+        args.threshold,
+        groups.len(),
+        args.index_name
+    );
+    for (i, group) in groups.iter().take(args.limit).enumerate() {
+        let (a, b) = &group.files;
+        let files = if a == b { format!("{} (within file)", a) } else { format!("{} <-> {}", a, b) };
+        println!(
+            "{}. {} - {} pairs, max similarity {:.3}",
+            i + 1,
+            files,
+            group.pairs.len(),
+            group.pairs[0].similarity
+        );
+        for pair in group.pairs.iter().take(args.examples) {
+            let (pa, pb) = (&passages[pair.a], &passages[pair.b]);
+            let location = |p: &Passage| highlight::location(&p.metadata).unwrap_or_else(|| p.id.clone());
+            println!("   {:.3}  {}  ~  {}", pair.similarity, location(pa), location(pb));
+            println!("          {}", highlight::snippet(&pa.text, &[], 100, false).replace('\n', " "));
+        }
+        println!();
+    }
+    if groups.len() > args.limit {
+        println!("... {} more file groups (use --limit to show more)", groups.len() - args.limit);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passage(id: &str, source: &str) -> Passage {
+        Passage {
+            id: id.to_string(),
+            text: String::new(),
+            metadata: serde_json::json!({ "source": source }),
+        }
+    }
+
+    #[test]
+    fn test_exact_pairs_above_threshold() {
+        let vectors = vec![vec![1.0, 0.0], vec![0.99, 0.05], vec![0.0, 1.0], vec![2.0, 0.0]];
+        let pairs = exact_pairs(&vectors, 0.95);
+        let found: Vec<(usize, usize)> = pairs.iter().map(|p| (p.a, p.b)).collect();
+        assert_eq!(found, [(0, 1), (0, 3), (1, 3)]);
+        assert!((pairs[1].similarity - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_group_by_files() {
+        let passages = [passage("0", "b.rs"), passage("1", "a.rs"), passage("2", "a.rs"), passage("3", "b.rs")];
+        let pairs = [
+            Pair { a: 0, b: 1, similarity: 0.96 },
+            Pair { a: 1, b: 3, similarity: 0.99 },
+            Pair { a: 1, b: 2, similarity: 0.97 },
+        ];
+
+        let groups = group_by_files(&pairs, &passages, false);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].files, ("a.rs".to_string(), "b.rs".to_string()));
+        assert_eq!(groups[0].pairs, [Pair { a: 1, b: 3, similarity: 0.99 }, Pair { a: 1, b: 0, similarity: 0.96 }]);
+        assert_eq!(groups[1].files, ("a.rs".to_string(), "a.rs".to_string()));
+
+        let groups = group_by_files(&pairs, &passages, true);
+        assert_eq!(groups.len(), 1);
+    }
+}
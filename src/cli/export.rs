@@ -0,0 +1,150 @@
+//! Export command - push an index's passages and vectors to another vector database
+
+use std::path::PathBuf;
+
+use clap::Args;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::export::QdrantExporter;
+use crate::index::{find_index, IndexMeta, IndexSearcher, MetadataFilter, Passage};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Index name to export
+    pub index_name: String,
+
+    /// Where to export: a Qdrant collection, or a Parquet file (loadable into LanceDB)
+    #[arg(long, value_parser = ["qdrant", "parquet"])]
+    pub target: String,
+
+    /// Qdrant URL
+    #[arg(long, default_value = "http://localhost:6333")]
+    pub url: String,
+
+    /// Qdrant collection (default: the index name)
+    #[arg(long)]
+    pub collection: Option<String>,
+
+    /// Qdrant API key
+    #[arg(long, env = "QDRANT_API_KEY")]
+    pub api_key: Option<String>,
+
+    /// Drop and recreate the Qdrant collection if it exists
+    #[arg(long)]
+    pub recreate: bool,
+
+    /// Parquet file to write (default: <index>.parquet)
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+
+    /// Only export passages matching a metadata filter (e.g. "source:*.rs")
+    #[arg(long)]
+    pub filter: Option<String>,
+
+    /// Passages per Qdrant request or Parquet row group
+    #[arg(long, default_value = "256")]
+    pub batch_size: usize,
+}
+
+/// Destination for exported batches
+enum Sink {
+    Qdrant(QdrantExporter),
+    #[cfg(feature = "parquet")]
+    Parquet(Box<crate::export::ParquetExporter>),
+}
+
+impl Sink {
+    async fn write(&mut self, batch: &[(Passage, Vec<f32>)]) -> anyhow::Result<()> {
+        match self {
+            Sink::Qdrant(exporter) => exporter.upsert(batch).await,
+            #[cfg(feature = "parquet")]
+            Sink::Parquet(exporter) => exporter.write(batch),
+        }
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Sink::Qdrant(_) => Ok(()),
+            #[cfg(feature = "parquet")]
+            Sink::Parquet(exporter) => exporter.finish(),
+        }
+    }
+}
+
+pub async fn run(args: ExportArgs) -> anyhow::Result<()> {
+    if args.batch_size == 0 {
+        anyhow::bail!("--batch-size must be at least 1");
+    }
+
+    let index_dir = find_index(&args.index_name)?;
+    let meta_path = index_dir.join("documents.leann.meta.json");
+    let index_path = index_dir.join("documents.leann");
+    let meta = IndexMeta::load(&meta_path)?;
+
+    let scope = args.filter.as_deref().map(MetadataFilter::try_parse).transpose()?;
+    let searcher = IndexSearcher::load_scoped(&index_path, &meta, scope)?;
+
+    let (mut sink, destination) = match args.target.as_str() {
+        "qdrant" => {
+            let collection = args.collection.as_deref().unwrap_or(&args.index_name);
+            let exporter = QdrantExporter::new(&args.url, collection, args.api_key.clone())?;
+            exporter.prepare_collection(meta.dimensions, args.recreate).await?;
+            (Sink::Qdrant(exporter), format!("Qdrant collection '{}' at {}", collection, args.url))
+        }
+        _ => parquet_sink(&args, meta.dimensions)?,
+    };
+
+    let progress = ProgressBar::new(searcher.scoped_len() as u64);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} Exporting passages...")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+
+    let mut exported = 0;
+    let mut batch = Vec::with_capacity(args.batch_size);
+    for point in searcher.passage_vectors() {
+        let point = match point {
+            Err(e) if meta.is_pruned => {
+                anyhow::bail!("{} (the index is pruned; rebuild it to export its vectors)", e)
+            }
+            point => point?,
+        };
+        batch.push(point);
+        if batch.len() == args.batch_size {
+            sink.write(&batch).await?;
+            exported += batch.len();
+            progress.inc(batch.len() as u64);
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        sink.write(&batch).await?;
+        exported += batch.len();
+    }
+    sink.finish()?;
+    progress.finish_and_clear();
+
+    println!(
+        "Exported {} passages ({} dimensions) from index '{}' to {}",
+        exported, meta.dimensions, args.index_name, destination
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+fn parquet_sink(args: &ExportArgs, dimensions: usize) -> anyhow::Result<(Sink, String)> {
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(format!("{}.parquet", args.index_name)));
+    let exporter = crate::export::ParquetExporter::create(&output, dimensions)?;
+    Ok((Sink::Parquet(Box::new(exporter)), output.display().to_string()))
+}
+
+#[cfg(not(feature = "parquet"))]
+fn parquet_sink(_args: &ExportArgs, _dimensions: usize) -> anyhow::Result<(Sink, String)> {
+    anyhow::bail!("Parquet export not enabled. Rebuild with --features parquet")
+}
@@ -0,0 +1,124 @@
+//! Feedback command - mark search results good or bad for a query, so later
+//! searches for similar queries rank them accordingly
+
+use clap::Args;
+
+use crate::config::Config;
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::index::{feedback_path, find_index, read_feedback, record_feedback, FeedbackEntry, IndexMeta, PassageStore};
+
+#[derive(Args)]
+pub struct FeedbackArgs {
+    /// Index name the passages belong to
+    pub index_name: String,
+
+    /// Query the passages were judged for
+    #[arg(long, required_unless_present_any = ["list", "clear"])]
+    pub query: Option<String>,
+
+    /// Id of a passage that answers the query (repeatable)
+    #[arg(long)]
+    pub good: Vec<String>,
+
+    /// Id of a passage that doesn't answer the query (repeatable)
+    #[arg(long)]
+    pub bad: Vec<String>,
+
+    /// List the feedback given for the index
+    #[arg(long, conflicts_with_all = ["query", "clear"])]
+    pub list: bool,
+
+    /// Delete all feedback given for the index
+    #[arg(long, conflicts_with = "query")]
+    pub clear: bool,
+
+    /// API key for embedding service
+    #[arg(long, env = "OPENAI_API_KEY")]
+    pub embedding_api_key: Option<String>,
+
+    /// OpenAI API base URL
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub embedding_api_base: Option<String>,
+
+    /// Ollama host for embeddings
+    #[arg(long, env = "OLLAMA_HOST")]
+    pub embedding_host: Option<String>,
+}
+
+pub async fn run(args: FeedbackArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+
+    if args.list {
+        let entries = read_feedback(&index_dir)?;
+        if entries.is_empty() {
+            println!("No feedback given for index '{}'.", args.index_name);
+        }
+        for entry in &entries {
+            println!("{}  {}", crate::time::format_rfc3339(entry.timestamp), entry.query);
+            for id in &entry.good {
+                println!("  + {}", id);
+            }
+            for id in &entry.bad {
+                println!("  - {}", id);
+            }
+        }
+        return Ok(());
+    }
+
+    if args.clear {
+        let path = feedback_path(&index_dir);
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+            println!("Cleared the feedback for index '{}'.", args.index_name);
+        } else {
+            println!("No feedback given for index '{}'.", args.index_name);
+        }
+        return Ok(());
+    }
+
+    let Some(query) = args.query else {
+        anyhow::bail!("--query is required");
+    };
+    if args.good.is_empty() && args.bad.is_empty() {
+        anyhow::bail!("Give at least one passage id with --good or --bad");
+    }
+    if let Some(id) = args.good.iter().find(|id| args.bad.contains(id)) {
+        anyhow::bail!("Passage '{}' is marked both good and bad", id);
+    }
+
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    let store = PassageStore::open(&index_dir.join("documents.leann"))?;
+    if let Some(id) = args.good.iter().chain(&args.bad).find(|id| !store.contains(id)) {
+        anyhow::bail!("No passage '{}' in index '{}' (ids are shown by `leann search --format json`)", id, args.index_name);
+    }
+
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key,
+        api_base: args.embedding_api_base,
+        host: args.embedding_host,
+        ..Default::default()
+    };
+    let config = Config::load();
+    let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &config.embedding).await?;
+    let embedding = embedding_provider
+        .embed_for_task(&[query.as_str()], EmbeddingTask::Query, &meta.embedding_template(EmbeddingTask::Query))
+        .await?
+        .remove(0);
+
+    let entry = FeedbackEntry {
+        timestamp: crate::time::now_unix(),
+        query,
+        embedding,
+        good: args.good,
+        bad: args.bad,
+    };
+    record_feedback(&index_dir, &entry)?;
+    println!(
+        "Recorded {} good and {} bad passages for \"{}\" in {}",
+        entry.good.len(),
+        entry.bad.len(),
+        entry.query,
+        feedback_path(&index_dir).display()
+    );
+    Ok(())
+}
@@ -0,0 +1,400 @@
+//! Graph command - query the knowledge graph of an index, and the extraction
+//! that builds it
+//!
+//! `leann build --graph code` links functions to the functions they call,
+//! found with patterns over code chunks (Rust, Python, JavaScript/TypeScript,
+//! Go). `--graph llm` also asks an LLM for the `subject | relation | object`
+//! triples stated in each non-code chunk. The graph is stored next to the
+//! index; `leann graph query` shows an entity and its neighbors, and `leann ask
+//! --graph` adds passages about the neighbors of the entities it retrieves.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use clap::{Args, Subcommand};
+use regex::Regex;
+
+use super::highlight;
+use crate::chunker::{is_code_file, Chunk};
+use crate::index::{find_index, IndexMeta, IndexSearcher, KnowledgeGraph, Passage, SearchResult};
+use crate::llm::LlmProvider;
+
+/// Function definitions at the start of a line: `fn`, `def`, `function`, `func`
+/// (with Go method receivers)
+static DEFINITION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*(?:export\s+)?(?:pub(?:\([^)]*\))?\s+)?(?:async\s+)?(?:unsafe\s+)?(?:fn|def|function|func)\s+(?:\([^)]*\)\s*)?([A-Za-z_]\w*)",
+    )
+    .unwrap()
+});
+
+/// Calls: a name followed by an opening parenthesis
+static CALL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b([A-Za-z_]\w*)\s*\(").unwrap());
+
+/// Entity names shorter than this are too ambiguous to link
+const MIN_NAME_LEN: usize = 3;
+
+/// Entity names longer than this are sentences, not names
+const MAX_NAME_LEN: usize = 80;
+
+#[derive(Args)]
+pub struct GraphArgs {
+    #[command(subcommand)]
+    pub command: GraphCommands,
+}
+
+#[derive(Subcommand)]
+pub enum GraphCommands {
+    /// Show an entity, its relations, and passages about it and its neighbors
+    Query {
+        /// Index name
+        index_name: String,
+
+        /// Entity name (case-insensitive; a unique partial name also matches)
+        entity: String,
+
+        /// Relations followed from the entity for neighbors
+        #[arg(long, default_value = "1")]
+        hops: usize,
+
+        /// Passages shown
+        #[arg(long, default_value = "5")]
+        passages: usize,
+
+        /// Output format (text, json)
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+}
+
+/// Builds a knowledge graph from chunks as they are indexed
+pub(super) struct GraphExtractor<'a> {
+    /// LLM for relations in non-code chunks, if any
+    llm: Option<&'a LlmProvider>,
+    graph: KnowledgeGraph,
+    /// Functions defined, with the passage defining them
+    definitions: Vec<(String, String)>,
+    /// Calls as (caller, callee, passage); linked once all definitions are known
+    calls: Vec<(String, String, String)>,
+}
+
+impl<'a> GraphExtractor<'a> {
+    pub(super) fn new(llm: Option<&'a LlmProvider>) -> Self {
+        Self { llm, graph: KnowledgeGraph::default(), definitions: Vec::new(), calls: Vec::new() }
+    }
+
+    /// Extract entities and relations from one file's chunks
+    pub(super) async fn add_chunks(&mut self, path: &Path, chunks: &[Chunk]) {
+        if is_code_file(path) {
+            for chunk in chunks {
+                let (definitions, calls) = code_relations(&chunk.text);
+                self.definitions.extend(definitions.into_iter().map(|name| (name, chunk.id.clone())));
+                self.calls.extend(calls.into_iter().map(|(caller, callee)| (caller, callee, chunk.id.clone())));
+            }
+            return;
+        }
+        let Some(llm) = self.llm else {
+            return;
+        };
+        for chunk in chunks {
+            match llm.generate(&extraction_prompt(&chunk.text)).await {
+                Ok(reply) => {
+                    for (subject, relation, object) in parse_triples(&reply) {
+                        let from = self.graph.add_node(&subject, "entity", &chunk.id);
+                        let to = self.graph.add_node(&object, "entity", &chunk.id);
+                        self.graph.add_edge(from, &relation, to, &chunk.id);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to extract relations from {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Link calls to the functions they reach and return the graph
+    pub(super) fn finish(mut self) -> KnowledgeGraph {
+        for (name, passage) in &self.definitions {
+            self.graph.add_node(name, "function", passage);
+        }
+        let defined: HashSet<String> = self.definitions.iter().map(|(name, _)| name.to_lowercase()).collect();
+        for (caller, callee, passage) in &self.calls {
+            if defined.contains(&callee.to_lowercase()) {
+                let (from, to) = (self.graph.find(caller), self.graph.find(callee));
+                if let (Some(from), Some(to)) = (from, to) {
+                    self.graph.add_edge(from, "calls", to, passage);
+                }
+            }
+        }
+        self.graph
+    }
+}
+
+/// Functions defined in a code chunk, and calls as (caller, callee) made
+/// within those definitions
+fn code_relations(text: &str) -> (Vec<String>, Vec<(String, String)>) {
+    let mut definitions = Vec::new();
+    let mut calls = Vec::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        if let Some(name) = DEFINITION.captures(line).map(|c| c[1].to_string()) {
+            if name.len() >= MIN_NAME_LEN {
+                definitions.push(name.clone());
+                current = Some(name);
+            } else {
+                current = None;
+            }
+            continue;
+        }
+        let Some(caller) = &current else {
+            continue;
+        };
+        for callee in CALL.captures_iter(line).map(|c| c[1].to_string()) {
+            if callee.len() >= MIN_NAME_LEN && callee != *caller && !calls.iter().any(|(a, b)| a == caller && *b == callee) {
+                calls.push((caller.clone(), callee));
+            }
+        }
+    }
+    (definitions, calls)
+}
+
+fn extraction_prompt(text: &str) -> String {
+    format!(
+        "Extract the entities (people, organizations, places, products, concepts) and the relations between them \
+         that the following passage states. Reply with one relation per line as `subject | relation | object`, \
+         using short names and a short verb phrase for the relation, and nothing else.\n\n---\n{}\n---\n",
+        text.trim()
+    )
+}
+
+/// `subject | relation | object` lines of an LLM reply
+fn parse_triples(reply: &str) -> Vec<(String, String, String)> {
+    reply
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_start_matches(['-', '*']).trim().trim_matches('`');
+            let parts: Vec<&str> = line.split('|').map(str::trim).collect();
+            let [subject, relation, object] = parts[..] else {
+                return None;
+            };
+            let valid = |s: &str| !s.is_empty() && s.len() <= MAX_NAME_LEN;
+            (valid(subject) && valid(relation) && valid(object))
+                .then(|| (subject.to_string(), relation.to_lowercase(), object.to_string()))
+        })
+        .collect()
+}
+
+/// Passages about the neighbors of the entities in the question and in
+/// `results`, at most `limit` and none already in `results`, nearest first
+pub(crate) fn neighbor_passages(
+    graph: &KnowledgeGraph,
+    searcher: &IndexSearcher,
+    question: &str,
+    results: &[SearchResult],
+    limit: usize,
+) -> Vec<SearchResult> {
+    let retrieved: HashSet<&str> = results.iter().map(|r| r.id.as_str()).collect();
+    let mentioned = graph.mentioned_in(question);
+    let mut seeds = mentioned.clone();
+    seeds.extend(graph.in_passages(&retrieved));
+    seeds.sort_unstable();
+    seeds.dedup();
+
+    // Passages of entities the question names come first, then their neighbors'
+    let mut added: Vec<SearchResult> = Vec::new();
+    let mut seen = retrieved.clone();
+    let nodes = mentioned.into_iter().map(|n| (n, 0)).chain(graph.neighbors(&seeds, 1));
+    for (node, distance) in nodes {
+        for id in &graph.nodes()[node].passages {
+            if added.len() >= limit {
+                return added;
+            }
+            if !seen.insert(id.as_str()) {
+                continue;
+            }
+            match searcher.passage(id) {
+                Ok(passage) => added.push(SearchResult {
+                    id: passage.id,
+                    // Graph passages rank after every retrieved one
+                    score: 0.0,
+                    text: passage.text,
+                    metadata: with_graph_entity(passage.metadata, &graph.nodes()[node].name, distance),
+                }),
+                Err(e) => tracing::debug!("Skipping graph passage {}: {}", id, e),
+            }
+        }
+    }
+    added
+}
+
+/// Metadata noting the entity a passage was added for
+fn with_graph_entity(mut metadata: serde_json::Value, entity: &str, distance: usize) -> serde_json::Value {
+    if let Some(obj) = metadata.as_object_mut() {
+        obj.insert("graph_entity".to_string(), entity.into());
+        obj.insert("graph_hops".to_string(), distance.into());
+    }
+    metadata
+}
+
+pub async fn run(args: GraphArgs) -> anyhow::Result<()> {
+    match args.command {
+        GraphCommands::Query { index_name, entity, hops, passages, format } => {
+            query(&index_name, &entity, hops, passages, &format)
+        }
+    }
+}
+
+/// Load an index's graph, failing if it was built without one
+pub(crate) fn load_graph(index_name: &str, index_path: &Path) -> anyhow::Result<KnowledgeGraph> {
+    KnowledgeGraph::load(index_path)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Index '{}' has no knowledge graph. Rebuild it with --graph code (or --graph llm).",
+            index_name
+        )
+    })
+}
+
+fn query(index_name: &str, entity: &str, hops: usize, limit: usize, format: &str) -> anyhow::Result<()> {
+    let index_dir = find_index(index_name)?;
+    let index_path = index_dir.join("documents.leann");
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    let graph = load_graph(index_name, &index_path)?;
+
+    let node = match graph.find(entity) {
+        Some(node) => node,
+        None => match graph.find_containing(entity)[..] {
+            [node] => node,
+            [] => anyhow::bail!("No entity named '{}' in the graph of index '{}'", entity, index_name),
+            ref candidates => {
+                let names: Vec<&str> = candidates.iter().take(10).map(|&n| graph.nodes()[n].name.as_str()).collect();
+                anyhow::bail!("'{}' matches {} entities: {}", entity, candidates.len(), names.join(", "))
+            }
+        },
+    };
+
+    let searcher = IndexSearcher::load(&index_path, &meta)?;
+    let neighbors = graph.neighbors(&[node], hops);
+    let mut passages: Vec<(Passage, &str)> = Vec::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+    for n in std::iter::once(node).chain(neighbors.iter().map(|(n, _)| *n)) {
+        let name = graph.nodes()[n].name.as_str();
+        for id in &graph.nodes()[n].passages {
+            if passages.len() < limit && seen.insert(id.as_str()) {
+                if let Ok(passage) = searcher.passage(id) {
+                    passages.push((passage, name));
+                }
+            }
+        }
+    }
+
+    let target = &graph.nodes()[node];
+    let relations: Vec<(String, &str, bool)> = graph
+        .relations(node)
+        .map(|(edge, other, outgoing)| (edge.relation.clone(), graph.nodes()[other].name.as_str(), outgoing))
+        .collect();
+
+    if format == "json" {
+        let json = serde_json::json!({
+            "entity": target.name,
+            "kind": target.kind,
+            "relations": relations.iter().map(|(relation, other, outgoing)| {
+                let (from, to) = if *outgoing { (target.name.as_str(), *other) } else { (*other, target.name.as_str()) };
+                serde_json::json!({ "from": from, "relation": relation, "to": to })
+            }).collect::<Vec<_>>(),
+            "neighbors": neighbors.iter().map(|(n, distance)| serde_json::json!({
+                "entity": graph.nodes()[*n].name,
+                "kind": graph.nodes()[*n].kind,
+                "hops": distance,
+            })).collect::<Vec<_>>(),
+            "passages": passages.iter().map(|(p, entity)| serde_json::json!({
+                "id": p.id,
+                "entity": entity,
+                "text": p.text,
+                "metadata": p.metadata,
+            })).collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    println!("\n{} ({}) - {} passages\n", target.name, target.kind, target.passages.len());
+    for (relation, other, outgoing) in &relations {
+        if *outgoing {
+            println!("  {} {} {}", target.name, relation, other);
+        } else {
+            println!("  {} {} {}", other, relation, target.name);
+        }
+    }
+    if relations.is_empty() {
+        println!("  (no relations)");
+    }
+    if !neighbors.is_empty() && hops > 1 {
+        let names: Vec<String> = neighbors
+            .iter()
+            .map(|(n, distance)| format!("{} ({})", graph.nodes()[*n].name, distance))
+            .collect();
+        println!("\nNeighbors: {}", names.join(", "));
+    }
+    if !passages.is_empty() {
+        println!("\nPassages:");
+        for (passage, entity) in &passages {
+            let location = highlight::location(&passage.metadata).unwrap_or_else(|| passage.id.clone());
+            let snippet = highlight::snippet(&passage.text, &[], 100, false).replace('\n', " ");
+            println!("  - [{}] {}  {}", entity, location, snippet);
+        }
+    }
+    println!();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_relations() {
+        let code = "pub fn evaluate(input: &str) -> f64 {\n    let tokens = tokenize_expression(input);\n    parse(&tokens)\n}\n\n\
+                    def helper(x):\n    return evaluate(x) + len(x)\n";
+        let (definitions, calls) = code_relations(code);
+        assert_eq!(definitions, ["evaluate", "helper"]);
+        assert_eq!(
+            calls,
+            [
+                ("evaluate".to_string(), "tokenize_expression".to_string()),
+                ("evaluate".to_string(), "parse".to_string()),
+                ("helper".to_string(), "evaluate".to_string()),
+                ("helper".to_string(), "len".to_string()),
+            ]
+        );
+        let (definitions, _) = code_relations("func (s *Server) HandleSearch(w http.ResponseWriter) {");
+        assert_eq!(definitions, ["HandleSearch"]);
+    }
+
+    #[tokio::test]
+    async fn test_extractor_links_defined_callees() {
+        let chunk = |id: &str, text: &str| Chunk { id: id.to_string(), text: text.to_string(), metadata: serde_json::json!({}) };
+        let mut extractor = GraphExtractor::new(None);
+        let chunks = [
+            chunk("a", "fn evaluate(input: &str) {\n    parse(input);\n    println(input);\n}"),
+            chunk("b", "fn parse(input: &str) {\n    next_token(input)\n}"),
+        ];
+        extractor.add_chunks(Path::new("calc.rs"), &chunks).await;
+        let graph = extractor.finish();
+        let names: Vec<&str> = graph.nodes().iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["evaluate", "parse"]);
+        assert_eq!(graph.edges().len(), 1);
+        assert_eq!(graph.edges()[0].relation, "calls");
+        assert_eq!(graph.edges()[0].passages, ["a"]);
+    }
+
+    #[test]
+    fn test_parse_triples() {
+        let reply = "Here you go:\n- Marie Curie | discovered | Polonium\n`Polonium | Named After | Poland`\nbad | line\n";
+        assert_eq!(
+            parse_triples(reply),
+            [
+                ("Marie Curie".to_string(), "discovered".to_string(), "Polonium".to_string()),
+                ("Polonium".to_string(), "named after".to_string(), "Poland".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! Grep command - exact pattern matches and semantic search over an index's sources, interleaved
+
+use std::collections::BTreeSet;
+
+use clap::Args;
+use regex::{Regex, RegexBuilder};
+use tracing::{debug, info};
+
+use super::highlight;
+use crate::config::Config;
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::index::{resolve_index, IndexMeta, IndexSearcher, PassageStore, RecomputeSearcher, SearchOptions, SearchResult};
+
+#[derive(Args)]
+pub struct GrepArgs {
+    /// Regular expression to match exactly in the indexed files
+    pub pattern: String,
+
+    /// What you are looking for, in plain words, for the semantic search
+    /// (defaults to the pattern itself)
+    pub description: Option<String>,
+
+    /// Index name or s3://bucket/prefix URL to search (defaults to current directory name)
+    #[arg(short, long)]
+    pub index: Option<String>,
+
+    /// Treat the pattern as a literal string
+    #[arg(short = 'F', long)]
+    pub fixed_strings: bool,
+
+    /// Match the pattern case-insensitively
+    #[arg(long)]
+    pub ignore_case: bool,
+
+    /// Maximum exact matches to report
+    #[arg(long, default_value = "20")]
+    pub max_matches: usize,
+
+    /// Number of semantic results (default: `[search] top_k` in config.toml, else 5)
+    #[arg(long)]
+    pub top_k: Option<usize>,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+
+    /// Disable colored highlighting of matches
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// API key for embedding service
+    #[arg(long, env = "OPENAI_API_KEY")]
+    pub embedding_api_key: Option<String>,
+
+    /// OpenAI API base URL
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub embedding_api_base: Option<String>,
+
+    /// Ollama host for embeddings
+    #[arg(long, env = "OLLAMA_HOST")]
+    pub embedding_host: Option<String>,
+}
+
+/// A line matching the pattern
+#[derive(Debug, Clone, PartialEq)]
+struct ExactHit {
+    source: String,
+    /// 1-based line number
+    line: usize,
+    text: String,
+    /// Byte ranges of the matches within `text`
+    matches: Vec<(usize, usize)>,
+}
+
+/// One entry of the combined result list
+#[derive(Debug)]
+enum GrepHit {
+    /// An exact match inside a passage the semantic search also found
+    Both { exact: ExactHit, score: f32 },
+    Exact(ExactHit),
+    Semantic(SearchResult),
+}
+
+/// Whether a semantic result's passage covers `hit`'s line
+fn covers(result: &SearchResult, hit: &ExactHit) -> bool {
+    let meta = &result.metadata;
+    if meta.get("source").and_then(|s| s.as_str()) != Some(hit.source.as_str()) {
+        return false;
+    }
+    match (
+        meta.get("start_line").and_then(|v| v.as_u64()),
+        meta.get("end_line").and_then(|v| v.as_u64()),
+    ) {
+        (Some(start), Some(end)) => (start..=end).contains(&(hit.line as u64)),
+        (Some(start), None) => start == hit.line as u64,
+        _ => false,
+    }
+}
+
+/// Combine exact and semantic hits: exact matches inside semantic results
+/// first (by score), then the remaining hits of each kind alternately
+fn interleave(exact: Vec<ExactHit>, semantic: Vec<SearchResult>) -> Vec<GrepHit> {
+    let mut both = Vec::new();
+    let mut exact_only = Vec::new();
+    let mut matched = vec![false; semantic.len()];
+    for hit in exact {
+        match semantic.iter().position(|r| covers(r, &hit)) {
+            Some(i) => {
+                matched[i] = true;
+                both.push((semantic[i].score, hit));
+            }
+            None => exact_only.push(hit),
+        }
+    }
+    both.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut hits: Vec<GrepHit> = both.into_iter().map(|(score, exact)| GrepHit::Both { exact, score }).collect();
+    let mut exact_only = exact_only.into_iter();
+    let mut semantic_only = semantic.into_iter().zip(matched).filter(|(_, m)| !m).map(|(r, _)| r);
+    loop {
+        let exact = exact_only.next();
+        let semantic = semantic_only.next();
+        if exact.is_none() && semantic.is_none() {
+            break;
+        }
+        hits.extend(exact.map(GrepHit::Exact));
+        hits.extend(semantic.map(GrepHit::Semantic));
+    }
+    hits
+}
+
+/// Lines of `sources` matching `pattern`, up to `limit`
+fn scan_sources(sources: &[String], pattern: &Regex, limit: usize) -> Vec<ExactHit> {
+    let mut hits = Vec::new();
+    for source in sources {
+        let content = match std::fs::read_to_string(source) {
+            Ok(content) => content,
+            Err(e) => {
+                debug!("Skipping {}: {}", source, e);
+                continue;
+            }
+        };
+        for (i, line) in content.lines().enumerate() {
+            let matches: Vec<(usize, usize)> = pattern.find_iter(line).map(|m| (m.start(), m.end())).collect();
+            if matches.is_empty() {
+                continue;
+            }
+            hits.push(ExactHit {
+                source: source.clone(),
+                line: i + 1,
+                text: line.to_string(),
+                matches,
+            });
+            if hits.len() >= limit {
+                return hits;
+            }
+        }
+    }
+    hits
+}
+
+/// Source files of an index: the build manifest, else the passages' `source` fields
+fn indexed_sources(meta: &IndexMeta, index_path: &std::path::Path) -> anyhow::Result<Vec<String>> {
+    if let Some(provenance) = meta.provenance.as_ref().filter(|p| !p.files.is_empty()) {
+        return Ok(provenance.files.iter().map(|f| f.path.clone()).collect());
+    }
+    let mut sources = BTreeSet::new();
+    for passage in PassageStore::open(index_path)?.iter()? {
+        if let Some(source) = passage?.metadata.get("source").and_then(|s| s.as_str()) {
+            sources.insert(source.to_string());
+        }
+    }
+    Ok(sources.into_iter().collect())
+}
+
+pub async fn run(args: GrepArgs) -> anyhow::Result<()> {
+    let index_name = args.index.clone().unwrap_or_else(|| {
+        std::env::current_dir()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "index".to_string())
+    });
+    let index_dir = resolve_index(&index_name).await?;
+    let index_path = index_dir.join("documents.leann");
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+
+    let pattern = if args.fixed_strings { regex::escape(&args.pattern) } else { args.pattern.clone() };
+    let pattern = RegexBuilder::new(&pattern)
+        .case_insensitive(args.ignore_case)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Invalid pattern: {}", e))?;
+
+    let config = Config::load();
+    let top_k = args.top_k.unwrap_or(config.search.top_k);
+    let description = args.description.clone().unwrap_or_else(|| args.pattern.clone());
+
+    // Exact matching reads the files while the semantic search runs
+    let sources = indexed_sources(&meta, &index_path)?;
+    info!("Matching {} files in index '{}'", sources.len(), index_name);
+    let max_matches = args.max_matches;
+    let scan = tokio::task::spawn_blocking(move || scan_sources(&sources, &pattern, max_matches));
+
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        ..Default::default()
+    };
+    let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &config.embedding).await?;
+    let query_embedding = embedding_provider
+        .embed_for_task(&[description.as_str()], EmbeddingTask::Query, &meta.embedding_template(EmbeddingTask::Query))
+        .await?
+        .remove(0);
+    let semantic = if meta.is_pruned {
+        RecomputeSearcher::load(&index_path, meta.dimensions)?
+            .with_document_template(meta.embedding_template(EmbeddingTask::Document))
+            .search(&query_embedding, &embedding_provider, top_k, None)
+            .await?
+    } else {
+        let opts = SearchOptions::new(top_k, config.search.complexity);
+        IndexSearcher::load(&index_path, &meta)?.search_with_options(&query_embedding, &opts)?
+    };
+
+    let exact = scan.await?;
+    let hits = interleave(exact, semantic);
+    print_hits(&args, &description, &hits)
+}
+
+fn print_hits(args: &GrepArgs, description: &str, hits: &[GrepHit]) -> anyhow::Result<()> {
+    if args.format == "json" {
+        let exact_json = |hit: &ExactHit| {
+            serde_json::json!({
+                "source": hit.source,
+                "line": hit.line,
+                "text": hit.text,
+                "matches": hit.matches.iter().map(|(s, e)| [s, e]).collect::<Vec<_>>(),
+            })
+        };
+        let json: Vec<serde_json::Value> = hits
+            .iter()
+            .map(|hit| match hit {
+                GrepHit::Both { exact, score } => {
+                    let mut value = exact_json(exact);
+                    value["kind"] = "both".into();
+                    value["score"] = (*score).into();
+                    value
+                }
+                GrepHit::Exact(exact) => {
+                    let mut value = exact_json(exact);
+                    value["kind"] = "exact".into();
+                    value
+                }
+                GrepHit::Semantic(r) => serde_json::json!({
+                    "kind": "semantic",
+                    "id": r.id,
+                    "score": r.score,
+                    "text": r.text,
+                    "metadata": r.metadata,
+                }),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        println!("No matches for '{}'", args.pattern);
+        return Ok(());
+    }
+
+    let color = highlight::use_color(args.no_color);
+    let terms = highlight::query_terms(description);
+    for hit in hits {
+        match hit {
+            GrepHit::Both { exact, score } => {
+                println!("[both {:.4}] {}:{}", score, exact.source, exact.line);
+                println!("   {}", highlight::snippet(&exact.text, &exact.matches, 200, color));
+            }
+            GrepHit::Exact(exact) => {
+                println!("[exact] {}:{}", exact.source, exact.line);
+                println!("   {}", highlight::snippet(&exact.text, &exact.matches, 200, color));
+            }
+            GrepHit::Semantic(r) => {
+                let location = highlight::location(&r.metadata).unwrap_or_else(|| r.id.clone());
+                println!("[semantic {:.4}] {}", r.score, location);
+                let highlights = highlight::find_highlights(&r.text, &terms);
+                println!("   {}", highlight::snippet(&r.text, &highlights, 200, color));
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact(source: &str, line: usize) -> ExactHit {
+        ExactHit { source: source.to_string(), line, text: String::new(), matches: Vec::new() }
+    }
+
+    fn semantic(id: &str, score: f32, source: &str, lines: (u64, u64)) -> SearchResult {
+        SearchResult {
+            id: id.to_string(),
+            score,
+            text: String::new(),
+            metadata: serde_json::json!({ "source": source, "start_line": lines.0, "end_line": lines.1 }),
+        }
+    }
+
+    #[test]
+    fn test_interleave_puts_overlaps_first() {
+        let hits = interleave(
+            vec![exact("a.rs", 3), exact("b.rs", 40), exact("c.rs", 1)],
+            vec![semantic("x", 0.9, "d.rs", (1, 10)), semantic("y", 0.8, "b.rs", (30, 50))],
+        );
+        let kinds: Vec<String> = hits
+            .iter()
+            .map(|h| match h {
+                GrepHit::Both { exact, .. } => format!("both {}", exact.source),
+                GrepHit::Exact(e) => format!("exact {}", e.source),
+                GrepHit::Semantic(r) => format!("semantic {}", r.id),
+            })
+            .collect();
+        assert_eq!(kinds, ["both b.rs", "exact a.rs", "semantic x", "exact c.rs"]);
+    }
+
+    #[test]
+    fn test_scan_sources_reports_lines() {
+        let dir = std::env::temp_dir().join(format!("leann-grep-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lib.rs");
+        std::fs::write(&path, "fn main() {}\nfn retry_send() {}\n// retry later\n").unwrap();
+        let sources = vec![path.to_string_lossy().to_string(), "missing.rs".to_string()];
+
+        let hits = scan_sources(&sources, &Regex::new("retry").unwrap(), 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].line, hits[0].matches.clone()), (2, vec![(3, 8)]));
+        assert_eq!(hits[1].line, 3);
+
+        assert_eq!(scan_sources(&sources, &Regex::new("retry").unwrap(), 1).len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
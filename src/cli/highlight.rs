@@ -0,0 +1,174 @@
+//! Search result formatting - source locations, snippets, and query term highlighting
+
+use std::io::IsTerminal;
+
+use crate::transcribe::format_timestamp;
+
+const HIGHLIGHT_START: &str = "\x1b[1;33m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+
+/// Whether to color output: not disabled by flag or `NO_COLOR`, and stdout is a terminal
+pub fn use_color(no_color: bool) -> bool {
+    !no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+/// Query words worth highlighting (lowercased, 2+ characters)
+pub fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| t.chars().count() > 1)
+        .map(|t| t.to_lowercase())
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+/// Byte ranges of case-insensitive term matches in `text`, sorted and merged
+pub fn find_highlights(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let haystack = text.as_bytes();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for term in terms {
+        let needle = term.as_bytes();
+        if needle.is_empty() || needle.len() > haystack.len() {
+            continue;
+        }
+        let mut pos = 0;
+        while pos + needle.len() <= haystack.len() {
+            let end = pos + needle.len();
+            if text.is_char_boundary(pos)
+                && text.is_char_boundary(end)
+                && haystack[pos..end].eq_ignore_ascii_case(needle)
+            {
+                ranges.push((pos, end));
+                pos = end;
+            } else {
+                pos += 1;
+            }
+        }
+    }
+
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// `source:start-end` for a result, when the source path is known
+///
+/// Transcribed recordings get a media fragment link instead
+/// (`talk.mp3#t=754,790 (12:34-13:10)`).
+pub fn location(metadata: &serde_json::Value) -> Option<String> {
+    let source = metadata.get("source")?.as_str()?;
+    if let Some(start) = metadata.get("start_time").and_then(|v| v.as_f64()) {
+        let end = metadata.get("end_time").and_then(|v| v.as_f64()).unwrap_or(start);
+        return Some(format!(
+            "{}#t={},{} ({}-{})",
+            source,
+            start,
+            end,
+            format_timestamp(start),
+            format_timestamp(end)
+        ));
+    }
+    let start = metadata.get("start_line").and_then(|v| v.as_u64());
+    let end = metadata.get("end_line").and_then(|v| v.as_u64());
+    Some(match (start, end) {
+        (Some(start), Some(end)) if end > start => format!("{}:{}-{}", source, start, end),
+        (Some(start), _) => format!("{}:{}", source, start),
+        _ => source.to_string(),
+    })
+}
+
+/// A display snippet of at most ~`max_len` bytes, starting near the first highlight
+///
+/// Returns the snippet with highlights applied (ANSI when `color`), plus
+/// ellipses where text was cut.
+pub fn snippet(text: &str, highlights: &[(usize, usize)], max_len: usize, color: bool) -> String {
+    // Start a little before the first match so it has some context
+    let mut start = highlights
+        .first()
+        .filter(|(_, e)| *e > max_len)
+        .map_or(0, |(s, _)| s.saturating_sub(max_len / 4));
+    while start > 0 && !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (start + max_len).min(text.len());
+    while end > start && !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push_str("...");
+    }
+
+    let mut cursor = start;
+    if color {
+        for &(s, e) in highlights {
+            if e <= start || s >= end {
+                continue;
+            }
+            let (s, e) = (s.max(start), e.min(end));
+            out.push_str(&text[cursor..s]);
+            out.push_str(HIGHLIGHT_START);
+            out.push_str(&text[s..e]);
+            out.push_str(HIGHLIGHT_END);
+            cursor = e;
+        }
+    }
+    out.push_str(&text[cursor..end]);
+
+    if end < text.len() {
+        out.push_str("...");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_highlights() {
+        let terms = query_terms("Auth token, a");
+        assert_eq!(terms, vec!["auth", "token"]);
+
+        let text = "fn authenticate(token: &str) -> AUTH";
+        assert_eq!(find_highlights(text, &terms), vec![(3, 7), (16, 21), (32, 36)]);
+    }
+
+    #[test]
+    fn test_location() {
+        let meta = serde_json::json!({"source": "src/a.rs", "start_line": 10, "end_line": 42});
+        assert_eq!(location(&meta).unwrap(), "src/a.rs:10-42");
+        assert_eq!(location(&serde_json::json!({"source": "x.md"})).unwrap(), "x.md");
+        assert!(location(&serde_json::json!({})).is_none());
+
+        let meta = serde_json::json!({"source": "talk.mp3", "start_time": 754.0, "end_time": 790.5});
+        assert_eq!(location(&meta).unwrap(), "talk.mp3#t=754,790.5 (12:34-13:10)");
+    }
+
+    #[test]
+    fn test_snippet() {
+        let text = "alpha beta gamma";
+        let highlights = find_highlights(text, &["beta".to_string()]);
+        assert_eq!(
+            snippet(text, &highlights, 200, true),
+            format!("alpha {}beta{} gamma", HIGHLIGHT_START, HIGHLIGHT_END)
+        );
+        assert_eq!(snippet(text, &highlights, 200, false), text);
+
+        // A match past the window moves the snippet to it
+        let long = format!("{} needle", "x".repeat(300));
+        let highlights = find_highlights(&long, &["needle".to_string()]);
+        let shown = snippet(&long, &highlights, 100, false);
+        assert!(shown.starts_with("...") && shown.contains("needle"));
+    }
+}
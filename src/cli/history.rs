@@ -0,0 +1,131 @@
+//! Question history - the questions and answers of interactive `ask`
+//! sessions, kept in the `_history` index when `[analytics] ask_history` is on
+//!
+//! The index lives in `~/.leann/indexes/_history`, so `leann search -i
+//! _history` finds past answers from any project. It is created with the
+//! embedding model of the first index asked; later pairs are embedded with
+//! that model, whichever index they were asked of.
+
+use std::path::PathBuf;
+
+use tracing::info;
+
+use crate::config::EmbeddingConfig;
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::index::{FieldIndex, IndexBuilder, IndexMeta, Passage, PassageStore, META_VERSION};
+
+/// Name of the question history index
+pub(super) const HISTORY_INDEX: &str = "_history";
+
+/// `type` metadata of question history passages
+const HISTORY_TYPE: &str = "history";
+
+/// The `_history` index, created on the first recorded answer
+pub(super) struct QuestionHistory {
+    index_path: PathBuf,
+    meta_path: PathBuf,
+    /// The index's metadata; until it exists, what it will be created with
+    meta: IndexMeta,
+    embedding_provider: EmbeddingProvider,
+}
+
+impl QuestionHistory {
+    /// Open the history index, or prepare to create it with the embedding
+    /// settings of `asked`
+    pub(super) async fn open(
+        asked: &IndexMeta,
+        overrides: &ProviderOverrides,
+        config: &EmbeddingConfig,
+    ) -> anyhow::Result<Self> {
+        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("No home directory for the question history"))?;
+        let index_dir = home.join(".leann").join("indexes").join(HISTORY_INDEX);
+        let meta_path = index_dir.join("documents.leann.meta.json");
+        let meta = if meta_path.exists() { IndexMeta::load(&meta_path)? } else { history_meta(asked) };
+        let embedding_provider = EmbeddingProvider::for_index(&meta, overrides, config).await?;
+        Ok(Self { index_path: index_dir.join("documents.leann"), meta_path, meta, embedding_provider })
+    }
+
+    /// Add a question and its answer, asked of `index_name`
+    pub(super) async fn record(&mut self, index_name: &str, question: &str, answer: &str) -> anyhow::Result<()> {
+        let timestamp = crate::time::now_unix();
+        let text = format!("Question: {}\nAnswer: {}", question, answer.trim());
+        let passage = Passage {
+            id: crate::chunker::stable_chunk_id(&format!("history:{}", index_name), timestamp as usize, text.as_bytes()),
+            metadata: serde_json::json!({
+                "type": HISTORY_TYPE,
+                "source": index_name,
+                "question": question,
+                "timestamp": timestamp,
+            }),
+            text,
+        };
+        let embedding = self
+            .embedding_provider
+            .embed_for_task(&[&passage.text], EmbeddingTask::Document, &self.meta.embedding_template(EmbeddingTask::Document))
+            .await?
+            .remove(0);
+
+        if self.meta_path.exists() {
+            self.append(&passage, &embedding)?;
+        } else {
+            let params = self.meta.build_params();
+            let mut builder = IndexBuilder::new(self.meta.backend()?, self.meta.dimensions, params.graph_degree, params.complexity);
+            builder.add_passage(&passage.id, &passage.text, &embedding, passage.metadata.clone())?;
+            if let Some(parent) = self.index_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            builder.build(&self.index_path)?;
+            info!("Created question history index '{}'", HISTORY_INDEX);
+        }
+        self.meta.passage_count += 1;
+        self.meta.save(&self.meta_path)?;
+        Ok(())
+    }
+
+    /// Add a passage to the existing index
+    fn append(&self, passage: &Passage, embedding: &[f32]) -> anyhow::Result<()> {
+        let start_id = self.meta.passage_count;
+        let mut writer = PassageStore::open_for_append(&self.index_path)?;
+        writer.add(passage)?;
+        writer.finish()?;
+        if let Some(mut field_index) = FieldIndex::load(&self.index_path).filter(|f| f.len() == start_id) {
+            field_index.add(&passage.metadata);
+            field_index.save(&self.index_path)?;
+        }
+
+        let ids_path = self.index_path.with_extension("ids.txt");
+        let mut ids = std::fs::read_to_string(&ids_path).unwrap_or_default();
+        if !ids.is_empty() {
+            ids.push('\n');
+        }
+        ids.push_str(&passage.id);
+        std::fs::write(&ids_path, ids)?;
+
+        self.meta.backend()?.add_to_index(&[embedding.to_vec()], &self.index_path, self.meta.dimensions, start_id)?;
+        Ok(())
+    }
+}
+
+/// Metadata for a new history index: the embedding settings of `asked`, an
+/// HNSW graph, and none of its build settings
+fn history_meta(asked: &IndexMeta) -> IndexMeta {
+    IndexMeta {
+        version: META_VERSION.to_string(),
+        backend_name: "hnsw".to_string(),
+        passage_count: 0,
+        backend_kwargs: None,
+        sparse_model: None,
+        multi_vector: None,
+        is_recompute: false,
+        is_pruned: false,
+        created_at: Some(crate::time::now_unix()),
+        chunking: None,
+        text_stored: true,
+        passage_store: None,
+        provenance: None,
+        prompt_templates: None,
+        bm25_analyzer: None,
+        search_defaults: None,
+        ..asked.clone()
+    }
+}
@@ -0,0 +1,237 @@
+//! Info command - detailed statistics for a single index
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use clap::Args;
+
+use crate::index::{find_index, FieldIndex, IndexMeta, PassageStore};
+
+#[derive(Args)]
+pub struct InfoArgs {
+    /// Index name to inspect
+    pub index_name: String,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+
+    /// Number of source extensions to show
+    #[arg(long, default_value = "10")]
+    pub top_extensions: usize,
+}
+
+pub async fn run(args: InfoArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let meta_path = index_dir.join("documents.leann.meta.json");
+    let index_path = index_dir.join("documents.leann");
+
+    let meta = IndexMeta::load(&meta_path)?;
+
+    // File sizes per component (e.g. "passages.jsonl", "index", "embeddings")
+    let mut components: Vec<(String, u64)> = Vec::new();
+    for entry in std::fs::read_dir(&index_dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let component = file_name
+                .strip_prefix("documents.leann.")
+                .unwrap_or(&file_name)
+                .to_string();
+            components.push((component, metadata.len()));
+        }
+    }
+    components.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    let total_size: u64 = components.iter().map(|(_, size)| size).sum();
+
+    // Source statistics from passage metadata
+    let (extensions, file_count) = source_stats(&index_path)?;
+    let mut extensions: Vec<(String, usize)> = extensions.into_iter().collect();
+    extensions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    extensions.truncate(args.top_extensions);
+
+    let built_at = meta.created_at.map(crate::time::format_rfc3339);
+    let store = PassageStore::open(&index_path)?;
+    let (deleted_passages, passage_store) = (store.deleted_count(), store.format());
+
+    // Capabilities of the backend, if this build knows it
+    let backend_info = meta.backend().ok().map(|backend| *backend.info());
+
+    // Fields answerable from the secondary metadata index
+    let indexed_fields: Option<Vec<String>> = FieldIndex::load(&index_path)
+        .map(|f| f.fields().map(|name| name.to_string()).collect());
+
+    if args.format == "json" {
+        let json = serde_json::json!({
+            "name": args.index_name,
+            "path": index_dir.display().to_string(),
+            "version": meta.version,
+            "backend": meta.backend_name,
+            "backend_kwargs": meta.backend_kwargs,
+            "backend_capabilities": backend_info,
+            "embedding_model": meta.embedding_model,
+            "embedding_mode": meta.embedding_mode,
+            "embedding_options": meta.embedding_options,
+            "post_processing": meta.post_processing,
+            "sparse_model": meta.sparse_model,
+            "multi_vector": meta.multi_vector,
+            "dimensions": meta.dimensions,
+            "passage_count": meta.passage_count,
+            "deleted_passages": deleted_passages,
+            "file_count": file_count,
+            "chunking": meta.chunking,
+            "search_defaults": meta.search_defaults,
+            "is_recompute": meta.is_recompute,
+            "is_pruned": meta.is_pruned,
+            "text_stored": meta.text_stored,
+            "passage_store": passage_store.as_str(),
+            "indexed_fields": indexed_fields,
+            "built_at": built_at,
+            "total_size_bytes": total_size,
+            "components": components
+                .iter()
+                .map(|(name, size)| serde_json::json!({ "name": name, "size_bytes": size }))
+                .collect::<Vec<_>>(),
+            "top_extensions": extensions
+                .iter()
+                .map(|(ext, count)| serde_json::json!({ "extension": ext, "passages": count }))
+                .collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    println!("Index '{}'", args.index_name);
+    println!("{}", "=".repeat(50));
+    println!("  Path:            {}", index_dir.display());
+    println!("  Format version:  {}", meta.version);
+    println!("  Built:           {}", built_at.as_deref().unwrap_or("unknown"));
+    println!("  Passages:        {}", meta.passage_count);
+    if deleted_passages > 0 {
+        println!("  Deleted:         {} (run 'leann compact {}')", deleted_passages, args.index_name);
+    }
+    println!("  Source files:    {}", file_count);
+
+    println!("\nEmbedding");
+    println!("  Model:           {}", meta.embedding_model);
+    println!("  Mode:            {}", meta.embedding_mode);
+    println!("  Dimensions:      {}", meta.dimensions);
+    println!("  Post-processing: {}", meta.post_processing());
+    if let Some(sparse) = &meta.sparse_model {
+        println!("  Sparse model:    {}", sparse.model);
+    }
+    if let Some(multi_vector) = &meta.multi_vector {
+        println!("  Token vectors:   {} ({} dims)", multi_vector.model, multi_vector.dimensions);
+    }
+    if let Some(opts) = &meta.embedding_options {
+        if let Some(obj) = opts.as_object() {
+            for (key, value) in obj {
+                println!("  {:<16} {}", format!("{}:", key), value);
+            }
+        }
+    }
+
+    println!("\nBackend");
+    println!("  Name:            {}", meta.backend_name);
+    if let Some(info) = &backend_info {
+        let capabilities: Vec<&str> = [
+            (info.supports_update, "update"),
+            (info.supports_replace, "in-place replace"),
+            (info.supports_filtering, "native filtering"),
+            (info.supports_sharding, "sharding"),
+            (info.supports_pruning, "pruning"),
+        ]
+        .into_iter()
+        .filter_map(|(supported, name)| supported.then_some(name))
+        .collect();
+        println!("  Supports:        {}", capabilities.join(", "));
+        println!("  Quantization:    {}", info.quantizations.join(", "));
+    }
+    if let Some(kwargs) = meta.backend_kwargs.as_ref().and_then(|k| k.as_object()) {
+        for (key, value) in kwargs {
+            println!("  {:<16} {}", format!("{}:", key), value);
+        }
+    }
+    println!("  Recompute:       {}", meta.is_recompute);
+    println!("  Pruned:          {}", meta.is_pruned);
+    println!("  Text stored:     {}", meta.text_stored);
+    println!("  Passage store:   {}", passage_store);
+    match &indexed_fields {
+        Some(fields) => println!("  Indexed fields:  {}", fields.join(", ")),
+        None => println!("  Indexed fields:  (none, filters scan metadata)"),
+    }
+
+    println!("\nChunking");
+    match &meta.chunking {
+        Some(chunking) => {
+            println!("  Strategy:        {}", chunking.strategy);
+            println!("  Chunk size:      {}", chunking.chunk_size);
+            println!("  Chunk overlap:   {}", chunking.chunk_overlap);
+        }
+        None => println!("  (not recorded)"),
+    }
+
+    if let Some(defaults) = &meta.search_defaults {
+        println!("\nSearch defaults");
+        if let Some(top_k) = defaults.top_k {
+            println!("  Top k:           {}", top_k);
+        }
+        if let Some(complexity) = defaults.complexity {
+            println!("  Complexity:      {}", complexity);
+        }
+        if let Some(hybrid) = defaults.hybrid {
+            println!("  Hybrid:          {}", if hybrid { "always" } else { "never" });
+        }
+        if let Some(alpha) = defaults.hybrid_alpha {
+            println!("  Hybrid alpha:    {}", alpha);
+        }
+    }
+
+    println!("\nFiles ({:.2} MB total)", total_size as f64 / (1024.0 * 1024.0));
+    for (name, size) in &components {
+        println!("  {:<24} {:>10.2} MB", name, *size as f64 / (1024.0 * 1024.0));
+    }
+
+    if !extensions.is_empty() {
+        println!("\nTop source extensions");
+        for (ext, count) in &extensions {
+            println!("  {:<12} {} passages", ext, count);
+        }
+    }
+
+    Ok(())
+}
+
+/// Count passages per source extension and the number of distinct source files
+fn source_stats(index_path: &Path) -> anyhow::Result<(HashMap<String, usize>, usize)> {
+    let store = PassageStore::open(index_path)?;
+    let mut extensions: HashMap<String, usize> = HashMap::new();
+    let mut sources: HashSet<String> = HashSet::new();
+
+    for passage in store.iter()? {
+        let passage = match passage {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::warn!("Skipping unreadable passage: {}", e);
+                continue;
+            }
+        };
+
+        let Some(source) = passage.metadata.get("source").and_then(|s| s.as_str()) else {
+            continue;
+        };
+
+        let ext = Path::new(source)
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_else(|| "(none)".to_string());
+        *extensions.entry(ext).or_insert(0) += 1;
+
+        if !sources.contains(source) {
+            sources.insert(source.to_string());
+        }
+    }
+
+    Ok((extensions, sources.len()))
+}
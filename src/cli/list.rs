@@ -4,13 +4,17 @@ use std::path::PathBuf;
 
 use clap::Args;
 
-use crate::index::IndexMeta;
+use crate::index::{is_build_artifact, list_snapshots, previous_dir, IndexMeta};
 
 #[derive(Args)]
 pub struct ListArgs {
     /// Show detailed information
     #[arg(short, long)]
     pub detailed: bool,
+
+    /// Show each index's snapshots
+    #[arg(long)]
+    pub snapshots: bool,
 }
 
 pub async fn run(args: ListArgs) -> anyhow::Result<()> {
@@ -28,8 +32,8 @@ pub async fn run(args: ListArgs) -> anyhow::Result<()> {
     if local_path.exists() {
         for entry in std::fs::read_dir(&local_path)? {
             let entry = entry?;
-            if entry.path().is_dir() {
-                let index_name = entry.file_name().to_string_lossy().to_string();
+            let index_name = entry.file_name().to_string_lossy().to_string();
+            if entry.path().is_dir() && !is_build_artifact(&index_name) {
                 let meta_path = entry.path().join("documents.leann.meta.json");
 
                 let status = if meta_path.exists() { "OK" } else { "INCOMPLETE" };
@@ -46,8 +50,14 @@ pub async fn run(args: ListArgs) -> anyhow::Result<()> {
                 if let Ok(size) = calculate_dir_size(&entry.path()) {
                     print!(" [{:.1} MB]", size as f64 / (1024.0 * 1024.0));
                 }
+                if previous_dir(&entry.path()).exists() {
+                    print!(" (rollback available)");
+                }
 
                 println!();
+                if args.snapshots {
+                    print_snapshots(&entry.path());
+                }
                 total_indexes += 1;
             }
         }
@@ -69,8 +79,8 @@ pub async fn run(args: ListArgs) -> anyhow::Result<()> {
             let mut global_count = 0;
             for entry in std::fs::read_dir(&global_path)? {
                 let entry = entry?;
-                if entry.path().is_dir() {
-                    let index_name = entry.file_name().to_string_lossy().to_string();
+                let index_name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() && !is_build_artifact(&index_name) {
                     let meta_path = entry.path().join("documents.leann.meta.json");
 
                     let status = if meta_path.exists() { "OK" } else { "INCOMPLETE" };
@@ -86,8 +96,14 @@ pub async fn run(args: ListArgs) -> anyhow::Result<()> {
                     if let Ok(size) = calculate_dir_size(&entry.path()) {
                         print!(" [{:.1} MB]", size as f64 / (1024.0 * 1024.0));
                     }
+                    if previous_dir(&entry.path()).exists() {
+                        print!(" (rollback available)");
+                    }
 
                     println!();
+                    if args.snapshots {
+                        print_snapshots(&entry.path());
+                    }
                     total_indexes += 1;
                     global_count += 1;
                 }
@@ -110,6 +126,20 @@ pub async fn run(args: ListArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Print an index's snapshots, oldest first
+fn print_snapshots(index_dir: &std::path::Path) {
+    for snapshot in list_snapshots(index_dir) {
+        print!("        snapshot {}", snapshot.id);
+        if let Ok(meta) = snapshot.meta() {
+            print!(" ({} passages, {})", meta.passage_count, meta.embedding_model);
+        }
+        if let Ok(size) = calculate_dir_size(&snapshot.path) {
+            print!(" [{:.1} MB]", size as f64 / (1024.0 * 1024.0));
+        }
+        println!();
+    }
+}
+
 fn calculate_dir_size(path: &PathBuf) -> std::io::Result<u64> {
     let mut size = 0;
     for entry in std::fs::read_dir(path)? {
@@ -1,23 +1,48 @@
 //! MCP server - Model Context Protocol integration for Claude Code
 //!
-//! Provides LEANN search capabilities as MCP tools.
+//! Provides LEANN search capabilities as MCP tools, and prompts that fill
+//! retrieved passages into ready-made RAG prompts: `answer-from-index`
+//! (the index's `ask` template) and `summarize-file`.
+//!
+//! The server speaks over stdio, or with `--transport sse` over HTTP (MCP's
+//! Streamable HTTP transport, streaming responses as server-sent events), for
+//! agent hosts that can't spawn the binary locally.
 
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use clap::Args;
 use rmcp::{
-    ErrorData as McpError, ServerHandler, ServiceExt,
-    handler::server::{router::tool::ToolRouter, wrapper::Parameters},
+    ErrorData as McpError, RoleServer, ServerHandler, ServiceExt,
+    handler::server::{
+        router::{prompt::PromptRouter, tool::ToolRouter},
+        wrapper::Parameters,
+    },
     model::*,
+    prompt, prompt_handler, prompt_router,
     schemars::{self, JsonSchema},
+    service::RequestContext,
     tool, tool_handler, tool_router,
+    transport::streamable_http_server::{
+        session::local::LocalSessionManager, StreamableHttpServerConfig, StreamableHttpService,
+    },
 };
 use serde::{Deserialize, Serialize};
 use tokio::io::{stdin, stdout};
 use tracing::info;
 
-use crate::embedding::{EmbeddingMode, EmbeddingProvider};
-use crate::index::{find_index, IndexMeta, IndexSearcher, MetadataFilter, RecomputeSearcher, SearchOptions};
+use crate::config::Config;
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::index::{
+    find_index, is_build_artifact, IndexMeta, IndexSearcher, MetadataFilter, RecomputeSearcher, SearchOptions, SearchResult,
+};
+use crate::llm::{PromptTemplate, DEFAULT_ASK_TEMPLATE};
+
+use super::ask::pack_results;
+use super::summarize::{load_sources, pack, Subject};
+
+/// Token budget for the file passages of a `summarize-file` prompt
+const SUMMARIZE_PROMPT_BUDGET: usize = 16_000;
 
 #[derive(Args)]
 pub struct McpArgs {
@@ -25,6 +50,19 @@ pub struct McpArgs {
     #[arg(long)]
     pub index: Option<String>,
 
+    /// Transport: stdio, or sse to serve HTTP at /mcp (Streamable HTTP with
+    /// server-sent event responses)
+    #[arg(long, default_value = "stdio", value_parser = ["stdio", "sse"])]
+    pub transport: String,
+
+    /// Port to listen on with --transport sse
+    #[arg(long, default_value = "3333")]
+    pub port: u16,
+
+    /// Host to bind to with --transport sse
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+
     /// API key for embedding service
     #[arg(long, env = "OPENAI_API_KEY")]
     pub embedding_api_key: Option<String>,
@@ -39,7 +77,7 @@ pub struct McpArgs {
 }
 
 /// Input parameters for search tool
-#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
 pub struct SearchInput {
     /// Search query - the text to search for semantically
     #[schemars(description = "The search query text")]
@@ -50,30 +88,72 @@ pub struct SearchInput {
     #[schemars(description = "Name of the LEANN index to search")]
     index: Option<String>,
 
-    /// Number of results to return (default: 5)
-    #[serde(default = "default_top_k")]
+    /// Number of results to return (default: `[search] top_k` in config.toml, else 5)
+    #[serde(default)]
     #[schemars(description = "Number of results to return")]
-    top_k: usize,
+    top_k: Option<usize>,
 
     /// Filter expression (e.g., "source:*.rs" or "type=code")
     #[serde(default)]
     #[schemars(description = "Metadata filter expression")]
     filter: Option<String>,
 
-    /// Enable hybrid search combining vector + keyword matching
+    /// Namespace to restrict the search to, for indexes shared by several users or projects
     #[serde(default)]
-    #[schemars(description = "Enable hybrid vector + BM25 search")]
-    hybrid: bool,
-}
+    #[schemars(description = "Only search passages of this namespace")]
+    namespace: Option<String>,
+
+    /// Hybrid search combining vector + keyword matching (default: the index's default, else off)
+    #[serde(default)]
+    #[schemars(description = "Enable hybrid vector + BM25 search (default: the index's default)")]
+    hybrid: Option<bool>,
+
+    /// Minimum similarity score; lower-scoring results are dropped
+    #[serde(default)]
+    #[schemars(description = "Drop results scoring below this similarity")]
+    min_score: Option<f32>,
+
+    /// Number of results to skip, for fetching further pages
+    #[serde(default)]
+    #[schemars(description = "Number of results to skip (pagination)")]
+    offset: usize,
 
-fn default_top_k() -> usize {
-    5
+    /// Results per page (overrides top_k)
+    #[serde(default)]
+    #[schemars(description = "Results per page; overrides top_k")]
+    page_size: Option<usize>,
 }
 
 /// Input parameters for list_indexes tool
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct ListIndexesInput {}
 
+/// Arguments of the answer-from-index prompt
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct AnswerPromptInput {
+    /// Question to answer
+    #[schemars(description = "The question to answer")]
+    query: String,
+
+    /// Index name (optional if default index is set)
+    #[serde(default)]
+    #[schemars(description = "Name of the LEANN index to answer from")]
+    index: Option<String>,
+}
+
+/// Arguments of the summarize-file prompt
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SummarizePromptInput {
+    /// Source path of the file, as shown in search results
+    #[schemars(description = "Path of the file to summarize, as shown in search results (a trailing part is enough)")]
+    source: String,
+
+    /// Index name (optional if default index is set)
+    #[serde(default)]
+    #[schemars(description = "Name of the LEANN index containing the file")]
+    index: Option<String>,
+}
+
 /// LEANN MCP Server
 #[derive(Clone, Debug)]
 pub struct LeannMcpServer {
@@ -82,6 +162,7 @@ pub struct LeannMcpServer {
     embedding_api_base: Option<String>,
     embedding_host: Option<String>,
     tool_router: ToolRouter<Self>,
+    prompt_router: PromptRouter<Self>,
 }
 
 #[tool_router]
@@ -98,6 +179,7 @@ impl LeannMcpServer {
             embedding_api_base,
             embedding_host,
             tool_router: Self::tool_router(),
+            prompt_router: Self::prompt_router(),
         }
     }
 
@@ -132,17 +214,73 @@ impl LeannMcpServer {
     }
 }
 
+#[prompt_router]
 impl LeannMcpServer {
+    /// Answer a question from retrieved passages
+    #[prompt(
+        name = "answer-from-index",
+        description = "Retrieve passages for a question from a LEANN index and ask to answer it from them, using the index's ask prompt template."
+    )]
+    async fn answer_from_index(&self, params: Parameters<AnswerPromptInput>) -> Result<GetPromptResult, McpError> {
+        self.answer_prompt(params.0).await.map_err(prompt_error)
+    }
+
+    /// Summarize one file of an index
+    #[prompt(
+        name = "summarize-file",
+        description = "Ask to summarize a file of a LEANN index, given its passages in order."
+    )]
+    async fn summarize_file(&self, params: Parameters<SummarizePromptInput>) -> Result<GetPromptResult, McpError> {
+        self.summarize_prompt(params.0).map_err(prompt_error)
+    }
+}
+
+fn prompt_error(e: anyhow::Error) -> McpError {
+    McpError::invalid_params(e.to_string(), None)
+}
+
+impl LeannMcpServer {
+    /// The requested index, else the default one
+    fn index_name<'a>(&'a self, requested: Option<&'a String>) -> anyhow::Result<&'a String> {
+        requested.or(self.default_index.as_ref()).ok_or_else(|| {
+            anyhow::anyhow!("No index specified. Use --index flag or provide 'index' in the request.")
+        })
+    }
+
     async fn do_search(&self, input: SearchInput) -> anyhow::Result<String> {
-        let index_name = input
-            .index
-            .as_ref()
-            .or(self.default_index.as_ref())
-            .ok_or_else(|| {
-                anyhow::anyhow!(
-                    "No index specified. Use --index flag or provide 'index' in the request."
-                )
-            })?;
+        let (index_name, _, results) = self.retrieve(&input).await?;
+
+        // Format results as markdown
+        let mut output = format!("## Search Results for \"{}\"\n\n", input.query);
+        output.push_str(&format!(
+            "Found {} results in index '{}'.\n\n",
+            results.len(),
+            index_name
+        ));
+
+        for (i, result) in results.iter().enumerate() {
+            output.push_str(&format!(
+                "### Result {} (score: {:.4})\n\n",
+                input.offset + i + 1,
+                result.score
+            ));
+
+            if let Some(source) = result.metadata.get("source") {
+                if let Some(s) = source.as_str() {
+                    output.push_str(&format!("**Source:** `{}`\n\n", s));
+                }
+            }
+
+            output.push_str(&result.text);
+            output.push_str("\n\n---\n\n");
+        }
+
+        Ok(output)
+    }
+
+    /// Search the requested index; returns its name and metadata with the results
+    async fn retrieve(&self, input: &SearchInput) -> anyhow::Result<(String, IndexMeta, Vec<SearchResult>)> {
+        let index_name = self.index_name(input.index.as_ref())?;
 
         // Find index
         let index_dir = find_index(index_name)?;
@@ -153,77 +291,120 @@ impl LeannMcpServer {
         let meta = IndexMeta::load(&meta_path)?;
 
         // Create embedding provider
-        let embedding_mode = match meta.embedding_mode.as_str() {
-            "openai" => EmbeddingMode::OpenAI {
-                api_key: self.embedding_api_key.clone(),
-                base_url: self.embedding_api_base.clone(),
-            },
-            "ollama" => EmbeddingMode::Ollama {
-                host: self.embedding_host.clone(),
-            },
-            "gemini" => EmbeddingMode::Gemini {
-                api_key: std::env::var("GOOGLE_API_KEY").ok(),
-            },
-            _ => anyhow::bail!("Unknown embedding mode: {}", meta.embedding_mode),
+        let overrides = ProviderOverrides {
+            api_key: self.embedding_api_key.clone(),
+            api_base: self.embedding_api_base.clone(),
+            host: self.embedding_host.clone(),
+            ..Default::default()
         };
-
-        let embedding_provider =
-            EmbeddingProvider::new(meta.embedding_model.clone(), embedding_mode).await?;
+        let config = Config::load();
+        let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &config.embedding).await?;
 
         // Compute query embedding
-        let query_embedding = embedding_provider.embed(&[&input.query]).await?;
+        let query_embedding = embedding_provider
+            .embed_for_task(&[&input.query], EmbeddingTask::Query, &meta.embedding_template(EmbeddingTask::Query))
+            .await?;
         let query_embedding = &query_embedding[0];
 
         // Parse filter
-        let filter = input.filter.as_ref().and_then(|s| MetadataFilter::parse(s));
+        let mut filter = match &input.filter {
+            Some(s) => Some(MetadataFilter::try_parse(s)?),
+            None => None,
+        };
+        if let Some(namespace) = &input.namespace {
+            filter = Some(MetadataFilter::in_namespace(namespace, filter));
+        }
+
+        // Page of results to return: the input, then the index's defaults, then config.toml
+        let search_config = meta.search_config(&config.search);
+        let page_size = input.page_size.or(input.top_k).unwrap_or(search_config.top_k);
+        let mut page = SearchOptions::new(page_size, search_config.complexity).with_offset(input.offset);
+        if let Some(min_score) = input.min_score {
+            page = page.with_min_score(min_score);
+        }
 
         // Search
         let results = if meta.is_pruned {
-            let searcher = RecomputeSearcher::load(&index_path, meta.dimensions)?;
-            searcher
-                .search(query_embedding, &embedding_provider, input.top_k, filter.as_ref())
-                .await?
+            let searcher = RecomputeSearcher::load(&index_path, meta.dimensions)?
+                .with_document_template(meta.embedding_template(EmbeddingTask::Document));
+            let results = searcher
+                .search(query_embedding, &embedding_provider, page_size + input.offset, filter.as_ref())
+                .await?;
+            page.paginate(results)
         } else {
             let searcher = IndexSearcher::load(&index_path, &meta)?;
-            let mut opts = SearchOptions::new(input.top_k, 64);
+            let mut opts = page;
 
             if let Some(f) = filter {
                 opts = opts.with_filter(f);
             }
 
-            if input.hybrid {
-                opts = opts.with_hybrid(input.query.clone(), 0.7);
+            if input.hybrid.or(meta.default_hybrid()).unwrap_or(false) {
+                opts = opts.with_hybrid(input.query.clone(), search_config.hybrid_alpha);
             }
 
             searcher.search_with_options(query_embedding, &opts)?
         };
 
-        // Format results as markdown
-        let mut output = format!("## Search Results for \"{}\"\n\n", input.query);
-        output.push_str(&format!(
-            "Found {} results in index '{}'.\n\n",
-            results.len(),
-            index_name
-        ));
-
-        for (i, result) in results.iter().enumerate() {
-            output.push_str(&format!(
-                "### Result {} (score: {:.4})\n\n",
-                i + 1,
-                result.score
-            ));
+        Ok((index_name.clone(), meta, results))
+    }
 
-            if let Some(source) = result.metadata.get("source") {
-                if let Some(s) = source.as_str() {
-                    output.push_str(&format!("**Source:** `{}`\n\n", s));
-                }
+    /// The index's ask prompt with the passages retrieved for the question
+    async fn answer_prompt(&self, input: AnswerPromptInput) -> anyhow::Result<GetPromptResult> {
+        let search = SearchInput { query: input.query, index: input.index, ..Default::default() };
+        let (index_name, meta, results) = self.retrieve(&search).await?;
+
+        let config = Config::load();
+        let template = PromptTemplate::resolve(
+            None,
+            meta.prompt_templates.as_ref().and_then(|t| t.ask.as_deref()),
+            config.prompts.ask_path().as_deref(),
+            DEFAULT_ASK_TEMPLATE,
+        )?;
+        let context = pack_results(&results, None, "");
+        let vars = HashMap::from([
+            ("context", context.text),
+            ("question", search.query),
+            ("history", String::new()),
+        ]);
+
+        let mut sources: Vec<&str> = Vec::new();
+        for source in results.iter().filter_map(|r| r.metadata.get("source").and_then(|s| s.as_str())) {
+            if !sources.contains(&source) {
+                sources.push(source);
             }
-
-            output.push_str(&result.text);
-            output.push_str("\n\n---\n\n");
         }
+        Ok(GetPromptResult {
+            description: Some(format!(
+                "Answer from {} passages of index '{}' ({})",
+                results.len(),
+                index_name,
+                sources.join(", ")
+            )),
+            messages: vec![PromptMessage::new_text(PromptMessageRole::User, template.render(&vars))],
+        })
+    }
 
-        Ok(output)
+    /// A prompt to summarize one file, with its passages in order
+    fn summarize_prompt(&self, input: SummarizePromptInput) -> anyhow::Result<GetPromptResult> {
+        let index_name = self.index_name(input.index.as_ref())?;
+        let index_path = find_index(index_name)?.join("documents.leann");
+        let mut sources = load_sources(&index_path, None)?;
+        let source = find_source(sources.keys(), &input.source)?;
+        let texts: Vec<String> = sources.remove(&source).unwrap_or_default().into_iter().map(|p| p.text).collect();
+
+        // Large files are cut to the budget
+        let batches = pack(&texts, SUMMARIZE_PROMPT_BUDGET, SUMMARIZE_PROMPT_BUDGET, "");
+        let parts = batches.first().cloned().unwrap_or_default();
+        let description = if parts.len() < texts.len() {
+            format!("Summarize {} (first {} of {} passages)", source, parts.len(), texts.len())
+        } else {
+            format!("Summarize {} ({} passages)", source, texts.len())
+        };
+        Ok(GetPromptResult {
+            description: Some(description),
+            messages: vec![PromptMessage::new_text(PromptMessageRole::User, Subject::File(&source).prompt(&parts, false))],
+        })
     }
 
     fn do_list_indexes(&self) -> anyhow::Result<String> {
@@ -235,7 +416,7 @@ impl LeannMcpServer {
             if let Ok(entries) = std::fs::read_dir(&local_path) {
                 for entry in entries.flatten() {
                     if entry.path().is_dir() {
-                        if let Some(name) = entry.file_name().to_str() {
+                        if let Some(name) = entry.file_name().to_str().filter(|n| !is_build_artifact(n)) {
                             indexes.push(format!("{} (local)", name));
                         }
                     }
@@ -250,7 +431,7 @@ impl LeannMcpServer {
                 if let Ok(entries) = std::fs::read_dir(&global_path) {
                     for entry in entries.flatten() {
                         if entry.path().is_dir() {
-                            if let Some(name) = entry.file_name().to_str() {
+                            if let Some(name) = entry.file_name().to_str().filter(|n| !is_build_artifact(n)) {
                                 indexes.push(format!("{} (global)", name));
                             }
                         }
@@ -271,11 +452,33 @@ impl LeannMcpServer {
     }
 }
 
+/// The indexed source `requested` names: an exact match, else the only source
+/// ending with it at a path boundary
+fn find_source<'a>(sources: impl Iterator<Item = &'a String>, requested: &str) -> anyhow::Result<String> {
+    let requested = requested.trim_start_matches("./");
+    let mut matches = Vec::new();
+    for source in sources {
+        if source == requested {
+            return Ok(source.clone());
+        }
+        let boundary = source.len().checked_sub(requested.len() + 1).map(|i| source.as_bytes()[i]);
+        if source.ends_with(requested) && matches!(boundary, Some(b'/' | b'\\')) {
+            matches.push(source.clone());
+        }
+    }
+    match matches.len() {
+        0 => anyhow::bail!("No file '{}' in the index", requested),
+        1 => Ok(matches.remove(0)),
+        _ => anyhow::bail!("'{}' matches several files: {}", requested, matches.join(", ")),
+    }
+}
+
 #[tool_handler]
+#[prompt_handler]
 impl ServerHandler for LeannMcpServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder().enable_tools().enable_prompts().build(),
             server_info: Implementation {
                 name: "leann-mcp".to_string(),
                 title: Some("LEANN MCP Server".to_string()),
@@ -285,7 +488,8 @@ impl ServerHandler for LeannMcpServer {
             },
             instructions: Some(
                 "LEANN vector database MCP server. Use 'search' to find relevant documents \
-                 and 'list_indexes' to see available indexes."
+                 and 'list_indexes' to see available indexes. The 'answer-from-index' and \
+                 'summarize-file' prompts fill an index's passages into RAG prompts."
                     .to_string(),
             ),
             ..Default::default()
@@ -294,12 +498,7 @@ impl ServerHandler for LeannMcpServer {
 }
 
 pub async fn run(args: McpArgs, _verbose: bool) -> anyhow::Result<()> {
-    // Initialize logging to stderr (stdout is used for MCP protocol)
-    tracing_subscriber::fmt()
-        .with_writer(std::io::stderr)
-        .with_max_level(tracing::Level::INFO)
-        .init();
-
+    // Logs already go to stderr; stdout is used for MCP protocol
     info!("Starting LEANN MCP server...");
 
     let server = LeannMcpServer::new(
@@ -309,6 +508,10 @@ pub async fn run(args: McpArgs, _verbose: bool) -> anyhow::Result<()> {
         args.embedding_host,
     );
 
+    if args.transport == "sse" {
+        return serve_http(server, &args.host, args.port).await;
+    }
+
     // Serve using stdio transport
     let service = server.serve((stdin(), stdout())).await?;
 
@@ -317,3 +520,36 @@ pub async fn run(args: McpArgs, _verbose: bool) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Serve MCP over HTTP at `/mcp`, with a server per client session
+async fn serve_http(server: LeannMcpServer, host: &str, port: u16) -> anyhow::Result<()> {
+    let service = StreamableHttpService::new(
+        move || Ok(server.clone()),
+        LocalSessionManager::default().into(),
+        StreamableHttpServerConfig::default(),
+    );
+    let app = axum::Router::new().nest_service("/mcp", service);
+
+    let addr = format!("{}:{}", host, port);
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    println!("LEANN MCP server listening on http://{}/mcp", addr);
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_source() {
+        let sources = ["/repo/src/main.rs".to_string(), "/repo/src/cli/main.rs".to_string(), "/repo/README.md".to_string()];
+        assert_eq!(find_source(sources.iter(), "/repo/README.md").unwrap(), "/repo/README.md");
+        assert_eq!(find_source(sources.iter(), "README.md").unwrap(), "/repo/README.md");
+        assert_eq!(find_source(sources.iter(), "cli/main.rs").unwrap(), "/repo/src/cli/main.rs");
+        assert!(find_source(sources.iter(), "main.rs").unwrap_err().to_string().contains("several files"));
+        // Only whole path components match
+        assert!(find_source(sources.iter(), "ME.md").is_err());
+    }
+}
@@ -2,31 +2,81 @@
 
 pub mod build;
 mod search;
+mod search_all;
+mod grep;
 mod ask;
+mod run;
 mod list;
+mod info;
+mod analytics;
+mod feedback;
+mod summarize;
+mod graph;
+mod raptor;
+mod topics;
+mod dedup_report;
+mod usage;
 mod remove;
+mod remove_docs;
+mod compact;
+mod export;
+mod rollback;
+mod snapshot;
 mod react;
+mod repl;
+mod sessions;
 mod serve;
+mod daemon;
 mod update;
+mod stale;
+mod reembed;
+mod cancel;
 mod prune;
 mod config_cmd;
+mod highlight;
+mod history;
 #[cfg(feature = "mcp")]
 mod mcp;
+#[cfg(feature = "tui")]
+mod tui;
 
 use clap::{Parser, Subcommand};
 
 pub use build::BuildArgs;
 pub use search::SearchArgs;
+pub use search_all::SearchAllArgs;
+pub use grep::GrepArgs;
 pub use ask::AskArgs;
+pub use run::RunArgs;
 pub use list::ListArgs;
+pub use info::InfoArgs;
+pub use analytics::AnalyticsArgs;
+pub use feedback::FeedbackArgs;
+pub use summarize::SummarizeArgs;
+pub use graph::GraphArgs;
+pub use topics::TopicsArgs;
+pub use dedup_report::DedupReportArgs;
+pub use usage::UsageArgs;
 pub use remove::RemoveArgs;
+pub use remove_docs::RemoveDocsArgs;
+pub use compact::CompactArgs;
+pub use export::ExportArgs;
+pub use rollback::RollbackArgs;
+pub use snapshot::{RestoreArgs, SnapshotArgs};
 pub use react::ReactArgs;
+pub use sessions::SessionsArgs;
 pub use serve::ServeArgs;
+pub use daemon::DaemonArgs;
 pub use update::UpdateArgs;
+pub use stale::StaleArgs;
+pub use reembed::ReembedArgs;
+pub use cancel::CancelArgs;
 pub use prune::PruneArgs;
 pub use config_cmd::ConfigArgs;
 #[cfg(feature = "mcp")]
 pub use mcp::McpArgs;
+#[cfg(feature = "tui")]
+pub use tui::TuiArgs;
 
 /// LEANN - Lightweight vector database for RAG
 #[derive(Parser)]
@@ -42,6 +92,28 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub quiet: bool,
 
+    /// Refuse network access outside localhost (cached or local models only)
+    #[arg(long, global = true, env = "LEANN_OFFLINE", value_parser = clap::builder::BoolishValueParser::new())]
+    pub offline: bool,
+
+    /// Config profile to apply (a [profile.<name>] section of config.toml)
+    #[arg(long, global = true, env = "LEANN_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Seconds before an API request times out (default: [http] timeout, or 120)
+    #[arg(long, global = true, env = "LEANN_HTTP_TIMEOUT")]
+    pub timeout: Option<u64>,
+
+    /// PEM file of CA certificates to trust for API requests, e.g. behind a
+    /// TLS-inspecting proxy (default: [http] ca_cert)
+    #[arg(long, global = true, env = "LEANN_CA_CERT")]
+    pub ca_cert: Option<std::path::PathBuf>,
+
+    /// Export tracing spans to this OTLP/HTTP collector (e.g. http://localhost:4318)
+    #[cfg(feature = "otel")]
+    #[arg(long, global = true, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    pub otel_endpoint: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -54,24 +126,92 @@ pub enum Commands {
     /// Update an existing index with new documents
     Update(UpdateArgs),
 
+    /// Report files that are new, modified, or deleted since an index was built
+    Stale(StaleArgs),
+
+    /// Re-embed an index's stored passages with a different embedding model
+    Reembed(ReembedArgs),
+
+    /// Stop a build or update running inside `leann serve`, keeping its
+    /// embeddings so running it again resumes
+    Cancel(CancelArgs),
+
     /// Search documents in an index
     Search(SearchArgs),
 
+    /// Search all local and global indexes
+    SearchAll(SearchAllArgs),
+
+    /// Match a pattern exactly and search its meaning, with the hits interleaved
+    Grep(GrepArgs),
+
     /// Ask questions using RAG
     Ask(AskArgs),
 
+    /// Run a retrieval pipeline declared in a YAML file
+    Run(RunArgs),
+
     /// ReAct agent for multi-turn reasoning
     React(ReactArgs),
 
+    /// List and replay saved ReAct sessions (`react --session`)
+    Sessions(SessionsArgs),
+
     /// Start HTTP API server
     Serve(ServeArgs),
 
+    /// Keep indexes loaded in the background so searches start instantly
+    Daemon(DaemonArgs),
+
     /// List all indexes
     List(ListArgs),
 
+    /// Show detailed statistics for an index
+    Info(InfoArgs),
+
+    /// Summarize logged queries for an index
+    Analytics(AnalyticsArgs),
+
+    /// Mark passages good or bad for a query, to rank them for similar queries
+    Feedback(FeedbackArgs),
+
+    /// Cluster an index's passages into topics with representative passages
+    Topics(TopicsArgs),
+
+    /// Summarize an index's files and the whole corpus with an LLM, storing
+    /// the summaries as searchable passages
+    Summarize(SummarizeArgs),
+
+    /// Query the knowledge graph of an index built with --graph
+    Graph(GraphArgs),
+
+    /// Report near-duplicate passages, grouped by source file
+    DedupReport(DedupReportArgs),
+
+    /// Show cumulative embedding and LLM token usage and cost
+    Usage(UsageArgs),
+
     /// Remove an index
     Remove(RemoveArgs),
 
+    /// Remove the passages of files or directories from an index
+    RemoveDocs(RemoveDocsArgs),
+
+    /// Drop deleted passages from an index's passage file
+    Compact(CompactArgs),
+
+    /// Export passages and vectors to Qdrant or a Parquet file (for LanceDB)
+    Export(ExportArgs),
+
+    /// Restore the build an index had before its last rebuild
+    Rollback(RollbackArgs),
+
+    /// Save a named, timestamped snapshot of an index
+    Snapshot(SnapshotArgs),
+
+    /// Restore an index from a snapshot
+    Restore(RestoreArgs),
+
     /// Prune embeddings to enable recomputation mode
     Prune(PruneArgs),
 
@@ -81,23 +221,81 @@ pub enum Commands {
     /// Start MCP server for Claude Code integration
     #[cfg(feature = "mcp")]
     Mcp(McpArgs),
+
+    /// Browse search results interactively in the terminal
+    #[cfg(feature = "tui")]
+    Tui(TuiArgs),
 }
 
 impl Cli {
+    /// Whether the command prints JSON (`--format json`), so failures should
+    /// be reported as a JSON error envelope too
+    pub fn json_errors(&self) -> bool {
+        match &self.command {
+            Commands::Search(args) => args.format == "json" || args.explain,
+            Commands::SearchAll(args) => args.format == "json",
+            Commands::Grep(args) => args.format == "json",
+            Commands::Info(args) => args.format == "json",
+            Commands::Analytics(args) => args.format == "json",
+            Commands::Topics(args) => args.format == "json",
+            Commands::Summarize(args) => args.format == "json",
+            Commands::Sessions(args) => args.format() == "json",
+            Commands::Graph(args) => match &args.command {
+                graph::GraphCommands::Query { format, .. } => format == "json",
+            },
+            Commands::DedupReport(args) => args.format == "json",
+            Commands::Usage(args) => args.format == "json",
+            Commands::Stale(args) => args.format == "json",
+            Commands::Run(args) => args.format == "json",
+            _ => false,
+        }
+    }
+
     pub async fn run(self) -> anyhow::Result<()> {
+        crate::config::select_profile(self.profile.clone())?;
+        let config = crate::config::Config::load();
+        crate::http::set_offline(self.offline || config.offline);
+        let mut http = config.http;
+        http.timeout = self.timeout.or(http.timeout);
+        http.ca_cert = self.ca_cert.clone().or(http.ca_cert);
+        crate::http::configure(&http)?;
         match self.command {
             Commands::Build(args) => build::run(args, self.verbose).await,
             Commands::Update(args) => update::run(args, self.verbose).await,
+            Commands::Stale(args) => stale::run(args).await,
+            Commands::Reembed(args) => reembed::run(args).await,
+            Commands::Cancel(args) => cancel::run(args).await,
             Commands::Search(args) => search::run(args, self.verbose).await,
+            Commands::SearchAll(args) => search_all::run(args, self.verbose).await,
+            Commands::Grep(args) => grep::run(args).await,
             Commands::Ask(args) => ask::run(args, self.verbose).await,
+            Commands::Run(args) => run::run(args).await,
             Commands::React(args) => react::run(args, self.verbose).await,
+            Commands::Sessions(args) => sessions::run(args).await,
             Commands::Serve(args) => serve::run(args, self.verbose).await,
+            Commands::Daemon(args) => daemon::run(args).await,
             Commands::List(args) => list::run(args).await,
+            Commands::Info(args) => info::run(args).await,
+            Commands::Analytics(args) => analytics::run(args).await,
+            Commands::Feedback(args) => feedback::run(args).await,
+            Commands::Topics(args) => topics::run(args).await,
+            Commands::Summarize(args) => summarize::run(args, self.verbose).await,
+            Commands::Graph(args) => graph::run(args).await,
+            Commands::DedupReport(args) => dedup_report::run(args).await,
+            Commands::Usage(args) => usage::run(args).await,
             Commands::Remove(args) => remove::run(args).await,
+            Commands::RemoveDocs(args) => remove_docs::run(args).await,
+            Commands::Compact(args) => compact::run(args).await,
+            Commands::Export(args) => export::run(args).await,
+            Commands::Rollback(args) => rollback::run(args).await,
+            Commands::Snapshot(args) => snapshot::run_snapshot(args).await,
+            Commands::Restore(args) => snapshot::run_restore(args).await,
             Commands::Prune(args) => prune::run(args).await,
             Commands::Config(args) => config_cmd::run(args).await,
             #[cfg(feature = "mcp")]
             Commands::Mcp(args) => mcp::run(args, self.verbose).await,
+            #[cfg(feature = "tui")]
+            Commands::Tui(args) => tui::run(args).await,
         }
     }
 }
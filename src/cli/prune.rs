@@ -23,6 +23,14 @@ pub async fn run(args: PruneArgs) -> anyhow::Result<()> {
     // Load metadata
     let mut meta = IndexMeta::load(&meta_path)?;
 
+    if !meta.backend()?.info().supports_pruning {
+        anyhow::bail!(
+            "Index '{}' uses the {} backend, which needs its embeddings file to search; it can't be pruned.",
+            args.index_name,
+            meta.backend_name
+        );
+    }
+
     // Check if embeddings exist
     let embeddings_path = EmbeddingsStore::path_for_index(&index_path);
     if !embeddings_path.exists() {
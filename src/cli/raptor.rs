@@ -0,0 +1,169 @@
+//! RAPTOR-style summary tree - recursive cluster summaries indexed next to
+//! the chunks
+//!
+//! With `leann build --raptor`, the embedded chunks are clustered with k-means
+//! and an LLM summarizes each cluster; the summaries are embedded and
+//! clustered again, level by level, until one summary covers the corpus or
+//! the level limit is reached. Summary nodes are indexed alongside the chunks
+//! with `type=summary`, `summary_of=cluster`, their `level`, and the ids of
+//! their `children`, so broad questions match a high-level summary while
+//! specific ones still match raw chunks.
+
+use crate::backend::{assign, kmeans, XorShift};
+use crate::chunker::Chunk;
+use crate::index::NAMESPACE_FIELD;
+
+use super::summarize::{Subject, Summarizer, SUMMARY_TYPE};
+
+/// Seed for the initial cluster centers, so rebuilds give the same tree
+const CLUSTER_SEED: u64 = 42;
+
+/// A chunk or summary the next level clusters
+#[derive(Clone)]
+pub(super) struct RaptorNode {
+    id: String,
+    text: String,
+    embedding: Vec<f32>,
+    namespace: Option<serde_json::Value>,
+}
+
+/// Collects embedded chunks during a build, then summarizes them level by level
+pub(super) struct RaptorTree {
+    leaves: Vec<RaptorNode>,
+    dimensions: usize,
+    cluster_size: usize,
+    pub(super) max_levels: usize,
+}
+
+impl RaptorTree {
+    pub(super) fn new(dimensions: usize, cluster_size: usize, max_levels: usize) -> anyhow::Result<Self> {
+        if cluster_size < 2 {
+            anyhow::bail!("--raptor-cluster-size must be at least 2");
+        }
+        Ok(Self { leaves: Vec::new(), dimensions, cluster_size, max_levels })
+    }
+
+    /// Keep embedded chunks as the leaves of the tree
+    pub(super) fn add_leaves(&mut self, chunks: &[Chunk], embeddings: Vec<Vec<f32>>) {
+        self.leaves.extend(nodes(chunks, embeddings));
+    }
+
+    /// The leaves, which the first level summarizes
+    pub(super) fn take_leaves(&mut self) -> Vec<RaptorNode> {
+        std::mem::take(&mut self.leaves)
+    }
+
+    /// Summarize the clusters of `nodes` into summary chunks of `level`; nodes
+    /// alone in their cluster are returned to be carried up unchanged
+    pub(super) async fn summarize_level(
+        &self,
+        nodes: &[RaptorNode],
+        level: usize,
+        summarizer: &Summarizer<'_>,
+    ) -> anyhow::Result<(Vec<Chunk>, Vec<RaptorNode>)> {
+        let mut summaries = Vec::new();
+        let mut carried = Vec::new();
+        for members in cluster(nodes, self.dimensions, self.cluster_size) {
+            if members.len() < 2 {
+                carried.extend(members.into_iter().map(|i| nodes[i].clone()));
+                continue;
+            }
+            let texts = members.iter().map(|&i| nodes[i].text.clone()).collect();
+            let text = summarizer.map_reduce(texts, &Subject::Cluster(level)).await?;
+            let children: Vec<&RaptorNode> = members.iter().map(|&i| &nodes[i]).collect();
+            summaries.push(summary_chunk(text, level, &children));
+        }
+        Ok((summaries, carried))
+    }
+}
+
+/// Embedded chunks (or summaries) as nodes of the tree
+pub(super) fn nodes(chunks: &[Chunk], embeddings: Vec<Vec<f32>>) -> impl Iterator<Item = RaptorNode> + '_ {
+    chunks.iter().zip(embeddings).map(|(chunk, embedding)| RaptorNode {
+        id: chunk.id.clone(),
+        text: chunk.text.clone(),
+        embedding,
+        namespace: chunk.metadata.get(NAMESPACE_FIELD).cloned(),
+    })
+}
+
+/// Group nodes into clusters of about `cluster_size` by embedding
+fn cluster(nodes: &[RaptorNode], dimensions: usize, cluster_size: usize) -> Vec<Vec<usize>> {
+    let k = nodes.len().div_ceil(cluster_size);
+    if k <= 1 {
+        return vec![(0..nodes.len()).collect()];
+    }
+    let points: Vec<f32> = nodes.iter().flat_map(|n| n.embedding.iter().copied()).collect();
+    let centroids = kmeans(&points, dimensions, k, &mut XorShift::new(CLUSTER_SEED));
+    let mut clusters = vec![Vec::new(); k];
+    for (i, cluster) in assign(&points, dimensions, &centroids).into_iter().enumerate() {
+        clusters[cluster as usize].push(i);
+    }
+    clusters.retain(|members| !members.is_empty());
+    clusters
+}
+
+/// A summary node as a chunk, with an id derived from its children so each
+/// cluster gets its own passage
+fn summary_chunk(text: String, level: usize, children: &[&RaptorNode]) -> Chunk {
+    let ids: Vec<&str> = children.iter().map(|c| c.id.as_str()).collect();
+    let mut metadata = serde_json::json!({
+        "type": SUMMARY_TYPE,
+        "summary_of": "cluster",
+        "level": level,
+        "children": ids,
+    });
+    // Summaries stay in a namespace when all their children are in it
+    let namespace = children[0].namespace.clone();
+    if let Some(namespace) = namespace.filter(|ns| children.iter().all(|c| c.namespace.as_ref() == Some(ns))) {
+        metadata[NAMESPACE_FIELD] = namespace;
+    }
+    Chunk {
+        id: crate::chunker::stable_chunk_id("summary:cluster", level, ids.join("\n").as_bytes()),
+        text,
+        metadata,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, embedding: [f32; 2], namespace: Option<&str>) -> RaptorNode {
+        RaptorNode {
+            id: id.to_string(),
+            text: format!("text of {}", id),
+            embedding: embedding.to_vec(),
+            namespace: namespace.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn test_cluster_groups_nearby_nodes() {
+        let nodes = [
+            node("a", [0.0, 0.0], None),
+            node("b", [0.1, 0.0], None),
+            node("c", [10.0, 10.0], None),
+            node("d", [10.0, 10.1], None),
+        ];
+        let mut clusters = cluster(&nodes, 2, 2);
+        clusters.sort();
+        assert_eq!(clusters, vec![vec![0, 1], vec![2, 3]]);
+        assert_eq!(cluster(&nodes, 2, 8), vec![vec![0, 1, 2, 3]]);
+    }
+
+    #[test]
+    fn test_summary_chunk_metadata() {
+        let (a, b, c) = (node("a", [0.0; 2], Some("docs")), node("b", [0.0; 2], Some("docs")), node("c", [0.0; 2], None));
+        let chunk = summary_chunk("both".to_string(), 1, &[&a, &b]);
+        assert_eq!(chunk.metadata["type"], "summary");
+        assert_eq!(chunk.metadata["summary_of"], "cluster");
+        assert_eq!(chunk.metadata["level"], 1);
+        assert_eq!(chunk.metadata["children"], serde_json::json!(["a", "b"]));
+        assert_eq!(chunk.metadata[NAMESPACE_FIELD], "docs");
+
+        let mixed = summary_chunk("both".to_string(), 1, &[&a, &c]);
+        assert!(mixed.metadata.get(NAMESPACE_FIELD).is_none());
+        assert_ne!(mixed.id, chunk.id);
+    }
+}
@@ -1,27 +1,60 @@
 //! ReAct agent command - multi-turn reasoning with tools
+//!
+//! With `--session <name>`, the trace and answer are saved and later questions
+//! in the session see the earlier ones (see [`super::sessions`]). With
+//! `--interactive`, the agent keeps its scratchpad - the reasoning, searches,
+//! and observations so far - across the questions asked at the prompt.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
 
 use clap::Args;
 use tracing::info;
 
-use crate::embedding::{EmbeddingMode, EmbeddingProvider};
-use crate::index::{find_index, IndexMeta, IndexSearcher};
-use crate::llm::{LlmProvider, LlmType};
+use crate::config::Config;
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::index::{resolve_index, IndexMeta, IndexSearcher};
+use crate::llm::{
+    ChatMessage, LlmOverrides, LlmProvider, LlmType, PromptTemplate, TokenUsage, Tool, ToolCall, ToolChoice, ToolResponse,
+    DEFAULT_REACT_TEMPLATE,
+};
+
+use super::ask::GenerationArgs;
+use super::repl::{print_commands, Repl};
+use super::sessions::{print_steps, Session, Step};
 
 #[derive(Args)]
 pub struct ReactArgs {
-    /// Index name to query
+    /// Index name or s3://bucket/prefix URL to query
     pub index_name: String,
 
-    /// Question to answer
-    pub query: String,
+    /// Question to answer (omit for interactive mode)
+    pub query: Option<String>,
+
+    /// Ask questions at a prompt; the agent keeps its scratchpad between them
+    #[arg(long)]
+    pub interactive: bool,
+
+    /// LLM provider (default: `[llm] provider` in config.toml, else ollama)
+    #[cfg(feature = "local-llm")]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated", "local"])]
+    pub llm: Option<String>,
 
-    /// LLM provider
-    #[arg(long, default_value = "ollama", value_parser = ["ollama", "openai", "anthropic", "simulated"])]
-    pub llm: String,
+    /// LLM provider (default: `[llm] provider` in config.toml, else ollama)
+    #[cfg(not(feature = "local-llm"))]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated"])]
+    pub llm: Option<String>,
 
-    /// LLM model name
-    #[arg(long, default_value = "qwen3:8b")]
-    pub model: String,
+    /// LLM model name (default: `[llm] model` in config.toml, else qwen3:8b)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Tokenizer file for local GGUF models (default: tokenizer.json next to the model)
+    #[cfg(feature = "local-llm")]
+    #[arg(long)]
+    pub tokenizer: Option<String>,
 
     /// Ollama host
     #[arg(long, env = "OLLAMA_HOST")]
@@ -43,14 +76,49 @@ pub struct ReactArgs {
     #[arg(long, default_value = "3")]
     pub top_k: usize,
 
+    /// Stop reasoning after this many seconds and give the final answer
+    #[arg(long)]
+    pub timeout: Option<f64>,
+
+    /// Stop reasoning once this many LLM tokens (input and output) are used
+    /// and give the final answer
+    #[arg(long)]
+    pub token_budget: Option<usize>,
+
+    /// Steps without progress (repeated searches, searches finding no new
+    /// passages, no action) before the agent is asked to reflect; 0 disables
+    #[arg(long, default_value = "2")]
+    pub reflect_after: usize,
+
+    /// Parse actions from the model's text even when the provider has
+    /// native tool calling (OpenAI, Anthropic, Ollama)
+    #[arg(long)]
+    pub text_actions: bool,
+
     /// Show reasoning trace
     #[arg(long)]
     pub verbose: bool,
 
+    /// Save the trace and answer to this session, resuming its earlier
+    /// questions and answers as context (see `leann sessions`)
+    #[arg(long)]
+    pub session: Option<String>,
+
+    /// Prompt template file for the agent's instructions (Jinja-style; variables: question, history, tool_calling)
+    #[arg(long)]
+    pub prompt_template: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+
     /// API key for embedding service
     #[arg(long, env = "OPENAI_API_KEY")]
     pub embedding_api_key: Option<String>,
 
+    /// OpenAI API base URL
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub embedding_api_base: Option<String>,
+
     /// Ollama host for embeddings
     #[arg(long, env = "OLLAMA_HOST")]
     pub embedding_host: Option<String>,
@@ -61,9 +129,15 @@ struct ReActAgent<'a> {
     embedding_provider: &'a EmbeddingProvider,
     searcher: &'a IndexSearcher,
     llm: &'a LlmProvider,
+    template: &'a PromptTemplate,
     top_k: usize,
     max_steps: usize,
     verbose: bool,
+    /// Embedding prompt template for search queries
+    query_template: String,
+    limits: Limits,
+    /// Take actions as native tool calls rather than parsing them from text
+    tool_calling: bool,
 }
 
 impl<'a> ReActAgent<'a> {
@@ -71,6 +145,7 @@ impl<'a> ReActAgent<'a> {
         embedding_provider: &'a EmbeddingProvider,
         searcher: &'a IndexSearcher,
         llm: &'a LlmProvider,
+        template: &'a PromptTemplate,
         top_k: usize,
         max_steps: usize,
         verbose: bool,
@@ -79,95 +154,212 @@ impl<'a> ReActAgent<'a> {
             embedding_provider,
             searcher,
             llm,
+            template,
             top_k,
             max_steps,
             verbose,
+            query_template: String::new(),
+            limits: Limits::default(),
+            tool_calling: false,
         }
     }
 
-    async fn run(&self, query: &str) -> anyhow::Result<String> {
-        let system_prompt = r#"You are a helpful assistant that answers questions using available tools.
-
-Available tools:
-1. search(query) - Search the knowledge base for relevant information
-2. finish(answer) - Provide the final answer
+    /// Embed search queries with the index's query template
+    fn with_query_template(mut self, template: String) -> Self {
+        self.query_template = template;
+        self
+    }
 
-For each step, use the following format:
-Thought: [Your reasoning about what to do next]
-Action: [tool_name(argument)]
+    fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
 
-After getting search results, you'll see:
-Observation: [results from the tool]
+    fn with_tool_calling(mut self, tool_calling: bool) -> Self {
+        self.tool_calling = tool_calling;
+        self
+    }
 
-Continue until you have enough information, then use finish(answer) to provide your final answer.
+    /// A scratchpad for `query`, given earlier questions and answers of the session
+    fn start(&self, query: &str, earlier: &str) -> Scratchpad {
+        let mut vars = HashMap::from([("question", query.to_string()), ("history", earlier.to_string())]);
+        let text_prompt = self.template.render(&vars);
+        let transcript = if self.tool_calling {
+            vars.insert("tool_calling", "true".to_string());
+            Transcript::Tools { messages: vec![ChatMessage::User(self.template.render(&vars))], fallback: text_prompt }
+        } else {
+            Transcript::Text(text_prompt)
+        };
+        Scratchpad { transcript, searched: HashSet::new(), seen_passages: HashSet::new() }
+    }
 
-Important:
-- Always search for relevant information before answering
-- If the first search doesn't give enough info, try different search queries
-- Be concise in your final answer
-"#;
+    /// Answer the question the scratchpad ends with; returns the answer and
+    /// the steps taken
+    async fn answer(&self, pad: &mut Scratchpad) -> anyhow::Result<(String, Vec<Step>)> {
+        let transcript = &mut pad.transcript;
+        let mut steps = Vec::new();
 
-        let mut history = format!(
-            "{}\n\nQuestion: {}\n\nLet me search for relevant information.\n",
-            system_prompt, query
-        );
+        let started = Instant::now();
+        let usage_before = self.llm.usage();
+        let mut stalled = 0;
+        let mut stop = "Maximum steps reached.";
 
         for step in 0..self.max_steps {
+            if let Some(reason) = self.limits.exceeded(started, &self.llm.usage().since(&usage_before)) {
+                info!("{}", reason);
+                stop = reason;
+                break;
+            }
+
             if self.verbose {
                 println!("\n--- Step {} ---", step + 1);
             }
 
             // Get next action from LLM
-            let response = self.llm.generate(&history).await?;
+            let reply = self.next(transcript, ToolChoice::Auto).await?;
 
             if self.verbose {
-                println!("LLM: {}", response);
+                println!("LLM: {}", reply.text);
             }
 
-            history.push_str(&response);
-            history.push('\n');
-
-            // Parse action
-            if let Some(action) = self.parse_action(&response) {
-                match action {
-                    Action::Search(search_query) => {
-                        if self.verbose {
-                            println!("Searching: {}", search_query);
-                        }
+            let mut trace = Step { response: reply.text.clone(), observation: None, reflection: false };
+
+            // Act on it
+            let progressed = match &reply.action {
+                Some(Action::Search(search_query)) if !pad.searched.insert(normalize_query(search_query)) => {
+                    // Repeating a search would only repeat its results
+                    let observation = format!(
+                        "You already searched for \"{}\"; its results are above. \
+                         Search for something different or use finish(answer).",
+                        search_query
+                    );
+                    if self.verbose {
+                        println!("Repeated search: {}", search_query);
+                    }
+                    transcript.observe(&reply, &observation);
+                    trace.observation = Some(observation);
+                    false
+                }
+                Some(Action::Search(search_query)) => {
+                    if self.verbose {
+                        println!("Searching: {}", search_query);
+                    }
 
-                        let observation = self.execute_search(&search_query).await?;
-                        history.push_str(&format!("Observation: {}\n\n", observation));
+                    let (observation, ids) = self.execute_search(search_query).await?;
+                    transcript.observe(&reply, &observation);
 
-                        if self.verbose {
-                            println!("Observation: {}", observation);
-                        }
-                    }
-                    Action::Finish(answer) => {
-                        return Ok(answer);
+                    if self.verbose {
+                        println!("Observation: {}", observation);
                     }
+                    trace.observation = Some(observation);
+                    // Progress means passages the agent hasn't seen yet
+                    let seen = pad.seen_passages.len();
+                    pad.seen_passages.extend(ids);
+                    pad.seen_passages.len() > seen
                 }
-            } else {
-                // No valid action found, try to extract answer
-                if response.to_lowercase().contains("final answer")
-                    || response.to_lowercase().contains("the answer is")
-                {
-                    return Ok(response);
+                Some(Action::Finish(answer)) => {
+                    let answer = answer.clone();
+                    transcript.settle(&reply);
+                    steps.push(trace);
+                    return Ok((answer, steps));
+                }
+                None if reply.call.is_some() => {
+                    transcript.observe(&reply, "Unknown tool or missing argument. Call search with a query or finish with an answer.");
+                    false
                 }
+                None => {
+                    // No valid action found, try to extract answer
+                    if reply.text.to_lowercase().contains("final answer")
+                        || reply.text.to_lowercase().contains("the answer is")
+                    {
+                        steps.push(trace);
+                        return Ok((reply.text, steps));
+                    }
 
-                // Prompt for action
-                history.push_str("Please use an action: search(query) or finish(answer)\n");
+                    // Prompt for action
+                    transcript.instruct("Please use an action: search(query) or finish(answer)\n");
+                    false
+                }
+            };
+            steps.push(trace);
+
+            // Stalled for a while: step back and plan before the next action
+            stalled = if progressed { 0 } else { stalled + 1 };
+            if self.limits.reflect_after > 0 && stalled >= self.limits.reflect_after {
+                stalled = 0;
+                transcript.instruct(REFLECTION_PROMPT);
+                let reflection = self.next(transcript, ToolChoice::None).await?;
+                if reflection.call.is_some() {
+                    transcript.observe(&reflection, "Not run: reflect first, then act in the next step.");
+                }
+                if self.verbose {
+                    println!("\n--- Reflection ---\n{}", reflection.text);
+                }
+                steps.push(Step { response: reflection.text, observation: None, reflection: true });
             }
         }
 
-        // Max steps reached, ask for final answer
-        history.push_str("Maximum steps reached. Please provide your final answer using finish(answer).\n");
-        let final_response = self.llm.generate(&history).await?;
+        // Out of steps, time, or tokens: ask for the final answer
+        transcript.instruct(&format!("{} Please provide your final answer using finish(answer).\n", stop));
+        let reply = self.next(transcript, ToolChoice::Auto).await?;
+        transcript.settle(&reply);
+        steps.push(Step { response: reply.text.clone(), observation: None, reflection: false });
 
-        if let Some(Action::Finish(answer)) = self.parse_action(&final_response) {
-            Ok(answer)
-        } else {
-            Ok(final_response)
+        match reply.action {
+            Some(Action::Finish(answer)) => Ok((answer, steps)),
+            _ => Ok((reply.text, steps)),
+        }
+    }
+
+    /// Ask the LLM for its next reply and record it in the transcript
+    async fn next(&self, transcript: &mut Transcript, choice: ToolChoice) -> anyhow::Result<Reply> {
+        if let Transcript::Tools { messages, fallback } = transcript {
+            if let Some(response) = self.llm.generate_with_tools(messages, &TOOLS[..], choice).await? {
+                return Ok(self.tool_reply(messages, response));
+            }
+            // The model has no tool calling: write actions in the text instead
+            info!("Model {} can't call tools; parsing actions from its text", self.llm.model_name());
+            let text = std::mem::take(fallback) + &flatten(&messages[1..]);
+            *transcript = Transcript::Text(text);
         }
+        let Transcript::Text(history) = transcript else {
+            unreachable!("tool transcripts either reply or become text above");
+        };
+        let response = self.llm.generate(history).await?;
+        history.push_str(&response);
+        history.push('\n');
+        let action = self.parse_action(&response);
+        Ok(Reply { text: response, action, call: None })
+    }
+
+    /// Take the first tool call of a response as the action; any others are
+    /// declined so every call still gets a result
+    fn tool_reply(&self, messages: &mut Vec<ChatMessage>, response: ToolResponse) -> Reply {
+        let mut calls = response.calls.into_iter();
+        let call = calls.next();
+        let declined: Vec<ToolCall> = calls.collect();
+        messages.push(ChatMessage::Assistant {
+            text: response.text.clone(),
+            calls: call.iter().chain(&declined).cloned().collect(),
+        });
+        for extra in declined {
+            messages.push(ChatMessage::ToolResult {
+                call_id: extra.id,
+                name: extra.name,
+                content: "Not run: one action per step.".to_string(),
+            });
+        }
+
+        let mut text = response.text.trim().to_string();
+        let action = match &call {
+            Some(call) => {
+                text = format!("{}\nAction: {}({})", text, call.name, call.arguments).trim().to_string();
+                tool_action(call)
+            }
+            // Text alone: an action written out, else the answer
+            None => self.parse_action(&text).or_else(|| (!text.is_empty()).then(|| Action::Finish(text.clone()))),
+        };
+        Reply { text, action, call }
     }
 
     fn parse_action(&self, response: &str) -> Option<Action> {
@@ -217,12 +409,16 @@ Important:
         None
     }
 
-    async fn execute_search(&self, query: &str) -> anyhow::Result<String> {
-        let query_embedding = self.embedding_provider.embed(&[query]).await?;
+    /// Search the index; returns the observation and the ids of the passages in it
+    async fn execute_search(&self, query: &str) -> anyhow::Result<(String, Vec<String>)> {
+        let query_embedding = self
+            .embedding_provider
+            .embed_for_task(&[query], EmbeddingTask::Query, &self.query_template)
+            .await?;
         let results = self.searcher.search(&query_embedding[0], self.top_k, 64)?;
 
         if results.is_empty() {
-            return Ok("No relevant results found.".to_string());
+            return Ok(("No relevant results found.".to_string(), Vec::new()));
         }
 
         let mut observation = String::new();
@@ -235,18 +431,213 @@ Important:
             observation.push_str(&format!("[{}] {}\n", i + 1, snippet));
         }
 
-        Ok(observation)
+        Ok((observation, results.into_iter().map(|r| r.id).collect()))
+    }
+}
+
+/// Asked of the agent when its recent steps found nothing new
+const REFLECTION_PROMPT: &str = "Your recent steps found no new information. Before the next action, reflect:\n\
+Reflection:\n\
+1. What have you learned so far?\n\
+2. What information is still missing?\n\
+3. Which different search would find it, or can you already finish(answer)?\n";
+
+/// Limits on one run of the agent, besides its step count
+#[derive(Debug, Clone, Copy, Default)]
+struct Limits {
+    timeout: Option<Duration>,
+    /// LLM tokens (input and output)
+    token_budget: Option<usize>,
+    /// Steps without progress before the agent reflects (0: never)
+    reflect_after: usize,
+}
+
+impl Limits {
+    /// Why the run must stop, if a limit is reached
+    fn exceeded(&self, started: Instant, usage: &TokenUsage) -> Option<&'static str> {
+        if self.timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+            return Some("Time limit reached.");
+        }
+        if self.token_budget.is_some_and(|budget| usage.input_tokens + usage.output_tokens >= budget) {
+            return Some("Token budget used up.");
+        }
+        None
     }
 }
 
+/// A search query compared case- and whitespace-insensitively
+fn normalize_query(query: &str) -> String {
+    query.to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 enum Action {
     Search(String),
     Finish(String),
 }
 
+/// A reply of the LLM and the action it takes
+struct Reply {
+    /// The reply as shown in traces, tool calls written out
+    text: String,
+    action: Option<Action>,
+    /// The native tool call to answer, if any
+    call: Option<ToolCall>,
+}
+
+/// What the agent has done so far; kept across the questions of an
+/// interactive session
+struct Scratchpad {
+    transcript: Transcript,
+    /// Searches run, normalized
+    searched: HashSet<String>,
+    /// Ids of the passages observed
+    seen_passages: HashSet<String>,
+}
+
+impl Scratchpad {
+    /// Continue with another question
+    fn follow_up(&mut self, question: &str) {
+        self.transcript.instruct(&format!("\nNext question: {}\n\n", question));
+    }
+
+    /// Whether actions are native tool calls, rather than written in the text
+    fn tool_calling(&self) -> bool {
+        matches!(self.transcript, Transcript::Tools { .. })
+    }
+}
+
+/// The conversation so far, in the form the LLM is prompted with
+enum Transcript {
+    /// One growing prompt, with actions written as `search(...)` and `finish(...)`
+    Text(String),
+    /// Chat messages with native tool calls; `fallback` is the text prompt,
+    /// used if the model turns out to have no tool calling
+    Tools { messages: Vec<ChatMessage>, fallback: String },
+}
+
+impl Transcript {
+    /// Record what the reply's action observed
+    fn observe(&mut self, reply: &Reply, observation: &str) {
+        match (self, &reply.call) {
+            (Transcript::Text(history), _) => history.push_str(&format!("Observation: {}\n\n", observation)),
+            (Transcript::Tools { messages, .. }, Some(call)) => messages.push(ChatMessage::ToolResult {
+                call_id: call.id.clone(),
+                name: call.name.clone(),
+                content: observation.to_string(),
+            }),
+            (Transcript::Tools { messages, .. }, None) => {
+                messages.push(ChatMessage::User(format!("Observation: {}", observation)))
+            }
+        }
+    }
+
+    /// Give the last reply's tool call, if any, a result, so the
+    /// conversation can go on after the answer
+    fn settle(&mut self, reply: &Reply) {
+        if reply.call.is_some() {
+            let result = match reply.action {
+                Some(Action::Finish(_)) => "Answer given.",
+                _ => "Not run: the final answer was due.",
+            };
+            self.observe(reply, result);
+        }
+    }
+
+    /// Add an instruction for the LLM
+    fn instruct(&mut self, text: &str) {
+        match self {
+            Transcript::Text(history) => history.push_str(text),
+            Transcript::Tools { messages, .. } => messages.push(ChatMessage::User(text.trim().to_string())),
+        }
+    }
+}
+
+/// The agent's tools for native tool calling
+static TOOLS: LazyLock<[Tool; 2]> = LazyLock::new(|| {
+    [
+        Tool {
+            name: "search",
+            description: "Search the knowledge base for passages relevant to a query",
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": { "query": { "type": "string", "description": "What to search for" } },
+                "required": ["query"],
+            }),
+        },
+        Tool {
+            name: "finish",
+            description: "Give the final answer to the question",
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": { "answer": { "type": "string", "description": "The final answer" } },
+                "required": ["answer"],
+            }),
+        },
+    ]
+});
+
+/// The action of a tool call, if it names a tool with its argument
+fn tool_action(call: &ToolCall) -> Option<Action> {
+    let argument = |name: &str| call.arguments.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    match call.name.as_str() {
+        "search" => argument("query").map(Action::Search),
+        "finish" => argument("answer").map(Action::Finish),
+        _ => None,
+    }
+}
+
+/// A tool as a call with its required arguments, e.g. `search(query)`
+fn tool_signature(tool: &Tool) -> String {
+    let arguments: Vec<&str> = tool.parameters["required"]
+        .as_array()
+        .map(|required| required.iter().filter_map(|a| a.as_str()).collect())
+        .unwrap_or_default();
+    format!("{}({})", tool.name, arguments.join(", "))
+}
+
+/// Tool-calling messages as a text transcript
+fn flatten(messages: &[ChatMessage]) -> String {
+    let mut text = String::new();
+    for message in messages {
+        match message {
+            ChatMessage::User(instruction) => text.push_str(&format!("{}\n", instruction)),
+            ChatMessage::Assistant { text: reply, calls } => {
+                text.push_str(reply);
+                for call in calls {
+                    text.push_str(&format!("\nAction: {}({})", call.name, call.arguments));
+                }
+                text.push('\n');
+            }
+            ChatMessage::ToolResult { content, .. } => text.push_str(&format!("Observation: {}\n\n", content)),
+        }
+    }
+    text
+}
+
 pub async fn run(args: ReactArgs, _verbose: bool) -> anyhow::Result<()> {
+    if args.query.is_none() && !args.interactive {
+        anyhow::bail!("Question required in non-interactive mode. Use --interactive for interactive mode.");
+    }
+
+    // Resume the session, if any, before loading the index
+    let mut session = match &args.session {
+        Some(name) => {
+            let session = Session::load(name)?.unwrap_or_else(|| Session::new(name, &args.index_name));
+            if session.index != args.index_name {
+                anyhow::bail!(
+                    "Session '{}' searches index '{}'; use another session name for '{}'",
+                    name,
+                    session.index,
+                    args.index_name
+                );
+            }
+            Some(session)
+        }
+        None => None,
+    };
+
     // Find index
-    let index_dir = find_index(&args.index_name)?;
+    let index_dir = resolve_index(&args.index_name).await?;
     let meta_path = index_dir.join("documents.leann.meta.json");
     let index_path = index_dir.join("documents.leann");
 
@@ -258,65 +649,292 @@ pub async fn run(args: ReactArgs, _verbose: bool) -> anyhow::Result<()> {
         args.index_name, meta.passage_count
     );
 
+    // Resolve the prompt template: CLI, then index default, then config file
+    let config = Config::load();
+    let template = PromptTemplate::resolve(
+        args.prompt_template.as_deref(),
+        meta.prompt_templates.as_ref().and_then(|t| t.react.as_deref()),
+        config.prompts.react_path().as_deref(),
+        DEFAULT_REACT_TEMPLATE,
+    )?;
+
     // Create embedding provider
-    let embedding_mode = match meta.embedding_mode.as_str() {
-        "openai" => EmbeddingMode::OpenAI {
-            api_key: args.embedding_api_key.clone(),
-            base_url: None,
-        },
-        "ollama" => EmbeddingMode::Ollama {
-            host: args.embedding_host.clone(),
-        },
-        "gemini" => EmbeddingMode::Gemini {
-            api_key: std::env::var("GOOGLE_API_KEY").ok(),
-        },
-        _ => anyhow::bail!("Unknown embedding mode: {}", meta.embedding_mode),
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        ..Default::default()
     };
-
-    let embedding_provider = EmbeddingProvider::new(
-        meta.embedding_model.clone(),
-        embedding_mode,
-    ).await?;
+    let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &config.embedding).await?;
 
     // Load index
     let searcher = IndexSearcher::load(&index_path, &meta)?;
 
-    // Create LLM provider
-    let llm_type = match args.llm.as_str() {
-        "ollama" => LlmType::Ollama {
-            host: args.host.clone(),
-        },
-        "openai" => LlmType::OpenAI {
-            api_key: args.api_key.clone(),
-            base_url: args.api_base.clone(),
-        },
-        "anthropic" => LlmType::Anthropic {
-            api_key: args.api_key.clone(),
-            base_url: args.api_base.clone(),
-        },
-        "simulated" => LlmType::Simulated,
-        _ => anyhow::bail!("Unknown LLM provider: {}", args.llm),
+    // Create LLM provider: flags, then config.toml
+    let provider = args.llm.clone().unwrap_or_else(|| config.llm.provider.clone());
+    let model = args.model.clone().unwrap_or_else(|| config.llm.model.clone());
+    let llm_overrides = LlmOverrides {
+        host: args.host.clone(),
+        api_key: args.api_key.clone(),
+        api_base: args.api_base.clone(),
+        #[cfg(feature = "local-llm")]
+        tokenizer: args.tokenizer.clone(),
     };
+    let llm_type = LlmType::from_name(&provider, &llm_overrides, &config.llm)?;
 
-    let llm = LlmProvider::new(args.model.clone(), llm_type)?;
+    let llm = LlmProvider::new(model.clone(), llm_type)?
+        .with_options(args.generation.to_options())?
+        .with_redactor(args.generation.redactor()?);
 
-    println!("ReAct Agent using {} with model {}", args.llm, args.model);
-    println!("Question: {}\n", args.query);
+    println!("ReAct Agent using {} with model {}", provider, model);
+    if let Some(session) = session.as_ref().filter(|s| !s.turns.is_empty()) {
+        println!("Resuming session '{}' ({} earlier questions)", session.name, session.turns.len());
+    }
 
     // Run agent
     let agent = ReActAgent::new(
         &embedding_provider,
         &searcher,
         &llm,
+        &template,
         args.top_k,
         args.max_steps,
         args.verbose,
-    );
+    )
+    .with_query_template(meta.embedding_template(EmbeddingTask::Query))
+    .with_limits(Limits {
+        timeout: args.timeout.map(Duration::from_secs_f64),
+        token_budget: args.token_budget,
+        reflect_after: args.reflect_after,
+    })
+    .with_tool_calling(!args.text_actions && llm.supports_tools());
+
+    match args.query {
+        Some(query) if !args.interactive => {
+            println!("Question: {}\n", query);
+            let earlier = session.as_ref().map(Session::history).unwrap_or_default();
+            let mut pad = agent.start(&query, &earlier);
+            let (answer, steps) = agent.answer(&mut pad).await?;
+
+            println!("\n=== Final Answer ===\n{}", answer);
+            save_turn(session.as_mut(), &query, steps, &answer)
+        }
+        first => run_interactive(&agent, session, first).await,
+    }
+}
 
-    let answer = agent.run(&args.query).await?;
+/// Add a question to the session, if any, and save it
+fn save_turn(session: Option<&mut Session>, question: &str, steps: Vec<Step>, answer: &str) -> anyhow::Result<()> {
+    if let Some(session) = session {
+        session.push(question, steps, answer);
+        session.save()?;
+        info!("Saved to session '{}'", session.name);
+    }
+    Ok(())
+}
+
+/// Commands of the interactive mode
+const COMMANDS: &[(&str, &str)] = &[
+    ("/help", "Show this help message"),
+    ("/trace", "Show the reasoning trace since the last reset"),
+    ("/tools", "List the agent's tools"),
+    ("/reset", "Clear the scratchpad and start over"),
+    ("/quit", "Exit interactive mode"),
+];
+
+/// Answer questions from the prompt, starting with `first` if given; the
+/// scratchpad is kept until `/reset`
+async fn run_interactive(
+    agent: &ReActAgent<'_>,
+    mut session: Option<Session>,
+    first: Option<String>,
+) -> anyhow::Result<()> {
+    let mut repl = Repl::start("LEANN ReAct Interactive Mode", COMMANDS)?;
+    let mut pad: Option<Scratchpad> = None;
+    // Questions and traces since the last reset, for /trace
+    let mut traces: Vec<(String, Vec<Step>)> = Vec::new();
+    let mut pending = first;
+
+    while let Some(input) = pending.take().or_else(|| repl.read_line("You: ")) {
+        if input.starts_with('/') {
+            match input.as_str() {
+                "/help" | "/h" | "/?" => {
+                    println!("\nCommands:");
+                    print_commands(COMMANDS);
+                    println!();
+                }
+                "/trace" | "/t" => {
+                    if traces.is_empty() {
+                        println!("\nNo questions since the last reset.");
+                    }
+                    for (i, (question, steps)) in traces.iter().enumerate() {
+                        println!("\n=== Question {} ===\n{}", i + 1, question);
+                        print_steps(steps);
+                    }
+                    println!();
+                }
+                "/tools" => {
+                    let native = pad.as_ref().map_or(agent.tool_calling, Scratchpad::tool_calling);
+                    println!("\nTools ({}):", if native { "native tool calls" } else { "actions written in text" });
+                    for tool in TOOLS.iter() {
+                        println!("  {:<16} - {}", tool_signature(tool), tool.description);
+                    }
+                    println!();
+                }
+                "/reset" | "/r" => {
+                    pad = None;
+                    traces.clear();
+                    println!("\nScratchpad cleared.\n");
+                }
+                "/quit" | "/q" | "/exit" => {
+                    println!("\nGoodbye!");
+                    break;
+                }
+                _ => println!("\nUnknown command: {}. Type /help for available commands.\n", input),
+            }
+            continue;
+        }
 
-    println!("\n=== Final Answer ===\n{}", answer);
+        let mut current = match pad.take() {
+            Some(mut pad) => {
+                pad.follow_up(&input);
+                pad
+            }
+            None => agent.start(&input, &session.as_ref().map(Session::history).unwrap_or_default()),
+        };
+        match agent.answer(&mut current).await {
+            Ok((answer, steps)) => {
+                println!("\nLEANN: {}\n", answer);
+                traces.push((input.clone(), steps.clone()));
+                pad = Some(current);
+                save_turn(session.as_mut(), &input, steps, &answer)?;
+            }
+            // The scratchpad may end mid-step; start the next question afresh
+            Err(e) => {
+                eprintln!("\nError: {}\nThe scratchpad was cleared.\n", e);
+                traces.clear();
+            }
+        }
+    }
 
     Ok(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let usage = |tokens| TokenUsage { input_tokens: tokens, output_tokens: 10 };
+        let limits = Limits { timeout: Some(Duration::from_secs(60)), token_budget: Some(100), reflect_after: 2 };
+        assert_eq!(limits.exceeded(Instant::now(), &usage(50)), None);
+        assert_eq!(limits.exceeded(Instant::now(), &usage(90)), Some("Token budget used up."));
+
+        let timed_out = Limits { timeout: Some(Duration::ZERO), ..Limits::default() };
+        assert_eq!(timed_out.exceeded(Instant::now(), &usage(0)), Some("Time limit reached."));
+        assert_eq!(Limits::default().exceeded(Instant::now(), &usage(1_000_000)), None);
+    }
+
+    fn call(name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall { id: "call_1".to_string(), name: name.to_string(), arguments }
+    }
+
+    #[test]
+    fn test_tool_action() {
+        let search = tool_action(&call("search", serde_json::json!({ "query": "billing" })));
+        assert!(matches!(search, Some(Action::Search(q)) if q == "billing"));
+        let finish = tool_action(&call("finish", serde_json::json!({ "answer": "42" })));
+        assert!(matches!(finish, Some(Action::Finish(a)) if a == "42"));
+        assert!(tool_action(&call("search", serde_json::json!({}))).is_none());
+        assert!(tool_action(&call("delete", serde_json::json!({ "query": "x" }))).is_none());
+    }
+
+    #[test]
+    fn test_transcripts() {
+        let reply = Reply {
+            text: "Action: search({\"query\":\"billing\"})".to_string(),
+            action: None,
+            call: Some(call("search", serde_json::json!({ "query": "billing" }))),
+        };
+        let mut tools = Transcript::Tools {
+            messages: vec![
+                ChatMessage::User("Question: q".to_string()),
+                ChatMessage::Assistant { text: String::new(), calls: vec![reply.call.clone().unwrap()] },
+            ],
+            fallback: String::new(),
+        };
+        tools.observe(&reply, "[1] Billing passage");
+        tools.instruct("Reflect.\n");
+        let Transcript::Tools { messages, .. } = &tools else { unreachable!() };
+        assert_eq!(
+            messages[2],
+            ChatMessage::ToolResult {
+                call_id: "call_1".to_string(),
+                name: "search".to_string(),
+                content: "[1] Billing passage".to_string()
+            }
+        );
+        assert_eq!(messages[3], ChatMessage::User("Reflect.".to_string()));
+
+        // Falling back to text keeps the steps taken so far
+        assert_eq!(
+            flatten(&messages[1..]),
+            "\nAction: search({\"query\":\"billing\"})\nObservation: [1] Billing passage\n\nReflect.\n"
+        );
+
+        let mut text = Transcript::Text("Question: q\n".to_string());
+        text.observe(&Reply { call: None, ..reply }, "[1] Billing passage");
+        assert!(matches!(text, Transcript::Text(t) if t == "Question: q\nObservation: [1] Billing passage\n\n"));
+    }
+
+    #[test]
+    fn test_scratchpad_follow_up() {
+        let reply = Reply {
+            text: "Action: finish({\"answer\":\"42\"})".to_string(),
+            action: Some(Action::Finish("42".to_string())),
+            call: Some(call("finish", serde_json::json!({ "answer": "42" }))),
+        };
+        let mut pad = Scratchpad {
+            transcript: Transcript::Tools {
+                messages: vec![
+                    ChatMessage::User("Question: q".to_string()),
+                    ChatMessage::Assistant { text: String::new(), calls: vec![reply.call.clone().unwrap()] },
+                ],
+                fallback: String::new(),
+            },
+            searched: HashSet::new(),
+            seen_passages: HashSet::new(),
+        };
+        // The finish call gets a result before the next question
+        pad.transcript.settle(&reply);
+        pad.follow_up("And then?");
+        assert!(pad.tool_calling());
+        let Transcript::Tools { messages, .. } = &pad.transcript else { unreachable!() };
+        assert!(matches!(&messages[2], ChatMessage::ToolResult { content, .. } if content == "Answer given."));
+        assert_eq!(messages[3], ChatMessage::User("Next question: And then?".to_string()));
+
+        let mut text = Scratchpad {
+            transcript: Transcript::Text("Action: finish(42)\n".to_string()),
+            searched: HashSet::new(),
+            seen_passages: HashSet::new(),
+        };
+        text.transcript.settle(&Reply { call: None, ..reply });
+        text.follow_up("And then?");
+        assert!(!text.tool_calling());
+        assert!(matches!(text.transcript, Transcript::Text(t) if t == "Action: finish(42)\n\nNext question: And then?\n\n"));
+    }
+
+    #[test]
+    fn test_tool_signature() {
+        assert_eq!(tool_signature(&TOOLS[0]), "search(query)");
+        assert_eq!(tool_signature(&TOOLS[1]), "finish(answer)");
+    }
+
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!(normalize_query("  Sourdough   STARTER "), "sourdough starter");
+        assert_eq!(normalize_query("sourdough starter"), normalize_query("Sourdough\tstarter"));
+    }
+}
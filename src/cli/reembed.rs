@@ -0,0 +1,315 @@
+//! Reembed command - re-embed an index's stored passages with another model
+
+use std::io::IsTerminal;
+
+use clap::Args;
+use indicatif::{ProgressBar, ProgressStyle};
+use tracing::info;
+
+use super::build::{checkpoint_header, embedding_options, process_chunk_batch, StagingDir};
+use crate::backend::ShardPlan;
+use crate::cancel::Cancellation;
+use crate::chunker::Chunk;
+use crate::config::Config;
+use crate::embedding::{get_model_config, EmbeddingMode, EmbeddingProvider, PostProcessing, ProviderOverrides};
+use crate::index::{find_index, staging_dir, EmbeddingCheckpoint, IndexMeta, KnowledgeGraph, PassageStore, StreamingIndexBuilder, META_VERSION};
+use crate::usage::{format_cost, record_usage, UsageKind, UsageRecord};
+
+#[derive(Args)]
+pub struct ReembedArgs {
+    /// Index name
+    pub index_name: String,
+
+    /// New embedding model name
+    #[arg(long)]
+    pub embedding_model: String,
+
+    /// Embedding mode (default: the index's current mode)
+    #[cfg(feature = "local-embeddings")]
+    #[arg(long, value_parser = ["openai", "ollama", "gemini", "jina", "mock", "local"])]
+    pub embedding_mode: Option<String>,
+
+    /// Embedding mode (default: the index's current mode)
+    #[cfg(not(feature = "local-embeddings"))]
+    #[arg(long, value_parser = ["openai", "ollama", "gemini", "jina", "mock"])]
+    pub embedding_mode: Option<String>,
+
+    /// Ollama host for embeddings
+    #[arg(long, env = "OLLAMA_HOST")]
+    pub embedding_host: Option<String>,
+
+    /// OpenAI API base URL
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub embedding_api_base: Option<String>,
+
+    /// API key for embedding service (OpenAI)
+    #[arg(long, env = "OPENAI_API_KEY")]
+    pub embedding_api_key: Option<String>,
+
+    /// Google API key for Gemini embeddings
+    #[arg(long, env = "GOOGLE_API_KEY")]
+    pub google_api_key: Option<String>,
+
+    /// Jina AI API key (for the jina embedding mode and indexes built with --multi-vector)
+    #[arg(long, env = "JINA_API_KEY")]
+    pub jina_api_key: Option<String>,
+
+    /// Local model path (for local embedding mode)
+    #[cfg(feature = "local-embeddings")]
+    #[arg(long)]
+    pub embedding_model_path: Option<String>,
+
+    /// Prompt template for document embeddings (default: the new model's document prefix)
+    #[arg(long)]
+    pub embedding_prompt_template: Option<String>,
+
+    /// Prompt template for query embeddings (default: the new model's query prefix)
+    #[arg(long)]
+    pub query_prompt_template: Option<String>,
+
+    /// Batch size for embedding API calls (default: provider-specific)
+    #[arg(long)]
+    pub embedding_batch_size: Option<usize>,
+
+    /// Print the cost estimate and stop
+    #[arg(long)]
+    pub estimate_only: bool,
+
+    /// Skip the confirmation prompt
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+
+    /// Ask for confirmation when the estimated embedding cost exceeds this many USD
+    #[arg(long, default_value = "0.50")]
+    pub confirm_above: f64,
+}
+
+/// Ask before spending more than `confirm_above`; false if the user declines
+fn confirm_cost(cost: f64, args: &ReembedArgs) -> anyhow::Result<bool> {
+    if cost <= args.confirm_above || args.yes {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Estimated cost ${:.4} exceeds --confirm-above ${:.2}. Re-run with --yes to proceed.",
+            cost,
+            args.confirm_above
+        );
+    }
+
+    print!("Estimated cost ${:.4} exceeds ${:.2}. Proceed? [y/N]: ", cost, args.confirm_above);
+    std::io::Write::flush(&mut std::io::stdout())?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+pub async fn run(args: ReembedArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let index_path = index_dir.join("documents.leann");
+    let old_meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    let store = PassageStore::open(&index_path)?;
+
+    // Estimate tokens from the stored text; image passages would need their files
+    let mut estimated_tokens = 0;
+    for passage in store.iter()? {
+        let passage = passage?;
+        if passage.metadata.get("modality").and_then(|m| m.as_str()) == Some("image") {
+            anyhow::bail!(
+                "Index '{}' has image passages, which reembed doesn't support yet; rebuild it with 'leann build --force'",
+                args.index_name
+            );
+        }
+        estimated_tokens += crate::embedding::estimate_tokens(&passage.text);
+    }
+    if !old_meta.text_stored {
+        info!("Text-free index: passage text is re-read from the source files");
+    }
+
+    let config = Config::load();
+    let embedding_mode_name = args.embedding_mode.clone().unwrap_or_else(|| old_meta.embedding_mode.clone());
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        google_api_key: args.google_api_key.clone(),
+        jina_api_key: args.jina_api_key.clone(),
+        #[cfg(feature = "local-embeddings")]
+        model_path: args.embedding_model_path.clone(),
+    };
+    let embedding_mode = EmbeddingMode::from_name(&embedding_mode_name, &overrides, &config.embedding)?;
+
+    let model_config = get_model_config(&args.embedding_model);
+    let document_prefix = args
+        .embedding_prompt_template
+        .clone()
+        .unwrap_or_else(|| model_config.document_prefix.to_string());
+    let query_prefix = args
+        .query_prompt_template
+        .clone()
+        .unwrap_or_else(|| model_config.query_prefix.to_string());
+    let post_processing = PostProcessing::for_model(&args.embedding_model);
+
+    let embedding_provider = EmbeddingProvider::new(args.embedding_model.clone(), embedding_mode)
+        .await?
+        .with_post_processing(post_processing.clone())?;
+    let dimensions = embedding_provider.dimensions();
+
+    // Text-free indexes hold no text; estimate from the size of their source files
+    if !old_meta.text_stored {
+        estimated_tokens = old_meta.provenance.as_ref().map_or(0, |p| p.files.iter().map(|f| f.size as usize / 4).sum());
+    }
+    let estimated_cost = embedding_provider.cost(estimated_tokens);
+    println!("\nRe-embedding '{}':", args.index_name);
+    println!("  Passages:         {}", store.len());
+    println!("  Model:            {} / {} -> {} / {}", old_meta.embedding_mode, old_meta.embedding_model, embedding_mode_name, args.embedding_model);
+    println!("  Dimensions:       {} -> {}", old_meta.dimensions, dimensions);
+    println!("  Estimated tokens: ~{}", estimated_tokens);
+    println!("  Estimated cost:   {}", format_cost(estimated_cost));
+    if old_meta.sparse_model.is_some() || old_meta.multi_vector.is_some() {
+        println!("  Sparse and per-token vectors are recomputed too (their own models are kept)");
+    }
+    println!();
+    if args.estimate_only {
+        println!("Nothing was embedded (--estimate-only).");
+        return Ok(());
+    }
+    if let Some(cost) = estimated_cost {
+        if !confirm_cost(cost, &args)? {
+            println!("Re-embedding cancelled.");
+            return Ok(());
+        }
+    }
+
+    let sparse_provider = old_meta.sparse_model.as_ref().map(|info| info.provider(None));
+    let multi_vector_provider = match &old_meta.multi_vector {
+        Some(info) => Some(info.provider(args.jina_api_key.clone())?),
+        None => None,
+    };
+
+    // Build into a staging directory; the current index becomes the previous build
+    let staging = StagingDir::create(staging_dir(&index_dir))?;
+    let params = old_meta.build_params();
+    let mut builder = StreamingIndexBuilder::new(
+        old_meta.backend()?,
+        dimensions,
+        params.graph_degree,
+        params.complexity,
+        old_meta.is_recompute,
+        &staging.path.join("documents.leann"),
+    )?
+    .with_store_text(old_meta.text_stored)
+    .with_passage_format(store.format())?
+    .with_sparse_index(sparse_provider.is_some())
+    .with_multi_vector(old_meta.multi_vector.as_ref().map(|info| info.dimensions))?
+    .with_shard_plan(old_meta.shard_count().map(ShardPlan::Count))?
+    .with_diskann_params(params.diskann);
+
+    let batch_size = args.embedding_batch_size.unwrap_or(match embedding_mode_name.as_str() {
+        "ollama" => 32,
+        _ => 100,
+    });
+    let progress = ProgressBar::new(store.len() as u64);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} ({eta}) Re-embedding passages...")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+
+    // A cancelled or failed re-embed leaves the index as it was and resumes
+    // from the embeddings it checkpointed
+    let cancel = Cancellation::on_ctrl_c();
+    let mut checkpoint = EmbeddingCheckpoint::open(
+        &index_dir,
+        checkpoint_header(&embedding_provider, Some(&post_processing), &document_prefix, None),
+    )?;
+    if checkpoint.len() > 0 {
+        println!("Resuming: {} embeddings reused from an interrupted re-embed", checkpoint.len());
+    }
+
+    let start = std::time::Instant::now();
+    let mut total = 0;
+    let mut batch: Vec<Chunk> = Vec::with_capacity(batch_size);
+    // Passages of a text-free index that kept their text were generated (e.g.
+    // summaries) and keep it again; batches hold only one kind
+    let mut batch_generated = false;
+    for passage in store.iter()? {
+        let mut passage = passage?;
+        let generated = !old_meta.text_stored && !passage.text.is_empty();
+        if passage.text.is_empty() {
+            passage = store.get(&passage.id)?;
+            if passage.text.is_empty() {
+                anyhow::bail!(
+                    "Text of passage {} is unavailable (its source changed since indexing); rebuild the index instead",
+                    passage.id
+                );
+            }
+        }
+        if !batch.is_empty() && (batch.len() == batch_size || generated != batch_generated) {
+            cancel.check("Re-embed", &args.index_name)?;
+            process_chunk_batch(&batch, batch_generated, &embedding_provider, sparse_provider.as_ref(), multi_vector_provider.as_ref(), &document_prefix, &mut checkpoint, &mut builder).await?;
+            total += batch.len();
+            progress.inc(batch.len() as u64);
+            batch.clear();
+        }
+        batch_generated = generated;
+        batch.push(Chunk { id: passage.id, text: passage.text, metadata: passage.metadata });
+    }
+    if !batch.is_empty() {
+        cancel.check("Re-embed", &args.index_name)?;
+        process_chunk_batch(&batch, batch_generated, &embedding_provider, sparse_provider.as_ref(), multi_vector_provider.as_ref(), &document_prefix, &mut checkpoint, &mut builder).await?;
+        total += batch.len();
+    }
+    progress.finish_and_clear();
+    if total == 0 {
+        anyhow::bail!("Index '{}' has no passages to re-embed", args.index_name);
+    }
+    builder.build()?;
+    // Passage ids are unchanged, so the knowledge graph carries over
+    if let Some(graph) = KnowledgeGraph::load(&index_path)? {
+        graph.save(&staging.path.join("documents.leann"))?;
+    }
+
+    let mut backend_kwargs = old_meta.backend()?.build_kwargs(&params);
+    if let Some(shards) = old_meta.shard_count() {
+        backend_kwargs["shards"] = shards.into();
+    }
+    let meta = IndexMeta {
+        version: META_VERSION.to_string(),
+        embedding_model: args.embedding_model.clone(),
+        embedding_mode: embedding_mode_name,
+        dimensions,
+        passage_count: total,
+        backend_kwargs: Some(backend_kwargs),
+        // Passages keep the instructions recorded for their file types
+        embedding_options: embedding_options(&query_prefix, &document_prefix, &old_meta.document_instructions()),
+        post_processing: Some(post_processing),
+        is_pruned: false,
+        created_at: Some(crate::time::now_unix()),
+        ..old_meta
+    };
+    meta.save(&staging.path.join("documents.leann.meta.json"))?;
+    drop(store);
+    staging.commit(&index_dir)?;
+    checkpoint.remove()?;
+
+    let tokens = embedding_provider.tokens_used();
+    let cost = embedding_provider.cost(tokens);
+    record_usage(&[UsageRecord::new(
+        "reembed",
+        Some(&args.index_name),
+        UsageKind::Embedding,
+        embedding_provider.mode_name(),
+        embedding_provider.model_name(),
+    )
+    .with_tokens(tokens, 0, cost)]);
+
+    println!("Index '{}' re-embedded with {}", args.index_name, args.embedding_model);
+    println!("  Passages: {}", total);
+    println!("  Dimensions: {}", dimensions);
+    println!("  Total time: {:.1}s", start.elapsed().as_secs_f64());
+    println!("  Embedding tokens: ~{} ({})", tokens, format_cost(cost));
+    println!("  Run 'leann rollback {}' to restore the previous embeddings", args.index_name);
+    Ok(())
+}
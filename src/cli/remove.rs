@@ -4,6 +4,8 @@ use std::path::PathBuf;
 
 use clap::Args;
 
+use crate::index::{checkpoint_path, previous_dir, query_log_path, snapshots_dir};
+
 #[derive(Args)]
 pub struct RemoveArgs {
     /// Index name to remove
@@ -102,6 +104,16 @@ fn remove_index(
     }
 
     std::fs::remove_dir_all(path)?;
+    for dir in [previous_dir(path), snapshots_dir(path)] {
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+    }
+    for file in [query_log_path(path), checkpoint_path(path)] {
+        if file.exists() {
+            std::fs::remove_file(&file)?;
+        }
+    }
     println!("Index '{}' removed from {}.", name, location);
 
     Ok(())
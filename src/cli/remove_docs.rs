@@ -0,0 +1,106 @@
+//! Remove-docs command - delete the passages of some files from an index
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use clap::Args;
+
+use crate::index::{find_index, manifest_path, IndexMeta, PassageStore};
+
+#[derive(Args)]
+pub struct RemoveDocsArgs {
+    /// Index name to remove documents from
+    pub index_name: String,
+
+    /// Files or directories whose passages to remove
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// List the matching files without changing the index
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub async fn run(args: RemoveDocsArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let meta_path = index_dir.join("documents.leann.meta.json");
+    let index_path = index_dir.join("documents.leann");
+    let mut meta = IndexMeta::load(&meta_path)?;
+
+    let targets: Vec<PathBuf> = args.paths.iter().flat_map(|p| path_forms(p)).collect();
+    let store = PassageStore::open(&index_path)?;
+    let mut ids = Vec::new();
+    let mut sources: BTreeSet<String> = BTreeSet::new();
+    for passage in store.iter()? {
+        let passage = passage?;
+        let Some(source) = passage.metadata.get("source").and_then(|s| s.as_str()) else {
+            continue;
+        };
+        if path_forms(Path::new(source)).iter().any(|s| targets.iter().any(|t| s.starts_with(t))) {
+            sources.insert(source.to_string());
+            ids.push(passage.id);
+        }
+    }
+    drop(store);
+
+    if ids.is_empty() {
+        println!("No passages in index '{}' come from the given paths.", args.index_name);
+        return Ok(());
+    }
+
+    if args.dry_run {
+        println!("Would remove {} passages from {} files:", ids.len(), sources.len());
+        for source in &sources {
+            println!("  {}", source);
+        }
+        return Ok(());
+    }
+
+    let removed = PassageStore::delete(&index_path, &ids)?;
+    let removed_vectors = remove_vectors(&index_path, &meta, &ids)?;
+    if let Some(provenance) = meta.provenance.as_mut() {
+        let files: HashSet<String> = sources.iter().map(|s| manifest_path(Path::new(s))).collect();
+        provenance.files.retain(|f| !files.contains(&manifest_path(Path::new(&f.path))));
+    }
+    meta.save(&meta_path)?;
+
+    println!("Removed {} passages from {} files in index '{}'.", removed, sources.len(), args.index_name);
+    if removed_vectors > 0 {
+        println!("  Removed their {} vectors from the graph; updates reuse the keys.", removed_vectors);
+    }
+    println!("  Run 'leann compact {}' to reclaim their space.", args.index_name);
+    println!("  'leann update' adds files that still exist under the index's docs roots again.");
+
+    Ok(())
+}
+
+/// Remove the vectors of the passages `ids` from the graph, if the backend
+/// can; returns how many were removed
+pub(super) fn remove_vectors(index_path: &Path, meta: &IndexMeta, ids: &[String]) -> anyhow::Result<usize> {
+    let backend = meta.backend()?;
+    let ids_path = index_path.with_extension("ids.txt");
+    if !backend.info().supports_replace || !ids_path.exists() {
+        return Ok(0);
+    }
+    let ids: HashSet<&str> = ids.iter().map(|id| id.as_str()).collect();
+    let keys: Vec<u64> = std::fs::read_to_string(&ids_path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, id)| ids.contains(id))
+        .map(|(k, _)| k as u64)
+        .collect();
+    if !keys.is_empty() {
+        backend.remove_from_index(&keys, index_path, meta.dimensions)?;
+    }
+    Ok(keys.len())
+}
+
+/// A path as given (without `.` components), and canonicalized if it exists
+fn path_forms(path: &Path) -> Vec<PathBuf> {
+    let normalized: PathBuf = path.components().filter(|c| !matches!(c, Component::CurDir)).collect();
+    let mut forms = vec![normalized];
+    if let Ok(canonical) = path.canonicalize() {
+        forms.push(canonical);
+    }
+    forms
+}
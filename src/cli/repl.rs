@@ -0,0 +1,78 @@
+//! Line editor for the interactive modes of `ask` and `react`
+//!
+//! Input history is shared between them and kept in `~/.leann/history.txt`.
+
+use std::path::PathBuf;
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+/// Reads input lines, with history
+pub(super) struct Repl {
+    editor: DefaultEditor,
+    history_path: PathBuf,
+}
+
+impl Repl {
+    /// Print the banner with the mode's `(command, description)` pairs and
+    /// open the editor with the saved history
+    pub(super) fn start(title: &str, commands: &[(&str, &str)]) -> anyhow::Result<Self> {
+        println!("\n🔍 {}", title);
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        println!("Commands:");
+        print_commands(commands);
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+
+        let mut editor = DefaultEditor::new()?;
+        let history_path = dirs::home_dir()
+            .map(|h| h.join(".leann").join("history.txt"))
+            .unwrap_or_else(|| PathBuf::from(".leann_history"));
+        if history_path.exists() {
+            let _ = editor.load_history(&history_path);
+        }
+        Ok(Self { editor, history_path })
+    }
+
+    /// The next non-empty line, trimmed; `None` at end of input
+    pub(super) fn read_line(&mut self, prompt: &str) -> Option<String> {
+        loop {
+            match self.editor.readline(prompt) {
+                Ok(line) => {
+                    let input = line.trim();
+                    if input.is_empty() {
+                        continue;
+                    }
+                    let _ = self.editor.add_history_entry(input);
+                    return Some(input.to_string());
+                }
+                Err(ReadlineError::Interrupted) => {
+                    println!("\nInterrupted. Type /quit to exit.");
+                }
+                Err(ReadlineError::Eof) => {
+                    println!("\nGoodbye!");
+                    return None;
+                }
+                Err(err) => {
+                    eprintln!("Error: {:?}", err);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Repl {
+    fn drop(&mut self) {
+        if let Some(parent) = self.history_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = self.editor.save_history(&self.history_path);
+    }
+}
+
+/// Print `(command, description)` pairs, one per line
+pub(super) fn print_commands(commands: &[(&str, &str)]) {
+    for (command, description) in commands {
+        println!("  {:<9} - {}", command, description);
+    }
+}
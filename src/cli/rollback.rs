@@ -0,0 +1,45 @@
+//! Rollback command - restore an index's previous build
+
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::index::{previous_dir, rollback_build, IndexMeta};
+
+#[derive(Args)]
+pub struct RollbackArgs {
+    /// Index name to roll back
+    pub index_name: String,
+}
+
+pub async fn run(args: RollbackArgs) -> anyhow::Result<()> {
+    let index_dir = find_rollback_target(&args.index_name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "No previous build of index '{}' found. A previous build is kept when the index is rebuilt with --force.",
+            args.index_name
+        )
+    })?;
+
+    rollback_build(&index_dir)?;
+
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    println!("Index '{}' rolled back to its previous build", args.index_name);
+    println!("  Passages: {}", meta.passage_count);
+    if let Some(created_at) = meta.created_at {
+        println!("  Built: {}", crate::time::format_rfc3339(created_at));
+    }
+    println!("  Run 'leann rollback {}' again to return to the newer build.", args.index_name);
+
+    Ok(())
+}
+
+/// Index directory with a previous build, local project first
+///
+/// The index itself may be missing if a build was interrupted mid-swap.
+fn find_rollback_target(name: &str) -> Option<PathBuf> {
+    let mut candidates = vec![PathBuf::from(".leann").join("indexes").join(name)];
+    if let Some(home) = dirs::home_dir() {
+        candidates.push(home.join(".leann").join("indexes").join(name));
+    }
+    candidates.into_iter().find(|dir| previous_dir(dir).exists())
+}
@@ -0,0 +1,552 @@
+//! Run command - a retrieval pipeline declared in a YAML file
+//!
+//! A pipeline lists its stages in the order they run, each configured with
+//! the same settings as the `search` and `ask` flags:
+//!
+//! ```yaml
+//! index: my-docs
+//! stages:
+//!   - stage: expand        # synonyms, typo correction, BM25 terms, LLM rewrites
+//!     multi_query: 3
+//!   - stage: search        # dense vector search
+//!     top_k: 20
+//!     filter: "type=code"
+//!   - stage: hybrid        # fuse BM25 (or sparse) scores
+//!     alpha: 0.7
+//!   - stage: rerank        # mmr or multi_vector
+//!     method: mmr
+//!     lambda: 0.6
+//!   - stage: pack          # token budget for the retrieved passages
+//!     budget: 3000
+//!   - stage: generate      # answer with an LLM
+//!     provider: ollama
+//!     model: qwen3:8b
+//! ```
+//!
+//! Only `search` is required. Stages left out are off, so a pipeline runs the
+//! same way whatever the defaults of the flags become. Settings a stage leaves
+//! unset (e.g. `top_k`) take the index's search defaults, then config.toml.
+//! Without a `generate` stage the results are printed as `leann search`
+//! prints them.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::config::Config;
+use crate::index::{resolve_index, IndexMeta};
+use crate::llm::{
+    context_window, count_tokens, GenerationOptions, LlmOverrides, LlmProvider, LlmType, PiiRedactor, PromptTemplate,
+    DEFAULT_ASK_TEMPLATE, DEFAULT_MAX_TOKENS,
+};
+use crate::usage::{format_cost, record_usage, UsageKind, UsageRecord};
+
+use super::ask::pack_results;
+use super::search::{search_loaded, LoadedIndex, SearchArgs};
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Pipeline file (YAML)
+    pub pipeline: PathBuf,
+
+    /// Query or question to run the pipeline on
+    pub query: String,
+
+    /// Index to run against (default: the pipeline's `index`, else the current directory name)
+    #[arg(short, long)]
+    pub index: Option<String>,
+
+    /// Output format for pipelines without a generate stage (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+}
+
+/// A retrieval pipeline
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    /// Index to run against
+    pub index: Option<String>,
+    pub stages: Vec<Stage>,
+}
+
+/// A step of a pipeline, tagged by `stage`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum Stage {
+    Expand(ExpandStage),
+    Search(SearchStage),
+    Hybrid(HybridStage),
+    Rerank(RerankStage),
+    Pack(PackStage),
+    Generate(GenerateStage),
+}
+
+/// Query expansion before the search
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpandStage {
+    /// Add synonyms from the index's synonym file
+    #[serde(default = "default_true")]
+    pub synonyms: bool,
+    /// Correct words missing from the index's vocabulary
+    #[serde(default = "default_true")]
+    pub correct: bool,
+    /// Add related terms of BM25 matches to short queries
+    #[serde(default = "default_true")]
+    pub bm25: bool,
+    /// Also search N LLM-written reformulations
+    pub multi_query: Option<usize>,
+    /// Blend in an LLM-written hypothetical answer with this weight (HyDE)
+    pub hyde: Option<f32>,
+}
+
+/// Dense vector search
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchStage {
+    pub top_k: Option<usize>,
+    pub complexity: Option<usize>,
+    pub min_score: Option<f32>,
+    pub filter: Option<String>,
+    /// "pre" or "post"
+    pub filter_mode: Option<String>,
+    pub namespace: Option<String>,
+    pub boost: Option<String>,
+}
+
+/// Fusion of keyword (BM25) or learned sparse scores with the vector scores
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HybridStage {
+    /// Weight of the vector scores, 0.0-1.0
+    pub alpha: Option<f32>,
+    /// Fuse the index's sparse vectors instead of BM25
+    #[serde(default)]
+    pub sparse: bool,
+}
+
+/// Reranking of the fused candidates
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RerankStage {
+    pub method: RerankMethod,
+    /// MMR trade-off: 1.0 = pure relevance, 0.0 = pure diversity
+    pub lambda: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RerankMethod {
+    /// Maximal marginal relevance
+    Mmr,
+    /// Late interaction over per-token vectors (indexes built with --multi-vector)
+    MultiVector,
+}
+
+/// Packing of the passages into the prompt
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackStage {
+    /// Token budget for the passages (default: the model's context window
+    /// less the rest of the prompt and the answer)
+    pub budget: Option<usize>,
+}
+
+/// Answer generation
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenerateStage {
+    /// LLM provider (default: `[llm] provider` in config.toml)
+    pub provider: Option<String>,
+    /// LLM model (default: `[llm] model` in config.toml)
+    pub model: Option<String>,
+    /// Prompt template file, relative to the pipeline file
+    pub prompt_template: Option<PathBuf>,
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Personal information to replace in the prompt, as for `leann ask --redact`
+    /// (comma-separated: emails, phones, names)
+    pub redact: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Stage {
+    fn name(&self) -> &'static str {
+        match self {
+            Stage::Expand(_) => "expand",
+            Stage::Search(_) => "search",
+            Stage::Hybrid(_) => "hybrid",
+            Stage::Rerank(_) => "rerank",
+            Stage::Pack(_) => "pack",
+            Stage::Generate(_) => "generate",
+        }
+    }
+
+    /// Position of the stage in a pipeline
+    fn position(&self) -> usize {
+        match self {
+            Stage::Expand(_) => 0,
+            Stage::Search(_) => 1,
+            Stage::Hybrid(_) => 2,
+            Stage::Rerank(_) => 3,
+            Stage::Pack(_) => 4,
+            Stage::Generate(_) => 5,
+        }
+    }
+}
+
+impl Pipeline {
+    /// Load and check the pipeline file at `path`
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Couldn't read pipeline file {}: {}", path.display(), e))?;
+        let mut pipeline = Self::parse(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid pipeline file {}: {}", path.display(), e))?;
+
+        // Prompt templates are shared along with the pipeline file
+        if let Some(template) = pipeline.generate_mut().and_then(|g| g.prompt_template.as_mut()) {
+            if template.is_relative() {
+                *template = path.parent().unwrap_or(Path::new("")).join(&*template);
+            }
+        }
+        Ok(pipeline)
+    }
+
+    /// Parse a pipeline, checking that each stage appears at most once, in
+    /// pipeline order, with a search stage
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let pipeline: Self = serde_yaml::from_str(content)?;
+        for pair in pipeline.stages.windows(2) {
+            if pair[0].position() >= pair[1].position() {
+                anyhow::bail!(
+                    "stage '{}' can't follow '{}' (stages run in the order expand, search, hybrid, rerank, pack, \
+                    generate, each at most once)",
+                    pair[1].name(),
+                    pair[0].name()
+                );
+            }
+        }
+        if pipeline.search().is_none() {
+            anyhow::bail!("a pipeline needs a search stage");
+        }
+        if pipeline.pack().is_some() && pipeline.generate().is_none() {
+            anyhow::bail!("a pack stage needs a generate stage to pack passages for");
+        }
+        if let Some(redact) = pipeline.generate().and_then(|g| g.redact.as_deref()) {
+            PiiRedactor::from_names(redact)?;
+        }
+        Ok(pipeline)
+    }
+
+    fn expand(&self) -> Option<&ExpandStage> {
+        self.stages.iter().find_map(|s| match s {
+            Stage::Expand(stage) => Some(stage),
+            _ => None,
+        })
+    }
+
+    fn search(&self) -> Option<&SearchStage> {
+        self.stages.iter().find_map(|s| match s {
+            Stage::Search(stage) => Some(stage),
+            _ => None,
+        })
+    }
+
+    fn hybrid(&self) -> Option<&HybridStage> {
+        self.stages.iter().find_map(|s| match s {
+            Stage::Hybrid(stage) => Some(stage),
+            _ => None,
+        })
+    }
+
+    fn rerank(&self) -> Option<&RerankStage> {
+        self.stages.iter().find_map(|s| match s {
+            Stage::Rerank(stage) => Some(stage),
+            _ => None,
+        })
+    }
+
+    fn pack(&self) -> Option<&PackStage> {
+        self.stages.iter().find_map(|s| match s {
+            Stage::Pack(stage) => Some(stage),
+            _ => None,
+        })
+    }
+
+    fn generate(&self) -> Option<&GenerateStage> {
+        self.stages.iter().find_map(|s| match s {
+            Stage::Generate(stage) => Some(stage),
+            _ => None,
+        })
+    }
+
+    fn generate_mut(&mut self) -> Option<&mut GenerateStage> {
+        self.stages.iter_mut().find_map(|s| match s {
+            Stage::Generate(stage) => Some(stage),
+            _ => None,
+        })
+    }
+
+    /// `leann search` arguments running the expansion, search, hybrid, and
+    /// rerank stages for `query`
+    ///
+    /// Credentials and hosts come from the environment, as for `leann search`.
+    pub fn search_args(&self, query: &str, index: &str) -> anyhow::Result<SearchArgs> {
+        let mut args = super::search::parse_args(&[query.to_string()])?;
+        args.index = Some(index.to_string());
+
+        // Stages left out are off, rather than the flag defaults
+        args.expand = false;
+        args.auto_hybrid = false;
+        args.no_hybrid = self.hybrid().is_none();
+        args.no_synonyms = true;
+        args.no_correct = true;
+
+        if let Some(expand) = self.expand() {
+            args.no_synonyms = !expand.synonyms;
+            args.no_correct = !expand.correct;
+            args.expand = expand.bm25;
+            args.multi_query = expand.multi_query;
+            if let Some(weight) = expand.hyde {
+                args.hyde = true;
+                args.hyde_weight = weight;
+            }
+        }
+
+        let search = self.search().ok_or_else(|| anyhow::anyhow!("a pipeline needs a search stage"))?;
+        args.top_k = search.top_k;
+        args.complexity = search.complexity;
+        args.min_score = search.min_score;
+        args.filter = search.filter.clone();
+        if let Some(mode) = &search.filter_mode {
+            if mode != "pre" && mode != "post" {
+                anyhow::bail!("filter_mode must be 'pre' or 'post', not '{}'", mode);
+            }
+            args.filter_mode = mode.clone();
+        }
+        args.namespace = search.namespace.clone();
+        args.boost = search.boost.clone();
+
+        if let Some(hybrid) = self.hybrid() {
+            if hybrid.sparse {
+                args.sparse = true;
+                if let Some(alpha) = hybrid.alpha {
+                    args.sparse_alpha = alpha;
+                }
+            } else {
+                args.hybrid = true;
+                args.hybrid_alpha = hybrid.alpha;
+            }
+        }
+
+        if let Some(rerank) = self.rerank() {
+            match rerank.method {
+                RerankMethod::Mmr => {
+                    args.mmr = true;
+                    if let Some(lambda) = rerank.lambda {
+                        args.mmr_lambda = lambda;
+                    }
+                }
+                RerankMethod::MultiVector => args.multi_vector = true,
+            }
+        }
+
+        // Expansion with an LLM uses the generation model
+        if let Some(generate) = self.generate() {
+            args.llm = generate.provider.clone();
+            args.model = generate.model.clone();
+        }
+        Ok(args)
+    }
+}
+
+pub async fn run(args: RunArgs) -> anyhow::Result<()> {
+    let pipeline = Pipeline::load(&args.pipeline)?;
+    let index_name = args.index.clone().or_else(|| pipeline.index.clone()).unwrap_or_else(|| {
+        std::env::current_dir()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "index".to_string())
+    });
+    let stages: Vec<&str> = pipeline.stages.iter().map(Stage::name).collect();
+    info!("Running pipeline {} ({}) on '{}'", args.pipeline.display(), stages.join(" → "), index_name);
+
+    let index_dir = resolve_index(&index_name).await?;
+    let mut search_args = pipeline.search_args(&args.query, &index_name)?;
+    search_args.format = args.format.clone();
+
+    let index = LoadedIndex::load(&index_dir, &index_name, &search_args).await?;
+    let embedded_before = index.embedding_provider().tokens_used();
+    let output = search_loaded(&index, &index_name, &search_args).await?;
+
+    let config = Config::load();
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    let Some(generate) = pipeline.generate() else {
+        return super::search::print_results(
+            &search_args,
+            &output.results,
+            output.corrected_query.as_deref(),
+            search_args.page_size(&meta.search_config(&config.search)),
+        );
+    };
+
+    // Generation, as `leann ask` does it
+    let template = PromptTemplate::resolve(
+        generate.prompt_template.as_deref(),
+        meta.prompt_templates.as_ref().and_then(|t| t.ask.as_deref()),
+        config.prompts.ask_path().as_deref(),
+        DEFAULT_ASK_TEMPLATE,
+    )?;
+    let provider = generate.provider.clone().unwrap_or_else(|| config.llm.provider.clone());
+    let model = generate.model.clone().unwrap_or_else(|| config.llm.model.clone());
+    let llm_overrides = LlmOverrides {
+        host: search_args.llm_host.clone(),
+        api_key: search_args.llm_api_key.clone(),
+        api_base: search_args.llm_api_base.clone(),
+        #[cfg(feature = "local-llm")]
+        tokenizer: search_args.tokenizer.clone(),
+    };
+    let llm_type = LlmType::from_name(&provider, &llm_overrides, &config.llm)?;
+    let llm = LlmProvider::new(model.clone(), llm_type)?.with_options(GenerationOptions {
+        system: generate.system_prompt.clone(),
+        temperature: generate.temperature,
+        top_p: generate.top_p,
+        max_tokens: generate.max_tokens,
+        stop: generate.stop.clone(),
+    })?
+    .with_redactor(generate.redact.as_deref().map(PiiRedactor::from_names).transpose()?);
+    println!("Using {} with model {}", provider, model);
+
+    if output.results.is_empty() {
+        println!("\nAnswer:\nNo relevant passages found.");
+        return Ok(());
+    }
+
+    let mut vars = HashMap::from([("question", args.query.clone()), ("history", String::new())]);
+    let budget = pipeline.pack().and_then(|p| p.budget).or_else(|| {
+        let reserve = generate.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS) as usize;
+        let overhead = count_tokens(&model, &template.render(&vars));
+        context_window(&provider, &model).map(|window| window.saturating_sub(reserve).saturating_sub(overhead))
+    });
+    let packed = pack_results(&output.results, budget, &model);
+    println!("Context: {}", packed.summary());
+
+    vars.insert("context", packed.text);
+    let prompt = template.render(&vars);
+    print!("\nAnswer:\n");
+    llm.generate_stream(&prompt, &mut |chunk| {
+        print!("{}", chunk);
+        let _ = std::io::stdout().flush();
+    })
+    .await?;
+
+    let generation = llm.usage();
+    let embedding_provider = index.embedding_provider();
+    let embedded = embedding_provider.tokens_used().saturating_sub(embedded_before);
+    let generation_cost = llm.cost(&generation);
+    let embedding_cost = embedding_provider.cost(embedded);
+    println!(
+        "\n\nUsage: ~{} input / ~{} output tokens, {}",
+        generation.input_tokens,
+        generation.output_tokens,
+        format_cost(generation_cost.zip(embedding_cost).map(|(g, e)| g + e))
+    );
+    record_usage(&[
+        UsageRecord::new("run", Some(&index_name), UsageKind::Generation, llm.provider_name(), llm.model_name())
+            .with_tokens(generation.input_tokens, generation.output_tokens, generation_cost),
+        UsageRecord::new(
+            "run",
+            Some(&index_name),
+            UsageKind::Embedding,
+            embedding_provider.mode_name(),
+            embedding_provider.model_name(),
+        )
+        .with_tokens(embedded, 0, embedding_cost),
+    ]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIPELINE: &str = r#"
+index: my-docs
+stages:
+  - stage: expand
+    multi_query: 3
+    correct: false
+  - stage: search
+    top_k: 20
+    filter: "type=code"
+    filter_mode: post
+  - stage: hybrid
+    alpha: 0.6
+  - stage: rerank
+    method: mmr
+    lambda: 0.8
+  - stage: pack
+    budget: 3000
+  - stage: generate
+    provider: openai
+    model: gpt-4o-mini
+    temperature: 0.2
+    redact: emails,names
+"#;
+
+    #[test]
+    fn test_parse_pipeline() {
+        let pipeline = Pipeline::parse(PIPELINE).unwrap();
+        assert_eq!(pipeline.index.as_deref(), Some("my-docs"));
+        assert_eq!(pipeline.stages.len(), 6);
+        assert_eq!(pipeline.pack().and_then(|p| p.budget), Some(3000));
+        assert_eq!(pipeline.rerank().map(|r| r.method), Some(RerankMethod::Mmr));
+
+        let args = pipeline.search_args("how are tokens refreshed?", "my-docs").unwrap();
+        assert_eq!(args.query, "how are tokens refreshed?");
+        assert_eq!(args.top_k, Some(20));
+        assert_eq!(args.filter.as_deref(), Some("type=code"));
+        assert_eq!(args.filter_mode, "post");
+        assert!(args.hybrid && !args.auto_hybrid && !args.no_hybrid);
+        assert_eq!(args.hybrid_alpha, Some(0.6));
+        assert!(args.mmr);
+        assert_eq!(args.mmr_lambda, 0.8);
+        assert_eq!(args.multi_query, Some(3));
+        assert!(args.no_correct && !args.no_synonyms && args.expand);
+        assert_eq!(args.llm.as_deref(), Some("openai"));
+        assert_eq!(pipeline.generate().and_then(|g| g.redact.as_deref()), Some("emails,names"));
+    }
+
+    #[test]
+    fn test_left_out_stages_are_off() {
+        let pipeline = Pipeline::parse("stages:\n  - stage: search\n").unwrap();
+        let args = pipeline.search_args("auth", "docs").unwrap();
+        assert!(!args.expand && !args.hybrid && !args.auto_hybrid && args.no_hybrid && !args.mmr);
+        assert!(args.no_synonyms && args.no_correct);
+        assert_eq!(args.top_k, None);
+    }
+
+    #[test]
+    fn test_invalid_pipelines() {
+        // Out of order, repeated, missing search, unknown settings
+        assert!(Pipeline::parse("stages:\n  - stage: search\n  - stage: expand\n").is_err());
+        assert!(Pipeline::parse("stages:\n  - stage: search\n  - stage: search\n").is_err());
+        assert!(Pipeline::parse("stages:\n  - stage: generate\n").is_err());
+        assert!(Pipeline::parse("stages:\n  - stage: search\n    topk: 5\n").is_err());
+        assert!(Pipeline::parse("stages:\n  - stage: search\n  - stage: pack\n").is_err());
+        assert!(Pipeline::parse("stages:\n  - stage: search\n  - stage: generate\n    redact: ssn\n").is_err());
+        assert!(Pipeline::parse("stages:\n  - stage: search\n  - stage: rerank\n    method: cross_encoder\n").is_err());
+    }
+}
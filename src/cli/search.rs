@@ -1,27 +1,57 @@
 //! Search command - query an index
 
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
 use clap::Args;
+use serde::{Deserialize, Serialize};
 use tracing::info;
 
-use crate::embedding::{EmbeddingMode, EmbeddingProvider};
-use crate::index::{expand_from_passages, find_index, IndexMeta, IndexSearcher, MetadataFilter, RecomputeSearcher, SearchOptions, SearchResult, should_expand};
+use crate::config::{Config, SearchConfig};
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::index::{
+    aggregate_by_source, expand_from_passages, Boost, log_query, resolve_index, should_expand, FilterMode, IndexMeta,
+    IndexSearcher, MetadataFilter, PassageStore, QueryLogEntry, RecomputeSearcher, SearchExplanation, SearchOptions, SearchResult,
+    Synonyms,
+};
+use crate::llm::{hypothetical_document, reformulate_query, LlmOverrides, LlmProvider, LlmType, ReformulationCache};
+
+use super::highlight;
+
+/// Chunk hits fetched per requested file in `--format files`
+const FILE_HITS_PER_FILE: usize = 5;
 
-#[derive(Args)]
+#[derive(Args, Clone, Serialize, Deserialize)]
 pub struct SearchArgs {
     /// Search query
     pub query: String,
 
-    /// Index name to search (defaults to current directory name)
+    /// Index name or s3://bucket/prefix URL to search (defaults to current directory name)
     #[arg(short, long)]
     pub index: Option<String>,
 
-    /// Number of results to return
-    #[arg(long, default_value = "5")]
-    pub top_k: usize,
+    /// Number of results to return (default: the index's default, then `[search] top_k`
+    /// in config.toml, else 5)
+    #[arg(long)]
+    pub top_k: Option<usize>,
+
+    /// Drop results scoring below this similarity
+    #[arg(long)]
+    pub min_score: Option<f32>,
 
-    /// Search complexity (higher = more accurate but slower)
-    #[arg(long, default_value = "64")]
-    pub complexity: usize,
+    /// Number of results to skip (for pagination)
+    #[arg(long, default_value = "0")]
+    pub offset: usize,
+
+    /// Results per page (overrides --top-k; use with --offset)
+    #[arg(long)]
+    pub page_size: Option<usize>,
+
+    /// Search complexity, higher = more accurate but slower
+    /// (default: the index's default, then `[search] complexity` in config.toml, else 64)
+    #[arg(long)]
+    pub complexity: Option<usize>,
 
     /// Show file paths in results
     #[arg(long)]
@@ -31,6 +61,42 @@ pub struct SearchArgs {
     #[arg(long, short = 'f')]
     pub filter: Option<String>,
 
+    /// When to apply the filter: "pre" restricts the vector search to matching
+    /// passages, "post" filters the nearest neighbors (faster, may return fewer results)
+    #[arg(long, default_value = "pre", value_parser = ["pre", "post"])]
+    pub filter_mode: String,
+
+    /// Only return passages of this modality: "image" (indexed with --images) or "text"
+    #[arg(long, value_parser = ["text", "image"])]
+    pub modality: Option<String>,
+
+    /// Only return passages in this natural language, by ISO 639-3 code or English
+    /// name (e.g. "deu" or "German"); shorthand for --filter "lang=deu"
+    #[arg(long)]
+    pub language: Option<String>,
+
+    /// Only return passages of this namespace (see `leann build --namespace`)
+    #[arg(long)]
+    pub namespace: Option<String>,
+
+    /// Multiply the scores of results matching metadata filters, as
+    /// comma-separated `filter:weight` rules (e.g. "source^src/:1.5,modified>now-30d:1.2")
+    #[arg(long)]
+    pub boost: Option<String>,
+
+    /// Rank without the relevance feedback given with `leann feedback`
+    #[arg(long)]
+    pub no_feedback: bool,
+
+    /// Don't add synonyms from the index's synonym file (<name>.synonyms.toml)
+    #[arg(long)]
+    pub no_synonyms: bool,
+
+    /// Search the query as typed, without correcting words missing from the
+    /// index's vocabulary
+    #[arg(long)]
+    pub no_correct: bool,
+
     /// Enable hybrid search (vector + BM25)
     /// Use "auto" to automatically enable for short queries (1-3 words)
     #[arg(long)]
@@ -40,18 +106,118 @@ pub struct SearchArgs {
     #[arg(long, default_value = "true")]
     pub auto_hybrid: bool,
 
+    /// Vector search only, even for short queries or an index whose default
+    /// is hybrid search
+    #[arg(long, conflicts_with = "hybrid")]
+    pub no_hybrid: bool,
+
     /// Expand short queries with related terms for better recall
     #[arg(long, default_value = "true")]
     pub expand: bool,
 
-    /// Weight for vector scores in hybrid mode (0.0-1.0, default 0.7)
-    #[arg(long, default_value = "0.7")]
-    pub hybrid_alpha: f32,
+    /// Weight for vector scores in hybrid mode, 0.0-1.0
+    /// (default: the index's default, then `[search] hybrid_alpha` in config.toml, else 0.7)
+    #[arg(long)]
+    pub hybrid_alpha: Option<f32>,
+
+    /// Fuse learned sparse (SPLADE, BM42) scores with the vector scores; needs an
+    /// index built with --sparse-model (replaces automatic BM25 hybrid search)
+    #[arg(long)]
+    pub sparse: bool,
+
+    /// Weight for vector scores in sparse fusion (0.0-1.0)
+    #[arg(long, default_value = "0.5")]
+    pub sparse_alpha: f32,
+
+    /// text-embeddings-inference server for --sparse (default: the one used at build time)
+    #[arg(long, env = "LEANN_SPARSE_HOST")]
+    pub sparse_host: Option<String>,
+
+    /// Rerank the top dense candidates by late interaction (ColBERT-style MaxSim);
+    /// needs an index built with --multi-vector (experimental)
+    #[arg(long)]
+    pub multi_vector: bool,
+
+    /// Jina AI API key (for --multi-vector and the jina embedding mode)
+    #[arg(long, env = "JINA_API_KEY")]
+    pub jina_api_key: Option<String>,
+
+    /// Diversify results with maximal marginal relevance (fewer near-duplicates)
+    #[arg(long)]
+    pub mmr: bool,
+
+    /// MMR trade-off: 1.0 = pure relevance, 0.0 = pure diversity
+    #[arg(long, default_value = "0.5")]
+    pub mmr_lambda: f32,
+
+    /// Keep only the best result per value of a metadata field (e.g. "source")
+    #[arg(long)]
+    pub group_by: Option<String>,
+
+    /// After each code hit, add the passages defining the symbols it calls
+    /// (needs the `code` enricher at build time)
+    #[arg(long)]
+    pub include_definitions: bool,
+
+    /// Retrieve with HyDE: embed an LLM-written hypothetical answer to the query
+    /// (blended with the query embedding) instead of the query alone
+    #[arg(long)]
+    pub hyde: bool,
+
+    /// Weight of the hypothetical answer in the HyDE blend: 1.0 = hypothetical answer only
+    #[arg(long, default_value = "0.5")]
+    pub hyde_weight: f32,
 
-    /// Output format (text, json)
-    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    /// Also search N LLM-written reformulations of the query and fuse the
+    /// rankings with reciprocal rank fusion (reformulations are cached)
+    #[arg(long, value_name = "N")]
+    pub multi_query: Option<usize>,
+
+    /// LLM provider for --hyde and --multi-query (default: `[llm] provider` in config.toml, else ollama)
+    #[cfg(feature = "local-llm")]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated", "local"])]
+    pub llm: Option<String>,
+
+    /// LLM provider for --hyde and --multi-query (default: `[llm] provider` in config.toml, else ollama)
+    #[cfg(not(feature = "local-llm"))]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated"])]
+    pub llm: Option<String>,
+
+    /// LLM model name for --hyde and --multi-query (default: `[llm] model` in config.toml, else qwen3:8b)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Tokenizer file for local GGUF models (default: tokenizer.json next to the model)
+    #[cfg(feature = "local-llm")]
+    #[arg(long)]
+    pub tokenizer: Option<String>,
+
+    /// Ollama host for the LLM
+    #[arg(long, env = "OLLAMA_HOST")]
+    pub llm_host: Option<String>,
+
+    /// OpenAI/Anthropic API key for the LLM
+    #[arg(long, env = "OPENAI_API_KEY")]
+    pub llm_api_key: Option<String>,
+
+    /// OpenAI API base URL for the LLM
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub llm_api_base: Option<String>,
+
+    /// Print how each result was scored (vector, BM25, and sparse scores, fusion
+    /// weights, filters, boosts) and why other candidates were dropped, as JSON
+    #[arg(long, conflicts_with = "multi_query")]
+    pub explain: bool,
+
+    /// Output format (text, json, files). "files" prints one line per matching
+    /// file (path, best score, hit count), ranked by combined score
+    #[arg(long, default_value = "text", value_parser = ["text", "json", "files"])]
     pub format: String,
 
+    /// Disable colored highlighting of query terms
+    #[arg(long)]
+    pub no_color: bool,
+
     /// API key for embedding service
     #[arg(long, env = "OPENAI_API_KEY")]
     pub embedding_api_key: Option<String>,
@@ -64,15 +230,19 @@ pub struct SearchArgs {
     #[arg(long, env = "OLLAMA_HOST")]
     pub embedding_host: Option<String>,
 
-    /// Query prompt template prefix for asymmetric embedding models
-    /// (e.g., "query: " for E5 models, or custom prefix for Instructor models)
+    /// Query prompt template for asymmetric embedding models: a prefix (e.g., "query: "
+    /// for E5 models) or an instruction with a `{text}` placeholder (Instructor-style)
     #[arg(long)]
     pub query_prompt_template: Option<String>,
+
+    /// Maximum concurrent embedding requests when searching a pruned index
+    #[arg(long, default_value = "4")]
+    pub recompute_concurrency: usize,
 }
 
 pub async fn run(args: SearchArgs, _verbose: bool) -> anyhow::Result<()> {
     // Default to current directory name if no index specified
-    let index_name = args.index.unwrap_or_else(|| {
+    let index_name = args.index.clone().unwrap_or_else(|| {
         std::env::current_dir()
             .ok()
             .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
@@ -80,134 +250,508 @@ pub async fn run(args: SearchArgs, _verbose: bool) -> anyhow::Result<()> {
     });
 
     // Find index
-    let index_dir = find_index(&index_name)?;
-    let meta_path = index_dir.join("documents.leann.meta.json");
-    let index_path = index_dir.join("documents.leann");
+    let index_dir = resolve_index(&index_name).await?;
 
-    // Load metadata
-    let meta = IndexMeta::load(&meta_path)?;
+    // Latency for the query log covers query transformations and the search
+    // (and loading the index, unless a daemon has it loaded)
+    let search_start = std::time::Instant::now();
 
-    // Check if index is pruned (needs recomputation)
-    let is_pruned = meta.is_pruned;
+    // Search through `leann daemon` if one is running, else in this process
+    // (explanations are only recorded in-process)
+    let proxied = if args.explain {
+        None
+    } else {
+        super::daemon::proxy_search(&index_dir, &index_name, &args).await
+    };
+    let SearchOutput { results, corrected_query, explanation } = match proxied {
+        Some(output) => output?,
+        None => {
+            let index = LoadedIndex::load(&index_dir, &index_name, &args).await?;
+            search_loaded(&index, &index_name, &args).await?
+        }
+    };
 
-    info!(
-        "Searching index '{}' ({} passages, {} dims){}",
-        index_name, meta.passage_count, meta.dimensions,
-        if is_pruned { " [recompute mode]" } else { "" }
-    );
+    let config = Config::load();
+    if config.analytics.log_queries {
+        let logged_filter = combined_filter(&args)?.and_then(|f| serde_json::to_string(&f).ok());
+        let entry = QueryLogEntry::new(
+            "search",
+            &args.query,
+            logged_filter.as_deref(),
+            search_start.elapsed().as_millis() as u64,
+            results.iter().map(|r| r.id.clone()).collect(),
+        );
+        if let Err(e) = log_query(&index_dir, &entry) {
+            tracing::warn!("Failed to log query: {}", e);
+        }
+    }
+
+    if let Some(explanation) = explanation {
+        let mut json = serde_json::json!({ "index": index_name, "query": args.query });
+        if let Some(corrected) = &corrected_query {
+            json["corrected_query"] = corrected.as_str().into();
+        }
+        if let (Some(json), serde_json::Value::Object(fields)) = (json.as_object_mut(), serde_json::to_value(&explanation)?) {
+            json.extend(fields);
+        }
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    print_results(&args, &results, corrected_query.as_deref(), args.page_size(&meta.search_config(&config.search)))
+}
+
+/// `leann search` arguments parsed on their own, for pipelines (`leann run`)
+#[derive(clap::Parser)]
+#[command(name = "leann search", no_binary_name = true)]
+struct SearchCommand {
+    #[command(flatten)]
+    args: SearchArgs,
+}
+
+/// Parse `leann search` arguments (e.g. `["auth flow", "--top-k", "10"]`)
+pub(crate) fn parse_args(args: &[String]) -> anyhow::Result<SearchArgs> {
+    use clap::Parser;
 
-    // Create embedding provider from metadata
-    let embedding_mode = match meta.embedding_mode.as_str() {
-        "openai" => EmbeddingMode::OpenAI {
+    Ok(SearchCommand::try_parse_from(args)?.args)
+}
+
+impl SearchArgs {
+    /// Results per page: --page-size, then --top-k, then `config` (the index's
+    /// defaults over config.toml)
+    pub(super) fn page_size(&self, config: &SearchConfig) -> usize {
+        self.page_size.or(self.top_k).unwrap_or(config.top_k)
+    }
+}
+
+/// An index with its embedding provider and vector searcher, ready to search
+///
+/// `leann daemon` keeps these loaded between searches.
+pub(crate) struct LoadedIndex {
+    meta: IndexMeta,
+    index_path: PathBuf,
+    embedding_provider: EmbeddingProvider,
+    /// Vector searcher (None for pruned indexes, which are searched by recomputation)
+    searcher: Option<IndexSearcher>,
+}
+
+impl LoadedIndex {
+    /// Load the index in `index_dir` with the embedding settings of `args`
+    pub(crate) async fn load(index_dir: &Path, index_name: &str, args: &SearchArgs) -> anyhow::Result<Self> {
+        let meta_path = index_dir.join("documents.leann.meta.json");
+        let index_path = index_dir.join("documents.leann");
+        let meta = IndexMeta::load(&meta_path)?;
+
+        info!(
+            "Searching index '{}' ({} passages, {} dims){}",
+            index_name, meta.passage_count, meta.dimensions,
+            if meta.is_pruned { " [recompute mode]" } else { "" }
+        );
+
+        // Create embedding provider from metadata
+        let overrides = ProviderOverrides {
             api_key: args.embedding_api_key.clone(),
-            base_url: args.embedding_api_base.clone(),
-        },
-        "ollama" => EmbeddingMode::Ollama {
+            api_base: args.embedding_api_base.clone(),
             host: args.embedding_host.clone(),
-        },
-        "gemini" => EmbeddingMode::Gemini {
-            api_key: std::env::var("GOOGLE_API_KEY").ok(),
-        },
-        _ => anyhow::bail!("Unknown embedding mode in index: {}", meta.embedding_mode),
+            jina_api_key: args.jina_api_key.clone(),
+            ..Default::default()
+        };
+        let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &Config::load().embedding).await?;
+
+        let searcher = if meta.is_pruned {
+            None
+        } else {
+            Some(IndexSearcher::load(&index_path, &meta)?)
+        };
+
+        Ok(Self {
+            meta,
+            index_path,
+            embedding_provider,
+            searcher,
+        })
+    }
+
+    pub(crate) fn embedding_provider(&self) -> &EmbeddingProvider {
+        &self.embedding_provider
+    }
+}
+
+/// The `--filter`, `--modality`, `--language`, and `--namespace` filters, combined
+fn combined_filter(args: &SearchArgs) -> anyhow::Result<Option<MetadataFilter>> {
+    let filter = match &args.filter {
+        Some(filter_str) => Some(MetadataFilter::try_parse(filter_str)?),
+        None => None,
+    };
+
+    // Text passages have no modality field, so "text" means "not an image"
+    let modality_filter = args.modality.as_deref().map(|modality| match modality {
+        "image" => MetadataFilter::try_parse("modality=image"),
+        _ => MetadataFilter::try_parse("modality!=image"),
+    }).transpose()?;
+    let language_filter = match args.language.as_deref() {
+        Some(language) => {
+            let code = crate::chunker::language_code(language)
+                .ok_or_else(|| anyhow::anyhow!("Unknown language: {} (use an ISO 639-3 code such as 'deu')", language))?;
+            Some(MetadataFilter::try_parse(&format!("lang={}", code))?)
+        }
+        None => None,
+    };
+    let mut filters: Vec<MetadataFilter> = [filter, modality_filter, language_filter].into_iter().flatten().collect();
+    let filter = match filters.len() {
+        0 | 1 => filters.pop(),
+        _ => Some(MetadataFilter::And { and: filters }),
     };
+    Ok(match &args.namespace {
+        Some(namespace) => Some(MetadataFilter::in_namespace(namespace, filter)),
+        None => filter,
+    })
+}
+
+/// What a search found
+pub(crate) struct SearchOutput {
+    pub results: Vec<SearchResult>,
+    /// The query with its typos fixed, if it had any
+    pub corrected_query: Option<String>,
+    /// Why the results ranked as they did, with `--explain`
+    pub explanation: Option<SearchExplanation>,
+}
 
-    let embedding_provider = EmbeddingProvider::new(
-        meta.embedding_model.clone(),
-        embedding_mode,
-    ).await?;
+/// Run a search on a loaded index, with its explanation if `--explain` is set
+pub(crate) async fn search_loaded(
+    index: &LoadedIndex,
+    index_name: &str,
+    args: &SearchArgs,
+) -> anyhow::Result<SearchOutput> {
+    let LoadedIndex { meta, index_path, embedding_provider, searcher } = index;
+    if args.explain && meta.is_pruned {
+        anyhow::bail!("--explain is not supported for pruned indexes (recompute mode)");
+    }
 
     // Get query template from CLI args, metadata, or model defaults
-    let query_template = args.query_prompt_template.clone().unwrap_or_else(|| {
-        // Try to get from index metadata
-        if let Some(opts) = &meta.embedding_options {
-            if let Some(template) = opts.get("query_prompt_template").and_then(|v| v.as_str()) {
-                return template.to_string();
-            }
-        }
-        // Fall back to model defaults
-        crate::embedding::get_model_config(&meta.embedding_model).query_prefix.to_string()
-    });
+    let query_template = args.query_prompt_template
+        .clone()
+        .unwrap_or_else(|| meta.embedding_template(EmbeddingTask::Query));
 
     if !query_template.is_empty() {
         tracing::debug!("Using query prefix: {:?}", query_template);
     }
 
-    // Parse filter
-    let filter = if let Some(filter_str) = &args.filter {
-        if let Some(f) = MetadataFilter::parse(filter_str) {
-            Some(f)
-        } else {
-            anyhow::bail!("Invalid filter syntax: {}", filter_str);
+    let filter = combined_filter(args)?;
+
+    let synonyms = match index_path.parent() {
+        Some(index_dir) if !args.no_synonyms => Synonyms::load(index_dir)?,
+        _ => None,
+    };
+
+    // Fix typos against the index's vocabulary, leaving synonym terms as they are
+    let corrected_query = match searcher {
+        Some(searcher) if !args.no_correct => {
+            searcher.correct_query(&args.query, |word| synonyms.as_ref().is_some_and(|s| s.defines(word)))?
         }
+        _ => None,
+    };
+    let typed = corrected_query.as_deref().unwrap_or(&args.query);
+
+    // Add what the index's abbreviations and aliases stand for
+    let query = match &synonyms {
+        Some(synonyms) => synonyms.expand(typed),
+        None => typed.to_string(),
+    };
+    if query != typed {
+        info!("Added synonyms: '{}' → '{}'", typed, query);
+    }
+
+    // Determine if hybrid search should be used: the index's default, else
+    // automatically for short queries
+    let word_count = typed.split_whitespace().count();
+    let auto_hybrid = meta.default_hybrid().unwrap_or(args.auto_hybrid && word_count <= 3);
+    let use_hybrid = args.hybrid || (auto_hybrid && !args.no_hybrid && !args.sparse);
+
+    // Page of results to return: flags, then the index's defaults, then config.toml
+    let config = Config::load();
+    let search_config = meta.search_config(&config.search);
+    let page_size = args.page_size(&search_config);
+    let complexity = args.complexity.unwrap_or(search_config.complexity);
+
+    // Files mode pages over files, so fetch enough chunks to fill the requested files
+    let files_mode = args.format == "files";
+    let mut page = if files_mode {
+        SearchOptions::new((page_size + args.offset) * FILE_HITS_PER_FILE, complexity)
+    } else {
+        SearchOptions::new(page_size, complexity).with_offset(args.offset)
+    };
+    if let Some(min_score) = args.min_score {
+        page = page.with_min_score(min_score);
+    }
+    if let Some(boost) = &args.boost {
+        page = page.with_boost(Boost::parse(boost)?);
+    }
+    if args.no_feedback {
+        page = page.without_feedback();
+    }
+
+    // LLM for query transformations (--hyde, --multi-query)
+    let llm = if args.hyde || args.multi_query.is_some() {
+        let provider = args.llm.as_deref().unwrap_or(&config.llm.provider);
+        let overrides = LlmOverrides {
+            host: args.llm_host.clone(),
+            api_key: args.llm_api_key.clone(),
+            api_base: args.llm_api_base.clone(),
+            #[cfg(feature = "local-llm")]
+            tokenizer: args.tokenizer.clone(),
+        };
+        let llm_type = LlmType::from_name(provider, &overrides, &config.llm)?;
+        let model = args.model.clone().unwrap_or_else(|| config.llm.model.clone());
+        Some(LlmProvider::new(model, llm_type)?)
     } else {
         None
     };
 
-    // Determine if hybrid search should be used
-    let word_count = args.query.split_whitespace().count();
-    let use_hybrid = args.hybrid || (args.auto_hybrid && word_count <= 3);
+    // HyDE: search near a hypothetical answer rather than the question alone
+    if let Some(llm) = llm.as_ref().filter(|_| args.hyde) {
+        let embedding = hyde_embedding(llm, embedding_provider, &meta.embedding_template(EmbeddingTask::Document), typed).await?;
+        page = page.with_hyde(embedding, args.hyde_weight);
+    }
+
+    // Multi-query: also search LLM-written reformulations and fuse the rankings
+    let reformulations = match (&llm, args.multi_query) {
+        (Some(llm), Some(n)) => {
+            let cache = Mutex::new(ReformulationCache::load());
+            let queries = reformulate_query(llm, typed, n, &cache).await?;
+            for query in &queries {
+                info!("Reformulated query: '{}'", query);
+            }
+            queries
+        }
+        _ => Vec::new(),
+    };
 
     // Search - use recompute mode if index is pruned
-    let results: Vec<SearchResult> = if is_pruned {
+    let mut explanation = None;
+    let results: Vec<SearchResult> = if meta.is_pruned {
         if use_hybrid {
             info!("Note: Hybrid search is not supported in recompute mode, using vector search only");
         }
+        if args.mmr || args.group_by.is_some() {
+            info!("Note: --mmr and --group-by are not supported in recompute mode");
+        }
+        if args.sparse || args.multi_vector {
+            info!("Note: --sparse and --multi-vector are not supported in recompute mode, using vector search only");
+        }
 
         // No expansion in recompute mode
-        let query_embedding = embedding_provider
-            .embed_with_template(&[&args.query], &query_template)
+        let mut queries: Vec<&str> = vec![query.as_str()];
+        queries.extend(reformulations.iter().map(|q| q.as_str()));
+        let query_embeddings = embedding_provider
+            .embed_for_task(&queries, EmbeddingTask::Query, &query_template)
             .await?;
 
-        let searcher = RecomputeSearcher::load(&index_path, meta.dimensions)?;
-        searcher.search(
-            &query_embedding[0],
-            &embedding_provider,
-            args.top_k,
-            filter.as_ref(),
-        ).await?
+        let searcher = RecomputeSearcher::load(index_path, meta.dimensions)?
+            .with_concurrency(args.recompute_concurrency)
+            .with_document_template(meta.embedding_template(EmbeddingTask::Document));
+        let mut lists = Vec::with_capacity(query_embeddings.len());
+        for query_embedding in &query_embeddings {
+            lists.push(searcher.search(
+                &page.query_vector(query_embedding)?,
+                embedding_provider,
+                page.top_k + page.offset,
+                filter.as_ref(),
+            ).await?);
+        }
+        if lists.len() == 1 {
+            page.paginate(lists.remove(0))
+        } else {
+            page.fuse(lists)
+        }
     } else {
         // Normal search with vector index
-        let searcher = IndexSearcher::load(&index_path, &meta)?;
+        let searcher = searcher
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Index '{}' has no vector searcher loaded", index_name))?;
 
         // Expand query using BM25 matches if enabled
-        let search_query = if args.expand && should_expand(&args.query) {
-            let bm25_texts = searcher.bm25_search(&args.query, 5)?;
+        let search_query = if args.expand && should_expand(typed) {
+            let bm25_texts = searcher.bm25_search(&query, 5)?;
             if !bm25_texts.is_empty() {
                 let text_refs: Vec<&str> = bm25_texts.iter().map(|s| s.as_str()).collect();
-                let expanded = expand_from_passages(&args.query, &text_refs, 5);
-                if expanded != args.query {
-                    info!("Expanded query: '{}' → '{}'", args.query, expanded);
+                let expanded = expand_from_passages(&query, &text_refs, 5);
+                if expanded != query {
+                    info!("Expanded query: '{}' → '{}'", query, expanded);
                 }
                 expanded
             } else {
-                args.query.clone()
+                query.clone()
             }
         } else {
-            args.query.clone()
+            query.clone()
         };
 
-        // Compute query embedding
-        let query_embedding = embedding_provider
-            .embed_with_template(&[&search_query], &query_template)
+        // Compute query embeddings (the expanded query, then any reformulations)
+        let mut queries: Vec<&str> = vec![search_query.as_str()];
+        queries.extend(reformulations.iter().map(|q| q.as_str()));
+        let query_embeddings = embedding_provider
+            .embed_for_task(&queries, EmbeddingTask::Query, &query_template)
             .await?;
 
-        let mut opts = SearchOptions::new(args.top_k, args.complexity);
+        let filter_mode: FilterMode = args.filter_mode.parse()
+            .map_err(|e: String| anyhow::anyhow!(e))?;
+        let mut opts = page.with_filter_mode(filter_mode);
 
         if let Some(f) = filter {
             opts = opts.with_filter(f);
         }
 
+        if args.mmr {
+            opts = opts.with_mmr(args.mmr_lambda);
+        }
+
+        if let Some(field) = &args.group_by {
+            opts = opts.with_group_by(field.clone());
+        }
+
         if use_hybrid {
-            if word_count <= 3 && !args.hybrid {
+            if !args.hybrid && meta.default_hybrid().is_none() {
                 tracing::debug!("Auto-enabling hybrid search for short query ({} words)", word_count);
             }
-            opts = opts.with_hybrid(search_query.clone(), args.hybrid_alpha);
+            opts = opts.with_hybrid(search_query.clone(), args.hybrid_alpha.unwrap_or(search_config.hybrid_alpha));
+        }
+
+        if args.sparse {
+            let Some(sparse_model) = &meta.sparse_model else {
+                anyhow::bail!(
+                    "Index '{}' has no sparse vectors. Rebuild it with --sparse-model to use --sparse.",
+                    index_name
+                );
+            };
+            let sparse_query = sparse_model
+                .provider(args.sparse_host.clone())
+                .embed(&[query.as_str()])
+                .await?
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            opts = opts.with_sparse(sparse_query, args.sparse_alpha);
+        }
+
+        if args.multi_vector {
+            let Some(multi_vector) = &meta.multi_vector else {
+                anyhow::bail!(
+                    "Index '{}' has no per-token vectors. Rebuild it with --multi-vector to use --multi-vector.",
+                    index_name
+                );
+            };
+            let query_tokens = multi_vector
+                .provider(args.jina_api_key.clone())?
+                .embed(&[query.as_str()], EmbeddingTask::Query)
+                .await?
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            opts = opts.with_multi_vector(query_tokens);
         }
 
-        searcher.search_with_options(&query_embedding[0], &opts)?
+        if args.explain {
+            let (results, mut explained) = searcher.search_explained(&query_embeddings[0], &opts)?;
+            explained.search_query = Some(search_query.clone());
+            explanation = Some(explained);
+            results
+        } else if queries.len() == 1 {
+            searcher.search_with_options(&query_embeddings[0], &opts)?
+        } else {
+            let fused: Vec<(&[f32], &str)> = query_embeddings
+                .iter()
+                .map(|e| e.as_slice())
+                .zip(queries.iter().copied())
+                .collect();
+            searcher.search_fused(&fused, &opts)?
+        }
+    };
+
+    let results = if args.include_definitions { include_definitions(index_path, results)? } else { results };
+
+    Ok(SearchOutput { results, corrected_query, explanation })
+}
+
+/// Most definitions added after one hit
+const DEFINITIONS_PER_HIT: usize = 3;
+
+/// Symbols defined in more passages than this are too ambiguous to follow
+const MAX_DEFINITION_SITES: usize = 3;
+
+/// Insert, after each hit, the passages defining the symbols in its `calls`
+/// metadata; they score 0.0 and carry `definition_of` with the symbol
+fn include_definitions(index_path: &Path, results: Vec<SearchResult>) -> anyhow::Result<Vec<SearchResult>> {
+    let calls = |result: &SearchResult| -> Vec<String> {
+        result.metadata.get("calls")
+            .and_then(|c| c.as_array())
+            .map(|c| c.iter().filter_map(|s| s.as_str().map(String::from)).collect())
+            .unwrap_or_default()
     };
+    let wanted: HashSet<String> = results.iter().flat_map(calls).collect();
+    if wanted.is_empty() {
+        return Ok(results);
+    }
+
+    // One pass over the passages for where each wanted symbol is defined
+    let store = PassageStore::open(index_path)?;
+    let mut sites: HashMap<String, Vec<String>> = HashMap::new();
+    for passage in store.iter()? {
+        let passage = passage?;
+        let Some(defines) = passage.metadata.get("defines").and_then(|d| d.as_array()) else {
+            continue;
+        };
+        for symbol in defines.iter().filter_map(|d| d.as_str()).filter(|d| wanted.contains(*d)) {
+            sites.entry(symbol.to_string()).or_default().push(passage.id.clone());
+        }
+    }
+    sites.retain(|_, ids| ids.len() <= MAX_DEFINITION_SITES);
+
+    let mut seen: HashSet<String> = results.iter().map(|r| r.id.clone()).collect();
+    let mut expanded = Vec::with_capacity(results.len());
+    for result in results {
+        let symbols = calls(&result);
+        expanded.push(result);
+        let mut added = 0;
+        for symbol in &symbols {
+            for id in sites.get(symbol).into_iter().flatten() {
+                if added == DEFINITIONS_PER_HIT || !seen.insert(id.clone()) {
+                    continue;
+                }
+                // `get` re-reads the text of text-free indexes
+                let passage = store.get(id)?;
+                let mut metadata = passage.metadata;
+                metadata["definition_of"] = symbol.as_str().into();
+                expanded.push(SearchResult { id: passage.id, score: 0.0, text: passage.text, metadata });
+                added += 1;
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+/// Print results in the requested format, noting any spelling correction
+/// (on stderr for the machine-readable formats)
+pub(super) fn print_results(
+    args: &SearchArgs,
+    results: &[SearchResult],
+    corrected_query: Option<&str>,
+    page_size: usize,
+) -> anyhow::Result<()> {
+    let query = corrected_query.unwrap_or(&args.query);
+    let terms = highlight::query_terms(query);
+    if let Some(corrected) = corrected_query.filter(|_| args.format != "text") {
+        info!("Searched for '{}' instead of '{}' (--no-correct searches as typed)", corrected, args.query);
+    }
+
+    if args.format == "files" {
+        for file in aggregate_by_source(results).iter().skip(args.offset).take(page_size) {
+            println!("{}\t{:.4}\t{}", file.source, file.best_score, file.hits);
+        }
+        return Ok(());
+    }
 
-    // Output results
     if args.format == "json" {
         let json_results: Vec<serde_json::Value> = results
             .iter()
@@ -217,15 +761,36 @@ pub async fn run(args: SearchArgs, _verbose: bool) -> anyhow::Result<()> {
                     "score": r.score,
                     "text": r.text,
                     "metadata": r.metadata,
+                    "highlights": highlight::find_highlights(&r.text, &terms)
+                        .into_iter()
+                        .map(|(start, end)| [start, end])
+                        .collect::<Vec<_>>(),
                 })
             })
             .collect();
         println!("{}", serde_json::to_string_pretty(&json_results)?);
     } else {
-        println!("\nSearch results for '{}' (top {}):\n", args.query, results.len());
+        if corrected_query.is_some() {
+            println!("\nShowing results for '{}' instead of '{}' (--no-correct searches as typed)", query, args.query);
+        }
+        if args.offset > 0 {
+            println!(
+                "\nSearch results for '{}' ({}-{}):\n",
+                query,
+                args.offset + 1,
+                args.offset + results.len()
+            );
+        } else {
+            println!("\nSearch results for '{}' (top {}):\n", query, results.len());
+        }
+
+        let color = highlight::use_color(args.no_color);
 
         for (i, result) in results.iter().enumerate() {
-            println!("{}. Score: {:.4}", i + 1, result.score);
+            match highlight::location(&result.metadata) {
+                Some(location) => println!("{}. Score: {:.4}  {}", args.offset + i + 1, result.score, location),
+                None => println!("{}. Score: {:.4}", args.offset + i + 1, result.score),
+            }
 
             if args.show_metadata {
                 if let Some(source) = result.metadata.get("source") {
@@ -242,14 +807,16 @@ pub async fn run(args: SearchArgs, _verbose: bool) -> anyhow::Result<()> {
             }
 
             // Truncate text for display (respecting UTF-8 boundaries)
-            let display_text = if result.text.len() > 200 {
-                let mut end = 200;
-                while end > 0 && !result.text.is_char_boundary(end) {
-                    end -= 1;
+            let display_text = if result.text.is_empty() {
+                // Text-free index whose source file is missing or changed
+                let source = result.metadata.get("source").and_then(|s| s.as_str()).unwrap_or("?");
+                match (result.metadata.get("start_line"), result.metadata.get("end_line")) {
+                    (Some(start), Some(end)) => format!("[text not stored] {} (lines {}-{})", source, start, end),
+                    _ => format!("[text not stored] {}", source),
                 }
-                format!("{}...", &result.text[..end])
             } else {
-                result.text.clone()
+                let highlights = highlight::find_highlights(&result.text, &terms);
+                highlight::snippet(&result.text, &highlights, 200, color)
             };
             println!("   {}", display_text);
             println!();
@@ -259,3 +826,22 @@ pub async fn run(args: SearchArgs, _verbose: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
+
+/// Embed an LLM-written hypothetical answer to `query` the way passages were embedded
+pub(crate) async fn hyde_embedding(
+    llm: &LlmProvider,
+    embedding_provider: &EmbeddingProvider,
+    document_template: &str,
+    query: &str,
+) -> anyhow::Result<Vec<f32>> {
+    let passage = hypothetical_document(llm, query).await?;
+    tracing::debug!("HyDE passage: {}", passage);
+
+    let embeddings = embedding_provider
+        .embed_for_task(&[&passage], EmbeddingTask::Document, document_template)
+        .await?;
+    embeddings
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Embedding provider returned no embedding for the HyDE passage"))
+}
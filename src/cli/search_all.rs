@@ -0,0 +1,307 @@
+//! Search-all command - query every local and global index at once
+
+use std::collections::HashMap;
+
+use clap::Args;
+use tracing::info;
+
+use crate::config::Config;
+use crate::embedding::{EmbeddingMode, EmbeddingProvider, EmbeddingTask, PostProcessing, ProviderOverrides};
+use crate::index::{list_indexes, FilterMode, IndexMeta, IndexSearcher, MetadataFilter, SearchOptions, SearchResult};
+
+#[derive(Args)]
+pub struct SearchAllArgs {
+    /// Search query
+    pub query: String,
+
+    /// Number of results to return from each index
+    #[arg(long, default_value = "3")]
+    pub top_k: usize,
+
+    /// Search complexity (higher = more accurate but slower)
+    #[arg(long, default_value = "64")]
+    pub complexity: usize,
+
+    /// Filter results by metadata (e.g., "source:*.rs" or "type=code")
+    #[arg(long, short = 'f')]
+    pub filter: Option<String>,
+
+    /// When to apply the filter (pre, post); see `leann search --help`
+    #[arg(long, default_value = "pre", value_parser = ["pre", "post"])]
+    pub filter_mode: String,
+
+    /// How to merge results: "rank" interleaves by rank (scores from different
+    /// models aren't comparable), "score" sorts by raw score
+    #[arg(long, default_value = "rank", value_parser = ["rank", "score"])]
+    pub merge: String,
+
+    /// Show file paths in results
+    #[arg(long)]
+    pub show_metadata: bool,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+
+    /// API key for embedding service
+    #[arg(long, env = "OPENAI_API_KEY")]
+    pub embedding_api_key: Option<String>,
+
+    /// OpenAI API base URL
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub embedding_api_base: Option<String>,
+
+    /// Ollama host for embeddings
+    #[arg(long, env = "OLLAMA_HOST")]
+    pub embedding_host: Option<String>,
+}
+
+/// An index that can be searched, with its query embedding group
+struct Target {
+    name: String,
+    index_path: std::path::PathBuf,
+    meta: IndexMeta,
+    /// (embedding mode, model, query prefix, post-processing) - indexes sharing a key
+    /// share one query embedding
+    group: (String, String, String, PostProcessing),
+}
+
+pub async fn run(args: SearchAllArgs, _verbose: bool) -> anyhow::Result<()> {
+    let filter = match &args.filter {
+        Some(filter_str) => Some(MetadataFilter::try_parse(filter_str)?),
+        None => None,
+    };
+    let filter_mode: FilterMode = args.filter_mode.parse()
+        .map_err(|e: String| anyhow::anyhow!(e))?;
+
+    // Enumerate indexes and skip ones that can't be searched directly
+    let mut targets: Vec<Target> = Vec::new();
+    let mut skipped: Vec<(String, String)> = Vec::new();
+
+    for location in list_indexes() {
+        let meta = match IndexMeta::load(&location.path.join("documents.leann.meta.json")) {
+            Ok(meta) => meta,
+            Err(e) => {
+                skipped.push((location.name, format!("unreadable metadata: {}", e)));
+                continue;
+            }
+        };
+        if meta.is_pruned {
+            skipped.push((location.name, "pruned (use 'leann search' for recompute mode)".to_string()));
+            continue;
+        }
+
+        let query_prefix = meta.embedding_template(EmbeddingTask::Query);
+
+        targets.push(Target {
+            name: location.name,
+            index_path: location.path.join("documents.leann"),
+            group: (meta.embedding_mode.clone(), meta.embedding_model.clone(), query_prefix, meta.post_processing()),
+            meta,
+        });
+    }
+
+    if targets.is_empty() {
+        println!("No searchable indexes found. Run 'leann list' to see available indexes.");
+        return Ok(());
+    }
+
+    info!("Searching {} indexes", targets.len());
+
+    // Embed the query once per (mode, model, prefix, post-processing) group
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        ..Default::default()
+    };
+    let config = Config::load();
+    let mut embeddings: HashMap<(String, String, String, PostProcessing), Vec<f32>> = HashMap::new();
+    for target in &targets {
+        if embeddings.contains_key(&target.group) {
+            continue;
+        }
+        let (mode, model, prefix, post_processing) = &target.group;
+
+        let embedding_mode = match EmbeddingMode::from_name(mode, &overrides, &config.embedding) {
+            Ok(embedding_mode) => embedding_mode,
+            Err(e) => {
+                tracing::warn!("{} (model {})", e, model);
+                continue;
+            }
+        };
+
+        let embedded = async {
+            let provider = EmbeddingProvider::new(model.clone(), embedding_mode)
+                .await?
+                .with_post_processing(post_processing.clone())?;
+            provider.embed_for_task(&[&args.query], EmbeddingTask::Query, prefix).await
+        }
+        .await;
+
+        match embedded {
+            Ok(mut vectors) if !vectors.is_empty() => {
+                embeddings.insert(target.group.clone(), vectors.remove(0));
+            }
+            Ok(_) => tracing::warn!("No query embedding returned by {} / {}", mode, model),
+            Err(e) => tracing::warn!("Could not embed query with {} / {}: {}", mode, model, e),
+        }
+    }
+
+    // Search all indexes concurrently
+    let mut tasks = Vec::new();
+    for target in targets {
+        let Some(query_embedding) = embeddings.get(&target.group).cloned() else {
+            skipped.push((target.name, format!("embedding provider {} unavailable", target.meta.embedding_mode)));
+            continue;
+        };
+
+        let mut opts = SearchOptions::new(args.top_k, args.complexity).with_filter_mode(filter_mode);
+        if let Some(f) = &filter {
+            opts = opts.with_filter(f.clone());
+        }
+
+        let name = target.name.clone();
+        let task = tokio::task::spawn_blocking(move || {
+            let searcher = IndexSearcher::load(&target.index_path, &target.meta)?;
+            searcher.search_with_options(&query_embedding, &opts)
+        });
+        tasks.push((name, task));
+    }
+
+    let mut per_index: Vec<(String, Vec<SearchResult>)> = Vec::new();
+    for (name, task) in tasks {
+        match task.await {
+            Ok(Ok(results)) => per_index.push((name, results)),
+            Ok(Err(e)) => skipped.push((name, format!("search failed: {}", e))),
+            Err(e) => skipped.push((name, format!("search task failed: {}", e))),
+        }
+    }
+
+    let merged = merge_results(per_index, &args.merge);
+
+    if args.format == "json" {
+        let json = serde_json::json!({
+            "query": args.query,
+            "results": merged
+                .iter()
+                .map(|(index, r)| serde_json::json!({
+                    "index": index,
+                    "id": r.id,
+                    "score": r.score,
+                    "text": r.text,
+                    "metadata": r.metadata,
+                }))
+                .collect::<Vec<_>>(),
+            "skipped": skipped
+                .iter()
+                .map(|(index, reason)| serde_json::json!({ "index": index, "reason": reason }))
+                .collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    println!("\nSearch results for '{}' across all indexes ({}):\n", args.query, merged.len());
+
+    for (i, (index, result)) in merged.iter().enumerate() {
+        println!("{}. [{}] Score: {:.4}", i + 1, index, result.score);
+
+        if args.show_metadata {
+            if let Some(source) = result.metadata.get("source") {
+                println!("   Source: {}", source);
+            }
+        }
+
+        let display_text = if result.text.len() > 200 {
+            let mut end = 200;
+            while end > 0 && !result.text.is_char_boundary(end) {
+                end -= 1;
+            }
+            format!("{}...", &result.text[..end])
+        } else {
+            result.text.clone()
+        };
+        println!("   {}", display_text);
+        println!();
+    }
+
+    if !skipped.is_empty() {
+        println!("Skipped indexes:");
+        for (index, reason) in &skipped {
+            println!("  {}: {}", index, reason);
+        }
+    }
+
+    Ok(())
+}
+
+/// Merge per-index results, tagging each with its index name
+///
+/// "rank" takes the best result from each index, then the second best, and so on;
+/// "score" sorts everything by raw score.
+fn merge_results(per_index: Vec<(String, Vec<SearchResult>)>, merge: &str) -> Vec<(String, SearchResult)> {
+    if merge == "score" {
+        let mut all: Vec<(String, SearchResult)> = per_index
+            .into_iter()
+            .flat_map(|(name, results)| results.into_iter().map(move |r| (name.clone(), r)))
+            .collect();
+        all.sort_by(|a, b| b.1.score.partial_cmp(&a.1.score).unwrap_or(std::cmp::Ordering::Equal));
+        return all;
+    }
+
+    let mut iters: Vec<(String, std::vec::IntoIter<SearchResult>)> = per_index
+        .into_iter()
+        .map(|(name, results)| (name, results.into_iter()))
+        .collect();
+    let mut merged = Vec::new();
+    loop {
+        let mut any = false;
+        for (name, iter) in iters.iter_mut() {
+            if let Some(result) = iter.next() {
+                merged.push((name.clone(), result));
+                any = true;
+            }
+        }
+        if !any {
+            break;
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: &str, score: f32) -> SearchResult {
+        SearchResult {
+            id: id.to_string(),
+            score,
+            text: String::new(),
+            metadata: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn test_merge_by_rank_interleaves() {
+        let per_index = vec![
+            ("a".to_string(), vec![result("a1", 0.9), result("a2", 0.8), result("a3", 0.7)]),
+            ("b".to_string(), vec![result("b1", 0.3)]),
+        ];
+        let merged = merge_results(per_index, "rank");
+        let ids: Vec<&str> = merged.iter().map(|(_, r)| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a1", "b1", "a2", "a3"]);
+        assert_eq!(merged[1].0, "b");
+    }
+
+    #[test]
+    fn test_merge_by_score() {
+        let per_index = vec![
+            ("a".to_string(), vec![result("a1", 0.5)]),
+            ("b".to_string(), vec![result("b1", 0.9)]),
+        ];
+        let merged = merge_results(per_index, "score");
+        assert_eq!(merged[0].1.id, "b1");
+    }
+}
@@ -2,9 +2,14 @@
 
 use clap::Args;
 
+use super::ask::GenerationArgs;
+
+#[cfg(feature = "grpc")]
+mod grpc;
+
 #[derive(Args)]
 pub struct ServeArgs {
-    /// Index name to serve
+    /// Index name or s3://bucket/prefix URL to serve
     pub index_name: String,
 
     /// Port to listen on
@@ -19,13 +24,76 @@ pub struct ServeArgs {
     #[arg(long)]
     pub cors: bool,
 
+    /// Also serve the gRPC API (proto/leann.proto) on this port
+    #[cfg(feature = "grpc")]
+    #[arg(long)]
+    pub grpc_port: Option<u16>,
+
     /// API key for embedding service
     #[arg(long, env = "OPENAI_API_KEY")]
     pub embedding_api_key: Option<String>,
 
+    /// OpenAI API base URL
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub embedding_api_base: Option<String>,
+
     /// Ollama host for embeddings
     #[arg(long, env = "OLLAMA_HOST")]
     pub embedding_host: Option<String>,
+
+    /// Only load passages matching this metadata filter (e.g., "source^docs/")
+    #[arg(long)]
+    pub scope: Option<String>,
+
+    /// Cache the embeddings and results of this many recent queries, so
+    /// repeated queries are answered without re-embedding or searching (0 = off)
+    #[arg(long, default_value = "0")]
+    pub cache_size: usize,
+
+    /// LLM provider for POST /ask (the endpoint is disabled without one)
+    #[cfg(feature = "local-llm")]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated", "local"])]
+    pub llm: Option<String>,
+
+    /// LLM provider for POST /ask (the endpoint is disabled without one)
+    #[cfg(not(feature = "local-llm"))]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated"])]
+    pub llm: Option<String>,
+
+    /// LLM model name (default: `[llm] model` in config.toml, else qwen3:8b)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Tokenizer file for local GGUF models (default: tokenizer.json next to the model)
+    #[cfg(feature = "local-llm")]
+    #[arg(long)]
+    pub tokenizer: Option<String>,
+
+    /// Ollama host for the LLM
+    #[arg(long, env = "OLLAMA_HOST")]
+    pub llm_host: Option<String>,
+
+    /// OpenAI/Anthropic API key for the LLM
+    #[arg(long, env = "OPENAI_API_KEY")]
+    pub llm_api_key: Option<String>,
+
+    /// OpenAI API base URL for the LLM
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub llm_api_base: Option<String>,
+
+    /// Default generation options for /ask (requests may override them)
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+
+    /// Enable POST /build, which builds indexes from paths on the server's
+    /// filesystem and streams progress events (and the gRPC AddDocuments call)
+    #[arg(long)]
+    pub allow_build: bool,
+
+    /// Reject search and ask requests that don't name a namespace, for
+    /// indexes shared by several users or projects (see `leann build --namespace`)
+    #[arg(long)]
+    pub require_namespace: bool,
 }
 
 #[cfg(feature = "server")]
@@ -40,11 +108,13 @@ pub async fn run(args: ServeArgs, _verbose: bool) -> anyhow::Result<()> {
     use tokio::sync::RwLock;
     use tracing::info;
 
-    use crate::embedding::{EmbeddingMode, EmbeddingProvider};
-    use crate::index::{find_index, IndexMeta, IndexSearcher};
+    use crate::config::Config;
+    use crate::embedding::{EmbeddingProvider, ProviderOverrides};
+    use crate::index::{resolve_index, IndexMeta, IndexSearcher, LruCache, MetadataFilter};
+    use crate::llm::{context_window, LlmOverrides, LlmProvider, LlmType, PromptTemplate, DEFAULT_ASK_TEMPLATE, DEFAULT_MAX_TOKENS};
 
     // Find and load index
-    let index_dir = find_index(&args.index_name)?;
+    let index_dir = resolve_index(&args.index_name).await?;
     let meta_path = index_dir.join("documents.leann.meta.json");
     let index_path = index_dir.join("documents.leann");
 
@@ -55,35 +125,92 @@ pub async fn run(args: ServeArgs, _verbose: bool) -> anyhow::Result<()> {
         args.index_name, meta.passage_count, meta.dimensions
     );
 
+    let config = Config::load();
+
     // Create embedding provider
-    let embedding_mode = match meta.embedding_mode.as_str() {
-        "openai" => EmbeddingMode::OpenAI {
-            api_key: args.embedding_api_key.clone(),
-            base_url: None,
-        },
-        "ollama" => EmbeddingMode::Ollama {
-            host: args.embedding_host.clone(),
-        },
-        "gemini" => EmbeddingMode::Gemini {
-            api_key: std::env::var("GOOGLE_API_KEY").ok(),
-        },
-        _ => anyhow::bail!("Unknown embedding mode: {}", meta.embedding_mode),
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        ..Default::default()
     };
+    let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &config.embedding).await?;
 
-    let embedding_provider = EmbeddingProvider::new(
-        meta.embedding_model.clone(),
-        embedding_mode,
-    ).await?;
+    // Parse scope filter
+    let scope = match &args.scope {
+        Some(scope_str) => Some(
+            MetadataFilter::try_parse(scope_str)
+                .map_err(|e| anyhow::anyhow!("Invalid --scope: {}", e))?,
+        ),
+        None => None,
+    };
 
-    // Load index
-    let searcher = IndexSearcher::load(&index_path, &meta)?;
+    // Load index (restricted to scope if given)
+    let index_version = crate::index::index_version(&index_dir);
+    let searcher = IndexSearcher::load_scoped(&index_path, &meta, scope)?.with_cache(args.cache_size);
+
+    if let Some(scope_str) = &args.scope {
+        info!(
+            "Scope '{}': serving {} of {} passages",
+            scope_str,
+            searcher.scoped_len(),
+            meta.passage_count
+        );
+    }
+
+    // Optional LLM for /ask
+    let model = args.model.clone().unwrap_or_else(|| config.llm.model.clone());
+    let llm = match args.llm.as_deref() {
+        Some(provider) => {
+            let overrides = LlmOverrides {
+                host: args.llm_host.clone(),
+                api_key: args.llm_api_key.clone(),
+                api_base: args.llm_api_base.clone(),
+                #[cfg(feature = "local-llm")]
+                tokenizer: args.tokenizer.clone(),
+            };
+            let llm_type = LlmType::from_name(provider, &overrides, &config.llm)?;
+            Some(
+                LlmProvider::new(model.clone(), llm_type)?
+                    .with_options(args.generation.to_options())?
+                    .with_redactor(args.generation.redactor()?),
+            )
+        }
+        None => None,
+    };
+
+    let template = PromptTemplate::resolve(
+        None,
+        meta.prompt_templates.as_ref().and_then(|t| t.ask.as_deref()),
+        config.prompts.ask_path().as_deref(),
+        DEFAULT_ASK_TEMPLATE,
+    )?;
+
+    // Prompt tokens available for /ask before reserving room for the answer
+    let prompt_budget = args.llm.as_deref().and_then(|provider| {
+        let reserve = args.generation.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS) as usize;
+        context_window(provider, &model).map(|window| window.saturating_sub(reserve))
+    });
 
     // Shared state
     let state = Arc::new(AppState {
         embedding_provider: RwLock::new(embedding_provider),
         searcher: RwLock::new(searcher),
         index_name: args.index_name.clone(),
-        meta,
+        index_dir: index_dir.clone(),
+        index_version: std::sync::Mutex::new(index_version),
+        scope: args.scope.clone(),
+        meta: RwLock::new(meta),
+        cache_size: args.cache_size,
+        query_embeddings: (args.cache_size > 0).then(|| std::sync::Mutex::new(LruCache::new(args.cache_size))),
+        llm,
+        template,
+        prompt_budget,
+        allow_build: args.allow_build,
+        running: std::sync::Mutex::new(Vec::new()),
+        require_namespace: args.require_namespace,
+        query_log: config.analytics.log_queries.then(|| index_dir.clone()),
+        search_defaults: config.search.clone(),
     });
 
     // Build router
@@ -92,8 +219,15 @@ pub async fn run(args: ServeArgs, _verbose: bool) -> anyhow::Result<()> {
         .route("/health", get(health))
         .route("/indexes", get(list_indexes))
         .route("/search", post(search))
+        .route("/ask", post(ask))
+        .route("/build", post(build))
+        .route("/build/cancel", post(cancel_build))
+        .route("/feedback", post(feedback))
         .route("/info", get(info_handler))
-        .with_state(state);
+        .layer(axum::middleware::from_fn(trace_request))
+        .with_state(state.clone());
+    #[cfg(feature = "grpc")]
+    let grpc_state = state;
 
     if args.cors {
         app = app.layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any));
@@ -103,10 +237,35 @@ pub async fn run(args: ServeArgs, _verbose: bool) -> anyhow::Result<()> {
     println!("LEANN server listening on http://{}", addr);
     println!("  GET  /indexes - List available indexes");
     println!("  POST /search  - Search the index");
+    if args.llm.is_some() {
+        println!("  POST /ask     - Answer a question from the index (RAG)");
+    }
+    if args.allow_build {
+        println!("  POST /build   - Build an index, streaming progress events");
+        println!("  POST /build/cancel - Stop a running build, keeping its embeddings for the next attempt");
+    }
+    if config.analytics.log_queries {
+        println!("  POST /feedback - Report which results of a logged query were used");
+    }
     println!("  GET  /info    - Get index information");
     println!("  GET  /health  - Health check");
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
+
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_port) = args.grpc_port {
+        let grpc_addr: std::net::SocketAddr = format!("{}:{}", args.host, grpc_port).parse()?;
+        println!("LEANN gRPC server listening on {} (leann.v1.Leann)", grpc_addr);
+        let grpc = tonic::transport::Server::builder()
+            .add_service(grpc::server(grpc_state))
+            .serve(grpc_addr);
+        tokio::try_join!(
+            async { axum::serve(listener, app).await.map_err(anyhow::Error::from) },
+            async { grpc.await.map_err(anyhow::Error::from) },
+        )?;
+        return Ok(());
+    }
+
     axum::serve(listener, app).await?;
 
     Ok(())
@@ -117,12 +276,127 @@ struct AppState {
     embedding_provider: tokio::sync::RwLock<crate::embedding::EmbeddingProvider>,
     searcher: tokio::sync::RwLock<crate::index::IndexSearcher>,
     index_name: String,
-    meta: crate::index::IndexMeta,
+    index_dir: std::path::PathBuf,
+    /// Version of the index (and its feedback) when it was loaded
+    index_version: std::sync::Mutex<crate::index::IndexVersion>,
+    scope: Option<String>,
+    meta: tokio::sync::RwLock<crate::index::IndexMeta>,
+    /// Number of recent queries cached (0 = caching disabled)
+    cache_size: usize,
+    /// Embeddings of recent query texts, if caching is enabled
+    query_embeddings: Option<std::sync::Mutex<crate::index::LruCache<Vec<f32>>>>,
+    llm: Option<crate::llm::LlmProvider>,
+    template: crate::llm::PromptTemplate,
+    /// Token budget for /ask prompts, if the model's context window is known
+    prompt_budget: Option<usize>,
+    /// Whether POST /build is enabled
+    allow_build: bool,
+    /// Builds and updates started over the API, by index name, for POST /build/cancel
+    running: std::sync::Mutex<Vec<(String, crate::cancel::Cancellation)>>,
+    /// Whether search and ask requests must name a namespace
+    require_namespace: bool,
+    /// Index directory to log queries for, if query logging is enabled
+    query_log: Option<std::path::PathBuf>,
+    /// Defaults for requests that leave search options unset, from config.toml
+    /// (the index's own defaults take precedence)
+    search_defaults: crate::config::SearchConfig,
+}
+
+/// Reload the index if `leann update`, a rebuild, or `leann feedback` changed
+/// it since it was loaded
+///
+/// The reloaded searcher starts with an empty result cache, so results from
+/// before the change are never served. An index rebuilt with another
+/// embedding model needs a restart, as queries are embedded with the old one.
+#[cfg(feature = "server")]
+async fn refresh_index(state: &AppState) -> anyhow::Result<()> {
+    use crate::index::{IndexMeta, IndexSearcher, MetadataFilter};
+
+    let meta_path = state.index_dir.join("documents.leann.meta.json");
+    let version = crate::index::index_version(&state.index_dir);
+    if *state.index_version.lock().unwrap_or_else(|e| e.into_inner()) == version {
+        return Ok(());
+    }
+
+    let meta = IndexMeta::load(&meta_path)?;
+    let current = state.meta.read().await;
+    if meta.embedding_model != current.embedding_model || meta.dimensions != current.dimensions {
+        tracing::warn!(
+            "Index '{}' was rebuilt with embedding model {}; restart the server to serve it",
+            state.index_name,
+            meta.embedding_model
+        );
+        *state.index_version.lock().unwrap_or_else(|e| e.into_inner()) = version;
+        return Ok(());
+    }
+    drop(current);
+
+    let scope = state.scope.as_deref().map(MetadataFilter::try_parse).transpose()?;
+    let index_path = state.index_dir.join("documents.leann");
+    let searcher = IndexSearcher::load_scoped(&index_path, &meta, scope)?.with_cache(state.cache_size);
+    *state.searcher.write().await = searcher;
+    *state.meta.write().await = meta;
+    *state.index_version.lock().unwrap_or_else(|e| e.into_inner()) = version;
+    tracing::info!("Reloaded updated index '{}'", state.index_name);
+    Ok(())
+}
+
+/// Embed a query, reusing the embedding of an identical earlier query when cached
+#[cfg(feature = "server")]
+async fn embed_query(state: &AppState, query: &str) -> anyhow::Result<Vec<f32>> {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    use crate::embedding::EmbeddingTask;
+
+    let mut hasher = DefaultHasher::new();
+    query.hash(&mut hasher);
+    let key = hasher.finish();
+    if let Some(cache) = &state.query_embeddings {
+        if let Some(embedding) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(key) {
+            return Ok(embedding);
+        }
+    }
+
+    let query_template = state.meta.read().await.embedding_template(EmbeddingTask::Query);
+    let embedding = state
+        .embedding_provider
+        .read()
+        .await
+        .embed_for_task(&[query], EmbeddingTask::Query, &query_template)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No embedding returned for the query"))?;
+
+    if let Some(cache) = &state.query_embeddings {
+        cache.lock().unwrap_or_else(|e| e.into_inner()).insert(key, embedding.clone());
+    }
+    Ok(embedding)
+}
+
+/// Log a query if query logging is enabled, returning its id
+#[cfg(feature = "server")]
+fn log_served_query(
+    state: &AppState,
+    query: &str,
+    filter: Option<&str>,
+    latency_ms: u64,
+    result_ids: Vec<String>,
+) -> Option<String> {
+    let index_dir = state.query_log.as_ref()?;
+    let entry = crate::index::QueryLogEntry::new("serve", query, filter, latency_ms, result_ids);
+    match crate::index::log_query(index_dir, &entry) {
+        Ok(()) => Some(entry.id),
+        Err(e) => {
+            tracing::warn!("Failed to log query: {}", e);
+            None
+        }
+    }
 }
 
 #[cfg(feature = "server")]
 async fn root() -> &'static str {
-    "LEANN API Server\n\nEndpoints:\n  POST /search - Search the index\n  GET  /info   - Get index information\n  GET  /health - Health check\n"
+    "LEANN API Server\n\nEndpoints:\n  POST /search - Search the index\n  POST /ask    - Answer a question (requires --llm)\n  POST /build  - Build an index, streaming progress (requires --allow-build)\n  POST /feedback - Report used results of a logged query\n  GET  /info   - Get index information\n  GET  /health - Health check\n"
 }
 
 #[cfg(feature = "server")]
@@ -130,16 +404,39 @@ async fn health() -> &'static str {
     "ok"
 }
 
+/// Run a request in a span (joining the caller's trace with `--otel-endpoint`)
+#[cfg(feature = "server")]
+async fn trace_request(request: axum::extract::Request, next: axum::middleware::Next) -> axum::response::Response {
+    use tracing::Instrument;
+
+    let span = tracing::info_span!(
+        "http.request",
+        method = %request.method(),
+        path = request.uri().path(),
+        status = tracing::field::Empty,
+    );
+    #[cfg(feature = "otel")]
+    crate::telemetry::set_parent(&span, request.headers());
+    let response = next.run(request).instrument(span.clone()).await;
+    span.record("status", response.status().as_u16());
+    response
+}
+
 #[cfg(feature = "server")]
 async fn info_handler(
     axum::extract::State(state): axum::extract::State<std::sync::Arc<AppState>>,
 ) -> axum::response::Json<serde_json::Value> {
+    let scoped_passage_count = state.searcher.read().await.scoped_len();
+    let meta = state.meta.read().await;
+
     axum::response::Json(serde_json::json!({
         "index_name": state.index_name,
-        "passage_count": state.meta.passage_count,
-        "dimensions": state.meta.dimensions,
-        "embedding_model": state.meta.embedding_model,
-        "backend": state.meta.backend_name,
+        "passage_count": meta.passage_count,
+        "scope": state.scope,
+        "scoped_passage_count": scoped_passage_count,
+        "dimensions": meta.dimensions,
+        "embedding_model": meta.embedding_model,
+        "backend": meta.backend_name,
     }))
 }
 
@@ -155,6 +452,12 @@ struct IndexInfo {
 
 #[cfg(feature = "server")]
 async fn list_indexes() -> axum::response::Json<Vec<IndexInfo>> {
+    axum::response::Json(local_indexes())
+}
+
+/// Indexes under `.leann/indexes` in the server's working directory
+#[cfg(feature = "server")]
+fn local_indexes() -> Vec<IndexInfo> {
     use std::path::PathBuf;
 
     let mut indexes = Vec::new();
@@ -164,8 +467,8 @@ async fn list_indexes() -> axum::response::Json<Vec<IndexInfo>> {
     if local_path.exists() {
         if let Ok(entries) = std::fs::read_dir(&local_path) {
             for entry in entries.flatten() {
-                if entry.path().is_dir() {
-                    let name = entry.file_name().to_string_lossy().to_string();
+                let name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() && !crate::index::is_build_artifact(&name) {
                     let meta_path = entry.path().join("documents.leann.meta.json");
 
                     let (status, passage_count, backend) = if meta_path.exists() {
@@ -199,7 +502,7 @@ async fn list_indexes() -> axum::response::Json<Vec<IndexInfo>> {
         }
     }
 
-    axum::response::Json(indexes)
+    indexes
 }
 
 #[cfg(feature = "server")]
@@ -223,28 +526,53 @@ fn dir_size(path: &std::path::PathBuf) -> std::io::Result<u64> {
 #[derive(serde::Deserialize)]
 struct SearchRequest {
     query: String,
-    #[serde(default = "default_top_k")]
-    top_k: usize,
+    /// Number of results (default: the server's `[search] top_k`)
+    #[serde(default)]
+    top_k: Option<usize>,
     #[serde(default)]
     filter: Option<String>,
+    /// Only search passages of this namespace
+    #[serde(default)]
+    namespace: Option<String>,
+    /// Hybrid search (default: the index's default, else off)
+    #[serde(default)]
+    hybrid: Option<bool>,
+    /// Weight for vector scores in hybrid mode (default: the index's default, then
+    /// the server's `[search] hybrid_alpha`)
+    #[serde(default)]
+    hybrid_alpha: Option<f32>,
+    /// MMR lambda; enables diversification when set
+    #[serde(default)]
+    mmr_lambda: Option<f32>,
+    /// Metadata field to collapse results by
+    #[serde(default)]
+    group_by: Option<String>,
+    /// Drop results scoring below this
     #[serde(default)]
-    hybrid: bool,
-    #[serde(default = "default_alpha")]
-    hybrid_alpha: f32,
+    min_score: Option<f32>,
+    /// Score multipliers as `filter:weight` rules, e.g. "source^src/:1.5"
+    #[serde(default)]
+    boost: Option<String>,
+    /// Number of results to skip
+    #[serde(default)]
+    offset: usize,
+    /// Results per page (overrides top_k)
+    #[serde(default)]
+    page_size: Option<usize>,
 }
 
-#[cfg(feature = "server")]
-fn default_top_k() -> usize { 5 }
-
-#[cfg(feature = "server")]
-fn default_alpha() -> f32 { 0.7 }
-
 #[cfg(feature = "server")]
 #[derive(serde::Serialize)]
 struct SearchResponse {
     results: Vec<SearchResultJson>,
     query: String,
     took_ms: u64,
+    offset: usize,
+    /// Offset of the next page, if this page was full
+    next_offset: Option<usize>,
+    /// Id to report used results to POST /feedback with, if queries are logged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_id: Option<String>,
 }
 
 #[cfg(feature = "server")]
@@ -256,42 +584,109 @@ struct SearchResultJson {
     metadata: serde_json::Value,
 }
 
+/// An HTTP status and message for a failed request
+#[cfg(feature = "server")]
+type ApiError = (axum::http::StatusCode, String);
+
+/// A request's filter, restricted to its namespace
+///
+/// Fails if the filter is invalid, or if the server requires a namespace and
+/// the request names none.
+#[cfg(feature = "server")]
+fn request_filter(
+    state: &AppState,
+    filter: Option<&str>,
+    namespace: Option<&str>,
+) -> Result<Option<crate::index::MetadataFilter>, ApiError> {
+    use crate::index::MetadataFilter;
+
+    let filter = filter
+        .map(MetadataFilter::try_parse)
+        .transpose()
+        .map_err(|e| (axum::http::StatusCode::BAD_REQUEST, e.to_string()))?;
+    match namespace {
+        Some(namespace) => Ok(Some(MetadataFilter::in_namespace(namespace, filter))),
+        None if state.require_namespace => Err((
+            axum::http::StatusCode::BAD_REQUEST,
+            "This server requires a namespace for every request".to_string(),
+        )),
+        None => Ok(filter),
+    }
+}
+
 #[cfg(feature = "server")]
 async fn search(
     axum::extract::State(state): axum::extract::State<std::sync::Arc<AppState>>,
     axum::Json(req): axum::Json<SearchRequest>,
-) -> Result<axum::response::Json<SearchResponse>, (axum::http::StatusCode, String)> {
-    use crate::index::{MetadataFilter, SearchOptions};
+) -> Result<axum::response::Json<SearchResponse>, ApiError> {
+    run_search(&state, req).await.map(axum::response::Json)
+}
+
+/// Search the served index (shared by POST /search and the gRPC API)
+#[cfg(feature = "server")]
+async fn run_search(state: &AppState, req: SearchRequest) -> Result<SearchResponse, ApiError> {
+    use crate::index::SearchOptions;
 
     let start = std::time::Instant::now();
 
+    refresh_index(state)
+        .await
+        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
     // Compute embedding
-    let embedding_provider = state.embedding_provider.read().await;
-    let query_embedding = embedding_provider
-        .embed(&[&req.query])
+    let query_embedding = embed_query(state, &req.query)
         .await
         .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    // Build search options
-    let mut opts = SearchOptions::new(req.top_k, 64);
+    // Build search options: the request, then the index's defaults, then config.toml
+    let (defaults, default_hybrid) = {
+        let meta = state.meta.read().await;
+        (meta.search_config(&state.search_defaults), meta.default_hybrid())
+    };
+    let page_size = req.page_size.or(req.top_k).unwrap_or(defaults.top_k);
+    let mut opts = SearchOptions::new(page_size, defaults.complexity).with_offset(req.offset);
 
-    if let Some(filter_str) = &req.filter {
-        if let Some(filter) = MetadataFilter::parse(filter_str) {
-            opts = opts.with_filter(filter);
-        }
+    if let Some(min_score) = req.min_score {
+        opts = opts.with_min_score(min_score);
     }
 
-    if req.hybrid {
-        opts = opts.with_hybrid(req.query.clone(), req.hybrid_alpha);
+    if let Some(boost) = &req.boost {
+        let boost = crate::index::Boost::parse(boost)
+            .map_err(|e| (axum::http::StatusCode::BAD_REQUEST, e.to_string()))?;
+        opts = opts.with_boost(boost);
+    }
+
+    if let Some(filter) = request_filter(state, req.filter.as_deref(), req.namespace.as_deref())? {
+        opts = opts.with_filter(filter);
+    }
+
+    if req.hybrid.or(default_hybrid).unwrap_or(false) {
+        opts = opts.with_hybrid(req.query.clone(), req.hybrid_alpha.unwrap_or(defaults.hybrid_alpha));
+    }
+
+    if let Some(lambda) = req.mmr_lambda {
+        opts = opts.with_mmr(lambda);
+    }
+
+    if let Some(field) = &req.group_by {
+        opts = opts.with_group_by(field.clone());
     }
 
     // Search
     let searcher = state.searcher.read().await;
     let results = searcher
-        .search_with_options(&query_embedding[0], &opts)
+        .search_with_options(&query_embedding, &opts)
         .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let took_ms = start.elapsed().as_millis() as u64;
+    let next_offset = (results.len() == opts.top_k).then_some(opts.offset + results.len());
+    let query_id = log_served_query(
+        state,
+        &req.query,
+        req.filter.as_deref(),
+        took_ms,
+        results.iter().map(|r| r.id.clone()).collect(),
+    );
 
     let response = SearchResponse {
         results: results
@@ -305,9 +700,343 @@ async fn search(
             .collect(),
         query: req.query,
         took_ms,
+        offset: opts.offset,
+        next_offset,
+        query_id,
     };
 
-    Ok(axum::response::Json(response))
+    Ok(response)
+}
+
+#[cfg(feature = "server")]
+#[derive(serde::Deserialize)]
+struct AskRequest {
+    question: String,
+    /// Number of passages to retrieve (default: the server's `[search] top_k`)
+    #[serde(default)]
+    top_k: Option<usize>,
+    #[serde(default)]
+    filter: Option<String>,
+    /// Only retrieve passages of this namespace
+    #[serde(default)]
+    namespace: Option<String>,
+    /// Token budget for retrieved passages (default: fit the model's context window)
+    #[serde(default)]
+    context_budget: Option<usize>,
+    /// Overrides for the server's generation options
+    #[serde(flatten)]
+    options: crate::llm::GenerationOptions,
+}
+
+#[cfg(feature = "server")]
+#[derive(serde::Serialize)]
+struct AskResponse {
+    answer: String,
+    /// Passages given to the LLM, in citation order
+    sources: Vec<SearchResultJson>,
+    /// Passages retrieved before fitting the context budget
+    passages_retrieved: usize,
+    question: String,
+    took_ms: u64,
+    /// Id to report used results to POST /feedback with, if queries are logged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_id: Option<String>,
+}
+
+#[cfg(feature = "server")]
+async fn ask(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<AppState>>,
+    axum::Json(req): axum::Json<AskRequest>,
+) -> Result<axum::response::Json<AskResponse>, ApiError> {
+    use axum::http::StatusCode;
+
+    let start = std::time::Instant::now();
+
+    let prepared = prepare_answer(&state, &req).await?;
+    let answer = match &prepared.prompt {
+        Some(prompt) => {
+            let answer = served_llm(&state)?
+                .generate_with_options(prompt, &req.options)
+                .await
+                .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+            log_cited(&state, prepared.query_id.as_deref(), &answer, &prepared.results);
+            answer
+        }
+        None => NO_PASSAGES_ANSWER.to_string(),
+    };
+
+    Ok(axum::response::Json(AskResponse {
+        answer,
+        sources: prepared
+            .results
+            .into_iter()
+            .map(|r| SearchResultJson {
+                id: r.id,
+                score: r.score,
+                text: r.text,
+                metadata: r.metadata,
+            })
+            .collect(),
+        passages_retrieved: prepared.passages_retrieved,
+        question: req.question,
+        took_ms: start.elapsed().as_millis() as u64,
+        query_id: prepared.query_id,
+    }))
+}
+
+/// Answer given when no passages match a question
+#[cfg(feature = "server")]
+const NO_PASSAGES_ANSWER: &str = "No relevant passages found.";
+
+/// The server's LLM, or an error if it was started without --llm
+#[cfg(feature = "server")]
+fn served_llm(state: &AppState) -> Result<&crate::llm::LlmProvider, ApiError> {
+    state.llm.as_ref().ok_or_else(|| {
+        (
+            axum::http::StatusCode::NOT_IMPLEMENTED,
+            "No LLM configured; start the server with --llm".to_string(),
+        )
+    })
+}
+
+/// Passages retrieved for a question and the prompt built from them
+#[cfg(feature = "server")]
+struct PreparedAnswer {
+    /// Passages that fit the context budget, in citation order
+    results: Vec<crate::index::SearchResult>,
+    /// Passages retrieved before fitting the context budget
+    passages_retrieved: usize,
+    /// Prompt for the LLM, or `None` if no passages were found
+    prompt: Option<String>,
+    query_id: Option<String>,
+}
+
+/// Retrieve passages for a question and build its prompt (shared by POST /ask and the gRPC API)
+#[cfg(feature = "server")]
+async fn prepare_answer(state: &AppState, req: &AskRequest) -> Result<PreparedAnswer, ApiError> {
+    use std::collections::HashMap;
+
+    use axum::http::StatusCode;
+
+    use crate::index::SearchOptions;
+    use crate::llm::count_tokens;
+
+    let start = std::time::Instant::now();
+
+    let llm = served_llm(state)?;
+
+    req.options
+        .validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    refresh_index(state)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let (defaults, default_hybrid) = {
+        let meta = state.meta.read().await;
+        (meta.search_config(&state.search_defaults), meta.default_hybrid())
+    };
+    let mut opts = SearchOptions::new(req.top_k.unwrap_or(defaults.top_k), defaults.complexity);
+    if let Some(filter) = request_filter(state, req.filter.as_deref(), req.namespace.as_deref())? {
+        opts = opts.with_filter(filter);
+    }
+    if default_hybrid == Some(true) {
+        opts = opts.with_hybrid(req.question.clone(), defaults.hybrid_alpha);
+    }
+    let query_embedding = embed_query(state, &req.question)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut results = state
+        .searcher
+        .read()
+        .await
+        .search_with_options(&query_embedding, &opts)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let passages_retrieved = results.len();
+    let query_id = log_served_query(
+        state,
+        &req.question,
+        req.filter.as_deref(),
+        start.elapsed().as_millis() as u64,
+        results.iter().map(|r| r.id.clone()).collect(),
+    );
+
+    let prompt = (!results.is_empty()).then(|| {
+        let mut vars = HashMap::from([("question", req.question.clone())]);
+        let budget = req.context_budget.or_else(|| {
+            let overhead = count_tokens(llm.model_name(), &state.template.render(&vars));
+            state.prompt_budget.map(|b| b.saturating_sub(overhead))
+        });
+        let packed = super::ask::pack_results(&results, budget, llm.model_name());
+        results.truncate(packed.included);
+
+        vars.insert("context", packed.text);
+        state.template.render(&vars)
+    });
+
+    Ok(PreparedAnswer {
+        results,
+        passages_retrieved,
+        prompt,
+        query_id,
+    })
+}
+
+/// Log the passages an answer cites as used, if queries are logged
+#[cfg(feature = "server")]
+fn log_cited(state: &AppState, query_id: Option<&str>, answer: &str, results: &[crate::index::SearchResult]) {
+    let (Some(index_dir), Some(query_id)) = (&state.query_log, query_id) else {
+        return;
+    };
+    let used = super::ask::cited_ids(answer, results);
+    if !used.is_empty() {
+        if let Err(e) = crate::index::log_used(index_dir, query_id, used) {
+            tracing::warn!("Failed to log used passages: {}", e);
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+#[derive(serde::Deserialize)]
+struct FeedbackRequest {
+    /// `query_id` from a /search or /ask response
+    query_id: String,
+    /// Ids of the results that were used
+    used: Vec<String>,
+}
+
+/// Record which results of a logged query were used, for `leann analytics`
+#[cfg(feature = "server")]
+async fn feedback(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<AppState>>,
+    axum::Json(req): axum::Json<FeedbackRequest>,
+) -> Result<axum::http::StatusCode, (axum::http::StatusCode, String)> {
+    use axum::http::StatusCode;
+
+    let index_dir = state.query_log.as_ref().ok_or_else(|| {
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            "Query logging is disabled; set log_queries = true under [analytics] in the config file".to_string(),
+        )
+    })?;
+    crate::index::log_used(index_dir, &req.query_id, req.used)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(feature = "server")]
+#[derive(serde::Deserialize)]
+struct BuildRequest {
+    /// `leann build` arguments, e.g. ["my-docs", "--docs", "./docs", "--force"]
+    args: Vec<String>,
+}
+
+/// Run a build, streaming its progress events as server-sent events
+///
+/// The stream ends after the `finished`, `failed`, or `cancelled` event.
+#[cfg(feature = "server")]
+async fn build(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<AppState>>,
+    axum::Json(req): axum::Json<BuildRequest>,
+) -> Result<
+    axum::response::sse::Sse<impl futures::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>>>,
+    (axum::http::StatusCode, String),
+> {
+    use axum::http::StatusCode;
+    use axum::response::sse::{Event, Sse};
+
+    if !state.allow_build {
+        return Err((StatusCode::FORBIDDEN, "Builds are disabled; start the server with --allow-build".to_string()));
+    }
+
+    let mut args = super::build::parse_args(&req.args).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    args.progress_events = Some(tx);
+    let index = args.index_name.clone().unwrap_or_default();
+    let cancel = state.start_job(&index);
+    args.cancel = Some(cancel.clone());
+
+    // Builds run on their own thread and runtime so requests keep being served
+    std::thread::spawn(move || {
+        match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => {
+                if let Err(e) = runtime.block_on(super::build::run(args, false)) {
+                    tracing::warn!("Build requested over the API failed: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to start build runtime: {}", e),
+        }
+        state.finish_job(&cancel);
+    });
+
+    let events = futures::stream::unfold(rx, |mut rx| async move {
+        let record = rx.recv().await?;
+        let event = Event::default().json_data(&record).unwrap_or_default();
+        Some((Ok(event), rx))
+    });
+    Ok(Sse::new(events))
+}
+
+#[cfg(feature = "server")]
+impl AppState {
+    /// Register a build or update started over the API, returning what cancels it
+    fn start_job(&self, index: &str) -> crate::cancel::Cancellation {
+        let cancel = crate::cancel::Cancellation::new();
+        if let Ok(mut running) = self.running.lock() {
+            running.push((index.to_string(), cancel.clone()));
+        }
+        cancel
+    }
+
+    /// Forget a build or update once it has stopped
+    fn finish_job(&self, cancel: &crate::cancel::Cancellation) {
+        if let Ok(mut running) = self.running.lock() {
+            running.retain(|(_, job)| !job.same(cancel));
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+#[derive(serde::Deserialize)]
+struct CancelRequest {
+    /// Index whose build to stop (default: every running build)
+    #[serde(default)]
+    index: Option<String>,
+}
+
+/// Stop running builds and updates after their current batch
+///
+/// They leave their index unchanged and keep the embeddings computed so far,
+/// so the same build started again resumes from there.
+#[cfg(feature = "server")]
+async fn cancel_build(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<AppState>>,
+    axum::Json(req): axum::Json<CancelRequest>,
+) -> Result<(axum::http::StatusCode, axum::Json<serde_json::Value>), ApiError> {
+    use axum::http::StatusCode;
+
+    if !state.allow_build {
+        return Err((StatusCode::FORBIDDEN, "Builds are disabled; start the server with --allow-build".to_string()));
+    }
+    let running = state.running.lock().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut cancelled = Vec::new();
+    for (index, cancel) in running.iter() {
+        if req.index.as_ref().is_none_or(|name| name == index) {
+            cancel.cancel();
+            cancelled.push(index.clone());
+        }
+    }
+    if cancelled.is_empty() {
+        let message = match &req.index {
+            Some(name) => format!("No build of '{}' is running", name),
+            None => "No build is running".to_string(),
+        };
+        return Err((StatusCode::NOT_FOUND, message));
+    }
+    Ok((StatusCode::ACCEPTED, axum::Json(serde_json::json!({ "cancelled": cancelled }))))
 }
 
 #[cfg(not(feature = "server"))]
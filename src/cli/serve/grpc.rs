@@ -0,0 +1,264 @@
+//! gRPC API - the `leann.v1.Leann` service from `proto/leann.proto`
+//!
+//! Served next to the HTTP API when `leann serve` gets `--grpc-port`. It
+//! shares the HTTP server's loaded index, caches, LLM, and query log.
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::Stream;
+use tonic::{Request, Response, Status};
+
+use super::{AppState, ApiError};
+
+pub mod proto {
+    tonic::include_proto!("leann.v1");
+}
+
+use proto::leann_server::{Leann, LeannServer};
+use proto::{
+    add_documents_response, ask_response, AddDocumentsRequest, AddDocumentsResponse, AskFinished, AskRequest,
+    AskResponse, AskSources, ListIndexesRequest, ListIndexesResponse, SearchRequest, SearchResponse,
+};
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// The gRPC service for a served index
+pub struct LeannService {
+    state: Arc<AppState>,
+}
+
+/// gRPC server for the state shared with the HTTP API
+pub fn server(state: Arc<AppState>) -> LeannServer<LeannService> {
+    LeannServer::new(LeannService { state })
+}
+
+/// The gRPC status for an HTTP API error
+fn status((code, message): ApiError) -> Status {
+    use axum::http::StatusCode;
+
+    match code {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+        StatusCode::FORBIDDEN => Status::permission_denied(message),
+        StatusCode::NOT_IMPLEMENTED => Status::unimplemented(message),
+        StatusCode::BAD_GATEWAY => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
+
+fn result_message(result: crate::index::SearchResult) -> proto::SearchResult {
+    proto::SearchResult {
+        id: result.id,
+        score: result.score,
+        text: result.text,
+        metadata_json: result.metadata.to_string(),
+    }
+}
+
+/// Stream whose items are sent on an unbounded channel
+fn channel_stream<T: Send + 'static>(
+    rx: tokio::sync::mpsc::UnboundedReceiver<Result<T, Status>>,
+) -> ResponseStream<T> {
+    Box::pin(futures::stream::unfold(rx, |mut rx| async move {
+        let item = rx.recv().await?;
+        Some((item, rx))
+    }))
+}
+
+#[tonic::async_trait]
+impl Leann for LeannService {
+    async fn search(&self, request: Request<SearchRequest>) -> Result<Response<SearchResponse>, Status> {
+        let req = request.into_inner();
+        let req = super::SearchRequest {
+            query: req.query,
+            top_k: req.top_k.map(|k| k as usize),
+            filter: req.filter,
+            namespace: req.namespace,
+            hybrid: req.hybrid,
+            hybrid_alpha: req.hybrid_alpha,
+            mmr_lambda: req.mmr_lambda,
+            group_by: req.group_by,
+            min_score: req.min_score,
+            boost: req.boost,
+            offset: req.offset as usize,
+            page_size: None,
+        };
+        let response = super::run_search(&self.state, req).await.map_err(status)?;
+
+        Ok(Response::new(SearchResponse {
+            results: response
+                .results
+                .into_iter()
+                .map(|r| proto::SearchResult {
+                    id: r.id,
+                    score: r.score,
+                    text: r.text,
+                    metadata_json: r.metadata.to_string(),
+                })
+                .collect(),
+            took_ms: response.took_ms,
+            next_offset: response.next_offset.map(|o| o as u32),
+            query_id: response.query_id,
+        }))
+    }
+
+    type AskStream = ResponseStream<AskResponse>;
+
+    async fn ask(&self, request: Request<AskRequest>) -> Result<Response<Self::AskStream>, Status> {
+        let start = std::time::Instant::now();
+        let req = request.into_inner();
+        let req = super::AskRequest {
+            question: req.question,
+            top_k: req.top_k.map(|k| k as usize),
+            filter: req.filter,
+            namespace: req.namespace,
+            context_budget: req.context_budget.map(|b| b as usize),
+            options: crate::llm::GenerationOptions {
+                system: req.system_prompt,
+                temperature: req.temperature,
+                top_p: req.top_p,
+                max_tokens: req.max_tokens,
+                stop: req.stop,
+            },
+        };
+        let prepared = super::prepare_answer(&self.state, &req).await.map_err(status)?;
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let send = |tx: &tokio::sync::mpsc::UnboundedSender<Result<AskResponse, Status>>, event| {
+            let _ = tx.send(Ok(AskResponse { event: Some(event) }));
+        };
+        send(
+            &tx,
+            ask_response::Event::Sources(AskSources {
+                sources: prepared.results.iter().cloned().map(result_message).collect(),
+                passages_retrieved: prepared.passages_retrieved as u32,
+            }),
+        );
+
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            match &prepared.prompt {
+                Some(prompt) => {
+                    let llm = match super::served_llm(&state) {
+                        Ok(llm) => llm,
+                        Err(e) => {
+                            let _ = tx.send(Err(status(e)));
+                            return;
+                        }
+                    };
+                    let chunks = tx.clone();
+                    let generated = llm
+                        .generate_stream_with_options(prompt, &req.options, &mut |chunk| {
+                            if !chunk.is_empty() {
+                                send(&chunks, ask_response::Event::AnswerChunk(chunk.to_string()));
+                            }
+                        })
+                        .await;
+                    match generated {
+                        Ok(answer) => {
+                            super::log_cited(&state, prepared.query_id.as_deref(), &answer, &prepared.results)
+                        }
+                        Err(e) => {
+                            let _ = tx.send(Err(Status::unavailable(e.to_string())));
+                            return;
+                        }
+                    }
+                }
+                None => send(&tx, ask_response::Event::AnswerChunk(super::NO_PASSAGES_ANSWER.to_string())),
+            }
+            send(
+                &tx,
+                ask_response::Event::Finished(AskFinished {
+                    took_ms: start.elapsed().as_millis() as u64,
+                    query_id: prepared.query_id,
+                }),
+            );
+        });
+
+        Ok(Response::new(channel_stream(rx)))
+    }
+
+    async fn list_indexes(
+        &self,
+        _request: Request<ListIndexesRequest>,
+    ) -> Result<Response<ListIndexesResponse>, Status> {
+        let indexes = super::local_indexes()
+            .into_iter()
+            .map(|info| proto::IndexInfo {
+                name: info.name,
+                status: info.status,
+                size_mb: info.size_mb,
+                passage_count: info.passage_count.map(|c| c as u64),
+                backend: info.backend,
+            })
+            .collect();
+        Ok(Response::new(ListIndexesResponse { indexes }))
+    }
+
+    type AddDocumentsStream = ResponseStream<AddDocumentsResponse>;
+
+    async fn add_documents(
+        &self,
+        request: Request<AddDocumentsRequest>,
+    ) -> Result<Response<Self::AddDocumentsStream>, Status> {
+        use add_documents_response::Event;
+
+        if !self.state.allow_build {
+            return Err(Status::permission_denied(
+                "Adding documents is disabled; start the server with --allow-build",
+            ));
+        }
+        let req = request.into_inner();
+        if req.paths.is_empty() {
+            return Err(Status::invalid_argument("No paths to add"));
+        }
+
+        let mut args = vec![self.state.index_name.clone()];
+        for path in &req.paths {
+            args.push("--docs".to_string());
+            args.push(path.clone());
+        }
+        if let Some(namespace) = &req.namespace {
+            args.push("--namespace".to_string());
+            args.push(namespace.clone());
+        }
+        // There is no one to confirm the estimated cost
+        args.push("--yes".to_string());
+        let mut args = crate::cli::update::parse_args(&args).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let cancel = self.state.start_job(&self.state.index_name);
+        args.cancel = Some(cancel.clone());
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let send = move |event| {
+            let _ = tx.send(Ok(AddDocumentsResponse { event: Some(event) }));
+        };
+        send(Event::Message(format!(
+            "Adding {} paths to index '{}'",
+            req.paths.len(),
+            self.state.index_name
+        )));
+
+        // Updates run on their own thread and runtime so requests keep being served
+        let meta_path = self.state.index_dir.join("documents.leann.meta.json");
+        let state = self.state.clone();
+        std::thread::spawn(move || {
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(anyhow::Error::from)
+                .and_then(|runtime| runtime.block_on(crate::cli::update::run(args, false)))
+                .and_then(|()| crate::index::IndexMeta::load(&meta_path));
+            match result {
+                // Searches pick up the updated index on their next request
+                Ok(meta) => send(Event::PassageCount(meta.passage_count as u64)),
+                Err(e) => {
+                    tracing::warn!("Update requested over gRPC failed: {}", e);
+                    send(Event::Error(e.to_string()))
+                }
+            }
+            state.finish_job(&cancel);
+        });
+
+        Ok(Response::new(channel_stream(rx)))
+    }
+}
@@ -0,0 +1,276 @@
+//! Sessions command - list and replay saved ReAct agent sessions
+//!
+//! `leann react --session <name>` saves each question with the agent's
+//! reasoning trace, the observations its searches returned, and the final
+//! answer to `.leann/sessions/<name>.json`. Asking again with the same session
+//! resumes it: earlier questions and answers are given to the agent as the
+//! `{{ history }}` template variable.
+
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+
+use super::ask::format_history;
+
+#[derive(Args)]
+pub struct SessionsArgs {
+    #[command(subcommand)]
+    pub command: Option<SessionsCommands>,
+}
+
+#[derive(Subcommand)]
+pub enum SessionsCommands {
+    /// List saved sessions, most recently used first (the default)
+    List {
+        /// Output format (text, json)
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+
+    /// Replay a session: each question, the reasoning trace, and the answer
+    Show {
+        /// Session name
+        name: String,
+
+        /// Output format (text, json)
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+
+    /// Delete a session
+    Remove {
+        /// Session name
+        name: String,
+    },
+}
+
+impl SessionsArgs {
+    pub fn format(&self) -> &str {
+        match &self.command {
+            Some(SessionsCommands::List { format } | SessionsCommands::Show { format, .. }) => format,
+            _ => "text",
+        }
+    }
+}
+
+/// One LLM response in the agent loop, with what its action observed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct Step {
+    pub response: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observation: Option<String>,
+    /// A reflection the agent was asked for after steps without progress
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reflection: bool,
+}
+
+/// A question asked in a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct Turn {
+    pub question: String,
+    pub steps: Vec<Step>,
+    pub answer: String,
+    pub timestamp: u64,
+}
+
+/// A saved ReAct session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct Session {
+    pub name: String,
+    /// Index the session searches
+    pub index: String,
+    pub created: u64,
+    pub updated: u64,
+    pub turns: Vec<Turn>,
+}
+
+impl Session {
+    pub(super) fn new(name: &str, index: &str) -> Self {
+        let now = crate::time::now_unix();
+        Self { name: name.to_string(), index: index.to_string(), created: now, updated: now, turns: Vec::new() }
+    }
+
+    fn dir() -> PathBuf {
+        PathBuf::from(".leann").join("sessions")
+    }
+
+    fn path(name: &str) -> anyhow::Result<PathBuf> {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            anyhow::bail!("Invalid session name '{}': use letters, digits, '-', '_', and '.'", name);
+        }
+        Ok(Self::dir().join(format!("{}.json", name)))
+    }
+
+    /// Load a saved session, if there is one
+    pub(super) fn load(name: &str) -> anyhow::Result<Option<Self>> {
+        let path = Self::path(name)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let session = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse session {}: {}", path.display(), e))?;
+        Ok(Some(session))
+    }
+
+    pub(super) fn save(&self) -> anyhow::Result<()> {
+        std::fs::create_dir_all(Self::dir())?;
+        std::fs::write(Self::path(&self.name)?, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// All saved sessions, most recently used first
+    fn list() -> anyhow::Result<Vec<Self>> {
+        let Ok(entries) = std::fs::read_dir(Self::dir()) else {
+            return Ok(Vec::new());
+        };
+        let mut sessions = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                let content = std::fs::read_to_string(&path)?;
+                match serde_json::from_str::<Self>(&content) {
+                    Ok(session) => sessions.push(session),
+                    Err(e) => tracing::warn!("Skipping unreadable session {}: {}", path.display(), e),
+                }
+            }
+        }
+        sessions.sort_by(|a, b| b.updated.cmp(&a.updated).then_with(|| a.name.cmp(&b.name)));
+        Ok(sessions)
+    }
+
+    /// Earlier questions and answers, for the `{{ history }}` template variable
+    pub(super) fn history(&self) -> String {
+        let turns: Vec<(String, String)> =
+            self.turns.iter().map(|t| (t.question.clone(), t.answer.clone())).collect();
+        format_history(&turns)
+    }
+
+    pub(super) fn push(&mut self, question: &str, steps: Vec<Step>, answer: &str) {
+        self.updated = crate::time::now_unix();
+        self.turns.push(Turn { question: question.to_string(), steps, answer: answer.to_string(), timestamp: self.updated });
+    }
+}
+
+pub async fn run(args: SessionsArgs) -> anyhow::Result<()> {
+    match args.command.unwrap_or(SessionsCommands::List { format: "text".to_string() }) {
+        SessionsCommands::List { format } => list(&format),
+        SessionsCommands::Show { name, format } => show(&name, &format),
+        SessionsCommands::Remove { name } => {
+            Session::load(&name)?.ok_or_else(|| not_found(&name))?;
+            std::fs::remove_file(Session::path(&name)?)?;
+            println!("Session '{}' removed", name);
+            Ok(())
+        }
+    }
+}
+
+fn not_found(name: &str) -> anyhow::Error {
+    anyhow::anyhow!("Session '{}' not found. List sessions with 'leann sessions'.", name)
+}
+
+fn list(format: &str) -> anyhow::Result<()> {
+    let sessions = Session::list()?;
+    if format == "json" {
+        let summaries: Vec<serde_json::Value> = sessions
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "name": s.name,
+                    "index": s.index,
+                    "created": s.created,
+                    "updated": s.updated,
+                    "turns": s.turns.len(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&summaries)?);
+        return Ok(());
+    }
+
+    if sessions.is_empty() {
+        println!("No sessions. Start one with: leann react <index> <question> --session <name>");
+        return Ok(());
+    }
+    println!("{:<24} {:<20} {:>6}  Last used", "Session", "Index", "Turns");
+    for session in &sessions {
+        println!(
+            "{:<24} {:<20} {:>6}  {}",
+            session.name,
+            session.index,
+            session.turns.len(),
+            crate::time::format_rfc3339(session.updated)
+        );
+    }
+    Ok(())
+}
+
+fn show(name: &str, format: &str) -> anyhow::Result<()> {
+    let session = Session::load(name)?.ok_or_else(|| not_found(name))?;
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&session)?);
+        return Ok(());
+    }
+
+    println!("Session '{}' on index '{}'", session.name, session.index);
+    for (i, turn) in session.turns.iter().enumerate() {
+        println!("\n=== Question {} ({}) ===\n{}", i + 1, crate::time::format_rfc3339(turn.timestamp), turn.question);
+        print_steps(&turn.steps);
+        println!("\n=== Final Answer ===\n{}", turn.answer);
+    }
+    Ok(())
+}
+
+/// Print a reasoning trace, numbering the steps but not the reflections
+pub(super) fn print_steps(steps: &[Step]) {
+    let mut step = 0;
+    for trace in steps {
+        if trace.reflection {
+            println!("\n--- Reflection ---\n{}", trace.response);
+            continue;
+        }
+        step += 1;
+        println!("\n--- Step {} ---", step);
+        println!("LLM: {}", trace.response);
+        if let Some(observation) = &trace.observation {
+            println!("Observation: {}", observation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_names() {
+        assert!(Session::path("release-notes_2.v1").is_ok());
+        assert!(Session::path("../escape").is_err());
+        assert!(Session::path(".hidden").is_err());
+        assert!(Session::path("").is_err());
+    }
+
+    #[test]
+    fn test_history_and_round_trip() {
+        let mut session = Session::new("notes", "docs");
+        assert_eq!(session.history(), "");
+        let steps = vec![
+            Step { response: "Action: search(bread)".to_string(), observation: Some("[1] Sourdough".to_string()), reflection: false },
+            Step { response: "Action: finish(Use a starter)".to_string(), observation: None, reflection: false },
+        ];
+        session.push("How is bread made?", steps, "Use a starter");
+        session.push("How long does it rise?", Vec::new(), "Overnight");
+        assert_eq!(
+            session.history(),
+            "User: How is bread made?\nAssistant: Use a starter\n\nUser: How long does it rise?\nAssistant: Overnight"
+        );
+
+        let json = serde_json::to_string(&session).unwrap();
+        assert!(!json.contains("\"observation\":null"));
+        assert_eq!(serde_json::from_str::<Session>(&json).unwrap(), session);
+    }
+}
@@ -0,0 +1,55 @@
+//! Snapshot and restore commands - keep named copies of an index
+
+use clap::Args;
+
+use crate::index::{create_snapshot, find_index, find_snapshot, restore_snapshot};
+
+#[derive(Args)]
+pub struct SnapshotArgs {
+    /// Index name to snapshot
+    pub index_name: String,
+
+    /// Label appended to the snapshot's timestamp id (e.g. "before-ast-chunking")
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Hard-link files instead of copying them (saves space, but `update` and
+    /// `prune` rewrite files in place, which changes linked snapshots too)
+    #[arg(long)]
+    pub link: bool,
+}
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Index name to restore
+    pub index_name: String,
+
+    /// Snapshot id, unique id prefix, or label (see `leann list --snapshots`)
+    #[arg(long)]
+    pub snapshot: String,
+}
+
+pub async fn run_snapshot(args: SnapshotArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let snapshot = create_snapshot(&index_dir, args.name.as_deref(), args.link)?;
+
+    println!("Snapshot '{}' of index '{}' created", snapshot.id, args.index_name);
+    println!("  Restore with: leann restore {} --snapshot {}", args.index_name, snapshot.id);
+
+    Ok(())
+}
+
+pub async fn run_restore(args: RestoreArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let snapshot = find_snapshot(&index_dir, &args.snapshot)?;
+    let meta = snapshot.meta()?;
+
+    restore_snapshot(&index_dir, &snapshot)?;
+
+    println!("Index '{}' restored from snapshot '{}'", args.index_name, snapshot.id);
+    println!("  Passages: {}", meta.passage_count);
+    println!("  Embedding model: {}", meta.embedding_model);
+    println!("  Run 'leann rollback {}' to return to the replaced build.", args.index_name);
+
+    Ok(())
+}
@@ -0,0 +1,248 @@
+//! Stale command - compare an index's file manifest with the filesystem
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+
+use super::build::{collect_file_paths, load_file_content, WalkOptions};
+use super::update;
+use crate::globs::PathFilter;
+use crate::index::{find_index, FileManifestEntry, IndexMeta};
+
+#[derive(Args)]
+pub struct StaleArgs {
+    /// Index name
+    pub index_name: String,
+
+    /// Run an incremental update for the changes found
+    #[arg(long)]
+    pub fix: bool,
+
+    /// Skip the update's confirmation prompt (with --fix)
+    #[arg(long, short = 'y', requires = "fix")]
+    pub yes: bool,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+}
+
+/// Files on disk compared with the manifest recorded at build time
+#[derive(Debug, Default, PartialEq)]
+struct Freshness {
+    /// On disk and matched by the build settings, but not indexed
+    new: Vec<String>,
+    /// Indexed, but no longer on disk
+    deleted: Vec<String>,
+    /// Indexed, but the content changed since
+    modified: Vec<String>,
+    unchanged: usize,
+}
+
+impl Freshness {
+    fn is_fresh(&self) -> bool {
+        self.new.is_empty() && self.deleted.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Modification time as seconds since the Unix epoch
+fn modified_secs(path: &Path) -> Option<u64> {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// Whether a file's content differs from its manifest entry
+///
+/// Files whose modification time still matches are taken as unchanged without
+/// being read; the rest are hashed, so a touched but unedited file is fresh.
+fn is_modified(entry: &FileManifestEntry) -> bool {
+    let path = Path::new(&entry.path);
+    if entry.modified.is_some() && modified_secs(path) == entry.modified {
+        return false;
+    }
+    load_file_content(path).map(|content| crate::chunker::content_hash(&content)) != Some(entry.hash.clone())
+}
+
+/// Path used to match manifest entries with files on disk, which may be
+/// recorded relative to a different directory
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Classify the files a build would index now against the manifest
+///
+/// Files inside archives are tracked by their archive: an archive counts as
+/// indexed if any of its entries is, and as deleted if it's gone. Archives
+/// aren't checked for modification.
+fn compare(manifest: &[FileManifestEntry], current: &[PathBuf], is_modified: impl Fn(&FileManifestEntry) -> bool) -> Freshness {
+    // Indexed files (or archives) by canonical path, with the path as recorded
+    let mut indexed: HashMap<PathBuf, (&str, Option<&FileManifestEntry>)> = HashMap::new();
+    for entry in manifest {
+        match crate::archive::archive_of(&entry.path) {
+            Some(archive) => {
+                indexed.entry(canonical(Path::new(archive))).or_insert((archive, None));
+            }
+            None => {
+                indexed.insert(canonical(Path::new(&entry.path)), (&entry.path, Some(entry)));
+            }
+        }
+    }
+
+    let mut freshness = Freshness::default();
+    for (path, entry) in indexed.values() {
+        if !Path::new(path).exists() {
+            freshness.deleted.push(path.to_string());
+        } else if entry.is_some_and(&is_modified) {
+            freshness.modified.push(path.to_string());
+        } else {
+            freshness.unchanged += 1;
+        }
+    }
+    let current: HashSet<PathBuf> = current.iter().map(|p| canonical(p)).collect();
+    freshness.new = current
+        .into_iter()
+        .filter(|p| !indexed.contains_key(p))
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+
+    freshness.new.sort();
+    freshness.deleted.sort();
+    freshness.modified.sort();
+    freshness
+}
+
+pub async fn run(args: StaleArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    let Some(provenance) = meta.provenance.as_ref().filter(|p| !p.files.is_empty()) else {
+        anyhow::bail!(
+            "Index '{}' has no file manifest (it predates manifests); rebuild it with 'leann build --force'",
+            args.index_name
+        );
+    };
+    if let Some(source) = &provenance.source {
+        anyhow::bail!(
+            "Index '{}' was built with --source {}, which stale doesn't support yet",
+            args.index_name,
+            source
+        );
+    }
+
+    // Walk the documents with the settings recorded at build time
+    let docs: Vec<PathBuf> = provenance.docs.iter().map(PathBuf::from).collect();
+    let path_filter = PathFilter::new(
+        provenance.include.as_deref().unwrap_or_default(),
+        provenance.exclude.as_deref().unwrap_or_default(),
+    )?;
+    let walk = WalkOptions {
+        file_types: provenance.file_types.as_deref(),
+        exclude_types: provenance.exclude_types.as_deref(),
+        include_hidden: provenance.include_hidden,
+        follow_symlinks: provenance.follow_symlinks,
+        max_files: provenance.max_files,
+        max_total_size_mb: provenance.max_total_size_mb,
+        max_file_size_kb: provenance.max_file_size_kb.unwrap_or(1024),
+        data_max_file_size_kb: provenance.data_max_file_size_kb,
+        path_filter: &path_filter,
+    };
+    let current = collect_file_paths(&docs, &walk)?;
+    let freshness = compare(&provenance.files, &current, is_modified);
+
+    if args.format == "json" {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "index": args.index_name,
+                "fresh": freshness.is_fresh(),
+                "new": freshness.new,
+                "deleted": freshness.deleted,
+                "modified": freshness.modified,
+                "unchanged": freshness.unchanged,
+            }))?
+        );
+    } else {
+        print_freshness(&args.index_name, &freshness);
+    }
+
+    if args.fix && !freshness.is_fresh() {
+        let mut update_args = vec![args.index_name.clone()];
+        if args.yes {
+            update_args.push("--yes".to_string());
+        }
+        update::run(update::parse_args(&update_args)?, false).await?;
+    }
+    Ok(())
+}
+
+fn print_freshness(index_name: &str, freshness: &Freshness) {
+    if freshness.is_fresh() {
+        println!("Index '{}' is up to date ({} files)", index_name, freshness.unchanged);
+        return;
+    }
+
+    println!(
+        "\nIndex '{}': {} new, {} modified, {} deleted, {} unchanged files\n",
+        index_name,
+        freshness.new.len(),
+        freshness.modified.len(),
+        freshness.deleted.len(),
+        freshness.unchanged
+    );
+    for (label, paths) in [("New (not indexed)", &freshness.new), ("Modified since indexing", &freshness.modified), ("Deleted (still indexed)", &freshness.deleted)] {
+        if paths.is_empty() {
+            continue;
+        }
+        println!("{}:", label);
+        for path in paths {
+            println!("  {}", path);
+        }
+        println!();
+    }
+    println!("Run 'leann stale {} --fix' or 'leann update {}' to bring it up to date.", index_name, index_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str) -> FileManifestEntry {
+        FileManifestEntry { path: path.to_string(), size: 0, modified: None, hash: String::new(), chunks: 1 }
+    }
+
+    #[test]
+    fn test_compare_classifies_files() {
+        let dir = std::env::temp_dir().join(format!("leann-stale-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        for name in ["same.md", "edited.md", "added.md", "docs.zip"] {
+            std::fs::write(dir.join(name), "x").unwrap();
+        }
+
+        let manifest = [
+            // Recorded relative to another directory than the docs root
+            entry(&format!("{}/./same.md", dir.display())),
+            entry(&path("edited.md")),
+            entry(&path("gone.md")),
+            entry(&format!("{}!a.md", path("docs.zip"))),
+            entry(&format!("{}!b.md", path("docs.zip"))),
+        ];
+        let current: Vec<PathBuf> = ["same.md", "edited.md", "added.md", "docs.zip"].iter().map(|n| dir.join(n)).collect();
+        let edited = path("edited.md");
+        let freshness = compare(&manifest, &current, |e| e.path == edited);
+
+        assert_eq!(
+            freshness,
+            Freshness {
+                new: vec![path("added.md")],
+                deleted: vec![path("gone.md")],
+                modified: vec![path("edited.md")],
+                unchanged: 2,
+            }
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,575 @@
+//! Summarize command - map-reduce an index's passages into per-file and
+//! corpus summaries with an LLM
+//!
+//! Each file's passages are packed into prompts that fit the model's context,
+//! summarized, and the partial summaries combined until one remains; the file
+//! summaries are reduced the same way into an overview of the corpus. The
+//! summaries are stored back in the index as passages with `type=summary`
+//! metadata, so searches can find them (and `--filter type=summary` lists them).
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use clap::Args;
+use indicatif::{ProgressBar, ProgressStyle};
+use tracing::info;
+
+use super::ask::GenerationArgs;
+use crate::backend::BackendError;
+use crate::config::Config;
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::error::LeannError;
+use crate::index::{
+    find_index, FieldIndex, IndexMeta, MetadataFilter, MultiVectorWriter, Passage, PassageStore, SparseIndex,
+    NAMESPACE_FIELD,
+};
+use crate::llm::{context_window, count_tokens, LlmOverrides, LlmProvider, LlmType, DEFAULT_MAX_TOKENS};
+use crate::usage::{format_cost, record_usage, UsageKind, UsageRecord};
+
+/// Metadata `type` of stored summaries
+pub(super) const SUMMARY_TYPE: &str = "summary";
+
+/// Prompt tokens per LLM call when the model's context window is unknown
+const DEFAULT_PROMPT_BUDGET: usize = 4000;
+
+/// Tokens set aside for the instructions of each prompt
+const INSTRUCTION_TOKENS: usize = 200;
+
+#[derive(Args)]
+pub struct SummarizeArgs {
+    /// Index name to summarize
+    pub index_name: String,
+
+    /// Only summarize passages matching this metadata filter (e.g. "source^src/cli/")
+    #[arg(long)]
+    pub filter: Option<String>,
+
+    /// LLM provider (default: `[llm] provider` in config.toml, else ollama)
+    #[cfg(feature = "local-llm")]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated", "local"])]
+    pub llm: Option<String>,
+
+    /// LLM provider (default: `[llm] provider` in config.toml, else ollama)
+    #[cfg(not(feature = "local-llm"))]
+    #[arg(long, value_parser = ["ollama", "openai", "anthropic", "gemini", "simulated"])]
+    pub llm: Option<String>,
+
+    /// LLM model name (default: `[llm] model` in config.toml, else qwen3:8b)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Tokenizer file for local GGUF models (default: tokenizer.json next to the model)
+    #[cfg(feature = "local-llm")]
+    #[arg(long)]
+    pub tokenizer: Option<String>,
+
+    /// Ollama host
+    #[arg(long, env = "OLLAMA_HOST")]
+    pub host: Option<String>,
+
+    /// OpenAI/Anthropic API key
+    #[arg(long, env = "OPENAI_API_KEY")]
+    pub api_key: Option<String>,
+
+    /// OpenAI API base URL
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub api_base: Option<String>,
+
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+
+    /// Tokens of text sent per LLM call (default: the model's context window
+    /// less the answer, or 4000)
+    #[arg(long)]
+    pub context_budget: Option<usize>,
+
+    /// Only summarize files, without an overview of the corpus
+    #[arg(long)]
+    pub no_corpus: bool,
+
+    /// Print the summaries without storing them in the index
+    #[arg(long)]
+    pub no_store: bool,
+
+    /// API key for embedding service
+    #[arg(long, env = "OPENAI_API_KEY")]
+    pub embedding_api_key: Option<String>,
+
+    /// OpenAI API base URL for embeddings
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub embedding_api_base: Option<String>,
+
+    /// Ollama host for embeddings
+    #[arg(long, env = "OLLAMA_HOST")]
+    pub embedding_host: Option<String>,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+}
+
+/// The summary of one source file
+#[derive(serde::Serialize)]
+struct FileSummary {
+    source: String,
+    passages: usize,
+    summary: String,
+}
+
+/// What a map-reduce pass summarizes, for its prompts
+pub(super) enum Subject<'a> {
+    File(&'a str),
+    Corpus,
+    /// A cluster of related passages (level 1) or of lower-level summaries
+    Cluster(usize),
+}
+
+impl Subject<'_> {
+    /// Instructions for summarizing `parts`; `partial` when they are earlier summaries
+    pub(super) fn prompt(&self, parts: &[&str], partial: bool) -> String {
+        let mut prompt = match (self, partial) {
+            (Subject::File(source), false) => format!(
+                "The following passages are consecutive parts of {}. Summarize what it contains or does: \
+                 its purpose, main parts, and anything a newcomer should know.",
+                source
+            ),
+            (Subject::File(source), true) => format!(
+                "The following are summaries of consecutive parts of {}. Combine them into one summary of the whole file.",
+                source
+            ),
+            (Subject::Corpus, false) => "The following are summaries of the files in a document collection. \
+                 Write an overview of the collection for a newcomer: what it covers, how it is organized, \
+                 and which files matter most."
+                .to_string(),
+            (Subject::Corpus, true) => "The following are overviews of parts of a document collection. \
+                 Combine them into one overview of the whole collection."
+                .to_string(),
+            (Subject::Cluster(1), false) => "The following passages from a document collection are about related \
+                 things. Summarize what they cover together: the main ideas, facts, and names a reader would look for."
+                .to_string(),
+            (Subject::Cluster(_), _) => "The following are summaries of related parts of a document collection. \
+                 Combine them into one summary of what they cover together."
+                .to_string(),
+        };
+        prompt.push_str(" Reply with the summary only, in one or two short paragraphs.\n\n");
+        for part in parts {
+            prompt.push_str("---\n");
+            prompt.push_str(part.trim());
+            prompt.push('\n');
+        }
+        prompt.push_str("---\n");
+        prompt
+    }
+}
+
+/// Split `texts` into runs of consecutive texts within `budget` tokens each,
+/// truncating texts longer than `max_part` tokens
+pub(super) fn pack<'a>(texts: &'a [String], budget: usize, max_part: usize, model: &str) -> Vec<Vec<&'a str>> {
+    let mut batches: Vec<Vec<&str>> = Vec::new();
+    let mut used = 0;
+    for text in texts {
+        let text = crate::chunker::truncate_to_tokens(text, max_part);
+        let tokens = count_tokens(model, text);
+        match batches.last_mut() {
+            Some(batch) if used + tokens <= budget => batch.push(text),
+            _ => {
+                batches.push(vec![text]);
+                used = 0;
+            }
+        }
+        used += tokens;
+    }
+    batches
+}
+
+/// Summarizes texts with an LLM within a per-call token budget
+pub(super) struct Summarizer<'a> {
+    llm: &'a LlmProvider,
+    budget: usize,
+}
+
+impl<'a> Summarizer<'a> {
+    /// Prompts fit `budget` tokens, or by default the model's context window
+    /// less `max_tokens` for the reply
+    pub(super) fn new(llm: &'a LlmProvider, budget: Option<usize>, max_tokens: usize) -> Self {
+        let budget = budget.unwrap_or_else(|| {
+            context_window(llm.provider_name(), llm.model_name())
+                .map(|window| window.saturating_sub(max_tokens))
+                .unwrap_or(DEFAULT_PROMPT_BUDGET)
+        });
+        Self { llm, budget: budget.saturating_sub(INSTRUCTION_TOKENS).max(INSTRUCTION_TOKENS) }
+    }
+
+    /// Summarize `texts` in as many calls as fit the budget, then combine the
+    /// partial summaries until one remains
+    pub(super) async fn map_reduce(&self, texts: Vec<String>, subject: &Subject<'_>) -> anyhow::Result<String> {
+        let mut texts = texts;
+        let mut partial = false;
+        loop {
+            // Partial summaries are capped at a third of the budget, so each
+            // call combines at least two and the passes converge
+            let max_part = if partial { self.budget / 3 } else { self.budget };
+            let mut summaries = Vec::new();
+            for batch in pack(&texts, self.budget, max_part, self.llm.model_name()) {
+                let summary = self.llm.generate(&subject.prompt(&batch, partial)).await?;
+                summaries.push(summary.trim().to_string());
+            }
+            if summaries.len() <= 1 {
+                return Ok(summaries.pop().unwrap_or_default());
+            }
+            texts = summaries;
+            partial = true;
+        }
+    }
+}
+
+/// Position of a passage within its source, for ordering a file's passages
+fn position(passage: &Passage) -> u64 {
+    ["byte_start", "start_line"]
+        .iter()
+        .find_map(|key| passage.metadata.get(*key).and_then(|v| v.as_u64()))
+        .unwrap_or(0)
+}
+
+fn is_summary(metadata: &serde_json::Value) -> bool {
+    metadata.get("type").and_then(|t| t.as_str()) == Some(SUMMARY_TYPE)
+}
+
+/// Passages to summarize, grouped by source in source order (summaries and
+/// image passages are skipped)
+pub(super) fn load_sources(
+    index_path: &Path,
+    filter: Option<&MetadataFilter>,
+) -> anyhow::Result<BTreeMap<String, Vec<Passage>>> {
+    let store = PassageStore::open(index_path)?;
+    let mut sources: BTreeMap<String, Vec<Passage>> = BTreeMap::new();
+    for passage in store.iter()? {
+        let passage = passage?;
+        let metadata = &passage.metadata;
+        if is_summary(metadata)
+            || metadata.get("modality").and_then(|m| m.as_str()) == Some("image")
+            || filter.is_some_and(|f| !f.matches(metadata))
+        {
+            continue;
+        }
+        let Some(source) = metadata.get("source").and_then(|s| s.as_str()).map(String::from) else {
+            continue;
+        };
+        // Text-free indexes re-read the text from the source on lookup
+        let passage = if passage.text.is_empty() { store.get(&passage.id)? } else { passage };
+        if !passage.text.trim().is_empty() {
+            sources.entry(source).or_default().push(passage);
+        }
+    }
+    for passages in sources.values_mut() {
+        passages.sort_by_key(position);
+    }
+    Ok(sources)
+}
+
+pub async fn run(args: SummarizeArgs, _verbose: bool) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let index_path = index_dir.join("documents.leann");
+    let meta_path = index_dir.join("documents.leann.meta.json");
+    let meta = IndexMeta::load(&meta_path)?;
+
+    if !args.no_store {
+        let backend = meta.backend()?;
+        if !backend.info().supports_update {
+            anyhow::bail!(
+                "{}. Use --no-store to print the summaries without storing them.",
+                BackendError::unsupported(backend.info(), "adding summaries")
+            );
+        }
+    }
+
+    let filter = args.filter.as_deref().map(MetadataFilter::try_parse).transpose()?;
+    let sources = load_sources(&index_path, filter.as_ref())?;
+    if sources.is_empty() {
+        anyhow::bail!("No passages in index '{}' to summarize", args.index_name);
+    }
+
+    // Create LLM provider: flags, then config.toml
+    let config = Config::load();
+    let provider = args.llm.clone().unwrap_or_else(|| config.llm.provider.clone());
+    let model = args.model.clone().unwrap_or_else(|| config.llm.model.clone());
+    let llm_overrides = LlmOverrides {
+        host: args.host.clone(),
+        api_key: args.api_key.clone(),
+        api_base: args.api_base.clone(),
+        #[cfg(feature = "local-llm")]
+        tokenizer: args.tokenizer.clone(),
+    };
+    let llm_type = LlmType::from_name(&provider, &llm_overrides, &config.llm)?;
+    let llm = LlmProvider::new(model.clone(), llm_type)?
+        .with_options(args.generation.to_options())?
+        .with_redactor(args.generation.redactor()?);
+
+    let max_tokens = args.generation.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS) as usize;
+    let summarizer = Summarizer::new(&llm, args.context_budget, max_tokens);
+
+    info!("Summarizing {} files of index '{}' with {} {}", sources.len(), args.index_name, provider, model);
+    let progress = ProgressBar::new(sources.len() as u64);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+
+    let mut files = Vec::with_capacity(sources.len());
+    for (source, passages) in &sources {
+        progress.set_message(source.clone());
+        let texts = passages.iter().map(|p| p.text.clone()).collect();
+        let summary = summarizer.map_reduce(texts, &Subject::File(source)).await?;
+        files.push(FileSummary { source: source.clone(), passages: passages.len(), summary });
+        progress.inc(1);
+    }
+
+    let corpus = if args.no_corpus {
+        None
+    } else {
+        progress.set_message("corpus overview");
+        let texts = files.iter().map(|f| format!("{}:\n{}", f.source, f.summary)).collect();
+        Some(summarizer.map_reduce(texts, &Subject::Corpus).await?)
+    };
+    progress.finish_and_clear();
+
+    let generation = llm.usage();
+    let generation_cost = llm.cost(&generation);
+    record_usage(&[UsageRecord::new(
+        "summarize",
+        Some(&args.index_name),
+        UsageKind::Generation,
+        llm.provider_name(),
+        llm.model_name(),
+    )
+    .with_tokens(generation.input_tokens, generation.output_tokens, generation_cost)]);
+
+    let stored = if args.no_store {
+        0
+    } else {
+        let summaries = summary_passages(&files, corpus.as_deref(), &sources, args.filter.as_deref());
+        let summarized: HashSet<&str> = files.iter().map(|f| f.source.as_str()).collect();
+        let replaces = |metadata: &serde_json::Value| match metadata.get("summary_of").and_then(|s| s.as_str()) {
+            Some("file") => metadata.get("source").and_then(|s| s.as_str()).is_some_and(|s| summarized.contains(s)),
+            Some("corpus") => corpus.is_some() && metadata.get("filter").and_then(|f| f.as_str()) == args.filter.as_deref(),
+            _ => false,
+        };
+        store_summaries(&args, &index_path, &meta_path, meta, summaries, replaces).await?
+    };
+
+    if args.format == "json" {
+        let json = serde_json::json!({
+            "index": args.index_name,
+            "files": files,
+            "corpus": corpus,
+            "stored": stored,
+        });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    for file in &files {
+        println!("## {} ({} passages)\n\n{}\n", file.source, file.passages, file.summary);
+    }
+    if let Some(corpus) = &corpus {
+        println!("## Overview\n\n{}\n", corpus);
+    }
+    println!("LLM tokens: ~{} in, ~{} out ({})", generation.input_tokens, generation.output_tokens, format_cost(generation_cost));
+    if !args.no_store {
+        println!(
+            "Stored {} new summaries in index '{}' (search them with --filter type=summary)",
+            stored, args.index_name
+        );
+    }
+    Ok(())
+}
+
+/// Passages holding the summaries, with ids derived from their content so an
+/// unchanged summary keeps its passage
+fn summary_passages(
+    files: &[FileSummary],
+    corpus: Option<&str>,
+    sources: &BTreeMap<String, Vec<Passage>>,
+    filter: Option<&str>,
+) -> Vec<Passage> {
+    let mut passages: Vec<Passage> = files
+        .iter()
+        .map(|file| {
+            let mut metadata = serde_json::json!({
+                "type": SUMMARY_TYPE,
+                "summary_of": "file",
+                "source": file.source,
+                "passages": file.passages,
+            });
+            let namespace = sources[&file.source].first().and_then(|p| p.metadata.get(NAMESPACE_FIELD)).cloned();
+            if let Some(namespace) = namespace {
+                metadata[NAMESPACE_FIELD] = namespace;
+            }
+            Passage {
+                id: crate::chunker::stable_chunk_id(&format!("summary:{}", file.source), 0, file.summary.as_bytes()),
+                text: file.summary.clone(),
+                metadata,
+            }
+        })
+        .collect();
+    if let Some(corpus) = corpus {
+        let mut metadata = serde_json::json!({
+            "type": SUMMARY_TYPE,
+            "summary_of": "corpus",
+            "files": files.len(),
+        });
+        if let Some(filter) = filter {
+            metadata["filter"] = filter.into();
+        }
+        passages.push(Passage {
+            id: crate::chunker::stable_chunk_id("summary:corpus", 0, corpus.as_bytes()),
+            text: corpus.to_string(),
+            metadata,
+        });
+    }
+    passages
+}
+
+/// Replace the stored summaries `replaces` selects with `summaries`: outdated
+/// ones are deleted, new ones embedded and appended; returns how many were added
+async fn store_summaries(
+    args: &SummarizeArgs,
+    index_path: &Path,
+    meta_path: &Path,
+    mut meta: IndexMeta,
+    summaries: Vec<Passage>,
+    replaces: impl Fn(&serde_json::Value) -> bool,
+) -> anyhow::Result<usize> {
+    let store = PassageStore::open(index_path)?;
+    let new_ids: HashSet<&str> = summaries.iter().map(|p| p.id.as_str()).collect();
+    let outdated: Vec<String> = store
+        .iter()?
+        .filter_map(|p| p.ok())
+        .filter(|p| is_summary(&p.metadata) && replaces(&p.metadata) && !new_ids.contains(p.id.as_str()))
+        .map(|p| p.id)
+        .collect();
+    let summaries: Vec<Passage> = summaries.into_iter().filter(|p| !store.contains(&p.id)).collect();
+    drop(store);
+
+    if !outdated.is_empty() {
+        PassageStore::delete(index_path, &outdated)?;
+        super::remove_docs::remove_vectors(index_path, &meta, &outdated)?;
+        info!("Removed {} outdated summaries", outdated.len());
+    }
+    if summaries.is_empty() {
+        return Ok(0);
+    }
+
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        ..Default::default()
+    };
+    let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &Config::load().embedding).await?;
+    if embedding_provider.dimensions() != meta.dimensions {
+        return Err(LeannError::DimensionMismatch {
+            expected: meta.dimensions,
+            actual: embedding_provider.dimensions(),
+            source_name: "provider",
+        }
+        .into());
+    }
+
+    let texts: Vec<&str> = summaries.iter().map(|p| p.text.as_str()).collect();
+    let document_template = meta.embedding_template(EmbeddingTask::Document);
+    let embeddings = embedding_provider
+        .embed_for_task(&texts, EmbeddingTask::Document, &document_template)
+        .await?;
+    let embedding_tokens = embedding_provider.tokens_used();
+    let embedding_cost = embedding_provider.cost(embedding_tokens);
+    record_usage(&[UsageRecord::new(
+        "summarize",
+        Some(&args.index_name),
+        UsageKind::Embedding,
+        embedding_provider.mode_name(),
+        embedding_provider.model_name(),
+    )
+    .with_tokens(embedding_tokens, 0, embedding_cost)]);
+
+    // Keep sparse and per-token stores in sync when they are; stale ones
+    // stay unavailable until a rebuild
+    let mut sparse_index = match &meta.sparse_model {
+        Some(_) => SparseIndex::load(index_path)?.filter(|s| s.len() == meta.passage_count),
+        None => None,
+    };
+    if let (Some(sparse_model), Some(sparse_index)) = (&meta.sparse_model, sparse_index.as_mut()) {
+        for vector in sparse_model.provider(None).embed(&texts).await? {
+            sparse_index.add(&vector);
+        }
+    }
+    let multi_vector_writer = match &meta.multi_vector {
+        Some(_) => MultiVectorWriter::open_for_append(index_path).ok().filter(|w| w.len() == meta.passage_count),
+        None => None,
+    };
+    let token_vectors = match (&meta.multi_vector, &multi_vector_writer) {
+        (Some(multi_vector), Some(_)) => multi_vector.provider(None)?.embed(&texts, EmbeddingTask::Document).await?,
+        _ => Vec::new(),
+    };
+
+    // Summaries keep their text, even in text-free indexes: there's no source to re-read it from
+    let start_id = meta.passage_count;
+    let mut field_index = FieldIndex::load(index_path).filter(|f| f.len() == start_id);
+    let mut passage_writer = PassageStore::open_for_append(index_path)?;
+    for passage in &summaries {
+        passage_writer.add(passage)?;
+        if let Some(field_index) = field_index.as_mut() {
+            field_index.add(&passage.metadata);
+        }
+    }
+    passage_writer.finish()?;
+    if let Some(field_index) = &field_index {
+        field_index.save(index_path)?;
+    }
+    if let Some(sparse_index) = &sparse_index {
+        sparse_index.save(index_path)?;
+    }
+    if let Some(mut writer) = multi_vector_writer {
+        for tokens in &token_vectors {
+            writer.add(tokens)?;
+        }
+        writer.finish()?;
+    }
+
+    let ids_path = index_path.with_extension("ids.txt");
+    let mut ids: Vec<String> = if ids_path.exists() {
+        std::fs::read_to_string(&ids_path)?.lines().map(String::from).collect()
+    } else {
+        Vec::new()
+    };
+    ids.extend(summaries.iter().map(|p| p.id.clone()));
+    std::fs::write(&ids_path, ids.join("\n"))?;
+
+    meta.backend()?.add_to_index(&embeddings, index_path, meta.dimensions, start_id)?;
+    meta.passage_count += summaries.len();
+    meta.save(meta_path)?;
+
+    Ok(summaries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_and_prompts() {
+        let texts: Vec<String> = vec!["a".repeat(400), "b".repeat(400), "c".repeat(400), "d".repeat(2000)];
+        // ~100 tokens each, and the last capped at 150
+        let batches = pack(&texts, 250, 150, "gpt-4o-mini");
+        let sizes: Vec<Vec<usize>> = batches.iter().map(|b| b.iter().map(|t| t.len()).collect()).collect();
+        assert_eq!(sizes, [vec![400, 400], vec![400, 600]]);
+        assert_eq!(pack(&texts, 150, 150, "gpt-4o-mini").len(), 4);
+
+        let prompt = Subject::File("src/main.rs").prompt(&["fn main() {}"], false);
+        assert!(prompt.starts_with("The following passages are consecutive parts of src/main.rs."));
+        assert!(prompt.ends_with("---\nfn main() {}\n---\n"));
+        assert!(Subject::Corpus.prompt(&[], true).contains("overviews of parts"));
+    }
+}
@@ -0,0 +1,260 @@
+//! Topics command - cluster stored embeddings into a topic map of the corpus
+
+use std::collections::{HashMap, HashSet};
+
+use clap::Args;
+use tracing::info;
+
+use super::highlight;
+use crate::backend::{assign, kmeans, XorShift};
+use crate::config::Config;
+use crate::index::{find_index, Bm25Analyzer, IndexMeta, IndexSearcher, Passage};
+use crate::llm::{LlmOverrides, LlmProvider, LlmType};
+
+#[derive(Args)]
+pub struct TopicsArgs {
+    /// Index name
+    pub index_name: String,
+
+    /// Number of topics (default: about sqrt(passages / 2), at most 20)
+    #[arg(long, short = 'k')]
+    pub topics: Option<usize>,
+
+    /// Label terms shown per topic
+    #[arg(long, default_value = "5")]
+    pub terms: usize,
+
+    /// Representative passages shown per topic
+    #[arg(long, default_value = "3")]
+    pub examples: usize,
+
+    /// Also name each topic with this LLM provider (ollama, openai, anthropic, gemini)
+    #[arg(long)]
+    pub llm: Option<String>,
+
+    /// LLM model name for --llm (default: `[llm] model` in config.toml, else qwen3:8b)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Seed for choosing the initial cluster centers
+    #[arg(long, default_value = "42")]
+    pub seed: u64,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+}
+
+/// A cluster of passages
+struct Topic {
+    /// Label terms, most distinctive first
+    terms: Vec<String>,
+    /// LLM-written name, if requested
+    name: Option<String>,
+    size: usize,
+    /// Passages nearest the cluster center
+    examples: Vec<Passage>,
+}
+
+/// Default number of topics for a corpus of `passages`
+fn default_topic_count(passages: usize) -> usize {
+    ((passages as f64 / 2.0).sqrt().round() as usize).clamp(1, 20)
+}
+
+/// The most distinctive terms of each cluster: frequency in the cluster
+/// weighted by BM25 inverse document frequency across the corpus
+fn label_terms(
+    texts: &[&str],
+    assignments: &[u32],
+    clusters: usize,
+    analyzer: &Bm25Analyzer,
+    count: usize,
+) -> Vec<Vec<String>> {
+    let mut doc_freq: HashMap<String, usize> = HashMap::new();
+    let mut cluster_freq: Vec<HashMap<String, usize>> = vec![HashMap::new(); clusters];
+    for (text, &cluster) in texts.iter().zip(assignments) {
+        let tokens = analyzer.analyze(text);
+        for token in tokens.iter().filter(|t| t.chars().count() > 2) {
+            *cluster_freq[cluster as usize].entry(token.clone()).or_default() += 1;
+        }
+        let unique: HashSet<String> = tokens.into_iter().collect();
+        for token in unique {
+            *doc_freq.entry(token).or_default() += 1;
+        }
+    }
+
+    let n = texts.len() as f32;
+    cluster_freq
+        .into_iter()
+        .map(|freq| {
+            let mut scored: Vec<(String, f32)> = freq
+                .into_iter()
+                .map(|(term, tf)| {
+                    let df = doc_freq[&term] as f32;
+                    let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                    (term, tf as f32 * idf)
+                })
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            scored.into_iter().take(count).map(|(term, _)| term).collect()
+        })
+        .collect()
+}
+
+/// Squared L2 distance
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// A short topic name from an LLM, given its terms and examples
+async fn name_topic(llm: &LlmProvider, terms: &[String], examples: &[Passage]) -> anyhow::Result<String> {
+    let mut prompt = format!(
+        "These passages form one topic of a document collection. Its most distinctive terms are: {}.\n\n",
+        terms.join(", ")
+    );
+    for passage in examples {
+        let text: String = passage.text.chars().take(500).collect();
+        prompt.push_str(&format!("---\n{}\n", text));
+    }
+    prompt.push_str("---\n\nName the topic in 2 to 5 words. Reply with the name only.");
+    let name = llm.generate(&prompt).await?;
+    Ok(name.trim().trim_matches('"').lines().next().unwrap_or_default().to_string())
+}
+
+pub async fn run(args: TopicsArgs) -> anyhow::Result<()> {
+    let index_dir = find_index(&args.index_name)?;
+    let index_path = index_dir.join("documents.leann");
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    if meta.is_pruned {
+        anyhow::bail!(
+            "Index '{}' is pruned; topics are found from stored embeddings. Rebuild it without pruning.",
+            args.index_name
+        );
+    }
+
+    let searcher = IndexSearcher::load(&index_path, &meta)?;
+    let (passages, vectors): (Vec<Passage>, Vec<Vec<f32>>) =
+        searcher.passage_vectors().collect::<anyhow::Result<Vec<_>>>()?.into_iter().unzip();
+    if passages.is_empty() {
+        anyhow::bail!("Index '{}' has no passages", args.index_name);
+    }
+
+    let dim = meta.dimensions;
+    let k = args.topics.unwrap_or_else(|| default_topic_count(passages.len())).clamp(1, passages.len());
+    info!("Clustering {} passages into {} topics", passages.len(), k);
+    let points: Vec<f32> = vectors.iter().flatten().copied().collect();
+    let centroids = kmeans(&points, dim, k, &mut XorShift::new(args.seed));
+    let assignments = assign(&points, dim, &centroids);
+
+    let analyzer = Bm25Analyzer::new(meta.bm25_analyzer.clone().unwrap_or_default().tokenizer).with_stopwords(true);
+    let texts: Vec<&str> = passages.iter().map(|p| p.text.as_str()).collect();
+    let terms = label_terms(&texts, &assignments, k, &analyzer, args.terms);
+
+    let llm = match &args.llm {
+        Some(provider) => {
+            let config = Config::load();
+            let llm_type = LlmType::from_name(provider, &LlmOverrides::default(), &config.llm)?;
+            let model = args.model.clone().unwrap_or_else(|| config.llm.model.clone());
+            Some(LlmProvider::new(model, llm_type)?)
+        }
+        None => None,
+    };
+
+    let mut topics = Vec::with_capacity(k);
+    for (cluster, terms) in terms.into_iter().enumerate() {
+        let centroid = &centroids[cluster * dim..(cluster + 1) * dim];
+        let mut members: Vec<(usize, f32)> = assignments
+            .iter()
+            .enumerate()
+            .filter(|(_, &c)| c as usize == cluster)
+            .map(|(i, _)| (i, distance(&vectors[i], centroid)))
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        members.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let examples: Vec<Passage> = members.iter().take(args.examples).map(|(i, _)| passages[*i].clone()).collect();
+        let name = match &llm {
+            Some(llm) => Some(name_topic(llm, &terms, &examples).await?),
+            None => None,
+        };
+        topics.push(Topic { terms, name, size: members.len(), examples });
+    }
+    topics.sort_by_key(|t| std::cmp::Reverse(t.size));
+
+    print_topics(&args, &topics, passages.len())
+}
+
+fn print_topics(args: &TopicsArgs, topics: &[Topic], total: usize) -> anyhow::Result<()> {
+    if args.format == "json" {
+        let json: Vec<serde_json::Value> = topics
+            .iter()
+            .map(|t| {
+                serde_json::json!({
+                    "name": t.name,
+                    "terms": t.terms,
+                    "size": t.size,
+                    "examples": t.examples.iter().map(|p| serde_json::json!({
+                        "id": p.id,
+                        "text": p.text,
+                        "metadata": p.metadata,
+                    })).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    println!("\n{} topics in index '{}' ({} passages):\n", topics.len(), args.index_name, total);
+    for (i, topic) in topics.iter().enumerate() {
+        let share = topic.size as f64 * 100.0 / total as f64;
+        match &topic.name {
+            Some(name) => println!("{}. {} [{}] - {} passages ({:.1}%)", i + 1, name, topic.terms.join(", "), topic.size, share),
+            None => println!("{}. {} - {} passages ({:.1}%)", i + 1, topic.terms.join(", "), topic.size, share),
+        }
+        for passage in &topic.examples {
+            let location = highlight::location(&passage.metadata).unwrap_or_else(|| passage.id.clone());
+            let snippet = highlight::snippet(&passage.text, &[], 100, false).replace('\n', " ");
+            println!("   - {}  {}", location, snippet);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_topic_count() {
+        assert_eq!(default_topic_count(1), 1);
+        assert_eq!(default_topic_count(200), 10);
+        assert_eq!(default_topic_count(1_000_000), 20);
+    }
+
+    #[test]
+    fn test_label_terms_prefers_distinctive_words() {
+        let texts = [
+            "retry the request with exponential backoff",
+            "backoff and retry on timeout for the request",
+            "render the chart legend with colors",
+            "chart axis colors and legend layout for the request",
+        ];
+        let analyzer = Bm25Analyzer::default().with_stopwords(true);
+        let labels = label_terms(&texts, &[0, 0, 1, 1], 2, &analyzer, 2);
+        assert_eq!(labels[0], ["backoff", "retry"]);
+        assert_eq!(labels[1], ["chart", "colors"]);
+    }
+
+    #[test]
+    fn test_kmeans_separates_clusters() {
+        let points: Vec<f32> = [[1.0, 0.0], [0.9, 0.1], [0.0, 1.0], [0.1, 0.9]].concat();
+        let centroids = kmeans(&points, 2, 2, &mut XorShift::new(7));
+        let assignments = assign(&points, 2, &centroids);
+        assert_eq!(assignments[0], assignments[1]);
+        assert_eq!(assignments[2], assignments[3]);
+        assert_ne!(assignments[0], assignments[2]);
+    }
+}
@@ -0,0 +1,529 @@
+//! TUI command - interactive search browser
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use super::highlight::{find_highlights, location, query_terms, snippet};
+use crate::config::Config;
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::index::{
+    resolve_index, IndexMeta, IndexSearcher, MetadataFilter, Passage, PassageStore, SearchOptions, SearchResult,
+};
+
+/// Pause in typing after which the query is searched
+const SEARCH_DELAY: Duration = Duration::from_millis(300);
+
+#[derive(Args)]
+pub struct TuiArgs {
+    /// Initial query
+    pub query: Option<String>,
+
+    /// Index name or s3://bucket/prefix URL to browse (defaults to current directory name)
+    #[arg(short, long)]
+    pub index: Option<String>,
+
+    /// Number of results to list (default: `[search] top_k` in config.toml, else 5)
+    #[arg(long)]
+    pub top_k: Option<usize>,
+
+    /// Passages from the same file shown before and after the selected one
+    #[arg(long, default_value = "1")]
+    pub context: usize,
+
+    /// API key for embedding service
+    #[arg(long, env = "OPENAI_API_KEY")]
+    pub embedding_api_key: Option<String>,
+
+    /// OpenAI API base URL for embeddings
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    pub embedding_api_base: Option<String>,
+
+    /// Ollama host for embeddings
+    #[arg(long, env = "OLLAMA_HOST")]
+    pub embedding_host: Option<String>,
+}
+
+/// Input box being edited
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    Query,
+    Filter,
+}
+
+/// Passage ids of each source file, in file order
+///
+/// Built on first use, as it reads the whole passage store.
+struct SourceOrder {
+    by_source: HashMap<String, Vec<String>>,
+    /// Source and position within it, by passage id
+    positions: HashMap<String, (String, usize)>,
+}
+
+impl SourceOrder {
+    fn build(passages: &PassageStore) -> anyhow::Result<Self> {
+        let mut keyed: HashMap<String, Vec<(u64, String)>> = HashMap::new();
+        for passage in passages.iter()? {
+            let passage = passage?;
+            let Some(source) = passage.metadata.get("source").and_then(|s| s.as_str()) else {
+                continue;
+            };
+            keyed
+                .entry(source.to_string())
+                .or_default()
+                .push((position_in_file(&passage.metadata), passage.id));
+        }
+
+        let mut by_source = HashMap::with_capacity(keyed.len());
+        let mut positions = HashMap::new();
+        for (source, mut ids) in keyed {
+            ids.sort();
+            let ids: Vec<String> = ids.into_iter().map(|(_, id)| id).collect();
+            for (i, id) in ids.iter().enumerate() {
+                positions.insert(id.clone(), (source.clone(), i));
+            }
+            by_source.insert(source, ids);
+        }
+        Ok(Self { by_source, positions })
+    }
+
+    /// Ids of up to `context` passages before and after `id` in its file
+    fn around(&self, id: &str, context: usize) -> (Vec<&str>, Vec<&str>) {
+        let Some((source, i)) = self.positions.get(id) else {
+            return (Vec::new(), Vec::new());
+        };
+        let ids = &self.by_source[source];
+        let before = ids[i.saturating_sub(context)..*i].iter().map(String::as_str).collect();
+        let after = ids[(i + 1).min(ids.len())..(i + 1 + context).min(ids.len())]
+            .iter()
+            .map(String::as_str)
+            .collect();
+        (before, after)
+    }
+}
+
+/// Sort key of a passage within its source file
+fn position_in_file(metadata: &serde_json::Value) -> u64 {
+    ["byte_start", "start_line", "chunk_index"]
+        .iter()
+        .find_map(|field| metadata.get(*field).and_then(|v| v.as_u64()))
+        .unwrap_or(0)
+}
+
+/// Editor command opening `path` at `line`, from `$VISUAL` or `$EDITOR` (else vi)
+fn editor_command(editor: Option<String>, path: &str, line: Option<u64>) -> Command {
+    let editor = editor.filter(|e| !e.trim().is_empty()).unwrap_or_else(|| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let mut command = Command::new(words.next().unwrap_or("vi"));
+    command.args(words);
+    if let Some(line) = line {
+        command.arg(format!("+{}", line));
+    }
+    command.arg(path);
+    command
+}
+
+struct App<'a> {
+    embedding_provider: &'a EmbeddingProvider,
+    searcher: &'a IndexSearcher,
+    passages: &'a PassageStore,
+    query_template: String,
+    top_k: usize,
+    complexity: usize,
+    hybrid_alpha: f32,
+    context: usize,
+
+    query: String,
+    filter: String,
+    focus: Focus,
+    hybrid: bool,
+    diversify: bool,
+    group_by_source: bool,
+
+    results: Vec<SearchResult>,
+    list: ListState,
+    status: String,
+    /// When the inputs last changed without being searched
+    edited: Option<Instant>,
+    order: Option<SourceOrder>,
+}
+
+impl App<'_> {
+    async fn search(&mut self) {
+        self.edited = None;
+        let query = self.query.trim().to_string();
+        if query.is_empty() {
+            self.results.clear();
+            self.list.select(None);
+            self.status = "Type a query".to_string();
+            return;
+        }
+
+        let start = Instant::now();
+        match self.run_search(&query).await {
+            Ok(results) => {
+                self.status = format!("{} results in {} ms", results.len(), start.elapsed().as_millis());
+                self.list.select((!results.is_empty()).then_some(0));
+                self.results = results;
+            }
+            Err(e) => self.status = format!("Error: {}", e),
+        }
+    }
+
+    async fn run_search(&self, query: &str) -> anyhow::Result<Vec<SearchResult>> {
+        let embedding = self
+            .embedding_provider
+            .embed_for_task(&[query], EmbeddingTask::Query, &self.query_template)
+            .await?;
+
+        let mut opts = SearchOptions::new(self.top_k, self.complexity);
+        if !self.filter.trim().is_empty() {
+            opts = opts.with_filter(MetadataFilter::try_parse(self.filter.trim())?);
+        }
+        if self.hybrid {
+            opts = opts.with_hybrid(query.to_string(), self.hybrid_alpha);
+        }
+        if self.diversify {
+            opts = opts.with_mmr(0.5);
+        }
+        if self.group_by_source {
+            opts = opts.with_group_by("source".to_string());
+        }
+        self.searcher.search_with_options(&embedding[0], &opts)
+    }
+
+    fn selected(&self) -> Option<&SearchResult> {
+        self.list.selected().and_then(|i| self.results.get(i))
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.results.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, self.results.len() as isize - 1);
+        self.list.select(Some(next as usize));
+    }
+
+    fn edit(&mut self, key: KeyEvent) {
+        let field = match self.focus {
+            Focus::Query => &mut self.query,
+            Focus::Filter => &mut self.filter,
+        };
+        match key.code {
+            KeyCode::Char(c) => field.push(c),
+            KeyCode::Backspace => {
+                field.pop();
+            }
+            _ => return,
+        }
+        self.edited = Some(Instant::now());
+    }
+
+    fn toggle(&mut self, option: fn(&mut Self) -> &mut bool) {
+        let value = option(self);
+        *value = !*value;
+        self.edited = Some(Instant::now());
+    }
+
+    /// Passages before and after the selected one in its file
+    fn neighbors(&mut self, id: &str) -> anyhow::Result<(Vec<Passage>, Vec<Passage>)> {
+        if self.context == 0 {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        if self.order.is_none() {
+            self.order = Some(SourceOrder::build(self.passages)?);
+        }
+        let (before, after) = self.order.as_ref().map(|o| o.around(id, self.context)).unwrap_or_default();
+        let fetch = |ids: Vec<&str>| ids.into_iter().map(|id| self.passages.get(id)).collect::<anyhow::Result<Vec<_>>>();
+        Ok((fetch(before)?, fetch(after)?))
+    }
+
+    /// Open the selected result's source file in the editor, suspending the UI
+    fn open_in_editor(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+        let Some(result) = self.selected() else {
+            return Ok(());
+        };
+        let Some(source) = result.metadata.get("source").and_then(|s| s.as_str()) else {
+            self.status = "Selected result has no source file".to_string();
+            return Ok(());
+        };
+        if !Path::new(source).exists() {
+            self.status = format!("{} not found", source);
+            return Ok(());
+        }
+        let line = result.metadata.get("start_line").and_then(|v| v.as_u64());
+        let editor = std::env::var("VISUAL").ok().or_else(|| std::env::var("EDITOR").ok());
+        let mut command = editor_command(editor, source, line);
+
+        ratatui::restore();
+        let status = command.status();
+        *terminal = ratatui::init();
+        terminal.clear()?;
+
+        match status {
+            Ok(s) if !s.success() => self.status = format!("Editor exited with {}", s),
+            Ok(_) => {}
+            Err(e) => self.status = format!("Failed to start editor: {}", e),
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [inputs, body, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [query_area, filter_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(inputs);
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(body);
+
+        let focused = |focus: Focus| {
+            if self.focus == focus {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(self.query.as_str())
+                .block(Block::default().borders(Borders::ALL).title("Query").border_style(focused(Focus::Query))),
+            query_area,
+        );
+        frame.render_widget(
+            Paragraph::new(self.filter.as_str()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Filter (e.g. source:*.rs)")
+                    .border_style(focused(Focus::Filter)),
+            ),
+            filter_area,
+        );
+        let (area, text) = match self.focus {
+            Focus::Query => (query_area, &self.query),
+            Focus::Filter => (filter_area, &self.filter),
+        };
+        frame.set_cursor_position((area.x + 1 + text.chars().count() as u16, area.y + 1));
+
+        let terms = query_terms(&self.query);
+        let items: Vec<ListItem> = self
+            .results
+            .iter()
+            .map(|r| {
+                let place = location(&r.metadata).unwrap_or_else(|| r.id.clone());
+                let preview = snippet(&r.text, &find_highlights(&r.text, &terms), 80, false).replace('\n', " ");
+                ListItem::new(vec![
+                    Line::from(vec![
+                        Span::styled(format!("{:.3} ", r.score), Style::default().fg(Color::Green)),
+                        Span::styled(place, Style::default().fg(Color::Cyan)),
+                    ]),
+                    Line::from(Span::styled(preview, Style::default().fg(Color::Gray))),
+                ])
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!("Results - {}", self.status)))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+
+        let (title, preview) = match self.selected().cloned() {
+            Some(result) => {
+                let title = location(&result.metadata).unwrap_or_else(|| result.id.clone());
+                let text = match self.neighbors(&result.id) {
+                    Ok((before, after)) => preview_text(&result.text, &before, &after, &terms),
+                    Err(e) => Text::from(format!("Failed to read neighboring passages: {}", e)),
+                };
+                (title, text)
+            }
+            None => ("Preview".to_string(), Text::default()),
+        };
+        frame.render_widget(
+            Paragraph::new(preview)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .wrap(Wrap { trim: false }),
+            preview_area,
+        );
+
+        let on = |enabled: bool| if enabled { "on" } else { "off" };
+        frame.render_widget(
+            Paragraph::new(format!(
+                " Tab: query/filter  ↑↓: select  Enter: search/open  ^O: open in $EDITOR  \
+                 ^B: hybrid ({})  ^D: diversify ({})  ^G: group by file ({})  Esc: quit",
+                on(self.hybrid),
+                on(self.diversify),
+                on(self.group_by_source)
+            ))
+            .style(Style::default().fg(Color::DarkGray)),
+            help,
+        );
+    }
+}
+
+/// The selected passage with query terms highlighted, between its dimmed neighbors
+fn preview_text(text: &str, before: &[Passage], after: &[Passage], terms: &[String]) -> Text<'static> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines: Vec<Line> = Vec::new();
+    for passage in before {
+        lines.extend(passage.text.lines().map(|l| Line::styled(l.to_string(), dim)));
+        lines.push(Line::styled("─".repeat(20), dim));
+    }
+    for line in text.lines() {
+        let mut spans = Vec::new();
+        let mut pos = 0;
+        for (start, end) in find_highlights(line, terms) {
+            spans.push(Span::raw(line[pos..start].to_string()));
+            spans.push(Span::styled(
+                line[start..end].to_string(),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ));
+            pos = end;
+        }
+        spans.push(Span::raw(line[pos..].to_string()));
+        lines.push(Line::from(spans));
+    }
+    for passage in after {
+        lines.push(Line::styled("─".repeat(20), dim));
+        lines.extend(passage.text.lines().map(|l| Line::styled(l.to_string(), dim)));
+    }
+    Text::from(lines)
+}
+
+pub async fn run(args: TuiArgs) -> anyhow::Result<()> {
+    let index_name = args.index.clone().unwrap_or_else(|| {
+        std::env::current_dir()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "index".to_string())
+    });
+    let index_dir = resolve_index(&index_name).await?;
+    let index_path = index_dir.join("documents.leann");
+    let meta = IndexMeta::load(&index_dir.join("documents.leann.meta.json"))?;
+    if meta.is_pruned {
+        anyhow::bail!(
+            "Index '{}' is pruned; browse it with `leann search`, which recomputes embeddings",
+            index_name
+        );
+    }
+
+    let config = Config::load();
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        ..Default::default()
+    };
+    let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &config.embedding).await?;
+    let searcher = IndexSearcher::load(&index_path, &meta)?;
+    let passages = PassageStore::open(&index_path)?;
+
+    let search_config = meta.search_config(&config.search);
+    let mut app = App {
+        embedding_provider: &embedding_provider,
+        searcher: &searcher,
+        passages: &passages,
+        query_template: meta.embedding_template(EmbeddingTask::Query),
+        top_k: args.top_k.unwrap_or(search_config.top_k),
+        complexity: search_config.complexity,
+        hybrid_alpha: search_config.hybrid_alpha,
+        context: args.context,
+        query: args.query.clone().unwrap_or_default(),
+        filter: String::new(),
+        focus: Focus::Query,
+        hybrid: meta.default_hybrid().unwrap_or(false),
+        diversify: false,
+        group_by_source: false,
+        results: Vec::new(),
+        list: ListState::default(),
+        status: format!("{} ({} passages)", index_name, meta.passage_count),
+        edited: None,
+        order: None,
+    };
+    if !app.query.is_empty() {
+        app.search().await;
+    }
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut app, &mut terminal).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(app: &mut App<'_>, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if app.edited.is_some_and(|t| t.elapsed() >= SEARCH_DELAY) {
+            app.search().await;
+            continue;
+        }
+        if !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if ctrl => return Ok(()),
+            KeyCode::Char('o') if ctrl => app.open_in_editor(terminal)?,
+            KeyCode::Char('b') if ctrl => app.toggle(|a| &mut a.hybrid),
+            KeyCode::Char('d') if ctrl => app.toggle(|a| &mut a.diversify),
+            KeyCode::Char('g') if ctrl => app.toggle(|a| &mut a.group_by_source),
+            KeyCode::Tab | KeyCode::BackTab => {
+                app.focus = match app.focus {
+                    Focus::Query => Focus::Filter,
+                    Focus::Filter => Focus::Query,
+                }
+            }
+            KeyCode::Up => app.move_selection(-1),
+            KeyCode::Down => app.move_selection(1),
+            KeyCode::PageUp => app.move_selection(-10),
+            KeyCode::PageDown => app.move_selection(10),
+            KeyCode::Enter if app.edited.is_some() => app.search().await,
+            KeyCode::Enter => app.open_in_editor(terminal)?,
+            _ => app.edit(key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_in_file_prefers_byte_offsets() {
+        let meta = serde_json::json!({ "byte_start": 120, "start_line": 7, "chunk_index": 2 });
+        assert_eq!(position_in_file(&meta), 120);
+        assert_eq!(position_in_file(&serde_json::json!({ "chunk_index": 2 })), 2);
+        assert_eq!(position_in_file(&serde_json::json!({})), 0);
+    }
+
+    #[test]
+    fn test_editor_command_jumps_to_line() {
+        let command = editor_command(Some("code --wait".to_string()), "src/main.rs", Some(42));
+        assert_eq!(command.get_program(), "code");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["--wait", "+42", "src/main.rs"]);
+
+        let command = editor_command(None, "notes.md", None);
+        assert_eq!(command.get_program(), "vi");
+        assert_eq!(command.get_args().collect::<Vec<_>>(), ["notes.md"]);
+    }
+}
@@ -1,25 +1,34 @@
 //! Update command - add passages to an existing index
 
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
 use clap::Args;
 use indicatif::{ProgressBar, ProgressStyle};
 use tracing::info;
 
-use crate::backend::{BackendBuilder, BackendType};
-use crate::chunker::{Chunk, ChunkingStrategy};
-use crate::embedding::{EmbeddingMode, EmbeddingProvider};
-use crate::index::{find_index, IndexMeta, PassageStore, Passage};
+use crate::backend::BackendError;
+use crate::cancel::Cancellation;
+use crate::chunker::{
+    Chunk, ChunkLengthLimit, ChunkLengthPolicy, ChunkingStrategy, EnrichmentPipeline, SecretScanner, SecretsPolicy, SmartChunker,
+};
+use crate::config::Config;
+use crate::embedding::{EmbeddingProvider, EmbeddingTask, ProviderOverrides};
+use crate::error::LeannError;
+use crate::globs::PathFilter;
+use crate::index::{find_index, manifest_path, text_free_passage, EmbeddingCheckpoint, FieldIndex, FileManifestEntry, IndexMeta, PassageStore, Passage, MultiVectorWriter, SparseIndex};
+use crate::usage::{format_cost, record_usage, UsageKind, UsageRecord};
 
-use super::build::load_documents;
+use super::build::{checkpoint_header, embed_with_checkpoint, load_documents, load_file_content, LoadOptions};
 
 #[derive(Args)]
 pub struct UpdateArgs {
     /// Index name to update
     pub index_name: String,
 
-    /// Document directories and/or files to add
-    #[arg(long, required = true)]
+    /// Document directories and/or files to add (default: roots recorded at build time)
+    #[arg(long)]
     pub docs: Vec<PathBuf>,
 
     /// API key for embedding service
@@ -34,25 +43,143 @@ pub struct UpdateArgs {
     #[arg(long, env = "OLLAMA_HOST")]
     pub embedding_host: Option<String>,
 
-    /// Document chunk size in tokens
-    #[arg(long, default_value = "256")]
-    pub doc_chunk_size: usize,
+    /// Jina AI API key, for indexes built with --multi-vector or the jina embedding mode
+    #[arg(long, env = "JINA_API_KEY")]
+    pub jina_api_key: Option<String>,
 
-    /// Document chunk overlap in tokens
-    #[arg(long, default_value = "128")]
-    pub doc_chunk_overlap: usize,
+    /// Document chunk size in tokens (default: value used at build time, or 256)
+    #[arg(long)]
+    pub doc_chunk_size: Option<usize>,
 
-    /// File types to include (comma-separated)
+    /// Document chunk overlap in tokens (default: value used at build time, or 128)
+    #[arg(long)]
+    pub doc_chunk_overlap: Option<usize>,
+
+    /// File types to include (comma-separated, default: types used at build time)
     #[arg(long)]
     pub file_types: Option<String>,
 
-    /// Include hidden files
+    /// Only add files matching this glob, relative to the --docs directory
+    /// (repeatable; default: patterns used at build time)
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Skip files matching this glob (repeatable; added to the patterns used at build time)
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Include hidden files (default: setting used at build time)
     #[arg(long)]
     pub include_hidden: bool,
 
-    /// Chunking strategy: simple, ast, or auto (default: auto)
-    #[arg(long, default_value = "auto", value_parser = ["simple", "ast", "auto"])]
-    pub chunking_strategy: String,
+    /// Follow symbolic links (default: setting used at build time)
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Chunking strategy: simple, ast, or auto (default: strategy used at build time, or auto)
+    #[arg(long, value_parser = ["simple", "ast", "auto"])]
+    pub chunking_strategy: Option<String>,
+
+    /// Namespace for the added chunks (default: the one given at build time);
+    /// e.g. `--docs ./alice --namespace alice` adds a tenant's documents
+    #[arg(long)]
+    pub namespace: Option<String>,
+
+    /// Metadata enrichers to apply (comma-separated, default: enrichers used at build time)
+    #[arg(long)]
+    pub enrichers: Option<String>,
+
+    /// Skip the confirmation prompt
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+
+    /// Ask for confirmation when the estimated embedding cost exceeds this many USD
+    #[arg(long, default_value = "0.50")]
+    pub confirm_above: f64,
+
+    /// Stops the update when it runs inside `leann serve` (default: Ctrl-C)
+    #[arg(skip)]
+    pub cancel: Option<Cancellation>,
+}
+
+/// Files found by an update, classified against the build manifest, by
+/// manifest path (see `manifest_path`)
+#[derive(Debug, Default)]
+struct ChangeSet {
+    new: Vec<String>,
+    modified: Vec<String>,
+    unchanged: Vec<String>,
+    deleted: Vec<String>,
+}
+
+impl ChangeSet {
+    /// Whether chunks from this source need to be embedded
+    fn needs_embedding(&self, source: &str) -> bool {
+        !self.unchanged.contains(&manifest_path(Path::new(source)))
+    }
+}
+
+/// Tombstone outdated passages and drop deleted files from the manifest
+fn remove_stale(index_path: &Path, stale_ids: &[String], changes: &ChangeSet, meta: &mut IndexMeta) -> anyhow::Result<usize> {
+    let removed = PassageStore::delete(index_path, stale_ids)?;
+    if let Some(provenance) = meta.provenance.as_mut() {
+        provenance.files.retain(|f| !changes.deleted.contains(&manifest_path(Path::new(&f.path))));
+    }
+    Ok(removed)
+}
+
+/// Compare the sources of freshly loaded chunks with the manifest recorded at build time
+fn detect_changes(chunks: &[Chunk], manifest: &[FileManifestEntry]) -> ChangeSet {
+    let mut changes = ChangeSet::default();
+    let mut seen: HashSet<String> = HashSet::new();
+    // Chunk sources are walked from the docs roots as given, and older
+    // manifests recorded them that way too, so both sides may be relative
+    let entries: HashMap<String, &FileManifestEntry> =
+        manifest.iter().map(|f| (manifest_path(Path::new(&f.path)), f)).collect();
+
+    for chunk in chunks {
+        let Some(source) = chunk.metadata.get("source").and_then(|s| s.as_str()) else {
+            continue;
+        };
+        let path = manifest_path(Path::new(source));
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+
+        match entries.get(&path) {
+            None => changes.new.push(path),
+            Some(entry) => {
+                let current = load_file_content(Path::new(&path))
+                    .map(|content| crate::chunker::content_hash(&content));
+                if current.as_deref() == Some(entry.hash.as_str()) {
+                    changes.unchanged.push(path);
+                } else {
+                    changes.modified.push(path);
+                }
+            }
+        }
+    }
+
+    changes.deleted = entries.into_keys().filter(|path| !Path::new(path).exists()).collect();
+    changes.deleted.sort();
+
+    changes
+}
+
+/// `leann update` arguments parsed on their own, for updates requested over
+/// the API or by `leann stale --fix`
+#[derive(clap::Parser)]
+#[command(name = "leann update", no_binary_name = true)]
+struct UpdateCommand {
+    #[command(flatten)]
+    args: UpdateArgs,
+}
+
+/// Parse `leann update` arguments (e.g. `["my-docs", "--docs", "./new"]`)
+pub fn parse_args(args: &[String]) -> anyhow::Result<UpdateArgs> {
+    use clap::Parser;
+
+    Ok(UpdateCommand::try_parse_from(args)?.args)
 }
 
 pub async fn run(args: UpdateArgs, _verbose: bool) -> anyhow::Result<()> {
@@ -65,16 +192,30 @@ pub async fn run(args: UpdateArgs, _verbose: bool) -> anyhow::Result<()> {
 
     // Load metadata
     let mut meta = IndexMeta::load(&meta_path)?;
+    if let Some(source) = meta.provenance.as_ref().and_then(|p| p.source.as_ref()) {
+        anyhow::bail!(
+            "Index '{}' was built with --source {}, which update doesn't support yet; rebuild it with 'leann build --force'",
+            args.index_name,
+            source
+        );
+    }
+    let manifest = meta.provenance.as_ref().map(|p| p.files.as_slice()).unwrap_or_default();
+    if let Some(archive) = manifest.iter().find_map(|f| crate::archive::archive_of(&f.path)) {
+        anyhow::bail!(
+            "Index '{}' includes archive {}, which update doesn't support yet; rebuild it with 'leann build --force'",
+            args.index_name,
+            archive
+        );
+    }
 
     // Check backend supports updates
-    let backend_type = match meta.backend_name.as_str() {
-        "hnsw" => BackendType::Hnsw,
-        "diskann" => anyhow::bail!(
-            "DiskANN backend does not support incremental updates. \
-            Use 'leann build --force' to rebuild the entire index."
-        ),
-        _ => anyhow::bail!("Unknown backend: {}", meta.backend_name),
-    };
+    let backend = meta.backend()?;
+    if !backend.info().supports_update {
+        anyhow::bail!(
+            "{}. Use 'leann build --force' to rebuild the entire index.",
+            BackendError::unsupported(backend.info(), "incremental updates")
+        );
+    }
 
     info!(
         "Current index: {} passages, {} dimensions",
@@ -82,32 +223,23 @@ pub async fn run(args: UpdateArgs, _verbose: bool) -> anyhow::Result<()> {
     );
 
     // Create embedding provider from metadata
-    let embedding_mode = match meta.embedding_mode.as_str() {
-        "openai" => EmbeddingMode::OpenAI {
-            api_key: args.embedding_api_key.clone(),
-            base_url: args.embedding_api_base.clone(),
-        },
-        "ollama" => EmbeddingMode::Ollama {
-            host: args.embedding_host.clone(),
-        },
-        "gemini" => EmbeddingMode::Gemini {
-            api_key: std::env::var("GOOGLE_API_KEY").ok(),
-        },
-        _ => anyhow::bail!("Unknown embedding mode in index: {}", meta.embedding_mode),
+    let overrides = ProviderOverrides {
+        api_key: args.embedding_api_key.clone(),
+        api_base: args.embedding_api_base.clone(),
+        host: args.embedding_host.clone(),
+        jina_api_key: args.jina_api_key.clone(),
+        ..Default::default()
     };
-
-    let embedding_provider = EmbeddingProvider::new(
-        meta.embedding_model.clone(),
-        embedding_mode,
-    ).await?;
+    let embedding_provider = EmbeddingProvider::for_index(&meta, &overrides, &Config::load().embedding).await?;
 
     // Verify dimensions match
     if embedding_provider.dimensions() != meta.dimensions {
-        anyhow::bail!(
-            "Embedding dimension mismatch: index has {}, provider has {}",
-            meta.dimensions,
-            embedding_provider.dimensions()
-        );
+        return Err(LeannError::DimensionMismatch {
+            expected: meta.dimensions,
+            actual: embedding_provider.dimensions(),
+            source_name: "provider",
+        }
+        .into());
     }
 
     // Load new documents
@@ -119,31 +251,232 @@ pub async fn run(args: UpdateArgs, _verbose: bool) -> anyhow::Result<()> {
     );
     progress.set_message("Loading new documents...");
 
-    let file_types: Option<Vec<String>> = args.file_types.map(|ft| {
-        ft.split(',')
-            .map(|s| s.trim().to_string())
-            .collect()
-    });
+    // Fall back to settings recorded at build time for anything not given on the CLI
+    let provenance = meta.provenance.clone().unwrap_or_default();
+
+    let docs: Vec<PathBuf> = if args.docs.is_empty() {
+        provenance.docs.iter().map(PathBuf::from).collect()
+    } else {
+        args.docs.clone()
+    };
+    if docs.is_empty() {
+        anyhow::bail!(
+            "No document roots recorded for index '{}'. Pass --docs to choose what to add.",
+            args.index_name
+        );
+    }
 
-    let chunking_strategy: ChunkingStrategy = args.chunking_strategy.parse()
+    let file_types: Option<Vec<String>> = args
+        .file_types
+        .map(|ft| {
+            ft.split(',')
+                .map(|s| s.trim().to_string())
+                .collect()
+        })
+        .or(provenance.file_types.clone());
+
+    let chunk_size = args.doc_chunk_size
+        .or(meta.chunking.as_ref().map(|c| c.chunk_size))
+        .unwrap_or(256);
+    let chunk_overlap = args.doc_chunk_overlap
+        .or(meta.chunking.as_ref().map(|c| c.chunk_overlap))
+        .unwrap_or(128);
+    let chunking_strategy: ChunkingStrategy = args.chunking_strategy
+        .or(meta.chunking.as_ref().map(|c| c.strategy.clone()))
+        .and_then(|s| s.parse().ok())
         .unwrap_or(ChunkingStrategy::Auto);
+    let include_hidden = args.include_hidden || provenance.include_hidden;
+    let follow_symlinks = args.follow_symlinks || provenance.follow_symlinks;
 
+    // Glob patterns recorded at build time already include the config file's
+    let include = if args.include.is_empty() {
+        provenance.include.clone().unwrap_or_default()
+    } else {
+        args.include.clone()
+    };
+    let mut exclude = provenance.exclude.clone().unwrap_or_default();
+    exclude.extend(args.exclude.iter().cloned());
+    let path_filter = PathFilter::new(&include, &exclude)?;
+    let enrichers = match &args.enrichers {
+        Some(names) => EnrichmentPipeline::from_names(&names.split(',').collect::<Vec<_>>())?,
+        None => EnrichmentPipeline::from_names(provenance.enrichers.as_deref().unwrap_or_default())?,
+    };
+
+    // Reuse the build's length policy; older indexes split at the model's limit
+    let length_policy: ChunkLengthPolicy = meta.chunking.as_ref()
+        .and_then(|c| c.length_policy.as_deref())
+        .and_then(|p| p.parse().ok())
+        .unwrap_or_default();
+    let length_limit = meta.chunking.as_ref()
+        .and_then(|c| c.max_chunk_tokens)
+        .or_else(|| crate::embedding::max_input_tokens(&meta.embedding_model))
+        .map(|max_tokens| ChunkLengthLimit::new(length_policy, max_tokens));
+
+    // Same for secrets; older indexes redact
+    let secrets_policy: SecretsPolicy = meta.chunking.as_ref()
+        .and_then(|c| c.secrets.as_deref())
+        .and_then(|p| p.parse().ok())
+        .unwrap_or_default();
+
+    let chunker = SmartChunker::new(chunking_strategy, chunk_size, chunk_overlap).with_structured_data(meta.text_stored);
     let chunks = load_documents(
-        &args.docs,
-        args.doc_chunk_size,
-        args.doc_chunk_overlap,
-        file_types.as_deref(),
-        args.include_hidden,
-        chunking_strategy,
+        &docs,
+        &LoadOptions {
+            chunker: &chunker,
+            file_types: file_types.as_deref(),
+            include_hidden,
+            follow_symlinks,
+            enrichers: &enrichers,
+            instructions: &meta.document_instructions(),
+            length_limit: length_limit.as_ref(),
+            secret_scanner: &SecretScanner::new(secrets_policy),
+            path_filter: &path_filter,
+            namespace: args.namespace.as_deref().or(provenance.namespace.as_deref()),
+        },
     )?;
 
-    progress.finish_with_message(format!("Loaded {} new chunks", chunks.len()));
+    progress.finish_with_message(format!("Loaded {} chunks", chunks.len()));
+
+    // Only embed chunks from new or modified files
+    let changes = detect_changes(&chunks, &provenance.files);
+    let loaded_ids: HashSet<String> = chunks.iter().map(|c| c.id.clone()).collect();
+    let mut chunks_per_source: HashMap<String, usize> = HashMap::new();
+    for source in chunks.iter().filter_map(|c| c.metadata.get("source").and_then(|s| s.as_str())) {
+        *chunks_per_source.entry(manifest_path(Path::new(source))).or_insert(0) += 1;
+    }
+    let chunks: Vec<Chunk> = chunks
+        .into_iter()
+        .filter(|c| {
+            c.metadata
+                .get("source")
+                .and_then(|s| s.as_str())
+                .map(|source| changes.needs_embedding(source))
+                .unwrap_or(true)
+        })
+        .collect();
+
+    // Chunks have stable ids, so unchanged chunks of modified files are already indexed
+    let existing = PassageStore::open(&index_path)?;
+    let mut seen_ids: HashSet<String> = HashSet::new();
+    let changed_file_chunks = chunks.len();
+    let chunks: Vec<Chunk> = chunks
+        .into_iter()
+        .filter(|c| !existing.contains(&c.id) && seen_ids.insert(c.id.clone()))
+        .collect();
+    let already_indexed = changed_file_chunks - chunks.len();
+
+    // Passages of deleted files, and old chunks of modified files, are tombstoned
+    let stale_sources: HashSet<&str> = changes.modified.iter().chain(&changes.deleted).map(|s| s.as_str()).collect();
+    let stale_ids: Vec<String> = if stale_sources.is_empty() {
+        Vec::new()
+    } else {
+        // Passage sources are recorded as walked; resolve each one once
+        let mut is_stale: HashMap<String, bool> = HashMap::new();
+        existing
+            .iter()?
+            .filter_map(|p| p.ok())
+            .filter(|p| {
+                let Some(source) = p.metadata.get("source").and_then(|s| s.as_str()) else {
+                    return false;
+                };
+                let stale = *is_stale
+                    .entry(source.to_string())
+                    .or_insert_with(|| stale_sources.contains(manifest_path(Path::new(source)).as_str()));
+                stale && !loaded_ids.contains(&p.id)
+            })
+            .map(|p| p.id)
+            .collect()
+    };
+    drop(existing);
+
+    // Pre-flight summary
+    let estimated_tokens: usize = chunks
+        .iter()
+        .map(|c| crate::embedding::estimate_tokens(&c.text))
+        .sum();
+    let estimated_cost = crate::embedding::embedding_cost(&meta.embedding_mode, &meta.embedding_model, estimated_tokens);
+
+    println!("\nPre-flight summary for '{}':", args.index_name);
+    println!("  New files:        {}", changes.new.len());
+    println!("  Modified files:   {}", changes.modified.len());
+    println!("  Deleted files:    {}", changes.deleted.len());
+    println!("  Unchanged files:  {}", changes.unchanged.len());
+    println!("  Chunks to embed:  {}", chunks.len());
+    if already_indexed > 0 {
+        println!("  Already indexed:  {} chunks", already_indexed);
+    }
+    println!("  Estimated tokens: ~{}", estimated_tokens);
+    match estimated_cost {
+        Some(cost) => println!("  Estimated cost:   ~${:.4} ({})", cost, meta.embedding_model),
+        None => println!("  Estimated cost:   unknown ({})", meta.embedding_model),
+    }
+    if !stale_ids.is_empty() {
+        println!("  Passages to remove: {}", stale_ids.len());
+    }
+    println!();
+
+    if chunks.is_empty() && stale_ids.is_empty() {
+        println!("Index '{}' is up to date", args.index_name);
+        return Ok(());
+    }
+    // Outdated vectors are removed from the graph, and new passages take over
+    // their keys and those freed earlier (by remove-docs or compact). Sparse,
+    // per-token, and recompute embedding stores are aligned with the keys and
+    // can only be appended to, so those indexes append new vectors instead.
+    let ids_path = index_path.with_extension("ids.txt");
+    let mut ids: Vec<String> = if ids_path.exists() {
+        std::fs::read_to_string(&ids_path)?.lines().map(String::from).collect()
+    } else {
+        Vec::new()
+    };
+    let supports_replace = backend.info().supports_replace;
+    let stale_keys: Vec<u64> = if supports_replace {
+        let stale: HashSet<&str> = stale_ids.iter().map(|id| id.as_str()).collect();
+        (0..ids.len()).filter(|&k| stale.contains(ids[k].as_str())).map(|k| k as u64).collect()
+    } else {
+        Vec::new()
+    };
+    let mut free_keys: Vec<u64> = Vec::new();
+    if supports_replace && meta.sparse_model.is_none() && meta.multi_vector.is_none() && !meta.is_recompute {
+        free_keys.extend(&stale_keys);
+        free_keys.extend(backend.free_keys(&index_path)?.into_iter().filter(|&k| (k as usize) < ids.len()));
+        free_keys.sort_unstable();
+        free_keys.dedup();
+    }
 
     if chunks.is_empty() {
-        println!("No new documents found to add");
+        if !stale_keys.is_empty() {
+            backend.remove_from_index(&stale_keys, &index_path, meta.dimensions)?;
+        }
+        let removed = remove_stale(&index_path, &stale_ids, &changes, &mut meta)?;
+        meta.save(&meta_path)?;
+        println!("Index '{}' updated: {} passages removed", args.index_name, removed);
         return Ok(());
     }
 
+    if let Some(cost) = estimated_cost {
+        if cost > args.confirm_above && !args.yes {
+            if !std::io::stdin().is_terminal() {
+                anyhow::bail!(
+                    "Estimated cost ${:.4} exceeds --confirm-above ${:.2}. Re-run with --yes to proceed.",
+                    cost,
+                    args.confirm_above
+                );
+            }
+
+            print!("Estimated cost ${:.4} exceeds ${:.2}. Proceed? [y/N]: ", cost, args.confirm_above);
+            std::io::Write::flush(&mut std::io::stdout())?;
+
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+
+            if !matches!(input.trim().to_lowercase().as_str(), "y" | "yes") {
+                println!("Update cancelled.");
+                return Ok(());
+            }
+        }
+    }
+
     // Compute embeddings for new chunks
     let progress = ProgressBar::new(chunks.len() as u64);
     progress.set_style(
@@ -157,28 +490,83 @@ pub async fn run(args: UpdateArgs, _verbose: bool) -> anyhow::Result<()> {
     let batch_size = 100;
     let mut all_embeddings = Vec::with_capacity(chunks.len());
 
+    // Embed new passages the way the rest of the index was embedded
+    let default_template = meta.embedding_template(EmbeddingTask::Document);
+
+    // Nothing is written to the index until every chunk is embedded; a
+    // cancelled or failed update resumes from the embeddings it checkpointed
+    let cancel = args.cancel.clone().unwrap_or_else(Cancellation::on_ctrl_c);
+    let mut checkpoint = EmbeddingCheckpoint::open(
+        &index_dir,
+        checkpoint_header(&embedding_provider, meta.post_processing.as_ref(), &default_template, None),
+    )?;
+    if checkpoint.len() > 0 {
+        println!("Resuming: {} embeddings reused from an interrupted update", checkpoint.len());
+    }
+
     for batch in chunks.chunks(batch_size) {
-        let texts: Vec<&str> = batch.iter().map(|c| c.text.as_str()).collect();
-        let embeddings = embedding_provider.embed(&texts).await?;
+        cancel.check("Update", &args.index_name)?;
+        let embeddings = embed_with_checkpoint(batch, &embedding_provider, &default_template, &mut checkpoint).await?;
         all_embeddings.extend(embeddings);
         progress.inc(batch.len() as u64);
     }
 
     progress.finish_with_message("Embeddings computed");
 
-    // Open passage store for appending
+    let embedding_tokens = embedding_provider.tokens_used();
+    let embedding_cost = embedding_provider.cost(embedding_tokens);
+    record_usage(&[UsageRecord::new(
+        "update",
+        Some(&args.index_name),
+        UsageKind::Embedding,
+        embedding_provider.mode_name(),
+        embedding_provider.model_name(),
+    )
+    .with_tokens(embedding_tokens, 0, embedding_cost)]);
+
+    // Extend the sparse index if it's in sync; otherwise sparse search is unavailable until a rebuild
+    let mut sparse_index = match &meta.sparse_model {
+        Some(_) => SparseIndex::load(&index_path)?.filter(|s| s.len() == meta.passage_count),
+        None => None,
+    };
+    if let (Some(sparse_model), Some(sparse_index)) = (&meta.sparse_model, sparse_index.as_mut()) {
+        let sparse_provider = sparse_model.provider(None);
+        for batch in chunks.chunks(batch_size) {
+            let texts: Vec<&str> = batch.iter().map(|c| c.text.as_str()).collect();
+            for vector in sparse_provider.embed(&texts).await? {
+                sparse_index.add(&vector);
+            }
+        }
+    } else if meta.sparse_model.is_some() {
+        tracing::warn!("Sparse index is missing or stale; rebuild with 'leann build --force' to use --sparse");
+    }
+
+    // Compute per-token vectors too; they're appended once the passages are written
+    let multi_vector_writer = match &meta.multi_vector {
+        Some(_) => MultiVectorWriter::open_for_append(&index_path)
+            .ok()
+            .filter(|w| w.len() == meta.passage_count),
+        None => None,
+    };
+    let mut new_token_vectors = Vec::new();
+    if let (Some(multi_vector), Some(_)) = (&meta.multi_vector, &multi_vector_writer) {
+        let multi_vector_provider = multi_vector.provider(args.jina_api_key.clone())?;
+        for batch in chunks.chunks(batch_size) {
+            let texts: Vec<&str> = batch.iter().map(|c| c.text.as_str()).collect();
+            new_token_vectors.extend(multi_vector_provider.embed(&texts, EmbeddingTask::Document).await?);
+        }
+    } else if meta.multi_vector.is_some() {
+        tracing::warn!("Multi-vector store is missing or stale; rebuild with 'leann build --force' to use --multi-vector");
+    }
+
+    // Open passage store for appending; new passages keep their stable ids and
+    // get freed vector keys, then keys after the existing ones (see the ids file below)
     let mut passage_writer = PassageStore::open_for_append(&index_path)?;
     let start_id = meta.passage_count;
-
-    // Reassign IDs starting from current count
-    let new_chunks: Vec<Chunk> = chunks
-        .into_iter()
-        .enumerate()
-        .map(|(i, mut c)| {
-            c.id = (start_id + i).to_string();
-            c
-        })
-        .collect();
+    let new_chunks = chunks;
+    let reused_keys = &free_keys[..free_keys.len().min(new_chunks.len())];
+    let reused: HashSet<u64> = reused_keys.iter().copied().collect();
+    let removed_keys: Vec<u64> = stale_keys.iter().copied().filter(|k| !reused.contains(k)).collect();
 
     // Add passages to store
     let progress = ProgressBar::new(new_chunks.len() as u64);
@@ -189,47 +577,79 @@ pub async fn run(args: UpdateArgs, _verbose: bool) -> anyhow::Result<()> {
             .progress_chars("#>-"),
     );
 
-    for chunk in &new_chunks {
-        let passage = Passage {
-            id: chunk.id.clone(),
-            text: chunk.text.clone(),
-            metadata: chunk.metadata.clone(),
+    // Extend the field index if it's in sync; otherwise searches scan metadata instead
+    let mut field_index = FieldIndex::load(&index_path).filter(|f| f.len() == start_id);
+
+    for (i, chunk) in new_chunks.iter().enumerate() {
+        let passage = if meta.text_stored {
+            Passage {
+                id: chunk.id.clone(),
+                text: chunk.text.clone(),
+                metadata: chunk.metadata.clone(),
+            }
+        } else {
+            text_free_passage(&chunk.id, &chunk.text, chunk.metadata.clone())
         };
         passage_writer.add(&passage)?;
+        if let Some(field_index) = field_index.as_mut() {
+            match reused_keys.get(i) {
+                Some(&key) => field_index.replace(key as usize, &passage.metadata),
+                None => field_index.add(&passage.metadata),
+            }
+        }
         progress.inc(1);
     }
 
     passage_writer.finish()?;
+    if let Some(field_index) = &field_index {
+        field_index.save(&index_path)?;
+    }
+    if let Some(sparse_index) = &sparse_index {
+        sparse_index.save(&index_path)?;
+    }
+    if let Some(mut writer) = multi_vector_writer {
+        for tokens in &new_token_vectors {
+            writer.add(tokens)?;
+        }
+        writer.finish()?;
+    }
     progress.finish_with_message("Passages added");
 
     // Update IDs file
-    let ids_path = index_path.with_extension("ids.txt");
-    let mut ids_content = if ids_path.exists() {
-        std::fs::read_to_string(&ids_path)?
-    } else {
-        String::new()
-    };
+    for (i, chunk) in new_chunks.iter().enumerate() {
+        match reused_keys.get(i) {
+            Some(&key) => ids[key as usize] = chunk.id.clone(),
+            None => ids.push(chunk.id.clone()),
+        }
+    }
+    std::fs::write(&ids_path, ids.join("\n"))?;
 
-    for chunk in &new_chunks {
-        if !ids_content.is_empty() {
-            ids_content.push('\n');
+    // Put vectors under reused keys, drop the remaining outdated ones, and add the rest
+    let (replaced_embeddings, appended_embeddings) = all_embeddings.split_at(reused_keys.len());
+    if !reused_keys.is_empty() || !removed_keys.is_empty() {
+        backend.replace_in_index(reused_keys, replaced_embeddings, &removed_keys, &index_path, meta.dimensions)?;
+    }
+    if !appended_embeddings.is_empty() {
+        backend.add_to_index(appended_embeddings, &index_path, meta.dimensions, start_id)?;
+    }
+
+    // Record new and modified files in the build manifest
+    let manifest = &mut meta.provenance.get_or_insert_with(Default::default).files;
+    for source in changes.new.iter().chain(&changes.modified) {
+        let path = Path::new(source);
+        if let Some(content) = load_file_content(path) {
+            let count = chunks_per_source.get(source).copied().unwrap_or_default();
+            manifest.retain(|f| manifest_path(Path::new(&f.path)) != *source);
+            manifest.push(FileManifestEntry::for_file(path, &content, count));
         }
-        ids_content.push_str(&chunk.id);
     }
-    std::fs::write(&ids_path, ids_content)?;
 
-    // Add embeddings to vector index
-    let backend = BackendBuilder::new(backend_type);
-    backend.add_to_index(
-        &all_embeddings,
-        &index_path,
-        meta.dimensions,
-        start_id,
-    )?;
+    let removed = remove_stale(&index_path, &stale_ids, &changes, &mut meta)?;
 
     // Update metadata
-    meta.passage_count += new_chunks.len();
+    meta.passage_count += appended_embeddings.len();
     meta.save(&meta_path)?;
+    checkpoint.remove()?;
 
     println!(
         "Index '{}' updated: {} → {} passages",
@@ -237,6 +657,17 @@ pub async fn run(args: UpdateArgs, _verbose: bool) -> anyhow::Result<()> {
         start_id,
         meta.passage_count
     );
+    if removed > 0 {
+        println!("  Removed {} outdated passages (run 'leann compact {}' to reclaim space)", removed, args.index_name);
+    }
+    if !reused_keys.is_empty() || !removed_keys.is_empty() {
+        println!(
+            "  Reused {} freed vector keys and removed {} outdated vectors",
+            reused_keys.len(),
+            removed_keys.len()
+        );
+    }
+    println!("  Embedding tokens: ~{} ({})", embedding_tokens, format_cost(embedding_cost));
 
     Ok(())
 }
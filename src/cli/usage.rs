@@ -0,0 +1,65 @@
+//! Usage command - cumulative embedding and LLM usage and cost
+
+use clap::Args;
+
+use crate::usage::{ledger_path, read_ledger, UsageSummary, UsageTotals};
+
+#[derive(Args)]
+pub struct UsageArgs {
+    /// Only count usage from the last N days
+    #[arg(long)]
+    pub days: Option<u64>,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    pub format: String,
+}
+
+pub async fn run(args: UsageArgs) -> anyhow::Result<()> {
+    let path = ledger_path().ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?;
+    let records = read_ledger(&path)?;
+    let since = args.days.map(|days| crate::time::now_unix().saturating_sub(days * 24 * 60 * 60));
+    let summary = UsageSummary::from_records(&records, since);
+
+    if args.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    if summary.total.records == 0 {
+        println!("No usage recorded{}.", args.days.map(|d| format!(" in the last {} days", d)).unwrap_or_default());
+        return Ok(());
+    }
+
+    match args.days {
+        Some(days) => println!("Usage in the last {} days", days),
+        None => println!("Usage since {}", records.first().map(|r| crate::time::format_rfc3339(r.timestamp)).unwrap_or_default()),
+    }
+    println!("{}", "=".repeat(50));
+    println!("  Ledger: {}", path.display());
+    print_totals("Total", &summary.total);
+
+    println!("\nBy model");
+    for (model, totals) in &summary.by_model {
+        print_totals(model, totals);
+    }
+
+    println!("\nBy command");
+    for (command, totals) in &summary.by_command {
+        print_totals(command, totals);
+    }
+
+    println!("\nToken counts are estimates; costs use built-in price lists.");
+    Ok(())
+}
+
+fn print_totals(label: &str, totals: &UsageTotals) {
+    let mut cost = format!("${:.4}", totals.cost_usd);
+    if totals.unpriced > 0 {
+        cost.push_str(&format!(" + {} unpriced", totals.unpriced));
+    }
+    println!(
+        "  {:<48} {:>12} in {:>10} out  {}",
+        label, totals.input_tokens, totals.output_tokens, cost
+    );
+}
@@ -11,14 +11,74 @@
 //! # base_url = "http://localhost:1234/v1"  # for lmstudio/openai-compatible
 //! # api_key = "sk-..."  # for openai/gemini
 //!
+//! [llm]
+//! provider = "ollama"  # ollama, openai, anthropic, gemini
+//! model = "qwen3:8b"
+//!
+//! [search]
+//! top_k = 5
+//! complexity = 64
+//! hybrid_alpha = 0.7
+//!
 //! [build]
 //! chunk_size = 256
 //! chunk_overlap = 128
 //! max_file_size_kb = 1024
+//! include = ["src/**/*.rs", "docs/**"]  # glob patterns, relative to --docs
+//! exclude = ["**/generated/**"]
+//!
+//! [prompts]
+//! ask = "~/.config/leann/ask.j2"  # template file for `leann ask`
+//! react = "~/.config/leann/react.j2"  # template file for `leann react`
+//!
+//! [analytics]
+//! log_queries = true  # record queries for `leann analytics`
+//! ask_history = true  # keep interactive `ask` answers in the `_history` index
+//!
+//! [http]
+//! timeout = 300  # seconds per request to embedding and LLM APIs
+//! proxy = "http://proxy.corp:3128"  # default: HTTPS_PROXY / HTTP_PROXY
+//! ca_cert = "~/corp-ca.pem"  # extra CA certificates to trust
+//!
+//! # Selected with --profile (or LEANN_PROFILE); each profile overrides the
+//! # sections above key by key
+//! [profile.work.embedding]
+//! provider = "openai"
+//! model = "text-embedding-3-small"
+//!
+//! [profile.work.llm]
+//! provider = "openai"
+//! model = "gpt-4o-mini"
+//!
+//! [profile.offline]
+//! offline = true
 //! ```
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Profile chosen with `--profile`, applied by every [`Config::load`]
+static PROFILE: Mutex<Option<String>> = Mutex::new(None);
+
+/// The profile selected with `--profile`, if any
+pub fn selected_profile() -> Option<String> {
+    PROFILE.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Select the profile later loads apply, failing if config.toml doesn't define it
+pub fn select_profile(name: Option<String>) -> anyhow::Result<()> {
+    if let Some(name) = &name {
+        let path = Config::config_path();
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            anyhow::anyhow!("Profile '{}' selected, but {} can't be read: {}", name, path.display(), e)
+        })?;
+        Config::parse(&content, Some(name))?;
+    }
+    *PROFILE.lock().unwrap_or_else(|e| e.into_inner()) = name;
+    Ok(())
+}
 
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -26,8 +86,31 @@ pub struct Config {
     #[serde(default)]
     pub embedding: EmbeddingConfig,
 
+    #[serde(default)]
+    pub llm: LlmConfig,
+
+    #[serde(default)]
+    pub search: SearchConfig,
+
     #[serde(default)]
     pub build: BuildConfig,
+
+    #[serde(default)]
+    pub prompts: PromptsConfig,
+
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+
+    #[serde(default)]
+    pub http: HttpConfig,
+
+    /// Refuse network access outside localhost, as with `--offline`
+    #[serde(default)]
+    pub offline: bool,
+
+    /// Named overrides of the sections above, selected with `--profile`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile: BTreeMap<String, toml::Table>,
 }
 
 /// Embedding provider configuration
@@ -53,6 +136,11 @@ pub struct EmbeddingConfig {
     /// Prompt template for document embeddings
     pub prompt_template: Option<String>,
 
+    /// Document prompt templates by file type ("code", "markdown", "python", ...),
+    /// overriding the model's document prefix for chunks of those files
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub instructions: BTreeMap<String, String>,
+
     /// Batch size for embedding requests
     pub batch_size: Option<usize>,
 }
@@ -66,6 +154,7 @@ impl Default for EmbeddingConfig {
             base_url: None,
             api_key: None,
             prompt_template: None,
+            instructions: BTreeMap::new(),
             batch_size: None,
         }
     }
@@ -79,6 +168,85 @@ fn default_model() -> String {
     "nomic-embed-text".to_string()
 }
 
+/// LLM defaults for `ask`, `react`, `serve`, and `search --hyde`/`--multi-query`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmConfig {
+    /// Provider type: ollama, openai, anthropic, gemini
+    #[serde(default = "default_llm_provider")]
+    pub provider: String,
+
+    /// Model name
+    #[serde(default = "default_llm_model")]
+    pub model: String,
+
+    /// Host for Ollama
+    pub host: Option<String>,
+
+    /// Base URL for OpenAI-compatible, Anthropic, or Gemini APIs
+    pub base_url: Option<String>,
+
+    /// API key for OpenAI/Anthropic/Gemini
+    pub api_key: Option<String>,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            provider: default_llm_provider(),
+            model: default_llm_model(),
+            host: None,
+            base_url: None,
+            api_key: None,
+        }
+    }
+}
+
+fn default_llm_provider() -> String {
+    "ollama".to_string()
+}
+
+fn default_llm_model() -> String {
+    "qwen3:8b".to_string()
+}
+
+/// Search defaults for `search`, `ask`, `serve`, and `mcp`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchConfig {
+    /// Number of results to return
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+
+    /// Search complexity (higher = more accurate but slower)
+    #[serde(default = "default_complexity")]
+    pub complexity: usize,
+
+    /// Weight for vector scores in hybrid search (0.0-1.0)
+    #[serde(default = "default_hybrid_alpha")]
+    pub hybrid_alpha: f32,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            top_k: default_top_k(),
+            complexity: default_complexity(),
+            hybrid_alpha: default_hybrid_alpha(),
+        }
+    }
+}
+
+fn default_top_k() -> usize {
+    5
+}
+
+fn default_complexity() -> usize {
+    64
+}
+
+fn default_hybrid_alpha() -> f32 {
+    0.7
+}
+
 /// Build configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuildConfig {
@@ -99,6 +267,12 @@ pub struct BuildConfig {
 
     /// File types to exclude
     pub exclude_types: Option<Vec<String>>,
+
+    /// Glob patterns a file must match to be indexed
+    pub include: Option<Vec<String>>,
+
+    /// Glob patterns of files to skip
+    pub exclude: Option<Vec<String>>,
 }
 
 impl Default for BuildConfig {
@@ -109,6 +283,8 @@ impl Default for BuildConfig {
             max_file_size_kb: default_max_file_size_kb(),
             file_types: None,
             exclude_types: None,
+            include: None,
+            exclude: None,
         }
     }
 }
@@ -125,6 +301,87 @@ fn default_max_file_size_kb() -> usize {
     1024
 }
 
+/// Prompt template files for LLM commands
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PromptsConfig {
+    /// Template for `leann ask` (variables: context, question, history)
+    pub ask: Option<PathBuf>,
+
+    /// Template for `leann react` (variables: question)
+    pub react: Option<PathBuf>,
+}
+
+impl PromptsConfig {
+    /// Template path for `ask`, with a leading `~/` expanded
+    pub fn ask_path(&self) -> Option<PathBuf> {
+        self.ask.as_deref().map(expand_home)
+    }
+
+    /// Template path for `react`, with a leading `~/` expanded
+    pub fn react_path(&self) -> Option<PathBuf> {
+        self.react.as_deref().map(expand_home)
+    }
+}
+
+/// Search analytics configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AnalyticsConfig {
+    /// Log queries, latencies, and result ids next to each index (off by default)
+    #[serde(default)]
+    pub log_queries: bool,
+
+    /// Keep the questions and answers of interactive `ask` sessions in the
+    /// `_history` index (off by default)
+    #[serde(default)]
+    pub ask_history: bool,
+}
+
+/// HTTP client settings for embedding, LLM, and storage APIs
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct HttpConfig {
+    /// Seconds before a request times out (default: 120)
+    pub timeout: Option<u64>,
+
+    /// Seconds to wait for a connection (default: 30)
+    pub connect_timeout: Option<u64>,
+
+    /// Proxy for all requests (default: HTTPS_PROXY, HTTP_PROXY, or ALL_PROXY);
+    /// NO_PROXY still applies
+    pub proxy: Option<String>,
+
+    /// PEM file of CA certificates to trust in addition to the system's
+    pub ca_cert: Option<PathBuf>,
+
+    /// Idle connections kept open per host (default: 10)
+    pub max_idle_per_host: Option<usize>,
+}
+
+impl HttpConfig {
+    /// CA certificate path, with a leading `~/` expanded
+    pub fn ca_cert_path(&self) -> Option<PathBuf> {
+        self.ca_cert.as_deref().map(expand_home)
+    }
+}
+
+/// Overlay `overrides` onto `base`, merging nested tables key by key
+fn merge_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(nested)) => merge_tables(existing, nested),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn expand_home(path: &std::path::Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
 impl Config {
     /// Get the config file path
     pub fn config_path() -> PathBuf {
@@ -137,10 +394,11 @@ impl Config {
     /// Load config from file, returning defaults if not found
     pub fn load() -> Self {
         let path = Self::config_path();
+        let profile = selected_profile();
         if path.exists() {
             match std::fs::read_to_string(&path) {
                 Ok(content) => {
-                    match toml::from_str(&content) {
+                    match Self::parse(&content, profile.as_deref()) {
                         Ok(config) => {
                             tracing::debug!("Loaded config from {:?}", path);
                             return config;
@@ -158,6 +416,32 @@ impl Config {
         Self::default()
     }
 
+    /// Parse a config file, with the overrides of `profile` applied
+    pub fn parse(content: &str, profile: Option<&str>) -> anyhow::Result<Self> {
+        let mut table: toml::Table = toml::from_str(content)?;
+        if let Some(name) = profile {
+            let overrides = table
+                .get("profile")
+                .and_then(|profiles| profiles.get(name))
+                .and_then(|p| p.as_table())
+                .cloned()
+                .ok_or_else(|| {
+                    let available: Vec<&str> = table
+                        .get("profile")
+                        .and_then(|p| p.as_table())
+                        .map(|p| p.keys().map(String::as_str).collect())
+                        .unwrap_or_default();
+                    anyhow::anyhow!(
+                        "Unknown profile: {} (available: {})",
+                        name,
+                        if available.is_empty() { "none".to_string() } else { available.join(", ") }
+                    )
+                })?;
+            merge_tables(&mut table, overrides);
+        }
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+
     /// Save config to file
     pub fn save(&self) -> anyhow::Result<()> {
         let path = Self::config_path();
@@ -201,6 +485,25 @@ model = "nomic-embed-text"
 # Batch size for embedding requests (default: 32 for ollama, 100 for openai)
 # batch_size = 32
 
+[llm]
+# Default provider and model for ask, react, serve --llm, and search --hyde
+# (overridden by --llm and --model)
+# provider = "ollama"
+# model = "qwen3:8b"
+
+# Ollama host, or base URL / API key for OpenAI-compatible, Anthropic, and
+# Gemini APIs (flags and their environment variables take precedence)
+# host = "http://localhost:11434"
+# base_url = "http://localhost:1234/v1"
+# api_key = "sk-..."
+
+[search]
+# Defaults for search, ask, serve, and mcp (overridden by --top-k,
+# --complexity, --hybrid-alpha, and per-request values)
+# top_k = 5
+# complexity = 64
+# hybrid_alpha = 0.7
+
 [build]
 # Chunk size in tokens (default: 256)
 chunk_size = 256
@@ -216,6 +519,40 @@ max_file_size_kb = 1024
 
 # File types to exclude
 # exclude_types = [".min.js", ".lock"]
+
+# Glob patterns, relative to the --docs directory ("*" stays within a
+# directory, "**" crosses directories; patterns without "/" match file names).
+# Added to --include/--exclude; .leannignore files use gitignore syntax instead.
+# include = ["src/**/*.rs", "docs/**"]
+# exclude = ["**/generated/**", "*.min.js"]
+
+[prompts]
+# Prompt template files (Jinja-style: {{ context }}, {{ question }}, {{ history }},
+# {% if history %}...{% endif %}). Overridden by --prompt-template and by
+# per-index templates set with `leann build --ask-template`.
+# ask = "~/.config/leann/ask.j2"
+# react = "~/.config/leann/react.j2"
+
+[analytics]
+# Record queries, latencies, and result ids next to each index for
+# `leann analytics` (default: false)
+# log_queries = true
+# Keep the questions and answers of interactive `ask` sessions in the
+# `_history` index, searchable with `leann search -i _history` (default: false)
+# ask_history = true
+
+# Profiles, selected with --profile <name> or LEANN_PROFILE, override the
+# sections above key by key
+# [profile.work.embedding]
+# provider = "openai"
+# model = "text-embedding-3-small"
+#
+# [profile.work.llm]
+# provider = "openai"
+# model = "gpt-4o-mini"
+#
+# [profile.offline]
+# offline = true  # same as --offline
 "#;
 
         if let Some(parent) = path.parent() {
@@ -236,6 +573,27 @@ mod tests {
         assert_eq!(config.embedding.provider, "ollama");
         assert_eq!(config.embedding.model, "nomic-embed-text");
         assert_eq!(config.build.chunk_size, 256);
+        assert_eq!(config.llm.model, "qwen3:8b");
+        assert_eq!(config.search.top_k, 5);
+    }
+
+    #[test]
+    fn test_parse_llm_and_search() {
+        let toml = r#"
+[llm]
+provider = "anthropic"
+model = "claude-3-5-haiku-latest"
+
+[search]
+top_k = 10
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.llm.provider, "anthropic");
+        assert_eq!(config.llm.model, "claude-3-5-haiku-latest");
+        assert!(config.llm.host.is_none());
+        assert_eq!(config.search.top_k, 10);
+        assert_eq!(config.search.complexity, 64);
+        assert_eq!(config.search.hybrid_alpha, 0.7);
     }
 
     #[test]
@@ -248,10 +606,76 @@ base_url = "http://localhost:1234/v1"
 
 [build]
 chunk_size = 512
+exclude = ["**/generated/**"]
 "#;
         let config: Config = toml::from_str(toml).unwrap();
         assert_eq!(config.embedding.provider, "lmstudio");
         assert_eq!(config.embedding.model, "mxbai-embed-large-v1");
         assert_eq!(config.build.chunk_size, 512);
+        assert_eq!(config.build.exclude, Some(vec!["**/generated/**".to_string()]));
+        assert!(config.build.include.is_none());
+        assert!(config.prompts.ask.is_none());
+        assert!(!config.analytics.log_queries);
+        assert!(!config.analytics.ask_history);
+    }
+
+    #[test]
+    fn test_profile_overrides_sections() {
+        let toml = r#"
+[embedding]
+provider = "ollama"
+host = "http://localhost:11434"
+
+[llm]
+model = "qwen3:8b"
+
+[profile.work.embedding]
+provider = "openai"
+model = "text-embedding-3-small"
+
+[profile.offline]
+offline = true
+"#;
+        let base = Config::parse(toml, None).unwrap();
+        assert_eq!(base.embedding.provider, "ollama");
+        assert!(!base.offline);
+        assert_eq!(base.profile.keys().collect::<Vec<_>>(), ["offline", "work"]);
+
+        let work = Config::parse(toml, Some("work")).unwrap();
+        assert_eq!(work.embedding.provider, "openai");
+        assert_eq!(work.embedding.model, "text-embedding-3-small");
+        assert_eq!(work.embedding.host.as_deref(), Some("http://localhost:11434"));
+        assert_eq!(work.llm.model, "qwen3:8b");
+
+        assert!(Config::parse(toml, Some("offline")).unwrap().offline);
+
+        let err = Config::parse(toml, Some("home")).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown profile: home (available: offline, work)");
+    }
+
+    #[test]
+    fn test_parse_prompts() {
+        let toml = r#"
+[prompts]
+ask = "/etc/leann/ask.j2"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.prompts.ask_path().unwrap(), PathBuf::from("/etc/leann/ask.j2"));
+        assert!(config.prompts.react_path().is_none());
+    }
+
+    #[test]
+    fn test_parse_http() {
+        let toml = r#"
+[http]
+timeout = 300
+proxy = "http://proxy.corp:3128"
+ca_cert = "/etc/ssl/corp-ca.pem"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.http.timeout, Some(300));
+        assert_eq!(config.http.proxy.as_deref(), Some("http://proxy.corp:3128"));
+        assert_eq!(config.http.ca_cert_path().unwrap(), PathBuf::from("/etc/ssl/corp-ca.pem"));
+        assert_eq!(config.http.connect_timeout, None);
     }
 }
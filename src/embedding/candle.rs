@@ -5,7 +5,7 @@ use std::path::PathBuf;
 use candle_core::{DType, Device, Tensor};
 use candle_nn::VarBuilder;
 use candle_transformers::models::bert::{BertModel, Config as BertConfig, DTYPE};
-use hf_hub::{api::sync::Api, Repo, RepoType};
+use hf_hub::{api::sync::Api, Cache, Repo, RepoType};
 use tokenizers::Tokenizer;
 use tracing::info;
 
@@ -40,6 +40,22 @@ impl CandleEmbedding {
                 base.join("tokenizer.json"),
                 base.join("model.safetensors"),
             )
+        } else if crate::http::is_offline() {
+            // Offline: only use files already in the HuggingFace cache
+            let repo = Cache::from_env().repo(Repo::new(model_name.clone(), RepoType::Model));
+            let cached = |file: &str| {
+                repo.get(file).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Local embeddings need {} from the HuggingFace Hub ({}), but offline mode is on and it isn't cached. \
+                        Run once online or pass --embedding-model-path.",
+                        file,
+                        model_name
+                    )
+                })
+            };
+
+            let weights = cached("model.safetensors").or_else(|_| cached("pytorch_model.bin"))?;
+            (cached("config.json")?, cached("tokenizer.json")?, weights)
         } else {
             // Download from HuggingFace Hub
             let api = Api::new()?;
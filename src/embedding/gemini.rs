@@ -6,7 +6,8 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
-use crate::http::create_client;
+use super::EmbeddingTask;
+use crate::http::{check_response, create_client};
 
 /// Gemini embedding provider
 pub struct GeminiEmbedding {
@@ -17,9 +18,11 @@ pub struct GeminiEmbedding {
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 struct EmbedContentRequest {
     model: String,
     content: Content,
+    task_type: &'static str,
 }
 
 #[derive(Serialize)]
@@ -48,9 +51,11 @@ struct BatchEmbedRequest {
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 struct EmbedRequest {
     model: String,
     content: Content,
+    task_type: &'static str,
 }
 
 #[derive(Deserialize)]
@@ -61,6 +66,7 @@ struct BatchEmbedResponse {
 impl GeminiEmbedding {
     /// Create a new Gemini embedding provider
     pub fn new(model_name: String, api_key: Option<String>) -> anyhow::Result<Self> {
+        crate::http::check_network("Gemini embeddings", "https://generativelanguage.googleapis.com")?;
         let api_key = api_key
             .or_else(|| env::var("GOOGLE_API_KEY").ok())
             .or_else(|| env::var("GEMINI_API_KEY").ok())
@@ -93,22 +99,24 @@ impl GeminiEmbedding {
         self.dimensions
     }
 
-    /// Compute embeddings
-    pub async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+    /// Compute embeddings for a task (sent as `taskType`)
+    pub async fn embed(&self, texts: &[&str], task: EmbeddingTask) -> anyhow::Result<Vec<Vec<f32>>> {
         if texts.is_empty() {
             return Ok(Vec::new());
         }
 
+        let task_type = task.gemini_task_type();
+
         // Use batch API for multiple texts
         if texts.len() > 1 {
-            self.batch_embed(texts).await
+            self.batch_embed(texts, task_type).await
         } else {
-            let embedding = self.single_embed(texts[0]).await?;
+            let embedding = self.single_embed(texts[0], task_type).await?;
             Ok(vec![embedding])
         }
     }
 
-    async fn single_embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+    async fn single_embed(&self, text: &str, task_type: &'static str) -> anyhow::Result<Vec<f32>> {
         let url = format!(
             "https://generativelanguage.googleapis.com/v1beta/models/{}:embedContent?key={}",
             self.model_name, self.api_key
@@ -121,6 +129,7 @@ impl GeminiEmbedding {
                     text: text.to_string(),
                 }],
             },
+            task_type,
         };
 
         let response = self
@@ -128,15 +137,16 @@ impl GeminiEmbedding {
             .post(&url)
             .json(&request)
             .send()
+            .await?;
+        let response = check_response(response, "Gemini")
             .await?
-            .error_for_status()?
             .json::<EmbedContentResponse>()
             .await?;
 
         Ok(response.embedding.values)
     }
 
-    async fn batch_embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+    async fn batch_embed(&self, texts: &[&str], task_type: &'static str) -> anyhow::Result<Vec<Vec<f32>>> {
         let url = format!(
             "https://generativelanguage.googleapis.com/v1beta/models/{}:batchEmbedContents?key={}",
             self.model_name, self.api_key
@@ -156,6 +166,7 @@ impl GeminiEmbedding {
                             text: text.to_string(),
                         }],
                     },
+                    task_type,
                 })
                 .collect();
 
@@ -166,8 +177,9 @@ impl GeminiEmbedding {
                 .post(&url)
                 .json(&request)
                 .send()
+                .await?;
+            let response = check_response(response, "Gemini")
                 .await?
-                .error_for_status()?
                 .json::<BatchEmbedResponse>()
                 .await?;
 
@@ -0,0 +1,111 @@
+//! Per-file-type document instructions (`[embedding.instructions]`)
+//!
+//! ```toml
+//! [embedding.instructions]
+//! code = "Represent this code for retrieval: "
+//! markdown = "passage: "
+//! python = "Represent this Python function for retrieval: {text}"
+//! ```
+//!
+//! Keys are file types: the language detected from a file's extension
+//! (`rust`, `python`, `markdown`, `text`, `pdf`, `json`, ...), or `code` for
+//! any source file; a language key wins over `code`. Builds record each
+//! chunk's instruction in its `embedding_instruction` metadata, so passages
+//! are embedded the same way again by `update`, `reembed`, and recompute-mode
+//! searches. Chunks without one use the index's document template.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::chunker::{detect_language, is_code_file, Chunk};
+
+/// Metadata field holding a chunk's document template
+const INSTRUCTION_FIELD: &str = "embedding_instruction";
+
+/// Document templates by file type
+#[derive(Debug, Clone, Default)]
+pub struct DocumentInstructions {
+    by_type: BTreeMap<String, String>,
+}
+
+impl DocumentInstructions {
+    pub fn new(by_type: BTreeMap<String, String>) -> Self {
+        Self { by_type }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_type.is_empty()
+    }
+
+    /// The templates, for sizing chunks
+    pub fn templates(&self) -> impl Iterator<Item = &str> {
+        self.by_type.values().map(String::as_str)
+    }
+
+    /// The templates by file type, for recording in the index metadata
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(&self.by_type).unwrap_or_default()
+    }
+
+    /// Templates recorded in the index metadata by `to_json`
+    pub fn from_json(value: &Value) -> Self {
+        Self::new(serde_json::from_value(value.clone()).unwrap_or_default())
+    }
+
+    /// Document template for the file at `path`, if its type has one
+    pub fn for_file(&self, path: &Path) -> Option<&str> {
+        let language = detect_language(path);
+        language
+            .and_then(|language| self.by_type.get(language))
+            .or_else(|| is_code_file(path).then(|| self.by_type.get("code")).flatten())
+            .map(String::as_str)
+    }
+
+    /// Record the template of the file at `path` in its chunks (chunks that
+    /// already have one keep it)
+    pub fn tag(&self, path: &Path, chunks: &mut [Chunk]) {
+        let Some(template) = self.for_file(path) else {
+            return;
+        };
+        for chunk in chunks {
+            if let Some(fields) = chunk.metadata.as_object_mut() {
+                fields.entry(INSTRUCTION_FIELD).or_insert_with(|| template.into());
+            }
+        }
+    }
+}
+
+/// Document template of a passage: its recorded instruction, else `default`
+pub fn document_template<'a>(metadata: &'a Value, default: &'a str) -> &'a str {
+    metadata.get(INSTRUCTION_FIELD).and_then(Value::as_str).unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instructions() {
+        let instructions = DocumentInstructions::new(BTreeMap::from([
+            ("code".to_string(), "code: ".to_string()),
+            ("python".to_string(), "python: {text}".to_string()),
+            ("markdown".to_string(), "passage: ".to_string()),
+        ]));
+        assert_eq!(instructions.for_file(Path::new("src/main.rs")), Some("code: "));
+        assert_eq!(instructions.for_file(Path::new("app.py")), Some("python: {text}"));
+        assert_eq!(instructions.for_file(Path::new("README.md")), Some("passage: "));
+        assert_eq!(instructions.for_file(Path::new("notes.txt")), None);
+        assert_eq!(instructions.for_file(Path::new("config.toml")), None);
+
+        let chunk = |metadata: Value| Chunk { id: "1".to_string(), text: "fn main() {}".to_string(), metadata };
+        let mut chunks = vec![chunk(serde_json::json!({})), chunk(serde_json::json!({ INSTRUCTION_FIELD: "own: " }))];
+        instructions.tag(Path::new("src/main.rs"), &mut chunks);
+        assert_eq!(document_template(&chunks[0].metadata, "default: "), "code: ");
+        assert_eq!(document_template(&chunks[1].metadata, "default: "), "own: ");
+        assert_eq!(document_template(&serde_json::json!({}), "default: "), "default: ");
+
+        assert_eq!(DocumentInstructions::from_json(&instructions.to_json()).by_type, instructions.by_type);
+    }
+}
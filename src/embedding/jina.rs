@@ -0,0 +1,174 @@
+//! Jina AI embedding provider (text, and images with CLIP models)
+//!
+//! `jina-clip-v1`/`jina-clip-v2` embed texts and images into the same space,
+//! so a text query retrieves images.
+
+use std::env;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::{get_model_config, EmbeddingTask};
+use crate::http::{check_response, create_client};
+
+/// Inputs sent per request
+const BATCH_SIZE: usize = 64;
+
+/// Jina AI embedding provider
+pub struct JinaEmbedding {
+    client: Client,
+    api_base: String,
+    api_key: String,
+    model_name: String,
+    dimensions: usize,
+}
+
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: Vec<Input>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Input {
+    Text(String),
+    #[cfg_attr(not(feature = "images"), allow(dead_code))]
+    Image { image: String },
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    data: Vec<EmbedData>,
+}
+
+#[derive(Deserialize)]
+struct EmbedData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl JinaEmbedding {
+    /// Create a Jina AI embedding provider (`JINA_API_KEY`)
+    pub fn new(model_name: String, api_key: Option<String>) -> anyhow::Result<Self> {
+        crate::http::check_network("Jina embeddings", "https://api.jina.ai/v1")?;
+        let api_key = api_key
+            .or_else(|| env::var("JINA_API_KEY").ok())
+            .ok_or_else(|| anyhow::anyhow!("JINA_API_KEY not set"))?;
+
+        let dimensions = get_model_config(&model_name).dimensions;
+
+        info!("Jina embedding provider: {} ({} dims)", model_name, dimensions);
+
+        Ok(Self {
+            client: create_client(),
+            api_base: "https://api.jina.ai/v1".to_string(),
+            api_key,
+            model_name,
+            dimensions,
+        })
+    }
+
+    /// Get dimensions
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Compute text embeddings for a task (sent as `task` to jina-embeddings-v3)
+    pub async fn embed(&self, texts: &[&str], task: EmbeddingTask) -> anyhow::Result<Vec<Vec<f32>>> {
+        let task = (self.model_name == "jina-embeddings-v3").then_some(match task {
+            EmbeddingTask::Query => "retrieval.query",
+            EmbeddingTask::Document => "retrieval.passage",
+            EmbeddingTask::Clustering => "separation",
+        });
+        let inputs = texts.iter().map(|t| Input::Text(t.to_string())).collect();
+        self.embed_inputs(inputs, task).await
+    }
+
+    /// Compute image embeddings (CLIP models) from encoded image files (PNG, JPEG, WebP)
+    #[cfg(feature = "images")]
+    pub async fn embed_images(&self, images: &[Vec<u8>]) -> anyhow::Result<Vec<Vec<f32>>> {
+        if !self.model_name.starts_with("jina-clip") {
+            anyhow::bail!(
+                "{} can't embed images; use a CLIP model such as jina-clip-v2",
+                self.model_name
+            );
+        }
+        let inputs = images
+            .iter()
+            .map(|bytes| Input::Image { image: base64_encode(bytes) })
+            .collect();
+        self.embed_inputs(inputs, None).await
+    }
+
+    async fn embed_inputs(&self, inputs: Vec<Input>, task: Option<&'static str>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let mut all_embeddings = Vec::with_capacity(inputs.len());
+        let mut inputs = inputs.into_iter().peekable();
+
+        while inputs.peek().is_some() {
+            let batch: Vec<Input> = inputs.by_ref().take(BATCH_SIZE).collect();
+            let batch_len = batch.len();
+            let request = EmbedRequest {
+                model: &self.model_name,
+                input: batch,
+                task,
+            };
+
+            let response = self
+                .client
+                .post(format!("{}/embeddings", self.api_base))
+                .bearer_auth(&self.api_key)
+                .json(&request)
+                .send()
+                .await?;
+
+            let response = check_response(response, "Jina").await?;
+            let mut data = response.json::<EmbedResponse>().await?.data;
+            if data.len() != batch_len {
+                anyhow::bail!("Jina returned {} embeddings for {} inputs", data.len(), batch_len);
+            }
+            data.sort_by_key(|d| d.index);
+            all_embeddings.extend(data.into_iter().map(|d| d.embedding));
+        }
+
+        Ok(all_embeddings)
+    }
+}
+
+/// Standard base64 with padding, as the API expects for inline images
+#[cfg(feature = "images")]
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(all(test, feature = "images"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xFF, 0xFE]), "//4=");
+    }
+}
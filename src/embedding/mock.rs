@@ -0,0 +1,102 @@
+//! Mock embedding provider - deterministic pseudo-embeddings without a model
+//!
+//! Each word of the text is hashed into one dimension (with a hashed sign),
+//! and the counts are normalized: texts sharing words point the same way, so
+//! searches behave plausibly, and the same text always embeds the same. For
+//! tests and benchmarks that shouldn't depend on a network or a model.
+
+use tracing::info;
+
+/// Dimensions when the model name doesn't set them
+const DEFAULT_DIMENSIONS: usize = 64;
+
+/// Mock embedding provider
+pub struct MockEmbedding {
+    dimensions: usize,
+}
+
+impl MockEmbedding {
+    /// Create a mock provider; a model name ending in `:<n>` (e.g. `mock:256`)
+    /// sets the dimensions
+    pub fn new(model_name: &str) -> anyhow::Result<Self> {
+        let dimensions = match model_name.rsplit_once(':') {
+            Some((_, dims)) => dims
+                .parse()
+                .ok()
+                .filter(|&d| d > 0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid mock embedding dimensions: {}", dims))?,
+            None => DEFAULT_DIMENSIONS,
+        };
+        info!("Mock embedding provider: {} ({} dims)", model_name, dimensions);
+        Ok(Self { dimensions })
+    }
+
+    /// Get dimensions
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Embed texts
+    pub fn embed(&self, texts: &[&str]) -> Vec<Vec<f32>> {
+        texts.iter().map(|text| self.embed_one(text)).collect()
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut embedding = vec![0.0f32; self.dimensions];
+        let words = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase());
+        let mut any = false;
+        for word in words {
+            let (slot, sign) = self.slot(&word);
+            embedding[slot] += sign;
+            any = true;
+        }
+        // Texts without words still get a distinct direction
+        if !any {
+            let (slot, sign) = self.slot(text);
+            embedding[slot] += sign;
+        }
+
+        let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for x in &mut embedding {
+                *x /= norm;
+            }
+        }
+        embedding
+    }
+
+    /// Dimension and sign a token is hashed to (FNV-1a, so indexes embed the
+    /// same across builds of leann)
+    fn slot(&self, token: &str) -> (usize, f32) {
+        let hash = token
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        ((hash % self.dimensions as u64) as usize, sign)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn test_mock_embeddings() {
+        assert_eq!(MockEmbedding::new("mock").unwrap().dimensions(), DEFAULT_DIMENSIONS);
+        assert!(MockEmbedding::new("mock:zero").is_err());
+
+        let mock = MockEmbedding::new("mock:256").unwrap();
+        let embeddings = mock.embed(&["Rust borrow checker", "the rust BORROW checker", "baking sourdough bread", ""]);
+        assert_eq!(embeddings[0].len(), 256);
+        assert_eq!(mock.embed(&["Rust borrow checker"])[0], embeddings[0]);
+        assert!(cosine(&embeddings[0], &embeddings[1]) > cosine(&embeddings[0], &embeddings[2]));
+        assert!((cosine(&embeddings[3], &embeddings[3]) - 1.0).abs() < 1e-6);
+    }
+}
@@ -3,15 +3,36 @@
 mod openai;
 mod ollama;
 mod gemini;
+mod instructions;
+mod jina;
+mod mock;
 mod models;
+mod multi_vector;
+mod postprocess;
+mod sparse;
+mod task;
 
 #[cfg(feature = "local-embeddings")]
 mod candle;
 
-pub use models::{get_model_config, ModelConfig};
+pub use instructions::{document_template, DocumentInstructions};
+pub use models::{
+    embedding_cost, estimate_tokens, get_model_config, max_input_tokens, multilingual_alternative, ModelConfig,
+};
+pub use multi_vector::MultiVectorEmbedding;
+pub use postprocess::PostProcessing;
+pub use sparse::SparseEmbedding;
+pub use task::{template_tokens, EmbeddingTask};
+
+use task::apply_template;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use tracing::info;
 
+use crate::config::EmbeddingConfig;
+use crate::index::IndexMeta;
+
 /// Embedding mode configuration
 #[derive(Debug, Clone)]
 pub enum EmbeddingMode {
@@ -25,28 +46,107 @@ pub enum EmbeddingMode {
     Gemini {
         api_key: Option<String>,
     },
+    Jina {
+        api_key: Option<String>,
+    },
+    /// Deterministic hash-based pseudo-embeddings, for tests and benchmarks
+    Mock,
     #[cfg(feature = "local-embeddings")]
     Local {
         model_path: Option<String>,
     },
 }
 
+/// Provider connection settings from command-line flags (and their
+/// environment variables), which take precedence over config.toml
+#[derive(Debug, Clone, Default)]
+pub struct ProviderOverrides {
+    /// API key for OpenAI-compatible services
+    pub api_key: Option<String>,
+    /// Base URL for OpenAI-compatible services
+    pub api_base: Option<String>,
+    /// Ollama host
+    pub host: Option<String>,
+    pub google_api_key: Option<String>,
+    pub jina_api_key: Option<String>,
+    /// Model directory for local embeddings
+    #[cfg(feature = "local-embeddings")]
+    pub model_path: Option<String>,
+}
+
+impl EmbeddingMode {
+    /// The provider called `mode` (as stored in index metadata), connected
+    /// with `overrides`, then `config`; providers fall back to their own
+    /// environment variables for anything left unset
+    pub fn from_name(mode: &str, overrides: &ProviderOverrides, config: &EmbeddingConfig) -> anyhow::Result<Self> {
+        Ok(match mode {
+            "openai" | "lmstudio" => EmbeddingMode::OpenAI {
+                api_key: overrides.api_key.clone().or_else(|| config.api_key.clone()),
+                base_url: overrides.api_base.clone().or_else(|| config.base_url.clone()),
+            },
+            "ollama" => EmbeddingMode::Ollama {
+                host: overrides.host.clone().or_else(|| config.host.clone()),
+            },
+            "gemini" => EmbeddingMode::Gemini {
+                api_key: overrides.google_api_key.clone().or_else(|| config.api_key.clone()),
+            },
+            "jina" => EmbeddingMode::Jina {
+                api_key: overrides.jina_api_key.clone(),
+            },
+            "mock" => EmbeddingMode::Mock,
+            #[cfg(feature = "local-embeddings")]
+            "local" => EmbeddingMode::Local {
+                model_path: overrides.model_path.clone(),
+            },
+            _ => anyhow::bail!("Unknown embedding mode: {}", mode),
+        })
+    }
+
+    /// The provider an index was built with
+    pub fn from_meta_and_overrides(
+        meta: &IndexMeta,
+        overrides: &ProviderOverrides,
+        config: &EmbeddingConfig,
+    ) -> anyhow::Result<Self> {
+        Self::from_name(&meta.embedding_mode, overrides, config)
+    }
+}
+
 /// Unified embedding provider
 pub struct EmbeddingProvider {
     model_name: String,
     dimensions: usize,
     inner: EmbeddingProviderInner,
+    post: PostProcessing,
+    /// Estimated input tokens sent so far, for cost reporting
+    tokens_used: AtomicUsize,
+    /// Inputs truncated to the model's input limit, for the build report
+    truncated_inputs: AtomicUsize,
 }
 
 enum EmbeddingProviderInner {
     OpenAI(openai::OpenAIEmbedding),
     Ollama(ollama::OllamaEmbedding),
     Gemini(gemini::GeminiEmbedding),
+    Jina(jina::JinaEmbedding),
+    Mock(mock::MockEmbedding),
     #[cfg(feature = "local-embeddings")]
     Local(candle::CandleEmbedding),
 }
 
 impl EmbeddingProvider {
+    /// Create the provider an index was built with, applying its post-processing
+    pub async fn for_index(
+        meta: &IndexMeta,
+        overrides: &ProviderOverrides,
+        config: &EmbeddingConfig,
+    ) -> anyhow::Result<Self> {
+        let mode = EmbeddingMode::from_meta_and_overrides(meta, overrides, config)?;
+        Self::new(meta.embedding_model.clone(), mode)
+            .await?
+            .with_post_processing(meta.post_processing())
+    }
+
     /// Create a new embedding provider
     pub async fn new(model_name: String, mode: EmbeddingMode) -> anyhow::Result<Self> {
         let (inner, dimensions) = match mode {
@@ -75,6 +175,19 @@ impl EmbeddingProvider {
                 let dims = provider.dimensions();
                 (EmbeddingProviderInner::Gemini(provider), dims)
             }
+            EmbeddingMode::Jina { api_key } => {
+                let provider = jina::JinaEmbedding::new(
+                    model_name.clone(),
+                    api_key,
+                )?;
+                let dims = provider.dimensions();
+                (EmbeddingProviderInner::Jina(provider), dims)
+            }
+            EmbeddingMode::Mock => {
+                let provider = mock::MockEmbedding::new(&model_name)?;
+                let dims = provider.dimensions();
+                (EmbeddingProviderInner::Mock(provider), dims)
+            }
             #[cfg(feature = "local-embeddings")]
             EmbeddingMode::Local { model_path } => {
                 let provider = candle::CandleEmbedding::new(
@@ -95,9 +208,23 @@ impl EmbeddingProvider {
             model_name,
             dimensions,
             inner,
+            post: PostProcessing::default(),
+            tokens_used: AtomicUsize::new(0),
+            truncated_inputs: AtomicUsize::new(0),
         })
     }
 
+    /// Post-process every embedding (normalize, truncate, round)
+    ///
+    /// Indexes record the post-processing they were built with; pass that
+    /// here so queries and new passages match.
+    pub fn with_post_processing(mut self, post: PostProcessing) -> anyhow::Result<Self> {
+        post.validate(self.dimensions)?;
+        self.dimensions = post.output_dimensions(self.dimensions);
+        self.post = post;
+        Ok(self)
+    }
+
     /// Get embedding dimensions
     pub fn dimensions(&self) -> usize {
         self.dimensions
@@ -108,38 +235,133 @@ impl EmbeddingProvider {
         &self.model_name
     }
 
-    /// Compute embeddings for texts
-    pub async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+    /// Embedding mode name ("openai", "ollama", ...), as stored in index metadata
+    pub fn mode_name(&self) -> &'static str {
         match &self.inner {
-            EmbeddingProviderInner::OpenAI(p) => p.embed(texts).await,
-            EmbeddingProviderInner::Ollama(p) => p.embed(texts).await,
-            EmbeddingProviderInner::Gemini(p) => p.embed(texts).await,
+            EmbeddingProviderInner::OpenAI(_) => "openai",
+            EmbeddingProviderInner::Ollama(_) => "ollama",
+            EmbeddingProviderInner::Gemini(_) => "gemini",
+            EmbeddingProviderInner::Jina(_) => "jina",
+            EmbeddingProviderInner::Mock(_) => "mock",
             #[cfg(feature = "local-embeddings")]
-            EmbeddingProviderInner::Local(p) => p.embed(texts),
+            EmbeddingProviderInner::Local(_) => "local",
         }
     }
 
-    /// Compute embeddings with a prompt template prefix
+    /// Estimated input tokens embedded so far (text only; images aren't counted)
+    pub fn tokens_used(&self) -> usize {
+        self.tokens_used.load(Ordering::Relaxed)
+    }
+
+    /// Inputs cut to the model's input limit so far (see `embed_texts`)
+    pub fn truncated_inputs(&self) -> usize {
+        self.truncated_inputs.load(Ordering::Relaxed)
+    }
+
+    /// Cost in USD of `tokens` input tokens with this provider, if the price is known
+    pub fn cost(&self, tokens: usize) -> Option<f64> {
+        embedding_cost(self.mode_name(), &self.model_name, tokens)
+    }
+
+    /// Compute embeddings of texts for a task, applying a prompt template
     ///
-    /// Useful for asymmetric embedding models like E5, BGE, or Instructor
-    /// that expect prefixes like "query: " or "passage: "
-    pub async fn embed_with_template(
+    /// The template is a prefix ("query: " for E5) or contains `{text}`
+    /// (Instructor-style instructions). Providers with native task types
+    /// (Gemini, jina-embeddings-v3) are also told the task.
+    pub async fn embed_for_task(
         &self,
         texts: &[&str],
+        task: EmbeddingTask,
         template: &str,
     ) -> anyhow::Result<Vec<Vec<f32>>> {
         if template.is_empty() {
-            return self.embed(texts).await;
+            return self.embed_texts(texts, task).await;
         }
 
-        // Apply template prefix to all texts
         let templated: Vec<String> = texts
             .iter()
-            .map(|t| format!("{}{}", template, t))
+            .map(|t| apply_template(template, t))
             .collect();
 
         let refs: Vec<&str> = templated.iter().map(|s| s.as_str()).collect();
-        self.embed(&refs).await
+        self.embed_texts(&refs, task).await
+    }
+
+    /// Compute document embeddings, each text with its own prompt template
+    /// (see `document_template`)
+    pub async fn embed_documents(&self, texts: &[&str], templates: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let templated: Vec<String> = texts
+            .iter()
+            .zip(templates)
+            .map(|(text, template)| apply_template(template, text))
+            .collect();
+
+        let refs: Vec<&str> = templated.iter().map(|s| s.as_str()).collect();
+        self.embed_texts(&refs, EmbeddingTask::Document).await
+    }
+
+    /// Compute and post-process embeddings, truncating texts longer than the
+    /// model's input limit as a last resort (builds normally apply a
+    /// `ChunkLengthPolicy` first)
+    #[tracing::instrument(
+        name = "embedding.batch",
+        skip_all,
+        fields(mode = self.mode_name(), model = %self.model_name, texts = texts.len())
+    )]
+    async fn embed_texts(&self, texts: &[&str], task: EmbeddingTask) -> anyhow::Result<Vec<Vec<f32>>> {
+        let limited: Vec<&str>;
+        let texts = match max_input_tokens(&self.model_name) {
+            Some(max) if texts.iter().any(|t| estimate_tokens(t) > max) => {
+                let over = texts.iter().filter(|t| estimate_tokens(t) > max).count();
+                tracing::debug!("Truncating {} over-length inputs to {} tokens for {}", over, max, self.model_name);
+                self.truncated_inputs.fetch_add(over, Ordering::Relaxed);
+                limited = texts
+                    .iter()
+                    .map(|t| crate::chunker::truncate_to_tokens(t, max))
+                    .collect();
+                &limited[..]
+            }
+            _ => texts,
+        };
+        let tokens: usize = texts.iter().map(|t| estimate_tokens(t)).sum();
+        self.tokens_used.fetch_add(tokens, Ordering::Relaxed);
+
+        let mut embeddings = match &self.inner {
+            EmbeddingProviderInner::OpenAI(p) => p.embed(texts).await?,
+            EmbeddingProviderInner::Ollama(p) => p.embed(texts).await?,
+            EmbeddingProviderInner::Gemini(p) => p.embed(texts, task).await?,
+            EmbeddingProviderInner::Jina(p) => p.embed(texts, task).await?,
+            EmbeddingProviderInner::Mock(p) => p.embed(texts),
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingProviderInner::Local(p) => p.embed(texts)?,
+        };
+
+        self.post_process(&mut embeddings);
+        Ok(embeddings)
+    }
+
+    /// Compute embeddings of images (encoded PNG, JPEG, or WebP files) in the
+    /// same space as text, for multimodal models such as jina-clip-v2
+    #[cfg(feature = "images")]
+    pub async fn embed_images(&self, images: &[Vec<u8>]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let mut embeddings = match &self.inner {
+            EmbeddingProviderInner::Jina(p) => p.embed_images(images).await?,
+            _ => anyhow::bail!(
+                "{} can't embed images; use --embedding-mode jina with a CLIP model such as jina-clip-v2",
+                self.model_name
+            ),
+        };
+
+        self.post_process(&mut embeddings);
+        Ok(embeddings)
+    }
+
+    fn post_process(&self, embeddings: &mut [Vec<f32>]) {
+        if !self.post.is_identity() {
+            for embedding in embeddings {
+                self.post.apply(embedding);
+            }
+        }
     }
 }
 
@@ -147,6 +369,7 @@ impl EmbeddingProvider {
 ///
 /// These templates are used with the `--embedding-prompt-template` and
 /// `--query-prompt-template` CLI options for models like E5, BGE, and Instructor.
+/// Templates may also place the text with `{text}` instead of being a prefix.
 #[allow(dead_code)]
 pub mod templates {
     /// E5 model query prefix (use with --query-prompt-template)
@@ -162,3 +385,35 @@ pub mod templates {
     /// Instructor model passage prefix
     pub const INSTRUCTOR_PASSAGE: &str = "Represent the document for retrieval: ";
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_override_config() {
+        let config = EmbeddingConfig {
+            host: Some("http://config:11434".to_string()),
+            base_url: Some("http://config:1234/v1".to_string()),
+            api_key: Some("config-key".to_string()),
+            ..Default::default()
+        };
+        let overrides = ProviderOverrides {
+            host: Some("http://flag:11434".to_string()),
+            ..Default::default()
+        };
+
+        match EmbeddingMode::from_name("ollama", &overrides, &config).unwrap() {
+            EmbeddingMode::Ollama { host } => assert_eq!(host.as_deref(), Some("http://flag:11434")),
+            other => panic!("unexpected mode {:?}", other),
+        }
+        match EmbeddingMode::from_name("lmstudio", &overrides, &config).unwrap() {
+            EmbeddingMode::OpenAI { api_key, base_url } => {
+                assert_eq!(api_key.as_deref(), Some("config-key"));
+                assert_eq!(base_url.as_deref(), Some("http://config:1234/v1"));
+            }
+            other => panic!("unexpected mode {:?}", other),
+        }
+        assert!(EmbeddingMode::from_name("word2vec", &overrides, &config).is_err());
+    }
+}
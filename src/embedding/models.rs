@@ -5,6 +5,8 @@
 //! - Some produce normalized embeddings (use cosine)
 //! - Some have specific token limits
 
+use super::EmbeddingTask;
+
 /// Model configuration for embedding
 #[derive(Debug, Clone)]
 pub struct ModelConfig {
@@ -12,6 +14,8 @@ pub struct ModelConfig {
     pub document_prefix: &'static str,
     /// Prefix to add to queries during search
     pub query_prefix: &'static str,
+    /// Prefix to add to texts embedded for clustering
+    pub clustering_prefix: &'static str,
     /// Whether embeddings are L2 normalized
     pub normalized: bool,
     /// Embedding dimensions
@@ -23,12 +27,24 @@ impl Default for ModelConfig {
         Self {
             document_prefix: "",
             query_prefix: "",
+            clustering_prefix: "",
             normalized: false,
             dimensions: 768,
         }
     }
 }
 
+impl ModelConfig {
+    /// Prefix for text embedded for `task`
+    pub fn prefix(&self, task: EmbeddingTask) -> &'static str {
+        match task {
+            EmbeddingTask::Document => self.document_prefix,
+            EmbeddingTask::Query => self.query_prefix,
+            EmbeddingTask::Clustering => self.clustering_prefix,
+        }
+    }
+}
+
 /// Get model configuration for known models
 pub fn get_model_config(model_name: &str) -> ModelConfig {
     // Normalize model name (remove version tags like :latest)
@@ -40,6 +56,7 @@ pub fn get_model_config(model_name: &str) -> ModelConfig {
         | "text-embedding-nomic-embed-text-v1.5" => ModelConfig {
             document_prefix: "search_document: ",
             query_prefix: "search_query: ",
+            clustering_prefix: "clustering: ",
             normalized: true,
             dimensions: 768,
         },
@@ -48,6 +65,7 @@ pub fn get_model_config(model_name: &str) -> ModelConfig {
         "mxbai-embed-large" | "mxbai-embed-large-v1" => ModelConfig {
             document_prefix: "Represent this document for retrieval: ",
             query_prefix: "Represent this sentence for searching relevant passages: ",
+            clustering_prefix: "",
             normalized: true,
             dimensions: 1024,
         },
@@ -57,6 +75,7 @@ pub fn get_model_config(model_name: &str) -> ModelConfig {
         | "bge-small-en-v1.5" | "bge-base-en-v1.5" | "bge-large-en-v1.5" => ModelConfig {
             document_prefix: "",
             query_prefix: "Represent this sentence for searching relevant passages: ",
+            clustering_prefix: "",
             normalized: true,
             dimensions: match base_name {
                 s if s.contains("small") => 384,
@@ -71,6 +90,7 @@ pub fn get_model_config(model_name: &str) -> ModelConfig {
         | "multilingual-e5-small" | "multilingual-e5-base" | "multilingual-e5-large" => ModelConfig {
             document_prefix: "passage: ",
             query_prefix: "query: ",
+            clustering_prefix: "query: ",
             normalized: true,
             dimensions: match base_name {
                 s if s.contains("small") => 384,
@@ -79,10 +99,23 @@ pub fn get_model_config(model_name: &str) -> ModelConfig {
             },
         },
 
+        // Snowflake Arctic Embed (v1) - instruction prefix for queries only
+        "snowflake-arctic-embed" | "snowflake-arctic-embed-l" | "snowflake-arctic-embed-m" => ModelConfig {
+            document_prefix: "",
+            query_prefix: "Represent this sentence for searching relevant passages: ",
+            clustering_prefix: "",
+            normalized: true,
+            dimensions: match base_name {
+                s if s.ends_with("-m") => 768,
+                _ => 1024,
+            },
+        },
+
         // GTE models - no prefix needed
         "gte-small" | "gte-base" | "gte-large" => ModelConfig {
             document_prefix: "",
             query_prefix: "",
+            clustering_prefix: "",
             normalized: true,
             dimensions: match base_name {
                 s if s.contains("small") => 384,
@@ -95,6 +128,7 @@ pub fn get_model_config(model_name: &str) -> ModelConfig {
         "all-minilm" | "all-MiniLM-L6-v2" | "all-MiniLM-L12-v2" => ModelConfig {
             document_prefix: "",
             query_prefix: "",
+            clustering_prefix: "",
             normalized: true,
             dimensions: 384,
         },
@@ -103,27 +137,117 @@ pub fn get_model_config(model_name: &str) -> ModelConfig {
         "text-embedding-3-small" => ModelConfig {
             document_prefix: "",
             query_prefix: "",
+            clustering_prefix: "",
             normalized: true,
             dimensions: 1536,
         },
         "text-embedding-3-large" => ModelConfig {
             document_prefix: "",
             query_prefix: "",
+            clustering_prefix: "",
             normalized: true,
             dimensions: 3072,
         },
         "text-embedding-ada-002" => ModelConfig {
             document_prefix: "",
             query_prefix: "",
+            clustering_prefix: "",
             normalized: true,
             dimensions: 1536,
         },
 
+        // Jina models - tasks are sent as a request parameter, CLIP models also embed images
+        "jina-embeddings-v3" | "jina-clip-v1" | "jina-clip-v2"
+        | "jina-embeddings-v2-base-en" | "jina-embeddings-v2-base-code" => ModelConfig {
+            document_prefix: "",
+            query_prefix: "",
+            clustering_prefix: "",
+            normalized: true,
+            dimensions: match base_name {
+                "jina-embeddings-v3" | "jina-clip-v2" => 1024,
+                _ => 768,
+            },
+        },
+
         // Default for unknown models
         _ => ModelConfig::default(),
     }
 }
 
+/// Price in USD per million input tokens for hosted embedding models
+///
+/// Returns `None` for models without a known price (including local models).
+pub fn price_per_million_tokens(model_name: &str) -> Option<f64> {
+    match model_name {
+        "text-embedding-3-small" => Some(0.02),
+        "text-embedding-3-large" => Some(0.13),
+        "text-embedding-ada-002" => Some(0.10),
+        "gemini-embedding-001" => Some(0.15),
+        "text-embedding-004" => Some(0.0),
+        "jina-embeddings-v3" | "jina-embeddings-v2-base-en" | "jina-embeddings-v2-base-code" => Some(0.02),
+        "jina-clip-v1" | "jina-clip-v2" => Some(0.02),
+        _ => None,
+    }
+}
+
+/// Cost in USD of embedding `tokens` tokens with a model served by `mode`
+///
+/// Models run locally (ollama, local) are free; `None` means the price is unknown.
+pub fn embedding_cost(mode: &str, model_name: &str, tokens: usize) -> Option<f64> {
+    match mode {
+        "ollama" | "local" | "mock" => Some(0.0),
+        _ => price_per_million_tokens(model_name).map(|price| tokens as f64 / 1_000_000.0 * price),
+    }
+}
+
+/// Maximum input length in tokens for known models
+///
+/// Builds fit chunks (with their prompt template) within this limit. Returns
+/// `None` for unknown models (no limit is enforced).
+pub fn max_input_tokens(model_name: &str) -> Option<usize> {
+    let base_name = model_name.split(':').next().unwrap_or(model_name);
+
+    match base_name {
+        "nomic-embed-text" | "nomic-embed-text-v1" | "nomic-embed-text-v1.5"
+        | "text-embedding-nomic-embed-text-v1.5" => Some(8192),
+        "nomic-embed-text-v2-moe" => Some(512),
+        "text-embedding-3-small" | "text-embedding-3-large" | "text-embedding-ada-002" => Some(8191),
+        "text-embedding-004" | "text-multilingual-embedding-002" | "gemini-embedding-001" => Some(2048),
+        "jina-embeddings-v3" | "jina-embeddings-v2-base-en" | "jina-embeddings-v2-base-code" | "jina-clip-v2" => Some(8192),
+        "bge-m3" | "snowflake-arctic-embed2" => Some(8192),
+        "snowflake-arctic-embed" => Some(512),
+        "all-minilm" | "all-MiniLM-L6-v2" | "all-MiniLM-L12-v2" => Some(256),
+        s if s.starts_with("mxbai-embed-large")
+            || s.starts_with("bge-")
+            || s.starts_with("e5-")
+            || s.starts_with("multilingual-e5-")
+            || s.starts_with("gte-") => Some(512),
+        _ => None,
+    }
+}
+
+/// Multilingual counterpart of an English-only model from the same provider
+///
+/// Returns `None` for models that already handle most languages (OpenAI,
+/// Gemini, Jina, bge-m3, multilingual-e5) and for unknown models.
+pub fn multilingual_alternative(model_name: &str) -> Option<&'static str> {
+    let base_name = model_name.split(':').next().unwrap_or(model_name);
+
+    match base_name {
+        // Ollama
+        "nomic-embed-text" | "mxbai-embed-large" | "all-minilm" | "snowflake-arctic-embed" => Some("bge-m3"),
+        "e5-small" | "e5-small-v2" => Some("multilingual-e5-small"),
+        "e5-base" | "e5-base-v2" => Some("multilingual-e5-base"),
+        "e5-large" | "e5-large-v2" => Some("multilingual-e5-large"),
+        _ => None,
+    }
+}
+
+/// Rough token count for a text (about 4 characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,6 +260,15 @@ mod tests {
         assert!(config.normalized);
     }
 
+    #[test]
+    fn test_task_prefix() {
+        let config = get_model_config("nomic-embed-text");
+        assert_eq!(config.prefix(EmbeddingTask::Document), "search_document: ");
+        assert_eq!(config.prefix(EmbeddingTask::Query), "search_query: ");
+        assert_eq!(config.prefix(EmbeddingTask::Clustering), "clustering: ");
+        assert_eq!(get_model_config("snowflake-arctic-embed:335m").prefix(EmbeddingTask::Document), "");
+    }
+
     #[test]
     fn test_nomic_with_version() {
         let config = get_model_config("nomic-embed-text:latest");
@@ -155,4 +288,28 @@ mod tests {
         assert_eq!(config.document_prefix, "");
         assert_eq!(config.query_prefix, "");
     }
+
+    #[test]
+    fn test_price_and_token_estimate() {
+        assert_eq!(price_per_million_tokens("text-embedding-3-small"), Some(0.02));
+        assert_eq!(price_per_million_tokens("nomic-embed-text"), None);
+        assert_eq!(embedding_cost("openai", "text-embedding-3-large", 2_000_000), Some(0.26));
+        assert_eq!(embedding_cost("ollama", "nomic-embed-text", 2_000_000), Some(0.0));
+        assert_eq!(embedding_cost("openai", "my-proxy-model", 1000), None);
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(max_input_tokens("bge-small-en-v1.5"), Some(512));
+        assert_eq!(max_input_tokens("nomic-embed-text:latest"), Some(8192));
+        assert_eq!(max_input_tokens("bge-m3:567m"), Some(8192));
+        assert_eq!(max_input_tokens("jina-embeddings-v3"), Some(8192));
+        assert_eq!(max_input_tokens("some-unknown-model"), None);
+    }
+
+    #[test]
+    fn test_multilingual_alternative() {
+        assert_eq!(multilingual_alternative("nomic-embed-text:latest"), Some("bge-m3"));
+        assert_eq!(multilingual_alternative("e5-base-v2"), Some("multilingual-e5-base"));
+        assert_eq!(multilingual_alternative("text-embedding-3-small"), None);
+        assert_eq!(multilingual_alternative("bge-m3"), None);
+    }
 }
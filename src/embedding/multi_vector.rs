@@ -0,0 +1,127 @@
+//! Multi-vector (ColBERT-style) embeddings from the Jina AI API
+//!
+//! Late-interaction models embed every token of a text instead of pooling
+//! them into one vector; queries and documents are compared token by token
+//! (MaxSim). Per-token vectors are unit length.
+
+use std::env;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::EmbeddingTask;
+use crate::http::{check_response, create_client};
+
+/// Per-token vectors of one text
+pub type TokenVectors = Vec<Vec<f32>>;
+
+/// Texts sent per request
+const BATCH_SIZE: usize = 32;
+
+/// Multi-vector embedding provider (`jina-colbert-v2` and compatible APIs)
+pub struct MultiVectorEmbedding {
+    client: Client,
+    api_base: String,
+    api_key: String,
+    model_name: String,
+    dimensions: usize,
+}
+
+#[derive(Serialize)]
+struct MultiVectorRequest<'a> {
+    model: &'a str,
+    dimensions: usize,
+    input_type: &'static str,
+    embedding_type: &'static str,
+    input: &'a [&'a str],
+}
+
+#[derive(Deserialize)]
+struct MultiVectorResponse {
+    data: Vec<MultiVectorData>,
+}
+
+#[derive(Deserialize)]
+struct MultiVectorData {
+    index: usize,
+    embeddings: TokenVectors,
+}
+
+impl MultiVectorEmbedding {
+    /// Create a multi-vector embedding provider
+    ///
+    /// The API key defaults to `JINA_API_KEY`; the base URL to
+    /// `LEANN_MULTI_VECTOR_API_BASE`, then `https://api.jina.ai/v1`.
+    pub fn new(model_name: String, dimensions: usize, api_key: Option<String>) -> anyhow::Result<Self> {
+        let api_key = api_key
+            .or_else(|| env::var("JINA_API_KEY").ok())
+            .ok_or_else(|| anyhow::anyhow!("JINA_API_KEY not set (needed for multi-vector embeddings)"))?;
+        let api_base = env::var("LEANN_MULTI_VECTOR_API_BASE")
+            .unwrap_or_else(|_| "https://api.jina.ai/v1".to_string());
+        crate::http::check_network("Multi-vector embeddings", &api_base)?;
+
+        info!(
+            "Multi-vector embedding provider: {} @ {} ({} dims per token)",
+            model_name, api_base, dimensions
+        );
+
+        Ok(Self {
+            client: create_client(),
+            api_base: api_base.trim_end_matches('/').to_string(),
+            api_key,
+            model_name,
+            dimensions,
+        })
+    }
+
+    /// Compute per-token vectors for a task (queries and documents are encoded differently)
+    pub async fn embed(&self, texts: &[&str], task: EmbeddingTask) -> anyhow::Result<Vec<TokenVectors>> {
+        let input_type = match task {
+            EmbeddingTask::Query => "query",
+            _ => "document",
+        };
+        let mut all_vectors = Vec::with_capacity(texts.len());
+
+        for batch in texts.chunks(BATCH_SIZE) {
+            let request = MultiVectorRequest {
+                model: &self.model_name,
+                dimensions: self.dimensions,
+                input_type,
+                embedding_type: "float",
+                input: batch,
+            };
+
+            let response = self
+                .client
+                .post(format!("{}/multi-vector", self.api_base))
+                .bearer_auth(&self.api_key)
+                .json(&request)
+                .send()
+                .await?;
+
+            let response = check_response(response, "Multi-vector embedding").await?;
+            let mut data = response.json::<MultiVectorResponse>().await?.data;
+            if data.len() != batch.len() {
+                anyhow::bail!(
+                    "Multi-vector embedding API returned {} results for {} texts",
+                    data.len(),
+                    batch.len()
+                );
+            }
+            data.sort_by_key(|d| d.index);
+
+            for item in data {
+                if item.embeddings.iter().any(|v| v.len() != self.dimensions) {
+                    anyhow::bail!(
+                        "Multi-vector embedding API returned token vectors of the wrong size (expected {})",
+                        self.dimensions
+                    );
+                }
+                all_vectors.push(item.embeddings);
+            }
+        }
+
+        Ok(all_vectors)
+    }
+}
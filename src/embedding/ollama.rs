@@ -35,6 +35,7 @@ impl OllamaEmbedding {
             .or_else(|| env::var("LEANN_OLLAMA_HOST").ok())
             .or_else(|| env::var("OLLAMA_HOST").ok())
             .unwrap_or_else(|| "http://localhost:11434".to_string());
+        crate::http::check_network("Ollama embeddings", &host)?;
 
         let client = create_client();
 
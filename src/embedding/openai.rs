@@ -23,17 +23,20 @@ impl OpenAIEmbedding {
         api_key: Option<String>,
         base_url: Option<String>,
     ) -> anyhow::Result<Self> {
+        let base_url = base_url.or_else(|| env::var("OPENAI_BASE_URL").ok());
+        crate::http::check_network("OpenAI embeddings", base_url.as_deref().unwrap_or(crate::http::OPENAI_API_BASE))?;
+
         let api_key = api_key
             .or_else(|| env::var("OPENAI_API_KEY").ok())
             .ok_or_else(|| anyhow::anyhow!("OPENAI_API_KEY not set"))?;
 
         let mut config = OpenAIConfig::new().with_api_key(api_key);
 
-        if let Some(base_url) = base_url.or_else(|| env::var("OPENAI_BASE_URL").ok()) {
+        if let Some(base_url) = base_url {
             config = config.with_api_base(base_url);
         }
 
-        let client = Client::with_config(config);
+        let client = Client::with_config(config).with_http_client(crate::http::create_client());
 
         // Determine dimensions based on model name (will be verified/updated on first embed call)
         let dimensions = match model_name.as_str() {
@@ -96,7 +99,12 @@ impl OpenAIEmbedding {
                 .input(EmbeddingInput::StringArray(batch.to_vec()))
                 .build()?;
 
-            let response = self.client.embeddings().create(request).await?;
+            let response = self
+                .client
+                .embeddings()
+                .create(request)
+                .await
+                .map_err(|e| crate::http::openai_error("OpenAI", e))?;
 
             for embedding_data in response.data {
                 all_embeddings.push(embedding_data.embedding);
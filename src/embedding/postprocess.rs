@@ -0,0 +1,222 @@
+//! Embedding post-processing - normalize, truncate, and round provider output
+//!
+//! Remote providers return vectors as the model produced them: Ollama's
+//! nomic-embed-text output, for example, is not unit length, so inner-product
+//! scores depend on vector magnitude. Post-processing is applied to every
+//! embedding, recorded in the index metadata, and reapplied to queries.
+
+use serde::{Deserialize, Serialize};
+
+use super::get_model_config;
+
+/// Largest finite f16 value
+const F16_MAX: f32 = 65504.0;
+
+/// Smallest positive normal f16 value (2^-14)
+const F16_MIN_NORMAL: f32 = 6.103_515_6e-5;
+
+/// Numeric precision embeddings are rounded to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// Full precision (no rounding)
+    #[default]
+    F32,
+    /// IEEE half precision
+    F16,
+    /// bfloat16 (f32 range, 8-bit mantissa)
+    Bf16,
+}
+
+impl Precision {
+    fn is_f32(&self) -> bool {
+        *self == Precision::F32
+    }
+}
+
+impl std::str::FromStr for Precision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "f32" => Ok(Precision::F32),
+            "f16" => Ok(Precision::F16),
+            "bf16" => Ok(Precision::Bf16),
+            _ => Err(format!("Unknown precision '{}' (expected f32, f16, or bf16)", s)),
+        }
+    }
+}
+
+/// Post-processing applied to every embedding a provider returns
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PostProcessing {
+    /// L2-normalize vectors to unit length
+    #[serde(default)]
+    pub normalize: bool,
+
+    /// Keep only the first N dimensions (Matryoshka models); applied before normalizing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncate_dims: Option<usize>,
+
+    /// Round values to this precision; applied last
+    #[serde(default, skip_serializing_if = "Precision::is_f32")]
+    pub precision: Precision,
+}
+
+impl PostProcessing {
+    /// Defaults for a model: normalize when the model's vectors are meant to be unit length
+    pub fn for_model(model_name: &str) -> Self {
+        Self {
+            normalize: get_model_config(model_name).normalized,
+            ..Default::default()
+        }
+    }
+
+    /// Whether this changes embeddings at all
+    pub fn is_identity(&self) -> bool {
+        !self.normalize && self.truncate_dims.is_none() && self.precision.is_f32()
+    }
+
+    /// Dimensions of processed vectors, given the provider's dimensions
+    pub fn output_dimensions(&self, dimensions: usize) -> usize {
+        match self.truncate_dims {
+            Some(dims) => dims.min(dimensions),
+            None => dimensions,
+        }
+    }
+
+    /// Check the settings against the provider's dimensions
+    pub fn validate(&self, dimensions: usize) -> anyhow::Result<()> {
+        if let Some(dims) = self.truncate_dims {
+            if dims == 0 {
+                anyhow::bail!("Embedding dimensions to keep must be at least 1");
+            }
+            if dims > dimensions {
+                anyhow::bail!(
+                    "Cannot truncate embeddings to {} dimensions: the model produces {}",
+                    dims,
+                    dimensions
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply to one embedding
+    pub fn apply(&self, embedding: &mut Vec<f32>) {
+        if let Some(dims) = self.truncate_dims {
+            embedding.truncate(dims);
+        }
+
+        if self.normalize {
+            let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                for x in embedding.iter_mut() {
+                    *x /= norm;
+                }
+            }
+        }
+
+        match self.precision {
+            Precision::F32 => {}
+            Precision::F16 => embedding.iter_mut().for_each(|x| *x = round_f16(*x)),
+            Precision::Bf16 => embedding.iter_mut().for_each(|x| *x = round_bf16(*x)),
+        }
+    }
+}
+
+impl std::fmt::Display for PostProcessing {
+    /// e.g. "first 256 dims, normalize, f16", or "none"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut steps = Vec::new();
+        if let Some(dims) = self.truncate_dims {
+            steps.push(format!("first {} dims", dims));
+        }
+        if self.normalize {
+            steps.push("normalize".to_string());
+        }
+        match self.precision {
+            Precision::F32 => {}
+            Precision::F16 => steps.push("f16".to_string()),
+            Precision::Bf16 => steps.push("bf16".to_string()),
+        }
+
+        if steps.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", steps.join(", "))
+        }
+    }
+}
+
+/// Nearest bfloat16 value (round half to even)
+fn round_bf16(x: f32) -> f32 {
+    if !x.is_finite() {
+        return x;
+    }
+    let bits = x.to_bits();
+    let rounding = 0x7FFF + ((bits >> 16) & 1);
+    f32::from_bits(bits.wrapping_add(rounding) & 0xFFFF_0000)
+}
+
+/// Nearest f16 value (round half to even), clamped to the f16 range
+fn round_f16(x: f32) -> f32 {
+    if !x.is_finite() {
+        return x;
+    }
+    if x.abs() < F16_MIN_NORMAL {
+        // Subnormal f16 values are multiples of 2^-24
+        let scale = 16_777_216.0; // 2^24
+        return (x * scale).round_ties_even() / scale;
+    }
+    let bits = x.to_bits();
+    let rounding = 0xFFF + ((bits >> 13) & 1);
+    let rounded = f32::from_bits(bits.wrapping_add(rounding) & 0xFFFF_E000);
+    rounded.clamp(-F16_MAX, F16_MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_and_truncate() {
+        let post = PostProcessing {
+            normalize: true,
+            truncate_dims: Some(2),
+            ..Default::default()
+        };
+        let mut embedding = vec![3.0, 4.0, 12.0];
+        post.apply(&mut embedding);
+        assert_eq!(embedding, vec![0.6, 0.8]);
+        assert_eq!(post.output_dimensions(768), 2);
+        assert_eq!(post.to_string(), "first 2 dims, normalize");
+        assert_eq!(PostProcessing::default().to_string(), "none");
+        assert!(post.validate(1).is_err());
+
+        let mut zero = vec![0.0, 0.0];
+        PostProcessing { normalize: true, ..Default::default() }.apply(&mut zero);
+        assert_eq!(zero, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_precision_rounding() {
+        assert_eq!(round_bf16(1.0), 1.0);
+        assert_eq!(round_bf16(1.0 + 1.0 / 256.0), 1.0); // halfway rounds to even
+        assert_eq!(round_bf16(1.0 + 3.0 / 256.0), 1.0 + 1.0 / 64.0);
+
+        assert_eq!(round_f16(0.1), 0.099_975_586);
+        assert_eq!(round_f16(1.0 + 1.0 / 4096.0), 1.0);
+        assert_eq!(round_f16(1e6), F16_MAX);
+        assert_eq!(round_f16(1e-6), 17.0 * 2f32.powi(-24));
+
+        assert_eq!("BF16".parse::<Precision>(), Ok(Precision::Bf16));
+        assert!("int8".parse::<Precision>().is_err());
+    }
+
+    #[test]
+    fn test_model_defaults() {
+        assert!(PostProcessing::for_model("nomic-embed-text").normalize);
+        assert!(PostProcessing::for_model("some-unknown-model").is_identity());
+    }
+}
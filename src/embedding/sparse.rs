@@ -0,0 +1,115 @@
+//! Learned sparse embeddings (SPLADE, BM42) from a text-embeddings-inference server
+//!
+//! A sparse embedding maps vocabulary term IDs to weights. Unlike BM25, the
+//! model expands texts with related terms and learns how much each term
+//! matters, so lexical matching also finds synonyms.
+
+use std::env;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::http::{check_response, create_client};
+
+/// Term ID -> weight pairs, sorted by term ID
+pub type SparseVector = Vec<(u32, f32)>;
+
+/// Texts sent per request
+const BATCH_SIZE: usize = 32;
+
+/// Sparse embedding provider backed by a text-embeddings-inference server
+/// (`/embed_sparse`), which runs the model locally or remotely
+pub struct SparseEmbedding {
+    client: Client,
+    host: String,
+}
+
+#[derive(Serialize)]
+struct EmbedSparseRequest<'a> {
+    inputs: &'a [&'a str],
+    truncate: bool,
+}
+
+#[derive(Deserialize)]
+struct SparseValue {
+    index: u32,
+    value: f32,
+}
+
+impl SparseEmbedding {
+    /// Create a sparse embedding provider
+    ///
+    /// The host defaults to `LEANN_SPARSE_HOST`, then `http://localhost:8080`.
+    pub fn new(model_name: &str, host: Option<String>) -> Self {
+        let host = host
+            .or_else(|| env::var("LEANN_SPARSE_HOST").ok())
+            .unwrap_or_else(|| "http://localhost:8080".to_string());
+
+        info!("Sparse embedding provider: {} @ {}", model_name, host);
+
+        Self {
+            client: create_client(),
+            host: host.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Compute sparse embeddings
+    pub async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<SparseVector>> {
+        crate::http::check_network("Sparse embeddings", &self.host)?;
+        let mut all_vectors = Vec::with_capacity(texts.len());
+
+        for batch in texts.chunks(BATCH_SIZE) {
+            let request = EmbedSparseRequest {
+                inputs: batch,
+                truncate: true,
+            };
+
+            let response = self
+                .client
+                .post(format!("{}/embed_sparse", self.host))
+                .json(&request)
+                .send()
+                .await?;
+
+            let response = check_response(response, "Sparse embedding").await?;
+            let vectors: Vec<Vec<SparseValue>> = response.json().await?;
+            if vectors.len() != batch.len() {
+                anyhow::bail!(
+                    "Sparse embedding server returned {} vectors for {} texts",
+                    vectors.len(),
+                    batch.len()
+                );
+            }
+            all_vectors.extend(vectors.into_iter().map(to_sparse_vector));
+        }
+
+        Ok(all_vectors)
+    }
+}
+
+/// Drop non-positive weights and sort by term ID
+fn to_sparse_vector(values: Vec<SparseValue>) -> SparseVector {
+    let mut vector: SparseVector = values
+        .into_iter()
+        .filter(|v| v.value > 0.0)
+        .map(|v| (v.index, v.value))
+        .collect();
+    vector.sort_by_key(|(term, _)| *term);
+    vector.dedup_by_key(|(term, _)| *term);
+    vector
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_sparse_vector() {
+        let values: Vec<SparseValue> = serde_json::from_str(
+            r#"[{"index": 2054, "value": 0.8}, {"index": 17, "value": 1.25}, {"index": 99, "value": 0.0}]"#,
+        )
+        .unwrap();
+        assert_eq!(to_sparse_vector(values), vec![(17, 1.25), (2054, 0.8)]);
+    }
+}
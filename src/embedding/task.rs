@@ -0,0 +1,66 @@
+//! Embedding tasks and prompt templates for asymmetric models
+//!
+//! Asymmetric models embed documents and queries differently: most expect a
+//! task prefix ("search_query: ", "passage: "), Instructor-style models an
+//! instruction around the text, and some APIs (Gemini) take the task as a
+//! request parameter instead.
+
+/// What a text is embedded for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingTask {
+    /// Passages stored in an index
+    Document,
+    /// Search queries matched against documents
+    Query,
+    /// Texts compared with each other (no command uses this yet)
+    #[allow(dead_code)]
+    Clustering,
+}
+
+impl EmbeddingTask {
+    /// Gemini `taskType` request parameter
+    pub fn gemini_task_type(self) -> &'static str {
+        match self {
+            EmbeddingTask::Document => "RETRIEVAL_DOCUMENT",
+            EmbeddingTask::Query => "RETRIEVAL_QUERY",
+            EmbeddingTask::Clustering => "CLUSTERING",
+        }
+    }
+}
+
+/// Apply an embedding prompt template to a text
+///
+/// `{text}` in the template is replaced by the text (e.g. "Represent the
+/// document for retrieval: {text}"); a template without the placeholder is
+/// used as a prefix.
+pub fn apply_template(template: &str, text: &str) -> String {
+    if template.contains("{text}") {
+        template.replace("{text}", text)
+    } else {
+        format!("{}{}", template, text)
+    }
+}
+
+/// Estimated tokens a prompt template adds to each text
+pub fn template_tokens(template: &str) -> usize {
+    super::estimate_tokens(&apply_template(template, ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_template() {
+        assert_eq!(apply_template("query: ", "rust"), "query: rust");
+        assert_eq!(apply_template("", "rust"), "rust");
+        assert_eq!(
+            apply_template("Instruct: find code\nQuery: {text}", "retry"),
+            "Instruct: find code\nQuery: retry"
+        );
+        assert_eq!(apply_template("<{text}>", "a"), "<a>");
+        assert_eq!(template_tokens(""), 0);
+        assert_eq!(template_tokens("passage: "), 3);
+        assert_eq!(template_tokens("Query: {text}"), 2);
+    }
+}
@@ -0,0 +1,169 @@
+//! Structured errors - failures callers can tell apart, with exit codes
+//!
+//! Most errors travel as `anyhow::Error`; the ones wrappers and editors may
+//! want to handle are raised as a `LeannError` inside it. `exit_code` and
+//! `envelope` find it in the error chain, so context added on the way up
+//! doesn't hide it.
+
+use std::path::PathBuf;
+
+use crate::backend::BackendError;
+
+/// Exit code for errors without a more specific one
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Errors with a stable code and exit status
+#[derive(Debug, thiserror::Error)]
+pub enum LeannError {
+    #[error("Index '{name}' not found. Run 'leann list' to see available indexes.")]
+    IndexNotFound { name: String },
+
+    #[error("Embedding dimension mismatch: index has {expected}, {source_name} has {actual}")]
+    DimensionMismatch {
+        expected: usize,
+        actual: usize,
+        /// What produced the mismatched vectors (e.g. "provider")
+        source_name: &'static str,
+    },
+
+    #[error("{service} rejected the credentials (HTTP {status}): {message}")]
+    ProviderAuth { service: String, status: u16, message: String },
+
+    #[error("{service} API error {status}: {message}")]
+    Provider { service: String, status: u16, message: String },
+
+    #[error("{} has format version {found}, newer than this leann supports ({supported}); upgrade leann", path.display())]
+    FormatIncompatible { path: PathBuf, found: u64, supported: u64 },
+
+    #[error("{component} needs network access ({url}), but offline mode is on (--offline or LEANN_OFFLINE=1)")]
+    Offline { component: String, url: String },
+
+    #[error("{operation} of '{index}' was cancelled; the index is unchanged, and running it again reuses the embeddings computed so far")]
+    Cancelled { operation: &'static str, index: String },
+}
+
+impl LeannError {
+    /// Stable identifier for the kind of error, as used in JSON envelopes
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::IndexNotFound { .. } => "index_not_found",
+            Self::DimensionMismatch { .. } => "dimension_mismatch",
+            Self::ProviderAuth { .. } => "provider_auth",
+            Self::Provider { .. } => "provider_error",
+            Self::FormatIncompatible { .. } => "format_incompatible",
+            Self::Offline { .. } => "offline",
+            Self::Cancelled { .. } => "cancelled",
+        }
+    }
+
+    /// Process exit status (2 is left to command-line usage errors)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::IndexNotFound { .. } => 3,
+            Self::DimensionMismatch { .. } => 4,
+            Self::FormatIncompatible { .. } => 5,
+            Self::ProviderAuth { .. } => 6,
+            Self::Provider { .. } => 7,
+            Self::Offline { .. } => 8,
+            Self::Cancelled { .. } => crate::cancel::EXIT_CANCELLED,
+        }
+    }
+
+    /// Fields of the error, for JSON envelopes
+    fn details(&self) -> serde_json::Value {
+        match self {
+            Self::IndexNotFound { name } => serde_json::json!({ "index": name }),
+            Self::DimensionMismatch { expected, actual, source_name } => {
+                serde_json::json!({ "expected": expected, "actual": actual, "source": source_name })
+            }
+            Self::ProviderAuth { service, status, .. } | Self::Provider { service, status, .. } => {
+                serde_json::json!({ "service": service, "status": status })
+            }
+            Self::FormatIncompatible { path, found, supported } => {
+                serde_json::json!({ "path": path, "found": found, "supported": supported })
+            }
+            Self::Offline { component, url } => serde_json::json!({ "component": component, "url": url }),
+            Self::Cancelled { operation, index } => serde_json::json!({ "operation": operation, "index": index }),
+        }
+    }
+
+    /// Error for an HTTP API's failure status, telling rejected credentials apart
+    pub fn provider(service: &str, status: u16, message: String) -> Self {
+        let service = service.to_string();
+        match status {
+            401 | 403 => Self::ProviderAuth { service, status, message },
+            _ => Self::Provider { service, status, message },
+        }
+    }
+}
+
+/// The structured error in an error's chain, if any
+fn find(err: &anyhow::Error) -> Option<&LeannError> {
+    err.chain().find_map(|cause| cause.downcast_ref::<LeannError>())
+}
+
+/// Code and exit status of an error: a `LeannError`'s own, a missing backend
+/// index as `index_not_found`, else a generic failure
+fn classify(err: &anyhow::Error) -> (&'static str, i32) {
+    if let Some(err) = find(err) {
+        return (err.code(), err.exit_code());
+    }
+    let not_found = err
+        .chain()
+        .any(|cause| matches!(cause.downcast_ref::<BackendError>(), Some(BackendError::NotFound { .. })));
+    if not_found {
+        ("index_not_found", 3)
+    } else {
+        ("error", EXIT_FAILURE)
+    }
+}
+
+/// Whether an error is an operation stopped by Ctrl-C or `leann cancel`
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    matches!(find(err), Some(LeannError::Cancelled { .. }))
+}
+
+/// Process exit status for an error
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    classify(err).1
+}
+
+/// JSON error envelope: `{"error": {"code", "message", "exit_code", "details"}}`
+pub fn envelope(err: &anyhow::Error) -> serde_json::Value {
+    let (code, exit_code) = classify(err);
+    let mut error = serde_json::json!({
+        "code": code,
+        "message": format!("{:#}", err),
+        "exit_code": exit_code,
+    });
+    if let Some(details) = find(err).map(LeannError::details) {
+        error["details"] = details;
+    }
+    serde_json::json!({ "error": error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classifies_through_context() {
+        let err = anyhow::Error::new(LeannError::IndexNotFound { name: "docs".into() }).context("Loading index");
+        assert_eq!(exit_code(&err), 3);
+        let json = envelope(&err);
+        assert_eq!(json["error"]["code"], "index_not_found");
+        assert_eq!(json["error"]["details"]["index"], "docs");
+        assert_eq!(
+            json["error"]["message"],
+            "Loading index: Index 'docs' not found. Run 'leann list' to see available indexes."
+        );
+
+        let auth = anyhow::Error::new(LeannError::provider("OpenAI", 401, "Incorrect API key".into()));
+        assert_eq!((exit_code(&auth), envelope(&auth)["error"]["code"].as_str()), (6, Some("provider_auth")));
+        assert_eq!(exit_code(&anyhow::Error::new(LeannError::provider("Ollama", 500, String::new()))), 7);
+
+        let plain = anyhow::anyhow!("Something else");
+        assert_eq!(exit_code(&plain), EXIT_FAILURE);
+        assert!(envelope(&plain)["error"].get("details").is_none());
+    }
+}
@@ -0,0 +1,241 @@
+//! Export passages and their vectors to external vector databases
+//!
+//! `leann export` pushes an index's passages, stored vectors, and metadata to
+//! a Qdrant collection, or writes them to a Parquet file that LanceDB (and
+//! most other vector stores) can load, so nothing has to be re-embedded.
+
+use reqwest::Client;
+use serde_json::json;
+
+use crate::http::{check_response, create_client};
+use crate::index::Passage;
+
+/// Qdrant point ID for a passage (a UUID derived from its LEANN ID)
+///
+/// Qdrant only accepts integer or UUID point IDs; deriving it from the
+/// passage ID makes re-exports overwrite points instead of duplicating them.
+pub fn point_id(passage_id: &str) -> String {
+    uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, passage_id.as_bytes()).to_string()
+}
+
+/// Qdrant payload for a passage: its LEANN ID, text, and metadata
+pub fn payload(passage: &Passage) -> serde_json::Value {
+    json!({
+        "passage_id": passage.id,
+        "text": passage.text,
+        "metadata": passage.metadata,
+    })
+}
+
+/// Writes points to a Qdrant collection over its REST API
+pub struct QdrantExporter {
+    client: Client,
+    collection_url: String,
+    api_key: Option<String>,
+}
+
+impl QdrantExporter {
+    pub fn new(url: &str, collection: &str, api_key: Option<String>) -> anyhow::Result<Self> {
+        crate::http::check_network("Qdrant export", url)?;
+        Ok(Self {
+            client: create_client(),
+            collection_url: format!("{}/collections/{}", url.trim_end_matches('/'), collection),
+            api_key,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.api_key {
+            Some(key) => request.header("api-key", key),
+            None => request,
+        }
+    }
+
+    /// Create the collection for vectors of `dimensions` unless it exists,
+    /// dropping it first if `recreate` is set
+    ///
+    /// LEANN vectors are normalized and searched by inner product, so the
+    /// collection uses dot-product distance.
+    pub async fn prepare_collection(&self, dimensions: usize, recreate: bool) -> anyhow::Result<()> {
+        if recreate {
+            let response = self.request(reqwest::Method::DELETE, &self.collection_url).send().await?;
+            check_response(response, "Qdrant").await?;
+        } else {
+            let response = self.request(reqwest::Method::GET, &self.collection_url).send().await?;
+            if response.status().is_success() {
+                return Ok(());
+            }
+            if response.status() != reqwest::StatusCode::NOT_FOUND {
+                check_response(response, "Qdrant").await?;
+            }
+        }
+
+        let response = self
+            .request(reqwest::Method::PUT, &self.collection_url)
+            .json(&json!({ "vectors": { "size": dimensions, "distance": "Dot" } }))
+            .send()
+            .await?;
+        check_response(response, "Qdrant").await?;
+        Ok(())
+    }
+
+    /// Upsert a batch of passages with their vectors
+    pub async fn upsert(&self, batch: &[(Passage, Vec<f32>)]) -> anyhow::Result<()> {
+        let points: Vec<serde_json::Value> = batch
+            .iter()
+            .map(|(passage, vector)| {
+                json!({
+                    "id": point_id(&passage.id),
+                    "vector": vector,
+                    "payload": payload(passage),
+                })
+            })
+            .collect();
+
+        let url = format!("{}/points?wait=true", self.collection_url);
+        let response = self
+            .request(reqwest::Method::PUT, &url)
+            .json(&json!({ "points": points }))
+            .send()
+            .await?;
+        check_response(response, "Qdrant").await?;
+        Ok(())
+    }
+}
+
+/// Writes passages to a Parquet file with columns `id`, `text`, `metadata`
+/// (a JSON string), and `vector` (a fixed-size list of f32)
+#[cfg(feature = "parquet")]
+pub struct ParquetExporter {
+    writer: parquet::arrow::ArrowWriter<std::fs::File>,
+    schema: std::sync::Arc<arrow_schema::Schema>,
+    dimensions: usize,
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetExporter {
+    pub fn create(path: &std::path::Path, dimensions: usize) -> anyhow::Result<Self> {
+        use arrow_schema::{DataType, Field, Schema};
+        use parquet::basic::{Compression, ZstdLevel};
+        use parquet::file::properties::WriterProperties;
+
+        let schema = std::sync::Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("text", DataType::Utf8, false),
+            Field::new("metadata", DataType::Utf8, false),
+            Field::new(
+                "vector",
+                DataType::FixedSizeList(
+                    std::sync::Arc::new(Field::new("item", DataType::Float32, true)),
+                    dimensions as i32,
+                ),
+                false,
+            ),
+        ]));
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let file = std::fs::File::create(path)?;
+        let writer = parquet::arrow::ArrowWriter::try_new(file, schema.clone(), Some(props))?;
+
+        Ok(Self {
+            writer,
+            schema,
+            dimensions,
+        })
+    }
+
+    /// Write a batch of passages with their vectors as one record batch
+    pub fn write(&mut self, batch: &[(Passage, Vec<f32>)]) -> anyhow::Result<()> {
+        use arrow_array::builder::{FixedSizeListBuilder, Float32Builder};
+        use arrow_array::{ArrayRef, RecordBatch, StringArray};
+
+        let ids = StringArray::from_iter_values(batch.iter().map(|(p, _)| p.id.as_str()));
+        let texts = StringArray::from_iter_values(batch.iter().map(|(p, _)| p.text.as_str()));
+        let metadata = StringArray::from_iter_values(batch.iter().map(|(p, _)| p.metadata.to_string()));
+
+        let mut vectors = FixedSizeListBuilder::with_capacity(
+            Float32Builder::with_capacity(batch.len() * self.dimensions),
+            self.dimensions as i32,
+            batch.len(),
+        );
+        for (passage, vector) in batch {
+            if vector.len() != self.dimensions {
+                anyhow::bail!(
+                    "Passage {} has a {}-dimensional vector, expected {}",
+                    passage.id,
+                    vector.len(),
+                    self.dimensions
+                );
+            }
+            vectors.values().append_slice(vector);
+            vectors.append(true);
+        }
+
+        let columns: Vec<ArrayRef> = vec![
+            std::sync::Arc::new(ids),
+            std::sync::Arc::new(texts),
+            std::sync::Arc::new(metadata),
+            std::sync::Arc::new(vectors.finish()),
+        ];
+        self.writer.write(&RecordBatch::try_new(self.schema.clone(), columns)?)?;
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passage(id: &str) -> Passage {
+        Passage {
+            id: id.to_string(),
+            text: format!("text of {}", id),
+            metadata: json!({"source": "a.md"}),
+        }
+    }
+
+    #[test]
+    fn test_point_id_is_stable_uuid() {
+        let id = point_id("a.md#0");
+        assert_eq!(id, point_id("a.md#0"));
+        assert_ne!(id, point_id("a.md#1"));
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+    }
+
+    #[test]
+    fn test_payload() {
+        let payload = payload(&passage("p1"));
+        assert_eq!(payload["passage_id"], "p1");
+        assert_eq!(payload["text"], "text of p1");
+        assert_eq!(payload["metadata"]["source"], "a.md");
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_export() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let dir = std::env::temp_dir().join(format!("leann-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.parquet");
+
+        let mut exporter = ParquetExporter::create(&path, 2).unwrap();
+        exporter
+            .write(&[(passage("p1"), vec![1.0, 0.0]), (passage("p2"), vec![0.0, 1.0])])
+            .unwrap();
+        assert!(exporter.write(&[(passage("p3"), vec![1.0])]).is_err());
+        exporter.write(&[(passage("p4"), vec![0.6, 0.8])]).unwrap();
+        exporter.finish().unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
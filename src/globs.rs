@@ -0,0 +1,108 @@
+//! Glob include/exclude patterns for choosing which files to index
+//!
+//! Patterns match paths relative to the `--docs` directory they were found
+//! in, using `/` as the separator: `*` stays within a directory, `**` crosses
+//! directories, and a pattern without `/` (e.g. `*.min.js`) matches the file
+//! name at any depth, as in `.gitignore`.
+
+use std::path::Path;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Include and exclude glob patterns
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    /// Files must match one of these, if any are given
+    include: Option<GlobSet>,
+    /// Files matching any of these are skipped
+    exclude: Option<GlobSet>,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            include: build_set(include)?,
+            exclude: build_set(exclude)?,
+        })
+    }
+
+    /// Whether `path`, found under `root`, passes the patterns
+    ///
+    /// A path matches if either its path relative to `root` or the path as
+    /// given (without a leading `./`) matches, so files passed directly to
+    /// `--docs` can be matched too.
+    pub fn allows(&self, path: &Path, root: &Path) -> bool {
+        let relative = path.strip_prefix(root).ok().filter(|p| !p.as_os_str().is_empty());
+        let given = path.strip_prefix(".").unwrap_or(path);
+        let matches = |set: &GlobSet| {
+            relative.is_some_and(|p| set.is_match(p)) || set.is_match(given)
+        };
+
+        if self.exclude.as_ref().is_some_and(&matches) {
+            return false;
+        }
+        self.include.as_ref().is_none_or(matches)
+    }
+}
+
+fn build_set(patterns: &[String]) -> anyhow::Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim().trim_start_matches("./");
+        // Like .gitignore: no separator means the file name at any depth
+        let pattern = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+        let glob = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid glob pattern '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+    Ok(Some(builder.build()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let strings = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        PathFilter::new(&strings(include), &strings(exclude)).unwrap()
+    }
+
+    #[test]
+    fn test_include_exclude() {
+        let root = Path::new("/repo");
+        let f = filter(&["src/**/*.rs", "README.md"], &["**/generated/**"]);
+        assert!(f.allows(Path::new("/repo/src/main.rs"), root));
+        assert!(f.allows(Path::new("/repo/src/cli/build.rs"), root));
+        assert!(f.allows(Path::new("/repo/README.md"), root));
+        assert!(!f.allows(Path::new("/repo/docs/guide.md"), root));
+        assert!(!f.allows(Path::new("/repo/tests/it.rs"), root));
+        assert!(!f.allows(Path::new("/repo/src/generated/api.rs"), root));
+    }
+
+    #[test]
+    fn test_basename_patterns_and_empty_filter() {
+        let root = Path::new("./docs");
+        let f = filter(&[], &["*.min.js"]);
+        assert!(!f.allows(Path::new("./docs/js/app.min.js"), root));
+        assert!(f.allows(Path::new("./docs/js/app.js"), root));
+
+        // "*" doesn't cross directories
+        let f = filter(&["*.md"], &[]);
+        assert!(f.allows(Path::new("./docs/a/b/notes.md"), root));
+        let f = filter(&["docs/*.md"], &[]);
+        assert!(f.allows(Path::new("./docs/notes.md"), Path::new("./docs")));
+        assert!(!f.allows(Path::new("./docs/a/notes.md"), Path::new("./docs")));
+
+        assert!(PathFilter::default().allows(Path::new("anything.txt"), Path::new(".")));
+        assert!(PathFilter::new(&["src/[".to_string()], &[]).is_err());
+    }
+}
@@ -1,23 +1,108 @@
 //! HTTP utilities for API providers
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 use std::time::Duration;
 
-use reqwest::{Client, Response};
+use anyhow::Context;
+use reqwest::{Certificate, Client, NoProxy, Proxy, Response, Url};
 
-/// Create a reqwest client with connection pooling and sensible defaults
+use crate::config::HttpConfig;
+use crate::error::LeannError;
+
+/// Default OpenAI API base URL (for embeddings, chat, and transcription)
+pub const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+
+/// Set by `--offline` / `LEANN_OFFLINE=1`
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Client shared by every API provider, built from the `[http]` settings
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Forbid network access for the rest of the process
 ///
-/// The client is configured with:
-/// - Connection pooling (max 10 idle connections per host)
-/// - 30 second timeout
-/// - Keepalive connections
-pub fn create_client() -> Client {
-    Client::builder()
-        .pool_max_idle_per_host(10)
+/// Services on this machine (e.g. Ollama on localhost) stay reachable;
+/// anything else fails in `check_network`.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Whether offline mode is on
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Fail in offline mode unless `url` is on this machine, naming the
+/// `component` that needed the network
+pub fn check_network(component: &str, url: &str) -> anyhow::Result<()> {
+    if !is_offline() || is_loopback(url) {
+        return Ok(());
+    }
+    Err(LeannError::Offline { component: component.to_string(), url: url.to_string() }.into())
+}
+
+/// Whether `url` (with or without a scheme) points at this machine
+fn is_loopback(url: &str) -> bool {
+    let parsed = Url::parse(url)
+        .ok()
+        .filter(|u| u.has_host())
+        .or_else(|| Url::parse(&format!("http://{}", url)).ok());
+    let Some(host) = parsed.as_ref().and_then(|u| u.host_str()) else {
+        return false;
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .trim_matches(['[', ']'])
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Configure the client `create_client` returns for the rest of the process
+///
+/// Fails if the proxy URL or CA certificates are invalid, so a typo doesn't
+/// surface as an opaque connection error later.
+pub fn configure(settings: &HttpConfig) -> anyhow::Result<()> {
+    let client = build_client(settings)?;
+    let _ = CLIENT.set(client);
+    Ok(())
+}
+
+/// Build a reqwest client with connection pooling, timeouts, and the
+/// configured proxy and CA certificates
+///
+/// Defaults: 10 idle connections per host, 120 second request timeout,
+/// 30 second connect timeout, and proxies from the environment.
+fn build_client(settings: &HttpConfig) -> anyhow::Result<Client> {
+    let mut builder = Client::builder()
+        .pool_max_idle_per_host(settings.max_idle_per_host.unwrap_or(10))
         .pool_idle_timeout(Duration::from_secs(90))
-        .timeout(Duration::from_secs(120))
-        .connect_timeout(Duration::from_secs(30))
-        .build()
-        .expect("Failed to create HTTP client")
+        .timeout(Duration::from_secs(settings.timeout.unwrap_or(120)))
+        .connect_timeout(Duration::from_secs(settings.connect_timeout.unwrap_or(30)));
+
+    if let Some(proxy) = &settings.proxy {
+        let proxy = Proxy::all(proxy).with_context(|| format!("Invalid proxy URL: {}", proxy))?;
+        builder = builder.proxy(proxy.no_proxy(NoProxy::from_env()));
+    }
+
+    if let Some(path) = settings.ca_cert_path() {
+        let pem = std::fs::read(&path).with_context(|| format!("Failed to read CA certificate {}", path.display()))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .ok()
+            .filter(|certs| !certs.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("No PEM certificates in {}", path.display()))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    builder.build().context("Failed to create HTTP client")
+}
+
+/// The shared HTTP client (cheap to clone; clones share its connection pool)
+pub fn create_client() -> Client {
+    CLIENT
+        .get_or_init(|| build_client(&HttpConfig::default()).expect("Failed to create HTTP client"))
+        .clone()
 }
 
 /// Check HTTP response status and return detailed error if not successful
@@ -44,16 +129,52 @@ pub async fn check_response(response: Response, service_name: &str) -> anyhow::R
         body
     };
 
-    anyhow::bail!("{} API error {}: {}", service_name, status, error_detail)
+    Err(LeannError::provider(service_name, status.as_u16(), error_detail).into())
+}
+
+/// Error for a failed OpenAI client call, telling rejected API keys apart
+pub fn openai_error(service_name: &str, err: async_openai::error::OpenAIError) -> anyhow::Error {
+    match &err {
+        async_openai::error::OpenAIError::ApiError(api) if api.code.as_deref() == Some("invalid_api_key") => {
+            LeannError::provider(service_name, 401, api.message.clone()).into()
+        }
+        _ => err.into(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_loopback_hosts() {
+        assert!(is_loopback("http://localhost:11434"));
+        assert!(is_loopback("http://127.0.0.1:8080/v1"));
+        assert!(is_loopback("http://[::1]:1234"));
+        assert!(is_loopback("localhost:11434"));
+        assert!(!is_loopback("https://api.openai.com/v1"));
+        assert!(!is_loopback("http://192.168.1.20:11434"));
+    }
+
     #[test]
     fn test_create_client() {
         // Just verify it creates without panicking
         let _client = create_client();
     }
+
+    #[test]
+    fn test_client_settings() {
+        let settings = HttpConfig { timeout: Some(5), proxy: Some("http://proxy.local:3128".into()), ..Default::default() };
+        assert!(build_client(&settings).is_ok());
+
+        let bad_proxy = HttpConfig { proxy: Some("not a url".into()), ..Default::default() };
+        assert!(build_client(&bad_proxy).is_err());
+
+        let path = std::env::temp_dir().join(format!("leann-ca-{}.pem", std::process::id()));
+        std::fs::write(&path, "not a certificate").unwrap();
+        let bad_cert = HttpConfig { ca_cert: Some(path.clone()), ..Default::default() };
+        let err = build_client(&bad_cert).unwrap_err();
+        assert!(err.to_string().contains("No PEM certificates"));
+        std::fs::remove_file(&path).unwrap();
+    }
 }
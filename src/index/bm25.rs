@@ -3,7 +3,9 @@
 use std::sync::LazyLock;
 
 use regex::Regex;
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 /// BM25 parameters
 const K1: f32 = 1.2;
@@ -11,10 +13,269 @@ const B: f32 = 0.75;
 
 /// Cached regex for tokenization (compiled once)
 static TOKEN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"[a-zA-Z0-9]+").unwrap()
+    Regex::new(r"[\p{L}\p{N}]+").unwrap()
 });
 
+/// Runs of Chinese, Japanese, or Korean characters (written without spaces)
+static CJK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\p{Han}\p{Hiragana}\p{Katakana}\p{Hangul}]+").unwrap()
+});
+
+/// Identifiers, including underscores (code tokenizer)
+static IDENT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\p{L}\p{N}_]+").unwrap()
+});
+
+/// English stopwords (the Lucene default set)
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it", "no",
+    "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these", "they", "this",
+    "to", "was", "will", "with",
+];
+
+/// How text is split into BM25 terms
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bm25Tokenizer {
+    /// Letter/digit runs (suits space-separated languages)
+    #[default]
+    Words,
+    /// Words, with CJK runs split into overlapping character bigrams
+    Ngram,
+    /// Identifiers, also split at `snake_case` and `camelCase` boundaries
+    Code,
+}
+
+impl Bm25Tokenizer {
+    /// Tokenizer suited to a dominant language (ISO 639-3 code)
+    pub fn for_language(lang: &str) -> Self {
+        match lang {
+            "cmn" | "jpn" | "kor" => Self::Ngram,
+            _ => Self::Words,
+        }
+    }
+
+    fn tokenize(self, text: &str) -> Vec<String> {
+        match self {
+            Self::Words => tokenize(text),
+            Self::Ngram => tokenize_ngrams(text),
+            Self::Code => tokenize_code(text),
+        }
+    }
+}
+
+impl std::str::FromStr for Bm25Tokenizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "words" => Ok(Self::Words),
+            "ngram" => Ok(Self::Ngram),
+            "code" => Ok(Self::Code),
+            _ => Err(format!("Unknown BM25 tokenizer: {} (expected words, ngram, or code)", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Bm25Tokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Words => "words",
+            Self::Ngram => "ngram",
+            Self::Code => "code",
+        })
+    }
+}
+
+/// Passage field indexed next to the text, from the passage metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bm25Field {
+    /// File name of the `source` path (`src/index/searcher.rs` -> `searcher.rs`)
+    Filename,
+    /// Name of the function, class, or other code block (`name`)
+    Symbols,
+}
+
+impl Bm25Field {
+    /// This field's text for a passage, if its metadata has one
+    fn text(self, metadata: &Value) -> Option<&str> {
+        match self {
+            Self::Filename => {
+                let source = metadata.get("source")?.as_str()?;
+                source.rsplit(['/', '\\', '!']).next()
+            }
+            Self::Symbols => {
+                let name = metadata.get("name")?.as_str()?;
+                // Parts of a split block are named `<name>_part<n>`
+                Some(match name.rsplit_once("_part") {
+                    Some((base, part)) if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) => base,
+                    _ => name,
+                })
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Bm25Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Filename => "filename",
+            Self::Symbols => "symbols",
+        })
+    }
+}
+
+/// A field and how much one occurrence of a term in it counts, relative to
+/// one occurrence in the text
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bm25FieldWeight {
+    pub field: Bm25Field,
+    pub weight: f32,
+}
+
+/// Parse a `--bm25-fields` list such as `filename:2,symbols:3` ("none" for no fields)
+pub fn parse_bm25_fields(spec: &str) -> anyhow::Result<Vec<Bm25FieldWeight>> {
+    if spec.trim() == "none" {
+        return Ok(Vec::new());
+    }
+    spec.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|entry| {
+            let (name, weight) = entry.split_once(':').unwrap_or((entry, "1"));
+            let field = match name.trim() {
+                "filename" => Bm25Field::Filename,
+                "symbols" => Bm25Field::Symbols,
+                other => anyhow::bail!("Unknown BM25 field: {} (expected filename or symbols)", other),
+            };
+            let weight: f32 = weight
+                .trim()
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid weight '{}' for BM25 field {}", weight.trim(), field))?;
+            if !weight.is_finite() || weight <= 0.0 {
+                anyhow::bail!("BM25 field weight must be positive: {}", entry);
+            }
+            Ok(Bm25FieldWeight { field, weight })
+        })
+        .collect()
+}
+
+/// Text analysis for BM25: tokenizer, then stopword removal and stemming
+///
+/// Documents and queries go through the same analyzer, recorded in the index
+/// metadata at build time.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bm25Analyzer {
+    #[serde(default)]
+    pub tokenizer: Bm25Tokenizer,
+    /// Snowball stemmer language (e.g. "english"), if stemming
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stemmer: Option<String>,
+    /// Drop English stopwords
+    #[serde(default)]
+    pub stopwords: bool,
+    /// Metadata fields scored with the text, each with its weight
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Bm25FieldWeight>,
+}
+
+impl Bm25Analyzer {
+    pub fn new(tokenizer: Bm25Tokenizer) -> Self {
+        Self {
+            tokenizer,
+            ..Self::default()
+        }
+    }
+
+    /// Stem terms with the Snowball stemmer for a language (name or ISO 639-3 code)
+    pub fn with_stemmer(mut self, language: &str) -> anyhow::Result<Self> {
+        let (name, _) = stemmer_algorithm(language).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No stemmer for '{}' (available: {})",
+                language,
+                STEMMERS.iter().map(|(name, _, _)| *name).collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        self.stemmer = Some(name.to_string());
+        Ok(self)
+    }
+
+    /// Drop English stopwords ("the", "and", ...)
+    pub fn with_stopwords(mut self, stopwords: bool) -> Self {
+        self.stopwords = stopwords;
+        self
+    }
+
+    /// Score matches in metadata fields (file name, symbol names) as weighted
+    /// additions to the text
+    pub fn with_fields(mut self, fields: Vec<Bm25FieldWeight>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Split text into BM25 terms
+    pub fn analyze(&self, text: &str) -> Vec<String> {
+        let mut tokens = self.tokenizer.tokenize(text);
+        if self.stopwords {
+            tokens.retain(|t| !ENGLISH_STOPWORDS.contains(&t.as_str()));
+        }
+        if let Some((_, algorithm)) = self.stemmer.as_deref().and_then(stemmer_algorithm) {
+            let stemmer = rust_stemmers::Stemmer::create(algorithm);
+            for token in &mut tokens {
+                if let std::borrow::Cow::Owned(stem) = stemmer.stem(token) {
+                    *token = stem;
+                }
+            }
+        }
+        tokens
+    }
+}
+
+/// Snowball stemmers: (name, ISO 639-3 code, algorithm)
+const STEMMERS: &[(&str, &str, rust_stemmers::Algorithm)] = {
+    use rust_stemmers::Algorithm::*;
+    &[
+        ("arabic", "ara", Arabic),
+        ("danish", "dan", Danish),
+        ("dutch", "nld", Dutch),
+        ("english", "eng", English),
+        ("finnish", "fin", Finnish),
+        ("french", "fra", French),
+        ("german", "deu", German),
+        ("greek", "ell", Greek),
+        ("hungarian", "hun", Hungarian),
+        ("italian", "ita", Italian),
+        ("norwegian", "nob", Norwegian),
+        ("portuguese", "por", Portuguese),
+        ("romanian", "ron", Romanian),
+        ("russian", "rus", Russian),
+        ("spanish", "spa", Spanish),
+        ("swedish", "swe", Swedish),
+        ("tamil", "tam", Tamil),
+        ("turkish", "tur", Turkish),
+    ]
+};
+
+/// Stemmer for a language name or ISO 639-3 code
+fn stemmer_algorithm(language: &str) -> Option<(&'static str, rust_stemmers::Algorithm)> {
+    let language = language.to_lowercase();
+    STEMMERS
+        .iter()
+        .find(|(name, code, _)| *name == language || *code == language)
+        .map(|(name, _, algorithm)| (*name, *algorithm))
+}
+
+/// Whether a Snowball stemmer exists for a language (name or ISO 639-3 code)
+pub fn has_stemmer(language: &str) -> bool {
+    stemmer_algorithm(language).is_some()
+}
+
 /// Simple BM25 scorer
+///
+/// With analyzer fields, this is BM25F: a term's frequency in a document is
+/// its count in the text plus its weighted count in each field, and the
+/// document length is weighted the same way.
 pub struct Bm25Scorer {
     /// Document frequency: term -> number of documents containing term
     doc_freq: FxHashMap<String, usize>,
@@ -22,44 +283,60 @@ pub struct Bm25Scorer {
     num_docs: usize,
     /// Average document length
     avg_doc_len: f32,
-    /// Document lengths
-    doc_lengths: Vec<usize>,
-    /// Term frequencies per document: doc_id -> (term -> count)
-    term_freqs: Vec<FxHashMap<String, usize>>,
+    /// Document lengths (weighted)
+    doc_lengths: Vec<f32>,
+    /// Term frequencies per document: doc_id -> (term -> weighted count)
+    term_freqs: Vec<FxHashMap<String, f32>>,
+    /// Analyzer for documents and queries
+    analyzer: Bm25Analyzer,
+    /// Occurrences of each lowercased word in the text, before stemming or
+    /// stopword removal (for spelling correction)
+    vocabulary: FxHashMap<String, usize>,
 }
 
 impl Bm25Scorer {
-    /// Build a BM25 scorer from documents
-    pub fn build(documents: &[String]) -> Self {
+    /// Build a BM25 scorer from documents and their metadata, which supplies
+    /// the analyzer's fields (documents without metadata have none)
+    #[tracing::instrument(name = "bm25.index", skip_all, fields(documents = documents.len()))]
+    pub fn build(documents: &[String], metadata: &[Value], analyzer: &Bm25Analyzer) -> Self {
         let num_docs = documents.len();
         let mut doc_freq: FxHashMap<String, usize> = FxHashMap::default();
         let mut doc_lengths = Vec::with_capacity(num_docs);
         let mut term_freqs = Vec::with_capacity(num_docs);
-        let mut total_len = 0usize;
-
-        for doc in documents {
-            let tokens = tokenize(doc);
-            let doc_len = tokens.len();
-            doc_lengths.push(doc_len);
-            total_len += doc_len;
+        let mut total_len = 0.0f32;
+        let mut vocabulary: FxHashMap<String, usize> = FxHashMap::default();
 
-            let mut tf: FxHashMap<String, usize> = FxHashMap::default();
-            let mut seen: FxHashSet<String> = FxHashSet::default();
-
-            for token in tokens {
-                *tf.entry(token.clone()).or_insert(0) += 1;
-
-                if !seen.contains(&token) {
-                    *doc_freq.entry(token.clone()).or_insert(0) += 1;
-                    seen.insert(token);
+        for (i, doc) in documents.iter().enumerate() {
+            for word in tokenize(doc) {
+                *vocabulary.entry(word).or_insert(0) += 1;
+            }
+            let mut weighted: Vec<(Vec<String>, f32)> = vec![(analyzer.analyze(doc), 1.0)];
+            if let Some(meta) = metadata.get(i) {
+                for field in &analyzer.fields {
+                    if let Some(text) = field.field.text(meta) {
+                        weighted.push((analyzer.analyze(text), field.weight));
+                    }
                 }
             }
 
+            let mut doc_len = 0.0f32;
+            let mut tf: FxHashMap<String, f32> = FxHashMap::default();
+            for (tokens, weight) in weighted {
+                doc_len += tokens.len() as f32 * weight;
+                for token in tokens {
+                    *tf.entry(token).or_insert(0.0) += weight;
+                }
+            }
+            for token in tf.keys() {
+                *doc_freq.entry(token.clone()).or_insert(0) += 1;
+            }
+            doc_lengths.push(doc_len);
+            total_len += doc_len;
             term_freqs.push(tf);
         }
 
-        let avg_doc_len = if num_docs > 0 {
-            total_len as f32 / num_docs as f32
+        let avg_doc_len = if num_docs > 0 && total_len > 0.0 {
+            total_len / num_docs as f32
         } else {
             1.0
         };
@@ -70,12 +347,20 @@ impl Bm25Scorer {
             avg_doc_len,
             doc_lengths,
             term_freqs,
+            analyzer: analyzer.clone(),
+            vocabulary,
         }
     }
 
+    /// Words of the documents and how often each occurs
+    pub fn vocabulary(&self) -> &FxHashMap<String, usize> {
+        &self.vocabulary
+    }
+
     /// Score a query against all documents
+    #[tracing::instrument(name = "bm25.score", skip_all, fields(documents = self.num_docs))]
     pub fn score_query(&self, query: &str) -> Vec<f32> {
-        let query_tokens = tokenize(query);
+        let query_tokens = self.analyzer.analyze(query);
         let mut scores = vec![0.0f32; self.num_docs];
 
         for token in &query_tokens {
@@ -88,12 +373,12 @@ impl Bm25Scorer {
             let idf = ((self.num_docs as f32 - df + 0.5) / (df + 0.5) + 1.0).ln();
 
             for (doc_id, tf_map) in self.term_freqs.iter().enumerate() {
-                let tf = *tf_map.get(token).unwrap_or(&0) as f32;
+                let tf = *tf_map.get(token).unwrap_or(&0.0);
                 if tf == 0.0 {
                     continue;
                 }
 
-                let doc_len = self.doc_lengths[doc_id] as f32;
+                let doc_len = self.doc_lengths[doc_id];
                 let norm = 1.0 - B + B * (doc_len / self.avg_doc_len);
 
                 // BM25 score component
@@ -127,10 +412,65 @@ impl Bm25Scorer {
 fn tokenize(text: &str) -> Vec<String> {
     TOKEN_REGEX.find_iter(text)
         .map(|m| m.as_str().to_lowercase())
-        .filter(|s| s.len() > 1) // Skip single-character tokens
+        .filter(|s| s.chars().nth(1).is_some()) // Skip single-character tokens
         .collect()
 }
 
+/// Word tokenization, with CJK runs split into character bigrams instead
+/// (a lone CJK character is kept as a unigram)
+fn tokenize_ngrams(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut last = 0;
+    for run in CJK_REGEX.find_iter(text) {
+        tokens.extend(tokenize(&text[last..run.start()]));
+        let chars: Vec<char> = run.as_str().chars().collect();
+        if chars.len() == 1 {
+            tokens.push(chars[0].to_string());
+        }
+        tokens.extend(chars.windows(2).map(|pair| pair.iter().collect::<String>()));
+        last = run.end();
+    }
+    tokens.extend(tokenize(&text[last..]));
+    tokens
+}
+
+/// Code tokenization: each identifier, plus its `snake_case`/`camelCase` parts
+/// (`IndexSearcher` -> `indexsearcher`, `index`, `searcher`)
+fn tokenize_code(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for ident in IDENT_REGEX.find_iter(text) {
+        let parts = split_identifier(ident.as_str());
+        if parts.len() > 1 {
+            tokens.push(ident.as_str().trim_matches('_').to_lowercase());
+        }
+        tokens.extend(parts);
+    }
+    tokens.retain(|t| t.chars().nth(1).is_some());
+    tokens
+}
+
+/// Lowercased words of an identifier, split at underscores and case changes
+/// (`parseHTTPResponse_v2` -> `parse`, `http`, `response`, `v2`)
+fn split_identifier(ident: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    for segment in ident.split('_').filter(|s| !s.is_empty()) {
+        let chars: Vec<char> = segment.chars().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (prev, cur) = (chars[i - 1], chars[i]);
+            let next_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            let boundary = cur.is_uppercase()
+                && (prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower));
+            if boundary {
+                parts.push(chars[start..i].iter().collect::<String>().to_lowercase());
+                start = i;
+            }
+        }
+        parts.push(chars[start..].iter().collect::<String>().to_lowercase());
+    }
+    parts
+}
+
 /// Combine vector scores with BM25 scores
 pub fn hybrid_rerank(
     vector_results: &[(usize, f32)],
@@ -196,6 +536,81 @@ mod tests {
         assert!(tokens.contains(&"456abc".to_string()));
     }
 
+    #[test]
+    fn test_tokenize_ngrams() {
+        assert_eq!(
+            tokenize_ngrams("检索增强 RAG 系统"),
+            vec!["检索", "索增", "增强", "rag", "系统"]
+        );
+        assert_eq!(tokenize_ngrams("東 index"), vec!["東", "index"]);
+        // Without n-grams a CJK run is one token, so partial matches fail
+        assert_eq!(tokenize("检索增强"), vec!["检索增强"]);
+
+        let docs = vec!["检索增强生成系统".to_string(), "向量数据库".to_string()];
+        let scorer = Bm25Scorer::build(&docs, &[], &Bm25Analyzer::new(Bm25Tokenizer::Ngram));
+        let scores = scorer.score_query("增强生成");
+        assert!(scores[0] > 0.0);
+        assert_eq!(scores[1], 0.0);
+        assert_eq!(Bm25Tokenizer::for_language("jpn"), Bm25Tokenizer::Ngram);
+    }
+
+    #[test]
+    fn test_tokenize_code() {
+        assert_eq!(split_identifier("parseHTTPResponse_v2"), vec!["parse", "http", "response", "v2"]);
+        assert_eq!(
+            tokenize_code("let searcher = IndexSearcher::load(index_path);"),
+            vec!["let", "searcher", "indexsearcher", "index", "searcher", "load", "index_path", "index", "path"]
+        );
+
+        let docs = vec!["fn build_field_index() {}".to_string(), "struct FieldMap;".to_string()];
+        let scorer = Bm25Scorer::build(&docs, &[], &Bm25Analyzer::new(Bm25Tokenizer::Code));
+        let scores = scorer.score_query("FieldIndex");
+        assert!(scores[0] > scores[1]);
+    }
+
+    #[test]
+    fn test_analyzer_stemming_and_stopwords() {
+        let analyzer = Bm25Analyzer::default().with_stemmer("eng").unwrap().with_stopwords(true);
+        assert_eq!(analyzer.stemmer.as_deref(), Some("english"));
+        assert_eq!(analyzer.analyze("The indexes are running"), vec!["index", "run"]);
+        assert!(Bm25Analyzer::default().with_stemmer("klingon").is_err());
+
+        let docs = vec!["Indexing documents quickly".to_string(), "A quick brown fox".to_string()];
+        let scorer = Bm25Scorer::build(&docs, &[], &analyzer);
+        assert!(scorer.score_query("indexed document")[0] > 0.0);
+    }
+
+    #[test]
+    fn test_bm25_fields() {
+        let fields = parse_bm25_fields("filename:2, symbols:3").unwrap();
+        assert_eq!(fields, [
+            Bm25FieldWeight { field: Bm25Field::Filename, weight: 2.0 },
+            Bm25FieldWeight { field: Bm25Field::Symbols, weight: 3.0 },
+        ]);
+        assert!(parse_bm25_fields("none").unwrap().is_empty());
+        assert!(parse_bm25_fields("title:2").is_err());
+        assert!(parse_bm25_fields("filename:0").is_err());
+
+        assert_eq!(Bm25Field::Filename.text(&serde_json::json!({"source": "src/index/searcher.rs"})), Some("searcher.rs"));
+        assert_eq!(Bm25Field::Symbols.text(&serde_json::json!({"name": "load_part2"})), Some("load"));
+        assert_eq!(Bm25Field::Symbols.text(&serde_json::json!({"name": "sub_partition"})), Some("sub_partition"));
+
+        // Both passages mention the searcher once; only one is the searcher
+        let docs = vec![
+            "Uses the searcher to rank results".to_string(),
+            "Searcher loads the index and runs queries".to_string(),
+        ];
+        let metadata = vec![
+            serde_json::json!({"source": "src/cli/search.rs", "name": "run"}),
+            serde_json::json!({"source": "src/index/searcher.rs", "name": "IndexSearcher"}),
+        ];
+        let analyzer = Bm25Analyzer::new(Bm25Tokenizer::Code);
+        let plain = Bm25Scorer::build(&docs, &metadata, &analyzer).score_query("searcher");
+        assert!(plain[0] > plain[1]);
+        let fielded = Bm25Scorer::build(&docs, &metadata, &analyzer.with_fields(fields)).score_query("searcher");
+        assert!(fielded[1] > fielded[0]);
+    }
+
     #[test]
     fn test_bm25_basic_scoring() {
         let docs = vec![
@@ -204,7 +619,7 @@ mod tests {
             "the dog chases the fox around the yard".to_string(),
         ];
 
-        let scorer = Bm25Scorer::build(&docs);
+        let scorer = Bm25Scorer::build(&docs, &[], &Bm25Analyzer::default());
         let results = scorer.search("quick fox", 3);
 
         assert!(!results.is_empty());
@@ -219,7 +634,7 @@ mod tests {
             "rust programming".to_string(),            // 1x "rust"
         ];
 
-        let scorer = Bm25Scorer::build(&docs);
+        let scorer = Bm25Scorer::build(&docs, &[], &Bm25Analyzer::default());
         let scores = scorer.score_query("rust");
 
         // Doc with more "rust" should score higher
@@ -234,7 +649,7 @@ mod tests {
             "common".to_string(),
         ];
 
-        let scorer = Bm25Scorer::build(&docs);
+        let scorer = Bm25Scorer::build(&docs, &[], &Bm25Analyzer::default());
         let scores = scorer.score_query("rare");
 
         // Only first doc has "rare", so only it should score
@@ -246,7 +661,7 @@ mod tests {
     #[test]
     fn test_bm25_empty_query() {
         let docs = vec!["hello world".to_string()];
-        let scorer = Bm25Scorer::build(&docs);
+        let scorer = Bm25Scorer::build(&docs, &[], &Bm25Analyzer::default());
         let scores = scorer.score_query("");
 
         assert_eq!(scores[0], 0.0);
@@ -255,7 +670,7 @@ mod tests {
     #[test]
     fn test_bm25_no_match() {
         let docs = vec!["hello world".to_string()];
-        let scorer = Bm25Scorer::build(&docs);
+        let scorer = Bm25Scorer::build(&docs, &[], &Bm25Analyzer::default());
         let results = scorer.search("xyz", 5);
 
         assert!(results.is_empty());
@@ -270,7 +685,7 @@ mod tests {
             "apple apple apple".to_string(),
         ];
 
-        let scorer = Bm25Scorer::build(&docs);
+        let scorer = Bm25Scorer::build(&docs, &[], &Bm25Analyzer::default());
         let results = scorer.search("apple", 2);
 
         // Should return only top 2
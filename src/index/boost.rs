@@ -0,0 +1,133 @@
+//! Query-time score boosts by metadata
+//!
+//! A boost expression is a comma-separated list of `filter:weight` rules, e.g.
+//! `source^src/:1.5,modified>now-30d:1.2`. Each rule's filter uses the
+//! `--filter` syntax; a result's score is multiplied by the weight of every
+//! rule it matches, so recent files or core directories rank higher without
+//! re-embedding anything. Within a rule, combine conditions with `AND` rather
+//! than `,`, which separates rules.
+
+use serde_json::Value;
+
+use super::filter::MetadataFilter;
+
+/// One `filter:weight` rule
+#[derive(Debug, Clone)]
+struct BoostRule {
+    /// The rule as written, for explanations
+    source: String,
+    filter: MetadataFilter,
+    weight: f32,
+}
+
+/// Multiplicative score boosts for results matching metadata filters
+#[derive(Debug, Clone)]
+pub struct Boost {
+    rules: Vec<BoostRule>,
+}
+
+impl std::fmt::Display for Boost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rules: Vec<&str> = self.rules.iter().map(|rule| rule.source.as_str()).collect();
+        f.write_str(&rules.join(","))
+    }
+}
+
+impl Boost {
+    /// Parse a boost expression such as `source^src/:1.5,modified>now-30d:1.2`
+    pub fn parse(expr: &str) -> anyhow::Result<Self> {
+        let rules = split_rules(expr)
+            .into_iter()
+            .map(|rule| {
+                let (filter, weight) = rule
+                    .rsplit_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("Boost rule '{}' has no weight (e.g. 'source^src/:1.5')", rule))?;
+                let weight: f32 = weight
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid weight '{}' in boost rule '{}'", weight.trim(), rule))?;
+                if !weight.is_finite() || weight <= 0.0 {
+                    anyhow::bail!("Boost weight must be positive in rule '{}'", rule);
+                }
+                let filter = MetadataFilter::try_parse(filter)
+                    .map_err(|e| anyhow::anyhow!("Invalid filter in boost rule '{}': {}", rule, e))?;
+                Ok(BoostRule { source: rule.to_string(), filter, weight })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if rules.is_empty() {
+            anyhow::bail!("Empty boost expression");
+        }
+        Ok(Self { rules })
+    }
+
+    /// Product of the weights of the rules `metadata` matches (1.0 if none)
+    pub fn factor(&self, metadata: &Value) -> f32 {
+        self.rules
+            .iter()
+            .filter(|rule| rule.filter.matches(metadata))
+            .map(|rule| rule.weight)
+            .product()
+    }
+
+    /// The rules `metadata` matches, as written
+    pub fn matching(&self, metadata: &Value) -> Vec<&str> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.filter.matches(metadata))
+            .map(|rule| rule.source.as_str())
+            .collect()
+    }
+}
+
+/// Split at commas outside brackets, parentheses, and quotes
+fn split_rules(expr: &str) -> Vec<&str> {
+    let mut rules = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    for (i, c) in expr.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                rules.push(expr[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    rules.push(expr[start..].trim());
+    rules.retain(|rule| !rule.is_empty());
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_boost_factor() {
+        let boost = Boost::parse("source^src/:1.5, type in [code,test]:2,lines>100:0.5").unwrap();
+        assert_eq!(boost.rules.len(), 3);
+        assert_eq!(boost.factor(&json!({"source": "src/main.rs", "type": "code", "lines": 10})), 3.0);
+        assert_eq!(boost.factor(&json!({"source": "docs/a.md", "lines": 400})), 0.5);
+        assert_eq!(boost.factor(&json!({"source": "docs/a.md"})), 1.0);
+        assert_eq!(boost.matching(&json!({"source": "docs/a.md", "lines": 400})), ["lines>100:0.5"]);
+
+        // Glob filters contain ':' themselves; the weight follows the last one
+        let boost = Boost::parse("source:*.rs:1.2").unwrap();
+        assert_eq!(boost.factor(&json!({"source": "lib.rs"})), 1.2);
+    }
+
+    #[test]
+    fn test_boost_parse_errors() {
+        assert!(Boost::parse("").is_err());
+        assert!(Boost::parse("source^src/").is_err());
+        assert!(Boost::parse("source^src/:fast").is_err());
+        assert!(Boost::parse("source^src/:0").is_err());
+    }
+}
@@ -2,20 +2,24 @@
 
 use std::io::{BufWriter, Write};
 use std::path::Path;
+use std::sync::Arc;
 
 use tracing::info;
 
-use crate::backend::{BackendBuilder, BackendType};
+use crate::backend::{Backend, BackendError, BuildParams, DiskAnnParams, ShardManifest, ShardPlan};
+use crate::error::LeannError;
 
 use super::embeddings::EmbeddingsWriter;
-use super::passages::{Passage, PassageStore, PassageStoreWriter};
+use super::field_index::FieldIndex;
+use super::multi_vector::MultiVectorWriter;
+use super::passages::{Passage, PassageFormat, PassageStore, PassageStoreWriter};
+use super::sparse::SparseIndex;
 
 /// Builder for creating a LEANN index (in-memory, for small datasets)
 pub struct IndexBuilder {
-    backend_type: BackendType,
+    backend: Arc<dyn Backend>,
     dimensions: usize,
-    graph_degree: usize,
-    complexity: usize,
+    params: BuildParams,
     passages: Vec<Passage>,
     embeddings: Vec<Vec<f32>>,
     ids: Vec<String>,
@@ -26,16 +30,19 @@ pub struct IndexBuilder {
 impl IndexBuilder {
     /// Create a new index builder
     pub fn new(
-        backend_type: BackendType,
+        backend: Arc<dyn Backend>,
         dimensions: usize,
         graph_degree: usize,
         complexity: usize,
     ) -> Self {
         Self {
-            backend_type,
+            backend,
             dimensions,
-            graph_degree,
-            complexity,
+            params: BuildParams {
+                graph_degree,
+                complexity,
+                ..Default::default()
+            },
             passages: Vec::new(),
             embeddings: Vec::new(),
             ids: Vec::new(),
@@ -63,11 +70,12 @@ impl IndexBuilder {
         metadata: serde_json::Value,
     ) -> anyhow::Result<()> {
         if embedding.len() != self.dimensions {
-            anyhow::bail!(
-                "Embedding dimension mismatch: expected {}, got {}",
-                self.dimensions,
-                embedding.len()
-            );
+            return Err(LeannError::DimensionMismatch {
+                expected: self.dimensions,
+                actual: embedding.len(),
+                source_name: "embedding",
+            }
+            .into());
         }
 
         self.passages.push(Passage {
@@ -82,6 +90,7 @@ impl IndexBuilder {
     }
 
     /// Build and save the index
+    #[tracing::instrument(name = "index.build", skip_all, fields(passages = self.passages.len()))]
     pub fn build(self, index_path: &Path) -> anyhow::Result<()> {
         info!(
             "Building index with {} passages, {} dimensions{}",
@@ -102,6 +111,13 @@ impl IndexBuilder {
         let ids_content = self.ids.join("\n");
         std::fs::write(&ids_path, ids_content)?;
 
+        // Write per-field value indexes for pre-filtering
+        let mut field_index = FieldIndex::default();
+        for passage in &self.passages {
+            field_index.add(&passage.metadata);
+        }
+        field_index.save(index_path)?;
+
         // In recompute mode, save embeddings to a separate file
         if self.recompute_mode {
             let embeddings_path = index_path.with_extension("embeddings");
@@ -114,44 +130,62 @@ impl IndexBuilder {
         }
 
         // Build vector index using backend
-        let backend = BackendBuilder::new(self.backend_type);
-        backend.build(
-            &self.embeddings,
-            &self.ids,
-            index_path,
-            self.dimensions,
-            self.graph_degree,
-            self.complexity,
-        )?;
+        self.backend
+            .build(&self.embeddings, &self.ids, index_path, self.dimensions, &self.params)?;
 
         info!("Index built successfully at {:?}", index_path);
         Ok(())
     }
 }
 
+/// Build a passage that keeps only a content hash in place of its text
+pub fn text_free_passage(id: &str, text: &str, mut metadata: serde_json::Value) -> Passage {
+    if let Some(obj) = metadata.as_object_mut() {
+        obj.insert(
+            "text_hash".to_string(),
+            crate::chunker::content_hash(text).into(),
+        );
+    }
+    Passage {
+        id: id.to_string(),
+        text: String::new(),
+        metadata,
+    }
+}
+
 /// Streaming builder that writes passages to disk as they come in,
 /// only keeping embeddings in memory for final index build.
 /// This reduces memory usage significantly for large datasets.
 pub struct StreamingIndexBuilder {
-    backend_type: BackendType,
+    backend: Arc<dyn Backend>,
     dimensions: usize,
-    graph_degree: usize,
-    complexity: usize,
+    params: BuildParams,
     recompute_mode: bool,
+    /// Whether passage text is persisted (false = text-free index)
+    store_text: bool,
     index_path: std::path::PathBuf,
     passage_writer: PassageStoreWriter,
     embeddings_writer: Option<EmbeddingsWriter>,
     ids_writer: BufWriter<std::fs::File>,
+    field_index: FieldIndex,
+    /// Learned sparse vectors, if enabled
+    sparse_index: Option<SparseIndex>,
+    /// Per-token vectors for late-interaction reranking, if enabled
+    multi_vector_writer: Option<MultiVectorWriter>,
     /// Only store embeddings in memory for final HNSW build
     embeddings: Vec<Vec<f32>>,
     ids: Vec<String>,
     count: usize,
+    /// How to split the graph into shards, if at all
+    shard_plan: Option<ShardPlan>,
+    /// Shards built so far (their embeddings are no longer held in memory)
+    shards: ShardManifest,
 }
 
 impl StreamingIndexBuilder {
     /// Create a streaming builder that writes to disk incrementally
     pub fn new(
-        backend_type: BackendType,
+        backend: Arc<dyn Backend>,
         dimensions: usize,
         graph_degree: usize,
         complexity: usize,
@@ -177,21 +211,136 @@ impl StreamingIndexBuilder {
         let ids_writer = BufWriter::new(ids_file);
 
         Ok(Self {
-            backend_type,
+            backend,
             dimensions,
-            graph_degree,
-            complexity,
+            params: BuildParams {
+                graph_degree,
+                complexity,
+                ..Default::default()
+            },
             recompute_mode,
+            store_text: true,
             index_path: index_path.to_path_buf(),
             passage_writer,
             embeddings_writer,
             ids_writer,
+            field_index: FieldIndex::default(),
+            sparse_index: None,
+            multi_vector_writer: None,
             embeddings: Vec::new(),
             ids: Vec::new(),
             count: 0,
+            shard_plan: None,
+            shards: ShardManifest::default(),
         })
     }
 
+    /// Split the vector index into shards, if the backend supports it
+    ///
+    /// With `ShardPlan::MaxPassages`, each shard's graph is built as soon as
+    /// it fills and its embeddings are dropped, so memory stays bounded by
+    /// the shard size.
+    pub fn with_shard_plan(mut self, plan: Option<ShardPlan>) -> anyhow::Result<Self> {
+        if plan.is_some() && !self.backend.info().supports_sharding {
+            return Err(BackendError::unsupported(self.backend.info(), "sharding").into());
+        }
+        if matches!(plan, Some(ShardPlan::Count(0) | ShardPlan::MaxPassages(0))) {
+            anyhow::bail!("Shard count and size must be at least 1");
+        }
+        self.shards.max_passages = match plan {
+            Some(ShardPlan::MaxPassages(max)) => Some(max),
+            _ => None,
+        };
+        self.shard_plan = plan;
+        Ok(self)
+    }
+
+    /// Set the pruning and beam parameters of a DiskANN graph
+    pub fn with_diskann_params(mut self, params: DiskAnnParams) -> Self {
+        self.params.diskann = params;
+        self
+    }
+
+    /// Build a shard from the embeddings held in memory and drop them
+    fn flush_shard(&mut self) -> anyhow::Result<()> {
+        let start = self.count - self.embeddings.len();
+        let shard = self.backend.build_shard(
+            &self.embeddings,
+            &self.index_path,
+            self.shards.shards.len(),
+            start,
+            self.dimensions,
+            &self.params,
+        )?;
+        self.shards.shards.push(shard);
+        self.embeddings = Vec::new();
+        self.ids.clear();
+        Ok(())
+    }
+
+    /// Set whether passage text is persisted
+    ///
+    /// When disabled, only metadata, a content hash, and vectors are stored;
+    /// text is re-read from the source file at query time.
+    pub fn with_store_text(mut self, enabled: bool) -> Self {
+        self.store_text = enabled;
+        self
+    }
+
+    /// Set the passage store format (JSONL by default)
+    pub fn with_passage_format(mut self, format: PassageFormat) -> anyhow::Result<Self> {
+        if format != PassageFormat::Jsonl {
+            self.passage_writer = PassageStore::create_with_format(&self.index_path, format)?;
+        }
+        Ok(self)
+    }
+
+    /// Set whether a learned sparse vector is stored per passage (see `add_sparse_vector`)
+    pub fn with_sparse_index(mut self, enabled: bool) -> Self {
+        self.sparse_index = enabled.then(SparseIndex::default);
+        self
+    }
+
+    /// Add the sparse vector of the passage just added with `add_passage`
+    pub fn add_sparse_vector(&mut self, vector: &[(u32, f32)]) -> anyhow::Result<()> {
+        let Some(sparse_index) = self.sparse_index.as_mut() else {
+            anyhow::bail!("Sparse vectors are not enabled for this index");
+        };
+        if sparse_index.len() + 1 != self.count {
+            anyhow::bail!(
+                "Sparse vector added for passage {} but {} passages were added",
+                sparse_index.len() + 1,
+                self.count
+            );
+        }
+        sparse_index.add(vector);
+        Ok(())
+    }
+
+    /// Store per-token vectors of this size per passage (see `add_token_vectors`)
+    pub fn with_multi_vector(mut self, dimensions: Option<usize>) -> anyhow::Result<Self> {
+        self.multi_vector_writer = match dimensions {
+            Some(dimensions) => Some(MultiVectorWriter::create(&self.index_path, dimensions)?),
+            None => None,
+        };
+        Ok(self)
+    }
+
+    /// Add the per-token vectors of the passage just added with `add_passage`
+    pub fn add_token_vectors(&mut self, tokens: &[Vec<f32>]) -> anyhow::Result<()> {
+        let Some(writer) = self.multi_vector_writer.as_mut() else {
+            anyhow::bail!("Multi-vector storage is not enabled for this index");
+        };
+        if writer.len() + 1 != self.count {
+            anyhow::bail!(
+                "Token vectors added for passage {} but {} passages were added",
+                writer.len() + 1,
+                self.count
+            );
+        }
+        writer.add(tokens)
+    }
+
     /// Add a passage - writes to disk immediately, keeps embedding in memory
     pub fn add_passage(
         &mut self,
@@ -199,22 +348,51 @@ impl StreamingIndexBuilder {
         text: &str,
         embedding: &[f32],
         metadata: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        self.push_passage(id, text, embedding, metadata, self.store_text)
+    }
+
+    /// Add a generated passage (e.g. a summary), which keeps its text even in
+    /// a text-free index, as there's no source to re-read it from
+    pub fn add_generated_passage(
+        &mut self,
+        id: &str,
+        text: &str,
+        embedding: &[f32],
+        metadata: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        self.push_passage(id, text, embedding, metadata, true)
+    }
+
+    fn push_passage(
+        &mut self,
+        id: &str,
+        text: &str,
+        embedding: &[f32],
+        metadata: serde_json::Value,
+        store_text: bool,
     ) -> anyhow::Result<()> {
         if embedding.len() != self.dimensions {
-            anyhow::bail!(
-                "Embedding dimension mismatch: expected {}, got {}",
-                self.dimensions,
-                embedding.len()
-            );
+            return Err(LeannError::DimensionMismatch {
+                expected: self.dimensions,
+                actual: embedding.len(),
+                source_name: "embedding",
+            }
+            .into());
         }
 
         // Write passage to disk immediately
-        let passage = Passage {
-            id: id.to_string(),
-            text: text.to_string(),
-            metadata,
+        let passage = if store_text {
+            Passage {
+                id: id.to_string(),
+                text: text.to_string(),
+                metadata,
+            }
+        } else {
+            text_free_passage(id, text, metadata)
         };
         self.passage_writer.add(&passage)?;
+        self.field_index.add(&passage.metadata);
 
         // Write ID to disk
         if self.count > 0 {
@@ -232,10 +410,15 @@ impl StreamingIndexBuilder {
         self.ids.push(id.to_string());
         self.count += 1;
 
+        if self.shard_plan == Some(ShardPlan::MaxPassages(self.embeddings.len())) {
+            self.flush_shard()?;
+        }
+
         Ok(())
     }
 
     /// Finalize and build the index
+    #[tracing::instrument(name = "index.build", skip_all, fields(passages = self.count))]
     pub fn build(mut self) -> anyhow::Result<()> {
         info!(
             "Building index with {} passages, {} dimensions{}",
@@ -247,22 +430,73 @@ impl StreamingIndexBuilder {
         // Flush all writers
         self.passage_writer.finish()?;
         self.ids_writer.flush()?;
+        self.field_index.save(&self.index_path)?;
+
+        if let Some(sparse_index) = &self.sparse_index {
+            if sparse_index.len() != self.count {
+                anyhow::bail!(
+                    "Sparse vectors missing: {} of {} passages have one",
+                    sparse_index.len(),
+                    self.count
+                );
+            }
+            sparse_index.save(&self.index_path)?;
+            info!("Sparse index saved to {:?}", self.index_path.with_extension("sparse"));
+        }
+
+        if let Some(writer) = self.multi_vector_writer {
+            if writer.len() != self.count {
+                anyhow::bail!(
+                    "Token vectors missing: {} of {} passages have them",
+                    writer.len(),
+                    self.count
+                );
+            }
+            writer.finish()?;
+            info!("Token vectors saved to {:?}", self.index_path.with_extension("colbert"));
+        }
 
         if let Some(writer) = self.embeddings_writer {
             writer.finish()?;
             info!("Embeddings saved to {:?}", self.index_path.with_extension("embeddings"));
         }
 
-        // Build vector index using backend
-        let backend = BackendBuilder::new(self.backend_type);
-        backend.build(
-            &self.embeddings,
-            &self.ids,
-            &self.index_path,
-            self.dimensions,
-            self.graph_degree,
-            self.complexity,
-        )?;
+        match self.shard_plan {
+            Some(ShardPlan::Count(shards)) => {
+                for (shard, (start, count)) in ShardPlan::Count(shards).split(self.count).into_iter().enumerate() {
+                    let info = self.backend.build_shard(
+                        &self.embeddings[start..start + count],
+                        &self.index_path,
+                        shard,
+                        start,
+                        self.dimensions,
+                        &self.params,
+                    )?;
+                    self.shards.shards.push(info);
+                }
+                self.shards.save(&self.index_path)?;
+            }
+            Some(ShardPlan::MaxPassages(_)) => {
+                // The last, partly filled shard
+                if !self.embeddings.is_empty() {
+                    let info = self.backend.build_shard(
+                        &self.embeddings,
+                        &self.index_path,
+                        self.shards.shards.len(),
+                        self.count - self.embeddings.len(),
+                        self.dimensions,
+                        &self.params,
+                    )?;
+                    self.shards.shards.push(info);
+                }
+                self.shards.save(&self.index_path)?;
+            }
+            None => {
+                // Build vector index using backend
+                self.backend
+                    .build(&self.embeddings, &self.ids, &self.index_path, self.dimensions, &self.params)?;
+            }
+        }
 
         info!("Index built successfully at {:?}", self.index_path);
         Ok(())
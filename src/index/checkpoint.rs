@@ -0,0 +1,187 @@
+//! Embedding checkpoints - embeddings kept from a build that didn't finish
+//!
+//! Builds, updates, and re-embeds append every embedded batch to
+//! `<index>.checkpoint` next to the index directory, keyed by passage id. When
+//! one is cancelled or fails, running it again reuses those vectors and only
+//! embeds the rest; the file is removed once the index is written.
+//!
+//! The file is a JSON header line naming the model and embedding settings,
+//! followed by records of a little-endian u32 id length, the id, and the
+//! vector as little-endian f32s. A checkpoint for other settings is discarded,
+//! and a record cut short by the interruption is dropped.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Checkpoint of the index at `index_dir`
+pub fn checkpoint_path(index_dir: &Path) -> PathBuf {
+    let mut name = index_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(".checkpoint");
+    index_dir.with_file_name(name)
+}
+
+/// What the checkpointed vectors were embedded with; vectors are only reused
+/// by an operation with the same header
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointHeader {
+    pub model: String,
+    pub dimensions: usize,
+    /// Prompt templates and other settings changing the vectors
+    pub settings: serde_json::Value,
+}
+
+/// Embeddings reused from an earlier attempt, and the file new ones are appended to
+pub struct EmbeddingCheckpoint {
+    path: PathBuf,
+    dimensions: usize,
+    vectors: HashMap<String, Vec<f32>>,
+    writer: BufWriter<File>,
+}
+
+impl EmbeddingCheckpoint {
+    /// Open the checkpoint of the index at `index_dir`, loading its vectors if
+    /// they were embedded with `header`, otherwise starting a new one
+    pub fn open(index_dir: &Path, header: CheckpointHeader) -> anyhow::Result<Self> {
+        let path = checkpoint_path(index_dir);
+        let dimensions = header.dimensions;
+        if let Some((vectors, valid_len)) = read_checkpoint(&path, &header) {
+            let file = std::fs::OpenOptions::new().write(true).open(&path)?;
+            // Drop a record cut short by the interruption before appending
+            file.set_len(valid_len)?;
+            let mut writer = BufWriter::new(file);
+            std::io::Seek::seek(&mut writer, std::io::SeekFrom::End(0))?;
+            return Ok(Self { path, dimensions, vectors, writer });
+        }
+
+        let mut writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer(&mut writer, &header)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(Self { path, dimensions, vectors: HashMap::new(), writer })
+    }
+
+    /// Number of vectors reused from earlier attempts
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Vector of a passage embedded by an earlier attempt
+    pub fn get(&self, id: &str) -> Option<&[f32]> {
+        self.vectors.get(id).map(|v| v.as_slice())
+    }
+
+    /// Append a batch of newly embedded vectors, flushed so they survive an interruption
+    pub fn add<'a>(&mut self, entries: impl IntoIterator<Item = (&'a str, &'a [f32])>) -> anyhow::Result<()> {
+        for (id, vector) in entries {
+            anyhow::ensure!(
+                vector.len() == self.dimensions,
+                "Checkpoint vector for {} has {} dimensions, expected {}",
+                id,
+                vector.len(),
+                self.dimensions
+            );
+            self.writer.write_all(&(id.len() as u32).to_le_bytes())?;
+            self.writer.write_all(id.as_bytes())?;
+            for value in vector {
+                self.writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Delete the checkpoint once the index it was for is written
+    pub fn remove(self) -> anyhow::Result<()> {
+        drop(self.writer);
+        std::fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Vectors of a checkpoint written with `header`, and the length of its
+/// complete records; `None` if there is none or it has another header
+fn read_checkpoint(path: &Path, header: &CheckpointHeader) -> Option<(HashMap<String, Vec<f32>>, u64)> {
+    let mut data = Vec::new();
+    File::open(path).ok()?.read_to_end(&mut data).ok()?;
+    let header_end = data.iter().position(|&b| b == b'\n')?;
+    let found: CheckpointHeader = serde_json::from_slice(&data[..header_end]).ok()?;
+    if &found != header {
+        return None;
+    }
+
+    let mut vectors = HashMap::new();
+    let mut pos = header_end + 1;
+    let vector_len = header.dimensions * std::mem::size_of::<f32>();
+    while let Some(id_len) = data.get(pos..pos + 4) {
+        let id_len = u32::from_le_bytes(id_len.try_into().ok()?) as usize;
+        let id_start = pos + 4;
+        let vector_start = id_start + id_len;
+        let (Some(id), Some(vector)) = (data.get(id_start..vector_start), data.get(vector_start..vector_start + vector_len))
+        else {
+            break;
+        };
+        let Ok(id) = std::str::from_utf8(id) else {
+            break;
+        };
+        let vector = vector
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        vectors.insert(id.to_string(), vector);
+        pos = vector_start + vector_len;
+    }
+    Some((vectors, pos as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(model: &str) -> CheckpointHeader {
+        CheckpointHeader {
+            model: model.to_string(),
+            dimensions: 2,
+            settings: serde_json::json!({ "document_prefix": "" }),
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_resumes() {
+        let root = std::env::temp_dir().join(format!("leann-checkpoint-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let index_dir = root.join("docs");
+
+        let mut checkpoint = EmbeddingCheckpoint::open(&index_dir, header("a")).unwrap();
+        assert_eq!(checkpoint.len(), 0);
+        checkpoint.add([("c1", &[1.0, 2.0][..]), ("c2", &[3.0, 4.0][..])]).unwrap();
+        assert!(checkpoint.add([("c3", &[1.0][..])]).is_err());
+        drop(checkpoint);
+
+        // A record cut short is dropped, and appending continues after the last whole one
+        let path = checkpoint_path(&index_dir);
+        assert_eq!(path, root.join("docs.checkpoint"));
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[5, 0, 0, 0, b'c']).unwrap();
+        drop(file);
+        let mut checkpoint = EmbeddingCheckpoint::open(&index_dir, header("a")).unwrap();
+        assert_eq!(checkpoint.len(), 2);
+        assert_eq!(checkpoint.get("c2"), Some(&[3.0, 4.0][..]));
+        checkpoint.add([("c3", &[5.0, 6.0][..])]).unwrap();
+        drop(checkpoint);
+        let checkpoint = EmbeddingCheckpoint::open(&index_dir, header("a")).unwrap();
+        assert_eq!(checkpoint.get("c3"), Some(&[5.0, 6.0][..]));
+        drop(checkpoint);
+
+        // Vectors of another model are discarded
+        let checkpoint = EmbeddingCheckpoint::open(&index_dir, header("b")).unwrap();
+        assert_eq!(checkpoint.len(), 0);
+        checkpoint.remove().unwrap();
+        assert!(!path.exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+//! Result diversification - MMR re-ranking and group-by collapsing
+//!
+//! Nearest neighbors are often near-duplicates (adjacent chunks of one file).
+//! MMR trades some relevance for diversity by penalizing candidates similar to
+//! results already chosen; collapsing keeps only the best result per group.
+
+/// Select `k` candidates by maximal marginal relevance
+///
+/// Each step picks the candidate maximizing
+/// `lambda * sim(query, c) - (1 - lambda) * max(sim(c, selected))`, so
+/// `lambda = 1.0` is pure relevance and `lambda = 0.0` pure diversity.
+/// Returns positions into `candidates` in selection order.
+pub fn mmr(query: &[f32], candidates: &[Vec<f32>], lambda: f32, k: usize) -> Vec<usize> {
+    let lambda = lambda.clamp(0.0, 1.0);
+    let relevance: Vec<f32> = candidates.iter().map(|c| cosine(query, c)).collect();
+
+    let mut selected: Vec<usize> = Vec::with_capacity(k.min(candidates.len()));
+    let mut chosen = vec![false; candidates.len()];
+    // Highest similarity of each candidate to anything selected so far
+    let mut redundancy = vec![0.0f32; candidates.len()];
+
+    while selected.len() < k.min(candidates.len()) {
+        let mut best: Option<(usize, f32)> = None;
+        for i in (0..candidates.len()).filter(|&i| !chosen[i]) {
+            let score = lambda * relevance[i] - (1.0 - lambda) * redundancy[i];
+            // Strictly greater keeps the earlier (higher-ranked) candidate on ties
+            if best.is_none_or(|(_, s)| score > s) {
+                best = Some((i, score));
+            }
+        }
+
+        let Some((pick, _)) = best else {
+            break;
+        };
+        chosen[pick] = true;
+        selected.push(pick);
+
+        for i in (0..candidates.len()).filter(|&i| !chosen[i]) {
+            let sim = cosine(&candidates[i], &candidates[pick]);
+            if selected.len() == 1 || sim > redundancy[i] {
+                redundancy[i] = sim;
+            }
+        }
+    }
+
+    selected
+}
+
+/// Keep only the first item for each key, preserving order
+///
+/// Items without a key are never collapsed.
+pub fn collapse_by<T>(items: Vec<T>, key: impl Fn(&T) -> Option<String>) -> Vec<T> {
+    let mut seen = std::collections::HashSet::new();
+    items
+        .into_iter()
+        .filter(|item| key(item).is_none_or(|k| seen.insert(k)))
+        .collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mmr_skips_near_duplicates() {
+        let query = vec![1.0, 0.0];
+        let candidates = vec![
+            vec![1.0, 0.1],  // most relevant
+            vec![1.0, 0.11], // near-duplicate of the first
+            vec![0.7, 0.7],  // less relevant but different
+        ];
+
+        assert_eq!(mmr(&query, &candidates, 1.0, 2), vec![0, 1]);
+        assert_eq!(mmr(&query, &candidates, 0.3, 2), vec![0, 2]);
+        assert_eq!(mmr(&query, &candidates, 0.5, 10).len(), 3);
+    }
+
+    #[test]
+    fn test_collapse_by() {
+        let items = vec![("a.rs", 1), ("a.rs", 2), ("", 3), ("b.rs", 4), ("", 5)];
+        let collapsed = collapse_by(items, |(source, _)| {
+            (!source.is_empty()).then(|| source.to_string())
+        });
+        assert_eq!(collapsed, vec![("a.rs", 1), ("", 3), ("b.rs", 4), ("", 5)]);
+    }
+}
@@ -9,6 +9,8 @@ use std::path::Path;
 
 use memmap2::Mmap;
 
+use crate::error::LeannError;
+
 /// Embeddings storage using memory-mapped file
 pub struct EmbeddingsStore {
     mmap: Mmap,
@@ -125,11 +127,12 @@ impl EmbeddingsWriter {
     /// 3. The pointer is valid for the lifetime of the embedding slice
     pub fn add(&mut self, embedding: &[f32]) -> anyhow::Result<()> {
         if embedding.len() != self.dimensions {
-            anyhow::bail!(
-                "Embedding dimension mismatch: expected {}, got {}",
-                self.dimensions,
-                embedding.len()
-            );
+            return Err(LeannError::DimensionMismatch {
+                expected: self.dimensions,
+                actual: embedding.len(),
+                source_name: "embedding",
+            }
+            .into());
         }
 
         // Safety: f32 is repr(C) compatible and has no padding bytes.
@@ -0,0 +1,257 @@
+//! Search explanations - how each candidate was scored, and why candidates
+//! that didn't make the page were dropped
+//!
+//! `IndexSearcher::search_explained` records a trace while it searches: the
+//! raw vector, BM25, sparse, and MaxSim scores of every candidate, the fused
+//! score, any boost, and the step that excluded it.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::filter::MetadataFilter;
+
+/// Why a candidate isn't in the returned page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Exclusion {
+    /// Deleted, or outside the loaded scope
+    NotInIndex,
+    /// Another vector of the same passage ranked higher
+    Duplicate,
+    /// Rejected by the metadata filter
+    Filtered,
+    /// Scored below `min_score` (before boosts)
+    BelowMinScore,
+    /// Enough candidates were found before reaching it
+    BeyondCandidateLimit,
+    /// A better result has the same `group_by` value
+    Collapsed,
+    /// Dropped by MMR diversification
+    Diversified,
+    /// Ranked below the requested page
+    BelowPage,
+    /// Ranked above the requested page (skipped by `offset`)
+    BeforePage,
+    /// Marked bad for this query with `leann feedback`
+    Feedback,
+}
+
+/// How one candidate was scored
+#[derive(Debug, Clone, Serialize)]
+pub struct CandidateExplanation {
+    pub id: String,
+    /// Position in the returned page (1-based), if returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<usize>,
+    /// Similarity from the vector search (none if BM25 or sparse scoring added the candidate)
+    pub vector_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bm25_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sparse_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxsim_score: Option<f32>,
+    /// Score after fusion and reranking, before boosts
+    pub fused_score: f32,
+    /// Product of the matching boost weights
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boost_factor: Option<f32>,
+    /// Boost rules the candidate matched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub boost_rules: Vec<String>,
+    /// Score multiplier from relevance feedback on similar queries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feedback_factor: Option<f32>,
+    /// Final score
+    pub score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded: Option<Exclusion>,
+    #[serde(skip_serializing_if = "serde_json::Value::is_null")]
+    pub metadata: serde_json::Value,
+}
+
+/// One score fusion step and the weight it gave the incoming scores
+#[derive(Debug, Clone, Serialize)]
+pub struct FusionStep {
+    /// "bm25", "sparse", or "maxsim"
+    pub method: &'static str,
+    /// Weight of the scores so far (after min-max normalization); the rest goes
+    /// to this step's scores. None when the step replaces the scores.
+    pub vector_weight: Option<f32>,
+}
+
+/// A search's settings and the scoring of each candidate
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchExplanation {
+    /// Text the search ran with (after any query expansion)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_query: Option<String>,
+    /// Metadata filter applied (including any scope)
+    pub filter: Option<MetadataFilter>,
+    pub filter_mode: String,
+    pub min_score: Option<f32>,
+    pub fusion: Vec<FusionStep>,
+    /// Boost expression, if any
+    pub boost: Option<String>,
+    /// Candidates requested from the vector index
+    pub fetched: usize,
+    /// Returned results, in order
+    pub results: Vec<CandidateExplanation>,
+    /// Candidates that were considered but not returned, best fused score first
+    pub excluded: Vec<CandidateExplanation>,
+}
+
+/// Scores of every candidate, collected while a search runs
+#[derive(Debug, Default)]
+pub(crate) struct SearchTrace {
+    pub explanation: SearchExplanation,
+    /// Vector similarities by position, before fusion
+    pub vector_scores: HashMap<usize, f32>,
+    pub bm25_scores: Option<Vec<f32>>,
+    pub sparse_scores: Option<Vec<f32>>,
+    pub maxsim_scores: HashMap<usize, f32>,
+    candidates: Vec<(usize, CandidateExplanation)>,
+    positions: HashMap<usize, usize>,
+}
+
+impl SearchTrace {
+    /// Record the fused ranking; every candidate starts out beyond the
+    /// candidate limit until the searcher examines it
+    pub fn ranked(&mut self, ranking: &[(usize, f32)], id_of: impl Fn(usize) -> String) {
+        for &(idx, score) in ranking {
+            if self.positions.contains_key(&idx) {
+                continue;
+            }
+            let candidate = CandidateExplanation {
+                id: id_of(idx),
+                rank: None,
+                vector_score: self.vector_scores.get(&idx).copied(),
+                bm25_score: self.bm25_scores.as_ref().and_then(|s| s.get(idx).copied()),
+                sparse_score: self.sparse_scores.as_ref().and_then(|s| s.get(idx).copied()),
+                maxsim_score: self.maxsim_scores.get(&idx).copied(),
+                fused_score: score,
+                boost_factor: None,
+                boost_rules: Vec::new(),
+                feedback_factor: None,
+                score,
+                excluded: Some(Exclusion::BeyondCandidateLimit),
+                metadata: serde_json::Value::Null,
+            };
+            self.positions.insert(idx, self.candidates.len());
+            self.candidates.push((idx, candidate));
+        }
+    }
+
+    fn candidate(&mut self, idx: usize) -> Option<&mut CandidateExplanation> {
+        let pos = *self.positions.get(&idx)?;
+        Some(&mut self.candidates[pos].1)
+    }
+
+    /// Mark a candidate as excluded
+    pub fn exclude(&mut self, idx: usize, reason: Exclusion) {
+        if let Some(candidate) = self.candidate(idx) {
+            candidate.excluded = Some(reason);
+        }
+    }
+
+    /// Mark a candidate as kept so far, with its metadata
+    pub fn keep(&mut self, idx: usize, metadata: &serde_json::Value) {
+        if let Some(candidate) = self.candidate(idx) {
+            candidate.excluded = None;
+            candidate.metadata = metadata.clone();
+        }
+    }
+
+    /// Record the boost applied to a candidate
+    pub fn boosted(&mut self, idx: usize, factor: f32, rules: Vec<&str>, score: f32) {
+        if let Some(candidate) = self.candidate(idx) {
+            candidate.boost_factor = Some(factor);
+            candidate.boost_rules = rules.into_iter().map(String::from).collect();
+            candidate.score = score;
+        }
+    }
+
+    /// Record the relevance feedback multiplier applied to a candidate
+    pub fn feedback(&mut self, idx: usize, factor: f32, score: f32) {
+        if let Some(candidate) = self.candidate(idx) {
+            candidate.feedback_factor = Some(factor);
+            candidate.score = score;
+        }
+    }
+
+    /// Exclude the kept candidates a step dropped (those not in `after`)
+    pub fn dropped<T>(&mut self, after: &[(usize, T)], reason: Exclusion) {
+        let remaining: std::collections::HashSet<usize> = after.iter().map(|(idx, _)| *idx).collect();
+        for (idx, candidate) in &mut self.candidates {
+            if candidate.excluded.is_none() && !remaining.contains(idx) {
+                candidate.excluded = Some(reason);
+            }
+        }
+    }
+
+    /// Finish with the returned page, in order
+    pub fn finish(mut self, page: &[usize]) -> SearchExplanation {
+        for (rank, idx) in page.iter().enumerate() {
+            if let Some(candidate) = self.candidate(*idx) {
+                candidate.rank = Some(rank + 1);
+            }
+        }
+        let mut results: Vec<CandidateExplanation> = Vec::with_capacity(page.len());
+        let mut excluded = Vec::new();
+        for (_, candidate) in self.candidates {
+            if candidate.rank.is_some() {
+                results.push(candidate);
+            } else {
+                excluded.push(candidate);
+            }
+        }
+        results.sort_by_key(|c| c.rank);
+        excluded.sort_by(|a, b| b.fused_score.total_cmp(&a.fused_score));
+        self.explanation.results = results;
+        self.explanation.excluded = excluded;
+        self.explanation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_records_exclusions() {
+        let mut trace = SearchTrace {
+            vector_scores: HashMap::from([(0, 0.9), (1, 0.8), (2, 0.7)]),
+            bm25_scores: Some(vec![0.0, 2.5, 1.0, 4.0]),
+            ..SearchTrace::default()
+        };
+        trace.ranked(&[(3, 0.95), (0, 0.9), (1, 0.8), (2, 0.7)], |idx| format!("p{}", idx));
+
+        let metadata = serde_json::json!({"source": "a.md"});
+        trace.keep(3, &metadata);
+        trace.exclude(0, Exclusion::Filtered);
+        trace.keep(1, &metadata);
+        trace.boosted(1, 2.0, vec!["source^a:2"], 1.6);
+        trace.dropped(&[(1, ())], Exclusion::BelowPage);
+
+        let explanation = trace.finish(&[1]);
+        assert_eq!(explanation.results.len(), 1);
+        let result = &explanation.results[0];
+        assert_eq!((result.id.as_str(), result.rank, result.score), ("p1", Some(1), 1.6));
+        assert_eq!((result.vector_score, result.bm25_score), (Some(0.8), Some(2.5)));
+        assert_eq!(result.boost_rules, ["source^a:2"]);
+
+        let excluded: Vec<(&str, Option<Exclusion>)> =
+            explanation.excluded.iter().map(|c| (c.id.as_str(), c.excluded)).collect();
+        assert_eq!(
+            excluded,
+            [
+                ("p3", Some(Exclusion::BelowPage)),
+                ("p0", Some(Exclusion::Filtered)),
+                ("p2", Some(Exclusion::BeyondCandidateLimit)),
+            ]
+        );
+        // Added by BM25, so no vector score
+        assert_eq!(explanation.excluded[0].vector_score, None);
+    }
+}
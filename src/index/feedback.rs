@@ -0,0 +1,253 @@
+//! Relevance feedback - passages marked good or bad for a query, applied to
+//! later searches for similar queries
+//!
+//! `leann feedback` appends each judgment as a JSON line, with the query's
+//! embedding, to `<name>.feedback.jsonl` next to the index directory (so it
+//! survives rebuilds, like the query log). `IndexSearcher` reads the file when
+//! it loads the index, and servers reload an index when its feedback changes
+//! (see `index_version`). A search counts the feedback given for queries within
+//! `MIN_SIMILARITY` of its own, weighted by how close they are: good passages
+//! score up to `GOOD_BOOST` higher, bad ones up to `BAD_PENALTY` lower, per
+//! judgment, within `MIN_FACTOR` and `MAX_FACTOR` overall.
+//! Feedback for practically the same query (`PIN_SIMILARITY`) pins instead:
+//! good passages are returned even if the search missed them, bad ones never.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Cosine similarity from which feedback on another query applies
+const MIN_SIMILARITY: f32 = 0.8;
+
+/// Cosine similarity from which feedback pins passages in or out
+const PIN_SIMILARITY: f32 = 0.95;
+
+/// Score increase of a good passage for the same query (1.0 doubles it)
+const GOOD_BOOST: f32 = 1.0;
+
+/// Score decrease of a bad passage for the same query
+const BAD_PENALTY: f32 = 0.5;
+
+/// Bounds of a passage's combined score multiplier, however often it's judged
+const MIN_FACTOR: f32 = 0.25;
+const MAX_FACTOR: f32 = 4.0;
+
+/// Passages judged for one query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackEntry {
+    /// Unix time of the feedback
+    pub timestamp: u64,
+    pub query: String,
+    /// The query's embedding, for matching later queries
+    pub embedding: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub good: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bad: Vec<String>,
+}
+
+/// Feedback file of the index at `index_dir`
+pub fn feedback_path(index_dir: &Path) -> PathBuf {
+    let mut name = index_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(".feedback.jsonl");
+    index_dir.with_file_name(name)
+}
+
+/// Append feedback for the index at `index_dir`
+pub fn record_feedback(index_dir: &Path, entry: &FeedbackEntry) -> anyhow::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(feedback_path(index_dir))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Read the feedback for the index at `index_dir`, oldest first
+///
+/// Malformed lines (e.g. from an interrupted write) are skipped.
+pub fn read_feedback(index_dir: &Path) -> anyhow::Result<Vec<FeedbackEntry>> {
+    let path = feedback_path(index_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(std::fs::read_to_string(&path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Feedback loaded with an index
+#[derive(Debug)]
+pub(crate) struct FeedbackStore {
+    entries: Vec<FeedbackEntry>,
+}
+
+impl FeedbackStore {
+    /// The feedback for the index at `index_dir`, if there is any
+    pub fn load(index_dir: &Path) -> Option<Self> {
+        match read_feedback(index_dir) {
+            Ok(entries) if !entries.is_empty() => Some(Self { entries }),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable relevance feedback: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Number of feedback entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// What the feedback on queries similar to `embedding` does to its results
+    pub fn for_query(&self, embedding: &[f32]) -> QueryFeedback {
+        let mut feedback = QueryFeedback::default();
+        for entry in &self.entries {
+            let similarity = cosine_similarity(embedding, &entry.embedding);
+            if similarity < MIN_SIMILARITY {
+                continue;
+            }
+            let weight = ((similarity - MIN_SIMILARITY) / (1.0 - MIN_SIMILARITY)).min(1.0);
+            for id in &entry.good {
+                *feedback.factors.entry(id.clone()).or_insert(1.0) *= 1.0 + GOOD_BOOST * weight;
+            }
+            for id in &entry.bad {
+                *feedback.factors.entry(id.clone()).or_insert(1.0) *= 1.0 - BAD_PENALTY * weight;
+            }
+            // Later judgments of the same query override earlier ones
+            if similarity >= PIN_SIMILARITY {
+                for id in &entry.good {
+                    feedback.blocked.remove(id);
+                    if !feedback.pinned.contains(id) {
+                        feedback.pinned.push(id.clone());
+                    }
+                }
+                for id in &entry.bad {
+                    feedback.pinned.retain(|p| p != id);
+                    feedback.blocked.insert(id.clone());
+                }
+            }
+        }
+        for factor in feedback.factors.values_mut() {
+            *factor = factor.clamp(MIN_FACTOR, MAX_FACTOR);
+        }
+        feedback
+    }
+}
+
+/// Feedback applying to one search
+#[derive(Debug, Default)]
+pub(crate) struct QueryFeedback {
+    /// Score multipliers by passage id
+    factors: HashMap<String, f32>,
+    /// Passages to return even if the search misses them
+    pinned: Vec<String>,
+    /// Passages never to return
+    blocked: HashSet<String>,
+}
+
+impl QueryFeedback {
+    pub fn is_empty(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    /// Score multiplier of a passage (1.0 if it has no feedback)
+    pub fn factor(&self, id: &str) -> f32 {
+        self.factors.get(id).copied().unwrap_or(1.0)
+    }
+
+    pub fn pinned(&self) -> &[String] {
+        &self.pinned
+    }
+
+    pub fn is_blocked(&self, id: &str) -> bool {
+        self.blocked.contains(id)
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    dot / (norm(a) * norm(b)).max(f32::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(query: &str, embedding: Vec<f32>, good: &[&str], bad: &[&str]) -> FeedbackEntry {
+        FeedbackEntry {
+            timestamp: 0,
+            query: query.to_string(),
+            embedding,
+            good: good.iter().map(|s| s.to_string()).collect(),
+            bad: bad.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_feedback_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("leann-feedback-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let index_dir = dir.join("docs");
+        assert_eq!(feedback_path(&index_dir), dir.join("docs.feedback.jsonl"));
+        assert!(FeedbackStore::load(&index_dir).is_none());
+
+        record_feedback(&index_dir, &entry("tokens", vec![1.0, 0.0], &["a"], &[])).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(feedback_path(&index_dir))
+            .unwrap()
+            .write_all(b"{\"truncated\n")
+            .unwrap();
+        record_feedback(&index_dir, &entry("auth", vec![0.0, 1.0], &[], &["b"])).unwrap();
+
+        let entries = read_feedback(&index_dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].bad, ["b"]);
+        assert!(FeedbackStore::load(&index_dir).is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_for_query() {
+        let store = FeedbackStore {
+            entries: vec![
+                entry("tokens", vec![1.0, 0.0], &["a", "c"], &["b"]),
+                // A nearby query (cosine 0.9): half weight, no pins
+                entry("token refresh", vec![0.9, 0.436], &["d"], &[]),
+                // Unrelated
+                entry("auth", vec![0.0, 1.0], &["e"], &[]),
+                // Changed mind about c
+                entry("tokens", vec![2.0, 0.0], &[], &["c"]),
+            ],
+        };
+        let feedback = store.for_query(&[1.0, 0.0]);
+        assert_eq!(feedback.factor("a"), 2.0);
+        assert_eq!(feedback.factor("b"), 0.5);
+        assert_eq!(feedback.factor("c"), 1.0);
+        assert!((feedback.factor("d") - 1.5).abs() < 0.01);
+        assert_eq!(feedback.factor("e"), 1.0);
+        assert_eq!(feedback.pinned(), ["a"]);
+        assert!(feedback.is_blocked("b") && feedback.is_blocked("c"));
+        assert!(!feedback.is_blocked("d"));
+
+        assert!(store.for_query(&[-1.0, 0.0]).is_empty());
+    }
+
+    #[test]
+    fn test_repeated_votes_are_bounded() {
+        let votes = |good: &[&str], bad: &[&str]| vec![entry("tokens", vec![1.0, 0.0], good, bad); 20];
+        let store = FeedbackStore { entries: votes(&["a"], &[]) };
+        assert_eq!(store.for_query(&[1.0, 0.0]).factor("a"), MAX_FACTOR);
+        let store = FeedbackStore { entries: votes(&[], &["b"]) };
+        assert_eq!(store.for_query(&[1.0, 0.0]).factor("b"), MIN_FACTOR);
+    }
+}
@@ -0,0 +1,429 @@
+//! Secondary metadata index for filtering without reading passages
+//!
+//! A columnar view of the metadata: each top-level field maps its distinct
+//! values to the passages holding them. A filter condition is evaluated once
+//! per distinct value instead of once per passage, so filters like
+//! `language=rust` or `source^src/` resolve to an allowed-id set without
+//! parsing `passages.jsonl`. Written at build time as `documents.leann.fields.json`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::filter::{FilterCondition, FilterOp, MetadataFilter};
+
+/// Fields may always have this many distinct values before uniqueness is checked
+const MIN_DISTINCT_VALUES: usize = 1024;
+
+/// Fields with nearly one value per passage (hashes, IDs) aren't worth indexing
+const MAX_UNIQUE_RATIO: f64 = 0.9;
+
+/// Set of passage offsets (positions in the ID mapping)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdBitset {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl IdBitset {
+    /// Empty set over `len` offsets
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    /// Set containing every offset below `len`
+    pub fn full(len: usize) -> Self {
+        let mut set = Self::new(len);
+        set.words.iter_mut().for_each(|w| *w = u64::MAX);
+        set.clear_tail();
+        set
+    }
+
+    fn from_offsets(len: usize, offsets: &[u32]) -> Self {
+        let mut set = Self::new(len);
+        for &offset in offsets {
+            set.insert(offset as usize);
+        }
+        set
+    }
+
+    pub fn insert(&mut self, offset: usize) {
+        if offset < self.len {
+            self.words[offset / 64] |= 1 << (offset % 64);
+        }
+    }
+
+    pub fn remove(&mut self, offset: usize) {
+        if offset < self.len {
+            self.words[offset / 64] &= !(1 << (offset % 64));
+        }
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        offset < self.len && self.words[offset / 64] & (1 << (offset % 64)) != 0
+    }
+
+    /// Number of offsets in the set
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Offsets in the set, in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(|&i| self.contains(i))
+    }
+
+    pub fn intersect(&mut self, other: &IdBitset) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a &= b;
+        }
+    }
+
+    pub fn union(&mut self, other: &IdBitset) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a |= b;
+        }
+    }
+
+    pub fn invert(&mut self) {
+        self.words.iter_mut().for_each(|w| *w = !*w);
+        self.clear_tail();
+    }
+
+    /// Keep bits past `len` zero so `count` and `invert` stay exact
+    fn clear_tail(&mut self) {
+        let extra = self.words.len() * 64 - self.len;
+        if extra > 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= u64::MAX >> extra;
+            }
+        }
+    }
+}
+
+/// Passages holding one metadata field
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FieldValues {
+    /// Offsets of passages that have the field at all (any type)
+    present: Vec<u32>,
+    /// Offsets per scalar value, keyed by [`value_key`]
+    values: BTreeMap<String, Vec<u32>>,
+    /// Offsets of passages where the field is an array or object
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    other: Vec<u32>,
+}
+
+/// Value indexes for the top-level metadata fields of an index
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FieldIndex {
+    /// Number of passages covered (must match the ID mapping to be used)
+    len: usize,
+    fields: BTreeMap<String, FieldValues>,
+    /// Fields dropped for exceeding the distinct value limit
+    #[serde(default)]
+    skipped: BTreeSet<String>,
+}
+
+impl FieldIndex {
+    fn path_for(index_path: &Path) -> std::path::PathBuf {
+        index_path.with_extension("fields.json")
+    }
+
+    /// Load the field index for an index, if one was written
+    pub fn load(index_path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(Self::path_for(index_path)).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(index) => Some(index),
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable field index: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, index_path: &Path) -> anyhow::Result<()> {
+        std::fs::write(Self::path_for(index_path), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Number of passages covered
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Record the metadata of the passage at the next offset
+    pub fn add(&mut self, metadata: &Value) {
+        let offset = self.len as u32;
+        self.len += 1;
+        self.insert(offset, metadata);
+    }
+
+    /// Record new metadata for the passage at `offset`, replacing what was
+    /// recorded there (for a passage that took over an outdated one's offset)
+    pub fn replace(&mut self, offset: usize, metadata: &Value) {
+        let offset = offset as u32;
+        for field in self.fields.values_mut() {
+            field.present.retain(|&o| o != offset);
+            field.other.retain(|&o| o != offset);
+            for offsets in field.values.values_mut() {
+                offsets.retain(|&o| o != offset);
+            }
+            field.values.retain(|_, offsets| !offsets.is_empty());
+        }
+        self.fields.retain(|_, field| !field.present.is_empty());
+        self.insert(offset, metadata);
+    }
+
+    fn insert(&mut self, offset: u32, metadata: &Value) {
+        let Some(obj) = metadata.as_object() else {
+            return;
+        };
+        for (field, value) in obj {
+            // Dotted names can't be addressed by filters (dots mean nesting)
+            if field.contains('.') || self.skipped.contains(field) {
+                continue;
+            }
+
+            let entry = self.fields.entry(field.clone()).or_default();
+            entry.present.push(offset);
+            match value_key(value) {
+                Some(key) => entry.values.entry(key).or_default().push(offset),
+                None => entry.other.push(offset),
+            }
+
+            let distinct = entry.values.len();
+            if distinct > MIN_DISTINCT_VALUES && distinct as f64 > self.len as f64 * MAX_UNIQUE_RATIO {
+                self.fields.remove(field);
+                self.skipped.insert(field.clone());
+            }
+        }
+    }
+
+    /// Names of the indexed fields
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.fields.keys().map(|k| k.as_str())
+    }
+
+    /// Allowed-id set for a filter, if every condition can be answered from the index
+    ///
+    /// Returns `None` for conditions on nested or unindexed fields, or on fields
+    /// holding arrays or objects; callers then scan passage metadata.
+    pub fn allowed(&self, filter: &MetadataFilter) -> Option<IdBitset> {
+        match filter {
+            MetadataFilter::Condition(cond) => self.condition(cond),
+            MetadataFilter::And { and } => {
+                let mut set = IdBitset::full(self.len);
+                for f in and {
+                    set.intersect(&self.allowed(f)?);
+                }
+                Some(set)
+            }
+            MetadataFilter::Or { or } => {
+                let mut set = IdBitset::new(self.len);
+                for f in or {
+                    set.union(&self.allowed(f)?);
+                }
+                Some(set)
+            }
+            MetadataFilter::Not { not } => {
+                let mut set = self.allowed(not)?;
+                set.invert();
+                Some(set)
+            }
+        }
+    }
+
+    fn condition(&self, cond: &FilterCondition) -> Option<IdBitset> {
+        // Dotted fields are nested paths, which aren't indexed
+        if cond.field.contains('.') || self.skipped.contains(&cond.field) {
+            return None;
+        }
+
+        // How the condition treats passages without the field (e.g. `!=` matches them)
+        let matches_missing = cond.matches(&Value::Object(Default::default()));
+
+        let Some(field) = self.fields.get(&cond.field) else {
+            return Some(if matches_missing {
+                IdBitset::full(self.len)
+            } else {
+                IdBitset::new(self.len)
+            });
+        };
+
+        if let FilterOp::Exists = cond.op {
+            return Some(IdBitset::from_offsets(self.len, &field.present));
+        }
+        if !field.other.is_empty() {
+            return None;
+        }
+
+        // Evaluate the condition once per distinct value
+        let mut set = IdBitset::new(self.len);
+        for (key, offsets) in &field.values {
+            let mut metadata = serde_json::Map::new();
+            metadata.insert(cond.field.clone(), key_value(key));
+            if cond.matches(&Value::Object(metadata)) {
+                for &offset in offsets {
+                    set.insert(offset as usize);
+                }
+            }
+        }
+
+        if matches_missing {
+            let mut missing = IdBitset::from_offsets(self.len, &field.present);
+            missing.invert();
+            set.union(&missing);
+        }
+
+        Some(set)
+    }
+}
+
+/// Index key for a scalar value, matching how filters compare values for equality
+fn value_key(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(format!("s:{}", s)),
+        Value::Number(n) => n.as_f64().map(|n| format!("n:{}", n)),
+        Value::Bool(b) => Some(format!("b:{}", b)),
+        Value::Null => Some("null".to_string()),
+        _ => None,
+    }
+}
+
+/// Value for an index key, equal (as filters compare) to the value it was made from
+fn key_value(key: &str) -> Value {
+    if let Some(s) = key.strip_prefix("s:") {
+        return Value::String(s.to_string());
+    }
+    if let Some(n) = key.strip_prefix("n:") {
+        return n
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map_or(Value::Null, Value::Number);
+    }
+    match key {
+        "b:true" => Value::Bool(true),
+        "b:false" => Value::Bool(false),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> FieldIndex {
+        let mut index = FieldIndex::default();
+        index.add(&serde_json::json!({"language": "rust", "is_test": false, "lines": 10}));
+        index.add(&serde_json::json!({"language": "rust", "is_test": true, "lines": 10.0}));
+        index.add(&serde_json::json!({"language": "python"}));
+        index
+    }
+
+    fn offsets(set: &IdBitset) -> Vec<usize> {
+        set.iter().collect()
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut index = index();
+        index.replace(0, &serde_json::json!({"language": "go"}));
+        assert_eq!(index.len(), 3);
+
+        let allowed = |filter: &str| offsets(&index.allowed(&MetadataFilter::try_parse(filter).unwrap()).unwrap());
+        assert_eq!(allowed("language=go"), [0]);
+        assert_eq!(allowed("language=rust"), [1]);
+        assert_eq!(allowed("is_test=false"), Vec::<usize>::new());
+        assert_eq!(allowed("lines=10"), [1]);
+    }
+
+    #[test]
+    fn test_bitset_invert_and_count() {
+        let mut set = IdBitset::new(70);
+        set.insert(3);
+        set.insert(69);
+        assert_eq!(set.count(), 2);
+        set.invert();
+        assert_eq!(set.count(), 68);
+        assert!(!set.contains(69));
+        assert!(!set.contains(70));
+    }
+
+    #[test]
+    fn test_allowed_matches_filter_semantics() {
+        let index = index();
+        let cases = [
+            "language=rust",
+            "language!=rust",
+            "is_test=false AND language=rust",
+            "NOT is_test=true",
+            "language in [python,go]",
+            "is_test?",
+            "lines=10",
+            "lines>=10",
+            "missing=1",
+            "missing!=1",
+            "language^ru",
+            "language=~^py",
+            "NOT language$*UST",
+        ];
+        let passages = [
+            serde_json::json!({"language": "rust", "is_test": false, "lines": 10}),
+            serde_json::json!({"language": "rust", "is_test": true, "lines": 10.0}),
+            serde_json::json!({"language": "python"}),
+        ];
+
+        for case in cases {
+            let filter = MetadataFilter::try_parse(case).unwrap();
+            let expected: Vec<usize> = passages
+                .iter()
+                .enumerate()
+                .filter(|(_, m)| filter.matches(m))
+                .map(|(i, _)| i)
+                .collect();
+            let allowed = index.allowed(&filter).unwrap();
+            assert_eq!(offsets(&allowed), expected, "filter: {}", case);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_conditions_fall_back() {
+        let mut index = index();
+        index.add(&serde_json::json!({"tags": ["a", "b"], "nested": {"x": 1}}));
+        assert!(index.allowed(&MetadataFilter::try_parse("tags=a").unwrap()).is_none());
+        assert!(index.allowed(&MetadataFilter::try_parse("nested.x=1").unwrap()).is_none());
+        assert!(index.allowed(&MetadataFilter::try_parse("language=rust OR tags~a").unwrap()).is_none());
+        // Existence doesn't depend on the value type
+        assert_eq!(index.allowed(&MetadataFilter::try_parse("tags?").unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_source_prefix() {
+        let mut index = FieldIndex::default();
+        for path in ["src/a.rs", "src/a.rs", "src/b.rs", "tests/c.rs"] {
+            index.add(&serde_json::json!({ "source": path }));
+        }
+        let allowed = index.allowed(&MetadataFilter::try_parse("source^src/").unwrap()).unwrap();
+        assert_eq!(offsets(&allowed), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_unique_fields_skipped() {
+        let mut index = FieldIndex::default();
+        for i in 0..=MIN_DISTINCT_VALUES {
+            index.add(&serde_json::json!({"hash": format!("h{}", i), "type": "code"}));
+        }
+        assert!(index.allowed(&MetadataFilter::try_parse("hash=h1").unwrap()).is_none());
+        assert_eq!(
+            index.allowed(&MetadataFilter::try_parse("type=code").unwrap()).unwrap().count(),
+            MIN_DISTINCT_VALUES + 1
+        );
+        assert_eq!(index.fields().collect::<Vec<_>>(), vec!["type"]);
+    }
+}
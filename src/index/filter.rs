@@ -1,5 +1,8 @@
 //! Metadata filtering for search results
 
+use std::sync::OnceLock;
+
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -19,6 +22,14 @@ pub enum FilterOp {
     StartsWith,
     EndsWith,
     Exists,
+    /// Regular expression match (`field=~regex`)
+    Matches,
+    /// Case-insensitive contains (`field~*value`)
+    IContains,
+    /// Case-insensitive starts with (`field^*value`)
+    IStartsWith,
+    /// Case-insensitive ends with (`field$*value`)
+    IEndsWith,
 }
 
 /// A single filter condition
@@ -27,15 +38,22 @@ pub struct FilterCondition {
     pub field: String,
     pub op: FilterOp,
     pub value: Value,
+    /// Compiled regex for `Matches`, built on first use and reused across candidates
+    #[serde(skip)]
+    regex: OnceLock<Option<Regex>>,
 }
 
-/// Combined filter with AND/OR logic
+/// Metadata field holding a passage's namespace (`leann build --namespace`)
+pub const NAMESPACE_FIELD: &str = "namespace";
+
+/// Combined filter with AND/OR/NOT logic
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum MetadataFilter {
     Condition(FilterCondition),
     And { and: Vec<MetadataFilter> },
     Or { or: Vec<MetadataFilter> },
+    Not { not: Box<MetadataFilter> },
 }
 
 impl MetadataFilter {
@@ -43,107 +61,83 @@ impl MetadataFilter {
     /// - Simple: "source:*.rs", "type=code", "lines>50"
     /// - Multiple (AND): "type=code,lines>50" or "type=code AND lines>50"
     /// - Multiple (OR): "type=code OR type=text"
+    /// - Negation and grouping: "NOT (type=test OR source~fixtures)"
     /// - In: "type in [code,text,doc]"
     /// - Not in: "type not_in [code,text]"
     /// - Contains: "source~keyword" or "source:*keyword*"
     /// - Starts with: "source^prefix" or "source:prefix*"
     /// - Ends with: "source$suffix" or "source:*suffix"
     /// - Exists: "field?"
+    /// - Dates: "modified>2024-01-01", "git_modified>now-30d"
+    /// - Regex: "source=~(?i)readme"
+    /// - Case-insensitive: "source~*readme", "source^*docs/", "source$*.MD"
+    ///
+    /// Precedence from lowest to highest: OR, AND (or ","), NOT, parentheses.
+    /// Returns `None` on invalid syntax; use [`MetadataFilter::try_parse`] for the error.
     pub fn parse(filter_str: &str) -> Option<Self> {
-        let filter_str = filter_str.trim();
+        Self::try_parse(filter_str).ok()
+    }
 
-        // Check for OR (lower precedence)
-        if filter_str.contains(" OR ") {
-            let parts: Vec<&str> = filter_str.split(" OR ").collect();
-            let filters: Vec<MetadataFilter> = parts
-                .iter()
-                .filter_map(|p| MetadataFilter::parse(p.trim()))
-                .collect();
-            if filters.len() > 1 {
-                return Some(MetadataFilter::Or { or: filters });
-            } else if filters.len() == 1 {
-                return Some(filters.into_iter().next().unwrap());
-            }
-            return None;
+    /// Parse a filter string, describing what is wrong if the syntax is invalid
+    pub fn try_parse(filter_str: &str) -> anyhow::Result<Self> {
+        let mut parser = FilterParser::new(filter_str);
+        parser.skip_whitespace();
+        if parser.at_end() {
+            anyhow::bail!("Empty filter expression");
         }
 
-        // Check for AND (comma or explicit AND)
-        // But avoid splitting commas inside [...] brackets
-        let has_and = filter_str.contains(" AND ");
-        let has_comma_outside_brackets = {
-            let mut depth = 0;
-            let mut found = false;
-            for c in filter_str.chars() {
-                match c {
-                    '[' => depth += 1,
-                    ']' => depth -= 1,
-                    ',' if depth == 0 => {
-                        found = true;
-                        break;
-                    }
-                    _ => {}
-                }
-            }
-            found
-        };
-
-        if has_and || has_comma_outside_brackets {
-            let parts: Vec<String> = if has_and {
-                filter_str.split(" AND ").map(|s| s.to_string()).collect()
-            } else {
-                // Split only commas outside brackets
-                let mut parts = Vec::new();
-                let mut current = String::new();
-                let mut depth = 0;
-                for c in filter_str.chars() {
-                    match c {
-                        '[' => {
-                            depth += 1;
-                            current.push(c);
-                        }
-                        ']' => {
-                            depth -= 1;
-                            current.push(c);
-                        }
-                        ',' if depth == 0 => {
-                            parts.push(current.clone());
-                            current.clear();
-                        }
-                        _ => current.push(c),
-                    }
-                }
-                if !current.is_empty() {
-                    parts.push(current);
-                }
-                parts
-            };
-            let filters: Vec<MetadataFilter> = parts
-                .iter()
-                .filter_map(|p| MetadataFilter::parse_single(p.trim()))
-                .collect();
-            if filters.len() > 1 {
-                return Some(MetadataFilter::And { and: filters });
-            } else if filters.len() == 1 {
-                return Some(filters.into_iter().next().unwrap());
-            }
-            return None;
+        let filter = parser.parse_or()?;
+        parser.skip_whitespace();
+        if !parser.at_end() {
+            anyhow::bail!(
+                "Unexpected '{}' at position {} in filter: {}",
+                parser.rest().chars().next().unwrap_or(' '),
+                parser.pos,
+                filter_str
+            );
         }
+        Ok(filter)
+    }
 
-        // Single condition
-        Self::parse_single(filter_str)
+    /// `filter` restricted to the passages of `namespace`
+    ///
+    /// The namespace condition is a plain equality, so indexes with a field
+    /// index resolve it to an allowed-id set before the vector search.
+    pub fn in_namespace(namespace: &str, filter: Option<Self>) -> Self {
+        let condition = Self::Condition(FilterCondition::new(
+            NAMESPACE_FIELD.to_string(),
+            FilterOp::Eq,
+            Value::String(namespace.to_string()),
+        ));
+        match filter {
+            Some(filter) => Self::And { and: vec![condition, filter] },
+            None => condition,
+        }
     }
 
     /// Parse a single filter condition
     fn parse_single(filter_str: &str) -> Option<Self> {
         let filter_str = filter_str.trim();
 
+        // Check for regex operator first, since patterns may contain any other operator: field=~regex
+        if let Some((field, pattern)) = filter_str.split_once("=~") {
+            let condition = FilterCondition::new(
+                field.trim().to_string(),
+                FilterOp::Matches,
+                Value::String(pattern.to_string()),
+            );
+            // Reject invalid patterns at parse time rather than silently matching nothing
+            condition.regex()?;
+            return Some(MetadataFilter::Condition(condition));
+        }
+
         // Check for "exists" operator: field?
         if filter_str.ends_with('?') {
-            return Some(MetadataFilter::Condition(FilterCondition {
-                field: filter_str[..filter_str.len() - 1].to_string(),
-                op: FilterOp::Exists,
-                value: Value::Null,
-            }));
+            return Some(MetadataFilter::Condition(FilterCondition::new(
+                filter_str[..filter_str.len() - 1].to_string(),
+                FilterOp::Exists,
+                Value::Null,
+            )));
         }
 
         // Check for "in" operator: field in [a,b,c]
@@ -155,11 +149,11 @@ impl MetadataFilter {
                     .split(',')
                     .map(|v| parse_value(v.trim()))
                     .collect();
-                return Some(MetadataFilter::Condition(FilterCondition {
+                return Some(MetadataFilter::Condition(FilterCondition::new(
                     field,
-                    op: FilterOp::In,
-                    value: Value::Array(values),
-                }));
+                    FilterOp::In,
+                    Value::Array(values),
+                )));
             }
         }
 
@@ -172,11 +166,11 @@ impl MetadataFilter {
                     .split(',')
                     .map(|v| parse_value(v.trim()))
                     .collect();
-                return Some(MetadataFilter::Condition(FilterCondition {
+                return Some(MetadataFilter::Condition(FilterCondition::new(
                     field,
-                    op: FilterOp::NotIn,
-                    value: Value::Array(values),
-                }));
+                    FilterOp::NotIn,
+                    Value::Array(values),
+                )));
             }
         }
 
@@ -184,11 +178,18 @@ impl MetadataFilter {
         if filter_str.contains('~') {
             let p: Vec<&str> = filter_str.splitn(2, '~').collect();
             if p.len() == 2 {
-                return Some(MetadataFilter::Condition(FilterCondition {
-                    field: p[0].to_string(),
-                    op: FilterOp::Contains,
-                    value: Value::String(p[1].to_string()),
-                }));
+                if let Some(value) = p[1].strip_prefix('*') {
+                    return Some(MetadataFilter::Condition(FilterCondition::new(
+                        p[0].to_string(),
+                        FilterOp::IContains,
+                        Value::String(value.to_string()),
+                    )));
+                }
+                return Some(MetadataFilter::Condition(FilterCondition::new(
+                    p[0].to_string(),
+                    FilterOp::Contains,
+                    Value::String(p[1].to_string()),
+                )));
             }
             return None;
         }
@@ -197,11 +198,18 @@ impl MetadataFilter {
         if filter_str.contains('^') && !filter_str.contains(">=") {
             let p: Vec<&str> = filter_str.splitn(2, '^').collect();
             if p.len() == 2 {
-                return Some(MetadataFilter::Condition(FilterCondition {
-                    field: p[0].to_string(),
-                    op: FilterOp::StartsWith,
-                    value: Value::String(p[1].to_string()),
-                }));
+                if let Some(value) = p[1].strip_prefix('*') {
+                    return Some(MetadataFilter::Condition(FilterCondition::new(
+                        p[0].to_string(),
+                        FilterOp::IStartsWith,
+                        Value::String(value.to_string()),
+                    )));
+                }
+                return Some(MetadataFilter::Condition(FilterCondition::new(
+                    p[0].to_string(),
+                    FilterOp::StartsWith,
+                    Value::String(p[1].to_string()),
+                )));
             }
             return None;
         }
@@ -210,11 +218,18 @@ impl MetadataFilter {
         if filter_str.contains('$') {
             let p: Vec<&str> = filter_str.splitn(2, '$').collect();
             if p.len() == 2 {
-                return Some(MetadataFilter::Condition(FilterCondition {
-                    field: p[0].to_string(),
-                    op: FilterOp::EndsWith,
-                    value: Value::String(p[1].to_string()),
-                }));
+                if let Some(value) = p[1].strip_prefix('*') {
+                    return Some(MetadataFilter::Condition(FilterCondition::new(
+                        p[0].to_string(),
+                        FilterOp::IEndsWith,
+                        Value::String(value.to_string()),
+                    )));
+                }
+                return Some(MetadataFilter::Condition(FilterCondition::new(
+                    p[0].to_string(),
+                    FilterOp::EndsWith,
+                    Value::String(p[1].to_string()),
+                )));
             }
             return None;
         }
@@ -223,51 +238,51 @@ impl MetadataFilter {
         let parts: Vec<&str> = if filter_str.contains("!=") {
             let p: Vec<&str> = filter_str.splitn(2, "!=").collect();
             if p.len() == 2 {
-                return Some(MetadataFilter::Condition(FilterCondition {
-                    field: p[0].to_string(),
-                    op: FilterOp::Ne,
-                    value: parse_value(p[1]),
-                }));
+                return Some(MetadataFilter::Condition(FilterCondition::new(
+                    p[0].to_string(),
+                    FilterOp::Ne,
+                    parse_value(p[1]),
+                )));
             }
             return None;
         } else if filter_str.contains(">=") {
             let p: Vec<&str> = filter_str.splitn(2, ">=").collect();
             if p.len() == 2 {
-                return Some(MetadataFilter::Condition(FilterCondition {
-                    field: p[0].to_string(),
-                    op: FilterOp::Gte,
-                    value: parse_value(p[1]),
-                }));
+                return Some(MetadataFilter::Condition(FilterCondition::new(
+                    p[0].to_string(),
+                    FilterOp::Gte,
+                    parse_comparison_value(p[1]),
+                )));
             }
             return None;
         } else if filter_str.contains("<=") {
             let p: Vec<&str> = filter_str.splitn(2, "<=").collect();
             if p.len() == 2 {
-                return Some(MetadataFilter::Condition(FilterCondition {
-                    field: p[0].to_string(),
-                    op: FilterOp::Lte,
-                    value: parse_value(p[1]),
-                }));
+                return Some(MetadataFilter::Condition(FilterCondition::new(
+                    p[0].to_string(),
+                    FilterOp::Lte,
+                    parse_comparison_value(p[1]),
+                )));
             }
             return None;
         } else if filter_str.contains('>') {
             let p: Vec<&str> = filter_str.splitn(2, '>').collect();
             if p.len() == 2 {
-                return Some(MetadataFilter::Condition(FilterCondition {
-                    field: p[0].to_string(),
-                    op: FilterOp::Gt,
-                    value: parse_value(p[1]),
-                }));
+                return Some(MetadataFilter::Condition(FilterCondition::new(
+                    p[0].to_string(),
+                    FilterOp::Gt,
+                    parse_comparison_value(p[1]),
+                )));
             }
             return None;
         } else if filter_str.contains('<') {
             let p: Vec<&str> = filter_str.splitn(2, '<').collect();
             if p.len() == 2 {
-                return Some(MetadataFilter::Condition(FilterCondition {
-                    field: p[0].to_string(),
-                    op: FilterOp::Lt,
-                    value: parse_value(p[1]),
-                }));
+                return Some(MetadataFilter::Condition(FilterCondition::new(
+                    p[0].to_string(),
+                    FilterOp::Lt,
+                    parse_comparison_value(p[1]),
+                )));
             }
             return None;
         } else if filter_str.contains('=') {
@@ -288,31 +303,31 @@ impl MetadataFilter {
         // Check for glob patterns
         if value.contains('*') {
             if value.starts_with('*') && value.ends_with('*') && value.len() > 2 {
-                return Some(MetadataFilter::Condition(FilterCondition {
+                return Some(MetadataFilter::Condition(FilterCondition::new(
                     field,
-                    op: FilterOp::Contains,
-                    value: Value::String(value[1..value.len() - 1].to_string()),
-                }));
+                    FilterOp::Contains,
+                    Value::String(value[1..value.len() - 1].to_string()),
+                )));
             } else if value.starts_with('*') {
-                return Some(MetadataFilter::Condition(FilterCondition {
+                return Some(MetadataFilter::Condition(FilterCondition::new(
                     field,
-                    op: FilterOp::EndsWith,
-                    value: Value::String(value[1..].to_string()),
-                }));
+                    FilterOp::EndsWith,
+                    Value::String(value[1..].to_string()),
+                )));
             } else if value.ends_with('*') {
-                return Some(MetadataFilter::Condition(FilterCondition {
+                return Some(MetadataFilter::Condition(FilterCondition::new(
                     field,
-                    op: FilterOp::StartsWith,
-                    value: Value::String(value[..value.len() - 1].to_string()),
-                }));
+                    FilterOp::StartsWith,
+                    Value::String(value[..value.len() - 1].to_string()),
+                )));
             }
         }
 
-        Some(MetadataFilter::Condition(FilterCondition {
+        Some(MetadataFilter::Condition(FilterCondition::new(
             field,
-            op: FilterOp::Eq,
-            value: parse_value(value),
-        }))
+            FilterOp::Eq,
+            parse_value(value),
+        )))
     }
 
     /// Check if metadata matches this filter
@@ -321,12 +336,219 @@ impl MetadataFilter {
             MetadataFilter::Condition(cond) => cond.matches(metadata),
             MetadataFilter::And { and } => and.iter().all(|f| f.matches(metadata)),
             MetadataFilter::Or { or } => or.iter().any(|f| f.matches(metadata)),
+            MetadataFilter::Not { not } => !not.matches(metadata),
         }
     }
 }
 
+/// Recursive-descent parser for filter expressions
+///
+/// Grammar:
+///   or_expr   := and_expr ("OR" and_expr)*
+///   and_expr  := unary (("AND" | ",") unary)*
+///   unary     := "NOT" unary | primary
+///   primary   := "(" or_expr ")" | condition
+struct FilterParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> FilterParser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume a keyword if it is next, followed by whitespace, '(' or the end
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if starts_with_keyword(self.rest(), keyword) {
+            self.pos += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> anyhow::Result<MetadataFilter> {
+        let mut filters = vec![self.parse_and()?];
+        loop {
+            self.skip_whitespace();
+            if !self.eat_keyword("OR") {
+                break;
+            }
+            filters.push(self.parse_and()?);
+        }
+
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            MetadataFilter::Or { or: filters }
+        })
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<MetadataFilter> {
+        let mut filters = vec![self.parse_unary()?];
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with(',') {
+                self.pos += 1;
+            } else if !self.eat_keyword("AND") {
+                break;
+            }
+            filters.push(self.parse_unary()?);
+        }
+
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            MetadataFilter::And { and: filters }
+        })
+    }
+
+    fn parse_unary(&mut self) -> anyhow::Result<MetadataFilter> {
+        self.skip_whitespace();
+        if self.eat_keyword("NOT") {
+            let inner = self.parse_unary()?;
+            return Ok(MetadataFilter::Not { not: Box::new(inner) });
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> anyhow::Result<MetadataFilter> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with('(') {
+            let open = self.pos;
+            self.pos += 1;
+            let inner = self.parse_or()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with(')') {
+                anyhow::bail!(
+                    "Missing ')' to close '(' at position {} in filter: {}",
+                    open,
+                    self.input
+                );
+            }
+            self.pos += 1;
+            return Ok(inner);
+        }
+
+        let start = self.pos;
+        let condition = self.take_condition();
+        if condition.is_empty() {
+            match self.rest().chars().next() {
+                Some(c) => anyhow::bail!(
+                    "Expected a condition at position {} but found '{}' in filter: {}",
+                    start,
+                    c,
+                    self.input
+                ),
+                None => anyhow::bail!(
+                    "Expected a condition at end of filter: {}",
+                    self.input
+                ),
+            }
+        }
+
+        MetadataFilter::parse_single(condition).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid condition '{}' at position {} in filter: {}",
+                condition,
+                start,
+                self.input
+            )
+        })
+    }
+
+    /// Consume the text of a single condition
+    ///
+    /// A condition ends at a top-level ",", " AND ", " OR ", or an unmatched ")".
+    /// Brackets, quotes, and balanced parentheses (e.g. in regexes) are kept intact.
+    fn take_condition(&mut self) -> &'a str {
+        let rest = self.rest();
+        let mut paren_depth = 0usize;
+        let mut bracket_depth = 0usize;
+        let mut in_quotes = false;
+        let mut end = rest.len();
+
+        for (i, c) in rest.char_indices() {
+            if in_quotes {
+                if c == '"' {
+                    in_quotes = false;
+                }
+                continue;
+            }
+            match c {
+                '"' => in_quotes = true,
+                '[' => bracket_depth += 1,
+                ']' => bracket_depth = bracket_depth.saturating_sub(1),
+                '(' => paren_depth += 1,
+                ')' if paren_depth == 0 => {
+                    end = i;
+                    break;
+                }
+                ')' => paren_depth -= 1,
+                ',' if paren_depth == 0 && bracket_depth == 0 => {
+                    end = i;
+                    break;
+                }
+                c if c.is_whitespace() && paren_depth == 0 && bracket_depth == 0 => {
+                    let after = rest[i..].trim_start();
+                    if starts_with_keyword(after, "AND") || starts_with_keyword(after, "OR") {
+                        end = i;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let condition = rest[..end].trim_end();
+        self.pos += end;
+        condition
+    }
+}
+
+/// Whether `s` starts with `keyword` as a whole word
+fn starts_with_keyword(s: &str, keyword: &str) -> bool {
+    s.starts_with(keyword)
+        && s[keyword.len()..]
+            .chars()
+            .next()
+            .map_or(true, |c| c.is_whitespace() || c == '(')
+}
+
 impl FilterCondition {
-    fn matches(&self, metadata: &Value) -> bool {
+    pub fn new(field: String, op: FilterOp, value: Value) -> Self {
+        Self {
+            field,
+            op,
+            value,
+            regex: OnceLock::new(),
+        }
+    }
+
+    /// Compiled regex for a `Matches` condition (None if the pattern is invalid)
+    fn regex(&self) -> Option<&Regex> {
+        self.regex
+            .get_or_init(|| self.value.as_str().and_then(|p| Regex::new(p).ok()))
+            .as_ref()
+    }
+
+    /// Check if metadata matches this condition
+    pub fn matches(&self, metadata: &Value) -> bool {
         let field_value = get_nested_value(metadata, &self.field);
 
         match &self.op {
@@ -369,6 +591,28 @@ impl FilterCondition {
                     .and_then(|v| v.as_str())
                     .map_or(false, |s| s.ends_with(pattern))
             }
+            FilterOp::Matches => match (self.regex(), field_value.and_then(|v| v.as_str())) {
+                (Some(re), Some(s)) => re.is_match(s),
+                _ => false,
+            },
+            FilterOp::IContains => {
+                let pattern = self.value.as_str().unwrap_or("").to_lowercase();
+                field_value
+                    .and_then(|v| v.as_str())
+                    .map_or(false, |s| s.to_lowercase().contains(&pattern))
+            }
+            FilterOp::IStartsWith => {
+                let pattern = self.value.as_str().unwrap_or("").to_lowercase();
+                field_value
+                    .and_then(|v| v.as_str())
+                    .map_or(false, |s| s.to_lowercase().starts_with(&pattern))
+            }
+            FilterOp::IEndsWith => {
+                let pattern = self.value.as_str().unwrap_or("").to_lowercase();
+                field_value
+                    .and_then(|v| v.as_str())
+                    .map_or(false, |s| s.to_lowercase().ends_with(&pattern))
+            }
         }
     }
 }
@@ -400,8 +644,14 @@ fn values_equal(a: &Value, b: &Value) -> bool {
 }
 
 fn compare_values(a: &Value, b: &Value) -> i32 {
-    match (a.as_f64(), b.as_f64()) {
-        (Some(n1), Some(n2)) => {
+    // Numbers compare numerically; dates (ISO-8601 strings or epoch seconds) by time
+    let numeric = match (a.as_f64(), b.as_f64()) {
+        (Some(n1), Some(n2)) => Some((n1, n2)),
+        _ => as_timestamp(a).zip(as_timestamp(b)),
+    };
+
+    match numeric {
+        Some((n1, n2)) => {
             if n1 < n2 {
                 -1
             } else if n1 > n2 {
@@ -410,14 +660,35 @@ fn compare_values(a: &Value, b: &Value) -> i32 {
                 0
             }
         }
-        _ => match (a.as_str(), b.as_str()) {
+        None => match (a.as_str(), b.as_str()) {
             (Some(s1), Some(s2)) => s1.cmp(s2) as i32,
             _ => 0,
         },
     }
 }
 
+/// Interpret a value as Unix seconds: numbers as epoch time, strings as ISO-8601 dates
+fn as_timestamp(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => crate::time::parse_datetime(s).map(|t| t as f64),
+        _ => None,
+    }
+}
+
+/// Parse the right-hand side of a comparison, resolving relative times ("now-30d") once
+fn parse_comparison_value(s: &str) -> Value {
+    match crate::time::parse_relative(s, crate::time::now_unix()) {
+        Some(secs) => Value::Number(secs.into()),
+        None => parse_value(s),
+    }
+}
+
 fn parse_value(s: &str) -> Value {
+    // Quoted values are always strings (allows spaces, commas, and keywords)
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        return Value::String(s[1..s.len() - 1].to_string());
+    }
     // Try to parse as number
     if let Ok(n) = s.parse::<i64>() {
         return Value::Number(n.into());
@@ -449,6 +720,24 @@ mod tests {
         assert!(matches!(filter, MetadataFilter::Condition(_)));
     }
 
+    #[test]
+    fn test_in_namespace() {
+        let alice = json!({"namespace": "alice", "type": "code"});
+        let bob = json!({"namespace": "bob", "type": "code"});
+        let untagged = json!({"type": "code"});
+
+        let filter = MetadataFilter::in_namespace("alice", None);
+        assert!(filter.matches(&alice));
+        assert!(!filter.matches(&bob));
+        assert!(!filter.matches(&untagged));
+
+        let filter = MetadataFilter::in_namespace("bob", MetadataFilter::parse("type=doc"));
+        assert!(!filter.matches(&bob));
+        let filter = MetadataFilter::in_namespace("bob", MetadataFilter::parse("type=code"));
+        assert!(filter.matches(&bob));
+        assert!(!filter.matches(&alice));
+    }
+
     #[test]
     fn test_filter_matches() {
         let metadata = json!({
@@ -549,4 +838,90 @@ mod tests {
         let filter = MetadataFilter::parse("missing?").unwrap();
         assert!(!filter.matches(&metadata));
     }
+
+    #[test]
+    fn test_filter_regex() {
+        let metadata = json!({
+            "source": "/repo/docs/README.md"
+        });
+
+        let filter = MetadataFilter::parse("source=~(?i)readme").unwrap();
+        assert!(filter.matches(&metadata));
+
+        let filter = MetadataFilter::parse(r"source=~^/repo/src/.*\.rs$").unwrap();
+        assert!(!filter.matches(&metadata));
+
+        assert!(MetadataFilter::parse("source=~(unclosed").is_none());
+    }
+
+    #[test]
+    fn test_filter_case_insensitive() {
+        let metadata = json!({
+            "source": "/repo/docs/README.md"
+        });
+
+        assert!(MetadataFilter::parse("source~*readme").unwrap().matches(&metadata));
+        assert!(!MetadataFilter::parse("source~readme").unwrap().matches(&metadata));
+        assert!(MetadataFilter::parse("source^*/REPO/").unwrap().matches(&metadata));
+        assert!(MetadataFilter::parse("source$*.md").unwrap().matches(&metadata));
+    }
+
+    #[test]
+    fn test_filter_not_and_grouping() {
+        let metadata = json!({
+            "type": "code",
+            "source": "src/main.rs",
+            "lines": 100
+        });
+
+        let filter = MetadataFilter::parse("NOT type=test").unwrap();
+        assert!(filter.matches(&metadata));
+
+        let filter = MetadataFilter::parse("NOT (type=test OR source~fixtures) AND lines>50").unwrap();
+        assert!(filter.matches(&metadata));
+
+        let filter = MetadataFilter::parse("type=doc AND (lines>50 OR source$.rs)").unwrap();
+        assert!(!filter.matches(&metadata));
+
+        // AND binds tighter than OR
+        let filter = MetadataFilter::parse("type=doc AND lines>50 OR source$.rs").unwrap();
+        assert!(filter.matches(&metadata));
+    }
+
+    #[test]
+    fn test_filter_syntax_errors() {
+        let err = MetadataFilter::try_parse("(type=code").unwrap_err();
+        assert!(err.to_string().contains("Missing ')'"));
+
+        let err = MetadataFilter::try_parse("type=code)").unwrap_err();
+        assert!(err.to_string().contains("Unexpected ')'"));
+
+        assert!(MetadataFilter::try_parse("type=code AND").is_err());
+        assert!(MetadataFilter::try_parse("").is_err());
+        assert!(MetadataFilter::try_parse("nonsense").is_err());
+    }
+
+    #[test]
+    fn test_filter_quoted_value() {
+        let metadata = json!({ "title": "Getting Started, Part 1" });
+        let filter = MetadataFilter::parse(r#"title="Getting Started, Part 1""#).unwrap();
+        assert!(filter.matches(&metadata));
+    }
+
+    #[test]
+    fn test_filter_dates() {
+        let metadata = json!({
+            "modified": "2024-06-01T12:00:00Z",
+            "git_modified": crate::time::now_unix() - 86_400
+        });
+
+        assert!(MetadataFilter::parse("modified>2024-01-01").unwrap().matches(&metadata));
+        assert!(!MetadataFilter::parse("modified<2024-06-01").unwrap().matches(&metadata));
+        assert!(MetadataFilter::parse("modified<=2024-06-01T12:00:00Z").unwrap().matches(&metadata));
+
+        // Epoch field against relative and absolute dates
+        assert!(MetadataFilter::parse("git_modified>now-30d").unwrap().matches(&metadata));
+        assert!(!MetadataFilter::parse("git_modified>now-1h").unwrap().matches(&metadata));
+        assert!(MetadataFilter::parse("git_modified>2020-01-01").unwrap().matches(&metadata));
+    }
 }
@@ -0,0 +1,229 @@
+//! Knowledge graph stored next to an index
+//!
+//! Entities (functions, people, concepts, ...) and the relations between them,
+//! each pointing at the passages they were extracted from. Written at build
+//! time with `--graph` as `documents.graph.json`; retrieval walks it to pull in
+//! passages about the neighbors of matched entities.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// An entity and the passages mentioning it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub name: String,
+    /// What the entity is, e.g. `function` or `entity`
+    pub kind: String,
+    pub passages: Vec<String>,
+}
+
+/// A relation between two entities, by node position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub relation: String,
+    pub to: usize,
+    /// Passages stating the relation
+    pub passages: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct KnowledgeGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+    /// Node positions by lowercased name
+    #[serde(skip)]
+    lookup: HashMap<String, usize>,
+    /// Edge positions by (from, relation, to)
+    #[serde(skip)]
+    edge_lookup: HashMap<(usize, String, usize), usize>,
+}
+
+impl KnowledgeGraph {
+    fn path_for(index_path: &Path) -> std::path::PathBuf {
+        index_path.with_extension("graph.json")
+    }
+
+    /// Load the graph for an index, if it was built with one
+    pub fn load(index_path: &Path) -> anyhow::Result<Option<Self>> {
+        let path = Self::path_for(index_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let mut graph: Self = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse knowledge graph {}: {}", path.display(), e))?;
+        graph.lookup = graph.nodes.iter().enumerate().map(|(i, n)| (n.name.to_lowercase(), i)).collect();
+        graph.edge_lookup = graph.edges.iter().enumerate().map(|(i, e)| ((e.from, e.relation.clone(), e.to), i)).collect();
+        Ok(Some(graph))
+    }
+
+    pub fn save(&self, index_path: &Path) -> anyhow::Result<()> {
+        std::fs::write(Self::path_for(index_path), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[GraphEdge] {
+        &self.edges
+    }
+
+    /// Add an entity mentioned in `passage` (names are case-insensitive);
+    /// returns its position
+    pub fn add_node(&mut self, name: &str, kind: &str, passage: &str) -> usize {
+        let node = match self.lookup.get(&name.to_lowercase()) {
+            Some(&node) => node,
+            None => {
+                self.nodes.push(GraphNode { name: name.to_string(), kind: kind.to_string(), passages: Vec::new() });
+                self.lookup.insert(name.to_lowercase(), self.nodes.len() - 1);
+                self.nodes.len() - 1
+            }
+        };
+        add_passage(&mut self.nodes[node].passages, passage);
+        node
+    }
+
+    /// Add a relation stated in `passage`
+    pub fn add_edge(&mut self, from: usize, relation: &str, to: usize, passage: &str) {
+        if from == to {
+            return;
+        }
+        let key = (from, relation.to_string(), to);
+        match self.edge_lookup.get(&key) {
+            Some(&edge) => add_passage(&mut self.edges[edge].passages, passage),
+            None => {
+                self.edges.push(GraphEdge { from, relation: key.1.clone(), to, passages: vec![passage.to_string()] });
+                self.edge_lookup.insert(key, self.edges.len() - 1);
+            }
+        }
+    }
+
+    /// The entity with this name, ignoring case
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.lookup.get(&name.trim().to_lowercase()).copied()
+    }
+
+    /// Entities whose name contains `text`, ignoring case
+    pub fn find_containing(&self, text: &str) -> Vec<usize> {
+        let text = text.trim().to_lowercase();
+        (0..self.nodes.len()).filter(|&i| self.nodes[i].name.to_lowercase().contains(&text)).collect()
+    }
+
+    /// Entities named in `text` as whole words, ignoring case
+    pub fn mentioned_in(&self, text: &str) -> Vec<usize> {
+        let text = text.to_lowercase();
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let mut found: Vec<usize> = self
+            .lookup
+            .iter()
+            .filter(|(name, _)| {
+                text.match_indices(name.as_str()).any(|(at, _)| {
+                    !text[..at].ends_with(is_word) && !text[at + name.len()..].starts_with(is_word)
+                })
+            })
+            .map(|(_, &node)| node)
+            .collect();
+        found.sort_unstable();
+        found
+    }
+
+    /// Entities extracted from any of `passages`
+    pub fn in_passages(&self, passages: &HashSet<&str>) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&i| self.nodes[i].passages.iter().any(|p| passages.contains(p.as_str())))
+            .collect()
+    }
+
+    /// Relations touching a node, as (relation, other node, outgoing)
+    pub fn relations(&self, node: usize) -> impl Iterator<Item = (&GraphEdge, usize, bool)> + '_ {
+        self.edges.iter().filter_map(move |edge| {
+            if edge.from == node {
+                Some((edge, edge.to, true))
+            } else if edge.to == node {
+                Some((edge, edge.from, false))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Nodes within `hops` relations of `seeds` (in either direction), nearest
+    /// first, with their distance; the seeds themselves are not included
+    pub fn neighbors(&self, seeds: &[usize], hops: usize) -> Vec<(usize, usize)> {
+        let mut seen: HashSet<usize> = seeds.iter().copied().collect();
+        let mut queue: VecDeque<(usize, usize)> = seeds.iter().map(|&s| (s, 0)).collect();
+        let mut found = Vec::new();
+        while let Some((node, distance)) = queue.pop_front() {
+            if distance == hops {
+                continue;
+            }
+            for (_, other, _) in self.relations(node) {
+                if seen.insert(other) {
+                    found.push((other, distance + 1));
+                    queue.push_back((other, distance + 1));
+                }
+            }
+        }
+        found
+    }
+}
+
+fn add_passage(passages: &mut Vec<String>, passage: &str) {
+    if !passages.iter().any(|p| p == passage) {
+        passages.push(passage.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> KnowledgeGraph {
+        let mut graph = KnowledgeGraph::default();
+        let parse = graph.add_node("parse_expr", "function", "p1");
+        let lex = graph.add_node("next_token", "function", "p2");
+        let eval = graph.add_node("evaluate", "function", "p3");
+        graph.add_edge(parse, "calls", lex, "p1");
+        graph.add_edge(eval, "calls", parse, "p3");
+        graph.add_edge(eval, "calls", parse, "p3");
+        graph
+    }
+
+    #[test]
+    fn test_nodes_merge_by_name() {
+        let mut graph = sample();
+        assert_eq!(graph.add_node("Parse_Expr", "function", "p4"), 0);
+        assert_eq!(graph.nodes()[0].passages, ["p1", "p4"]);
+        assert_eq!(graph.edges().len(), 2);
+        assert_eq!(graph.find("NEXT_TOKEN"), Some(1));
+        assert_eq!(graph.find_containing("token"), [1]);
+    }
+
+    #[test]
+    fn test_neighbors_and_mentions() {
+        let graph = sample();
+        assert_eq!(graph.neighbors(&[1], 1), [(0, 1)]);
+        assert_eq!(graph.neighbors(&[1], 2), [(0, 1), (2, 2)]);
+        assert_eq!(graph.mentioned_in("Where is parse_expr called? Not parse_exprs."), [0]);
+        assert!(graph.mentioned_in("evaluated").is_empty());
+        assert_eq!(graph.in_passages(&HashSet::from(["p3"])), [2]);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("leann-graph-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let index_path = dir.join("documents.leann");
+        assert!(KnowledgeGraph::load(&index_path).unwrap().is_none());
+        sample().save(&index_path).unwrap();
+        let graph = KnowledgeGraph::load(&index_path).unwrap().unwrap();
+        assert_eq!(graph.find("evaluate"), Some(2));
+        assert_eq!(graph.edges(), sample().edges());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,18 @@
 //! Index location utilities
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::error::LeannError;
+
+/// Suffix of the directory a build is staged in until it completes
+const STAGING_SUFFIX: &str = ".tmp";
+
+/// Suffix of the directory keeping an index's previous build, for `leann rollback`
+const PREVIOUS_SUFFIX: &str = ".prev";
+
+/// Suffix of the directory holding an index's snapshots
+const SNAPSHOTS_SUFFIX: &str = ".snapshots";
 
 /// Find an index by name in the current project or global registry
 ///
@@ -29,12 +41,143 @@ pub fn find_index(name: &str) -> anyhow::Result<PathBuf> {
         }
     }
 
-    anyhow::bail!(
-        "Index '{}' not found. Run 'leann list' to see available indexes.",
-        name
+    Err(LeannError::IndexNotFound { name: name.to_string() }.into())
+}
+
+/// An index found on disk
+#[derive(Debug, Clone)]
+pub struct IndexLocation {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// List all local and global indexes that have metadata
+///
+/// Local indexes shadow global ones with the same name, matching `find_index`.
+pub fn list_indexes() -> Vec<IndexLocation> {
+    let mut roots = vec![PathBuf::from(".leann").join("indexes")];
+    if let Some(home) = dirs::home_dir() {
+        roots.push(home.join(".leann").join("indexes"));
+    }
+
+    let mut indexes: Vec<IndexLocation> = Vec::new();
+    for root in roots {
+        let Ok(entries) = std::fs::read_dir(&root) else {
+            continue;
+        };
+        let mut found: Vec<IndexLocation> = entries
+            .flatten()
+            .filter(|e| e.path().join("documents.leann.meta.json").exists())
+            .filter(|e| !is_build_artifact(&e.file_name().to_string_lossy()))
+            .map(|e| IndexLocation {
+                name: e.file_name().to_string_lossy().to_string(),
+                path: e.path(),
+            })
+            .filter(|loc| !indexes.iter().any(|existing| existing.name == loc.name))
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        indexes.extend(found);
+    }
+
+    indexes
+}
+
+/// Directory a new build of the index at `index_dir` is staged in
+pub fn staging_dir(index_dir: &Path) -> PathBuf {
+    with_suffix(index_dir, STAGING_SUFFIX)
+}
+
+/// Directory the previous build of the index at `index_dir` is kept in
+pub fn previous_dir(index_dir: &Path) -> PathBuf {
+    with_suffix(index_dir, PREVIOUS_SUFFIX)
+}
+
+/// Directory the snapshots of the index at `index_dir` are kept in
+pub fn snapshots_dir(index_dir: &Path) -> PathBuf {
+    with_suffix(index_dir, SNAPSHOTS_SUFFIX)
+}
+
+fn with_suffix(index_dir: &Path, suffix: &str) -> PathBuf {
+    let mut name = index_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    index_dir.with_file_name(name)
+}
+
+/// Whether a directory in an index registry holds a staged build, previous
+/// build, or snapshots rather than an index of its own
+pub fn is_build_artifact(name: &str) -> bool {
+    [STAGING_SUFFIX, PREVIOUS_SUFFIX, SNAPSHOTS_SUFFIX]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+}
+
+/// Fail for an index name a build artifact of another index would have, as
+/// building that index would delete or hide it (e.g. "docs.tmp" is where
+/// "docs" is staged)
+pub fn check_index_name(name: &str) -> anyhow::Result<()> {
+    if is_build_artifact(name) {
+        anyhow::bail!(
+            "Index name '{}' is reserved: names ending in {} are used for staged builds, previous builds, and snapshots",
+            name,
+            [STAGING_SUFFIX, PREVIOUS_SUFFIX, SNAPSHOTS_SUFFIX].join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Modification times of an index's metadata, which every update or rebuild
+/// rewrites, and of its relevance feedback file
+pub type IndexVersion = (Option<SystemTime>, Option<SystemTime>);
+
+/// Version of the index at `index_dir`; servers holding it loaded reload it
+/// (dropping their cached results) once this changes
+pub fn index_version(index_dir: &Path) -> IndexVersion {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    (
+        modified(&index_dir.join("documents.leann.meta.json")),
+        modified(&super::feedback_path(index_dir)),
     )
 }
 
+/// Move a completed staged build into place, keeping the build it replaces
+/// as the previous one
+pub fn commit_staged_build(index_dir: &Path) -> anyhow::Result<()> {
+    let staging = staging_dir(index_dir);
+    let previous = previous_dir(index_dir);
+    if index_dir.exists() {
+        if previous.exists() {
+            std::fs::remove_dir_all(&previous)?;
+        }
+        std::fs::rename(index_dir, &previous)?;
+    }
+    std::fs::rename(&staging, index_dir)?;
+    Ok(())
+}
+
+/// Swap the index at `index_dir` with its previous build
+///
+/// Rolling back twice returns to the newer build. An index left missing by an
+/// interrupted swap is restored from its previous build.
+pub fn rollback_build(index_dir: &Path) -> anyhow::Result<()> {
+    let previous = previous_dir(index_dir);
+    if !previous.exists() {
+        anyhow::bail!("No previous build of {} to roll back to", index_dir.display());
+    }
+
+    let staging = staging_dir(index_dir);
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    if index_dir.exists() {
+        std::fs::rename(index_dir, &staging)?;
+    }
+    std::fs::rename(&previous, index_dir)?;
+    if staging.exists() {
+        std::fs::rename(&staging, &previous)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -46,4 +189,57 @@ mod tests {
         let err = result.unwrap_err().to_string();
         assert!(err.contains("not found"));
     }
+
+    #[test]
+    fn test_commit_and_rollback() {
+        let root = std::env::temp_dir().join(format!("leann-locate-{}", std::process::id()));
+        let index_dir = root.join("docs");
+        let stage = |version: &str| {
+            let staging = staging_dir(&index_dir);
+            std::fs::create_dir_all(&staging).unwrap();
+            std::fs::write(staging.join("version"), version).unwrap();
+            commit_staged_build(&index_dir).unwrap();
+        };
+        let version = |dir: &Path| std::fs::read_to_string(dir.join("version")).unwrap();
+
+        stage("1");
+        assert_eq!(version(&index_dir), "1");
+        assert!(!previous_dir(&index_dir).exists());
+        assert!(rollback_build(&index_dir).is_err());
+
+        stage("2");
+        assert_eq!(version(&index_dir), "2");
+        assert_eq!(version(&previous_dir(&index_dir)), "1");
+        assert!(!staging_dir(&index_dir).exists());
+
+        rollback_build(&index_dir).unwrap();
+        assert_eq!(version(&index_dir), "1");
+        assert_eq!(version(&previous_dir(&index_dir)), "2");
+        rollback_build(&index_dir).unwrap();
+        assert_eq!(version(&index_dir), "2");
+
+        assert!(is_build_artifact("docs.tmp"));
+        assert!(is_build_artifact("docs.prev"));
+        assert!(is_build_artifact("docs.snapshots"));
+        assert!(!is_build_artifact("docs"));
+        assert!(check_index_name("docs.tmp").is_err());
+        assert!(check_index_name("docs.v2").is_ok());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_index_version_tracks_feedback() {
+        let root = std::env::temp_dir().join(format!("leann-version-{}", std::process::id()));
+        let index_dir = root.join("docs");
+        std::fs::create_dir_all(&index_dir).unwrap();
+        std::fs::write(index_dir.join("documents.leann.meta.json"), "{}").unwrap();
+
+        let loaded = index_version(&index_dir);
+        assert!(loaded.0.is_some() && loaded.1.is_none());
+        std::fs::write(crate::index::feedback_path(&index_dir), "{}\n").unwrap();
+        assert_ne!(index_version(&index_dir), loaded);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -1,16 +1,31 @@
 //! Index metadata handling
 
 use std::path::Path;
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
+use super::bm25::Bm25Analyzer;
+use crate::config::SearchConfig;
+use crate::backend::{Backend, BackendError, BuildParams, DiskAnnParams};
+use crate::embedding::{get_model_config, DocumentInstructions, EmbeddingTask, MultiVectorEmbedding, PostProcessing, SparseEmbedding};
+
+/// Current metadata format version
+///
+/// - 1.0: original format
+/// - 1.1: adds build time, chunking settings, text-free mode, and build provenance
+///
+/// All fields added after 1.0 are optional or defaulted, so older metadata
+/// files still deserialize.
+pub const META_VERSION: &str = "1.1";
+
 /// Index metadata stored alongside the index files
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexMeta {
     /// Metadata format version
     pub version: String,
 
-    /// Backend used (hnsw, diskann)
+    /// Backend used (a name in the backend registry: hnsw, diskann, ivfpq)
     pub backend_name: String,
 
     /// Embedding model name
@@ -33,6 +48,18 @@ pub struct IndexMeta {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub embedding_options: Option<serde_json::Value>,
 
+    /// Post-processing applied to embeddings at build time (none if absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_processing: Option<PostProcessing>,
+
+    /// Learned sparse model whose vectors are stored next to the index (none if absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparse_model: Option<SparseModelInfo>,
+
+    /// Multi-vector (ColBERT-style) model whose per-token vectors are stored next to the index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multi_vector: Option<MultiVectorInfo>,
+
     /// Whether this index supports embedding recomputation
     #[serde(default)]
     pub is_recompute: bool,
@@ -40,6 +67,260 @@ pub struct IndexMeta {
     /// Whether embeddings have been pruned (deleted to save space)
     #[serde(default)]
     pub is_pruned: bool,
+
+    /// Build time as seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+
+    /// Chunking settings used at build time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunking: Option<ChunkingInfo>,
+
+    /// Whether passage text is stored (false = text re-read from source files)
+    #[serde(default = "default_text_stored")]
+    pub text_stored: bool,
+
+    /// Passage store format (`jsonl` if absent; readers detect it from the files)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passage_store: Option<String>,
+
+    /// Document roots, collection settings, and file manifest from build time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<BuildProvenance>,
+
+    /// Default prompt templates for LLM commands on this index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_templates: Option<PromptTemplates>,
+
+    /// BM25 text analysis for hybrid search (plain words if absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bm25_analyzer: Option<Bm25Analyzer>,
+
+    /// Recommended search settings for this index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_defaults: Option<SearchDefaults>,
+}
+
+fn default_text_stored() -> bool {
+    true
+}
+
+/// How the index was built, so later commands can reuse the original settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildProvenance {
+    /// Document roots passed to `--docs` (absolute where resolvable)
+    #[serde(default)]
+    pub docs: Vec<String>,
+
+    /// File types included (`--file-types`), if restricted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_types: Option<Vec<String>>,
+
+    /// File types excluded (`--exclude-types`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude_types: Option<Vec<String>>,
+
+    /// Glob patterns files had to match (`--include` and config), if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<String>>,
+
+    /// Glob patterns of files skipped (`--exclude` and config), if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<Vec<String>>,
+
+    /// Whether hidden files were included
+    #[serde(default)]
+    pub include_hidden: bool,
+
+    /// Whether symbolic links were followed
+    #[serde(default)]
+    pub follow_symlinks: bool,
+
+    /// Maximum file size in KB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_size_kb: Option<usize>,
+
+    /// Maximum number of files indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<usize>,
+
+    /// Total size in MB files were collected up to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_size_mb: Option<usize>,
+
+    /// Size limit in KB for data files (JSON, YAML, TOML), if different
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_max_file_size_kb: Option<usize>,
+
+    /// Metadata enrichers applied to chunks (`--enrichers`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enrichers: Option<Vec<String>>,
+
+    /// Kind of source indexed (`--source`), when not plain document files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    /// Namespace chunks were tagged with (`--namespace`), if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+
+    /// Files indexed, with enough detail to detect changes later
+    #[serde(default)]
+    pub files: Vec<FileManifestEntry>,
+}
+
+/// Learned sparse embedding model used at build time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SparseModelInfo {
+    /// Model name (e.g. naver/splade-v3)
+    pub model: String,
+
+    /// text-embeddings-inference server the vectors came from, if given at build time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+impl SparseModelInfo {
+    /// Provider for this model, preferring `host` over the one recorded at build time
+    pub fn provider(&self, host: Option<String>) -> SparseEmbedding {
+        SparseEmbedding::new(&self.model, host.or_else(|| self.host.clone()))
+    }
+}
+
+/// Multi-vector (late-interaction) model used at build time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiVectorInfo {
+    /// Model name (e.g. jina-colbert-v2)
+    pub model: String,
+
+    /// Dimensions of each token vector
+    pub dimensions: usize,
+}
+
+impl MultiVectorInfo {
+    /// Provider for this model
+    pub fn provider(&self, api_key: Option<String>) -> anyhow::Result<MultiVectorEmbedding> {
+        MultiVectorEmbedding::new(self.model.clone(), self.dimensions, api_key)
+    }
+}
+
+/// Per-index prompt templates (template source, not file paths)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptTemplates {
+    /// Template for `leann ask`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ask: Option<String>,
+
+    /// Template for `leann react`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub react: Option<String>,
+}
+
+/// Search settings recorded at build time, used by `search`, `ask`, `serve`,
+/// and `mcp` in place of the `[search]` config unless a flag or request sets
+/// them (the query prefix is the `query_prompt_template` embedding option)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<usize>,
+
+    /// Hybrid (vector + BM25) search for every query, or for none (not even
+    /// short ones); automatic for short queries if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hybrid: Option<bool>,
+
+    /// Weight of the vector scores in hybrid search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hybrid_alpha: Option<f32>,
+}
+
+impl SearchDefaults {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A single indexed file in the build manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileManifestEntry {
+    /// Canonical path of the file (see `manifest_path`); passage `source`
+    /// metadata keeps the path as walked, which may be relative
+    pub path: String,
+
+    /// File size in bytes
+    pub size: u64,
+
+    /// Last modification time as seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+
+    /// Content hash (see `chunker::content_hash`)
+    pub hash: String,
+
+    /// Number of chunks produced from this file
+    pub chunks: usize,
+}
+
+impl FileManifestEntry {
+    /// Create a manifest entry for a file whose content has already been read
+    pub fn for_file(path: &Path, content: &str, chunks: usize) -> Self {
+        let modified = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+
+        Self {
+            path: manifest_path(path),
+            size: content.len() as u64,
+            modified,
+            hash: crate::chunker::content_hash(content),
+            chunks,
+        }
+    }
+}
+
+/// Form of `path` recorded in the manifest, so files walked from relative
+/// and absolute roots (or a passage `source`) match their entries: canonical
+/// if the file exists, else under its canonical directory, else absolute
+pub fn manifest_path(path: &Path) -> String {
+    let resolved = path.canonicalize().ok().or_else(|| {
+        let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        Some(dir.canonicalize().ok()?.join(path.file_name()?))
+    });
+    resolved
+        .or_else(|| std::path::absolute(path).ok())
+        .unwrap_or_else(|| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Chunking settings recorded at build time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkingInfo {
+    /// Chunking strategy (simple, ast, auto)
+    pub strategy: String,
+
+    /// Chunk size in tokens
+    pub chunk_size: usize,
+
+    /// Chunk overlap in tokens
+    pub chunk_overlap: usize,
+
+    /// Policy for chunks over the model's input limit (error, truncate, split)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length_policy: Option<String>,
+
+    /// Token limit the length policy was applied with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_chunk_tokens: Option<usize>,
+
+    /// Policy for chunks containing secrets (skip, redact, allow)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<String>,
 }
 
 impl IndexMeta {
@@ -56,4 +337,216 @@ impl IndexMeta {
         std::fs::write(path, content)?;
         Ok(())
     }
+
+    /// The registered backend this index was built with
+    pub fn backend(&self) -> Result<Arc<dyn Backend>, BackendError> {
+        crate::backend::resolve(&self.backend_name)
+    }
+
+    /// Graph parameters the index was built with, defaulting any not recorded
+    pub fn build_params(&self) -> BuildParams {
+        let defaults = BuildParams::default();
+        let kwargs = self.backend_kwargs.as_ref();
+        let get = |key: &str| kwargs.and_then(|k| k.get(key));
+        BuildParams {
+            graph_degree: get("graph_degree").and_then(|v| v.as_u64()).map_or(defaults.graph_degree, |v| v as usize),
+            complexity: get("complexity").and_then(|v| v.as_u64()).map_or(defaults.complexity, |v| v as usize),
+            diskann: DiskAnnParams {
+                alpha: get("prune_alpha").and_then(|v| v.as_f64()).map_or(defaults.diskann.alpha, |v| v as f32),
+                beam_width: get("beam_width").and_then(|v| v.as_u64()).map_or(defaults.diskann.beam_width, |v| v as usize),
+            },
+        }
+    }
+
+    /// Number of shards the vector index was split into, if sharded
+    pub fn shard_count(&self) -> Option<usize> {
+        self.backend_kwargs.as_ref()?.get("shards")?.as_u64().map(|n| n as usize)
+    }
+
+    /// Post-processing to apply to new embeddings so they match the index
+    pub fn post_processing(&self) -> PostProcessing {
+        self.post_processing.clone().unwrap_or_default()
+    }
+
+    /// Prompt template for embedding text for `task` with this index's model
+    ///
+    /// Uses the template recorded at build time, falling back to the model's
+    /// default prefix.
+    pub fn embedding_template(&self, task: EmbeddingTask) -> String {
+        let key = match task {
+            EmbeddingTask::Document => Some("build_prompt_template"),
+            EmbeddingTask::Query => Some("query_prompt_template"),
+            EmbeddingTask::Clustering => None,
+        };
+
+        key.and_then(|key| self.embedding_options.as_ref()?.get(key)?.as_str())
+            .map(|template| template.to_string())
+            .unwrap_or_else(|| get_model_config(&self.embedding_model).prefix(task).to_string())
+    }
+
+    /// `[search]` settings with this index's recorded defaults in their place
+    pub fn search_config(&self, config: &SearchConfig) -> SearchConfig {
+        let Some(defaults) = &self.search_defaults else {
+            return config.clone();
+        };
+        SearchConfig {
+            top_k: defaults.top_k.unwrap_or(config.top_k),
+            complexity: defaults.complexity.unwrap_or(config.complexity),
+            hybrid_alpha: defaults.hybrid_alpha.unwrap_or(config.hybrid_alpha),
+        }
+    }
+
+    /// Whether the index recommends hybrid search for every query (`Some(true)`)
+    /// or for none (`Some(false)`)
+    pub fn default_hybrid(&self) -> Option<bool> {
+        self.search_defaults.as_ref()?.hybrid
+    }
+
+    /// Document templates by file type the index was built with
+    /// (`[embedding.instructions]` in config.toml)
+    pub fn document_instructions(&self) -> DocumentInstructions {
+        self.embedding_options
+            .as_ref()
+            .and_then(|options| options.get("document_instructions"))
+            .map(DocumentInstructions::from_json)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_v1_0_meta() {
+        // Metadata written before provenance fields existed
+        let json = r#"{
+            "version": "1.0",
+            "backend_name": "hnsw",
+            "embedding_model": "nomic-embed-text",
+            "embedding_mode": "ollama",
+            "dimensions": 768,
+            "passage_count": 42
+        }"#;
+        let meta: IndexMeta = serde_json::from_str(json).unwrap();
+        assert_eq!(meta.passage_count, 42);
+        assert!(meta.text_stored);
+        assert!(meta.chunking.is_none());
+        assert!(meta.provenance.is_none());
+        assert!(meta.prompt_templates.is_none());
+        assert!(meta.sparse_model.is_none());
+        assert!(meta.multi_vector.is_none());
+    }
+
+    #[test]
+    fn test_embedding_template() {
+        let json = r#"{
+            "version": "1.1",
+            "backend_name": "hnsw",
+            "embedding_model": "nomic-embed-text",
+            "embedding_mode": "ollama",
+            "dimensions": 768,
+            "passage_count": 1
+        }"#;
+        let mut meta: IndexMeta = serde_json::from_str(json).unwrap();
+        assert_eq!(meta.embedding_template(EmbeddingTask::Query), "search_query: ");
+
+        meta.embedding_options = Some(serde_json::json!({
+            "query_prompt_template": "Instruct: find code\nQuery: {text}",
+            "build_prompt_template": "",
+        }));
+        assert_eq!(meta.embedding_template(EmbeddingTask::Query), "Instruct: find code\nQuery: {text}");
+        assert_eq!(meta.embedding_template(EmbeddingTask::Document), "");
+        assert_eq!(meta.embedding_template(EmbeddingTask::Clustering), "clustering: ");
+    }
+
+    #[test]
+    fn test_provenance_roundtrip() {
+        let json = r#"{
+            "version": "1.1",
+            "backend_name": "hnsw",
+            "embedding_model": "nomic-embed-text",
+            "embedding_mode": "ollama",
+            "dimensions": 768,
+            "passage_count": 1,
+            "provenance": {
+                "docs": ["/tmp/docs"],
+                "include_hidden": true,
+                "files": [{"path": "/tmp/docs/a.md", "size": 10, "hash": "abc", "chunks": 1}]
+            }
+        }"#;
+        let meta: IndexMeta = serde_json::from_str(json).unwrap();
+        let provenance = meta.provenance.as_ref().unwrap();
+        assert_eq!(provenance.docs, vec!["/tmp/docs".to_string()]);
+        assert!(provenance.include_hidden);
+        assert_eq!(provenance.files[0].chunks, 1);
+        assert!(provenance.files[0].modified.is_none());
+
+        let serialized = serde_json::to_string(&meta).unwrap();
+        let reparsed: IndexMeta = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(reparsed.provenance.unwrap().files.len(), 1);
+    }
+
+    #[test]
+    fn test_manifest_path() {
+        let dir = std::env::temp_dir().join(format!("leann-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        let canonical = dir.canonicalize().unwrap();
+
+        // Existing files and deleted ones in an existing directory resolve the same way
+        let expected = canonical.join("a.md").to_string_lossy().to_string();
+        assert_eq!(manifest_path(&dir.join("./a.md")), expected);
+        assert_eq!(manifest_path(Path::new(&expected)), expected);
+        assert_eq!(manifest_path(&dir.join("gone.md")), canonical.join("gone.md").to_string_lossy());
+        assert!(Path::new(&manifest_path(Path::new("no-such-dir/x.md"))).is_absolute());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_params() {
+        let json = r#"{
+            "version": "1.1",
+            "backend_name": "diskann",
+            "embedding_model": "nomic-embed-text",
+            "embedding_mode": "ollama",
+            "dimensions": 768,
+            "passage_count": 1,
+            "backend_kwargs": {"graph_degree": 48, "complexity": 100, "prune_alpha": 1.5, "shards": 3}
+        }"#;
+        let mut meta: IndexMeta = serde_json::from_str(json).unwrap();
+        let params = meta.build_params();
+        assert_eq!(params.graph_degree, 48);
+        assert_eq!(params.complexity, 100);
+        assert_eq!(params.diskann.alpha, 1.5);
+        assert_eq!(params.diskann.beam_width, DiskAnnParams::default().beam_width);
+        assert_eq!(meta.shard_count(), Some(3));
+
+        meta.backend_kwargs = None;
+        assert_eq!(meta.build_params(), BuildParams::default());
+        assert_eq!(meta.shard_count(), None);
+    }
+
+    #[test]
+    fn test_search_defaults() {
+        let json = r#"{
+            "version": "1.1",
+            "backend_name": "hnsw",
+            "embedding_model": "nomic-embed-text",
+            "embedding_mode": "ollama",
+            "dimensions": 768,
+            "passage_count": 1,
+            "search_defaults": {"top_k": 12, "hybrid": true}
+        }"#;
+        let mut meta: IndexMeta = serde_json::from_str(json).unwrap();
+        let config = SearchConfig { top_k: 5, complexity: 64, hybrid_alpha: 0.7 };
+        let search = meta.search_config(&config);
+        assert_eq!((search.top_k, search.complexity, search.hybrid_alpha), (12, 64, 0.7));
+        assert_eq!(meta.default_hybrid(), Some(true));
+
+        meta.search_defaults = None;
+        assert_eq!(meta.search_config(&config).top_k, 5);
+        assert_eq!(meta.default_hybrid(), None);
+    }
 }
@@ -5,18 +5,51 @@ mod passages;
 mod builder;
 mod searcher;
 mod filter;
+mod field_index;
+mod graph;
+mod diversify;
 mod bm25;
+mod boost;
+mod explain;
+mod feedback;
+mod sparse;
+mod synonyms;
+mod spelling;
+mod multi_vector;
 mod embeddings;
 mod recompute;
 mod locate;
+mod remote;
+mod snapshot;
 mod query;
+mod query_log;
+mod result_cache;
+mod checkpoint;
 
-pub use meta::IndexMeta;
-pub use builder::{IndexBuilder, StreamingIndexBuilder};
-pub use passages::{Passage, PassageStore};
-pub use searcher::{IndexSearcher, SearchOptions, SearchResult};
-pub use filter::MetadataFilter;
+pub use bm25::{has_stemmer, parse_bm25_fields, Bm25Analyzer, Bm25Tokenizer};
+pub use boost::Boost;
+pub use explain::SearchExplanation;
+pub use meta::{manifest_path, BuildProvenance, ChunkingInfo, FileManifestEntry, IndexMeta, MultiVectorInfo, PromptTemplates, SearchDefaults, SparseModelInfo, META_VERSION};
+pub use builder::{text_free_passage, IndexBuilder, StreamingIndexBuilder};
+pub use passages::{Passage, PassageFormat, PassageStore};
+pub use searcher::{aggregate_by_source, FilterMode, IndexSearcher, SearchOptions, SearchResult};
+pub use filter::{MetadataFilter, NAMESPACE_FIELD};
+pub use field_index::FieldIndex;
+pub use graph::KnowledgeGraph;
+pub use sparse::SparseIndex;
+pub use synonyms::Synonyms;
+pub use multi_vector::MultiVectorWriter;
 pub use embeddings::{EmbeddingsStore, prune_embeddings};
 pub use recompute::RecomputeSearcher;
-pub use locate::find_index;
-pub use query::{expand_from_passages, should_expand};
+pub use locate::{
+    check_index_name, commit_staged_build, find_index, index_version, is_build_artifact, list_indexes, previous_dir,
+    rollback_build, snapshots_dir, staging_dir, IndexVersion,
+};
+pub use remote::resolve_index;
+pub use snapshot::{create_snapshot, find_snapshot, list_snapshots, restore_snapshot};
+pub use query::{expand_from_passages, extract_calls, extract_definitions, extract_imports, should_expand};
+pub use feedback::{feedback_path, read_feedback, record_feedback, FeedbackEntry};
+pub use query_log::{log_query, log_used, query_log_path, read_query_log, QueryAnalytics, QueryLogEntry};
+#[cfg(feature = "server")]
+pub use result_cache::LruCache;
+pub use checkpoint::{checkpoint_path, CheckpointHeader, EmbeddingCheckpoint};
@@ -0,0 +1,264 @@
+//! Multi-vector (ColBERT-style) storage and late-interaction scoring
+//!
+//! Stores every passage's per-token vectors in `documents.leann.colbert` and
+//! reranks dense candidates by MaxSim: each query token is matched to its most
+//! similar passage token, and the similarities are averaged. Layout
+//! (little-endian):
+//!
+//! ```text
+//! magic "LMVS" | version u32 | dims u32 | token vectors (f32 * dims)...
+//! | token offsets u64 * (passages + 1) | passages u64 | offsets position u64
+//! ```
+//!
+//! The offset table sits at the end so passages can be streamed in and
+//! appended later.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+
+const MAGIC: &[u8; 4] = b"LMVS";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: u64 = 12;
+const FOOTER_LEN: u64 = 16;
+
+/// Dense candidates reranked by MaxSim
+pub const RERANK_CANDIDATES: usize = 100;
+
+fn path_for(index_path: &Path) -> PathBuf {
+    index_path.with_extension("colbert")
+}
+
+/// Read-only, memory-mapped per-token vectors
+pub struct MultiVectorStore {
+    mmap: Mmap,
+    dimensions: usize,
+    /// Token offsets; passage `i` spans tokens `offsets[i]..offsets[i + 1]`
+    offsets: Vec<u64>,
+}
+
+impl MultiVectorStore {
+    /// Open the store next to an index, if it has one
+    pub fn open(index_path: &Path) -> anyhow::Result<Option<Self>> {
+        let path = path_for(index_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = File::open(&path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let (dimensions, offsets) = read_layout(&mmap)
+            .map_err(|e| anyhow::anyhow!("Invalid multi-vector store {:?}: {}", path, e))?;
+        Ok(Some(Self {
+            mmap,
+            dimensions,
+            offsets,
+        }))
+    }
+
+    /// Number of passages stored
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Token vectors of the passage at `idx` (position in the ID mapping)
+    pub fn get(&self, idx: usize) -> Option<Vec<Vec<f32>>> {
+        let (start, end) = (*self.offsets.get(idx)?, *self.offsets.get(idx + 1)?);
+        let token_bytes = self.dimensions * 4;
+        let tokens = (start..end)
+            .map(|token| {
+                let offset = HEADER_LEN as usize + token as usize * token_bytes;
+                self.mmap[offset..offset + token_bytes]
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect()
+            })
+            .collect();
+        Some(tokens)
+    }
+}
+
+/// Parse the header and offset table, checking that they agree with the file size
+fn read_layout(bytes: &[u8]) -> anyhow::Result<(usize, Vec<u64>)> {
+    let read_u32 = |at: usize| -> anyhow::Result<u32> {
+        let b = bytes.get(at..at + 4).ok_or_else(|| anyhow::anyhow!("truncated file"))?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let read_u64 = |at: usize| -> anyhow::Result<u64> {
+        let b = bytes.get(at..at + 8).ok_or_else(|| anyhow::anyhow!("truncated file"))?;
+        Ok(u64::from_le_bytes(b.try_into().unwrap()))
+    };
+
+    if bytes.get(..4) != Some(&MAGIC[..]) {
+        anyhow::bail!("not a multi-vector store");
+    }
+    let version = read_u32(4)?;
+    if version != FORMAT_VERSION {
+        anyhow::bail!("unsupported format version {}", version);
+    }
+    let dimensions = read_u32(8)? as usize;
+    if dimensions == 0 || (bytes.len() as u64) < HEADER_LEN + FOOTER_LEN {
+        anyhow::bail!("truncated file");
+    }
+
+    let footer = bytes.len() - FOOTER_LEN as usize;
+    let count = read_u64(footer)? as usize;
+    let offsets_pos = read_u64(footer + 8)? as usize;
+    if offsets_pos < HEADER_LEN as usize || offsets_pos + (count + 1) * 8 != footer {
+        anyhow::bail!("corrupt offset table");
+    }
+    let offsets: Vec<u64> = (0..=count)
+        .map(|i| read_u64(offsets_pos + i * 8))
+        .collect::<anyhow::Result<_>>()?;
+
+    let data_tokens = (offsets_pos as u64 - HEADER_LEN) / (dimensions as u64 * 4);
+    if offsets.windows(2).any(|w| w[0] > w[1]) || offsets[count] != data_tokens {
+        anyhow::bail!("corrupt offset table");
+    }
+    Ok((dimensions, offsets))
+}
+
+/// Streaming writer for per-token vectors
+pub struct MultiVectorWriter {
+    writer: BufWriter<File>,
+    dimensions: usize,
+    offsets: Vec<u64>,
+}
+
+impl MultiVectorWriter {
+    /// Create a new store next to an index
+    pub fn create(index_path: &Path, dimensions: usize) -> anyhow::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path_for(index_path))?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(dimensions as u32).to_le_bytes())?;
+        Ok(Self {
+            writer,
+            dimensions,
+            offsets: vec![0],
+        })
+    }
+
+    /// Reopen an existing store to add passages after the stored ones
+    pub fn open_for_append(index_path: &Path) -> anyhow::Result<Self> {
+        let path = path_for(index_path);
+        let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let (dimensions, offsets) = read_layout(&bytes)
+            .map_err(|e| anyhow::anyhow!("Invalid multi-vector store {:?}: {}", path, e))?;
+
+        // New tokens overwrite the offset table and footer, which `finish` rewrites;
+        // the file is unchanged until something is added
+        let data_end = HEADER_LEN + offsets[offsets.len() - 1] * dimensions as u64 * 4;
+        file.seek(SeekFrom::Start(data_end))?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            dimensions,
+            offsets,
+        })
+    }
+
+    /// Number of passages written so far (including any already stored)
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Append the next passage's token vectors
+    pub fn add(&mut self, tokens: &[Vec<f32>]) -> anyhow::Result<()> {
+        if let Some(token) = tokens.iter().find(|t| t.len() != self.dimensions) {
+            anyhow::bail!(
+                "Token vector dimension mismatch: expected {}, got {}",
+                self.dimensions,
+                token.len()
+            );
+        }
+        for token in tokens {
+            for value in token {
+                self.writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        let last = self.offsets[self.offsets.len() - 1];
+        self.offsets.push(last + tokens.len() as u64);
+        Ok(())
+    }
+
+    /// Write the offset table and footer
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        let offsets_pos = HEADER_LEN + self.offsets[self.offsets.len() - 1] * self.dimensions as u64 * 4;
+        for offset in &self.offsets {
+            self.writer.write_all(&offset.to_le_bytes())?;
+        }
+        self.writer.write_all(&((self.offsets.len() - 1) as u64).to_le_bytes())?;
+        self.writer.write_all(&offsets_pos.to_le_bytes())?;
+        self.writer.flush()?;
+
+        // Drop any leftover bytes of a longer offset table (appending no passages)
+        let file = self.writer.get_mut();
+        let end = file.stream_position()?;
+        file.set_len(end)?;
+        Ok(())
+    }
+}
+
+/// Late-interaction score: the mean over query tokens of their best dot
+/// product with any passage token (cosine similarity for unit vectors)
+pub fn maxsim(query: &[Vec<f32>], passage: &[Vec<f32>]) -> f32 {
+    if query.is_empty() || passage.is_empty() {
+        return 0.0;
+    }
+    let total: f32 = query
+        .iter()
+        .map(|q| {
+            passage
+                .iter()
+                .map(|p| q.iter().zip(p).map(|(a, b)| a * b).sum::<f32>())
+                .fold(f32::NEG_INFINITY, f32::max)
+        })
+        .sum();
+    total / query.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maxsim() {
+        let query = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        let passage = vec![vec![1.0, 0.0], vec![0.6, 0.8]];
+        // Best matches: 1.0 for the first query token, 0.8 for the second
+        assert!((maxsim(&query, &passage) - 0.9).abs() < 1e-6);
+        assert_eq!(maxsim(&query, &[]), 0.0);
+    }
+
+    #[test]
+    fn test_write_append_read() {
+        let dir = std::env::temp_dir().join(format!("leann-mv-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let index_path = dir.join("documents.leann");
+
+        let mut writer = MultiVectorWriter::create(&index_path, 2).unwrap();
+        writer.add(&[vec![1.0, 0.0], vec![0.0, 1.0]]).unwrap();
+        writer.add(&[]).unwrap();
+        assert!(writer.add(&[vec![1.0]]).is_err());
+        writer.finish().unwrap();
+
+        let mut writer = MultiVectorWriter::open_for_append(&index_path).unwrap();
+        assert_eq!(writer.len(), 2);
+        writer.add(&[vec![0.5, 0.5]]).unwrap();
+        writer.finish().unwrap();
+
+        let store = MultiVectorStore::open(&index_path).unwrap().unwrap();
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(0), Some(vec![vec![1.0, 0.0], vec![0.0, 1.0]]));
+        assert_eq!(store.get(1), Some(Vec::new()));
+        assert_eq!(store.get(2), Some(vec![vec![0.5, 0.5]]));
+        assert_eq!(store.get(3), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,12 +1,29 @@
-//! Passage storage - JSONL format with offset index
-
-use std::collections::HashMap;
+//! Passage storage - JSONL or compressed records with an offset index
+//!
+//! The default store is `passages.jsonl` plus `passages.idx.json`, which maps
+//! passage ids to byte offsets in the JSONL file. Version 2 of the offset index
+//! also lists deleted passages (tombstones), so passages can be removed without
+//! rewriting the JSONL file; `leann compact` drops them from the file. Version 1
+//! files (a bare id -> offset map) are still read.
+//!
+//! `passages.idx.bin` holds the same offsets as a table sorted by id, which
+//! readers memory-map and binary-search instead of parsing the JSON index.
+//!
+//! The compact store (`--passage-store compact`) keeps passages in
+//! `passages.bin`, a sequence of length-prefixed zstd-compressed JSON records,
+//! with only the binary offset table. Readers pick the format from the files
+//! present.
+
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 
+use memmap2::Mmap;
 use serde::{Deserialize, Serialize};
 
+use crate::error::LeannError;
+
 /// A single passage with text and metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Passage {
@@ -16,61 +33,545 @@ pub struct Passage {
     pub metadata: serde_json::Value,
 }
 
-/// Passage store using JSONL file with JSON offset index
-pub struct PassageStore {
-    /// Offset map: passage_id -> byte offset in JSONL file
+/// On-disk format of a passage store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PassageFormat {
+    /// One JSON passage per line, with a JSON offset index
+    #[default]
+    Jsonl,
+    /// Length-prefixed zstd-compressed records, with a binary offset table
+    Compact,
+}
+
+impl PassageFormat {
+    /// Format of the store at `base_path`, from the files present
+    pub fn detect(base_path: &Path) -> Self {
+        if Self::Compact.data_path(base_path).exists() {
+            Self::Compact
+        } else {
+            Self::Jsonl
+        }
+    }
+
+    fn data_path(self, base_path: &Path) -> PathBuf {
+        match self {
+            Self::Jsonl => base_path.with_extension("passages.jsonl"),
+            Self::Compact => base_path.with_extension("passages.bin"),
+        }
+    }
+
+    fn idx_path(self, base_path: &Path) -> PathBuf {
+        match self {
+            Self::Jsonl => base_path.with_extension("passages.idx.json"),
+            Self::Compact => base_path.with_extension("passages.idx.bin"),
+        }
+    }
+
+    /// Name used on the command line and in index metadata
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Compact => "compact",
+        }
+    }
+
+    /// Encode a passage's JSON as one record
+    fn encode(self, json: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Jsonl => {
+                let mut record = Vec::with_capacity(json.len() + 1);
+                record.extend_from_slice(json);
+                record.push(b'\n');
+                Ok(record)
+            }
+            Self::Compact => {
+                let compressed = zstd::bulk::compress(json, ZSTD_LEVEL)?;
+                let mut record = Vec::with_capacity(compressed.len() + 4);
+                record.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+                record.extend_from_slice(&compressed);
+                Ok(record)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for PassageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for PassageFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jsonl" => Ok(Self::Jsonl),
+            "compact" => Ok(Self::Compact),
+            _ => anyhow::bail!("Unknown passage store format: {} (expected jsonl or compact)", s),
+        }
+    }
+}
+
+/// zstd level for compact records; higher levels gain little on short passages
+const ZSTD_LEVEL: i32 = 3;
+
+/// Current version of the offset index format
+const IDX_VERSION: u32 = 2;
+
+/// Magic bytes and version of the binary offset table
+const BIN_IDX_MAGIC: &[u8; 4] = b"LPIX";
+const BIN_IDX_VERSION: u32 = 1;
+
+/// Offset index file contents
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum OffsetIndex {
+    Versioned {
+        version: u32,
+        offsets: HashMap<String, u64>,
+        /// Tombstoned passage ids, still present in the JSONL file
+        #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+        deleted: HashSet<String>,
+    },
+    /// Version 1: a bare id -> offset map
+    Legacy(HashMap<String, u64>),
+}
+
+/// Read a JSON offset index, returning the offsets and tombstoned ids
+fn read_json_offsets(idx_path: &Path) -> anyhow::Result<(HashMap<String, u64>, HashSet<String>)> {
+    let idx_content = std::fs::read_to_string(idx_path)?;
+    match serde_json::from_str(&idx_content)? {
+        OffsetIndex::Versioned { version, .. } if version > IDX_VERSION => Err(LeannError::FormatIncompatible {
+            path: idx_path.to_path_buf(),
+            found: version.into(),
+            supported: IDX_VERSION.into(),
+        }
+        .into()),
+        OffsetIndex::Versioned { offsets, mut deleted, .. } => {
+            deleted.retain(|id| offsets.contains_key(id));
+            Ok((offsets, deleted))
+        }
+        OffsetIndex::Legacy(offsets) => Ok((offsets, HashSet::new())),
+    }
+}
+
+/// Read the offset index of the store at `base_path` into memory
+fn read_offset_index(base_path: &Path, format: PassageFormat) -> anyhow::Result<(HashMap<String, u64>, HashSet<String>)> {
+    match format {
+        PassageFormat::Jsonl => read_json_offsets(&format.idx_path(base_path)),
+        PassageFormat::Compact => {
+            let table = MappedOffsets::open(&format.idx_path(base_path))?;
+            let deleted = table.deleted()?;
+            Ok((table.to_map(), deleted))
+        }
+    }
+}
+
+/// Write the offset index of the store at `base_path`
+///
+/// JSONL stores keep `passages.idx.json` (which Python LEANN reads) and get the
+/// binary table as a sidecar for fast opening; compact stores only have the table.
+fn write_offset_index(
+    base_path: &Path,
+    format: PassageFormat,
     offsets: HashMap<String, u64>,
+    deleted: HashSet<String>,
+) -> anyhow::Result<()> {
+    let data_len = std::fs::metadata(format.data_path(base_path))?.len();
+    write_offset_table(&table_path(base_path), &offsets, &deleted, data_len)?;
+    if format == PassageFormat::Jsonl {
+        let index = OffsetIndex::Versioned { version: IDX_VERSION, offsets, deleted };
+        std::fs::write(format.idx_path(base_path), serde_json::to_string(&index)?)?;
+    }
+    Ok(())
+}
+
+/// Path of the binary offset table
+fn table_path(base_path: &Path) -> PathBuf {
+    base_path.with_extension("passages.idx.bin")
+}
+
+/// Size of the offset table header: magic, version, entry count, key bytes, data file length
+const TABLE_HEADER_LEN: usize = 32;
+/// Size of an entry: key start in the key section, record offset
+const TABLE_ENTRY_LEN: usize = 16;
+
+/// Write a binary offset table
+///
+/// Entries are sorted by id so lookups can binary-search the memory-mapped
+/// file without loading it. Layout (little-endian): the header, the entries,
+/// the concatenated ids, then the tombstoned ids as a count followed by
+/// length-prefixed strings. `data_len` records the passage file's size, so a
+/// table left behind by another writer is detected.
+fn write_offset_table(
+    path: &Path,
+    offsets: &HashMap<String, u64>,
+    deleted: &HashSet<String>,
+    data_len: u64,
+) -> anyhow::Result<()> {
+    let mut entries: Vec<(&String, &u64)> = offsets.iter().collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    let key_bytes: usize = entries.iter().map(|(id, _)| id.len()).sum();
+
+    // Write next to the table and swap it in: readers may have the old one
+    // memory-mapped, and truncating it under them would corrupt their view
+    let staged = path.with_extension("bin.tmp");
+    let mut writer = BufWriter::new(File::create(&staged)?);
+    writer.write_all(BIN_IDX_MAGIC)?;
+    writer.write_all(&BIN_IDX_VERSION.to_le_bytes())?;
+    writer.write_all(&(entries.len() as u64).to_le_bytes())?;
+    writer.write_all(&(key_bytes as u64).to_le_bytes())?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    let mut key_start = 0u64;
+    for (id, offset) in &entries {
+        writer.write_all(&key_start.to_le_bytes())?;
+        writer.write_all(&offset.to_le_bytes())?;
+        key_start += id.len() as u64;
+    }
+    for (id, _) in &entries {
+        writer.write_all(id.as_bytes())?;
+    }
+    writer.write_all(&(deleted.len() as u64).to_le_bytes())?;
+    for id in deleted {
+        writer.write_all(&(id.len() as u32).to_le_bytes())?;
+        writer.write_all(id.as_bytes())?;
+    }
+    writer.flush()?;
+    drop(writer);
+    std::fs::rename(&staged, path)?;
+    Ok(())
+}
+
+/// A memory-mapped binary offset table (see `write_offset_table`)
+struct MappedOffsets {
+    mmap: Mmap,
+    count: usize,
+    keys_start: usize,
+    keys_len: usize,
+    data_len: u64,
+}
+
+impl MappedOffsets {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: the table is only replaced by renaming a new file over it
+        // (see `write_offset_table`), never modified in place
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let invalid = || anyhow::anyhow!("{} is not a valid passage offset table", path.display());
+        if mmap.len() < TABLE_HEADER_LEN || &mmap[..4] != BIN_IDX_MAGIC {
+            return Err(invalid());
+        }
+        let version = u32::from_le_bytes(mmap[4..8].try_into()?);
+        if version > BIN_IDX_VERSION {
+            return Err(LeannError::FormatIncompatible {
+                path: path.to_path_buf(),
+                found: version.into(),
+                supported: BIN_IDX_VERSION.into(),
+            }
+            .into());
+        }
+        let read_u64 = |at: usize| u64::from_le_bytes(mmap[at..at + 8].try_into().unwrap_or_default());
+        let count = read_u64(8) as usize;
+        let keys_len = read_u64(16) as usize;
+        let data_len = read_u64(24);
+        let keys_start = count
+            .checked_mul(TABLE_ENTRY_LEN)
+            .and_then(|n| n.checked_add(TABLE_HEADER_LEN))
+            .filter(|start| start.checked_add(keys_len).is_some_and(|end| end + 8 <= mmap.len()))
+            .ok_or_else(invalid)?;
+
+        Ok(Self { mmap, count, keys_start, keys_len, data_len })
+    }
+
+    fn read_u64(&self, at: usize) -> u64 {
+        u64::from_le_bytes(self.mmap[at..at + 8].try_into().unwrap_or_default())
+    }
+
+    /// Id and record offset of the `i`th entry
+    fn entry(&self, i: usize) -> (&[u8], u64) {
+        let at = TABLE_HEADER_LEN + i * TABLE_ENTRY_LEN;
+        let key_start = self.read_u64(at) as usize;
+        let key_end = if i + 1 < self.count {
+            self.read_u64(at + TABLE_ENTRY_LEN) as usize
+        } else {
+            self.keys_len
+        };
+        let key = self
+            .mmap
+            .get(self.keys_start + key_start..self.keys_start + key_end)
+            .unwrap_or_default();
+        (key, self.read_u64(at + 8))
+    }
+
+    /// Record offset of `id`, by binary search
+    fn get(&self, id: &str) -> Option<u64> {
+        let (mut lo, mut hi) = (0, self.count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (key, offset) = self.entry(mid);
+            match key.cmp(id.as_bytes()) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => return Some(offset),
+            }
+        }
+        None
+    }
+
+    fn ids(&self) -> impl Iterator<Item = &str> {
+        (0..self.count).filter_map(|i| std::str::from_utf8(self.entry(i).0).ok())
+    }
 
-    /// Path to JSONL file
-    jsonl_path: std::path::PathBuf,
+    fn to_map(&self) -> HashMap<String, u64> {
+        (0..self.count)
+            .map(|i| self.entry(i))
+            .map(|(key, offset)| (String::from_utf8_lossy(key).into_owned(), offset))
+            .collect()
+    }
+
+    /// Tombstoned ids that are in the table
+    fn deleted(&self) -> anyhow::Result<HashSet<String>> {
+        let mut at = self.keys_start + self.keys_len;
+        let count = self.read_u64(at) as usize;
+        at += 8;
+        let mut deleted = HashSet::new();
+        for _ in 0..count {
+            let len = self
+                .mmap
+                .get(at..at + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap_or_default()) as usize)
+                .ok_or_else(|| anyhow::anyhow!("Truncated passage offset table"))?;
+            let id = self
+                .mmap
+                .get(at + 4..at + 4 + len)
+                .ok_or_else(|| anyhow::anyhow!("Truncated passage offset table"))?;
+            let id = String::from_utf8(id.to_vec())?;
+            if self.get(&id).is_some() {
+                deleted.insert(id);
+            }
+            at += 4 + len;
+        }
+        Ok(deleted)
+    }
+}
+
+/// Id -> record offset lookup of an open store
+enum OffsetTable {
+    /// Loaded into memory from `passages.idx.json`
+    Map(HashMap<String, u64>),
+    /// Binary-searched in the memory-mapped `passages.idx.bin`
+    Mapped(MappedOffsets),
+}
+
+impl OffsetTable {
+    fn get(&self, id: &str) -> Option<u64> {
+        match self {
+            Self::Map(offsets) => offsets.get(id).copied(),
+            Self::Mapped(table) => table.get(id),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Map(offsets) => offsets.len(),
+            Self::Mapped(table) => table.count,
+        }
+    }
+
+    fn ids(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match self {
+            Self::Map(offsets) => Box::new(offsets.keys().map(String::as_str)),
+            Self::Mapped(table) => Box::new(table.ids()),
+        }
+    }
+}
+
+/// Reads records sequentially, yielding each one's offset and JSON
+struct RecordReader {
+    reader: BufReader<File>,
+    format: PassageFormat,
+    offset: u64,
+    failed: bool,
+}
+
+impl RecordReader {
+    /// Reader over the records of `path`, positioned at `offset`
+    fn open(path: &Path, format: PassageFormat, offset: u64) -> anyhow::Result<Self> {
+        let mut file = File::open(path)?;
+        if offset > 0 {
+            file.seek(SeekFrom::Start(offset))?;
+        }
+        Ok(Self {
+            reader: BufReader::new(file),
+            format,
+            offset,
+            failed: false,
+        })
+    }
+
+    fn read_next(&mut self) -> anyhow::Result<Option<(u64, Vec<u8>)>> {
+        loop {
+            let start = self.offset;
+            match self.format {
+                PassageFormat::Jsonl => {
+                    let mut line = Vec::new();
+                    let n = self.reader.read_until(b'\n', &mut line)?;
+                    if n == 0 {
+                        return Ok(None);
+                    }
+                    self.offset += n as u64;
+                    // Blank lines carry no passage
+                    if line.iter().all(u8::is_ascii_whitespace) {
+                        continue;
+                    }
+                    return Ok(Some((start, line)));
+                }
+                PassageFormat::Compact => {
+                    if self.reader.fill_buf()?.is_empty() {
+                        return Ok(None);
+                    }
+                    let mut len = [0u8; 4];
+                    self.reader.read_exact(&mut len)?;
+                    let mut compressed = vec![0u8; u32::from_le_bytes(len) as usize];
+                    self.reader.read_exact(&mut compressed)?;
+                    self.offset += 4 + compressed.len() as u64;
+                    return Ok(Some((start, zstd::decode_all(compressed.as_slice())?)));
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for RecordReader {
+    type Item = anyhow::Result<(u64, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let record = self.read_next().transpose();
+        self.failed = matches!(record, Some(Err(_)));
+        record
+    }
+}
+
+/// Passages removed by `PassageStore::compact`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactStats {
+    pub removed: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Passage store: a passage file with an offset index
+pub struct PassageStore {
+    /// Offset lookup: passage_id -> byte offset in the passage file
+    offsets: OffsetTable,
+
+    /// Tombstoned ids (a subset of `offsets`), hidden from lookups
+    deleted: HashSet<String>,
+
+    /// Path to the passage file
+    data_path: PathBuf,
+
+    format: PassageFormat,
 }
 
 impl PassageStore {
-    /// Create a new passage store for writing
+    /// Create a new JSONL passage store for writing
     pub fn create(base_path: &Path) -> anyhow::Result<PassageStoreWriter> {
-        let jsonl_path = base_path.with_extension("passages.jsonl");
-        let idx_path = base_path.with_extension("passages.idx.json");
+        Self::create_with_format(base_path, PassageFormat::Jsonl)
+    }
 
-        let file = File::create(&jsonl_path)?;
+    /// Create a new passage store in `format` for writing
+    ///
+    /// A passage file of the other format at `base_path` is removed, so the
+    /// format is detected correctly when the store is opened.
+    pub fn create_with_format(base_path: &Path, format: PassageFormat) -> anyhow::Result<PassageStoreWriter> {
+        let other = match format {
+            PassageFormat::Jsonl => PassageFormat::Compact,
+            PassageFormat::Compact => PassageFormat::Jsonl,
+        };
+        for path in [other.data_path(base_path), other.idx_path(base_path)] {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+        }
+
+        let file = File::create(format.data_path(base_path))?;
         let writer = BufWriter::new(file);
 
         Ok(PassageStoreWriter {
             writer,
             offsets: HashMap::new(),
-            jsonl_path,
-            idx_path,
+            deleted: HashSet::new(),
+            base_path: base_path.to_path_buf(),
+            format,
             current_offset: 0,
         })
     }
 
     /// Open an existing passage store for reading
+    ///
+    /// The binary offset table is memory-mapped, so opening is fast and memory
+    /// stays flat however many passages there are. JSONL stores without an
+    /// up-to-date table (built by Python LEANN or older versions, or changed
+    /// since) load `passages.idx.json` instead.
     pub fn open(base_path: &Path) -> anyhow::Result<Self> {
-        let jsonl_path = base_path.with_extension("passages.jsonl");
-        let idx_path = base_path.with_extension("passages.idx.json");
-
-        // Load offset index
-        let idx_content = std::fs::read_to_string(&idx_path)?;
-        let offsets: HashMap<String, u64> = serde_json::from_str(&idx_content)?;
+        let format = PassageFormat::detect(base_path);
+        let data_path = format.data_path(base_path);
+
+        let table = match format {
+            PassageFormat::Compact => Some(MappedOffsets::open(&table_path(base_path))?),
+            PassageFormat::Jsonl if table_path(base_path).exists() => {
+                let data_len = std::fs::metadata(&data_path)?.len();
+                match MappedOffsets::open(&table_path(base_path)) {
+                    Ok(table) if table.data_len == data_len => Some(table),
+                    Ok(_) => {
+                        tracing::debug!("Offset table of {} is stale, reading JSON index", data_path.display());
+                        None
+                    }
+                    Err(e) => {
+                        tracing::debug!("{}; reading JSON index", e);
+                        None
+                    }
+                }
+            }
+            PassageFormat::Jsonl => None,
+        };
+        let (offsets, deleted) = match table {
+            Some(table) => {
+                let deleted = table.deleted()?;
+                (OffsetTable::Mapped(table), deleted)
+            }
+            None => {
+                let (offsets, deleted) = read_json_offsets(&format.idx_path(base_path))?;
+                (OffsetTable::Map(offsets), deleted)
+            }
+        };
 
         Ok(Self {
             offsets,
-            jsonl_path,
+            deleted,
+            data_path,
+            format,
         })
     }
 
     /// Open an existing passage store for appending
     pub fn open_for_append(base_path: &Path) -> anyhow::Result<PassageStoreWriter> {
-        let jsonl_path = base_path.with_extension("passages.jsonl");
-        let idx_path = base_path.with_extension("passages.idx.json");
+        let format = PassageFormat::detect(base_path);
+        let data_path = format.data_path(base_path);
 
         // Load existing offset index
-        let idx_content = std::fs::read_to_string(&idx_path)?;
-        let offsets: HashMap<String, u64> = serde_json::from_str(&idx_content)?;
+        let (offsets, deleted) = read_offset_index(base_path, format)?;
 
         // Open file for appending
         let file = std::fs::OpenOptions::new()
             .append(true)
-            .open(&jsonl_path)?;
+            .open(&data_path)?;
 
         // Get current file size for offset tracking
         let current_offset = file.metadata()?.len();
@@ -80,68 +581,237 @@ impl PassageStore {
         Ok(PassageStoreWriter {
             writer,
             offsets,
-            jsonl_path,
-            idx_path,
+            deleted,
+            base_path: base_path.to_path_buf(),
+            format,
             current_offset,
         })
     }
 
+    /// Mark passages deleted without rewriting the passage file
+    ///
+    /// Returns the number of passages newly deleted; unknown ids are ignored.
+    pub fn delete(base_path: &Path, ids: &[String]) -> anyhow::Result<usize> {
+        let format = PassageFormat::detect(base_path);
+        let (offsets, mut deleted) = read_offset_index(base_path, format)?;
+        let before = deleted.len();
+        deleted.extend(ids.iter().filter(|id| offsets.contains_key(*id)).cloned());
+        let newly_deleted = deleted.len() - before;
+        if newly_deleted > 0 {
+            write_offset_index(base_path, format, offsets, deleted)?;
+        }
+        Ok(newly_deleted)
+    }
+
+    /// Rewrite the passage file without deleted passages (and records
+    /// superseded by a later passage with the same id), clearing the tombstones
+    pub fn compact(base_path: &Path) -> anyhow::Result<CompactStats> {
+        #[derive(Deserialize)]
+        struct PassageId {
+            id: String,
+        }
+
+        let format = PassageFormat::detect(base_path);
+        let data_path = format.data_path(base_path);
+        let mut tmp_path = data_path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        let (offsets, deleted) = read_offset_index(base_path, format)?;
+
+        let mut records = RecordReader::open(&data_path, format, 0)?;
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        let mut new_offsets = HashMap::with_capacity(offsets.len() - deleted.len());
+        let mut stats = CompactStats::default();
+        for record in records.by_ref() {
+            let (offset, json) = record?;
+            let id = serde_json::from_slice::<PassageId>(&json)?.id;
+            if offsets.get(&id) != Some(&offset) || deleted.contains(&id) {
+                stats.removed += 1;
+                continue;
+            }
+            new_offsets.insert(id, stats.bytes_after);
+            let record = match format {
+                // Keep JSONL lines byte for byte
+                PassageFormat::Jsonl => {
+                    let mut line = json;
+                    if !line.ends_with(b"\n") {
+                        line.push(b'\n');
+                    }
+                    line
+                }
+                PassageFormat::Compact => format.encode(&json)?,
+            };
+            writer.write_all(&record)?;
+            stats.bytes_after += record.len() as u64;
+        }
+        writer.flush()?;
+        stats.bytes_before = records.offset;
+
+        std::fs::rename(&tmp_path, &data_path)?;
+        write_offset_index(base_path, format, new_offsets, HashSet::new())?;
+        Ok(stats)
+    }
+
+    /// Format of the passage file
+    pub fn format(&self) -> PassageFormat {
+        self.format
+    }
+
     /// Get a passage by ID
     pub fn get(&self, id: &str) -> anyhow::Result<Passage> {
+        if self.deleted.contains(id) {
+            anyhow::bail!("Passage deleted: {}", id);
+        }
         let offset = self
             .offsets
             .get(id)
             .ok_or_else(|| anyhow::anyhow!("Passage not found: {}", id))?;
 
-        let mut file = File::open(&self.jsonl_path)?;
-        file.seek(SeekFrom::Start(*offset))?;
+        let (_, json) = RecordReader::open(&self.data_path, self.format, offset)?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Passage {} is past the end of {}", id, self.data_path.display()))??;
+
+        let mut passage: Passage = serde_json::from_slice(&json)?;
 
-        let mut reader = BufReader::new(file);
-        let mut line = String::new();
-        reader.read_line(&mut line)?;
+        // Text-free indexes store only a hash; re-read the text from the source file
+        if passage.text.is_empty() {
+            if let Some(text) = load_source_text(&passage.metadata) {
+                passage.text = text;
+            }
+        }
 
-        let passage: Passage = serde_json::from_str(&line)?;
         Ok(passage)
     }
 
+    /// Check whether a passage ID is present (and not deleted) in the store
+    pub fn contains(&self, id: &str) -> bool {
+        self.offsets.get(id).is_some() && !self.deleted.contains(id)
+    }
+
+    /// Iterate over all stored passages in file order
+    ///
+    /// Reads the passage file sequentially, skipping passages whose IDs are
+    /// not in the offset index (e.g. dropped by `retain`) or are deleted.
+    pub fn iter(&self) -> anyhow::Result<impl Iterator<Item = anyhow::Result<Passage>> + '_> {
+        let records = RecordReader::open(&self.data_path, self.format, 0)?;
+
+        Ok(records
+            .map(|record| -> anyhow::Result<Passage> { Ok(serde_json::from_slice(&record?.1)?) })
+            .filter(move |p| p.as_ref().map_or(true, |p| self.contains(&p.id))))
+    }
+
+    /// Keep only passages for which `keep` returns true
+    ///
+    /// Scans the passage file once and drops offsets for rejected passages, so
+    /// subsequent lookups behave as if those passages were never stored.
+    /// Returns the number of passages retained.
+    pub fn retain<F>(&mut self, mut keep: F) -> anyhow::Result<usize>
+    where
+        F: FnMut(&Passage) -> bool,
+    {
+        let mut rejected = Vec::new();
+        for passage in self.iter()? {
+            let passage = passage?;
+            if !keep(&passage) {
+                rejected.push(passage.id);
+            }
+        }
+
+        // Dropping entries needs an in-memory map
+        if let OffsetTable::Mapped(table) = &self.offsets {
+            self.offsets = OffsetTable::Map(table.to_map());
+        }
+        if let OffsetTable::Map(offsets) = &mut self.offsets {
+            for id in rejected {
+                offsets.remove(&id);
+            }
+        }
+
+        Ok(self.len())
+    }
+
     /// Get all passage IDs
-    pub fn ids(&self) -> impl Iterator<Item = &String> {
-        self.offsets.keys()
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.offsets.ids().filter(|id| !self.deleted.contains(*id))
     }
 
     /// Get passage count
     pub fn len(&self) -> usize {
-        self.offsets.len()
+        self.offsets.len() - self.deleted.len()
+    }
+
+    /// Number of deleted passages still in the passage file
+    pub fn deleted_count(&self) -> usize {
+        self.deleted.len()
     }
 
     /// Check if empty
     pub fn is_empty(&self) -> bool {
-        self.offsets.is_empty()
+        self.len() == 0
     }
 }
 
+/// Re-read passage text from its source file using recorded location metadata
+///
+/// Uses `byte_start`/`byte_end` when present, falling back to the
+/// `start_line`/`end_line` range. If a `text_hash` was recorded, the re-read
+/// text must match it; otherwise the source has changed and `None` is returned.
+fn load_source_text(metadata: &serde_json::Value) -> Option<String> {
+    let source = metadata.get("source")?.as_str()?;
+    let content = std::fs::read_to_string(source).ok()?;
+
+    let byte_range = metadata
+        .get("byte_start")
+        .and_then(|v| v.as_u64())
+        .zip(metadata.get("byte_end").and_then(|v| v.as_u64()));
+
+    let text = match byte_range {
+        Some((start, end)) => content.get(start as usize..end as usize)?.to_string(),
+        None => {
+            let start_line = metadata.get("start_line")?.as_u64()? as usize;
+            let end_line = metadata.get("end_line")?.as_u64()? as usize;
+            content
+                .lines()
+                .skip(start_line.saturating_sub(1))
+                .take(end_line.saturating_sub(start_line) + 1)
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    };
+
+    if let Some(expected) = metadata.get("text_hash").and_then(|v| v.as_str()) {
+        if crate::chunker::content_hash(&text) != expected {
+            tracing::debug!("Source {} changed since indexing, text unavailable", source);
+            return None;
+        }
+    }
+
+    Some(text)
+}
+
 /// Writer for creating a passage store
 pub struct PassageStoreWriter {
     writer: BufWriter<File>,
     offsets: HashMap<String, u64>,
-    jsonl_path: std::path::PathBuf,
-    idx_path: std::path::PathBuf,
+    deleted: HashSet<String>,
+    base_path: PathBuf,
+    format: PassageFormat,
     current_offset: u64,
 }
 
 impl PassageStoreWriter {
     /// Add a passage to the store
     pub fn add(&mut self, passage: &Passage) -> anyhow::Result<()> {
-        // Record offset before writing
+        // Record offset before writing; a re-added passage is no longer deleted
         self.offsets.insert(passage.id.clone(), self.current_offset);
+        self.deleted.remove(&passage.id);
 
-        // Write JSON line
-        let json = serde_json::to_string(passage)?;
-        self.writer.write_all(json.as_bytes())?;
-        self.writer.write_all(b"\n")?;
+        // Write the record (a JSON line, or a compressed block)
+        let record = self.format.encode(&serde_json::to_vec(passage)?)?;
+        self.writer.write_all(&record)?;
 
         // Update offset
-        self.current_offset += json.len() as u64 + 1; // +1 for newline
+        self.current_offset += record.len() as u64;
 
         Ok(())
     }
@@ -150,20 +820,143 @@ impl PassageStoreWriter {
     pub fn finish(mut self) -> anyhow::Result<()> {
         self.writer.flush()?;
 
-        // Save offset index as JSON (not pickle, for cross-platform compatibility)
-        let idx_content = serde_json::to_string(&self.offsets)?;
-        std::fs::write(&self.idx_path, idx_content)?;
+        // Save offset index as JSON or a binary table (not pickle, for cross-platform compatibility)
+        write_offset_index(&self.base_path, self.format, self.offsets, self.deleted)?;
 
         Ok(())
     }
+}
 
-    /// Get the JSONL path
-    pub fn jsonl_path(&self) -> &Path {
-        &self.jsonl_path
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_table_replaced_under_reader() {
+        let dir = std::env::temp_dir().join(format!("leann-offsets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("documents.passages.idx.bin");
+        let offsets = |ids: &[&str]| ids.iter().enumerate().map(|(i, id)| (id.to_string(), i as u64)).collect();
+
+        write_offset_table(&path, &offsets(&["a", "b"]), &HashSet::new(), 10).unwrap();
+        let mapped = MappedOffsets::open(&path).unwrap();
+        // A rewrite swaps in a new file; the mapped table keeps reading the old one
+        write_offset_table(&path, &offsets(&["a", "b", "c", "d"]), &HashSet::new(), 20).unwrap();
+        assert_eq!(mapped.get("b"), Some(1));
+        assert_eq!(mapped.get("c"), None);
+        assert_eq!(MappedOffsets::open(&path).unwrap().get("c"), Some(2));
+        assert!(!path.with_extension("bin.tmp").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    /// Get current passage count
-    pub fn len(&self) -> usize {
-        self.offsets.len()
+    fn passage(id: &str, text: &str) -> Passage {
+        Passage {
+            id: id.to_string(),
+            text: text.to_string(),
+            metadata: serde_json::json!({ "source": format!("{}.md", id) }),
+        }
+    }
+
+    #[test]
+    fn test_tombstones_and_compact() {
+        for format in [PassageFormat::Jsonl, PassageFormat::Compact] {
+            let dir = std::env::temp_dir().join(format!("leann-passages-{}-{}", format, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let base = dir.join("documents.leann");
+
+            // Creating a store replaces one of the other format
+            PassageStore::create(&base).unwrap().finish().unwrap();
+            let mut writer = PassageStore::create_with_format(&base, format).unwrap();
+            for id in ["a", "b", "c"] {
+                writer.add(&passage(id, id)).unwrap();
+            }
+            writer.finish().unwrap();
+            assert_eq!(PassageFormat::detect(&base), format);
+
+            assert_eq!(PassageStore::delete(&base, &["b".to_string(), "zz".to_string()]).unwrap(), 1);
+            let store = PassageStore::open(&base).unwrap();
+            assert_eq!(store.format(), format);
+            assert!(!store.contains("b"));
+            assert!(store.get("b").is_err());
+            assert_eq!(store.get("c").unwrap().text, "c");
+            assert_eq!(store.len(), 2);
+            assert_eq!(store.deleted_count(), 1);
+            assert_eq!(store.iter().unwrap().count(), 2);
+
+            // Appending keeps tombstones; re-adding an id revives it at its new record
+            let mut writer = PassageStore::open_for_append(&base).unwrap();
+            writer.add(&passage("c", "c v2")).unwrap();
+            writer.finish().unwrap();
+            let store = PassageStore::open(&base).unwrap();
+            assert_eq!(store.deleted_count(), 1);
+            assert_eq!(store.get("c").unwrap().text, "c v2");
+
+            let stats = PassageStore::compact(&base).unwrap();
+            assert_eq!(stats.removed, 2);
+            assert!(stats.bytes_after < stats.bytes_before);
+            let store = PassageStore::open(&base).unwrap();
+            assert_eq!(store.deleted_count(), 0);
+            assert_eq!(store.len(), 2);
+            assert_eq!(store.get("a").unwrap().text, "a");
+            assert_eq!(store.get("c").unwrap().text, "c v2");
+            let texts: Vec<String> = store.iter().unwrap().map(|p| p.unwrap().text).collect();
+            assert_eq!(texts, ["a", "c v2"]);
+
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_mapped_offset_table() {
+        let dir = std::env::temp_dir().join(format!("leann-passages-mapped-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("documents.leann");
+
+        let mut writer = PassageStore::create(&base).unwrap();
+        for i in 0..100 {
+            writer.add(&passage(&i.to_string(), &format!("text {}", i))).unwrap();
+        }
+        writer.finish().unwrap();
+        PassageStore::delete(&base, &["42".to_string()]).unwrap();
+
+        let store = PassageStore::open(&base).unwrap();
+        assert!(matches!(store.offsets, OffsetTable::Mapped(_)));
+        assert_eq!(store.len(), 99);
+        assert_eq!(store.get("7").unwrap().text, "text 7");
+        assert_eq!(store.get("99").unwrap().text, "text 99");
+        assert!(store.get("42").is_err());
+        assert!(!store.contains("100"));
+        assert_eq!(store.ids().count(), 99);
+
+        // A table older than the JSONL file (e.g. appended to by Python LEANN) is ignored
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(base.with_extension("passages.jsonl"))
+            .unwrap();
+        file.write_all(b"\n").unwrap();
+        let store = PassageStore::open(&base).unwrap();
+        assert!(matches!(store.offsets, OffsetTable::Map(_)));
+        assert_eq!(store.get("7").unwrap().text, "text 7");
+        assert!(!store.contains("42"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reads_version_1_offsets() {
+        let dir = std::env::temp_dir().join(format!("leann-passages-v1-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let idx_path = dir.join("documents.passages.idx.json");
+
+        std::fs::write(&idx_path, r#"{"0":0,"1":42}"#).unwrap();
+        let (offsets, deleted) = read_json_offsets(&idx_path).unwrap();
+        assert_eq!(offsets["1"], 42);
+        assert!(deleted.is_empty());
+
+        std::fs::write(&idx_path, r#"{"version":3,"offsets":{}}"#).unwrap();
+        assert!(read_json_offsets(&idx_path).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
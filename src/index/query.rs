@@ -1,7 +1,8 @@
 //! Query processing and expansion
 //!
 //! Index-derived query expansion using BM25 to find related terms.
-//! Uses AST-aware patterns to extract meaningful code symbols.
+//! Uses AST-aware patterns to extract meaningful code symbols, which also
+//! give code chunks their `defines`, `imports`, and `calls` metadata.
 
 use std::collections::HashMap;
 
@@ -30,6 +31,91 @@ static CODE_SYMBOL_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
     ]
 });
 
+/// Import statements: Rust `use`, Python `import` and `from ... import`,
+/// Java/Kotlin/Scala `import`, JavaScript/TypeScript `import ... from` and
+/// `require`, Go single-line imports, and C/C++ `#include`
+static IMPORT_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    vec![
+        Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+([\w:]+)").unwrap(),
+        Regex::new(r"(?m)^\s*from\s+([\w.]+)\s+import\b").unwrap(),
+        Regex::new(r"(?m)^\s*import\s+(?:static\s+)?([\w.]+)(?:\.\*)?\s*(?:;|\s+as\s+\w+)?\s*$").unwrap(),
+        Regex::new(r#"(?m)^\s*import\s+(?:[\w*{}\s,$]+\s+from\s+|\w+\s+)?["']([^"']+)["']"#).unwrap(),
+        Regex::new(r#"\brequire\(\s*["']([^"']+)["']\s*\)"#).unwrap(),
+        Regex::new(r#"(?m)^\s*#\s*include\s*[<"]([^>"]+)[>"]"#).unwrap(),
+    ]
+});
+
+/// Go import blocks: `import ( "fmt" ... )`
+static GO_IMPORT_BLOCK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?s)\bimport\s*\(([^)]*)\)"#).unwrap());
+static QUOTED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#""([^"]+)""#).unwrap());
+
+/// Calls: a name followed by an opening parenthesis
+static CALL_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b([A-Za-z_]\w*)\s*\(").unwrap());
+
+/// Keywords and built-in constructors that are followed by `(` without being calls
+const NOT_CALLS: &[&str] = &[
+    "if", "for", "while", "match", "return", "switch", "catch", "elif", "and", "or", "not", "in", "with",
+    "fn", "def", "function", "func", "sizeof", "typeof", "Some", "Ok", "Err",
+];
+
+/// Most symbols recorded per list, so chunk metadata stays small
+const MAX_RECORDED_SYMBOLS: usize = 32;
+
+/// Names defined in code text (functions, classes, structs, ...), in order of appearance
+pub fn extract_definitions(text: &str) -> Vec<String> {
+    let mut found: Vec<(usize, &str)> = CODE_SYMBOL_PATTERNS
+        .iter()
+        .flat_map(|pattern| pattern.captures_iter(text).filter_map(|caps| caps.get(1)))
+        .map(|m| (m.start(), m.as_str()))
+        .collect();
+    found.sort_unstable();
+    first_unique(found.into_iter().map(|(_, name)| name))
+}
+
+/// Modules imported by code text (`crate::index`, `os.path`, `./utils`, `stdio.h`, ...)
+pub fn extract_imports(text: &str) -> Vec<String> {
+    let mut found: Vec<(usize, &str)> = IMPORT_PATTERNS
+        .iter()
+        .flat_map(|pattern| pattern.captures_iter(text).filter_map(|caps| caps.get(1)))
+        .map(|m| (m.start(), m.as_str().trim_end_matches(':')))
+        .collect();
+    for block in GO_IMPORT_BLOCK.captures_iter(text) {
+        let body = block.get(1).expect("group 1 always matches");
+        found.extend(QUOTED.captures_iter(body.as_str()).map(|c| {
+            let m = c.get(1).expect("group 1 always matches");
+            (body.start() + m.start(), m.as_str())
+        }));
+    }
+    found.sort_unstable();
+    first_unique(found.into_iter().map(|(_, name)| name))
+}
+
+/// Functions and methods called in code text, in order of first call;
+/// names the text defines itself are left out
+pub fn extract_calls(text: &str) -> Vec<String> {
+    let defined = extract_definitions(text);
+    let calls = CALL_PATTERN
+        .captures_iter(text)
+        .filter_map(|caps| caps.get(1))
+        .map(|m| m.as_str())
+        .filter(|name| !NOT_CALLS.contains(name) && !defined.iter().any(|d| d == name));
+    first_unique(calls)
+}
+
+/// The first occurrence of each name, up to `MAX_RECORDED_SYMBOLS`
+fn first_unique<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for name in names {
+        if unique.len() == MAX_RECORDED_SYMBOLS {
+            break;
+        }
+        if !name.is_empty() && !unique.iter().any(|u| u == name) {
+            unique.push(name.to_string());
+        }
+    }
+    unique
+}
+
 /// Extract code symbol names (functions, classes, structs) from text
 fn extract_code_symbols(text: &str, max_symbols: usize) -> Vec<String> {
     let mut symbols: HashMap<String, usize> = HashMap::new();
@@ -49,9 +135,9 @@ fn extract_code_symbols(text: &str, max_symbols: usize) -> Vec<String> {
         }
     }
 
-    // Sort by frequency
+    // Sort by frequency, then name, so expansion is the same on every run
     let mut symbol_vec: Vec<_> = symbols.into_iter().collect();
-    symbol_vec.sort_by(|a, b| b.1.cmp(&a.1));
+    symbol_vec.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
     symbol_vec.into_iter().take(max_symbols).map(|(s, _)| s).collect()
 }
 
@@ -115,9 +201,9 @@ fn extract_key_terms(text: &str, max_terms: usize) -> Vec<String> {
         }
     }
 
-    // Sort by frequency and take top terms
+    // Sort by frequency (ties by term, so pages of one query agree) and take top terms
     let mut terms: Vec<_> = term_counts.into_iter().collect();
-    terms.sort_by(|a, b| b.1.cmp(&a.1));
+    terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
     terms.into_iter().take(max_terms).map(|(t, _)| t).collect()
 }
 
@@ -174,6 +260,27 @@ pub fn should_expand(query: &str) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_extract_imports() {
+        let rust = "use std::collections::{HashMap, HashSet};\npub(crate) use super::filter::MetadataFilter;\nuse regex::Regex;";
+        assert_eq!(extract_imports(rust), ["std::collections", "super::filter::MetadataFilter", "regex::Regex"]);
+        let python = "import os.path\nfrom typing import List\nimport numpy as np\n";
+        assert_eq!(extract_imports(python), ["os.path", "typing", "numpy"]);
+        let js = "import React, { useState } from 'react';\nimport './styles.css';\nconst fs = require(\"fs\");";
+        assert_eq!(extract_imports(js), ["react", "./styles.css", "fs"]);
+        let go = "import (\n    \"fmt\"\n    log \"github.com/sirupsen/logrus\"\n)\nimport \"os\"";
+        assert_eq!(extract_imports(go), ["fmt", "github.com/sirupsen/logrus", "os"]);
+        assert_eq!(extract_imports("import java.util.List;\n#include <stdio.h>"), ["java.util.List", "stdio.h"]);
+    }
+
+    #[test]
+    fn test_extract_definitions_and_calls() {
+        let text = "pub fn evaluate(input: &str) -> Option<f64> {\n    let tokens = tokenize_expression(input);\n    \
+                    if tokens.is_empty() { return None; }\n    Some(fold(&tokens))\n}\n\nstruct Calculator;";
+        assert_eq!(extract_definitions(text), ["evaluate", "Calculator"]);
+        assert_eq!(extract_calls(text), ["tokenize_expression", "is_empty", "fold"]);
+    }
+
     #[test]
     fn test_extract_key_terms() {
         // Use repeated words to ensure they appear in top terms
@@ -221,6 +328,14 @@ mod tests {
         // Should contain at least one expanded term
         assert!(expanded.contains("knowledge") || expanded.contains("graph") ||
                 expanded.contains("architecture") || expanded.contains("decisions"));
+
+        // Terms tied on frequency are picked the same way every time
+        let tied = ["hotel golf foxtrot echo delta charlie bravo alpha"];
+        let expected = expand_from_passages(query, &tied, 3);
+        assert_eq!(expected, "database alpha bravo charlie");
+        for _ in 0..20 {
+            assert_eq!(expand_from_passages(query, &tied, 3), expected);
+        }
     }
 
     #[test]
@@ -0,0 +1,241 @@
+//! Query log - opt-in record of searches for `leann analytics`
+//!
+//! With `log_queries = true` under `[analytics]` in the config file, each
+//! search and question is appended as a JSON line to `<name>.queries.jsonl`
+//! next to the index directory (so it survives rebuilds). Passages used for
+//! a query (cited by `ask`, or reported through the server's `/feedback`) are
+//! appended as separate lines referring to the query's id.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// A logged query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryLogEntry {
+    pub id: String,
+    /// Unix time of the query
+    pub timestamp: u64,
+    /// Command that ran the query (search, ask, serve)
+    pub command: String,
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    /// Time from query to results (excluding answer generation)
+    pub latency_ms: u64,
+    /// Result passage ids, best first
+    pub result_ids: Vec<String>,
+    /// Result passage ids the user or LLM went on to use
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub used_ids: Vec<String>,
+}
+
+impl QueryLogEntry {
+    pub fn new(command: &str, query: &str, filter: Option<&str>, latency_ms: u64, result_ids: Vec<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().simple().to_string()[..12].to_string(),
+            timestamp: crate::time::now_unix(),
+            command: command.to_string(),
+            query: query.to_string(),
+            filter: filter.map(|f| f.to_string()),
+            latency_ms,
+            result_ids,
+            used_ids: Vec::new(),
+        }
+    }
+}
+
+/// Passages used for an earlier query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UsedRecord {
+    used: String,
+    ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LogLine {
+    Query(QueryLogEntry),
+    Used(UsedRecord),
+}
+
+/// Query log of the index at `index_dir`
+pub fn query_log_path(index_dir: &Path) -> PathBuf {
+    let mut name = index_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(".queries.jsonl");
+    index_dir.with_file_name(name)
+}
+
+/// Append a query to the log
+pub fn log_query(index_dir: &Path, entry: &QueryLogEntry) -> anyhow::Result<()> {
+    append_line(index_dir, &serde_json::to_string(entry)?)
+}
+
+/// Record passages used for a logged query
+pub fn log_used(index_dir: &Path, query_id: &str, ids: Vec<String>) -> anyhow::Result<()> {
+    let record = UsedRecord { used: query_id.to_string(), ids };
+    append_line(index_dir, &serde_json::to_string(&record)?)
+}
+
+fn append_line(index_dir: &Path, line: &str) -> anyhow::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(query_log_path(index_dir))?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Read the log, with used passages merged into their queries
+///
+/// Malformed lines (e.g. from an interrupted write) are skipped.
+pub fn read_query_log(index_dir: &Path) -> anyhow::Result<Vec<QueryLogEntry>> {
+    let path = query_log_path(index_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries: Vec<QueryLogEntry> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for line in std::fs::read_to_string(&path)?.lines() {
+        match serde_json::from_str::<LogLine>(line) {
+            Ok(LogLine::Query(entry)) => {
+                positions.insert(entry.id.clone(), entries.len());
+                entries.push(entry);
+            }
+            Ok(LogLine::Used(record)) => {
+                if let Some(&i) = positions.get(&record.used) {
+                    for id in record.ids {
+                        if !entries[i].used_ids.contains(&id) {
+                            entries[i].used_ids.push(id);
+                        }
+                    }
+                }
+            }
+            Err(_) => continue,
+        }
+    }
+    Ok(entries)
+}
+
+/// Summary of a query log
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryAnalytics {
+    pub queries: usize,
+    pub distinct_queries: usize,
+    /// Most frequent queries with their counts
+    pub top_queries: Vec<(String, usize)>,
+    /// Most frequent queries that returned nothing
+    pub zero_result_queries: Vec<(String, usize)>,
+    pub zero_result_count: usize,
+    /// Share of queries with at least one used result
+    pub used_rate: f64,
+    pub latency_p50_ms: u64,
+    pub latency_p90_ms: u64,
+    pub latency_p99_ms: u64,
+}
+
+impl QueryAnalytics {
+    /// Summarize `entries`, listing up to `top` queries per ranking
+    pub fn from_entries(entries: &[QueryLogEntry], top: usize) -> Self {
+        // Queries differing only in case and spacing count as one
+        let normalize = |q: &str| q.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        let rank = |counts: HashMap<String, usize>| {
+            let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+            ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            ranked.truncate(top);
+            ranked
+        };
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut zero: HashMap<String, usize> = HashMap::new();
+        for entry in entries {
+            let query = normalize(&entry.query);
+            if entry.result_ids.is_empty() {
+                *zero.entry(query.clone()).or_insert(0) += 1;
+            }
+            *counts.entry(query).or_insert(0) += 1;
+        }
+        let distinct_queries = counts.len();
+        let zero_result_count = zero.values().sum();
+
+        let mut latencies: Vec<u64> = entries.iter().map(|e| e.latency_ms).collect();
+        latencies.sort_unstable();
+        let used = entries.iter().filter(|e| !e.used_ids.is_empty()).count();
+
+        Self {
+            queries: entries.len(),
+            distinct_queries,
+            top_queries: rank(counts),
+            zero_result_queries: rank(zero),
+            zero_result_count,
+            used_rate: if entries.is_empty() { 0.0 } else { used as f64 / entries.len() as f64 },
+            latency_p50_ms: percentile(&latencies, 50.0),
+            latency_p90_ms: percentile(&latencies, 90.0),
+            latency_p99_ms: percentile(&latencies, 99.0),
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_and_read() {
+        let root = std::env::temp_dir().join(format!("leann-query-log-{}", std::process::id()));
+        let index_dir = root.join("docs");
+        std::fs::create_dir_all(&index_dir).unwrap();
+
+        let entry = QueryLogEntry::new("ask", "auth setup", Some("lang=eng"), 40, vec!["1".into(), "2".into()]);
+        log_query(&index_dir, &entry).unwrap();
+        log_query(&index_dir, &QueryLogEntry::new("search", "nothing here", None, 10, vec![])).unwrap();
+        log_used(&index_dir, &entry.id, vec!["2".into()]).unwrap();
+        log_used(&index_dir, "unknown", vec!["9".into()]).unwrap();
+
+        assert_eq!(query_log_path(&index_dir), root.join("docs.queries.jsonl"));
+        let entries = read_query_log(&index_dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].used_ids, vec!["2".to_string()]);
+        assert_eq!(entries[0].filter.as_deref(), Some("lang=eng"));
+        assert!(entries[1].used_ids.is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_analytics() {
+        let entry = |query: &str, latency_ms: u64, results: usize| {
+            QueryLogEntry::new("search", query, None, latency_ms, (0..results).map(|i| i.to_string()).collect())
+        };
+        let mut entries = vec![
+            entry("Auth setup", 10, 3),
+            entry("auth  setup", 20, 3),
+            entry("rate limits", 30, 0),
+            entry("rate limits", 40, 0),
+            entry("billing", 100, 2),
+        ];
+        entries[0].used_ids = vec!["0".to_string()];
+
+        let analytics = QueryAnalytics::from_entries(&entries, 10);
+        assert_eq!(analytics.queries, 5);
+        assert_eq!(analytics.distinct_queries, 3);
+        assert_eq!(analytics.top_queries[0], ("auth setup".to_string(), 2));
+        assert_eq!(analytics.zero_result_queries, vec![("rate limits".to_string(), 2)]);
+        assert_eq!(analytics.zero_result_count, 2);
+        assert_eq!(analytics.latency_p50_ms, 30);
+        assert_eq!(analytics.latency_p90_ms, 100);
+        assert!((analytics.used_rate - 0.2).abs() < 1e-9);
+    }
+}
@@ -1,23 +1,36 @@
 //! Recompute searcher - brute-force search with on-demand embedding computation
 //!
 //! Used when an index has been pruned (embeddings deleted to save space).
-//! Recomputes embeddings for all passages during search.
+//! Recomputes embeddings for all passages during search. Batches are embedded
+//! concurrently (bounded) and scored as they arrive.
 
 use std::path::Path;
 
+use futures::stream::{self, StreamExt};
 use tracing::info;
 
-use crate::embedding::EmbeddingProvider;
+use crate::embedding::{document_template, EmbeddingProvider};
+use crate::error::LeannError;
 
 use super::filter::MetadataFilter;
 use super::passages::PassageStore;
 use super::searcher::SearchResult;
 
+/// Default number of passages per embedding request
+const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Default number of embedding requests in flight at once
+const DEFAULT_CONCURRENCY: usize = 4;
+
 /// Searcher that recomputes embeddings on-demand (for pruned indices)
 pub struct RecomputeSearcher {
     passages: PassageStore,
     id_map: Vec<String>,
     dimensions: usize,
+    batch_size: usize,
+    concurrency: usize,
+    /// Prompt template passages were embedded with at build time
+    document_template: String,
 }
 
 impl RecomputeSearcher {
@@ -36,7 +49,7 @@ impl RecomputeSearcher {
                 .map(|s| s.to_string())
                 .collect()
         } else {
-            passages.ids().cloned().collect()
+            passages.ids().map(String::from).collect()
         };
 
         info!("Loaded {} passages for recompute search", id_map.len());
@@ -45,10 +58,27 @@ impl RecomputeSearcher {
             passages,
             id_map,
             dimensions,
+            batch_size: DEFAULT_BATCH_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+            document_template: String::new(),
         })
     }
 
+    /// Set the maximum number of embedding requests in flight at once
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Embed passages with the template used at build time, so recomputed
+    /// vectors match the ones that were pruned
+    pub fn with_document_template(mut self, template: String) -> Self {
+        self.document_template = template;
+        self
+    }
+
     /// Search using brute-force with on-demand embedding computation
+    #[tracing::instrument(name = "recompute.search", skip_all, fields(passages = self.id_map.len(), top_k))]
     pub async fn search(
         &self,
         query_embedding: &[f32],
@@ -58,8 +88,9 @@ impl RecomputeSearcher {
     ) -> anyhow::Result<Vec<SearchResult>> {
         info!("Recompute search: computing embeddings for {} passages", self.id_map.len());
 
-        // Collect all passage texts
+        // Collect all passage texts, with the templates they were embedded with
         let mut texts: Vec<String> = Vec::with_capacity(self.id_map.len());
+        let mut templates: Vec<String> = Vec::with_capacity(self.id_map.len());
         let mut valid_indices: Vec<usize> = Vec::with_capacity(self.id_map.len());
 
         for (idx, id) in self.id_map.iter().enumerate() {
@@ -71,6 +102,7 @@ impl RecomputeSearcher {
                             continue;
                         }
                     }
+                    templates.push(document_template(&passage.metadata, &self.document_template).to_string());
                     texts.push(passage.text);
                     valid_indices.push(idx);
                 }
@@ -82,32 +114,51 @@ impl RecomputeSearcher {
             return Ok(Vec::new());
         }
 
-        // Compute embeddings in batches
-        let batch_size = 100;
-        let mut all_embeddings: Vec<Vec<f32>> = Vec::with_capacity(texts.len());
-
-        for batch in texts.chunks(batch_size) {
-            let batch_refs: Vec<&str> = batch.iter().map(|s| s.as_str()).collect();
-            let embeddings = embedding_provider.embed(&batch_refs).await?;
-            all_embeddings.extend(embeddings);
-        }
-
-        // Compute distances (inner product for MIPS)
-        let mut scored: Vec<(usize, f32)> = all_embeddings
-            .iter()
-            .enumerate()
-            .map(|(i, emb)| {
-                let score = dot_product(query_embedding, emb);
-                (valid_indices[i], score)
+        // Embed batches concurrently, scoring each batch as soon as it arrives
+        // and keeping only the running top-k
+        // Batches are passed as offsets: with a borrowed slice argument the
+        // future isn't `Send` for every lifetime, as the MCP handlers need
+        let offsets: Vec<usize> = (0..texts.len()).step_by(self.batch_size).collect();
+        let batch_count = offsets.len();
+
+        let (texts, templates) = (&texts, &templates);
+        let mut pending = stream::iter(offsets)
+            .map(|offset| async move {
+                let end = (offset + self.batch_size).min(texts.len());
+                let batch_refs: Vec<&str> = texts[offset..end].iter().map(|s| s.as_str()).collect();
+                let template_refs: Vec<&str> = templates[offset..end].iter().map(|s| s.as_str()).collect();
+                embedding_provider
+                    .embed_documents(&batch_refs, &template_refs)
+                    .await
+                    .map(|embeddings| (offset, embeddings))
             })
-            .collect();
-
-        // Sort by score descending
-        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            .buffer_unordered(self.concurrency);
+
+        let mut top: Vec<(usize, f32)> = Vec::with_capacity(top_k + 1);
+        let mut completed = 0usize;
+
+        while let Some(batch) = pending.next().await {
+            let (offset, embeddings) = batch?;
+            for (i, emb) in embeddings.iter().enumerate() {
+                if emb.len() != self.dimensions {
+                    return Err(LeannError::DimensionMismatch {
+                        expected: self.dimensions,
+                        actual: emb.len(),
+                        source_name: "provider",
+                    }
+                    .into());
+                }
+                // Inner product for MIPS
+                let score = dot_product(query_embedding, emb);
+                insert_top_k(&mut top, top_k, valid_indices[offset + i], score);
+            }
+            completed += 1;
+            tracing::debug!("Recompute search: {}/{} batches scored", completed, batch_count);
+        }
 
         // Take top-k and build results
         let mut results = Vec::with_capacity(top_k);
-        for (idx, score) in scored.into_iter().take(top_k) {
+        for (idx, score) in top {
             let id = &self.id_map[idx];
             if let Ok(passage) = self.passages.get(id) {
                 results.push(SearchResult {
@@ -121,19 +172,44 @@ impl RecomputeSearcher {
 
         Ok(results)
     }
+}
 
-    /// Get passage count
-    pub fn len(&self) -> usize {
-        self.id_map.len()
+/// Insert a candidate into a descending top-k list, dropping anything past k
+fn insert_top_k(top: &mut Vec<(usize, f32)>, k: usize, idx: usize, score: f32) {
+    if k == 0 {
+        return;
     }
-
-    /// Check if empty
-    pub fn is_empty(&self) -> bool {
-        self.id_map.is_empty()
+    if top.len() == k && top.last().is_some_and(|(_, worst)| score <= *worst) {
+        return;
     }
+    let pos = top
+        .iter()
+        .position(|(_, s)| score > *s)
+        .unwrap_or(top.len());
+    top.insert(pos, (idx, score));
+    top.truncate(k);
 }
 
 /// Compute dot product (inner product) between two vectors
 fn dot_product(a: &[f32], b: &[f32]) -> f32 {
     a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_top_k() {
+        let mut top = Vec::new();
+        for (idx, score) in [(0, 0.1), (1, 0.9), (2, 0.5), (3, 0.7), (4, 0.2)] {
+            insert_top_k(&mut top, 3, idx, score);
+        }
+        let ids: Vec<usize> = top.iter().map(|(idx, _)| *idx).collect();
+        assert_eq!(ids, vec![1, 3, 2]);
+
+        let mut empty = Vec::new();
+        insert_top_k(&mut empty, 0, 0, 1.0);
+        assert!(empty.is_empty());
+    }
+}
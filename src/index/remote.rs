@@ -0,0 +1,601 @@
+//! Remote index storage - open indexes kept in object storage
+//!
+//! An index stored under an object storage prefix (`s3://bucket/prefix`) is
+//! mirrored into a local cache directory and opened from there, so the
+//! passage store, embeddings, and backend graph keep being memory-mapped.
+//! Each open lists the prefix once and re-downloads only components whose
+//! ETag changed since they were cached.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::http::{check_response, create_client};
+
+/// Cached ETags, kept next to the mirrored components
+const CACHE_MANIFEST: &str = ".leann-remote.json";
+
+/// An object under a remote index's prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteObject {
+    /// Path relative to the index prefix (e.g. "documents.leann.meta.json")
+    pub key: String,
+    pub etag: String,
+    pub size: u64,
+}
+
+/// Object storage holding the components of one index
+#[async_trait]
+pub trait IndexStorage: Send + Sync {
+    /// Objects under the index prefix
+    async fn list(&self) -> anyhow::Result<Vec<RemoteObject>>;
+
+    /// Download an object to a local file
+    async fn download(&self, key: &str, dest: &Path) -> anyhow::Result<()>;
+}
+
+/// Whether an index name refers to remote storage
+fn is_remote(name: &str) -> bool {
+    name.starts_with("s3://")
+}
+
+/// Local directory of an index: remote indexes are synced into the cache
+/// first, other names are looked up with `find_index`
+pub async fn resolve_index(name: &str) -> anyhow::Result<PathBuf> {
+    if !is_remote(name) {
+        return super::find_index(name);
+    }
+
+    let location = S3Location::parse(name)?;
+    let cache_dir = remote_cache_dir().join("s3").join(&location.bucket).join(&location.prefix);
+    if crate::http::is_offline() && cache_dir.join("documents.leann.meta.json").exists() {
+        info!("Offline: using cached copy of {} in {}", name, cache_dir.display());
+        return Ok(cache_dir);
+    }
+    let storage = S3Storage::from_env(location)?;
+    sync_to_cache(&storage, &cache_dir).await?;
+    Ok(cache_dir)
+}
+
+/// Root of the remote index cache (`LEANN_CACHE_DIR`, default `~/.leann/cache`)
+fn remote_cache_dir() -> PathBuf {
+    std::env::var_os("LEANN_CACHE_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".leann").join("cache")))
+        .unwrap_or_else(|| PathBuf::from(".leann").join("cache"))
+}
+
+/// Bring `cache_dir` in line with the storage: download new and changed
+/// objects and delete files that are no longer stored
+///
+/// If the storage can't be listed, a previously synced cache is used as is.
+pub async fn sync_to_cache(storage: &dyn IndexStorage, cache_dir: &Path) -> anyhow::Result<()> {
+    let manifest_path = cache_dir.join(CACHE_MANIFEST);
+    let mut cached: HashMap<String, String> = std::fs::read_to_string(&manifest_path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let objects = match storage.list().await {
+        Ok(objects) => objects,
+        Err(e) if !cached.is_empty() => {
+            warn!("Failed to list remote index ({}), using cached copy in {}", e, cache_dir.display());
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    // Keys come from a bucket that may not be ours; none may leave the cache
+    if let Some(object) = objects.iter().find(|o| !is_safe_key(&o.key)) {
+        anyhow::bail!("Remote index has an object key outside its prefix: {:?}", object.key);
+    }
+    if !objects.iter().any(|o| o.key == "documents.leann.meta.json") {
+        anyhow::bail!("No LEANN index found in remote storage (missing documents.leann.meta.json)");
+    }
+
+    std::fs::create_dir_all(cache_dir)?;
+    let stored: HashMap<&str, &str> = objects.iter().map(|o| (o.key.as_str(), o.etag.as_str())).collect();
+    cached.retain(|key, _| {
+        let keep = stored.contains_key(key.as_str());
+        if !keep && is_safe_key(key) {
+            let _ = std::fs::remove_file(cache_dir.join(key));
+        }
+        keep
+    });
+
+    let stale: Vec<&RemoteObject> = objects
+        .iter()
+        .filter(|o| cached.get(&o.key) != Some(&o.etag) || !cache_dir.join(&o.key).exists())
+        .collect();
+    if !stale.is_empty() {
+        let bytes: u64 = stale.iter().map(|o| o.size).sum();
+        info!(
+            "Downloading {} of {} index components ({:.2} MB)",
+            stale.len(),
+            objects.len(),
+            bytes as f64 / (1024.0 * 1024.0)
+        );
+    }
+    for object in stale {
+        let dest = cache_dir.join(&object.key);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Download next to the destination so a failed download never replaces a cached file
+        let mut partial = dest.clone().into_os_string();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        storage.download(&object.key, &partial).await?;
+        std::fs::rename(&partial, &dest)?;
+        cached.insert(object.key.clone(), object.etag.clone());
+        std::fs::write(&manifest_path, serde_json::to_string_pretty(&cached)?)?;
+    }
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&cached)?)?;
+
+    Ok(())
+}
+
+/// Whether an object key is a plain relative path, safe to join onto the cache directory
+fn is_safe_key(key: &str) -> bool {
+    !key.is_empty()
+        && key != CACHE_MANIFEST
+        && !key.contains('\\')
+        && Path::new(key).components().all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+/// Bucket and key prefix of an `s3://bucket/prefix` URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Location {
+    pub bucket: String,
+    /// Key prefix without leading or trailing slashes
+    pub prefix: String,
+}
+
+impl S3Location {
+    pub fn parse(url: &str) -> anyhow::Result<Self> {
+        let rest = url
+            .strip_prefix("s3://")
+            .ok_or_else(|| anyhow::anyhow!("Not an S3 URL: {}", url))?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            anyhow::bail!("S3 URL has no bucket: {}", url);
+        }
+        Ok(Self {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+}
+
+/// AWS credentials for signing requests
+struct S3Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+/// Index components in an S3 (or S3-compatible) bucket
+///
+/// Configured from the standard `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`,
+/// `AWS_SESSION_TOKEN`, `AWS_REGION`, and `AWS_ENDPOINT_URL` variables.
+/// Without credentials, requests are unsigned (public buckets).
+pub struct S3Storage {
+    client: reqwest::Client,
+    location: S3Location,
+    region: String,
+    /// Custom endpoint (MinIO, R2, ...), addressed path-style
+    endpoint: Option<String>,
+    credentials: Option<S3Credentials>,
+}
+
+impl S3Storage {
+    pub fn from_env(location: S3Location) -> anyhow::Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let credentials = match (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key_id), Some(secret_access_key)) => Some(S3Credentials {
+                access_key_id,
+                secret_access_key,
+                session_token: var("AWS_SESSION_TOKEN"),
+            }),
+            (None, None) => None,
+            _ => anyhow::bail!("Set both AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or neither"),
+        };
+
+        Ok(Self {
+            client: create_client(),
+            location,
+            region: var("AWS_REGION")
+                .or_else(|| var("AWS_DEFAULT_REGION"))
+                .unwrap_or_else(|| "us-east-1".to_string()),
+            endpoint: var("AWS_ENDPOINT_URL").map(|e| e.trim_end_matches('/').to_string()),
+            credentials,
+        })
+    }
+
+    /// Scheme, host, and path of an object key (empty for the bucket itself)
+    fn url_parts(&self, key: &str) -> anyhow::Result<(String, String, String)> {
+        let encoded_key = uri_encode(key, false);
+        match &self.endpoint {
+            Some(endpoint) => {
+                let (scheme, host) = endpoint
+                    .split_once("://")
+                    .ok_or_else(|| anyhow::anyhow!("AWS_ENDPOINT_URL needs a scheme: {}", endpoint))?;
+                let mut path = format!("/{}", uri_encode(&self.location.bucket, false));
+                if !key.is_empty() {
+                    path = format!("{}/{}", path, encoded_key);
+                }
+                Ok((scheme.to_string(), host.to_string(), path))
+            }
+            None => Ok((
+                "https".to_string(),
+                format!("{}.s3.{}.amazonaws.com", self.location.bucket, self.region),
+                format!("/{}", encoded_key),
+            )),
+        }
+    }
+
+    /// A GET request for `key` with a query, signed if there are credentials
+    fn get(&self, key: &str, query: &[(&str, &str)]) -> anyhow::Result<reqwest::RequestBuilder> {
+        let (scheme, host, path) = self.url_parts(key)?;
+        crate::http::check_network("Remote index storage", &format!("{}://{}", scheme, host))?;
+        let mut params: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        params.sort();
+        let query = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let url = if query.is_empty() {
+            format!("{}://{}{}", scheme, host, path)
+        } else {
+            format!("{}://{}{}?{}", scheme, host, path, query)
+        };
+        let request = self.client.get(url);
+        let Some(credentials) = &self.credentials else {
+            return Ok(request);
+        };
+
+        let amz_date = amz_date(crate::time::now_unix());
+        let mut headers = vec![
+            ("host", host.clone()),
+            ("x-amz-content-sha256", UNSIGNED_PAYLOAD.to_string()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let authorization = authorization(credentials, &self.region, &amz_date, &path, &query, &headers);
+
+        let mut request = request.header("authorization", authorization);
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+        Ok(request)
+    }
+
+    fn object_key(&self, key: &str) -> String {
+        if self.location.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", self.location.prefix, key)
+        }
+    }
+}
+
+#[async_trait]
+impl IndexStorage for S3Storage {
+    async fn list(&self) -> anyhow::Result<Vec<RemoteObject>> {
+        let prefix = self.object_key("");
+        let mut objects = Vec::new();
+        let mut continuation: Option<String> = None;
+
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix.as_str())];
+            if let Some(token) = &continuation {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let response = self.get("", &query)?.send().await?;
+            let body = check_response(response, "S3").await?.text().await?;
+            let page = parse_list_objects(&body);
+
+            objects.extend(page.objects.into_iter().filter_map(|mut object| {
+                object.key = object.key.strip_prefix(&prefix)?.to_string();
+                // Skip "directory" placeholders
+                (!object.key.is_empty() && !object.key.ends_with('/')).then_some(object)
+            }));
+            match page.next_continuation_token {
+                Some(token) => continuation = Some(token),
+                None => break,
+            }
+        }
+
+        Ok(objects)
+    }
+
+    async fn download(&self, key: &str, dest: &Path) -> anyhow::Result<()> {
+        use std::io::Write;
+
+        let response = self.get(&self.object_key(key), &[])?.send().await?;
+        let mut response = check_response(response, "S3").await?;
+        let mut file = std::io::BufWriter::new(std::fs::File::create(dest)?);
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// One page of a ListObjectsV2 response
+struct ListObjectsPage {
+    objects: Vec<RemoteObject>,
+    next_continuation_token: Option<String>,
+}
+
+fn parse_list_objects(xml: &str) -> ListObjectsPage {
+    static CONTENTS_RE: OnceLock<Regex> = OnceLock::new();
+    let contents_re = CONTENTS_RE.get_or_init(|| Regex::new(r"(?s)<Contents>(.*?)</Contents>").unwrap());
+
+    let objects = contents_re
+        .captures_iter(xml)
+        .filter_map(|c| {
+            let contents = c.get(1)?.as_str();
+            Some(RemoteObject {
+                key: xml_element(contents, "Key")?,
+                etag: xml_element(contents, "ETag").unwrap_or_default(),
+                size: xml_element(contents, "Size").and_then(|s| s.parse().ok()).unwrap_or(0),
+            })
+        })
+        .collect();
+
+    let truncated = xml_element(xml, "IsTruncated").is_some_and(|t| t == "true");
+    ListObjectsPage {
+        objects,
+        next_continuation_token: truncated.then(|| xml_element(xml, "NextContinuationToken")).flatten(),
+    }
+}
+
+/// Text of the first `<name>` element, unescaped
+fn xml_element(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&format!("</{}>", name))?;
+    Some(
+        xml[start..end]
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// URI-encode per SigV4: everything but unreserved characters, and `/` too
+/// unless encoding a path
+fn uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// `x-amz-date` timestamp (e.g. "20240131T120000Z")
+fn amz_date(secs: u64) -> String {
+    crate::time::format_rfc3339(secs).replace(['-', ':'], "")
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SigV4 signing key for a date (YYYYMMDD), region, and service
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret_access_key).as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    hmac_sha256(&key, "aws4_request")
+}
+
+/// SigV4 `Authorization` header for an unsigned-payload GET request
+///
+/// `headers` must have lowercase names and be sorted by name.
+fn authorization(
+    credentials: &S3Credentials,
+    region: &str,
+    amz_date: &str,
+    path: &str,
+    query: &str,
+    headers: &[(&str, String)],
+) -> String {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "GET\n{}\n{}\n{}\n{}\n{}",
+        path, query, canonical_headers, signed_headers, UNSIGNED_PAYLOAD
+    );
+
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex(&hmac_sha256(
+        &signing_key(&credentials.secret_access_key, date, region, "s3"),
+        &string_to_sign,
+    ));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// In-memory storage that records downloads
+    struct MemoryStorage {
+        objects: Vec<(RemoteObject, &'static str)>,
+        downloads: Mutex<Vec<String>>,
+    }
+
+    impl MemoryStorage {
+        fn new(objects: &[(&str, &str, &'static str)]) -> Self {
+            Self {
+                objects: objects
+                    .iter()
+                    .map(|(key, etag, body)| {
+                        let object = RemoteObject {
+                            key: key.to_string(),
+                            etag: etag.to_string(),
+                            size: body.len() as u64,
+                        };
+                        (object, *body)
+                    })
+                    .collect(),
+                downloads: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl IndexStorage for MemoryStorage {
+        async fn list(&self) -> anyhow::Result<Vec<RemoteObject>> {
+            Ok(self.objects.iter().map(|(o, _)| o.clone()).collect())
+        }
+
+        async fn download(&self, key: &str, dest: &Path) -> anyhow::Result<()> {
+            let (_, body) = self.objects.iter().find(|(o, _)| o.key == key).unwrap();
+            self.downloads.lock().unwrap().push(key.to_string());
+            std::fs::write(dest, body)?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_downloads_only_changed_objects() {
+        let dir = std::env::temp_dir().join(format!("leann-remote-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let storage = MemoryStorage::new(&[
+            ("documents.leann.meta.json", "\"m1\"", "{}"),
+            ("documents.leann.passages.jsonl", "\"p1\"", "old"),
+            ("documents.leann.bm25.json", "\"b1\"", "bm25"),
+        ]);
+        sync_to_cache(&storage, &dir).await.unwrap();
+        assert_eq!(storage.downloads.lock().unwrap().len(), 3);
+
+        // Passages changed and the BM25 file is gone
+        let storage = MemoryStorage::new(&[
+            ("documents.leann.meta.json", "\"m1\"", "{}"),
+            ("documents.leann.passages.jsonl", "\"p2\"", "new"),
+        ]);
+        sync_to_cache(&storage, &dir).await.unwrap();
+        assert_eq!(*storage.downloads.lock().unwrap(), vec!["documents.leann.passages.jsonl"]);
+        assert_eq!(std::fs::read_to_string(dir.join("documents.leann.passages.jsonl")).unwrap(), "new");
+        assert!(!dir.join("documents.leann.bm25.json").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync_requires_index_metadata() {
+        let dir = std::env::temp_dir().join(format!("leann-remote-empty-{}", std::process::id()));
+        let storage = MemoryStorage::new(&[("notes.txt", "\"n\"", "hi")]);
+        assert!(sync_to_cache(&storage, &dir).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sync_rejects_keys_outside_cache() {
+        let root = std::env::temp_dir().join(format!("leann-remote-escape-{}", std::process::id()));
+        let dir = root.join("cache");
+        for key in ["../../escaped.txt", "/tmp/escaped.txt", "a\\..\\..\\escaped.txt", "sub/../../escaped.txt", ".leann-remote.json"] {
+            let storage = MemoryStorage::new(&[("documents.leann.meta.json", "\"m\"", "{}"), (key, "\"e\"", "pwned")]);
+            assert!(sync_to_cache(&storage, &dir).await.is_err(), "{}", key);
+            assert!(storage.downloads.lock().unwrap().is_empty());
+        }
+        assert!(!root.join("escaped.txt").exists());
+        assert!(is_safe_key("shards/0/documents.index"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_parse_s3_location() {
+        let location = S3Location::parse("s3://my-bucket/indexes/docs/").unwrap();
+        assert_eq!(location.bucket, "my-bucket");
+        assert_eq!(location.prefix, "indexes/docs");
+        assert_eq!(S3Location::parse("s3://my-bucket").unwrap().prefix, "");
+        assert!(S3Location::parse("s3:///docs").is_err());
+        assert!(S3Location::parse("/local/docs").is_err());
+    }
+
+    #[test]
+    fn test_parse_list_objects() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult>
+  <IsTruncated>true</IsTruncated>
+  <Contents><Key>docs/documents.leann.meta.json</Key><ETag>&quot;abc&quot;</ETag><Size>512</Size></Contents>
+  <Contents><Key>docs/a&amp;b.txt</Key><ETag>&quot;def&quot;</ETag><Size>7</Size></Contents>
+  <NextContinuationToken>token/1</NextContinuationToken>
+</ListBucketResult>"#;
+        let page = parse_list_objects(xml);
+        assert_eq!(page.objects.len(), 2);
+        assert_eq!(page.objects[0].key, "docs/documents.leann.meta.json");
+        assert_eq!(page.objects[0].etag, "\"abc\"");
+        assert_eq!(page.objects[0].size, 512);
+        assert_eq!(page.objects[1].key, "docs/a&b.txt");
+        assert_eq!(page.next_continuation_token.as_deref(), Some("token/1"));
+
+        let last = parse_list_objects("<IsTruncated>false</IsTruncated><NextContinuationToken>x</NextContinuationToken>");
+        assert!(last.next_continuation_token.is_none());
+    }
+
+    #[test]
+    fn test_uri_encode() {
+        assert_eq!(uri_encode("docs/a b+c.json", false), "docs/a%20b%2Bc.json");
+        assert_eq!(uri_encode("docs/", true), "docs%2F");
+    }
+
+    #[test]
+    fn test_signing_key() {
+        // Example from the AWS Signature Version 4 documentation
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex(&key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn test_amz_date() {
+        assert_eq!(amz_date(1_706_702_400), "20240131T120000Z");
+    }
+}
@@ -0,0 +1,85 @@
+//! Search result cache - least-recently-used caching of repeated searches
+//!
+//! Agents and MCP clients often send the same query several times in a row.
+//! `IndexSearcher::with_cache` keeps recent results keyed by a hash of the
+//! query embedding and search options. The cache belongs to the loaded index,
+//! so reloading an updated index starts with an empty one.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// A fixed-capacity cache evicting the least recently used entry
+#[derive(Debug)]
+pub struct LruCache<V> {
+    capacity: usize,
+    /// Value and the tick it was last used at
+    entries: HashMap<u64, (V, u64)>,
+    tick: u64,
+}
+
+impl<V: Clone> LruCache<V> {
+    /// Empty cache holding up to `capacity` entries
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Cached value for `key`, marking it recently used
+    pub fn get(&mut self, key: u64) -> Option<V> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(&key).map(|(value, last_used)| {
+            *last_used = tick;
+            value.clone()
+        })
+    }
+
+    /// Cache a value, evicting the least recently used entry when full
+    pub fn insert(&mut self, key: u64, value: V) {
+        self.tick += 1;
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            if let Some(oldest) = self.entries.iter().min_by_key(|(_, (_, used))| *used).map(|(k, _)| *k) {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key, (value, self.tick));
+    }
+}
+
+/// Cache key for a query embedding and the `Debug` form of its options
+///
+/// Embeddings are hashed bit for bit, so only identical queries share a key.
+pub fn cache_key(embedding: &[f32], options: &impl std::fmt::Debug) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for x in embedding {
+        x.to_bits().hash(&mut hasher);
+    }
+    format!("{:?}", options).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!(cache.get(1), Some("a"));
+        cache.insert(3, "c");
+        assert_eq!(cache.get(2), None);
+        assert_eq!(cache.get(1), Some("a"));
+        assert_eq!(cache.get(3), Some("c"));
+        assert_eq!(cache.entries.len(), 2);
+
+        assert_eq!(cache_key(&[0.5, 1.0], &(5, "x")), cache_key(&[0.5, 1.0], &(5, "x")));
+        assert_ne!(cache_key(&[0.5, 1.0], &(5, "x")), cache_key(&[0.5, 1.0], &(6, "x")));
+        assert_ne!(cache_key(&[0.5, 1.0], &(5, "x")), cache_key(&[0.5, 1.5], &(5, "x")));
+    }
+}
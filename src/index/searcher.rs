@@ -1,18 +1,31 @@
 //! Index searcher - query vector indexes
 
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::{Mutex, OnceLock};
 
+use serde::{Deserialize, Serialize};
 use tracing::info;
 
-use crate::backend::{BackendSearcher, BackendType};
+use crate::backend::BackendSearcher;
 
-use super::bm25::{Bm25Scorer, hybrid_rerank};
+use super::bm25::{Bm25Analyzer, Bm25Scorer, hybrid_rerank};
+use super::boost::Boost;
+use super::diversify::{collapse_by, mmr};
+use super::embeddings::EmbeddingsStore;
+use super::feedback::{FeedbackStore, QueryFeedback};
+use super::explain::{Exclusion, FusionStep, SearchExplanation, SearchTrace};
+use super::field_index::{FieldIndex, IdBitset};
 use super::filter::MetadataFilter;
 use super::meta::IndexMeta;
-use super::passages::PassageStore;
+use super::multi_vector::{maxsim, MultiVectorStore, RERANK_CANDIDATES};
+use super::passages::{Passage, PassageStore};
+use super::result_cache::{cache_key, LruCache};
+use super::sparse::SparseIndex;
+use super::spelling::SpellCorrector;
 
 /// Search result with passage text and metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub id: String,
     pub score: f32,
@@ -20,6 +33,84 @@ pub struct SearchResult {
     pub metadata: serde_json::Value,
 }
 
+/// Search hits aggregated by source file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHit {
+    pub source: String,
+    /// Highest score among the file's hits
+    pub best_score: f32,
+    /// Ranking score: best hit plus decaying credit for further hits
+    pub combined_score: f32,
+    /// Number of hits in this file
+    pub hits: usize,
+}
+
+/// Group results by `source` and rank files by combined score
+///
+/// Each file's hits are sorted by score and weighted 1, 1/2, 1/4, ... so the
+/// best hit dominates but several good hits outrank a single one. Results
+/// without a `source` are skipped.
+pub fn aggregate_by_source(results: &[SearchResult]) -> Vec<FileHit> {
+    let mut by_source: Vec<(String, Vec<f32>)> = Vec::new();
+    for result in results {
+        let Some(source) = result.metadata.get("source").and_then(|s| s.as_str()) else {
+            continue;
+        };
+        match by_source.iter_mut().find(|(s, _)| s == source) {
+            Some((_, scores)) => scores.push(result.score),
+            None => by_source.push((source.to_string(), vec![result.score])),
+        }
+    }
+
+    let mut files: Vec<FileHit> = by_source
+        .into_iter()
+        .map(|(source, mut scores)| {
+            scores.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+            let combined_score = scores
+                .iter()
+                .enumerate()
+                .map(|(i, score)| score / (1u32 << i.min(31)) as f32)
+                .sum();
+            FileHit {
+                source,
+                best_score: scores[0],
+                combined_score,
+                hits: scores.len(),
+            }
+        })
+        .collect();
+
+    // Stable sort keeps first-seen order (i.e. best rank) on ties
+    files.sort_by(|a, b| {
+        b.combined_score
+            .partial_cmp(&a.combined_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    files
+}
+
+/// When metadata filters are applied relative to the vector search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterMode {
+    /// Restrict the vector search to matching passages
+    #[default]
+    Pre,
+    /// Filter the nearest neighbors after retrieval (may return fewer than top-k)
+    Post,
+}
+
+impl std::str::FromStr for FilterMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pre" => Ok(Self::Pre),
+            "post" => Ok(Self::Post),
+            _ => Err(format!("Unknown filter mode: {}", s)),
+        }
+    }
+}
+
 /// Search options for advanced queries
 #[derive(Debug, Clone, Default)]
 pub struct SearchOptions {
@@ -35,6 +126,30 @@ pub struct SearchOptions {
     pub hybrid_alpha: f32,
     /// Query text (for BM25 in hybrid mode)
     pub query_text: Option<String>,
+    /// Whether the filter is applied before or after the vector search
+    pub filter_mode: FilterMode,
+    /// MMR trade-off between relevance (1.0) and diversity (0.0), if enabled
+    pub mmr_lambda: Option<f32>,
+    /// Metadata field to collapse results by (best result per value)
+    pub group_by: Option<String>,
+    /// Drop results scoring below this
+    pub min_score: Option<f32>,
+    /// Number of leading results to skip (for pagination)
+    pub offset: usize,
+    /// Embedding of an LLM-written hypothetical answer (HyDE), blended into the query vector
+    pub hyde_embedding: Option<Vec<f32>>,
+    /// Weight of the HyDE embedding in the blend: 1.0 = hypothetical answer only
+    pub hyde_weight: f32,
+    /// Learned sparse query vector, fused with the dense scores
+    pub sparse_query: Option<Vec<(u32, f32)>>,
+    /// Weight for dense scores in sparse fusion (0.0-1.0)
+    pub sparse_alpha: f32,
+    /// Per-token query vectors for late-interaction (MaxSim) reranking
+    pub multi_vector_query: Option<Vec<Vec<f32>>>,
+    /// Score multipliers for results matching metadata filters
+    pub boost: Option<Boost>,
+    /// Skip the index's relevance feedback (`leann feedback`)
+    pub ignore_feedback: bool,
 }
 
 impl SearchOptions {
@@ -46,6 +161,18 @@ impl SearchOptions {
             hybrid: false,
             hybrid_alpha: 0.7,
             query_text: None,
+            filter_mode: FilterMode::Pre,
+            mmr_lambda: None,
+            group_by: None,
+            min_score: None,
+            offset: 0,
+            hyde_embedding: None,
+            hyde_weight: 0.5,
+            sparse_query: None,
+            sparse_alpha: 0.5,
+            multi_vector_query: None,
+            boost: None,
+            ignore_feedback: false,
         }
     }
 
@@ -54,12 +181,193 @@ impl SearchOptions {
         self
     }
 
+    pub fn with_filter_mode(mut self, mode: FilterMode) -> Self {
+        self.filter_mode = mode;
+        self
+    }
+
+    pub fn with_mmr(mut self, lambda: f32) -> Self {
+        self.mmr_lambda = Some(lambda);
+        self
+    }
+
+    pub fn with_group_by(mut self, field: String) -> Self {
+        self.group_by = Some(field);
+        self
+    }
+
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+
+    /// Skip the first `offset` results; `top_k` becomes the page size
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
     pub fn with_hybrid(mut self, query_text: String, alpha: f32) -> Self {
         self.hybrid = true;
         self.hybrid_alpha = alpha;
         self.query_text = Some(query_text);
         self
     }
+
+    /// Fuse dense scores with learned sparse (SPLADE, BM42) scores
+    ///
+    /// Requires an index built with sparse vectors. Applied after BM25 when
+    /// hybrid search is also enabled.
+    pub fn with_sparse(mut self, query: Vec<(u32, f32)>, alpha: f32) -> Self {
+        self.sparse_query = Some(query);
+        self.sparse_alpha = alpha;
+        self
+    }
+
+    /// Rerank candidates by late interaction (MaxSim) with per-token query vectors
+    ///
+    /// Requires an index built with `--multi-vector`. At least the top 100
+    /// candidates are reranked, after any BM25 or sparse fusion; scores become
+    /// mean MaxSim similarities.
+    pub fn with_multi_vector(mut self, query_tokens: Vec<Vec<f32>>) -> Self {
+        self.multi_vector_query = Some(query_tokens);
+        self
+    }
+
+    /// Multiply the scores of results matching the boost's filters
+    ///
+    /// Applied after retrieval and any fusion or reranking, to a larger
+    /// candidate pool so boosted results can move up into the page;
+    /// `min_score` still applies to the unboosted scores.
+    pub fn with_boost(mut self, boost: Boost) -> Self {
+        self.boost = Some(boost);
+        self
+    }
+
+    /// Rank without the index's relevance feedback
+    pub fn without_feedback(mut self) -> Self {
+        self.ignore_feedback = true;
+        self
+    }
+
+    /// Search with a blend of the query and a hypothetical answer's embedding
+    ///
+    /// Only the vector search uses the blend; BM25 in hybrid mode still scores
+    /// the query text, so the two compose.
+    pub fn with_hyde(mut self, embedding: Vec<f32>, weight: f32) -> Self {
+        self.hyde_embedding = Some(embedding);
+        self.hyde_weight = weight;
+        self
+    }
+
+    /// Vector to search with: the query embedding, blended with the HyDE
+    /// embedding if one is set
+    pub fn query_vector(&self, query_embedding: &[f32]) -> anyhow::Result<Vec<f32>> {
+        match &self.hyde_embedding {
+            Some(hyde) => blend_embeddings(query_embedding, hyde, self.hyde_weight),
+            None => Ok(query_embedding.to_vec()),
+        }
+    }
+
+    /// Fuse the rankings of several queries with RRF, then apply `offset` and `top_k`
+    ///
+    /// Each list should hold the top `top_k + offset` results of one query;
+    /// `min_score` applies to their similarities before fusion.
+    pub fn fuse(&self, lists: Vec<Vec<SearchResult>>) -> Vec<SearchResult> {
+        let lists = lists
+            .into_iter()
+            .map(|list| {
+                list.into_iter()
+                    .filter(|r| self.min_score.map_or(true, |min| r.score >= min))
+                    .collect()
+            })
+            .collect();
+        reciprocal_rank_fusion(lists)
+            .into_iter()
+            .skip(self.offset)
+            .take(self.top_k)
+            .collect()
+    }
+
+    /// Apply `min_score`, `boost`, `offset`, and `top_k` to already-ranked results
+    ///
+    /// For searchers that don't take `SearchOptions` (e.g. recompute mode);
+    /// fetch `top_k + offset` results and pass them through this.
+    pub fn paginate(&self, results: Vec<SearchResult>) -> Vec<SearchResult> {
+        let mut results: Vec<SearchResult> = results
+            .into_iter()
+            .filter(|r| self.min_score.map_or(true, |min| r.score >= min))
+            .collect();
+        if let Some(boost) = &self.boost {
+            for result in &mut results {
+                result.score *= boost.factor(&result.metadata);
+            }
+            results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+        results
+            .into_iter()
+            .skip(self.offset)
+            .take(self.top_k)
+            .collect()
+    }
+}
+
+/// Rank offset for reciprocal rank fusion; damps the advantage of the very top ranks
+const RRF_K: f32 = 60.0;
+
+/// Merge rankings with reciprocal rank fusion
+///
+/// A result scores the sum of `1 / (RRF_K + rank)` over the lists it appears
+/// in, so passages found by several queries rise to the top. Scores of the
+/// fused results are RRF scores, not similarities.
+pub fn reciprocal_rank_fusion(lists: Vec<Vec<SearchResult>>) -> Vec<SearchResult> {
+    let mut fused: Vec<(SearchResult, f32)> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for list in lists {
+        for (rank, result) in list.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            match positions.get(&result.id) {
+                Some(&pos) => fused[pos].1 += score,
+                None => {
+                    positions.insert(result.id.clone(), fused.len());
+                    fused.push((result, score));
+                }
+            }
+        }
+    }
+
+    // Stable sort: ties keep first-seen order
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    fused
+        .into_iter()
+        .map(|(mut result, score)| {
+            result.score = score;
+            result
+        })
+        .collect()
+}
+
+/// Weighted mix of two embeddings, each normalized first so neither dominates
+/// by magnitude; the result is normalized for inner-product search
+fn blend_embeddings(a: &[f32], b: &[f32], weight_b: f32) -> anyhow::Result<Vec<f32>> {
+    if a.len() != b.len() {
+        anyhow::bail!("Cannot blend embeddings of {} and {} dimensions", a.len(), b.len());
+    }
+    if !(0.0..=1.0).contains(&weight_b) {
+        anyhow::bail!("HyDE weight must be between 0 and 1, got {}", weight_b);
+    }
+
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt().max(f32::EPSILON);
+    let (norm_a, norm_b) = (norm(a), norm(b));
+    let blended: Vec<f32> = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| (1.0 - weight_b) * x / norm_a + weight_b * y / norm_b)
+        .collect();
+
+    let norm_blended = norm(&blended);
+    Ok(blended.into_iter().map(|x| x / norm_blended).collect())
 }
 
 /// Searcher for querying a LEANN index
@@ -67,8 +375,26 @@ pub struct IndexSearcher {
     passages: PassageStore,
     backend: Box<dyn BackendSearcher>,
     id_map: Vec<String>,
-    /// All passage texts for BM25 (lazy-loaded)
-    all_texts: Option<Vec<String>>,
+    /// BM25 scorer over all passage texts (built on first use)
+    bm25: OnceLock<Bm25Scorer>,
+    /// BM25 text analysis the index was built with
+    bm25_analyzer: Bm25Analyzer,
+    /// Spelling corrector over the BM25 vocabulary (built on first use)
+    speller: OnceLock<SpellCorrector>,
+    /// Scope filter restricting which passages are loaded and returned
+    scope: Option<MetadataFilter>,
+    /// Per-field value indexes for pre-filtering (None for older indexes)
+    field_index: Option<FieldIndex>,
+    /// Stored embeddings (recompute-mode indexes), used for MMR when present
+    embeddings: Option<EmbeddingsStore>,
+    /// Learned sparse vectors (indexes built with a sparse model)
+    sparse: Option<SparseIndex>,
+    /// Per-token vectors (indexes built with --multi-vector)
+    multi_vector: Option<MultiVectorStore>,
+    /// Recent results by query embedding and options, if caching is enabled
+    cache: Option<Mutex<LruCache<Vec<SearchResult>>>>,
+    /// Relevance feedback given for the index, if any
+    feedback: Option<FeedbackStore>,
 }
 
 impl IndexSearcher {
@@ -88,26 +414,95 @@ impl IndexSearcher {
                 .collect()
         } else {
             // Fall back to passage store IDs
-            passages.ids().cloned().collect()
+            passages.ids().map(String::from).collect()
         };
 
         // Load backend
-        let backend_type = match meta.backend_name.as_str() {
-            "hnsw" => BackendType::Hnsw,
-            "diskann" => BackendType::DiskAnn,
-            _ => anyhow::bail!("Unknown backend: {}", meta.backend_name),
+        let backend = meta.backend()?.load_searcher(index_path, meta.dimensions)?;
+
+        // A field index that doesn't cover every passage is stale; fall back to scanning
+        let field_index = FieldIndex::load(index_path).filter(|f| f.len() == id_map.len());
+
+        let embeddings_path = EmbeddingsStore::path_for_index(index_path);
+        let embeddings = if EmbeddingsStore::exists(&embeddings_path) {
+            Some(EmbeddingsStore::open(&embeddings_path, meta.dimensions)?)
+        } else {
+            None
+        };
+
+        // Like the field index, a sparse index that doesn't cover every passage is stale
+        let sparse = match SparseIndex::load(index_path)? {
+            Some(sparse) if sparse.len() == id_map.len() => Some(sparse),
+            Some(sparse) => {
+                tracing::warn!(
+                    "Ignoring stale sparse index ({} of {} passages)",
+                    sparse.len(),
+                    id_map.len()
+                );
+                None
+            }
+            None => None,
+        };
+
+        let multi_vector = match MultiVectorStore::open(index_path)? {
+            Some(store) if store.len() == id_map.len() => Some(store),
+            Some(store) => {
+                tracing::warn!(
+                    "Ignoring stale multi-vector store ({} of {} passages)",
+                    store.len(),
+                    id_map.len()
+                );
+                None
+            }
+            None => None,
         };
 
-        let backend = backend_type.load_searcher(index_path, meta.dimensions)?;
+        let feedback = index_path.parent().and_then(FeedbackStore::load);
+        if let Some(feedback) = &feedback {
+            info!("Loaded relevance feedback for {} queries", feedback.len());
+        }
 
         Ok(Self {
             passages,
             backend,
             id_map,
-            all_texts: None,
+            bm25: OnceLock::new(),
+            bm25_analyzer: meta.bm25_analyzer.clone().unwrap_or_default(),
+            speller: OnceLock::new(),
+            scope: None,
+            field_index,
+            embeddings,
+            sparse,
+            multi_vector,
+            cache: None,
+            feedback,
         })
     }
 
+    /// Load an index, keeping only passages that match a scope filter
+    ///
+    /// Passages outside the scope are dropped from the passage store, so they
+    /// are never returned from search and don't occupy memory in the offset map.
+    pub fn load_scoped(
+        index_path: &Path,
+        meta: &IndexMeta,
+        scope: Option<MetadataFilter>,
+    ) -> anyhow::Result<Self> {
+        let mut searcher = Self::load(index_path, meta)?;
+
+        if let Some(filter) = scope {
+            let retained = searcher.passages.retain(|p| filter.matches(&p.metadata))?;
+            info!(
+                "Scoped index to {} of {} passages",
+                retained,
+                searcher.id_map.len()
+            );
+            searcher.scope = Some(filter);
+        }
+
+        Ok(searcher)
+    }
+
     /// Simple search for nearest neighbors
     pub fn search(
         &self,
@@ -119,35 +514,184 @@ impl IndexSearcher {
         self.search_with_options(query_embedding, &opts)
     }
 
+    /// Search with several phrasings of a query and fuse the rankings with RRF
+    ///
+    /// Each query is an embedding and its text (the BM25 query in hybrid mode).
+    pub fn search_fused(
+        &self,
+        queries: &[(&[f32], &str)],
+        opts: &SearchOptions,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        let mut per_query = opts.clone();
+        per_query.top_k = opts.top_k + opts.offset;
+        per_query.offset = 0;
+
+        let mut lists = Vec::with_capacity(queries.len());
+        for (embedding, text) in queries {
+            if per_query.hybrid {
+                per_query.query_text = Some(text.to_string());
+            }
+            lists.push(self.search_with_options(embedding, &per_query)?);
+        }
+        Ok(opts.fuse(lists))
+    }
+
+    /// Cache the results of up to `capacity` recent searches (0 disables caching)
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = (capacity > 0).then(|| Mutex::new(LruCache::new(capacity)));
+        self
+    }
+
     /// Advanced search with options
+    ///
+    /// With caching enabled, repeating a search with the same embedding and
+    /// options returns the cached results.
     pub fn search_with_options(
         &self,
         query_embedding: &[f32],
         opts: &SearchOptions,
     ) -> anyhow::Result<Vec<SearchResult>> {
-        // Fetch more results if filtering or hybrid, to ensure we have enough after processing
-        let fetch_k = if opts.filter.is_some() || opts.hybrid {
-            opts.top_k * 5 // More for hybrid to get diverse results
+        let Some(cache) = &self.cache else {
+            return self.search_uncached(query_embedding, opts);
+        };
+        let key = cache_key(query_embedding, opts);
+        if let Some(results) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(key) {
+            return Ok(results);
+        }
+        let results = self.search_uncached(query_embedding, opts)?;
+        cache.lock().unwrap_or_else(|e| e.into_inner()).insert(key, results.clone());
+        Ok(results)
+    }
+
+    /// Search, explaining how each candidate was scored and why the ones
+    /// not returned were dropped (bypasses the result cache)
+    pub fn search_explained(
+        &self,
+        query_embedding: &[f32],
+        opts: &SearchOptions,
+    ) -> anyhow::Result<(Vec<SearchResult>, SearchExplanation)> {
+        let mut trace = SearchTrace::default();
+        trace.explanation.filter = match (&self.scope, &opts.filter) {
+            (Some(scope), Some(filter)) => Some(MetadataFilter::And { and: vec![scope.clone(), filter.clone()] }),
+            (scope, filter) => filter.clone().or_else(|| scope.clone()),
+        };
+        trace.explanation.search_query = opts.query_text.clone();
+        trace.explanation.filter_mode = format!("{:?}", opts.filter_mode).to_lowercase();
+        trace.explanation.min_score = opts.min_score;
+        trace.explanation.boost = opts.boost.as_ref().map(|b| b.to_string());
+
+        let (results, page) = self.search_traced(query_embedding, opts, Some(&mut trace))?;
+        Ok((results, trace.finish(&page)))
+    }
+
+    fn search_uncached(
+        &self,
+        query_embedding: &[f32],
+        opts: &SearchOptions,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        Ok(self.search_traced(query_embedding, opts, None)?.0)
+    }
+
+    /// Search, recording candidate scores and exclusions in `trace` if given;
+    /// also returns the positions of the returned results
+    #[tracing::instrument(name = "index.search", skip_all, fields(top_k = opts.top_k, hybrid = opts.hybrid))]
+    fn search_traced(
+        &self,
+        query_embedding: &[f32],
+        opts: &SearchOptions,
+        mut trace: Option<&mut SearchTrace>,
+    ) -> anyhow::Result<(Vec<SearchResult>, Vec<usize>)> {
+        let feedback = self
+            .feedback
+            .as_ref()
+            .filter(|_| !opts.ignore_feedback)
+            .map(|store| store.for_query(query_embedding))
+            .filter(|feedback| !feedback.is_empty());
+        let query_vector = opts.query_vector(query_embedding)?;
+        let query_embedding = query_vector.as_slice();
+
+        let restricted = opts.filter.is_some() || self.scope.is_some() || self.has_missing_passages();
+        let allowed = if restricted && opts.filter_mode == FilterMode::Pre {
+            Some(self.allowed_ids(opts.filter.as_ref())?)
+        } else {
+            None
+        };
+
+        // When post-filtering, the metadata index (if it covers the filter) rejects
+        // candidates without loading their passages
+        let indexed = match (&allowed, &opts.filter, &self.field_index) {
+            (None, Some(filter), Some(field_index)) => field_index.allowed(filter),
+            _ => None,
+        };
+
+        // Diversification picks top_k from a larger candidate pool, and boosts
+        // and feedback may lift results from below the page into it
+        let reorder = opts.mmr_lambda.is_some() || opts.group_by.is_some() || opts.boost.is_some() || feedback.is_some();
+
+        // Results up to the end of the requested page
+        let wanted = opts.top_k + opts.offset;
+
+        let sparse = match (&opts.sparse_query, &self.sparse) {
+            (Some(query), Some(index)) => Some((query, index)),
+            (Some(_), None) => anyhow::bail!(
+                "This index has no sparse vectors. Rebuild it with --sparse-model to use sparse search."
+            ),
+            (None, _) => None,
+        };
+
+        let late_interaction = match (&opts.multi_vector_query, &self.multi_vector) {
+            (Some(query), Some(store)) => Some((query, store)),
+            (Some(_), None) => anyhow::bail!(
+                "This index has no per-token vectors. Rebuild it with --multi-vector to use late-interaction reranking."
+            ),
+            (None, _) => None,
+        };
+
+        // Fetch more results if post-filtering, hybrid, diversifying, or boosting, to ensure we have enough after processing
+        let fetch_k = if opts.hybrid || sparse.is_some() || reorder || (restricted && allowed.is_none()) {
+            wanted * 5 // More for hybrid to get diverse results
+        } else {
+            wanted
+        };
+        // Late interaction reranks a fixed pool of dense candidates
+        let fetch_k = if late_interaction.is_some() {
+            fetch_k.max(RERANK_CANDIDATES)
         } else {
-            opts.top_k
+            fetch_k
         };
 
         // Search backend
-        let (indices, distances) = self.backend.search(query_embedding, fetch_k, opts.complexity)?;
+        let span = tracing::info_span!("backend.search", k = fetch_k, filtered = allowed.is_some());
+        let (indices, distances) = match &allowed {
+            Some(allowed) if allowed.count() == 0 => return Ok((Vec::new(), Vec::new())),
+            Some(allowed) => span.in_scope(|| {
+                self.backend.search_filtered(
+                    query_embedding,
+                    fetch_k,
+                    opts.complexity,
+                    &|idx: u64| allowed.contains(idx as usize),
+                )
+            })?,
+            None => span.in_scope(|| self.backend.search(query_embedding, fetch_k, opts.complexity))?,
+        };
 
-        // Convert to (idx, score) pairs
+        // Convert to (idx, score) pairs. Backends return inner-product distances
+        // (1 - dot); report similarity so higher is better, as in recompute mode.
         let mut vector_results: Vec<(usize, f32)> = indices
             .iter()
             .zip(distances.iter())
-            .map(|(idx, dist)| (*idx as usize, *dist))
+            .map(|(idx, dist)| (*idx as usize, 1.0 - *dist))
             .collect();
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.explanation.fetched = fetch_k;
+            trace.vector_scores = vector_results.iter().copied().collect();
+        }
 
         // Apply hybrid search if enabled
         if opts.hybrid {
             if let Some(query_text) = &opts.query_text {
-                // Load all texts for BM25 if not cached
-                let all_texts = self.get_all_texts()?;
-                let scorer = Bm25Scorer::build(&all_texts);
+                let scorer = self.bm25_scorer()?;
                 let bm25_scores = scorer.score_query(query_text);
 
                 // Get top BM25 results that might not be in vector results
@@ -165,14 +709,69 @@ impl IndexSearcher {
                 }
 
                 vector_results = hybrid_rerank(&vector_results, &bm25_scores, opts.hybrid_alpha);
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.explanation.fusion.push(FusionStep { method: "bm25", vector_weight: Some(opts.hybrid_alpha) });
+                    trace.bm25_scores = Some(bm25_scores);
+                }
             }
         }
 
+        // Fuse learned sparse scores the same way, adding top sparse matches as candidates
+        if let Some((query, index)) = sparse {
+            let sparse_scores = index.score(query);
+
+            let mut sparse_top: Vec<(usize, f32)> = sparse_scores
+                .iter()
+                .copied()
+                .enumerate()
+                .filter(|(_, s)| *s > 0.0)
+                .collect();
+            sparse_top.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            sparse_top.truncate(fetch_k);
+
+            let candidates: std::collections::HashSet<usize> =
+                vector_results.iter().map(|(idx, _)| *idx).collect();
+            for (idx, _) in sparse_top {
+                if !candidates.contains(&idx) {
+                    vector_results.push((idx, 0.0));
+                }
+            }
+
+            vector_results = hybrid_rerank(&vector_results, &sparse_scores, opts.sparse_alpha);
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.explanation.fusion.push(FusionStep { method: "sparse", vector_weight: Some(opts.sparse_alpha) });
+                trace.sparse_scores = Some(sparse_scores);
+            }
+        }
+
+        // Rerank the candidates by MaxSim over per-token vectors
+        if let Some((query, store)) = late_interaction {
+            vector_results = vector_results
+                .into_iter()
+                .map(|(idx, score)| match store.get(idx) {
+                    Some(tokens) => (idx, maxsim(query, &tokens)),
+                    None => (idx, score),
+                })
+                .collect();
+            vector_results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.explanation.fusion.push(FusionStep { method: "maxsim", vector_weight: None });
+                trace.maxsim_scores = vector_results.iter().copied().collect();
+            }
+        }
+
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.ranked(&vector_results, |idx| self.id_map.get(idx).cloned().unwrap_or_else(|| idx.to_string()));
+        }
+
         // Convert to SearchResults and apply filtering
-        let mut results = Vec::with_capacity(opts.top_k);
+        let limit = if reorder { fetch_k } else { wanted };
+        let mut results: Vec<(usize, SearchResult)> = Vec::with_capacity(limit);
+        // A passage re-added after deletion has vectors at both positions
+        let mut seen_ids: std::collections::HashSet<String> = std::collections::HashSet::new();
 
         for (idx, score) in vector_results {
-            if results.len() >= opts.top_k {
+            if results.len() >= limit {
                 break;
             }
 
@@ -183,51 +782,313 @@ impl IndexSearcher {
                 idx.to_string()
             };
 
+            // Skip passages outside the loaded scope, and deleted ones
+            let exclusion = if !self.passages.contains(&id) {
+                Some(Exclusion::NotInIndex)
+            } else if seen_ids.contains(&id) {
+                Some(Exclusion::Duplicate)
+            } else if indexed.as_ref().is_some_and(|set| !set.contains(idx)) {
+                Some(Exclusion::Filtered)
+            } else if opts.min_score.is_some_and(|min| score < min) {
+                Some(Exclusion::BelowMinScore)
+            } else {
+                None
+            };
+            if let Some(exclusion) = exclusion {
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.exclude(idx, exclusion);
+                }
+                continue;
+            }
+
             // Get passage text and metadata
             match self.passages.get(&id) {
                 Ok(passage) => {
                     // Apply metadata filter
                     if let Some(filter) = &opts.filter {
                         if !filter.matches(&passage.metadata) {
+                            if let Some(trace) = trace.as_deref_mut() {
+                                trace.exclude(idx, Exclusion::Filtered);
+                            }
                             continue;
                         }
                     }
 
-                    results.push(SearchResult {
+                    if let Some(trace) = trace.as_deref_mut() {
+                        trace.keep(idx, &passage.metadata);
+                    }
+                    seen_ids.insert(id.clone());
+                    let result = SearchResult {
                         id,
                         score,
                         text: passage.text,
                         metadata: passage.metadata,
-                    });
+                    };
+                    results.push((idx, result));
                 }
                 Err(e) => {
                     tracing::warn!("Failed to load passage {}: {}", id, e);
+                    if let Some(trace) = trace.as_deref_mut() {
+                        trace.exclude(idx, Exclusion::NotInIndex);
+                    }
                 }
             }
         }
 
-        Ok(results)
+        if let Some(boost) = &opts.boost {
+            for (idx, result) in &mut results {
+                let factor = boost.factor(&result.metadata);
+                result.score *= factor;
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.boosted(*idx, factor, boost.matching(&result.metadata), result.score);
+                }
+            }
+        }
+
+        if let Some(feedback) = &feedback {
+            self.apply_feedback(feedback, opts, &mut results, trace.as_deref_mut());
+        }
+
+        // Break score ties by position so pages are stable across calls
+        results.sort_by(|a, b| {
+            b.1.score
+                .partial_cmp(&a.1.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.0.cmp(&b.0))
+        });
+
+        if let Some(field) = &opts.group_by {
+            results = collapse_by(results, |(_, r)| r.metadata.get(field).map(|v| v.to_string()));
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.dropped(&results, Exclusion::Collapsed);
+            }
+        }
+
+        if let Some(lambda) = opts.mmr_lambda {
+            results = self.mmr_rerank(query_embedding, results, lambda, wanted);
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.dropped(&results, Exclusion::Diversified);
+            }
+        }
+
+        results.truncate(wanted);
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.dropped(&results, Exclusion::BelowPage);
+        }
+        let page: Vec<(usize, SearchResult)> = results.into_iter().skip(opts.offset).collect();
+        if let Some(trace) = trace {
+            trace.dropped(&page, Exclusion::BeforePage);
+        }
+        let positions = page.iter().map(|(idx, _)| *idx).collect();
+        Ok((page.into_iter().map(|(_, r)| r).collect(), positions))
+    }
+
+    /// Scale the candidates' scores by relevance feedback, drop those marked
+    /// bad for the query, and add those marked good that the search missed
+    fn apply_feedback(
+        &self,
+        feedback: &QueryFeedback,
+        opts: &SearchOptions,
+        results: &mut Vec<(usize, SearchResult)>,
+        mut trace: Option<&mut SearchTrace>,
+    ) {
+        // Pinned passages score as if they were the best candidate
+        let top_score = results.iter().map(|(_, r)| r.score).reduce(f32::max).unwrap_or(1.0);
+        for id in feedback.pinned() {
+            if results.iter().any(|(_, r)| &r.id == id) || !self.passages.contains(id) {
+                continue;
+            }
+            let Some(idx) = self.id_map.iter().rposition(|i| i == id) else {
+                continue;
+            };
+            let Ok(passage) = self.passages.get(id) else {
+                continue;
+            };
+            if opts.filter.as_ref().is_some_and(|filter| !filter.matches(&passage.metadata)) {
+                continue;
+            }
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.ranked(&[(idx, top_score)], |_| id.clone());
+                trace.keep(idx, &passage.metadata);
+            }
+            let result = SearchResult { id: id.clone(), score: top_score, text: passage.text, metadata: passage.metadata };
+            results.push((idx, result));
+        }
+
+        results.retain(|(idx, result)| {
+            let blocked = feedback.is_blocked(&result.id);
+            if let Some(trace) = trace.as_deref_mut().filter(|_| blocked) {
+                trace.exclude(*idx, Exclusion::Feedback);
+            }
+            !blocked
+        });
+        for (idx, result) in results.iter_mut() {
+            let factor = feedback.factor(&result.id);
+            if factor == 1.0 {
+                continue;
+            }
+            let score = if feedback.pinned().contains(&result.id) { top_score } else { result.score };
+            // Scaled by magnitude, so good passages move up even from negative similarities
+            result.score = score + score.abs() * (factor - 1.0);
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.feedback(*idx, factor, result.score);
+            }
+        }
     }
 
-    /// Get all passage texts for BM25
-    fn get_all_texts(&self) -> anyhow::Result<Vec<String>> {
+    /// Re-rank candidates by maximal marginal relevance using their stored vectors
+    ///
+    /// Candidates are returned unchanged if any vector is unavailable (e.g. a
+    /// backend that can't return vectors and no embeddings file).
+    fn mmr_rerank(
+        &self,
+        query_embedding: &[f32],
+        results: Vec<(usize, SearchResult)>,
+        lambda: f32,
+        top_k: usize,
+    ) -> Vec<(usize, SearchResult)> {
+        let vectors: Option<Vec<Vec<f32>>> = results
+            .iter()
+            .map(|(idx, _)| self.stored_vector(*idx))
+            .collect();
+
+        let Some(vectors) = vectors else {
+            tracing::warn!("Stored vectors unavailable for this backend, skipping MMR re-ranking");
+            return results;
+        };
+
+        let order = mmr(query_embedding, &vectors, lambda, top_k);
+        let mut slots: Vec<Option<(usize, SearchResult)>> = results.into_iter().map(Some).collect();
+        order.into_iter().filter_map(|i| slots[i].take()).collect()
+    }
+
+    /// Offsets of passages matching a filter and the loaded scope
+    ///
+    /// Uses the persisted field index when it can answer the filter, and
+    /// otherwise scans passage metadata.
+    fn allowed_ids(&self, filter: Option<&MetadataFilter>) -> anyhow::Result<IdBitset> {
+        let indexed = match (filter, &self.field_index) {
+            (Some(filter), Some(field_index)) => field_index.allowed(filter),
+            (None, _) => Some(IdBitset::full(self.id_map.len())),
+            _ => None,
+        };
+
+        let mut allowed = match indexed {
+            Some(allowed) => allowed,
+            None => {
+                tracing::debug!("Filter not covered by field index, scanning passage metadata");
+                let offsets: std::collections::HashMap<&str, usize> = self
+                    .id_map
+                    .iter()
+                    .enumerate()
+                    .map(|(i, id)| (id.as_str(), i))
+                    .collect();
+
+                let mut allowed = IdBitset::new(self.id_map.len());
+                for passage in self.passages.iter()? {
+                    let passage = passage?;
+                    if filter.map_or(true, |f| f.matches(&passage.metadata)) {
+                        if let Some(&offset) = offsets.get(passage.id.as_str()) {
+                            allowed.insert(offset);
+                        }
+                    }
+                }
+                // Passages outside the scope are never iterated, so the set is already scoped
+                return Ok(allowed);
+            }
+        };
+
+        if self.scope.is_some() || self.has_missing_passages() {
+            let out_of_scope: Vec<usize> = allowed
+                .iter()
+                .filter(|&i| !self.passages.contains(&self.id_map[i]))
+                .collect();
+            for offset in out_of_scope {
+                allowed.remove(offset);
+            }
+        }
+
+        Ok(allowed)
+    }
+
+    /// Stored vector for a graph node, from the embeddings file or the backend
+    fn stored_vector(&self, idx: usize) -> Option<Vec<f32>> {
+        match &self.embeddings {
+            Some(store) => store.get(idx).map(|v| v.to_vec()),
+            None => self.backend.vector(idx as u64),
+        }
+    }
+
+    /// Live passages in the loaded scope with their stored vectors, in index order
+    ///
+    /// Errors on a passage whose vector is unavailable (e.g. a pruned index
+    /// whose backend can't return vectors).
+    pub fn passage_vectors(&self) -> impl Iterator<Item = anyhow::Result<(Passage, Vec<f32>)>> + '_ {
+        self.id_map
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| self.passages.contains(id))
+            .map(|(idx, id)| {
+                let vector = self
+                    .stored_vector(idx)
+                    .ok_or_else(|| anyhow::anyhow!("No stored vector for passage {}", id))?;
+                Ok((self.passages.get(id)?, vector))
+            })
+    }
+
+    /// Whether some vectors have no live passage (deleted, compacted away, or
+    /// superseded), so searches must check candidates against the store
+    fn has_missing_passages(&self) -> bool {
+        self.passages.len() < self.id_map.len()
+    }
+
+    /// BM25 scorer over all passage texts, built on first use and kept for
+    /// later searches
+    fn bm25_scorer(&self) -> anyhow::Result<&Bm25Scorer> {
+        if let Some(scorer) = self.bm25.get() {
+            return Ok(scorer);
+        }
+        let (all_texts, all_metadata) = self.get_all_texts()?;
+        Ok(self.bm25.get_or_init(|| Bm25Scorer::build(&all_texts, &all_metadata, &self.bm25_analyzer)))
+    }
+
+    /// The query with words missing from the index's vocabulary replaced by
+    /// the closest words in it, if any were (`serach` -> `search`)
+    ///
+    /// Words that `is_known` accepts are kept as typed.
+    pub fn correct_query(&self, query: &str, is_known: impl Fn(&str) -> bool) -> anyhow::Result<Option<String>> {
+        let speller = match self.speller.get() {
+            Some(speller) => speller,
+            None => {
+                let vocabulary = self.bm25_scorer()?.vocabulary();
+                self.speller.get_or_init(|| SpellCorrector::new(vocabulary))
+            }
+        };
+        Ok(speller.correct(query, is_known))
+    }
+
+    /// Get all passage texts for BM25, with their metadata when the analyzer
+    /// scores metadata fields
+    fn get_all_texts(&self) -> anyhow::Result<(Vec<String>, Vec<serde_json::Value>)> {
+        let with_metadata = !self.bm25_analyzer.fields.is_empty();
         let mut texts = Vec::with_capacity(self.id_map.len());
+        let mut metadata = Vec::new();
 
         for id in &self.id_map {
-            match self.passages.get(id) {
-                Ok(passage) => texts.push(passage.text),
-                Err(_) => texts.push(String::new()),
+            let passage = self.passages.get(id).ok();
+            if with_metadata {
+                metadata.push(passage.as_ref().map_or(serde_json::Value::Null, |p| p.metadata.clone()));
             }
+            texts.push(passage.map(|p| p.text).unwrap_or_default());
         }
 
-        Ok(texts)
+        Ok((texts, metadata))
     }
 
     /// BM25-only search for query expansion
     /// Returns passage texts of top matches
     pub fn bm25_search(&self, query: &str, top_k: usize) -> anyhow::Result<Vec<String>> {
-        let all_texts = self.get_all_texts()?;
-        let scorer = Bm25Scorer::build(&all_texts);
+        let scorer = self.bm25_scorer()?;
         let results = scorer.search(query, top_k);
 
         let texts: Vec<String> = results
@@ -245,13 +1106,111 @@ impl IndexSearcher {
         Ok(texts)
     }
 
+    /// Look up a passage by id
+    pub fn passage(&self, id: &str) -> anyhow::Result<Passage> {
+        self.passages.get(id)
+    }
+
     /// Get passage count
     pub fn len(&self) -> usize {
         self.backend.len()
     }
 
+    /// Get the number of passages visible within the loaded scope
+    pub fn scoped_len(&self) -> usize {
+        self.passages.len()
+    }
+
     /// Check if empty
     pub fn is_empty(&self) -> bool {
         self.backend.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate() {
+        let results: Vec<SearchResult> = [0.9, 0.8, 0.7, 0.3]
+            .iter()
+            .enumerate()
+            .map(|(i, score)| SearchResult {
+                id: i.to_string(),
+                score: *score,
+                text: String::new(),
+                metadata: serde_json::json!({}),
+            })
+            .collect();
+
+        let page = SearchOptions::new(2, 64).with_offset(1).with_min_score(0.5);
+        let ids: Vec<String> = page.paginate(results).into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_blend_embeddings() {
+        // Inputs are normalized before mixing, so magnitude doesn't matter
+        let blended = blend_embeddings(&[2.0, 0.0], &[0.0, 0.5], 0.5).unwrap();
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((blended[0] - half).abs() < 1e-6 && (blended[1] - half).abs() < 1e-6);
+
+        let query_only = blend_embeddings(&[3.0, 4.0], &[1.0, 0.0], 0.0).unwrap();
+        assert!((query_only[0] - 0.6).abs() < 1e-6 && (query_only[1] - 0.8).abs() < 1e-6);
+
+        assert!(blend_embeddings(&[1.0, 0.0], &[1.0], 0.5).is_err());
+        assert!(blend_embeddings(&[1.0], &[1.0], 1.5).is_err());
+
+        let opts = SearchOptions::new(5, 64);
+        assert_eq!(opts.query_vector(&[1.0, 2.0]).unwrap(), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let hit = |id: &str| SearchResult {
+            id: id.to_string(),
+            score: 0.9,
+            text: String::new(),
+            metadata: serde_json::json!({}),
+        };
+        let lists = vec![
+            vec![hit("a"), hit("b"), hit("c")],
+            vec![hit("c"), hit("b"), hit("d")],
+        ];
+
+        let fused = reciprocal_rank_fusion(lists.clone());
+        let ids: Vec<&str> = fused.iter().map(|r| r.id.as_str()).collect();
+        // c (1st and 3rd) edges out b (2nd twice); a and d appear once
+        assert_eq!(ids, vec!["c", "b", "a", "d"]);
+        assert!((fused[0].score - (1.0 / 61.0 + 1.0 / 63.0)).abs() < 1e-6);
+
+        let page = SearchOptions::new(2, 64).with_offset(1);
+        let ids: Vec<String> = page.fuse(lists).into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn test_aggregate_by_source() {
+        let hit = |source: &str, score: f32| SearchResult {
+            id: String::new(),
+            score,
+            text: String::new(),
+            metadata: serde_json::json!({ "source": source }),
+        };
+        let results = vec![
+            hit("a.rs", 0.9),
+            hit("b.rs", 0.8),
+            hit("b.rs", 0.7),
+            hit("a.rs", 0.1),
+            hit("c.rs", 0.5),
+        ];
+
+        let files = aggregate_by_source(&results);
+        let order: Vec<&str> = files.iter().map(|f| f.source.as_str()).collect();
+        // b.rs: 0.8 + 0.35 beats a.rs: 0.9 + 0.05
+        assert_eq!(order, vec!["b.rs", "a.rs", "c.rs"]);
+        assert_eq!(files[0].hits, 2);
+        assert_eq!(files[1].best_score, 0.9);
+    }
+}
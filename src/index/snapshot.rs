@@ -0,0 +1,178 @@
+//! Index snapshots - named copies of an index to restore later
+//!
+//! Snapshots of `.leann/indexes/<name>` live in `.leann/indexes/<name>.snapshots/<id>`,
+//! where the id is the UTC creation time plus an optional label
+//! (e.g. `20240131-120000-before-ast-chunking`).
+
+use std::path::{Path, PathBuf};
+
+use super::locate::{commit_staged_build, snapshots_dir, staging_dir};
+use super::IndexMeta;
+
+/// A snapshot of an index
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub id: String,
+    pub path: PathBuf,
+}
+
+impl Snapshot {
+    /// Metadata of the index as it was when snapshotted
+    pub fn meta(&self) -> anyhow::Result<IndexMeta> {
+        IndexMeta::load(&self.path.join("documents.leann.meta.json"))
+    }
+}
+
+/// Snapshot the index at `index_dir`
+///
+/// Files are copied, or hard-linked with `link` (which saves space, but
+/// `leann update` and `leann prune` rewrite files in place, changing linked
+/// snapshots too).
+pub fn create_snapshot(index_dir: &Path, label: Option<&str>, link: bool) -> anyhow::Result<Snapshot> {
+    if !index_dir.join("documents.leann.meta.json").exists() {
+        anyhow::bail!("{} is not a complete index", index_dir.display());
+    }
+
+    // "2024-01-31T12:00:00Z" -> "20240131-120000"
+    let stamp = crate::time::format_rfc3339(crate::time::now_unix());
+    let mut id = format!("{}-{}", stamp[..10].replace('-', ""), stamp[11..19].replace(':', ""));
+    if let Some(label) = label {
+        let label = sanitize_label(label);
+        if label.is_empty() {
+            anyhow::bail!("Snapshot name must contain letters, digits, '-', or '_'");
+        }
+        id = format!("{}-{}", id, label);
+    }
+
+    let root = snapshots_dir(index_dir);
+    let mut path = root.join(&id);
+    let mut n = 2;
+    while path.exists() {
+        path = root.join(format!("{}-{}", id, n));
+        n += 1;
+    }
+    let id = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+    if let Err(e) = copy_dir(index_dir, &path, link) {
+        let _ = std::fs::remove_dir_all(&path);
+        return Err(e);
+    }
+    Ok(Snapshot { id, path })
+}
+
+/// Snapshots of the index at `index_dir`, oldest first
+pub fn list_snapshots(index_dir: &Path) -> Vec<Snapshot> {
+    let Ok(entries) = std::fs::read_dir(snapshots_dir(index_dir)) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<Snapshot> = entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| Snapshot {
+            id: e.file_name().to_string_lossy().to_string(),
+            path: e.path(),
+        })
+        .collect();
+    snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+    snapshots
+}
+
+/// Find a snapshot by id, unique id prefix, or label
+pub fn find_snapshot(index_dir: &Path, id: &str) -> anyhow::Result<Snapshot> {
+    let snapshots = list_snapshots(index_dir);
+    if let Some(snapshot) = snapshots.iter().find(|s| s.id == id) {
+        return Ok(snapshot.clone());
+    }
+
+    let suffix = format!("-{}", id);
+    let mut matches: Vec<&Snapshot> = snapshots.iter().filter(|s| s.id.ends_with(&suffix)).collect();
+    if matches.is_empty() {
+        matches = snapshots.iter().filter(|s| s.id.starts_with(id)).collect();
+    }
+    match matches.as_slice() {
+        [snapshot] => Ok((*snapshot).clone()),
+        [] => anyhow::bail!(
+            "Snapshot '{}' not found. Run 'leann list --snapshots' to see available snapshots.",
+            id
+        ),
+        _ => anyhow::bail!(
+            "Snapshot '{}' is ambiguous: {}",
+            id,
+            matches.iter().map(|s| s.id.as_str()).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// Replace the index at `index_dir` with a copy of `snapshot`
+///
+/// The snapshot is kept, and the replaced build can be restored with `leann rollback`.
+pub fn restore_snapshot(index_dir: &Path, snapshot: &Snapshot) -> anyhow::Result<()> {
+    let staging = staging_dir(index_dir);
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    if let Err(e) = copy_dir(&snapshot.path, &staging, false) {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    commit_staged_build(index_dir)
+}
+
+/// Keep letters, digits, '-', and '_' of a snapshot label
+fn sanitize_label(label: &str) -> String {
+    label
+        .trim()
+        .chars()
+        .map(|c| if c.is_whitespace() { '-' } else { c })
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+/// Copy (or hard-link) a directory tree, falling back to copying where linking fails
+fn copy_dir(from: &Path, to: &Path, link: bool) -> anyhow::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target, link)?;
+        } else if !link || std::fs::hard_link(entry.path(), &target).is_err() {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let root = std::env::temp_dir().join(format!("leann-snapshot-{}", std::process::id()));
+        let index_dir = root.join("docs");
+        let meta_path = index_dir.join("documents.leann.meta.json");
+        std::fs::create_dir_all(&index_dir).unwrap();
+        std::fs::write(&meta_path, "v1").unwrap();
+
+        let first = create_snapshot(&index_dir, Some("before ast"), false).unwrap();
+        assert!(first.id.ends_with("-before-ast"));
+        assert_eq!(first.id.len(), "20240131-120000-before-ast".len());
+        let second = create_snapshot(&index_dir, Some("tuned"), true).unwrap();
+        assert_ne!(second.id, first.id);
+        assert_eq!(list_snapshots(&index_dir).len(), 2);
+
+        assert_eq!(find_snapshot(&index_dir, &first.id).unwrap().id, first.id);
+        assert_eq!(find_snapshot(&index_dir, "before-ast").unwrap().id, first.id);
+        assert_eq!(find_snapshot(&index_dir, "tuned").unwrap().id, second.id);
+        assert!(find_snapshot(&index_dir, "missing").is_err());
+
+        std::fs::remove_file(&meta_path).unwrap();
+        std::fs::write(&meta_path, "v2").unwrap();
+        restore_snapshot(&index_dir, &first).unwrap();
+        assert_eq!(std::fs::read_to_string(&meta_path).unwrap(), "v1");
+        assert!(first.path.exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+//! Inverted index over learned sparse vectors (SPLADE, BM42)
+//!
+//! Each vocabulary term maps to the passages with a non-zero weight for it;
+//! a query scores passages by the dot product of their sparse vectors, visiting
+//! only the postings of the query's terms. Written at build time as
+//! `documents.leann.sparse` in a compact little-endian binary format:
+//!
+//! ```text
+//! magic "LSPI" | version u32 | passages u32 | terms u32
+//! per term: term u32 | postings u32 | (passage u32, weight f32) * postings
+//! ```
+
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rustc_hash::FxHashMap;
+
+const MAGIC: &[u8; 4] = b"LSPI";
+const FORMAT_VERSION: u32 = 1;
+
+/// Term -> (passage offset, weight) postings
+#[derive(Debug, Default)]
+pub struct SparseIndex {
+    postings: FxHashMap<u32, Vec<(u32, f32)>>,
+    num_docs: usize,
+}
+
+impl SparseIndex {
+    fn path_for(index_path: &Path) -> PathBuf {
+        index_path.with_extension("sparse")
+    }
+
+    /// Load the sparse index next to an index, if it has one
+    pub fn load(index_path: &Path) -> anyhow::Result<Option<Self>> {
+        let path = Self::path_for(index_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = std::fs::read(&path)?;
+        Self::decode(&bytes)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("Invalid sparse index {:?}: {}", path, e))
+    }
+
+    /// Write the sparse index next to an index
+    pub fn save(&self, index_path: &Path) -> anyhow::Result<()> {
+        let file = std::fs::File::create(Self::path_for(index_path))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&self.encode())?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Number of passages indexed
+    pub fn len(&self) -> usize {
+        self.num_docs
+    }
+
+    /// Add the next passage's sparse vector (passages are added in ID-mapping order)
+    pub fn add(&mut self, vector: &[(u32, f32)]) {
+        let doc = self.num_docs as u32;
+        for &(term, weight) in vector {
+            if weight > 0.0 {
+                self.postings.entry(term).or_default().push((doc, weight));
+            }
+        }
+        self.num_docs += 1;
+    }
+
+    /// Dot-product score of every passage against a query vector
+    pub fn score(&self, query: &[(u32, f32)]) -> Vec<f32> {
+        let mut scores = vec![0.0f32; self.num_docs];
+        for (term, query_weight) in query {
+            if let Some(postings) = self.postings.get(term) {
+                for &(doc, weight) in postings {
+                    scores[doc as usize] += query_weight * weight;
+                }
+            }
+        }
+        scores
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut terms: Vec<&u32> = self.postings.keys().collect();
+        terms.sort();
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.num_docs as u32).to_le_bytes());
+        bytes.extend_from_slice(&(terms.len() as u32).to_le_bytes());
+        for term in terms {
+            let postings = &self.postings[term];
+            bytes.extend_from_slice(&term.to_le_bytes());
+            bytes.extend_from_slice(&(postings.len() as u32).to_le_bytes());
+            for (doc, weight) in postings {
+                bytes.extend_from_slice(&doc.to_le_bytes());
+                bytes.extend_from_slice(&weight.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.get(..4) != Some(&MAGIC[..]) {
+            anyhow::bail!("not a sparse index file");
+        }
+        let mut reader = Reader { bytes, pos: 4 };
+        let version = reader.u32()?;
+        if version != FORMAT_VERSION {
+            anyhow::bail!("unsupported format version {}", version);
+        }
+
+        let num_docs = reader.u32()? as usize;
+        let num_terms = reader.u32()? as usize;
+        let mut postings = FxHashMap::default();
+        for _ in 0..num_terms {
+            let term = reader.u32()?;
+            let count = reader.u32()? as usize;
+            let mut list = Vec::with_capacity(count.min(num_docs));
+            for _ in 0..count {
+                let doc = reader.u32()?;
+                if doc as usize >= num_docs {
+                    anyhow::bail!("passage offset {} out of range", doc);
+                }
+                list.push((doc, f32::from_bits(reader.u32()?)));
+            }
+            postings.insert(term, list);
+        }
+        Ok(Self { postings, num_docs })
+    }
+}
+
+/// Little-endian cursor over the encoded index
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        let chunk = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| anyhow::anyhow!("truncated file"))?;
+        self.pos += 4;
+        Ok(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_and_roundtrip() {
+        let mut index = SparseIndex::default();
+        index.add(&[(1, 0.5), (7, 2.0)]);
+        index.add(&[(7, 1.0)]);
+        index.add(&[]);
+
+        let query = vec![(7, 1.0), (9, 3.0)];
+        assert_eq!(index.score(&query), vec![2.0, 1.0, 0.0]);
+
+        let decoded = SparseIndex::decode(&index.encode()).unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded.score(&query), vec![2.0, 1.0, 0.0]);
+
+        assert!(SparseIndex::decode(b"LSPI\x01\x00").is_err());
+        assert!(SparseIndex::decode(b"nope").is_err());
+    }
+}
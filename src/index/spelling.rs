@@ -0,0 +1,232 @@
+//! Spelling correction - fixes typos in queries against the index's vocabulary
+//!
+//! A SymSpell-style corrector over the words counted while building the BM25
+//! scorer. Each vocabulary word is filed under every string left by deleting
+//! up to `MAX_EDIT_DISTANCE` characters from its first `PREFIX_LENGTH`
+//! characters. A query word missing from the vocabulary looks up its own
+//! deletes; the words found there are the candidates, and the closest by
+//! Damerau-Levenshtein distance wins, ties going to the more frequent word.
+//! Only lookups cost distance computations, so correction stays cheap however
+//! large the vocabulary is.
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use rustc_hash::FxHashMap;
+
+/// Most edits between a query word and its correction
+const MAX_EDIT_DISTANCE: usize = 2;
+
+/// Characters of a word the delete table covers
+const PREFIX_LENGTH: usize = 7;
+
+/// Query words shorter than this are left alone (too many close words)
+const MIN_WORD_LENGTH: usize = 4;
+
+/// Query words up to this long are corrected by one edit at most (two
+/// edits turn most short words into other words)
+const ONE_EDIT_MAX_LENGTH: usize = 8;
+
+/// Words as the BM25 tokenizer sees them
+static WORD_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\p{L}\p{N}]+").unwrap());
+
+/// Corrector over one index's vocabulary
+#[derive(Debug)]
+pub(crate) struct SpellCorrector {
+    /// Vocabulary words and their occurrence counts
+    words: Vec<(String, usize)>,
+    /// Indexes into `words` by word
+    lookup: FxHashMap<String, u32>,
+    /// Indexes into `words` by the deletes of their prefixes
+    deletes: FxHashMap<String, Vec<u32>>,
+}
+
+impl SpellCorrector {
+    /// Build a corrector from word occurrence counts
+    pub fn new(vocabulary: &FxHashMap<String, usize>) -> Self {
+        let mut words: Vec<(String, usize)> = vocabulary
+            .iter()
+            .filter(|(word, _)| word.chars().all(char::is_alphabetic))
+            .map(|(word, count)| (word.clone(), *count))
+            .collect();
+        words.sort_unstable();
+
+        let mut lookup = FxHashMap::default();
+        let mut deletes: FxHashMap<String, Vec<u32>> = FxHashMap::default();
+        for (i, (word, _)) in words.iter().enumerate() {
+            lookup.insert(word.clone(), i as u32);
+            for delete in prefix_deletes(word, MAX_EDIT_DISTANCE) {
+                deletes.entry(delete).or_default().push(i as u32);
+            }
+        }
+        Self { words, lookup, deletes }
+    }
+
+    /// The query with misspelled words replaced, if any were
+    ///
+    /// Words that `is_known` accepts (e.g. synonym terms) are kept as typed.
+    pub fn correct(&self, query: &str, is_known: impl Fn(&str) -> bool) -> Option<String> {
+        let mut corrected = String::with_capacity(query.len());
+        let mut last = 0;
+        for word in WORD_REGEX.find_iter(query) {
+            let lower = word.as_str().to_lowercase();
+            let Some(replacement) = self.correct_word(&lower).filter(|_| !is_known(&lower)) else {
+                continue;
+            };
+            corrected.push_str(&query[last..word.start()]);
+            corrected.push_str(&match_case(word.as_str(), replacement));
+            last = word.end();
+        }
+        if last == 0 {
+            return None;
+        }
+        corrected.push_str(&query[last..]);
+        Some(corrected)
+    }
+
+    /// The closest vocabulary word to a lowercased word missing from the vocabulary
+    fn correct_word(&self, word: &str) -> Option<&str> {
+        let length = word.chars().count();
+        if length < MIN_WORD_LENGTH || !word.chars().all(char::is_alphabetic) || self.lookup.contains_key(word) {
+            return None;
+        }
+        let max_distance = if length <= ONE_EDIT_MAX_LENGTH { 1 } else { MAX_EDIT_DISTANCE };
+
+        let mut seen = HashSet::new();
+        let mut best: Option<(usize, usize, &str)> = None;
+        for delete in prefix_deletes(word, max_distance) {
+            for &i in self.deletes.get(&delete).into_iter().flatten() {
+                if !seen.insert(i) {
+                    continue;
+                }
+                let (candidate, count) = &self.words[i as usize];
+                if candidate.chars().count().abs_diff(length) > max_distance {
+                    continue;
+                }
+                let distance = edit_distance(word, candidate);
+                if distance > max_distance {
+                    continue;
+                }
+                // Closest, then most frequent, then first alphabetically
+                let better = best.is_none_or(|(d, c, w)| {
+                    (distance, std::cmp::Reverse(*count), candidate.as_str()) < (d, std::cmp::Reverse(c), w)
+                });
+                if better {
+                    best = Some((distance, *count, candidate));
+                }
+            }
+        }
+        best.map(|(_, _, word)| word)
+    }
+}
+
+/// The word's prefix and every string left by deleting up to `max_distance`
+/// characters from it
+fn prefix_deletes(word: &str, max_distance: usize) -> HashSet<String> {
+    let prefix: Vec<char> = word.chars().take(PREFIX_LENGTH).collect();
+    let mut deletes = HashSet::from([prefix.iter().collect::<String>()]);
+    let mut frontier = vec![prefix];
+    for _ in 0..max_distance {
+        let mut next = Vec::new();
+        for chars in &frontier {
+            if chars.len() <= 1 {
+                continue;
+            }
+            for i in 0..chars.len() {
+                let mut shorter = chars.clone();
+                shorter.remove(i);
+                if deletes.insert(shorter.iter().collect()) {
+                    next.push(shorter);
+                }
+            }
+        }
+        frontier = next;
+    }
+    deletes
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions,
+/// and transpositions of adjacent characters each count one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// A lowercase correction capitalized like the word it replaces
+fn match_case(typed: &str, correction: &str) -> String {
+    if typed.chars().all(char::is_uppercase) && typed.chars().count() > 1 {
+        correction.to_uppercase()
+    } else if typed.chars().next().is_some_and(char::is_uppercase) {
+        let mut chars = correction.chars();
+        chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+    } else {
+        correction.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corrector(words: &[(&str, usize)]) -> SpellCorrector {
+        SpellCorrector::new(&words.iter().map(|(w, c)| (w.to_string(), *c)).collect())
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("serach", "search"), 1);
+        assert_eq!(edit_distance("embeding", "embedding"), 1);
+        assert_eq!(edit_distance("retreival", "retrieval"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_correct() {
+        let speller = corrector(&[
+            ("search", 40),
+            ("searches", 5),
+            ("embedding", 30),
+            ("retrieval", 12),
+            ("index", 50),
+            ("indexes", 8),
+            ("ranking", 3),
+            ("banking", 1),
+            ("v2", 4),
+        ]);
+        assert_eq!(speller.correct("serach the indx", |_| false).as_deref(), Some("search the index"));
+        assert_eq!(speller.correct("Embeding RETREIVAL", |_| false).as_deref(), Some("Embedding RETRIEVAL"));
+        // The more frequent of equally close words
+        assert_eq!(speller.correct("ganking", |_| false).as_deref(), Some("ranking"));
+        // Known, short, too distant, and non-alphabetic words stay
+        assert_eq!(speller.correct("search indexes", |_| false), None);
+        assert_eq!(speller.correct("serach", |w| w == "serach"), None);
+        assert_eq!(speller.correct("sarch v3 zzzzzzz", |_| false).as_deref(), Some("search v3 zzzzzzz"));
+        assert_eq!(speller.correct("idx", |_| false), None);
+        // Words up to 8 characters get one edit at most
+        assert_eq!(speller.correct("indx ndx", |_| false).as_deref(), Some("index ndx"));
+        assert_eq!(speller.correct("inxe", |_| false), None);
+        assert_eq!(speller.correct("sankng", |_| false), None);
+        assert_eq!(speller.correct("retreivl", |_| false), None);
+        assert_eq!(speller.correct("retreivals", |_| false).as_deref(), Some("retrieval"));
+    }
+}
@@ -0,0 +1,149 @@
+//! Synonym dictionary - domain aliases added to queries before search
+//!
+//! `<name>.synonyms.toml` next to an index directory (so it survives rebuilds,
+//! like the query log) maps terms to what they stand for:
+//!
+//! ```toml
+//! k8s = "kubernetes"
+//! auth = "authentication, login"
+//! "ci/cd" = ["continuous integration", "deployment pipeline"]
+//! ```
+//!
+//! A query containing a term, case-insensitively and as whole words, gets the
+//! term's expansions appended, so abbreviations also retrieve passages that
+//! spell them out. Lookup is one-way: `kubernetes` doesn't add `k8s`.
+
+use std::path::{Path, PathBuf};
+
+/// Terms and their expansions
+#[derive(Debug, Clone, Default)]
+pub struct Synonyms {
+    /// Lowercased term and its expansions, in file order
+    entries: Vec<(String, Vec<String>)>,
+}
+
+/// Synonym file of the index at `index_dir`
+pub fn synonyms_path(index_dir: &Path) -> PathBuf {
+    let mut name = index_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(".synonyms.toml");
+    index_dir.with_file_name(name)
+}
+
+impl Synonyms {
+    /// The synonyms for the index at `index_dir`, if it has a non-empty synonym file
+    pub fn load(index_dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = synonyms_path(index_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let synonyms = Self::parse(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid synonym file {}: {}", path.display(), e))?;
+        Ok(Some(synonyms).filter(|s| !s.is_empty()))
+    }
+
+    /// Parse `term = "expansion, ..."` or `term = ["expansion", ...]` lines
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let table: toml::Table = content.parse()?;
+        let entries = table
+            .into_iter()
+            .map(|(term, value)| {
+                let expansions: Vec<String> = match value {
+                    toml::Value::String(s) => s.split(',').map(|e| e.trim().to_string()).collect(),
+                    toml::Value::Array(items) => items
+                        .into_iter()
+                        .map(|item| match item {
+                            toml::Value::String(s) => Ok(s.trim().to_string()),
+                            other => Err(anyhow::anyhow!("'{}' has a non-string expansion: {}", term, other)),
+                        })
+                        .collect::<anyhow::Result<_>>()?,
+                    other => anyhow::bail!("'{}' must map to a string or a list of strings, not {}", term, other),
+                };
+                let expansions = expansions.into_iter().filter(|e| !e.is_empty()).collect();
+                Ok((term.trim().to_lowercase(), expansions))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { entries })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether a lowercased word is one of the terms, or a word of one
+    pub fn defines(&self, word: &str) -> bool {
+        self.entries.iter().any(|(term, _)| contains_words(term, word))
+    }
+
+    /// The query with the expansions of the terms it contains appended
+    pub fn expand(&self, query: &str) -> String {
+        let mut expanded = query.to_string();
+        for (term, expansions) in &self.entries {
+            if !contains_words(&query.to_lowercase(), term) {
+                continue;
+            }
+            for expansion in expansions {
+                if !contains_words(&expanded.to_lowercase(), &expansion.to_lowercase()) {
+                    expanded.push(' ');
+                    expanded.push_str(expansion);
+                }
+            }
+        }
+        expanded
+    }
+}
+
+/// Whether `phrase` occurs in `text` with no letters or digits adjoining it
+fn contains_words(text: &str, phrase: &str) -> bool {
+    if phrase.is_empty() {
+        return false;
+    }
+    text.match_indices(phrase).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let synonyms = Synonyms::parse(
+            "k8s = \"kubernetes\"\nauth = \"authentication, login\"\n\"ci/cd\" = [\"continuous integration\", \"pipeline\"]\n",
+        )
+        .unwrap();
+        assert_eq!(synonyms.expand("K8s pod limits"), "K8s pod limits kubernetes");
+        assert_eq!(synonyms.expand("auth flow"), "auth flow authentication login");
+        // Expansions already in the query aren't repeated
+        assert_eq!(synonyms.expand("auth login page"), "auth login page authentication");
+        assert_eq!(synonyms.expand("our ci/cd setup"), "our ci/cd setup continuous integration pipeline");
+        // Only whole words match
+        assert_eq!(synonyms.expand("author notes"), "author notes");
+        assert_eq!(synonyms.expand("kubernetes"), "kubernetes");
+        assert!(synonyms.defines("k8s") && synonyms.defines("cd"));
+        assert!(!synonyms.defines("kubernetes"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Synonyms::parse("k8s = 8").is_err());
+        assert!(Synonyms::parse("k8s = [\"kubernetes\", 8]").is_err());
+        assert!(Synonyms::parse("k8s kubernetes").is_err());
+        assert!(Synonyms::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_synonyms_path() {
+        let dir = std::env::temp_dir().join(format!("leann-synonyms-{}", std::process::id()));
+        let index_dir = dir.join("docs");
+        std::fs::create_dir_all(&index_dir).unwrap();
+        assert_eq!(synonyms_path(&index_dir), dir.join("docs.synonyms.toml"));
+        assert!(Synonyms::load(&index_dir).unwrap().is_none());
+        std::fs::write(synonyms_path(&index_dir), "db = \"database\"\n").unwrap();
+        assert_eq!(Synonyms::load(&index_dir).unwrap().unwrap().expand("db"), "db database");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
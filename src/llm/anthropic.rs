@@ -6,6 +6,7 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
+use super::{ChatMessage, GenerationOptions, Tool, ToolCall, ToolChoice, ToolResponse, DEFAULT_MAX_TOKENS};
 use crate::http::{check_response, create_client};
 
 /// Anthropic LLM provider
@@ -27,6 +28,14 @@ struct AnthropicRequest {
     model: String,
     max_tokens: u32,
     messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
 }
 
 #[derive(Deserialize)]
@@ -34,6 +43,10 @@ struct ContentBlock {
     #[serde(rename = "type")]
     content_type: String,
     text: Option<String>,
+    /// Set on `tool_use` blocks
+    id: Option<String>,
+    name: Option<String>,
+    input: Option<serde_json::Value>,
 }
 
 #[derive(Deserialize)]
@@ -48,13 +61,14 @@ impl AnthropicLlm {
         api_key: Option<String>,
         base_url: Option<String>,
     ) -> anyhow::Result<Self> {
-        let api_key = api_key
-            .or_else(|| env::var("ANTHROPIC_API_KEY").ok())
-            .ok_or_else(|| anyhow::anyhow!("ANTHROPIC_API_KEY not set"))?;
-
         let base_url = base_url
             .or_else(|| env::var("ANTHROPIC_BASE_URL").ok())
             .unwrap_or_else(|| "https://api.anthropic.com".to_string());
+        crate::http::check_network("Anthropic LLM", &base_url)?;
+
+        let api_key = api_key
+            .or_else(|| env::var("ANTHROPIC_API_KEY").ok())
+            .ok_or_else(|| anyhow::anyhow!("ANTHROPIC_API_KEY not set"))?;
 
         let client = create_client();
 
@@ -69,42 +83,179 @@ impl AnthropicLlm {
     }
 
     /// Generate a response
-    pub async fn generate(&self, prompt: &str) -> anyhow::Result<String> {
+    pub async fn generate(&self, prompt: &str, options: &GenerationOptions) -> anyhow::Result<String> {
         let request = AnthropicRequest {
             model: self.model_name.clone(),
-            max_tokens: 1000,
+            max_tokens: options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
             messages: vec![Message {
                 role: "user".to_string(),
                 content: prompt.to_string(),
             }],
+            system: options.system.clone(),
+            temperature: options.temperature,
+            top_p: options.top_p,
+            stop_sequences: options.stop.clone(),
         };
 
+        let anthropic_response = self.send(&request).await?;
+        Ok(text_of(&anthropic_response))
+    }
+
+    /// Continue a conversation with tools (`tool_use` / `tool_result` blocks)
+    pub async fn generate_with_tools(
+        &self,
+        conversation: &[ChatMessage],
+        tools: &[Tool],
+        choice: ToolChoice,
+        options: &GenerationOptions,
+    ) -> anyhow::Result<ToolResponse> {
+        let mut request = serde_json::json!({
+            "model": self.model_name,
+            "max_tokens": options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "messages": tool_messages(conversation),
+        });
+        if !tools.is_empty() {
+            request["tools"] = tools
+                .iter()
+                .map(|t| serde_json::json!({ "name": t.name, "description": t.description, "input_schema": t.parameters }))
+                .collect();
+            match choice {
+                ToolChoice::Auto => {}
+                ToolChoice::None => request["tool_choice"] = serde_json::json!({ "type": "none" }),
+                ToolChoice::Required => request["tool_choice"] = serde_json::json!({ "type": "any" }),
+            }
+        }
+        if let Some(system) = &options.system {
+            request["system"] = system.as_str().into();
+        }
+        if let Some(temperature) = options.temperature {
+            request["temperature"] = temperature.into();
+        }
+        if let Some(top_p) = options.top_p {
+            request["top_p"] = top_p.into();
+        }
+        if !options.stop.is_empty() {
+            request["stop_sequences"] = options.stop.clone().into();
+        }
+
+        let anthropic_response = self.send(&request).await?;
+        Ok(tool_response(anthropic_response))
+    }
+
+    async fn send(&self, request: &impl Serialize) -> anyhow::Result<AnthropicResponse> {
         let response = self
             .client
             .post(format!("{}/v1/messages", self.base_url))
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
-            .json(&request)
+            .json(request)
             .send()
             .await?;
 
         let response = check_response(response, "Anthropic").await?;
-        let anthropic_response: AnthropicResponse = response.json().await?;
-
-        let content = anthropic_response
-            .content
-            .iter()
-            .filter_map(|block| {
-                if block.content_type == "text" {
-                    block.text.clone()
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("");
-
-        Ok(content)
+        Ok(response.json().await?)
+    }
+}
+
+/// The text blocks of a response
+fn text_of(response: &AnthropicResponse) -> String {
+    response
+        .content
+        .iter()
+        .filter_map(|block| {
+            if block.content_type == "text" {
+                block.text.clone()
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// A conversation as Anthropic messages; tool results go in user messages,
+/// and consecutive messages of one role are merged
+fn tool_messages(conversation: &[ChatMessage]) -> Vec<serde_json::Value> {
+    let mut messages: Vec<serde_json::Value> = Vec::new();
+    for message in conversation {
+        let (role, blocks) = match message {
+            ChatMessage::User(text) => ("user", vec![serde_json::json!({ "type": "text", "text": text })]),
+            ChatMessage::Assistant { text, calls } => {
+                let text = (!text.is_empty()).then(|| serde_json::json!({ "type": "text", "text": text }));
+                let uses = calls.iter().map(|call| {
+                    serde_json::json!({ "type": "tool_use", "id": call.id, "name": call.name, "input": call.arguments })
+                });
+                ("assistant", text.into_iter().chain(uses).collect())
+            }
+            ChatMessage::ToolResult { call_id, content, .. } => (
+                "user",
+                vec![serde_json::json!({ "type": "tool_result", "tool_use_id": call_id, "content": content })],
+            ),
+        };
+        match messages.last_mut() {
+            Some(last) if last["role"] == role => {
+                last["content"].as_array_mut().expect("content is a block list").extend(blocks);
+            }
+            _ => messages.push(serde_json::json!({ "role": role, "content": blocks })),
+        }
+    }
+    messages
+}
+
+fn tool_response(response: AnthropicResponse) -> ToolResponse {
+    let text = text_of(&response);
+    let calls = response
+        .content
+        .into_iter()
+        .filter(|block| block.content_type == "tool_use")
+        .map(|block| ToolCall {
+            id: block.id.unwrap_or_default(),
+            name: block.name.unwrap_or_default(),
+            arguments: block.input.unwrap_or_default(),
+        })
+        .collect();
+    ToolResponse { text, calls }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_messages() {
+        let call = ToolCall { id: "toolu_1".to_string(), name: "search".to_string(), arguments: serde_json::json!({ "query": "q" }) };
+        let conversation = [
+            ChatMessage::User("Question".to_string()),
+            ChatMessage::Assistant { text: String::new(), calls: vec![call] },
+            ChatMessage::ToolResult { call_id: "toolu_1".to_string(), name: "search".to_string(), content: "[1] text".to_string() },
+            ChatMessage::User("Reflect".to_string()),
+        ];
+        assert_eq!(
+            serde_json::Value::Array(tool_messages(&conversation)),
+            serde_json::json!([
+                { "role": "user", "content": [{ "type": "text", "text": "Question" }] },
+                { "role": "assistant", "content": [{ "type": "tool_use", "id": "toolu_1", "name": "search", "input": { "query": "q" } }] },
+                { "role": "user", "content": [
+                    { "type": "tool_result", "tool_use_id": "toolu_1", "content": "[1] text" },
+                    { "type": "text", "text": "Reflect" },
+                ] },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tool_response() {
+        let response: AnthropicResponse = serde_json::from_value(serde_json::json!({
+            "content": [
+                { "type": "text", "text": "Let me look." },
+                { "type": "tool_use", "id": "toolu_2", "name": "search", "input": { "query": "billing" } },
+            ]
+        }))
+        .unwrap();
+        let response = tool_response(response);
+        assert_eq!(response.text, "Let me look.");
+        assert_eq!(response.calls[0].id, "toolu_2");
+        assert_eq!(response.calls[0].arguments["query"], "billing");
     }
 }
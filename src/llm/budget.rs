@@ -0,0 +1,206 @@
+//! Token budgets for RAG context - fit retrieved passages into the model's window
+
+/// Context window of Ollama's default `num_ctx` (longer prompts are truncated by the server)
+const OLLAMA_CONTEXT_TOKENS: usize = 4096;
+
+/// Context window used by the local GGUF provider
+const LOCAL_CONTEXT_TOKENS: usize = 4096;
+
+/// Don't bother including a truncated passage shorter than this
+const MIN_PASSAGE_TOKENS: usize = 32;
+
+/// Marker appended to a passage cut to fit the budget
+const TRUNCATION_MARKER: &str = " ...";
+
+/// Context window in tokens for a provider and model, if known
+pub fn context_window(provider: &str, model_name: &str) -> Option<usize> {
+    match provider {
+        "ollama" => return Some(OLLAMA_CONTEXT_TOKENS),
+        "local" => return Some(LOCAL_CONTEXT_TOKENS),
+        "simulated" => return None,
+        _ => {}
+    }
+
+    let model = model_name.to_lowercase();
+    let model = model.as_str();
+    match model {
+        m if m.starts_with("gpt-4o") || m.starts_with("gpt-4.1") || m.starts_with("gpt-4-turbo") => Some(128_000),
+        m if m.starts_with("o1") || m.starts_with("o3") || m.starts_with("o4") => Some(128_000),
+        m if m.starts_with("gpt-4") => Some(8_192),
+        m if m.starts_with("gpt-3.5-turbo") => Some(16_385),
+        m if m.starts_with("claude") => Some(200_000),
+        m if m.starts_with("gemini-1.5-pro") => Some(2_000_000),
+        m if m.starts_with("gemini") => Some(1_000_000),
+        _ => None,
+    }
+}
+
+/// Approximate characters per token for a model's tokenizer
+///
+/// Hosted frontier models use large vocabularies (~4 characters per token);
+/// open models tend to split text into more tokens, so they are counted
+/// conservatively.
+fn chars_per_token(model_name: &str) -> f64 {
+    let model = model_name.to_lowercase();
+    let large_vocab = ["gpt-", "o1", "o3", "o4", "claude", "gemini"];
+    if large_vocab.iter().any(|prefix| model.starts_with(prefix)) {
+        4.0
+    } else {
+        3.5
+    }
+}
+
+/// Estimated token count of `text` for a model
+pub fn count_tokens(model_name: &str, text: &str) -> usize {
+    (text.chars().count() as f64 / chars_per_token(model_name)).ceil() as usize
+}
+
+/// Retrieved passages packed into a token budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackedContext {
+    /// Numbered passages (`[1] ...`) separated by blank lines
+    pub text: String,
+    /// Passages included (the last may be truncated)
+    pub included: usize,
+    /// Passages available before packing
+    pub available: usize,
+    /// Whether the last included passage was truncated
+    pub truncated: bool,
+    /// Estimated tokens used
+    pub tokens: usize,
+}
+
+impl PackedContext {
+    /// One-line summary, e.g. "3 of 5 passages (~1800 tokens, last truncated)"
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} of {} passages (~{} tokens",
+            self.included, self.available, self.tokens
+        );
+        if self.truncated {
+            summary.push_str(", last truncated");
+        }
+        summary.push(')');
+        summary
+    }
+}
+
+/// Pack ranked passages into at most `budget` tokens
+///
+/// Passages are taken in rank order. The first one that doesn't fit is
+/// truncated to the remaining budget (if a useful amount is left) and the
+/// rest are dropped. With no budget every passage is included.
+pub fn pack_context(passages: &[&str], budget: Option<usize>, model_name: &str) -> PackedContext {
+    let mut entries: Vec<String> = Vec::new();
+    let mut tokens = 0;
+    let mut truncated = false;
+
+    for (i, passage) in passages.iter().enumerate() {
+        let entry = format!("[{}] {}", i + 1, passage);
+        let separator = if entries.is_empty() { 0 } else { count_tokens(model_name, "\n\n") };
+        let cost = separator + count_tokens(model_name, &entry);
+
+        let Some(budget) = budget else {
+            tokens += cost;
+            entries.push(entry);
+            continue;
+        };
+
+        if tokens + cost <= budget {
+            tokens += cost;
+            entries.push(entry);
+            continue;
+        }
+
+        let remaining = budget.saturating_sub(tokens + separator);
+        if remaining > 0 && (remaining >= MIN_PASSAGE_TOKENS || entries.is_empty()) {
+            let max_chars = (remaining as f64 * chars_per_token(model_name)) as usize;
+            let max_chars = max_chars.saturating_sub(TRUNCATION_MARKER.len());
+            let cut = truncate_chars(&entry, max_chars);
+            if !cut.is_empty() {
+                let entry = format!("{}{}", cut, TRUNCATION_MARKER);
+                tokens += separator + count_tokens(model_name, &entry);
+                entries.push(entry);
+                truncated = true;
+            }
+        }
+        break;
+    }
+
+    PackedContext {
+        included: entries.len(),
+        text: entries.join("\n\n"),
+        available: passages.len(),
+        truncated,
+        tokens,
+    }
+}
+
+/// Longest prefix of at most `max_chars` characters, cut at whitespace when possible
+fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    let end = match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => idx,
+        None => return text,
+    };
+
+    let cut = &text[..end];
+    match cut.rfind(char::is_whitespace) {
+        Some(ws) if ws >= end / 2 => cut[..ws].trim_end(),
+        _ => cut,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("openai", "gpt-4o-mini"), Some(128_000));
+        assert_eq!(context_window("anthropic", "claude-3-5-sonnet-20241022"), Some(200_000));
+        assert_eq!(context_window("ollama", "qwen3:8b"), Some(OLLAMA_CONTEXT_TOKENS));
+        assert_eq!(context_window("openai", "my-finetune"), None);
+        assert_eq!(context_window("simulated", "gpt-4o"), None);
+    }
+
+    #[test]
+    fn test_pack_without_budget() {
+        let packed = pack_context(&["alpha", "beta"], None, "gpt-4o");
+        assert_eq!(packed.text, "[1] alpha\n\n[2] beta");
+        assert_eq!(packed.included, 2);
+        assert!(!packed.truncated);
+    }
+
+    #[test]
+    fn test_pack_drops_and_truncates() {
+        let short = "word ".repeat(20); // ~100 chars, ~26 tokens with its number
+        let long = "word ".repeat(400);
+        let passages = [short.as_str(), long.as_str(), short.as_str()];
+
+        // Room for the first passage and part of the second
+        let packed = pack_context(&passages, Some(100), "gpt-4o");
+        assert_eq!(packed.included, 2);
+        assert_eq!(packed.available, 3);
+        assert!(packed.truncated);
+        assert!(packed.tokens <= 100);
+        assert!(packed.text.ends_with(TRUNCATION_MARKER));
+        assert!(!packed.text.contains("[3]"));
+
+        // Too little left over to be worth truncating the second passage
+        let packed = pack_context(&passages, Some(40), "gpt-4o");
+        assert_eq!(packed.included, 1);
+        assert!(!packed.truncated);
+
+        // The top passage is always included, truncated if necessary
+        let packed = pack_context(&[long.as_str()], Some(10), "gpt-4o");
+        assert_eq!(packed.included, 1);
+        assert!(packed.truncated);
+        assert!(packed.tokens <= 10);
+    }
+
+    #[test]
+    fn test_summary() {
+        let packed = pack_context(&["a", "b"], Some(4), "gpt-4o");
+        assert_eq!(packed.summary(), "1 of 2 passages (~2 tokens)");
+    }
+}
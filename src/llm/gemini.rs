@@ -0,0 +1,267 @@
+//! Google Gemini LLM provider
+
+use std::env;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::GenerationOptions;
+use crate::http::{check_response, create_client};
+
+/// Gemini LLM provider
+pub struct GeminiLlm {
+    client: Client,
+    api_key: String,
+    base_url: String,
+    model_name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest {
+    contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Content {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Part {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Deserialize)]
+struct Candidate {
+    #[serde(default)]
+    content: Option<Content>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    #[serde(default)]
+    block_reason: Option<String>,
+}
+
+impl GenerateContentResponse {
+    /// Text of the first candidate, or an error if the prompt was blocked
+    fn text(&self) -> anyhow::Result<String> {
+        if self.candidates.is_empty() {
+            if let Some(reason) = self.prompt_feedback.as_ref().and_then(|f| f.block_reason.as_ref()) {
+                anyhow::bail!("Gemini blocked the prompt: {}", reason);
+            }
+        }
+
+        Ok(self
+            .candidates
+            .first()
+            .and_then(|c| c.content.as_ref())
+            .map(|c| c.parts.iter().filter_map(|p| p.text.as_deref()).collect())
+            .unwrap_or_default())
+    }
+}
+
+impl GeminiLlm {
+    /// Create a new Gemini LLM provider
+    pub fn new(
+        model_name: String,
+        api_key: Option<String>,
+        base_url: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let base_url = base_url
+            .or_else(|| env::var("GEMINI_BASE_URL").ok())
+            .unwrap_or_else(|| "https://generativelanguage.googleapis.com".to_string());
+        crate::http::check_network("Gemini LLM", &base_url)?;
+
+        let api_key = api_key
+            .or_else(|| env::var("GOOGLE_API_KEY").ok())
+            .or_else(|| env::var("GEMINI_API_KEY").ok())
+            .ok_or_else(|| anyhow::anyhow!("GOOGLE_API_KEY or GEMINI_API_KEY not set"))?;
+
+        let client = create_client();
+
+        info!("Gemini LLM provider: {}", model_name);
+
+        Ok(Self {
+            client,
+            api_key,
+            base_url,
+            model_name,
+        })
+    }
+
+    fn request(prompt: &str, options: &GenerationOptions) -> GenerateContentRequest {
+        GenerateContentRequest {
+            contents: vec![Content {
+                role: Some("user".to_string()),
+                parts: vec![Part {
+                    text: Some(prompt.to_string()),
+                }],
+            }],
+            system_instruction: options.system.as_ref().map(|system| Content {
+                role: None,
+                parts: vec![Part {
+                    text: Some(system.clone()),
+                }],
+            }),
+            generation_config: GenerationConfig {
+                temperature: options.temperature,
+                top_p: options.top_p,
+                max_output_tokens: options.max_tokens,
+                stop_sequences: options.stop.clone(),
+            },
+        }
+    }
+
+    /// Generate a response
+    pub async fn generate(&self, prompt: &str, options: &GenerationOptions) -> anyhow::Result<String> {
+        let response = self
+            .client
+            .post(format!(
+                "{}/v1beta/models/{}:generateContent",
+                self.base_url, self.model_name
+            ))
+            .query(&[("key", &self.api_key)])
+            .json(&Self::request(prompt, options))
+            .send()
+            .await?;
+
+        let response = check_response(response, "Gemini").await?;
+        let response: GenerateContentResponse = response.json().await?;
+        response.text()
+    }
+
+    /// Generate a response, passing text to `on_chunk` as it arrives
+    ///
+    /// Returns the full response text.
+    pub async fn generate_stream(
+        &self,
+        prompt: &str,
+        options: &GenerationOptions,
+        on_chunk: &mut (dyn FnMut(&str) + Send),
+    ) -> anyhow::Result<String> {
+        let response = self
+            .client
+            .post(format!(
+                "{}/v1beta/models/{}:streamGenerateContent",
+                self.base_url, self.model_name
+            ))
+            .query(&[("alt", "sse"), ("key", self.api_key.as_str())])
+            .json(&Self::request(prompt, options))
+            .send()
+            .await?;
+
+        let mut response = check_response(response, "Gemini").await?;
+
+        let mut events = SseEvents::default();
+        let mut full_response = String::new();
+        while let Some(bytes) = response.chunk().await? {
+            for data in events.push(&bytes) {
+                let event: GenerateContentResponse = serde_json::from_str(&data)?;
+                let text = event.text()?;
+                if !text.is_empty() {
+                    on_chunk(&text);
+                    full_response.push_str(&text);
+                }
+            }
+        }
+
+        Ok(full_response)
+    }
+}
+
+/// Incremental parser for server-sent events, yielding `data:` payloads
+#[derive(Default)]
+struct SseEvents {
+    buf: Vec<u8>,
+}
+
+impl SseEvents {
+    /// Add received bytes and return the data of each completed line
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(bytes);
+
+        let mut data = Vec::new();
+        while let Some(newline) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(payload) = line.trim_end().strip_prefix("data:") {
+                let payload = payload.trim();
+                if !payload.is_empty() {
+                    data.push(payload.to_string());
+                }
+            }
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_events_across_chunks() {
+        let mut events = SseEvents::default();
+        assert!(events.push(b"data: {\"a\":").is_empty());
+        assert_eq!(events.push(b" 1}\r\n\r\ndata: {\"b\": 2}\n"), vec!["{\"a\": 1}", "{\"b\": 2}"]);
+        assert!(events.push(b": keep-alive\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_response_text() {
+        let json = r#"{"candidates": [{"content": {"role": "model", "parts": [{"text": "Hel"}, {"text": "lo"}]}}]}"#;
+        let response: GenerateContentResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.text().unwrap(), "Hello");
+
+        let blocked = r#"{"promptFeedback": {"blockReason": "SAFETY"}}"#;
+        let response: GenerateContentResponse = serde_json::from_str(blocked).unwrap();
+        assert!(response.text().is_err());
+    }
+
+    #[test]
+    fn test_request_serialization() {
+        let options = GenerationOptions {
+            system: Some("Be brief.".to_string()),
+            max_tokens: Some(64),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(GeminiLlm::request("hi", &options)).unwrap();
+        assert_eq!(value["contents"][0]["parts"][0]["text"], "hi");
+        assert_eq!(value["systemInstruction"]["parts"][0]["text"], "Be brief.");
+        assert_eq!(value["generationConfig"]["maxOutputTokens"], 64);
+        assert!(value["generationConfig"].get("temperature").is_none());
+    }
+}
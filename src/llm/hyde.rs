@@ -0,0 +1,33 @@
+//! HyDE (hypothetical document embeddings) - retrieve with an LLM-written answer
+//!
+//! A question and the passage that answers it are often far apart in
+//! embedding space. HyDE asks the LLM for a plausible answer and embeds that
+//! instead (or blended with the question), which lands closer to real answers.
+
+use super::{GenerationOptions, LlmProvider};
+
+/// Length limit for hypothetical passages
+const HYDE_MAX_TOKENS: u32 = 256;
+
+/// Prompt for generating a hypothetical passage
+const HYDE_PROMPT: &str = "Write a short passage that answers the question below, written as it \
+would appear in the documents or source code being searched. Write it confidently, without \
+hedging or mentioning that it is hypothetical.
+
+Question: ";
+
+/// Generate a hypothetical passage answering `query`
+pub async fn hypothetical_document(llm: &LlmProvider, query: &str) -> anyhow::Result<String> {
+    let options = GenerationOptions {
+        max_tokens: Some(HYDE_MAX_TOKENS),
+        ..Default::default()
+    };
+    let prompt = format!("{}{}\n\nPassage:", HYDE_PROMPT, query);
+    let passage = llm.generate_with_options(&prompt, &options).await?;
+
+    let passage = passage.trim();
+    if passage.is_empty() {
+        anyhow::bail!("LLM returned an empty hypothetical passage");
+    }
+    Ok(passage.to_string())
+}
@@ -0,0 +1,181 @@
+//! Local LLM provider - quantized GGUF models run with Candle
+//!
+//! Loads Llama-architecture GGUF files (Llama, Mistral, and derivatives) and
+//! generates on the CPU, so `ask` and `react` work without any server. The
+//! prompt is fed as raw text: models that expect chat markup should be used
+//! with a `--prompt-template` that includes it.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use candle_core::quantized::gguf_file;
+use candle_core::{Device, Tensor};
+use candle_transformers::generation::LogitsProcessor;
+use candle_transformers::models::quantized_llama::ModelWeights;
+use tokenizers::Tokenizer;
+use tracing::info;
+
+use super::{GenerationOptions, DEFAULT_MAX_TOKENS};
+
+/// Tokens the model can attend to (prompt plus generated)
+const CONTEXT_TOKENS: usize = 4096;
+
+/// End-of-sequence tokens used by common model families
+const EOS_TOKENS: &[&str] = &["</s>", "<|endoftext|>", "<|eot_id|>", "<|im_end|>", "<|end|>"];
+
+/// Local GGUF LLM provider
+pub struct LocalLlm {
+    model: Mutex<ModelWeights>,
+    tokenizer: Tokenizer,
+    device: Device,
+    eos_tokens: Vec<u32>,
+}
+
+impl LocalLlm {
+    /// Load a GGUF model
+    ///
+    /// The tokenizer defaults to `tokenizer.json` next to the model file.
+    pub fn new(model_path: String, tokenizer_path: Option<String>) -> anyhow::Result<Self> {
+        let model_path = PathBuf::from(model_path);
+        let tokenizer_path = match tokenizer_path {
+            Some(path) => PathBuf::from(path),
+            None => default_tokenizer_path(&model_path),
+        };
+
+        if !model_path.exists() {
+            anyhow::bail!("Model file not found: {}", model_path.display());
+        }
+        if !tokenizer_path.exists() {
+            anyhow::bail!(
+                "Tokenizer not found at {} (pass --tokenizer <tokenizer.json>)",
+                tokenizer_path.display()
+            );
+        }
+
+        info!("Loading local LLM: {}", model_path.display());
+
+        let device = Device::Cpu;
+
+        let mut file = std::fs::File::open(&model_path)?;
+        let content = gguf_file::Content::read(&mut file)
+            .map_err(|e| anyhow::anyhow!("Failed to read GGUF file {}: {}", model_path.display(), e))?;
+        let model = ModelWeights::from_gguf(content, &mut file, &device)?;
+
+        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow::anyhow!("Failed to load tokenizer: {}", e))?;
+
+        let eos_tokens = EOS_TOKENS
+            .iter()
+            .filter_map(|t| tokenizer.token_to_id(t))
+            .collect();
+
+        Ok(Self {
+            model: Mutex::new(model),
+            tokenizer,
+            device,
+            eos_tokens,
+        })
+    }
+
+    /// Generate a response
+    pub fn generate(&self, prompt: &str, options: &GenerationOptions) -> anyhow::Result<String> {
+        let prompt = match &options.system {
+            Some(system) => format!("{}\n\n{}", system, prompt),
+            None => prompt.to_string(),
+        };
+        let max_tokens = options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS) as usize;
+
+        let encoding = self
+            .tokenizer
+            .encode(prompt, true)
+            .map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))?;
+        let mut tokens = encoding.get_ids().to_vec();
+
+        // Keep the end of an over-long prompt (the question comes last)
+        let prompt_budget = CONTEXT_TOKENS.saturating_sub(max_tokens).max(1);
+        if tokens.len() > prompt_budget {
+            tokens.drain(..tokens.len() - prompt_budget);
+        }
+        let max_tokens = max_tokens.min(CONTEXT_TOKENS - tokens.len());
+
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        let mut sampler = LogitsProcessor::new(
+            seed,
+            options.temperature.map(f64::from),
+            options.top_p.map(f64::from),
+        );
+
+        let mut model = self
+            .model
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Local LLM is unavailable after an earlier failure"))?;
+
+        let mut generated: Vec<u32> = Vec::new();
+        let mut text = String::new();
+        let mut input = tokens;
+        let mut pos = 0;
+
+        while generated.len() < max_tokens {
+            let batch = Tensor::new(input.as_slice(), &self.device)?.unsqueeze(0)?;
+            let logits = model.forward(&batch, pos)?.squeeze(0)?;
+            pos += input.len();
+
+            let next = sampler.sample(&logits)?;
+            if self.eos_tokens.contains(&next) {
+                break;
+            }
+            generated.push(next);
+            input = vec![next];
+
+            text = self
+                .tokenizer
+                .decode(&generated, true)
+                .map_err(|e| anyhow::anyhow!("Detokenization failed: {}", e))?;
+            if let Some(end) = find_stop(&text, &options.stop) {
+                text.truncate(end);
+                break;
+            }
+        }
+
+        Ok(text.trim().to_string())
+    }
+}
+
+fn default_tokenizer_path(model_path: &Path) -> PathBuf {
+    model_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("tokenizer.json")
+}
+
+/// Byte offset of the earliest stop sequence in `text`
+fn find_stop(text: &str, stop: &[String]) -> Option<usize> {
+    stop.iter()
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| text.find(s.as_str()))
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_stop() {
+        let stop = vec!["###".to_string(), "\nUser:".to_string()];
+        assert_eq!(find_stop("answer\nUser: more ### x", &stop), Some(6));
+        assert_eq!(find_stop("no stop here", &stop), None);
+        assert_eq!(find_stop("text", &[String::new()]), None);
+    }
+
+    #[test]
+    fn test_default_tokenizer_path() {
+        assert_eq!(
+            default_tokenizer_path(Path::new("/models/mistral.gguf")),
+            PathBuf::from("/models/tokenizer.json")
+        );
+        assert_eq!(default_tokenizer_path(Path::new("model.gguf")), PathBuf::from("tokenizer.json"));
+    }
+}
@@ -3,30 +3,175 @@
 mod ollama;
 mod openai;
 mod anthropic;
+mod budget;
+mod gemini;
+mod hyde;
+mod multi_query;
+mod pricing;
+mod redact;
 mod simulated;
+mod structured;
+mod template;
+mod tools;
+#[cfg(feature = "local-llm")]
+mod local;
 
+pub use budget::{context_window, count_tokens, pack_context, PackedContext};
+pub use hyde::hypothetical_document;
+pub use multi_query::{reformulate_query, ReformulationCache};
+pub use pricing::{llm_price, TokenUsage};
+pub use redact::PiiRedactor;
+pub use structured::{generate_structured, OutputSchema};
+pub use template::{PromptTemplate, DEFAULT_ASK_TEMPLATE, DEFAULT_REACT_TEMPLATE};
+pub use tools::{ChatMessage, Tool, ToolCall, ToolChoice, ToolResponse};
+
+use std::sync::Mutex;
+
+use serde::Deserialize;
 use tracing::info;
 
+use crate::config::LlmConfig;
+
+/// Default completion length for providers that require one
+pub const DEFAULT_MAX_TOKENS: u32 = 1000;
+
+/// Sampling and prompt options for a generation request
+///
+/// Unset fields use the provider's defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GenerationOptions {
+    /// System prompt sent ahead of the user prompt
+    #[serde(default, alias = "system_prompt")]
+    pub system: Option<String>,
+
+    /// Sampling temperature
+    #[serde(default)]
+    pub temperature: Option<f32>,
+
+    /// Nucleus sampling probability mass
+    #[serde(default)]
+    pub top_p: Option<f32>,
+
+    /// Maximum tokens to generate
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+
+    /// Sequences that end generation
+    #[serde(default)]
+    pub stop: Vec<String>,
+}
+
+impl GenerationOptions {
+    /// Fill fields unset here from `defaults`
+    pub fn or_defaults(self, defaults: &GenerationOptions) -> Self {
+        Self {
+            system: self.system.or_else(|| defaults.system.clone()),
+            temperature: self.temperature.or(defaults.temperature),
+            top_p: self.top_p.or(defaults.top_p),
+            max_tokens: self.max_tokens.or(defaults.max_tokens),
+            stop: if self.stop.is_empty() { defaults.stop.clone() } else { self.stop },
+        }
+    }
+
+    /// Check values are in the ranges providers accept
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(t) = self.temperature {
+            if !(0.0..=2.0).contains(&t) {
+                anyhow::bail!("temperature must be between 0 and 2, got {}", t);
+            }
+        }
+        if let Some(p) = self.top_p {
+            if !(0.0..=1.0).contains(&p) {
+                anyhow::bail!("top_p must be between 0 and 1, got {}", p);
+            }
+        }
+        if self.max_tokens == Some(0) {
+            anyhow::bail!("max_tokens must be at least 1");
+        }
+        Ok(())
+    }
+}
+
 /// LLM provider type
 #[derive(Debug, Clone)]
 pub enum LlmType {
     Ollama { host: Option<String> },
     OpenAI { api_key: Option<String>, base_url: Option<String> },
     Anthropic { api_key: Option<String>, base_url: Option<String> },
+    Gemini { api_key: Option<String>, base_url: Option<String> },
     Simulated,
+    /// Quantized GGUF model run locally; the model name is the file path
+    #[cfg(feature = "local-llm")]
+    Local { tokenizer: Option<String> },
+}
+
+/// LLM connection settings from command-line flags (and their environment
+/// variables), which take precedence over config.toml
+#[derive(Debug, Clone, Default)]
+pub struct LlmOverrides {
+    /// Ollama host
+    pub host: Option<String>,
+    /// API key for OpenAI/Anthropic
+    pub api_key: Option<String>,
+    /// Base URL for OpenAI-compatible and Anthropic APIs
+    pub api_base: Option<String>,
+    /// Tokenizer file for local GGUF models
+    #[cfg(feature = "local-llm")]
+    pub tokenizer: Option<String>,
+}
+
+impl LlmType {
+    /// The provider called `provider`, connected with `overrides`, then
+    /// `config`; providers fall back to their own environment variables for
+    /// anything left unset
+    pub fn from_name(provider: &str, overrides: &LlmOverrides, config: &LlmConfig) -> anyhow::Result<Self> {
+        Ok(match provider {
+            "ollama" => LlmType::Ollama {
+                host: overrides.host.clone().or_else(|| config.host.clone()),
+            },
+            "openai" => LlmType::OpenAI {
+                api_key: overrides.api_key.clone().or_else(|| config.api_key.clone()),
+                base_url: overrides.api_base.clone().or_else(|| config.base_url.clone()),
+            },
+            "anthropic" => LlmType::Anthropic {
+                api_key: overrides.api_key.clone().or_else(|| config.api_key.clone()),
+                base_url: overrides.api_base.clone().or_else(|| config.base_url.clone()),
+            },
+            // The key and base URL flags default to the OpenAI variables, so Gemini
+            // only takes its settings from config.toml and GOOGLE_API_KEY
+            "gemini" => LlmType::Gemini {
+                api_key: config.api_key.clone(),
+                base_url: config.base_url.clone(),
+            },
+            "simulated" => LlmType::Simulated,
+            #[cfg(feature = "local-llm")]
+            "local" => LlmType::Local {
+                tokenizer: overrides.tokenizer.clone(),
+            },
+            _ => anyhow::bail!("Unknown LLM provider: {}", provider),
+        })
+    }
 }
 
 /// Unified LLM provider
 pub struct LlmProvider {
     model_name: String,
     inner: LlmProviderInner,
+    options: GenerationOptions,
+    /// Scrubs personal information from prompts, if set
+    redactor: Option<PiiRedactor>,
+    /// Estimated tokens sent and generated so far, for cost reporting
+    usage: Mutex<TokenUsage>,
 }
 
 enum LlmProviderInner {
     Ollama(ollama::OllamaLlm),
     OpenAI(openai::OpenAILlm),
     Anthropic(anthropic::AnthropicLlm),
+    Gemini(gemini::GeminiLlm),
     Simulated(simulated::SimulatedLlm),
+    #[cfg(feature = "local-llm")]
+    Local(local::LocalLlm),
 }
 
 impl LlmProvider {
@@ -42,23 +187,174 @@ impl LlmProvider {
             LlmType::Anthropic { api_key, base_url } => {
                 LlmProviderInner::Anthropic(anthropic::AnthropicLlm::new(model_name.clone(), api_key, base_url)?)
             }
+            LlmType::Gemini { api_key, base_url } => {
+                LlmProviderInner::Gemini(gemini::GeminiLlm::new(model_name.clone(), api_key, base_url)?)
+            }
             LlmType::Simulated => {
                 LlmProviderInner::Simulated(simulated::SimulatedLlm::new(model_name.clone())?)
             }
+            #[cfg(feature = "local-llm")]
+            LlmType::Local { tokenizer } => {
+                LlmProviderInner::Local(local::LocalLlm::new(model_name.clone(), tokenizer)?)
+            }
         };
 
         info!("Initialized LLM provider: {}", model_name);
 
-        Ok(Self { model_name, inner })
+        Ok(Self {
+            model_name,
+            inner,
+            options: GenerationOptions::default(),
+            redactor: None,
+            usage: Mutex::new(TokenUsage::default()),
+        })
+    }
+
+    /// Set the generation options used by `generate`
+    pub fn with_options(mut self, options: GenerationOptions) -> anyhow::Result<Self> {
+        options.validate()?;
+        self.options = options;
+        Ok(self)
+    }
+
+    /// Redact personal information from prompts before they are sent, and
+    /// restore it in responses
+    pub fn with_redactor(mut self, redactor: Option<PiiRedactor>) -> Self {
+        self.redactor = redactor;
+        self
     }
 
-    /// Generate a response
+    /// Generate a response with the provider's configured options
     pub async fn generate(&self, prompt: &str) -> anyhow::Result<String> {
+        self.generate_with_options(prompt, &GenerationOptions::default()).await
+    }
+
+    /// Generate a response, overriding configured options with those set in `options`
+    #[tracing::instrument(name = "llm.generate", skip_all, fields(provider = self.provider_name(), model = self.model_name()))]
+    pub async fn generate_with_options(
+        &self,
+        prompt: &str,
+        options: &GenerationOptions,
+    ) -> anyhow::Result<String> {
+        let options = options.clone().or_defaults(&self.options);
+        options.validate()?;
+        let Some(redactor) = &self.redactor else {
+            let response = self.generate_raw(prompt, &options).await?;
+            self.record_usage(prompt, &options, &response);
+            return Ok(response);
+        };
+        let redacted = redactor.redact(prompt);
+        if redacted.count() > 0 {
+            info!("Redacted {} values from the prompt", redacted.count());
+        }
+        let response = self.generate_raw(&redacted.text, &options).await?;
+        self.record_usage(&redacted.text, &options, &response);
+        Ok(redacted.restore(&response))
+    }
+
+    /// Generate a response from the provider as-is
+    async fn generate_raw(&self, prompt: &str, options: &GenerationOptions) -> anyhow::Result<String> {
         match &self.inner {
-            LlmProviderInner::Ollama(llm) => llm.generate(prompt).await,
-            LlmProviderInner::OpenAI(llm) => llm.generate(prompt).await,
-            LlmProviderInner::Anthropic(llm) => llm.generate(prompt).await,
-            LlmProviderInner::Simulated(llm) => llm.generate(prompt).await,
+            LlmProviderInner::Ollama(llm) => llm.generate(prompt, options).await,
+            LlmProviderInner::OpenAI(llm) => llm.generate(prompt, options).await,
+            LlmProviderInner::Anthropic(llm) => llm.generate(prompt, options).await,
+            LlmProviderInner::Gemini(llm) => llm.generate(prompt, options).await,
+            LlmProviderInner::Simulated(llm) => llm.generate(prompt, options).await,
+            #[cfg(feature = "local-llm")]
+            LlmProviderInner::Local(llm) => llm.generate(prompt, options),
+        }
+    }
+
+    /// Continue a conversation in which the model may call `tools`, with the
+    /// configured options
+    ///
+    /// Returns `None` when the provider, or an Ollama model, has no native
+    /// tool calling, so callers can fall back to writing actions in the prompt.
+    #[tracing::instrument(name = "llm.generate", skip_all, fields(provider = self.provider_name(), model = self.model_name(), tools = true))]
+    pub async fn generate_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+        choice: ToolChoice,
+    ) -> anyhow::Result<Option<ToolResponse>> {
+        let redacted = match &self.redactor {
+            Some(redactor) => Some(tools::redact_messages(redactor, messages)?),
+            None => None,
+        };
+        let sent = redacted.as_ref().map_or(messages, |(sent, _)| sent.as_slice());
+        if let Some((_, redacted)) = redacted.as_ref().filter(|(_, r)| r.count() > 0) {
+            info!("Redacted {} values from the conversation", redacted.count());
+        }
+
+        let options = &self.options;
+        let response = match &self.inner {
+            LlmProviderInner::OpenAI(llm) => Some(llm.generate_with_tools(sent, tools, choice, options).await?),
+            LlmProviderInner::Anthropic(llm) => Some(llm.generate_with_tools(sent, tools, choice, options).await?),
+            LlmProviderInner::Ollama(llm) => llm.generate_with_tools(sent, tools, choice, options).await?,
+            _ => None,
+        };
+        let Some(response) = response else {
+            return Ok(None);
+        };
+        self.record_usage(&tools::prompt_text(sent, tools), options, &response.generated_text());
+        Ok(Some(match redacted {
+            Some((_, redacted)) => response.restore(&redacted),
+            None => response,
+        }))
+    }
+
+    /// Generate a response with the configured options, passing text to
+    /// `on_chunk` as it arrives
+    ///
+    /// Providers without streaming support emit the whole response as one chunk.
+    /// Returns the full response text.
+    pub async fn generate_stream(
+        &self,
+        prompt: &str,
+        on_chunk: &mut (dyn FnMut(&str) + Send),
+    ) -> anyhow::Result<String> {
+        self.generate_stream_with_options(prompt, &GenerationOptions::default(), on_chunk)
+            .await
+    }
+
+    /// Stream a response, overriding configured options with those set in `options`
+    #[tracing::instrument(
+        name = "llm.generate",
+        skip_all,
+        fields(provider = self.provider_name(), model = self.model_name(), stream = true)
+    )]
+    pub async fn generate_stream_with_options(
+        &self,
+        prompt: &str,
+        options: &GenerationOptions,
+        on_chunk: &mut (dyn FnMut(&str) + Send),
+    ) -> anyhow::Result<String> {
+        let options = options.clone().or_defaults(&self.options);
+        options.validate()?;
+        match (&self.inner, &self.redactor) {
+            (LlmProviderInner::Gemini(llm), None) => {
+                let response = llm.generate_stream(prompt, &options, on_chunk).await?;
+                self.record_usage(prompt, &options, &response);
+                Ok(response)
+            }
+            (LlmProviderInner::Gemini(llm), Some(redactor)) => {
+                // Placeholders may arrive split across chunks
+                let redacted = redactor.redact(prompt);
+                let mut pending = String::new();
+                let response = llm
+                    .generate_stream(&redacted.text, &options, &mut |chunk| {
+                        on_chunk(&redacted.restore_chunk(&mut pending, chunk))
+                    })
+                    .await?;
+                self.record_usage(&redacted.text, &options, &response);
+                on_chunk(&redacted.restore(&pending));
+                Ok(redacted.restore(&response))
+            }
+            _ => {
+                let text = self.generate_with_options(prompt, &options).await?;
+                on_chunk(&text);
+                Ok(text)
+            }
         }
     }
 
@@ -66,4 +362,101 @@ impl LlmProvider {
     pub fn model_name(&self) -> &str {
         &self.model_name
     }
+
+    /// Provider name ("ollama", "openai", ...), as given to `--llm`
+    pub fn provider_name(&self) -> &'static str {
+        match &self.inner {
+            LlmProviderInner::Ollama(_) => "ollama",
+            LlmProviderInner::OpenAI(_) => "openai",
+            LlmProviderInner::Anthropic(_) => "anthropic",
+            LlmProviderInner::Gemini(_) => "gemini",
+            LlmProviderInner::Simulated(_) => "simulated",
+            #[cfg(feature = "local-llm")]
+            LlmProviderInner::Local(_) => "local",
+        }
+    }
+
+    /// Whether the provider has native tool calling (`generate_with_tools`);
+    /// some Ollama models still don't
+    pub fn supports_tools(&self) -> bool {
+        matches!(self.inner, LlmProviderInner::OpenAI(_) | LlmProviderInner::Anthropic(_) | LlmProviderInner::Ollama(_))
+    }
+
+    /// Estimated tokens sent and generated so far
+    pub fn usage(&self) -> TokenUsage {
+        self.usage.lock().map(|usage| *usage).unwrap_or_default()
+    }
+
+    /// Cost in USD of `usage` with this model, if the price is known
+    pub fn cost(&self, usage: &TokenUsage) -> Option<f64> {
+        llm_price(self.provider_name(), &self.model_name).map(|price| price.cost(usage))
+    }
+
+    /// Count a request's tokens (system prompt included) towards `usage`
+    fn record_usage(&self, prompt: &str, options: &GenerationOptions, response: &str) {
+        let system = options.system.as_deref().unwrap_or_default();
+        let request = TokenUsage {
+            input_tokens: count_tokens(&self.model_name, prompt) + count_tokens(&self.model_name, system),
+            output_tokens: count_tokens(&self.model_name, response),
+        };
+        if let Ok(mut usage) = self.usage.lock() {
+            *usage += request;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_options_or_defaults() {
+        let defaults = GenerationOptions {
+            system: Some("Be brief.".to_string()),
+            temperature: Some(0.2),
+            stop: vec!["END".to_string()],
+            ..Default::default()
+        };
+        let merged = GenerationOptions {
+            temperature: Some(0.9),
+            max_tokens: Some(50),
+            ..Default::default()
+        }
+        .or_defaults(&defaults);
+
+        assert_eq!(merged.system.as_deref(), Some("Be brief."));
+        assert_eq!(merged.temperature, Some(0.9));
+        assert_eq!(merged.max_tokens, Some(50));
+        assert!(merged.top_p.is_none());
+        assert_eq!(merged.stop, vec!["END".to_string()]);
+    }
+
+    #[test]
+    fn test_llm_type_flags_override_config() {
+        let config = LlmConfig {
+            host: Some("http://gpu-box:11434".to_string()),
+            base_url: Some("http://localhost:1234/v1".to_string()),
+            ..Default::default()
+        };
+
+        let overrides = LlmOverrides { host: Some("http://localhost:11434".to_string()), ..Default::default() };
+        match LlmType::from_name("ollama", &overrides, &config).unwrap() {
+            LlmType::Ollama { host } => assert_eq!(host.as_deref(), Some("http://localhost:11434")),
+            other => panic!("unexpected {:?}", other),
+        }
+        match LlmType::from_name("openai", &overrides, &config).unwrap() {
+            LlmType::OpenAI { base_url, .. } => assert_eq!(base_url.as_deref(), Some("http://localhost:1234/v1")),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(LlmType::from_name("bard", &overrides, &config).is_err());
+    }
+
+    #[test]
+    fn test_generation_options_validate() {
+        assert!(GenerationOptions::default().validate().is_ok());
+        let bad = |o: GenerationOptions| o.validate().is_err();
+        assert!(bad(GenerationOptions { temperature: Some(3.0), ..Default::default() }));
+        assert!(bad(GenerationOptions { top_p: Some(1.5), ..Default::default() }));
+        assert!(bad(GenerationOptions { max_tokens: Some(0), ..Default::default() }));
+    }
 }
@@ -0,0 +1,207 @@
+//! Multi-query expansion - LLM-written reformulations of a search query
+//!
+//! Each reformulation is searched separately and the rankings are fused, so
+//! passages phrased differently from the original query can still be found.
+//! Reformulations are cached in `~/.leann/cache/reformulations.json`, keyed by
+//! model, count, and query, so repeated searches skip the LLM call.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use super::LlmProvider;
+
+/// Most reformulations that can be requested
+pub const MAX_REFORMULATIONS: usize = 10;
+
+/// Cached queries kept before the oldest are evicted
+const MAX_CACHE_ENTRIES: usize = 1000;
+
+/// Reformulations cached for earlier queries
+#[derive(Default, Serialize, Deserialize)]
+pub struct ReformulationCache {
+    entries: HashMap<String, CachedReformulations>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedReformulations {
+    queries: Vec<String>,
+    created_at: u64,
+}
+
+impl ReformulationCache {
+    /// Load the cache from `~/.leann/cache` (empty if missing or unreadable)
+    pub fn load() -> Self {
+        let Some(path) = dirs::home_dir().map(|h| h.join(".leann").join("cache").join("reformulations.json")) else {
+            return Self::default();
+        };
+
+        let mut cache: Self = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        cache.path = Some(path);
+        cache
+    }
+
+    fn key(model_name: &str, n: usize, query: &str) -> String {
+        format!("{}\n{}\n{}", model_name, n, query.trim())
+    }
+
+    /// Cached reformulations of `query`, if any
+    pub fn get(&self, model_name: &str, n: usize, query: &str) -> Option<Vec<String>> {
+        self.entries
+            .get(&Self::key(model_name, n, query))
+            .map(|entry| entry.queries.clone())
+    }
+
+    /// Cache reformulations, evicting the oldest entries beyond the size limit
+    pub fn insert(&mut self, model_name: &str, n: usize, query: &str, queries: Vec<String>) {
+        self.entries.insert(
+            Self::key(model_name, n, query),
+            CachedReformulations {
+                queries,
+                created_at: crate::time::now_unix(),
+            },
+        );
+
+        if self.entries.len() > MAX_CACHE_ENTRIES {
+            let mut by_age: Vec<(u64, String)> = self
+                .entries
+                .iter()
+                .map(|(key, entry)| (entry.created_at, key.clone()))
+                .collect();
+            by_age.sort();
+            for (_, key) in by_age.into_iter().take(self.entries.len() - MAX_CACHE_ENTRIES) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    /// Write the cache back to disk
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Ask the LLM for `n` reformulations of `query`, using the cache when possible
+///
+/// Returns only the reformulations, not the original query; may return fewer
+/// than `n` if the LLM repeats itself.
+pub async fn reformulate_query(
+    llm: &LlmProvider,
+    query: &str,
+    n: usize,
+    cache: &Mutex<ReformulationCache>,
+) -> anyhow::Result<Vec<String>> {
+    if n == 0 || n > MAX_REFORMULATIONS {
+        anyhow::bail!("Number of query reformulations must be between 1 and {}", MAX_REFORMULATIONS);
+    }
+
+    let cached = cache
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(llm.model_name(), n, query));
+    if let Some(queries) = cached {
+        tracing::debug!("Using cached reformulations for '{}'", query);
+        return Ok(queries);
+    }
+
+    let prompt = format!(
+        "Write {} different search queries that would find documents answering the question below. \
+         Vary the wording and use synonyms or related technical terms. Output one query per line, \
+         without numbering or explanations.\n\nQuestion: {}\n\nQueries:",
+        n, query
+    );
+    let response = llm.generate(&prompt).await?;
+    let queries = parse_reformulations(&response, query, n);
+
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(llm.model_name(), n, query, queries.clone());
+        if let Err(e) = cache.save() {
+            tracing::warn!("Failed to save query reformulation cache: {}", e);
+        }
+    }
+
+    Ok(queries)
+}
+
+/// Extract up to `n` distinct queries from an LLM response, one per line
+///
+/// Strips list markers and quotes, and skips preambles ("Here are...:") and
+/// repeats of the original query.
+fn parse_reformulations(response: &str, original: &str, n: usize) -> Vec<String> {
+    let mut seen: HashSet<String> = HashSet::from([original.trim().to_lowercase()]);
+    let mut queries = Vec::new();
+
+    for line in response.lines() {
+        let line = line.trim();
+        let line = match line.find(['.', ')']) {
+            Some(end) if end > 0 && line[..end].chars().all(|c| c.is_ascii_digit()) => &line[end + 1..],
+            _ => line,
+        };
+        let line = line
+            .trim_start()
+            .trim_start_matches(['-', '*', '•'])
+            .trim()
+            .trim_matches(['"', '\'', '`'])
+            .trim();
+
+        if line.is_empty() || line.ends_with(':') {
+            continue;
+        }
+        if seen.insert(line.to_lowercase()) {
+            queries.push(line.to_string());
+        }
+        if queries.len() == n {
+            break;
+        }
+    }
+    queries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reformulations() {
+        let response = "Here are some alternative queries:\n\
+                        1. How to configure TLS certificates\n\
+                        2) \"setting up HTTPS\"\n\
+                        - how do I enable ssl?\n\
+                        * Setting up HTTPS\n\
+                        \n\
+                        TLS certificate configuration";
+        let queries = parse_reformulations(response, "How do I enable SSL?", 3);
+        assert_eq!(
+            queries,
+            vec!["How to configure TLS certificates", "setting up HTTPS", "TLS certificate configuration"]
+        );
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let mut cache = ReformulationCache::default();
+        cache.insert("m", 3, " query ", vec!["q1".to_string()]);
+        assert_eq!(cache.get("m", 3, "query"), Some(vec!["q1".to_string()]));
+        assert_eq!(cache.get("m", 4, "query"), None);
+        assert_eq!(cache.get("other", 3, "query"), None);
+
+        for i in 0..MAX_CACHE_ENTRIES + 5 {
+            cache.insert("m", 3, &format!("query {}", i), Vec::new());
+        }
+        assert_eq!(cache.entries.len(), MAX_CACHE_ENTRIES);
+    }
+}
@@ -6,6 +6,8 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
+use super::{ChatMessage, GenerationOptions, Tool, ToolCall, ToolChoice, ToolResponse};
+use crate::error::LeannError;
 use crate::http::{check_response, create_client};
 
 /// Ollama LLM provider
@@ -20,6 +22,29 @@ struct GenerateRequest {
     model: String,
     prompt: String,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "SamplingOptions::is_empty")]
+    options: SamplingOptions,
+}
+
+/// Ollama model parameters (`options` in the request body)
+#[derive(Serialize, Default)]
+struct SamplingOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+}
+
+impl SamplingOptions {
+    fn is_empty(&self) -> bool {
+        self.temperature.is_none() && self.top_p.is_none() && self.num_predict.is_none() && self.stop.is_empty()
+    }
 }
 
 #[derive(Deserialize)]
@@ -28,6 +53,31 @@ struct GenerateResponse {
     done: bool,
 }
 
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatResponseMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<ChatToolCall>,
+}
+
+#[derive(Deserialize)]
+struct ChatToolCall {
+    function: ChatFunctionCall,
+}
+
+#[derive(Deserialize)]
+struct ChatFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
 impl OllamaLlm {
     /// Create a new Ollama LLM provider
     pub fn new(model_name: String, host: Option<String>) -> anyhow::Result<Self> {
@@ -35,6 +85,7 @@ impl OllamaLlm {
             .or_else(|| env::var("LEANN_OLLAMA_HOST").ok())
             .or_else(|| env::var("OLLAMA_HOST").ok())
             .unwrap_or_else(|| "http://localhost:11434".to_string());
+        crate::http::check_network("Ollama LLM", &host)?;
 
         let client = create_client();
 
@@ -48,11 +99,18 @@ impl OllamaLlm {
     }
 
     /// Generate a response
-    pub async fn generate(&self, prompt: &str) -> anyhow::Result<String> {
+    pub async fn generate(&self, prompt: &str, options: &GenerationOptions) -> anyhow::Result<String> {
         let request = GenerateRequest {
             model: self.model_name.clone(),
             prompt: prompt.to_string(),
             stream: false,
+            system: options.system.clone(),
+            options: SamplingOptions {
+                temperature: options.temperature,
+                top_p: options.top_p,
+                num_predict: options.max_tokens,
+                stop: options.stop.clone(),
+            },
         };
 
         let response = self
@@ -80,4 +138,97 @@ impl OllamaLlm {
 
         Ok(full_response)
     }
+
+    /// Continue a conversation with tools through `/api/chat`; `None` if the
+    /// model doesn't support tools
+    pub async fn generate_with_tools(
+        &self,
+        conversation: &[ChatMessage],
+        tools: &[Tool],
+        choice: ToolChoice,
+        options: &GenerationOptions,
+    ) -> anyhow::Result<Option<ToolResponse>> {
+        let mut messages = Vec::new();
+        if let Some(system) = &options.system {
+            messages.push(serde_json::json!({ "role": "system", "content": system }));
+        }
+        messages.extend(conversation.iter().map(chat_message));
+        let sampling = SamplingOptions {
+            temperature: options.temperature,
+            top_p: options.top_p,
+            num_predict: options.max_tokens,
+            stop: options.stop.clone(),
+        };
+        let mut request = serde_json::json!({
+            "model": self.model_name,
+            "messages": messages,
+            "stream": false,
+            "options": sampling,
+        });
+        // Ollama has no tool_choice; leaving the tools out asks for text
+        if choice != ToolChoice::None && !tools.is_empty() {
+            request["tools"] = tools
+                .iter()
+                .map(|t| {
+                    serde_json::json!({
+                        "type": "function",
+                        "function": { "name": t.name, "description": t.description, "parameters": t.parameters },
+                    })
+                })
+                .collect();
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.host))
+            .json(&request)
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::BAD_REQUEST {
+            let body = response.text().await.unwrap_or_default();
+            let detail = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|json| json["error"].as_str().map(String::from))
+                .unwrap_or(body);
+            if detail.contains("does not support tools") {
+                info!("Model {} does not support tool calling", self.model_name);
+                return Ok(None);
+            }
+            return Err(LeannError::provider("Ollama", 400, detail).into());
+        }
+        let response = check_response(response, "Ollama").await?;
+        let chat: ChatResponse = response.json().await?;
+
+        // Ollama doesn't assign call ids; number them within the reply
+        let calls = chat
+            .message
+            .tool_calls
+            .into_iter()
+            .enumerate()
+            .map(|(i, call)| ToolCall {
+                id: format!("call_{}_{}", conversation.len(), i),
+                name: call.function.name,
+                arguments: call.function.arguments,
+            })
+            .collect();
+        Ok(Some(ToolResponse { text: chat.message.content, calls }))
+    }
+}
+
+/// A conversation message in Ollama's chat format
+fn chat_message(message: &ChatMessage) -> serde_json::Value {
+    match message {
+        ChatMessage::User(text) => serde_json::json!({ "role": "user", "content": text }),
+        ChatMessage::Assistant { text, calls } => serde_json::json!({
+            "role": "assistant",
+            "content": text,
+            "tool_calls": calls
+                .iter()
+                .map(|c| serde_json::json!({ "function": { "name": c.name, "arguments": c.arguments } }))
+                .collect::<Vec<_>>(),
+        }),
+        ChatMessage::ToolResult { name, content, .. } => {
+            serde_json::json!({ "role": "tool", "content": content, "tool_name": name })
+        }
+    }
 }
@@ -5,12 +5,17 @@ use std::env;
 use async_openai::{
     config::OpenAIConfig,
     types::{
-        ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs,
+        ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestToolMessageArgs,
+        ChatCompletionRequestUserMessageArgs, ChatCompletionToolArgs, ChatCompletionToolChoiceOption,
+        ChatCompletionToolType, CreateChatCompletionRequestArgs, FunctionCall, FunctionObjectArgs, Stop,
     },
     Client,
 };
 use tracing::info;
 
+use super::{ChatMessage, GenerationOptions, Tool, ToolCall, ToolChoice, ToolResponse, DEFAULT_MAX_TOKENS};
+
 /// OpenAI LLM provider
 pub struct OpenAILlm {
     client: Client<OpenAIConfig>,
@@ -24,17 +29,20 @@ impl OpenAILlm {
         api_key: Option<String>,
         base_url: Option<String>,
     ) -> anyhow::Result<Self> {
+        let base_url = base_url.or_else(|| env::var("OPENAI_BASE_URL").ok());
+        crate::http::check_network("OpenAI LLM", base_url.as_deref().unwrap_or(crate::http::OPENAI_API_BASE))?;
+
         let api_key = api_key
             .or_else(|| env::var("OPENAI_API_KEY").ok())
             .ok_or_else(|| anyhow::anyhow!("OPENAI_API_KEY not set"))?;
 
         let mut config = OpenAIConfig::new().with_api_key(api_key);
 
-        if let Some(base_url) = base_url.or_else(|| env::var("OPENAI_BASE_URL").ok()) {
+        if let Some(base_url) = base_url {
             config = config.with_api_base(base_url);
         }
 
-        let client = Client::with_config(config);
+        let client = Client::with_config(config).with_http_client(crate::http::create_client());
 
         info!("OpenAI LLM provider: {}", model_name);
 
@@ -42,17 +50,24 @@ impl OpenAILlm {
     }
 
     /// Generate a response
-    pub async fn generate(&self, prompt: &str) -> anyhow::Result<String> {
-        let request = CreateChatCompletionRequestArgs::default()
-            .model(&self.model_name)
-            .messages([ChatCompletionRequestUserMessageArgs::default()
+    pub async fn generate(&self, prompt: &str, options: &GenerationOptions) -> anyhow::Result<String> {
+        let mut messages = system_message(options)?;
+        messages.push(
+            ChatCompletionRequestUserMessageArgs::default()
                 .content(prompt)
                 .build()?
-                .into()])
-            .max_tokens(1000u32)
-            .build()?;
+                .into(),
+        );
+
+        let builder = request_builder(&self.model_name, messages, options);
+        let request = builder.build()?;
 
-        let response = self.client.chat().create(request).await?;
+        let response = self
+            .client
+            .chat()
+            .create(request)
+            .await
+            .map_err(|e| crate::http::openai_error("OpenAI", e))?;
 
         let content = response
             .choices
@@ -63,4 +78,130 @@ impl OpenAILlm {
 
         Ok(content)
     }
+
+    /// Continue a conversation with function tools
+    pub async fn generate_with_tools(
+        &self,
+        conversation: &[ChatMessage],
+        tools: &[Tool],
+        choice: ToolChoice,
+        options: &GenerationOptions,
+    ) -> anyhow::Result<ToolResponse> {
+        let mut messages = system_message(options)?;
+        for message in conversation {
+            messages.push(match message {
+                ChatMessage::User(text) => ChatCompletionRequestUserMessageArgs::default().content(text.as_str()).build()?.into(),
+                ChatMessage::Assistant { text, calls } => {
+                    let mut assistant = ChatCompletionRequestAssistantMessageArgs::default();
+                    if !text.is_empty() || calls.is_empty() {
+                        assistant.content(text.as_str());
+                    }
+                    if !calls.is_empty() {
+                        assistant.tool_calls(calls.iter().map(to_openai_call).collect::<Vec<_>>());
+                    }
+                    assistant.build()?.into()
+                }
+                ChatMessage::ToolResult { call_id, content, .. } => ChatCompletionRequestToolMessageArgs::default()
+                    .tool_call_id(call_id.as_str())
+                    .content(content.as_str())
+                    .build()?
+                    .into(),
+            });
+        }
+
+        let mut builder = request_builder(&self.model_name, messages, options);
+        let functions = tools
+            .iter()
+            .map(|tool| {
+                let function = FunctionObjectArgs::default()
+                    .name(tool.name)
+                    .description(tool.description)
+                    .parameters(tool.parameters.clone())
+                    .build()?;
+                ChatCompletionToolArgs::default().function(function).build()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !functions.is_empty() {
+            builder.tools(functions);
+            match choice {
+                ToolChoice::Auto => {}
+                ToolChoice::None => {
+                    builder.tool_choice(ChatCompletionToolChoiceOption::None);
+                }
+                ToolChoice::Required => {
+                    builder.tool_choice(ChatCompletionToolChoiceOption::Required);
+                }
+            }
+        }
+        let request = builder.build()?;
+
+        let response = self
+            .client
+            .chat()
+            .create(request)
+            .await
+            .map_err(|e| crate::http::openai_error("OpenAI", e))?;
+
+        let Some(message) = response.choices.into_iter().next().map(|c| c.message) else {
+            return Ok(ToolResponse::default());
+        };
+        let calls = message
+            .tool_calls
+            .unwrap_or_default()
+            .into_iter()
+            .map(|call| ToolCall {
+                id: call.id,
+                name: call.function.name,
+                // Models occasionally emit malformed JSON; keep it as a string
+                arguments: serde_json::from_str(&call.function.arguments)
+                    .unwrap_or(serde_json::Value::String(call.function.arguments)),
+            })
+            .collect();
+        Ok(ToolResponse { text: message.content.unwrap_or_default(), calls })
+    }
+}
+
+/// The system prompt as the first message, if there is one
+fn system_message(options: &GenerationOptions) -> anyhow::Result<Vec<ChatCompletionRequestMessage>> {
+    let mut messages = Vec::new();
+    if let Some(system) = &options.system {
+        messages.push(
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(system.as_str())
+                .build()?
+                .into(),
+        );
+    }
+    Ok(messages)
+}
+
+fn to_openai_call(call: &ToolCall) -> ChatCompletionMessageToolCall {
+    ChatCompletionMessageToolCall {
+        id: call.id.clone(),
+        r#type: ChatCompletionToolType::Function,
+        function: FunctionCall { name: call.name.clone(), arguments: call.arguments.to_string() },
+    }
+}
+
+/// A chat request for `messages` with the sampling options applied
+fn request_builder(
+    model_name: &str,
+    messages: Vec<ChatCompletionRequestMessage>,
+    options: &GenerationOptions,
+) -> CreateChatCompletionRequestArgs {
+    let mut builder = CreateChatCompletionRequestArgs::default();
+    builder
+        .model(model_name)
+        .messages(messages)
+        .max_tokens(options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS));
+    if let Some(temperature) = options.temperature {
+        builder.temperature(temperature);
+    }
+    if let Some(top_p) = options.top_p {
+        builder.top_p(top_p);
+    }
+    if !options.stop.is_empty() {
+        builder.stop(Stop::StringArray(options.stop.clone()));
+    }
+    builder
 }
@@ -0,0 +1,126 @@
+//! LLM pricing - token usage and cost per provider and model
+//!
+//! Prices are USD per million tokens from the providers' published price
+//! lists; models are matched by prefix so dated versions
+//! (`gpt-4o-mini-2024-07-18`) share their family's price.
+
+use std::ops::AddAssign;
+
+use serde::{Deserialize, Serialize};
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LlmPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl LlmPrice {
+    const FREE: Self = Self { input: 0.0, output: 0.0 };
+
+    /// Cost in USD of a request's tokens
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.input_tokens as f64 * self.input + usage.output_tokens as f64 * self.output) / 1_000_000.0
+    }
+}
+
+/// Hosted models by prefix, more specific prefixes first
+const PRICES: &[(&str, LlmPrice)] = &[
+    // OpenAI
+    ("gpt-4o-mini", LlmPrice { input: 0.15, output: 0.60 }),
+    ("gpt-4o", LlmPrice { input: 2.50, output: 10.00 }),
+    ("gpt-4.1-nano", LlmPrice { input: 0.10, output: 0.40 }),
+    ("gpt-4.1-mini", LlmPrice { input: 0.40, output: 1.60 }),
+    ("gpt-4.1", LlmPrice { input: 2.00, output: 8.00 }),
+    ("gpt-4-turbo", LlmPrice { input: 10.00, output: 30.00 }),
+    ("gpt-4", LlmPrice { input: 30.00, output: 60.00 }),
+    ("gpt-3.5-turbo", LlmPrice { input: 0.50, output: 1.50 }),
+    ("o1-mini", LlmPrice { input: 1.10, output: 4.40 }),
+    ("o1", LlmPrice { input: 15.00, output: 60.00 }),
+    ("o3-mini", LlmPrice { input: 1.10, output: 4.40 }),
+    ("o4-mini", LlmPrice { input: 1.10, output: 4.40 }),
+    ("o3", LlmPrice { input: 2.00, output: 8.00 }),
+    // Anthropic
+    ("claude-3-haiku", LlmPrice { input: 0.25, output: 1.25 }),
+    ("claude-3-5-haiku", LlmPrice { input: 0.80, output: 4.00 }),
+    ("claude-haiku-4", LlmPrice { input: 1.00, output: 5.00 }),
+    ("claude-3-opus", LlmPrice { input: 15.00, output: 75.00 }),
+    ("claude-opus-4", LlmPrice { input: 15.00, output: 75.00 }),
+    ("claude-3", LlmPrice { input: 3.00, output: 15.00 }),
+    ("claude-sonnet-4", LlmPrice { input: 3.00, output: 15.00 }),
+    // Google
+    ("gemini-1.5-flash", LlmPrice { input: 0.075, output: 0.30 }),
+    ("gemini-1.5-pro", LlmPrice { input: 1.25, output: 5.00 }),
+    ("gemini-2.0-flash-lite", LlmPrice { input: 0.075, output: 0.30 }),
+    ("gemini-2.0-flash", LlmPrice { input: 0.10, output: 0.40 }),
+    ("gemini-2.5-flash-lite", LlmPrice { input: 0.10, output: 0.40 }),
+    ("gemini-2.5-flash", LlmPrice { input: 0.30, output: 2.50 }),
+    ("gemini-2.5-pro", LlmPrice { input: 1.25, output: 10.00 }),
+];
+
+/// Price of a provider's model, if known
+///
+/// Local providers (ollama, local, simulated) are free.
+pub fn llm_price(provider: &str, model_name: &str) -> Option<LlmPrice> {
+    if matches!(provider, "ollama" | "local" | "simulated") {
+        return Some(LlmPrice::FREE);
+    }
+    let model = model_name.to_lowercase();
+    // Gemini model ids may be given as "models/gemini-..."
+    let model = model.rsplit('/').next().unwrap_or(&model);
+    PRICES
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, price)| *price)
+}
+
+/// Tokens sent to and generated by an LLM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+impl TokenUsage {
+    /// Usage accrued since `earlier`, a snapshot of the same counter
+    pub fn since(&self, earlier: &TokenUsage) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input_tokens.saturating_sub(earlier.input_tokens),
+            output_tokens: self.output_tokens.saturating_sub(earlier.output_tokens),
+        }
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_llm_price() {
+        assert_eq!(llm_price("openai", "gpt-4o-mini-2024-07-18").unwrap().input, 0.15);
+        assert_eq!(llm_price("openai", "gpt-4o").unwrap().output, 10.0);
+        assert_eq!(llm_price("anthropic", "claude-3-5-sonnet-latest").unwrap().input, 3.0);
+        assert_eq!(llm_price("anthropic", "claude-3-5-haiku-latest").unwrap().input, 0.8);
+        assert_eq!(llm_price("ollama", "qwen3:8b"), Some(LlmPrice::FREE));
+        assert_eq!(llm_price("openai", "my-finetune"), None);
+    }
+
+    #[test]
+    fn test_usage_cost() {
+        let mut usage = TokenUsage { input_tokens: 1_000_000, output_tokens: 0 };
+        let snapshot = usage;
+        usage += TokenUsage { input_tokens: 2000, output_tokens: 500 };
+
+        let delta = usage.since(&snapshot);
+        assert_eq!(delta, TokenUsage { input_tokens: 2000, output_tokens: 500 });
+        let price = llm_price("openai", "gpt-4o-mini").unwrap();
+        assert!((price.cost(&delta) - 0.0006).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,252 @@
+//! PII redaction - scrub prompts before they reach a remote LLM
+//!
+//! Emails, phone numbers, and person names in a prompt are replaced with
+//! numbered placeholders (`[EMAIL_1]`, `[NAME_2]`). The same value always gets
+//! the same placeholder within a prompt, so the model can still tell people
+//! apart, and placeholders in the response are mapped back to the original
+//! text before it is shown.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Kind of personal information to redact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PiiKind {
+    Email,
+    Phone,
+    Name,
+}
+
+impl PiiKind {
+    /// Placeholder label, e.g. `EMAIL` in `[EMAIL_1]`
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Email => "EMAIL",
+            Self::Phone => "PHONE",
+            Self::Name => "NAME",
+        }
+    }
+}
+
+impl std::str::FromStr for PiiKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "email" | "emails" => Ok(Self::Email),
+            "phone" | "phones" => Ok(Self::Phone),
+            "name" | "names" => Ok(Self::Name),
+            _ => Err(format!("Unknown redaction kind: {} (expected emails, phones, or names)", s)),
+        }
+    }
+}
+
+static EMAIL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b").unwrap()
+});
+
+/// International (`+44 20 7946 0958`) and grouped (`(555) 123-4567`) numbers
+static PHONE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{2,4}\)[\s.-]?|\d{2,4}[\s.-])\d{3,4}[\s.-]\d{3,4}\b").unwrap()
+});
+
+/// Titled names (`Dr. Jane Doe`) and common given names followed by a surname
+static NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    let given = GIVEN_NAMES.join("|");
+    Regex::new(&format!(
+        r"\b(?:(?:Mr|Mrs|Ms|Miss|Dr|Prof)\.?\s+[A-Z][a-z]+(?:\s+[A-Z][a-z]+)?|(?:{})\s+[A-Z][a-z]+(?:-[A-Z][a-z]+)?)\b",
+        given
+    ))
+    .unwrap()
+});
+
+/// Common given names, matched only when followed by a capitalized surname
+const GIVEN_NAMES: &[&str] = &[
+    "Aaron", "Adam", "Alex", "Alice", "Amanda", "Amy", "Andrew", "Anna", "Anne", "Anthony", "Ben", "Benjamin",
+    "Brian", "Carlos", "Carol", "Charles", "Chris", "Christopher", "Daniel", "David", "Deborah", "Elizabeth",
+    "Emily", "Emma", "Eric", "Frank", "Gary", "George", "Hannah", "Helen", "Jack", "James", "Jane", "Jason",
+    "Jennifer", "Jessica", "John", "Jonathan", "Joseph", "Joshua", "Julia", "Karen", "Kevin", "Laura", "Linda",
+    "Lisa", "Maria", "Mark", "Mary", "Matthew", "Michael", "Michelle", "Nancy", "Nicole", "Olivia", "Patricia",
+    "Paul", "Peter", "Rachel", "Rebecca", "Richard", "Robert", "Ryan", "Sarah", "Scott", "Sophia", "Stephen",
+    "Steven", "Susan", "Thomas", "Timothy", "William",
+];
+
+/// Replaces selected kinds of personal information with placeholders
+#[derive(Debug, Clone)]
+pub struct PiiRedactor {
+    kinds: Vec<PiiKind>,
+}
+
+impl PiiRedactor {
+    /// Redactor for a comma-separated list of kinds (e.g. "emails,phones,names")
+    pub fn from_names(names: &str) -> anyhow::Result<Self> {
+        let mut kinds = Vec::new();
+        for name in names.split(',').filter(|n| !n.trim().is_empty()) {
+            let kind: PiiKind = name.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        if kinds.is_empty() {
+            anyhow::bail!("No redaction kinds given (expected emails, phones, or names)");
+        }
+        Ok(Self { kinds })
+    }
+
+    /// Redact `text`, returning the scrubbed text and the placeholder mapping
+    pub fn redact(&self, text: &str) -> Redacted {
+        let mut spans: Vec<(usize, usize, PiiKind)> = Vec::new();
+        for &kind in &self.kinds {
+            let regex = match kind {
+                PiiKind::Email => &*EMAIL_REGEX,
+                PiiKind::Phone => &*PHONE_REGEX,
+                PiiKind::Name => &*NAME_REGEX,
+            };
+            for m in regex.find_iter(text) {
+                if kind == PiiKind::Phone && !is_phone_match(text, m.start(), m.end()) {
+                    continue;
+                }
+                spans.push((m.start(), m.end(), kind));
+            }
+        }
+
+        // Earlier, then longer, matches win
+        spans.sort_by_key(|&(start, end, _)| (start, std::cmp::Reverse(end)));
+
+        let mut redacted = Redacted::default();
+        let mut last = 0;
+        for (start, end, kind) in spans {
+            if start < last {
+                continue;
+            }
+            redacted.text.push_str(&text[last..start]);
+            let placeholder = redacted.placeholder(kind, &text[start..end]);
+            redacted.text.push_str(&placeholder);
+            last = end;
+        }
+        redacted.text.push_str(&text[last..]);
+        redacted
+    }
+}
+
+/// Reject digit runs that are part of a longer number, version, or IP address
+fn is_phone_match(text: &str, start: usize, end: usize) -> bool {
+    let before = text[..start].chars().next_back();
+    let mut after = text[end..].chars();
+    let (next, next2) = (after.next(), after.next());
+    let glued_before = before.is_some_and(|c| c.is_alphanumeric() || c == '.' || c == '-');
+    let glued_after = matches!(next, Some('.' | '-')) && next2.is_some_and(|c| c.is_ascii_digit());
+    !glued_before && !glued_after
+}
+
+/// Text with personal information replaced, and the values to restore
+#[derive(Debug, Clone, Default)]
+pub struct Redacted {
+    pub text: String,
+    /// (placeholder, original) pairs in order of first appearance
+    replacements: Vec<(String, String)>,
+}
+
+impl Redacted {
+    /// Placeholder for `original`, reusing the one it already has
+    fn placeholder(&mut self, kind: PiiKind, original: &str) -> String {
+        if let Some((placeholder, _)) = self.replacements.iter().find(|(_, o)| o == original) {
+            return placeholder.clone();
+        }
+        let prefix = format!("[{}_", kind.label());
+        let n = self.replacements.iter().filter(|(p, _)| p.starts_with(&prefix)).count() + 1;
+        let placeholder = format!("{}{}]", prefix, n);
+        self.replacements.push((placeholder.clone(), original.to_string()));
+        placeholder
+    }
+
+    /// Number of values replaced
+    pub fn count(&self) -> usize {
+        self.replacements.len()
+    }
+
+    /// Map placeholders in `text` (e.g. the LLM's answer) back to the originals
+    pub fn restore(&self, text: &str) -> String {
+        // Longest first, so `[NAME_1]` doesn't clobber part of `[NAME_10]`
+        let mut replacements: Vec<&(String, String)> = self.replacements.iter().collect();
+        replacements.sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+        let mut output = text.to_string();
+        for (placeholder, original) in replacements {
+            output = output.replace(placeholder.as_str(), original);
+        }
+        output
+    }
+
+    /// Restore a streamed chunk, holding back a trailing placeholder that may
+    /// be split across chunks until the rest of it arrives
+    ///
+    /// `pending` carries held-back text between calls; flush it with `restore`
+    /// once the stream ends.
+    pub fn restore_chunk(&self, pending: &mut String, chunk: &str) -> String {
+        pending.push_str(chunk);
+        let hold_from = match pending.rfind('[') {
+            Some(i) if !pending[i..].contains(']') && pending.len() - i <= MAX_PLACEHOLDER_LEN => i,
+            _ => pending.len(),
+        };
+        let ready: String = pending.drain(..hold_from).collect();
+        self.restore(&ready)
+    }
+}
+
+/// Longest placeholder held back while streaming (`[EMAIL_9999]`)
+const MAX_PLACEHOLDER_LEN: usize = 12;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_and_restore() {
+        let redactor = PiiRedactor::from_names("emails,phones,names").unwrap();
+        let text = "Contact Jane Doe (jane.doe@example.com, +1 555-123-4567). \
+                    Jane Doe reviews with Dr. Patel; cc bob@corp.io.";
+        let redacted = redactor.redact(text);
+
+        assert_eq!(
+            redacted.text,
+            "Contact [NAME_1] ([EMAIL_1], [PHONE_1]). [NAME_1] reviews with [NAME_2]; cc [EMAIL_2]."
+        );
+        assert_eq!(redacted.count(), 5);
+        assert_eq!(redacted.restore(&redacted.text), text);
+        assert_eq!(redacted.restore("Ask [NAME_2] at [EMAIL_2]"), "Ask Dr. Patel at bob@corp.io");
+    }
+
+    #[test]
+    fn test_redact_only_selected_kinds() {
+        let redactor = PiiRedactor::from_names("emails").unwrap();
+        let redacted = redactor.redact("John Smith <john@example.com>");
+        assert_eq!(redacted.text, "John Smith <[EMAIL_1]>");
+
+        assert!(PiiRedactor::from_names("emails,ssn").is_err());
+        assert!(PiiRedactor::from_names("").is_err());
+    }
+
+    #[test]
+    fn test_phone_false_positives() {
+        let redactor = PiiRedactor::from_names("phones").unwrap();
+        for text in ["released 2024-01-15", "host 192.168.100.200", "version 1.2.3", "id 123456789012"] {
+            assert_eq!(redactor.redact(text).text, text);
+        }
+        assert_eq!(redactor.redact("call (555) 123-4567").text, "call [PHONE_1]");
+        assert_eq!(redactor.redact("tel +44 20 7946 0958").text, "tel [PHONE_1]");
+    }
+
+    #[test]
+    fn test_restore_chunk() {
+        let redactor = PiiRedactor::from_names("emails").unwrap();
+        let redacted = redactor.redact("a@b.io");
+
+        let mut pending = String::new();
+        let mut output = redacted.restore_chunk(&mut pending, "Write to [EMA");
+        assert_eq!(output, "Write to ");
+        output.push_str(&redacted.restore_chunk(&mut pending, "IL_1] today [sic"));
+        output.push_str(&redacted.restore(&pending));
+        assert_eq!(output, "Write to a@b.io today [sic");
+    }
+}
@@ -2,6 +2,11 @@
 //!
 //! Returns canned responses without requiring external API calls.
 
+use serde_json::Value;
+
+use super::structured::{json_in_text, SCHEMA_MARKER};
+use super::GenerationOptions;
+
 /// Simulated LLM provider for testing
 pub struct SimulatedLlm {
     model_name: String,
@@ -14,7 +19,14 @@ impl SimulatedLlm {
     }
 
     /// Generate a simulated response
-    pub async fn generate(&self, prompt: &str) -> anyhow::Result<String> {
+    pub async fn generate(&self, prompt: &str, _options: &GenerationOptions) -> anyhow::Result<String> {
+        // Asked for JSON: the simplest value the schema allows
+        if let Some((_, schema)) = prompt.split_once(SCHEMA_MARKER) {
+            if let Some(schema) = json_in_text(schema) {
+                return Ok(sample_value(&schema).to_string());
+            }
+        }
+
         // Extract the question from the prompt if present
         let question = if prompt.contains("Question:") {
             prompt
@@ -55,3 +67,38 @@ impl SimulatedLlm {
         Ok(response)
     }
 }
+
+/// A value conforming to the common keywords of `schema`: the first enum
+/// value, every property, and as many array items as required (at least one)
+fn sample_value(schema: &Value) -> Value {
+    if let Some(value) = schema.get("const").or_else(|| schema.get("enum").and_then(|e| e.get(0))) {
+        return value.clone();
+    }
+    if let Some(first) = ["anyOf", "oneOf"].iter().find_map(|k| schema.get(*k).and_then(|s| s.get(0))) {
+        return sample_value(first);
+    }
+    let kind = match schema.get("type") {
+        Some(Value::Array(kinds)) => kinds.first().and_then(Value::as_str),
+        Some(kind) => kind.as_str(),
+        None if schema.get("properties").is_some() => Some("object"),
+        None if schema.get("items").is_some() => Some("array"),
+        None => None,
+    };
+    match kind {
+        Some("object") => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            Value::Object(properties.into_iter().flatten().map(|(k, v)| (k.clone(), sample_value(v))).collect())
+        }
+        Some("array") => {
+            let count = schema.get("minItems").and_then(Value::as_u64).unwrap_or(1).max(1);
+            let item = schema.get("items").map(sample_value).unwrap_or(Value::Null);
+            Value::Array(vec![item; count as usize])
+        }
+        Some("string") => Value::from("simulated"),
+        Some("integer") | Some("number") => {
+            Value::from(schema.get("minimum").and_then(Value::as_i64).unwrap_or(0))
+        }
+        Some("boolean") => Value::Bool(false),
+        _ => Value::Null,
+    }
+}
@@ -0,0 +1,223 @@
+//! Structured output - answers as JSON conforming to a user-supplied schema,
+//! for extraction (`ask --schema`)
+//!
+//! Providers with native tool calling are made to call a `respond` tool whose
+//! arguments follow the schema; the others are asked for JSON in the prompt.
+//! Either way the reply is validated, and rejected replies are sent back with
+//! the validation errors for another try.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::{ChatMessage, LlmProvider, Tool, ToolChoice};
+
+/// Introduces the schema in prompts asking for JSON; the schema follows it
+pub(super) const SCHEMA_MARKER: &str = "Reply with only a JSON value conforming to this JSON Schema:";
+
+/// Replies tried before giving up
+const MAX_ATTEMPTS: usize = 3;
+
+/// Tool the model answers through
+const RESPOND_TOOL: &str = "respond";
+
+/// Argument holding the answer when the schema's root is not an object
+/// (tool arguments always are)
+const WRAPPED_FIELD: &str = "value";
+
+/// A JSON schema answers must conform to
+pub struct OutputSchema {
+    schema: Value,
+    validator: jsonschema::Validator,
+}
+
+impl OutputSchema {
+    /// Read a schema from a JSON file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read schema {}: {}", path.display(), e))?;
+        let schema = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Schema {} is not valid JSON: {}", path.display(), e))?;
+        Self::new(schema)
+    }
+
+    pub fn new(schema: Value) -> anyhow::Result<Self> {
+        let validator = jsonschema::validator_for(&schema).map_err(|e| anyhow::anyhow!("Invalid JSON schema: {}", e))?;
+        Ok(Self { schema, validator })
+    }
+
+    /// Whether answers go in a `value` argument of the tool call
+    fn wrapped(&self) -> bool {
+        self.schema.get("type").and_then(Value::as_str) != Some("object")
+    }
+
+    fn tool(&self) -> Tool {
+        let parameters = if self.wrapped() {
+            serde_json::json!({
+                "type": "object",
+                "properties": { WRAPPED_FIELD: self.schema },
+                "required": [WRAPPED_FIELD],
+            })
+        } else {
+            self.schema.clone()
+        };
+        Tool {
+            name: RESPOND_TOOL,
+            description: "Give the answer. Its arguments are the answer.",
+            parameters,
+        }
+    }
+
+    /// `value` if it conforms, else its validation errors
+    fn check(&self, value: Value) -> Result<Value, String> {
+        let errors: Vec<String> = self
+            .validator
+            .iter_errors(&value)
+            .map(|e| match e.instance_path.as_str() {
+                "" => e.to_string(),
+                path => format!("{}: {}", path, e),
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+
+/// Generate an answer to `prompt` conforming to `schema`
+pub async fn generate_structured(llm: &LlmProvider, prompt: &str, schema: &OutputSchema) -> anyhow::Result<Value> {
+    if llm.supports_tools() {
+        if let Some(value) = generate_with_tool(llm, prompt, schema).await? {
+            return Ok(value);
+        }
+    }
+    generate_in_text(llm, prompt, schema).await
+}
+
+/// Answer through the `respond` tool; `None` if the model has no tool calling
+async fn generate_with_tool(llm: &LlmProvider, prompt: &str, schema: &OutputSchema) -> anyhow::Result<Option<Value>> {
+    let tools = [schema.tool()];
+    let mut conversation = vec![ChatMessage::User(format!(
+        "{}\n\nGive your answer by calling the `{}` tool.",
+        prompt, RESPOND_TOOL
+    ))];
+    let mut errors = String::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let Some(response) = llm.generate_with_tools(&conversation, &tools, ToolChoice::Required).await? else {
+            return Ok(None);
+        };
+        match response.calls.into_iter().find(|c| c.name == RESPOND_TOOL) {
+            Some(call) => {
+                let mut arguments = call.arguments.clone();
+                let answer = if schema.wrapped() { arguments[WRAPPED_FIELD].take() } else { arguments };
+                match schema.check(answer) {
+                    Ok(value) => return Ok(Some(value)),
+                    Err(e) => errors = e,
+                }
+                conversation.push(ChatMessage::Assistant { text: response.text, calls: vec![call.clone()] });
+                conversation.push(ChatMessage::ToolResult {
+                    call_id: call.id,
+                    name: call.name,
+                    content: format!("Rejected, the arguments don't match the schema: {}. Call `{}` again.", errors, RESPOND_TOOL),
+                });
+            }
+            // Ollama can't be made to call the tool; its text may still be the JSON
+            None => {
+                match json_in_text(&response.text).ok_or_else(|| "no JSON found".to_string()).and_then(|v| schema.check(v)) {
+                    Ok(value) => return Ok(Some(value)),
+                    Err(e) => errors = e,
+                }
+                conversation.push(ChatMessage::Assistant { text: response.text, calls: Vec::new() });
+                conversation.push(ChatMessage::User(format!(
+                    "That doesn't answer through the `{}` tool with arguments matching the schema ({}). Call it now.",
+                    RESPOND_TOOL, errors
+                )));
+            }
+        }
+    }
+    anyhow::bail!("No answer matching the schema after {} attempts: {}", MAX_ATTEMPTS, errors)
+}
+
+/// Answer with JSON in the reply text
+async fn generate_in_text(llm: &LlmProvider, prompt: &str, schema: &OutputSchema) -> anyhow::Result<Value> {
+    let request = format!("{}\n\n{}\n{}", prompt, SCHEMA_MARKER, serde_json::to_string_pretty(&schema.schema)?);
+    let mut next_prompt = request.clone();
+    let mut errors = String::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let reply = llm.generate(&next_prompt).await?;
+        match json_in_text(&reply).ok_or_else(|| "no JSON found".to_string()).and_then(|v| schema.check(v)) {
+            Ok(value) => return Ok(value),
+            Err(e) => errors = e,
+        }
+        next_prompt = format!(
+            "{}\n\nYour previous reply:\n{}\n\nIt was rejected: {}\nReply again with only the corrected JSON value.",
+            request,
+            reply.trim(),
+            errors
+        );
+    }
+    anyhow::bail!("No answer matching the schema after {} attempts: {}", MAX_ATTEMPTS, errors)
+}
+
+/// The first JSON object or array in `text`, which may wrap it in prose or a
+/// code fence
+pub(super) fn json_in_text(text: &str) -> Option<Value> {
+    if let Ok(value) = serde_json::from_str(text.trim()) {
+        return Some(value);
+    }
+    text.match_indices(['{', '['])
+        .find_map(|(start, _)| serde_json::Deserializer::from_str(&text[start..]).into_iter().next()?.ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_in_text() {
+        assert_eq!(json_in_text(" 42 "), Some(serde_json::json!(42)));
+        let fenced = "Here it is:\n```json\n{\"paths\": [\"/users\"]}\n```\nDone.";
+        assert_eq!(json_in_text(fenced), Some(serde_json::json!({ "paths": ["/users"] })));
+        assert_eq!(json_in_text("[not json] but [1, 2]"), Some(serde_json::json!([1, 2])));
+        assert_eq!(json_in_text("no json here"), None);
+    }
+
+    #[test]
+    fn test_output_schema_check() {
+        let schema = OutputSchema::new(serde_json::json!({
+            "type": "object",
+            "properties": { "endpoints": { "type": "array", "items": { "type": "string" } } },
+            "required": ["endpoints"],
+        }))
+        .unwrap();
+        assert!(!schema.wrapped());
+        assert!(schema.check(serde_json::json!({ "endpoints": ["/users"] })).is_ok());
+        let errors = schema.check(serde_json::json!({ "endpoints": [1] })).unwrap_err();
+        assert!(errors.starts_with("/endpoints/0: "), "{}", errors);
+        assert!(schema.check(serde_json::json!({})).is_err());
+
+        let list = OutputSchema::new(serde_json::json!({ "type": "array" })).unwrap();
+        assert!(list.wrapped());
+        assert_eq!(list.tool().parameters["required"], serde_json::json!(["value"]));
+        assert!(OutputSchema::new(serde_json::json!({ "type": 5 })).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_generate_structured_in_text() {
+        let llm = LlmProvider::new("sim".to_string(), super::super::LlmType::Simulated).unwrap();
+        let schema = OutputSchema::new(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "method": { "enum": ["GET", "POST"] },
+                "paths": { "type": "array", "items": { "type": "string" }, "minItems": 1 },
+            },
+            "required": ["method", "paths"],
+        }))
+        .unwrap();
+        let value = generate_structured(&llm, "List the endpoints.", &schema).await.unwrap();
+        assert_eq!(value["method"], "GET");
+        assert_eq!(value["paths"].as_array().map(Vec::len), Some(1));
+    }
+}
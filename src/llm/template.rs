@@ -0,0 +1,358 @@
+//! Prompt templates - a small Jinja-style subset for `ask` and `react` prompts
+//!
+//! Supported syntax:
+//! - `{{ name }}` substitutes a variable (missing variables render empty)
+//! - `{% if name %}...{% else %}...{% endif %}` branches on a non-empty
+//!   variable; `{% if not name %}` negates
+//! - `{# ... #}` comments
+//! - `{%-`, `-%}`, `{{-`, `-}}` trim whitespace before/after a tag
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Built-in template for `leann ask`
+pub const DEFAULT_ASK_TEMPLATE: &str = r#"{% if history %}Previous conversation:
+
+{{ history }}
+
+{% endif %}Here is some retrieved context that might help answer your question:
+
+{{ context }}
+
+Question: {{ question }}
+
+Please provide the best answer you can based on this context and your knowledge."#;
+
+/// Built-in template for `leann react` (the start of the agent transcript)
+pub const DEFAULT_REACT_TEMPLATE: &str = r#"You are a helpful assistant that answers questions using available tools.
+
+Available tools:
+1. search(query) - Search the knowledge base for relevant information
+2. finish(answer) - Provide the final answer
+
+{% if tool_calling %}For each step, think about what to do next, then call one tool. After a search,
+you'll get its results.
+{% else %}For each step, use the following format:
+Thought: [Your reasoning about what to do next]
+Action: [tool_name(argument)]
+
+After getting search results, you'll see:
+Observation: [results from the tool]
+{% endif %}
+Continue until you have enough information, then use finish(answer) to provide your final answer.
+
+Important:
+- Always search for relevant information before answering
+- If the first search doesn't give enough info, try different search queries
+- Be concise in your final answer
+
+
+{% if history %}Earlier in this session:
+
+{{ history }}
+
+{% endif %}Question: {{ question }}
+
+Let me search for relevant information.
+"#;
+
+/// Variables available to prompt templates
+pub const TEMPLATE_VARIABLES: &[&str] = &["context", "question", "history", "tool_calling"];
+
+/// A parsed prompt template
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Var(String),
+    If {
+        name: String,
+        negated: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Var(String),
+    If(String, bool),
+    Else,
+    EndIf,
+}
+
+impl PromptTemplate {
+    /// Parse a template, rejecting malformed tags and unbalanced blocks
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let mut tokens = tokenize(source)?.into_iter();
+        let (nodes, end) = parse_block(&mut tokens)?;
+        match end {
+            None => Ok(Self { nodes }),
+            Some(Token::Else) => anyhow::bail!("{{% else %}} without matching {{% if %}}"),
+            Some(_) => anyhow::bail!("{{% endif %}} without matching {{% if %}}"),
+        }
+    }
+
+    /// Read and parse a template file
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read prompt template {}: {}", path.display(), e))?;
+        Self::parse(&source)
+            .map_err(|e| anyhow::anyhow!("Invalid prompt template {}: {}", path.display(), e))
+    }
+
+    /// Pick the template to use: CLI file, then per-index default, then
+    /// config file, then the built-in template
+    pub fn resolve(
+        cli_path: Option<&Path>,
+        index_template: Option<&str>,
+        config_path: Option<&Path>,
+        default: &str,
+    ) -> anyhow::Result<Self> {
+        let template = if let Some(path) = cli_path {
+            Self::from_file(path)?
+        } else if let Some(source) = index_template {
+            Self::parse(source)
+                .map_err(|e| anyhow::anyhow!("Invalid prompt template in index metadata: {}", e))?
+        } else if let Some(path) = config_path {
+            Self::from_file(path)?
+        } else {
+            Self::parse(default)?
+        };
+        template.check_variables()?;
+        Ok(template)
+    }
+
+    /// Fail on variables other than `TEMPLATE_VARIABLES` (usually typos)
+    pub fn check_variables(&self) -> anyhow::Result<()> {
+        let mut names = Vec::new();
+        collect_names(&self.nodes, &mut names);
+        for name in names {
+            if !TEMPLATE_VARIABLES.contains(&name) {
+                anyhow::bail!(
+                    "Unknown template variable '{}' (available: {})",
+                    name,
+                    TEMPLATE_VARIABLES.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Render with the given variables
+    pub fn render(&self, vars: &HashMap<&str, String>) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, vars, &mut out);
+        out
+    }
+}
+
+fn tokenize(source: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    let mut trim_next = false;
+
+    loop {
+        let open = ["{{", "{%", "{#"]
+            .iter()
+            .filter_map(|tag| rest.find(tag))
+            .min();
+
+        let Some(open) = open else {
+            push_text(&mut tokens, rest, trim_next, false);
+            return Ok(tokens);
+        };
+
+        let kind = &rest[open..open + 2];
+        let close = match kind {
+            "{{" => "}}",
+            "{%" => "%}",
+            _ => "#}",
+        };
+        let after_open = &rest[open + 2..];
+        let close_at = after_open
+            .find(close)
+            .ok_or_else(|| anyhow::anyhow!("Unclosed '{}' tag", kind))?;
+
+        let mut inner = &after_open[..close_at];
+        let trim_before = inner.starts_with('-');
+        if trim_before {
+            inner = &inner[1..];
+        }
+        let trim_after = inner.ends_with('-');
+        if trim_after {
+            inner = &inner[..inner.len() - 1];
+        }
+
+        push_text(&mut tokens, &rest[..open], trim_next, trim_before);
+        trim_next = trim_after;
+        rest = &after_open[close_at + 2..];
+
+        let inner = inner.trim();
+        match kind {
+            "{{" => tokens.push(Token::Var(identifier(inner)?)),
+            "{%" => {
+                let words: Vec<&str> = inner.split_whitespace().collect();
+                tokens.push(match words.as_slice() {
+                    ["if", "not", name] => Token::If(identifier(name)?, true),
+                    ["if", name] => Token::If(identifier(name)?, false),
+                    ["else"] => Token::Else,
+                    ["endif"] => Token::EndIf,
+                    _ => anyhow::bail!("Unsupported tag '{{% {} %}}'", inner),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn push_text(tokens: &mut Vec<Token>, text: &str, trim_start: bool, trim_end: bool) {
+    let text = if trim_start { text.trim_start() } else { text };
+    let text = if trim_end { text.trim_end() } else { text };
+    if !text.is_empty() {
+        tokens.push(Token::Text(text.to_string()));
+    }
+}
+
+fn identifier(name: &str) -> anyhow::Result<String> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !name.starts_with(|c: char| c.is_ascii_digit());
+    if valid {
+        Ok(name.to_string())
+    } else {
+        anyhow::bail!("Invalid variable name '{}'", name)
+    }
+}
+
+/// Parse nodes until `{% else %}`, `{% endif %}`, or the end of input,
+/// returning the token that ended the block
+fn parse_block(
+    tokens: &mut impl Iterator<Item = Token>,
+) -> anyhow::Result<(Vec<Node>, Option<Token>)> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text(text) => nodes.push(Node::Text(text)),
+            Token::Var(name) => nodes.push(Node::Var(name)),
+            Token::If(name, negated) => {
+                let (then, end) = parse_block(tokens)?;
+                let otherwise = match end {
+                    Some(Token::EndIf) => Vec::new(),
+                    Some(Token::Else) => match parse_block(tokens)? {
+                        (otherwise, Some(Token::EndIf)) => otherwise,
+                        _ => anyhow::bail!("Missing {{% endif %}} for {{% if {} %}}", name),
+                    },
+                    _ => anyhow::bail!("Missing {{% endif %}} for {{% if {} %}}", name),
+                };
+                nodes.push(Node::If { name, negated, then, otherwise });
+            }
+            end @ (Token::Else | Token::EndIf) => return Ok((nodes, Some(end))),
+        }
+    }
+    Ok((nodes, None))
+}
+
+fn collect_names<'a>(nodes: &'a [Node], names: &mut Vec<&'a str>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Var(name) => names.push(name),
+            Node::If { name, then, otherwise, .. } => {
+                names.push(name);
+                collect_names(then, names);
+                collect_names(otherwise, names);
+            }
+        }
+    }
+}
+
+fn render_nodes(nodes: &[Node], vars: &HashMap<&str, String>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var(name) => {
+                if let Some(value) = vars.get(name.as_str()) {
+                    out.push_str(value);
+                }
+            }
+            Node::If { name, negated, then, otherwise } => {
+                let set = vars.get(name.as_str()).is_some_and(|v| !v.trim().is_empty());
+                if set != *negated {
+                    render_nodes(then, vars, out);
+                } else {
+                    render_nodes(otherwise, vars, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&'static str, &str)]) -> HashMap<&'static str, String> {
+        pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render_variables_and_conditionals() {
+        let template = PromptTemplate::parse(
+            "{# note #}{% if history %}H: {{history}}\n{% else %}(new){% endif %}Q: {{ question }}",
+        )
+        .unwrap();
+        assert_eq!(template.render(&vars(&[("question", "why?")])), "(new)Q: why?");
+        assert_eq!(
+            template.render(&vars(&[("question", "why?"), ("history", "earlier")])),
+            "H: earlier\nQ: why?"
+        );
+
+        let negated = PromptTemplate::parse("{% if not context %}none{% endif %}").unwrap();
+        assert_eq!(negated.render(&vars(&[])), "none");
+        assert_eq!(negated.render(&vars(&[("context", "x")])), "");
+    }
+
+    #[test]
+    fn test_whitespace_trimming() {
+        let template = PromptTemplate::parse("A\n  {%- if question -%}\n  {{ question }}\n{%- endif %}").unwrap();
+        assert_eq!(template.render(&vars(&[("question", "q")])), "Aq");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(PromptTemplate::parse("{{ question").is_err());
+        assert!(PromptTemplate::parse("{% if question %}open").is_err());
+        assert!(PromptTemplate::parse("{% endif %}").is_err());
+        assert!(PromptTemplate::parse("{% for x in y %}{% endfor %}").is_err());
+        assert!(PromptTemplate::parse("{{ bad name }}").is_err());
+
+        let typo = PromptTemplate::parse("{{ qeustion }}").unwrap();
+        assert!(typo.check_variables().is_err());
+    }
+
+    #[test]
+    fn test_default_templates() {
+        let ask = PromptTemplate::parse(DEFAULT_ASK_TEMPLATE).unwrap();
+        ask.check_variables().unwrap();
+        let prompt = ask.render(&vars(&[("context", "[1] text"), ("question", "q")]));
+        assert!(prompt.starts_with("Here is some retrieved context"));
+        assert!(prompt.contains("[1] text\n\nQuestion: q\n"));
+
+        let react = PromptTemplate::parse(DEFAULT_REACT_TEMPLATE).unwrap();
+        assert!(react.render(&vars(&[("question", "q")])).contains("\n\nQuestion: q\n\n"));
+        let resumed = react.render(&vars(&[("question", "q"), ("history", "User: p\nAssistant: a")]));
+        assert!(resumed.contains("\n\nEarlier in this session:\n\nUser: p\nAssistant: a\n\nQuestion: q\n"));
+        react.check_variables().unwrap();
+        let text = react.render(&vars(&[("question", "q")]));
+        assert!(text.contains("Action: [tool_name(argument)]\n\nAfter getting search results"));
+        let native = react.render(&vars(&[("question", "q"), ("tool_calling", "true")]));
+        assert!(native.contains("call one tool. After a search,\nyou'll get its results.\n\nContinue until"));
+        assert!(!native.contains("Observation:"));
+    }
+}
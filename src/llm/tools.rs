@@ -0,0 +1,170 @@
+//! Native tool calling - conversations where the model calls functions
+//! through the provider's tool API (OpenAI tools, Anthropic `tool_use`,
+//! Ollama tools) instead of writing the calls into its text
+
+use serde_json::Value;
+
+use super::redact::{PiiRedactor, Redacted};
+
+/// A function the model may call
+#[derive(Debug, Clone)]
+pub struct Tool {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// JSON Schema of the arguments object
+    pub parameters: Value,
+}
+
+/// Whether the model may call tools in a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolChoice {
+    Auto,
+    /// Text only; the tools are still sent so earlier calls stay valid
+    None,
+    /// Some tool must be called (Ollama can't be made to, and may reply with text)
+    Required,
+}
+
+/// A function call made by the model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    /// Provider-assigned id, echoed back with the result
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// A message of a tool-using conversation
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatMessage {
+    User(String),
+    Assistant { text: String, calls: Vec<ToolCall> },
+    ToolResult { call_id: String, name: String, content: String },
+}
+
+/// The model's reply: text, tool calls, or both
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolResponse {
+    pub text: String,
+    pub calls: Vec<ToolCall>,
+}
+
+/// Separates the texts of a conversation while it is redacted as one
+const SEPARATOR: &str = "\n\u{1e}\n";
+
+impl ChatMessage {
+    /// The texts the model reads, call arguments as JSON
+    fn texts(&self) -> Vec<String> {
+        match self {
+            ChatMessage::User(text) | ChatMessage::ToolResult { content: text, .. } => vec![text.clone()],
+            ChatMessage::Assistant { text, calls } => {
+                std::iter::once(text.clone()).chain(calls.iter().map(|c| c.arguments.to_string())).collect()
+            }
+        }
+    }
+
+    /// This message with its texts taken from `texts`, in `texts()` order
+    fn with_texts(&self, texts: &mut impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut next = || texts.next().ok_or_else(|| anyhow::anyhow!("Redacted conversation lost a message"));
+        Ok(match self {
+            ChatMessage::User(_) => ChatMessage::User(next()?),
+            ChatMessage::ToolResult { call_id, name, .. } => {
+                ChatMessage::ToolResult { call_id: call_id.clone(), name: name.clone(), content: next()? }
+            }
+            ChatMessage::Assistant { calls, .. } => {
+                let text = next()?;
+                let calls = calls
+                    .iter()
+                    .map(|call| Ok(ToolCall { arguments: serde_json::from_str(&next()?)?, ..call.clone() }))
+                    .collect::<anyhow::Result<_>>()?;
+                ChatMessage::Assistant { text, calls }
+            }
+        })
+    }
+}
+
+impl ToolResponse {
+    /// The reply with redaction placeholders mapped back to the originals
+    pub(super) fn restore(self, redacted: &Redacted) -> Self {
+        let calls = self
+            .calls
+            .into_iter()
+            .map(|call| {
+                let restored = serde_json::from_str(&redacted.restore(&call.arguments.to_string()));
+                ToolCall { arguments: restored.unwrap_or(call.arguments), ..call }
+            })
+            .collect();
+        Self { text: redacted.restore(&self.text), calls }
+    }
+
+    /// Everything the model generated, for token counting
+    pub(super) fn generated_text(&self) -> String {
+        let mut text = self.text.clone();
+        for call in &self.calls {
+            text.push_str(&call.arguments.to_string());
+        }
+        text
+    }
+}
+
+/// Everything sent to the model, for token counting
+pub(super) fn prompt_text(messages: &[ChatMessage], tools: &[Tool]) -> String {
+    let mut text: Vec<String> = messages.iter().flat_map(ChatMessage::texts).collect();
+    text.extend(tools.iter().map(|t| format!("{} {} {}", t.name, t.description, t.parameters)));
+    text.join("\n")
+}
+
+/// Redact a whole conversation with one placeholder mapping, so a value gets
+/// the same placeholder in every message
+pub(super) fn redact_messages(
+    redactor: &PiiRedactor,
+    messages: &[ChatMessage],
+) -> anyhow::Result<(Vec<ChatMessage>, Redacted)> {
+    let joined = messages.iter().flat_map(ChatMessage::texts).collect::<Vec<_>>().join(SEPARATOR);
+    let redacted = redactor.redact(&joined);
+    let mut texts = redacted.text.split(SEPARATOR).map(String::from);
+    let messages = messages.iter().map(|m| m.with_texts(&mut texts)).collect::<anyhow::Result<_>>()?;
+    Ok((messages, redacted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_messages_shares_placeholders() {
+        let redactor = PiiRedactor::from_names("emails").unwrap();
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({ "query": "mail from ann@example.com" }),
+        };
+        let messages = vec![
+            ChatMessage::User("Who is ann@example.com?".to_string()),
+            ChatMessage::Assistant { text: String::new(), calls: vec![call] },
+            ChatMessage::ToolResult {
+                call_id: "call_1".to_string(),
+                name: "search".to_string(),
+                content: "ann@example.com wrote to bob@example.com".to_string(),
+            },
+        ];
+
+        let (sent, redacted) = redact_messages(&redactor, &messages).unwrap();
+        assert_eq!(sent[0], ChatMessage::User("Who is [EMAIL_1]?".to_string()));
+        let ChatMessage::Assistant { calls, .. } = &sent[1] else { panic!("expected an assistant message") };
+        assert_eq!(calls[0].arguments["query"], "mail from [EMAIL_1]");
+        assert!(matches!(&sent[2], ChatMessage::ToolResult { content, .. } if content == "[EMAIL_1] wrote to [EMAIL_2]"));
+
+        let reply = ToolResponse {
+            text: "[EMAIL_2] replied".to_string(),
+            calls: vec![ToolCall {
+                id: "call_2".to_string(),
+                name: "search".to_string(),
+                arguments: serde_json::json!({ "query": "[EMAIL_2]" }),
+            }],
+        };
+        let restored = reply.restore(&redacted);
+        assert_eq!(restored.text, "bob@example.com replied");
+        assert_eq!(restored.calls[0].arguments["query"], "bob@example.com");
+    }
+}
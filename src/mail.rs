@@ -0,0 +1,474 @@
+//! Email import (mbox, Maildir, .eml) for `--source mail`
+//!
+//! Every message becomes one passage (split later if it exceeds the model's
+//! input limit) with `from`, `to`, `date`, `subject`, `message_id`, and
+//! `thread_id` metadata. The thread ID is the first message of the
+//! `References` chain, so `--group-by thread_id` returns one hit per thread.
+
+use std::path::{Path, PathBuf};
+
+use crate::chunker::Chunk;
+
+/// A parsed email message
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Message {
+    from: Option<String>,
+    to: Option<String>,
+    cc: Option<String>,
+    subject: Option<String>,
+    /// RFC 3339 date, if the `Date` header parses
+    date: Option<String>,
+    message_id: Option<String>,
+    thread_id: Option<String>,
+    body: String,
+}
+
+/// Find mail files under the given paths: `.mbox` and `.eml` files, and
+/// messages in Maildir `cur`/`new` folders
+pub fn collect_mail_files(paths: &[PathBuf], include_hidden: bool, max_files: Option<usize>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        let walker = ignore::WalkBuilder::new(path)
+            .hidden(!include_hidden)
+            .git_ignore(false)
+            .build();
+        for entry in walker.flatten() {
+            if max_files.is_some_and(|max| files.len() >= max) {
+                return files;
+            }
+            let path = entry.path();
+            if path.is_file() && is_mail_file(path) {
+                files.push(path.to_path_buf());
+            }
+        }
+    }
+    files
+}
+
+fn is_mail_file(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    if ext == "mbox" || ext == "eml" {
+        return true;
+    }
+    // Maildir: <folder>/{cur,new,tmp}/<message>
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    matches!(parent.file_name().and_then(|n| n.to_str()), Some("cur" | "new"))
+        && parent.parent().is_some_and(|folder| folder.join("cur").is_dir() && folder.join("new").is_dir())
+}
+
+/// Parse a mail file into one passage per message
+pub fn chunk_file(path: &Path, content: &str, chunk_id: &mut u64) -> Vec<Chunk> {
+    let mbox = is_mbox(path, content);
+    parse_file(path, content)
+        .iter()
+        .enumerate()
+        .map(|(i, message)| message_chunk(message, path, mbox.then_some(i), chunk_id))
+        .collect()
+}
+
+fn is_mbox(path: &Path, content: &str) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("mbox")) || content.starts_with("From ")
+}
+
+/// Parse a mail file: an mbox holds many messages, anything else is one message
+fn parse_file(path: &Path, content: &str) -> Vec<Message> {
+    let content = content.replace("\r\n", "\n");
+    if is_mbox(path, &content) {
+        split_mbox(&content).iter().map(|raw| parse_message(raw)).collect()
+    } else {
+        vec![parse_message(&content)]
+    }
+}
+
+/// Split an mbox into raw messages at `From ` separator lines, undoing `>From ` quoting
+fn split_mbox(content: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current: Option<String> = None;
+    let mut previous_blank = true;
+
+    for line in content.split_inclusive('\n') {
+        if previous_blank && line.starts_with("From ") {
+            messages.extend(current.take());
+            current = Some(String::new());
+        } else if let Some(message) = current.as_mut() {
+            let unquoted = line
+                .strip_prefix('>')
+                .filter(|rest| rest.trim_start_matches('>').starts_with("From "))
+                .unwrap_or(line);
+            message.push_str(unquoted);
+        }
+        previous_blank = line.trim().is_empty();
+    }
+    messages.extend(current);
+    messages
+}
+
+/// Parse one raw RFC 5322 message
+fn parse_message(raw: &str) -> Message {
+    let (headers, body) = split_headers(raw);
+    let header = |name: &str| header_value(&headers, name).map(decode_words);
+
+    let message_id = header_value(&headers, "message-id").and_then(|v| first_message_id(&v));
+    let thread_id = header_value(&headers, "references")
+        .and_then(|v| first_message_id(&v))
+        .or_else(|| header_value(&headers, "in-reply-to").and_then(|v| first_message_id(&v)))
+        .or_else(|| message_id.clone());
+
+    Message {
+        from: header("from"),
+        to: header("to"),
+        cc: header("cc"),
+        subject: header("subject"),
+        date: header_value(&headers, "date")
+            .and_then(|d| crate::time::parse_rfc2822(&d))
+            .map(crate::time::format_rfc3339),
+        message_id,
+        thread_id,
+        body: decode_body(&headers, body).trim().to_string(),
+    }
+}
+
+/// Build the passage for a message
+///
+/// The text starts with the subject and sender so they take part in semantic
+/// search; `index` is the position of the message within an mbox.
+fn message_chunk(message: &Message, source: &Path, index: Option<usize>, chunk_id: &mut u64) -> Chunk {
+    let mut text = String::new();
+    for (name, value) in [("Subject", &message.subject), ("From", &message.from), ("To", &message.to), ("Date", &message.date)] {
+        if let Some(value) = value {
+            text.push_str(&format!("{}: {}\n", name, value));
+        }
+    }
+    text.push('\n');
+    text.push_str(&message.body);
+
+    let mut metadata = serde_json::json!({
+        "source": source.to_string_lossy(),
+        "chunk_type": "email",
+    });
+    let obj = metadata.as_object_mut().expect("metadata is an object");
+    let fields = [
+        ("from", &message.from),
+        ("to", &message.to),
+        ("cc", &message.cc),
+        ("subject", &message.subject),
+        ("date", &message.date),
+        ("message_id", &message.message_id),
+        ("thread_id", &message.thread_id),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            obj.insert(name.to_string(), value.clone().into());
+        }
+    }
+    if let Some(index) = index {
+        obj.insert("message_index".to_string(), index.into());
+    }
+
+    *chunk_id += 1;
+    Chunk {
+        id: chunk_id.to_string(),
+        text,
+        metadata,
+    }
+}
+
+/// Split a message or MIME part into unfolded headers and the body
+fn split_headers(raw: &str) -> (Vec<(String, String)>, &str) {
+    let (head, body) = match raw.find("\n\n") {
+        Some(idx) => (&raw[..idx], &raw[idx + 2..]),
+        None => (raw, ""),
+    };
+
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            // Folded continuation of the previous header
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+fn header_value(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.clone())
+        .filter(|v| !v.is_empty())
+}
+
+/// The first `<id>` in a header, without the angle brackets
+fn first_message_id(value: &str) -> Option<String> {
+    let start = value.find('<')? + 1;
+    let end = start + value[start..].find('>')?;
+    Some(value[start..end].to_string()).filter(|id| !id.is_empty())
+}
+
+/// A parameter (e.g. `charset`) of a `Content-Type`-style header
+fn content_param(value: &str, param: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|p| {
+        let (name, v) = p.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case(param)
+            .then(|| v.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Decode a body: the plain-text part of multipart messages (HTML stripped to
+/// text when there is none), with transfer encoding and charset applied
+fn decode_body(headers: &[(String, String)], body: &str) -> String {
+    let content_type = header_value(headers, "content-type").unwrap_or_else(|| "text/plain".to_string());
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+
+    if mime.starts_with("multipart/") {
+        let Some(boundary) = content_param(&content_type, "boundary") else {
+            return body.to_string();
+        };
+        let parts: Vec<(Vec<(String, String)>, &str)> = body
+            .split(&format!("--{}", boundary))
+            .skip(1)
+            .take_while(|part| !part.starts_with("--"))
+            .map(|part| split_headers(part.strip_prefix('\n').unwrap_or(part)))
+            .filter(|(headers, _)| {
+                !header_value(headers, "content-disposition").is_some_and(|d| d.to_lowercase().starts_with("attachment"))
+            })
+            .collect();
+
+        let part_mime = |headers: &[(String, String)]| {
+            header_value(headers, "content-type").unwrap_or_else(|| "text/plain".to_string()).to_lowercase()
+        };
+        // multipart/alternative: prefer the plain text version
+        let preferred = parts
+            .iter()
+            .find(|(h, _)| part_mime(h).starts_with("text/plain"))
+            .or_else(|| parts.iter().find(|(h, _)| part_mime(h).starts_with("multipart/")))
+            .or_else(|| parts.iter().find(|(h, _)| part_mime(h).starts_with("text/html")));
+        return match preferred {
+            Some((headers, body)) if mime == "multipart/alternative" => decode_body(headers, body),
+            _ => parts
+                .iter()
+                .filter(|(h, _)| part_mime(h).starts_with("text/") || part_mime(h).starts_with("multipart/"))
+                .map(|(h, b)| decode_body(h, b))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        };
+    }
+
+    let encoding = header_value(headers, "content-transfer-encoding").unwrap_or_default().to_lowercase();
+    let bytes = match encoding.as_str() {
+        "base64" => base64_decode(body),
+        "quoted-printable" => quoted_printable_decode(body, false),
+        _ => body.as_bytes().to_vec(),
+    };
+    let charset = content_param(&content_type, "charset").unwrap_or_default();
+    let text = decode_charset(&bytes, &charset);
+
+    if mime == "text/html" {
+        strip_html(&text)
+    } else {
+        text
+    }
+}
+
+/// Decode RFC 2047 encoded words (`=?utf-8?B?...?=`, `=?iso-8859-1?Q?...?=`) in a header
+fn decode_words(value: String) -> String {
+    let mut out = String::new();
+    let mut rest = value.as_str();
+    let mut last_was_word = false;
+
+    while let Some(start) = rest.find("=?") {
+        let decoded = rest[start + 2..].splitn(4, '?').collect::<Vec<_>>();
+        let (charset, encoding, text) = match decoded.as_slice() {
+            [charset, encoding, text, tail] if tail.starts_with('=') => (*charset, *encoding, *text),
+            _ => break,
+        };
+        let between = &rest[..start];
+        // Whitespace between adjacent encoded words is dropped
+        if !(last_was_word && between.trim().is_empty()) {
+            out.push_str(between);
+        }
+        let bytes = match encoding {
+            "B" | "b" => base64_decode(text),
+            _ => quoted_printable_decode(text, true),
+        };
+        out.push_str(&decode_charset(&bytes, charset));
+        rest = &rest[start + charset.len() + encoding.len() + text.len() + 6..];
+        last_was_word = true;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Bytes in a charset as text: UTF-8 (lossy) or Latin-1 style single-byte charsets
+fn decode_charset(bytes: &[u8], charset: &str) -> String {
+    let charset = charset.to_lowercase();
+    if charset.starts_with("iso-8859") || charset == "latin1" || charset.starts_with("windows-125") {
+        bytes.iter().map(|&b| b as char).collect()
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+fn quoted_printable_decode(s: &str, underscore_is_space: bool) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'=' if bytes.get(i + 1) == Some(&b'\n') => i += 2, // soft line break
+            b'=' => {
+                let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 3;
+                    }
+                    None => {
+                        out.push(b'=');
+                        i += 1;
+                    }
+                }
+            }
+            b'_' if underscore_is_space => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(s: &str) -> Vec<u8> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    };
+    let sextets: Vec<u8> = s.bytes().filter_map(value).collect();
+    let mut out = Vec::with_capacity(sextets.len() * 3 / 4);
+    for group in sextets.chunks(4) {
+        let n = group.iter().enumerate().fold(0u32, |n, (i, &v)| n | (v as u32) << (18 - 6 * i));
+        let bytes = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        out.extend_from_slice(&bytes[..group.len().saturating_sub(1)]);
+    }
+    out
+}
+
+/// Crude HTML to text: drop tags, scripts, and styles, decode common entities
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let lower = tag.get(..7).unwrap_or(tag).to_lowercase();
+        let skip_to = if lower.starts_with("<script") || lower.starts_with("<style") {
+            let close = if lower.starts_with("<script") { "</script>" } else { "</style>" };
+            tag.to_lowercase().find(close).map(|i| i + close.len())
+        } else {
+            tag.find('>').map(|i| i + 1)
+        };
+        let Some(skip_to) = skip_to else {
+            rest = "";
+            break;
+        };
+        if lower.starts_with("<br") || lower.starts_with("<p") || lower.starts_with("<div") {
+            text.push('\n');
+        }
+        rest = &tag[skip_to..];
+    }
+    text.push_str(rest);
+
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MBOX: &str = "From alice@example.com Thu Feb 29 12:34:56 2024\n\
+From: Alice <alice@example.com>\n\
+To: bob@example.com\n\
+Subject: =?utf-8?B?UmVsZWFzZSBwbGFu?=\n\
+Date: Thu, 29 Feb 2024 12:34:56 +0000\n\
+Message-ID: <1@example.com>\n\
+\n\
+Ship on Monday.\n\
+>From the team\n\
+\n\
+From bob@example.com Thu Feb 29 13:00:00 2024\n\
+From: bob@example.com\n\
+Subject: Re: Release plan\n\
+In-Reply-To: <1@example.com>\n\
+Message-ID: <2@example.com>\n\
+Content-Type: multipart/alternative; boundary=\"b1\"\n\
+\n\
+--b1\n\
+Content-Type: text/plain; charset=iso-8859-1\n\
+Content-Transfer-Encoding: quoted-printable\n\
+\n\
+Sounds good, caf=E9 =\n\
+at noon.\n\
+--b1\n\
+Content-Type: text/html\n\
+\n\
+<p>Sounds good</p>\n\
+--b1--\n";
+
+    #[test]
+    fn test_parse_mbox() {
+        let messages = parse_file(Path::new("inbox.mbox"), MBOX);
+        assert_eq!(messages.len(), 2);
+
+        assert_eq!(messages[0].from.as_deref(), Some("Alice <alice@example.com>"));
+        assert_eq!(messages[0].subject.as_deref(), Some("Release plan"));
+        assert_eq!(messages[0].date.as_deref(), Some("2024-02-29T12:34:56Z"));
+        assert_eq!(messages[0].body, "Ship on Monday.\nFrom the team");
+        assert_eq!(messages[0].thread_id.as_deref(), Some("1@example.com"));
+
+        assert_eq!(messages[1].body, "Sounds good, café at noon.");
+        assert_eq!(messages[1].message_id.as_deref(), Some("2@example.com"));
+        assert_eq!(messages[1].thread_id.as_deref(), Some("1@example.com"));
+        assert_eq!(messages[1].date, None);
+    }
+
+    #[test]
+    fn test_message_chunk() {
+        let messages = parse_file(Path::new("inbox.mbox"), MBOX);
+        let mut chunk_id = 0;
+        let chunk = message_chunk(&messages[0], Path::new("inbox.mbox"), Some(0), &mut chunk_id);
+        assert!(chunk.text.starts_with("Subject: Release plan\nFrom: Alice"));
+        assert_eq!(chunk.metadata["from"], "Alice <alice@example.com>");
+        assert_eq!(chunk.metadata["thread_id"], "1@example.com");
+        assert_eq!(chunk.metadata["message_index"], 0);
+        assert!(chunk.metadata.get("cc").is_none());
+    }
+
+    #[test]
+    fn test_decoding() {
+        assert_eq!(decode_words("=?iso-8859-1?Q?Caf=E9_au_lait?= =?utf-8?B?IQ==?= ok".to_string()), "Café au lait! ok");
+        assert_eq!(base64_decode("Zm9vYmFy"), b"foobar");
+        assert_eq!(base64_decode("Zm8="), b"fo");
+        assert_eq!(strip_html("<p>a &amp; b</p><script>x()</script>c"), "\na & bc");
+    }
+}
@@ -6,30 +6,77 @@ mod chunker;
 mod cli;
 mod config;
 mod http;
+mod time;
+mod progress;
+mod cancel;
+mod usage;
+mod globs;
 mod index;
 mod backend;
 mod embedding;
+mod error;
+mod export;
 mod llm;
+mod archive;
+mod mail;
+mod vault;
+mod transcribe;
+#[cfg(feature = "otel")]
+mod telemetry;
 
 pub use config::Config;
 
 use clap::Parser;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 use cli::Cli;
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
+async fn main() {
+    // Parse CLI args; failures exit with their error's status, as a JSON
+    // envelope on stdout for commands printing JSON
+    let cli = Cli::parse();
+    let json_errors = cli.json_errors();
+
+    #[cfg(feature = "otel")]
+    let telemetry = match cli.otel_endpoint.as_deref().map(telemetry::Telemetry::new).transpose() {
+        Ok(telemetry) => telemetry,
+        Err(err) => fail(err, json_errors),
+    };
+
+    // Initialize tracing; spans only go to the OpenTelemetry exporter, the
+    // log shows events alone
+    let registry = tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "leann=info,warn".into()),
         )
-        .with(tracing_subscriber::fmt::layer().with_target(false))
-        .init();
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_target(false)
+                .with_writer(std::io::stderr)
+                .with_filter(tracing_subscriber::filter::filter_fn(|metadata| metadata.is_event())),
+        );
+    #[cfg(feature = "otel")]
+    let registry = registry.with(telemetry.as_ref().map(telemetry::Telemetry::layer));
+    registry.init();
 
-    // Parse CLI args and run
-    let cli = Cli::parse();
-    cli.run().await
+    let result = cli.run().await;
+    #[cfg(feature = "otel")]
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
+    }
+    if let Err(err) = result {
+        fail(err, json_errors);
+    }
+}
+
+/// Report an error and exit with its status
+fn fail(err: anyhow::Error, json_errors: bool) -> ! {
+    if json_errors {
+        println!("{}", error::envelope(&err));
+    } else {
+        eprintln!("Error: {:?}", err);
+    }
+    std::process::exit(error::exit_code(&err));
 }
@@ -0,0 +1,227 @@
+//! Build progress reporting - console progress bars or structured JSON events
+//!
+//! With `--progress json`, builds write one JSON event per line to stdout
+//! instead of drawing progress bars, so GUIs and scripts can follow long
+//! builds. Events can also be appended to a file, or sent over a channel when
+//! a build runs inside `leann serve`.
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A step of a build
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// The build started
+    Started { index: String },
+    /// Source files were found
+    Scanned { files: usize, images: usize },
+    /// Estimated embedding tokens and cost, if known
+    Estimate { tokens: Option<usize>, cost_usd: Option<f64> },
+    /// A batch of chunks was embedded
+    Batch {
+        files_done: usize,
+        files_total: usize,
+        chunks_embedded: usize,
+        batch_size: usize,
+        batch_ms: u64,
+        chunks_per_sec: f64,
+        /// Estimated seconds left, from the share of files processed
+        eta_secs: Option<u64>,
+    },
+    /// A note for the user (e.g. a changed model)
+    Message { message: String },
+    /// The index was built
+    Finished {
+        index: String,
+        passages: usize,
+        dimensions: usize,
+        elapsed_secs: f64,
+        embedding_secs: f64,
+        batches: usize,
+        /// Estimated tokens embedded
+        embedding_tokens: usize,
+        /// Inputs cut to the embedding model's input limit
+        truncated_inputs: usize,
+        cost_usd: Option<f64>,
+    },
+    /// The build failed
+    Failed { error: String },
+    /// The build was stopped by Ctrl-C or `leann cancel`; the embeddings
+    /// computed so far are kept for the next attempt
+    Cancelled { message: String },
+}
+
+/// An event with the time since the build started
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressRecord {
+    pub elapsed_ms: u64,
+    #[serde(flatten)]
+    pub event: ProgressEvent,
+}
+
+/// Sends build progress to the console, stdout as JSON, a file, or a channel
+pub struct ProgressReporter {
+    start: Instant,
+    /// Write events to stdout instead of drawing progress bars
+    json: bool,
+    file: Option<Mutex<std::fs::File>>,
+    channel: Option<UnboundedSender<ProgressRecord>>,
+}
+
+impl ProgressReporter {
+    /// Reporter writing JSON to stdout if `json`, and appending events to `file` if given
+    pub fn new(json: bool, file: Option<&Path>) -> anyhow::Result<Self> {
+        let file = match file {
+            Some(path) => Some(Mutex::new(
+                std::fs::OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+        Ok(Self {
+            start: Instant::now(),
+            json,
+            file,
+            channel: None,
+        })
+    }
+
+    /// Also send events to `channel`
+    pub fn with_channel(mut self, channel: Option<UnboundedSender<ProgressRecord>>) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Whether stdout carries JSON events, so human-readable output is left out
+    pub fn is_json(&self) -> bool {
+        self.json
+    }
+
+    /// Record an event
+    pub fn emit(&self, event: ProgressEvent) {
+        let record = ProgressRecord {
+            elapsed_ms: self.start.elapsed().as_millis() as u64,
+            event,
+        };
+        if self.json || self.file.is_some() {
+            let Ok(line) = serde_json::to_string(&record) else {
+                return;
+            };
+            if self.json {
+                let mut stdout = std::io::stdout().lock();
+                let _ = writeln!(stdout, "{}", line);
+                let _ = stdout.flush();
+            }
+            if let Some(file) = &self.file {
+                if let Ok(mut file) = file.lock() {
+                    let _ = writeln!(file, "{}", line);
+                }
+            }
+        }
+        if let Some(channel) = &self.channel {
+            let _ = channel.send(record);
+        }
+    }
+
+    /// Print a note, or emit it as an event in JSON mode
+    pub fn message(&self, message: String) {
+        if !self.json {
+            println!("{}", message);
+        }
+        self.emit(ProgressEvent::Message { message });
+    }
+
+    /// Spinner with a message (hidden in JSON mode)
+    pub fn spinner(&self, message: &str) -> ProgressBar {
+        if self.json {
+            return ProgressBar::hidden();
+        }
+        let progress = ProgressBar::new_spinner();
+        progress.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} {msg}")
+                .unwrap(),
+        );
+        progress.set_message(message.to_string());
+        progress
+    }
+
+    /// Progress bar over `len` files (hidden in JSON mode)
+    pub fn file_bar(&self, len: u64) -> ProgressBar {
+        if self.json {
+            return ProgressBar::hidden();
+        }
+        let progress = ProgressBar::new(len);
+        progress.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} files ({eta})")
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+        progress
+    }
+
+    /// Record an embedded batch, estimating the time left from the share of files done
+    pub fn batch(&self, files_done: usize, files_total: usize, chunks_embedded: usize, batch_size: usize, batch_ms: u64) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let chunks_per_sec = if elapsed > 0.0 { chunks_embedded as f64 / elapsed } else { 0.0 };
+        let eta_secs = (files_done > 0 && files_done <= files_total)
+            .then(|| (elapsed * (files_total - files_done) as f64 / files_done as f64).round() as u64);
+        self.emit(ProgressEvent::Batch {
+            files_done,
+            files_total,
+            chunks_embedded,
+            batch_size,
+            batch_ms,
+            chunks_per_sec: (chunks_per_sec * 10.0).round() / 10.0,
+            eta_secs,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_json() {
+        let record = ProgressRecord {
+            elapsed_ms: 1500,
+            event: ProgressEvent::Scanned { files: 12, images: 0 },
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"elapsed_ms":1500,"event":"scanned","files":12,"images":0}"#
+        );
+    }
+
+    #[test]
+    fn test_channel_and_eta() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let reporter = ProgressReporter::new(false, None).unwrap().with_channel(Some(tx));
+
+        reporter.batch(0, 4, 100, 100, 250);
+        reporter.batch(4, 4, 200, 100, 250);
+
+        match rx.try_recv().unwrap().event {
+            ProgressEvent::Batch { eta_secs, batch_ms, .. } => {
+                assert_eq!(eta_secs, None);
+                assert_eq!(batch_ms, 250);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        match rx.try_recv().unwrap().event {
+            ProgressEvent::Batch { eta_secs, chunks_embedded, .. } => {
+                assert_eq!(eta_secs, Some(0));
+                assert_eq!(chunks_embedded, 200);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! OpenTelemetry export - sends tracing spans to an OTLP collector
+//!
+//! With `--otel-endpoint`, the spans leann records (build, embedding batches,
+//! backend search, BM25 scoring, LLM calls, and HTTP requests to `leann
+//! serve`) are exported over OTLP/HTTP, so a trace viewer shows where the time
+//! of a request went. Requests to `leann serve` carrying a W3C `traceparent`
+//! header join the caller's trace.
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Path collectors accept OTLP/HTTP traces on
+const TRACES_PATH: &str = "/v1/traces";
+
+/// A running span exporter; call `shutdown` before exiting to flush it
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Telemetry {
+    /// Export spans to an OTLP/HTTP collector, e.g. `http://localhost:4318`
+    pub fn new(endpoint: &str) -> anyhow::Result<Self> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(traces_url(endpoint))
+            .build()
+            .map_err(|e| anyhow::anyhow!("Failed to create OTLP exporter for {}: {}", endpoint, e))?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name("leann").build())
+            .build();
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        Ok(Self { provider })
+    }
+
+    /// Layer forwarding spans to the exporter
+    pub fn layer<S>(&self) -> OpenTelemetryLayer<S, Tracer>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.provider.tracer("leann"))
+    }
+
+    /// Export the remaining spans and stop
+    pub fn shutdown(self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Warning: failed to export traces: {}", e);
+        }
+    }
+}
+
+/// Make a span the child of the trace in a request's `traceparent` header
+#[cfg(feature = "server")]
+pub fn set_parent(span: &tracing::Span, headers: &axum::http::HeaderMap) {
+    use opentelemetry::propagation::Extractor;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    struct Headers<'a>(&'a axum::http::HeaderMap);
+
+    impl Extractor for Headers<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|v| v.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(|k| k.as_str()).collect()
+        }
+    }
+
+    let context = opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(&Headers(headers)));
+    let _ = span.set_parent(context);
+}
+
+/// Traces URL for an endpoint given as a collector base URL or a full URL
+fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with(TRACES_PATH) {
+        endpoint.to_string()
+    } else {
+        format!("{}{}", endpoint, TRACES_PATH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traces_url() {
+        assert_eq!(traces_url("http://localhost:4318"), "http://localhost:4318/v1/traces");
+        assert_eq!(traces_url("http://localhost:4318/"), "http://localhost:4318/v1/traces");
+        assert_eq!(traces_url("https://otel.example.com/v1/traces"), "https://otel.example.com/v1/traces");
+    }
+}
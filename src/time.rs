@@ -0,0 +1,239 @@
+//! Time utilities for timestamps stored in index metadata
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Current time as seconds since the Unix epoch
+pub fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format a Unix timestamp as an RFC 3339 UTC string (e.g. "2024-01-31T12:00:00Z")
+pub fn format_rfc3339(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// Parse an ISO-8601 date or date-time into seconds since the Unix epoch
+///
+/// Accepts "2024-06-01", "2024-06-01T12:00:00Z", "2024-06-01 12:00",
+/// fractional seconds, and "+02:00"-style offsets. Dates before 1970 return None.
+pub fn parse_datetime(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (date, time) = match s.find(['T', ' ']) {
+        Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+        None => (s, None),
+    };
+
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parse_digits(parts.next()?, 4)?;
+    let month: u32 = parse_digits(parts.next()?, 2)?;
+    let day: u32 = parse_digits(parts.next()?, 2)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut secs = days_from_civil(year, month, day) * 86_400;
+
+    if let Some(time) = time {
+        // Split off the timezone designator
+        let (clock, offset) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+            (clock, 0)
+        } else if let Some(idx) = time.rfind(['+', '-']) {
+            let sign = if time[idx..].starts_with('-') { -1 } else { 1 };
+            let (h, m) = time[idx + 1..].split_once(':').unwrap_or((&time[idx + 1..], "0"));
+            let offset = h.parse::<i64>().ok()? * 3600 + m.parse::<i64>().ok()? * 60;
+            (&time[..idx], sign * offset)
+        } else {
+            (time, 0)
+        };
+
+        let mut fields = clock.splitn(3, ':');
+        let hour: i64 = parse_digits(fields.next()?, 2)?;
+        let minute: i64 = fields.next().map_or(Some(0), |m| parse_digits(m, 2))?;
+        let second: i64 = match fields.next() {
+            // Ignore fractional seconds
+            Some(sec) => parse_digits(sec.split('.').next()?, 2)?,
+            None => 0,
+        };
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        secs += hour * 3600 + minute * 60 + second - offset;
+    }
+
+    u64::try_from(secs).ok()
+}
+
+/// Parse an RFC 2822 email date ("Tue, 1 Jul 2024 10:52:37 +0200") into Unix seconds
+///
+/// The weekday and seconds are optional; zone names other than UT/GMT/Z and
+/// trailing comments ("(CEST)") are treated as UTC.
+pub fn parse_rfc2822(s: &str) -> Option<u64> {
+    let s = s.split('(').next()?.trim();
+    let s = s.split_once(',').map_or(s, |(_, rest)| rest);
+    let mut fields = s.split_whitespace();
+
+    let day: u32 = fields.next()?.parse().ok()?;
+    let month = match fields.next()?.to_ascii_lowercase().get(..3)? {
+        "jan" => 1, "feb" => 2, "mar" => 3, "apr" => 4, "may" => 5, "jun" => 6,
+        "jul" => 7, "aug" => 8, "sep" => 9, "oct" => 10, "nov" => 11, "dec" => 12,
+        _ => return None,
+    };
+    let year: i64 = match fields.next()?.parse().ok()? {
+        // Obsolete two-digit years
+        y @ 0..=49 => 2000 + y,
+        y @ 50..=999 => 1900 + y,
+        y => y,
+    };
+    if !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut clock = fields.next()?.split(':');
+    let hour: i64 = clock.next()?.parse().ok()?;
+    let minute: i64 = clock.next()?.parse().ok()?;
+    let second: i64 = clock.next().map_or(Some(0), |sec| sec.parse().ok())?;
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let offset = match fields.next() {
+        Some(zone) if zone.len() == 5 && (zone.starts_with('+') || zone.starts_with('-')) => {
+            let hours: i64 = zone[1..3].parse().ok()?;
+            let minutes: i64 = zone[3..].parse().ok()?;
+            let offset = hours * 3600 + minutes * 60;
+            if zone.starts_with('-') { -offset } else { offset }
+        }
+        _ => 0,
+    };
+
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    u64::try_from(secs).ok()
+}
+
+/// Parse a time relative to now ("now", "now-30d", "now+2h") into Unix seconds
+///
+/// Units: s, m (minutes), h, d, w.
+pub fn parse_relative(s: &str, now: u64) -> Option<u64> {
+    let rest = s.trim().strip_prefix("now")?;
+    if rest.is_empty() {
+        return Some(now);
+    }
+
+    let sign = match rest.chars().next()? {
+        '-' => -1,
+        '+' => 1,
+        _ => return None,
+    };
+    let amount = &rest[1..];
+    let unit = amount.chars().last()?;
+    let value: i64 = amount[..amount.len() - unit.len_utf8()].parse().ok()?;
+    let unit_secs = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86_400,
+        'w' => 7 * 86_400,
+        _ => return None,
+    };
+
+    let offset = value.checked_mul(unit_secs)?.checked_mul(sign)?;
+    u64::try_from(i64::try_from(now).ok()?.checked_add(offset)?).ok()
+}
+
+fn parse_digits<T: std::str::FromStr>(s: &str, width: usize) -> Option<T> {
+    if s.len() != width || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Convert a (year, month, day) civil date to days since 1970-01-01
+///
+/// Inverse of `civil_from_days` (Howard Hinnant's days-from-civil algorithm).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) civil date
+///
+/// Uses Howard Hinnant's days-to-civil algorithm (proleptic Gregorian calendar).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_epoch() {
+        assert_eq!(format_rfc3339(0), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_format_known_date() {
+        // 2024-02-29 12:34:56 UTC (leap day)
+        assert_eq!(format_rfc3339(1_709_210_096), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        assert_eq!(parse_datetime("1970-01-01"), Some(0));
+        assert_eq!(parse_datetime("2024-02-29T12:34:56Z"), Some(1_709_210_096));
+        assert_eq!(parse_datetime("2024-02-29T14:34:56+02:00"), Some(1_709_210_096));
+        assert_eq!(parse_datetime("2024-02-29 12:34:56.789Z"), Some(1_709_210_096));
+        assert_eq!(parse_datetime("2024-13-01"), None);
+        assert_eq!(parse_datetime("main.rs"), None);
+    }
+
+    #[test]
+    fn test_parse_rfc2822() {
+        assert_eq!(parse_rfc2822("Thu, 29 Feb 2024 12:34:56 +0000"), Some(1_709_210_096));
+        assert_eq!(parse_rfc2822("29 Feb 2024 14:34:56 +0200 (CEST)"), Some(1_709_210_096));
+        assert_eq!(parse_rfc2822("Thu, 29 Feb 2024 07:34 -0500"), Some(1_709_210_040));
+        assert_eq!(parse_rfc2822("Thu, 29 Feb 24 12:34:56 GMT"), Some(1_709_210_096));
+        assert_eq!(parse_rfc2822("yesterday"), None);
+    }
+
+    #[test]
+    fn test_parse_relative() {
+        let now = 1_000_000;
+        assert_eq!(parse_relative("now", now), Some(now));
+        assert_eq!(parse_relative("now-1d", now), Some(now - 86_400));
+        assert_eq!(parse_relative("now+2h", now), Some(now + 7200));
+        assert_eq!(parse_relative("now-3x", now), None);
+        assert_eq!(parse_relative("nowhere", now), None);
+        assert_eq!(parse_relative("now-99999999999999999w", now), None);
+        assert_eq!(parse_relative("now+99999999999999999w", now), None);
+    }
+}
@@ -0,0 +1,206 @@
+//! Audio transcription (Whisper) for indexing recordings
+//!
+//! Audio files are transcribed through an OpenAI-compatible
+//! `/audio/transcriptions` endpoint (OpenAI `whisper-1`, or a local server
+//! such as faster-whisper-server or whisper.cpp), and the timestamped segments
+//! are grouped into chunks that record where they start and end.
+
+use std::env;
+use std::path::Path;
+
+use async_openai::{
+    config::OpenAIConfig,
+    types::{AudioInput, AudioResponseFormat, CreateTranscriptionRequestArgs, TimestampGranularity},
+    Client,
+};
+use tracing::info;
+
+use crate::chunker::Chunk;
+
+/// Audio file types indexed with `--source audio`
+pub const AUDIO_TYPES: &[&str] = &[".mp3", ".wav", ".m4a", ".flac", ".ogg", ".webm"];
+
+/// Largest file the OpenAI transcription API accepts, in KB
+pub const MAX_AUDIO_FILE_SIZE_KB: usize = 25 * 1024;
+
+/// A transcribed stretch of audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+    pub text: String,
+}
+
+/// Whisper transcription client
+pub struct Transcriber {
+    client: Client<OpenAIConfig>,
+    model_name: String,
+    language: Option<String>,
+}
+
+impl Transcriber {
+    /// Create a transcriber
+    ///
+    /// The API key defaults to `OPENAI_API_KEY` (optional for local servers);
+    /// the base URL to `LEANN_TRANSCRIPTION_API_BASE`, then OpenAI.
+    pub fn new(
+        model_name: String,
+        api_key: Option<String>,
+        api_base: Option<String>,
+        language: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let api_base = api_base.or_else(|| env::var("LEANN_TRANSCRIPTION_API_BASE").ok());
+        crate::http::check_network("Transcription", api_base.as_deref().unwrap_or(crate::http::OPENAI_API_BASE))?;
+
+        let api_key = api_key.or_else(|| env::var("OPENAI_API_KEY").ok()).unwrap_or_default();
+        let mut config = OpenAIConfig::new().with_api_key(api_key);
+        if let Some(api_base) = api_base {
+            info!("Transcription: {} @ {}", model_name, api_base);
+            config = config.with_api_base(api_base);
+        } else {
+            info!("Transcription: {}", model_name);
+        }
+
+        Ok(Self {
+            client: Client::with_config(config).with_http_client(crate::http::create_client()),
+            model_name,
+            language,
+        })
+    }
+
+    /// Transcribe an audio file into timestamped segments
+    pub async fn transcribe(&self, path: &Path) -> anyhow::Result<Vec<Segment>> {
+        let mut request = CreateTranscriptionRequestArgs::default();
+        request
+            .file(AudioInput::from(path))
+            .model(&self.model_name)
+            .response_format(AudioResponseFormat::VerboseJson)
+            .timestamp_granularities(vec![TimestampGranularity::Segment]);
+        if let Some(language) = &self.language {
+            request.language(language);
+        }
+
+        let response = self
+            .client
+            .audio()
+            .transcribe_verbose_json(request.build()?)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to transcribe {}: {}", path.display(), e))?;
+
+        let segments = match response.segments {
+            Some(segments) => segments
+                .into_iter()
+                .map(|s| Segment {
+                    start: round_seconds(s.start),
+                    end: round_seconds(s.end),
+                    text: s.text.trim().to_string(),
+                })
+                .filter(|s| !s.text.is_empty())
+                .collect(),
+            // Servers without segment timestamps: one segment for the whole recording
+            None if !response.text.trim().is_empty() => vec![Segment {
+                start: 0.0,
+                end: round_seconds(response.duration),
+                text: response.text.trim().to_string(),
+            }],
+            None => Vec::new(),
+        };
+        Ok(segments)
+    }
+}
+
+/// Seconds rounded to centiseconds (f32 timestamps print with noise digits otherwise)
+fn round_seconds(secs: f32) -> f64 {
+    (secs as f64 * 100.0).round() / 100.0
+}
+
+/// Group consecutive segments into chunks of about `chunk_size` tokens
+///
+/// Segments are never split, so every chunk starts and ends on a segment
+/// boundary and records `start_time`/`end_time` (seconds) for deep links.
+pub fn chunk_segments(segments: &[Segment], source: &Path, chunk_size: usize, chunk_id: &mut u64) -> Vec<Chunk> {
+    let char_limit = chunk_size.max(1) * 4; // ~4 chars per token
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < segments.len() {
+        let mut end = start + 1;
+        let mut len = segments[start].text.len();
+        while end < segments.len() && len + 1 + segments[end].text.len() <= char_limit {
+            len += 1 + segments[end].text.len();
+            end += 1;
+        }
+
+        let group = &segments[start..end];
+        let text = group.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" ");
+        *chunk_id += 1;
+        chunks.push(Chunk {
+            id: chunk_id.to_string(),
+            text,
+            metadata: serde_json::json!({
+                "source": source.to_string_lossy(),
+                "chunk_type": "audio",
+                "start_time": group[0].start,
+                "end_time": group[group.len() - 1].end,
+            }),
+        });
+        start = end;
+    }
+
+    chunks
+}
+
+/// Format seconds as `m:ss` or `h:mm:ss`
+pub fn format_timestamp(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    let (hours, minutes, seconds) = (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, end: f64, text: &str) -> Segment {
+        Segment {
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_chunk_segments() {
+        let segments = vec![
+            segment(0.0, 4.5, "Welcome to the weekly sync."),
+            segment(4.5, 9.0, "First item is the release."),
+            segment(9.0, 15.25, "We shipped version two on Monday and the rollout went fine."),
+        ];
+        let mut chunk_id = 0;
+        // ~60 chars per chunk: the first two segments fit together, the third doesn't
+        let chunks = chunk_segments(&segments, Path::new("sync.mp3"), 15, &mut chunk_id);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].text, "Welcome to the weekly sync. First item is the release.");
+        assert_eq!(chunks[0].metadata["start_time"], 0.0);
+        assert_eq!(chunks[0].metadata["end_time"], 9.0);
+        assert_eq!(chunks[1].metadata["start_time"], 9.0);
+        assert_eq!(chunks[1].metadata["end_time"], 15.25);
+        assert_eq!(chunks[1].metadata["source"], "sync.mp3");
+        assert_eq!(chunks[1].id, "2");
+        assert!(chunk_segments(&[], Path::new("empty.wav"), 15, &mut chunk_id).is_empty());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0.0), "0:00");
+        assert_eq!(format_timestamp(75.9), "1:15");
+        assert_eq!(format_timestamp(3725.0), "1:02:05");
+    }
+}
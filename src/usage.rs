@@ -0,0 +1,214 @@
+//! Usage ledger - cumulative embedding and LLM token usage and cost
+//!
+//! Builds, updates, and questions append a record per model used to
+//! `~/.leann/usage.jsonl`; `leann usage` sums them up. Token counts are
+//! estimates (see `embedding::estimate_tokens` and `llm::count_tokens`), and
+//! costs come from the built-in price tables, so treat totals as approximate.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// What the tokens were spent on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageKind {
+    Embedding,
+    Generation,
+}
+
+impl std::fmt::Display for UsageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Embedding => write!(f, "embedding"),
+            Self::Generation => write!(f, "generation"),
+        }
+    }
+}
+
+/// Tokens one command spent on one model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Unix time of the command
+    pub timestamp: u64,
+    /// Command that used the model (build, update, ask)
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    pub kind: UsageKind,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: usize,
+    #[serde(default)]
+    pub output_tokens: usize,
+    /// Cost in USD, if the model's price is known
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+}
+
+impl UsageRecord {
+    pub fn new(command: &str, index: Option<&str>, kind: UsageKind, provider: &str, model: &str) -> Self {
+        Self {
+            timestamp: crate::time::now_unix(),
+            command: command.to_string(),
+            index: index.map(|i| i.to_string()),
+            kind,
+            provider: provider.to_string(),
+            model: model.to_string(),
+            input_tokens: 0,
+            output_tokens: 0,
+            cost_usd: None,
+        }
+    }
+
+    /// Set the tokens used and their cost
+    pub fn with_tokens(mut self, input_tokens: usize, output_tokens: usize, cost_usd: Option<f64>) -> Self {
+        self.input_tokens = input_tokens;
+        self.output_tokens = output_tokens;
+        self.cost_usd = cost_usd;
+        self
+    }
+}
+
+/// Location of the usage ledger
+pub fn ledger_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".leann").join("usage.jsonl"))
+}
+
+/// Append records to the ledger, warning instead of failing the command
+pub fn record_usage(records: &[UsageRecord]) {
+    let records: Vec<&UsageRecord> = records.iter().filter(|r| r.input_tokens + r.output_tokens > 0).collect();
+    if records.is_empty() {
+        return;
+    }
+    let Some(path) = ledger_path() else {
+        return;
+    };
+    if let Err(e) = append_records(&path, &records) {
+        tracing::warn!("Failed to record usage in {}: {}", path.display(), e);
+    }
+}
+
+fn append_records(path: &Path, records: &[&UsageRecord]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    for record in records {
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+    }
+    Ok(())
+}
+
+/// Records in the ledger at `path`, skipping malformed lines
+pub fn read_ledger(path: &Path) -> anyhow::Result<Vec<UsageRecord>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Usage summed over records with the same key
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub records: usize,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// Cost of the records with a known price
+    pub cost_usd: f64,
+    /// Records whose cost is unknown (not included in `cost_usd`)
+    pub unpriced: usize,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &UsageRecord) {
+        self.records += 1;
+        self.input_tokens += record.input_tokens;
+        self.output_tokens += record.output_tokens;
+        match record.cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced += 1,
+        }
+    }
+}
+
+/// Ledger totals overall, per model, and per command
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageSummary {
+    pub total: UsageTotals,
+    /// Keyed by "kind provider/model"
+    pub by_model: BTreeMap<String, UsageTotals>,
+    pub by_command: BTreeMap<String, UsageTotals>,
+}
+
+impl UsageSummary {
+    /// Sum records made at or after `since` (Unix time)
+    pub fn from_records(records: &[UsageRecord], since: Option<u64>) -> Self {
+        let mut summary = Self::default();
+        for record in records.iter().filter(|r| since.is_none_or(|since| r.timestamp >= since)) {
+            summary.total.add(record);
+            let model = format!("{} {}/{}", record.kind, record.provider, record.model);
+            summary.by_model.entry(model).or_default().add(record);
+            summary.by_command.entry(record.command.clone()).or_default().add(record);
+        }
+        summary
+    }
+}
+
+/// Human-readable cost, e.g. "$0.0123" or "unknown"
+pub fn format_cost(cost_usd: Option<f64>) -> String {
+    match cost_usd {
+        Some(cost) => format!("${:.4}", cost),
+        None => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_summary() {
+        let path = std::env::temp_dir().join(format!("leann-usage-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let embed = |timestamp: u64, tokens: usize, cost: Option<f64>| {
+            let mut record = UsageRecord::new("build", Some("docs"), UsageKind::Embedding, "openai", "text-embedding-3-small")
+                .with_tokens(tokens, 0, cost);
+            record.timestamp = timestamp;
+            record
+        };
+        let ask = UsageRecord::new("ask", Some("docs"), UsageKind::Generation, "openai", "my-finetune")
+            .with_tokens(1200, 300, None);
+        let records = [embed(100, 1_000_000, Some(0.02)), embed(200, 500_000, Some(0.01)), ask];
+        append_records(&path, &records.iter().collect::<Vec<_>>()).unwrap();
+
+        let read = read_ledger(&path).unwrap();
+        assert_eq!(read.len(), 3);
+
+        let summary = UsageSummary::from_records(&read, None);
+        assert_eq!(summary.total.records, 3);
+        assert_eq!(summary.total.input_tokens, 1_501_200);
+        assert_eq!(summary.total.output_tokens, 300);
+        assert!((summary.total.cost_usd - 0.03).abs() < 1e-9);
+        assert_eq!(summary.total.unpriced, 1);
+        assert_eq!(summary.by_model["embedding openai/text-embedding-3-small"].records, 2);
+        assert_eq!(summary.by_command["ask"].output_tokens, 300);
+
+        let recent = UsageSummary::from_records(&read, Some(150));
+        assert_eq!(recent.total.records, 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_format_cost() {
+        assert_eq!(format_cost(Some(0.01234)), "$0.0123");
+        assert_eq!(format_cost(None), "unknown");
+    }
+}
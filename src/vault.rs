@@ -0,0 +1,402 @@
+//! Obsidian vault (and Notion markdown export) import for `--source obsidian`
+//!
+//! Notes are chunked by heading. Wiki-links (`[[Note]]`, `[[Note|alias]]`,
+//! `[[Note#Heading]]`) and relative markdown links are resolved against the
+//! vault, YAML frontmatter becomes metadata, and every note records how many
+//! other notes link to it (`backlinks`).
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::chunker::Chunk;
+
+/// Metadata keys set by the importer (or by `--namespace`); frontmatter can't override them
+const RESERVED_KEYS: &[&str] = &[
+    "source", "chunk_type", "title", "heading", "tags", "links", "backlinks", "start_line", "end_line", "namespace",
+];
+
+fn wiki_link_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"!?\[\[([^\]|#]*)(#[^\]|]*)?(?:\|([^\]]*))?\]\]").unwrap())
+}
+
+fn markdown_link_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\[([^\]]*)\]\(([^)\s]+\.md)(?:#[^)]*)?\)").unwrap())
+}
+
+fn tag_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:^|\s)#([A-Za-z][\w/-]*)").unwrap())
+}
+
+/// Link graph of a vault, used to resolve links and count backlinks
+#[derive(Debug, Default)]
+pub struct Vault {
+    /// Lowercased note name -> note path
+    notes: HashMap<String, PathBuf>,
+    /// Note path -> number of other notes linking to it
+    backlinks: HashMap<PathBuf, usize>,
+}
+
+impl Vault {
+    /// Read every note once to build the link graph
+    pub fn scan(paths: &[PathBuf]) -> Self {
+        let mut vault = Self::default();
+        for path in paths {
+            vault.notes.entry(note_name(path).to_lowercase()).or_insert_with(|| path.clone());
+        }
+
+        for path in paths {
+            let Ok(content) = std::fs::read_to_string(path) else {
+                continue;
+            };
+            let targets: HashSet<PathBuf> = link_names(&content)
+                .iter()
+                .filter_map(|name| vault.resolve(name))
+                .filter(|target| target != path)
+                .collect();
+            for target in targets {
+                *vault.backlinks.entry(target).or_insert(0) += 1;
+            }
+        }
+        vault
+    }
+
+    /// The note a link points to (by file name, ignoring folders and case)
+    fn resolve(&self, link: &str) -> Option<PathBuf> {
+        let name = note_name(Path::new(&percent_decode(link.trim())));
+        self.notes.get(&name.to_lowercase()).cloned()
+    }
+
+    /// Chunk a note by heading, with its frontmatter, tags, and links as metadata
+    pub fn chunk_note(&self, path: &Path, content: &str, chunk_id: &mut u64) -> Vec<Chunk> {
+        let (frontmatter, body, body_line) = split_frontmatter(content);
+
+        let mut tags: Vec<String> = match frontmatter.get("tags") {
+            Some(Value::String(s)) => s.split(',').map(|t| t.trim().trim_start_matches('#').to_string()).collect(),
+            _ => Vec::new(),
+        };
+        for section in body.lines().filter(|l| !l.starts_with('#')) {
+            for cap in tag_re().captures_iter(section) {
+                tags.push(cap[1].to_string());
+            }
+        }
+        tags.retain(|t| !t.is_empty());
+        tags.sort();
+        tags.dedup();
+
+        let mut links: Vec<String> = link_names(content)
+            .iter()
+            .map(|name| match self.resolve(name) {
+                Some(target) => note_name(&target),
+                None => note_name(Path::new(&percent_decode(name))),
+            })
+            .collect();
+        links.sort();
+        links.dedup();
+
+        let title = match frontmatter.get("title") {
+            Some(Value::String(title)) => title.clone(),
+            _ => body
+                .lines()
+                .find_map(|l| l.strip_prefix("# "))
+                .map(|t| t.trim().to_string())
+                .unwrap_or_else(|| note_name(path)),
+        };
+
+        let mut note_metadata = Map::new();
+        note_metadata.insert("source".to_string(), path.to_string_lossy().into());
+        note_metadata.insert("chunk_type".to_string(), "note".into());
+        note_metadata.insert("title".to_string(), title.clone().into());
+        if !tags.is_empty() {
+            note_metadata.insert("tags".to_string(), tags.join(", ").into());
+        }
+        if !links.is_empty() {
+            note_metadata.insert("links".to_string(), links.join(", ").into());
+        }
+        note_metadata.insert(
+            "backlinks".to_string(),
+            self.backlinks.get(path).copied().unwrap_or(0).into(),
+        );
+        for (key, value) in frontmatter {
+            if !RESERVED_KEYS.contains(&key.as_str()) {
+                note_metadata.insert(key, value);
+            }
+        }
+
+        let mut chunks = Vec::new();
+        for section in heading_sections(body) {
+            let text = resolve_links(section.text.trim());
+            if text.is_empty() || text.lines().all(|l| l.starts_with('#')) {
+                continue;
+            }
+
+            let mut metadata = note_metadata.clone();
+            if !section.headings.is_empty() {
+                metadata.insert("heading".to_string(), section.headings.join(" > ").into());
+            }
+            metadata.insert("start_line".to_string(), (body_line + section.start_line).into());
+            metadata.insert("end_line".to_string(), (body_line + section.end_line).into());
+
+            let context = std::iter::once(title.as_str())
+                .chain(section.headings.iter().map(|h| h.as_str()))
+                .collect::<Vec<_>>()
+                .join(" > ");
+            *chunk_id += 1;
+            chunks.push(Chunk {
+                id: chunk_id.to_string(),
+                text: format!("{}\n\n{}", context, text),
+                metadata: Value::Object(metadata),
+            });
+        }
+        chunks
+    }
+}
+
+/// Note name from a file path: the file stem, without the 32-hex-digit ID
+/// that Notion exports append ("Roadmap 1a2b...")
+fn note_name(path: &Path) -> String {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    match stem.rsplit_once(' ') {
+        Some((name, id)) if id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit()) => name.to_string(),
+        _ => stem.to_string(),
+    }
+}
+
+/// Targets of wiki-links and relative markdown links in a note
+fn link_names(content: &str) -> Vec<String> {
+    let wiki = wiki_link_re().captures_iter(content).map(|c| c[1].to_string());
+    let markdown = markdown_link_re()
+        .captures_iter(content)
+        .filter(|c| !c[2].contains("://"))
+        .map(|c| c[2].to_string());
+    wiki.chain(markdown).filter(|name| !name.trim().is_empty()).collect()
+}
+
+/// Replace wiki-links with their display text (alias, or note name and heading)
+fn resolve_links(text: &str) -> String {
+    wiki_link_re()
+        .replace_all(text, |c: &regex::Captures| match (c.get(3), c.get(2)) {
+            (Some(alias), _) => alias.as_str().to_string(),
+            (None, Some(heading)) if c[1].is_empty() => heading.as_str()[1..].to_string(),
+            (None, Some(heading)) => format!("{} > {}", &c[1], &heading.as_str()[1..]),
+            (None, None) => c[1].to_string(),
+        })
+        .into_owned()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Split off YAML frontmatter, returning its fields, the body, and the
+/// number of lines before the body
+fn split_frontmatter(content: &str) -> (Map<String, Value>, &str, usize) {
+    let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+        return (Map::new(), content, 0);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (Map::new(), content, 0);
+    };
+    let yaml = &rest[..end];
+    let body = rest[end + 4..].split_once('\n').map_or("", |(_, body)| body);
+    let body_line = content.len() - body.len();
+    (parse_frontmatter(yaml), body, content[..body_line].lines().count())
+}
+
+/// Parse flat YAML frontmatter: scalars, inline lists, and `- item` lists
+/// (lists are joined with ", " so they can be filtered with `~`)
+fn parse_frontmatter(yaml: &str) -> Map<String, Value> {
+    let mut fields = Map::new();
+    let mut list: Option<(String, Vec<String>)> = None;
+
+    for line in yaml.lines() {
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some((_, items)) = list.as_mut() {
+                items.push(unquote(item).to_string());
+            }
+            continue;
+        }
+        if let Some((key, items)) = list.take() {
+            fields.insert(key, items.join(", ").into());
+        }
+        if line.starts_with([' ', '\t']) {
+            continue; // nested maps aren't supported
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_string(), value.trim());
+        if value.is_empty() {
+            list = Some((key, Vec::new()));
+        } else if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            let items: Vec<&str> = inner.split(',').map(|i| unquote(i.trim())).filter(|i| !i.is_empty()).collect();
+            fields.insert(key, items.join(", ").into());
+        } else {
+            fields.insert(key, scalar(value));
+        }
+    }
+    if let Some((key, items)) = list {
+        fields.insert(key, items.join(", ").into());
+    }
+    fields
+}
+
+fn unquote(s: &str) -> &str {
+    s.trim()
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .or_else(|| s.trim().strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+        .unwrap_or(s.trim())
+}
+
+fn scalar(value: &str) -> Value {
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match value.parse::<i64>() {
+            Ok(n) => n.into(),
+            Err(_) => match value.parse::<f64>() {
+                Ok(f) if f.is_finite() => f.into(),
+                _ => unquote(value).into(),
+            },
+        },
+    }
+}
+
+/// A run of lines under one heading
+struct Section {
+    /// Enclosing headings, outermost first
+    headings: Vec<String>,
+    text: String,
+    /// 1-based line range within the body
+    start_line: usize,
+    end_line: usize,
+}
+
+/// Split markdown into sections at headings (ignoring `#` lines in code fences)
+fn heading_sections(body: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut current = Section {
+        headings: Vec::new(),
+        text: String::new(),
+        start_line: 1,
+        end_line: 0,
+    };
+    let mut in_fence = false;
+
+    for (i, line) in body.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let level = line.bytes().take_while(|&b| b == b'#').count();
+        let is_heading = !in_fence && (1..=6).contains(&level) && line[level..].starts_with(' ');
+
+        if is_heading {
+            sections.push(std::mem::replace(
+                &mut current,
+                Section {
+                    headings: Vec::new(),
+                    text: String::new(),
+                    start_line: i + 1,
+                    end_line: i,
+                },
+            ));
+            headings.retain(|(l, _)| *l < level);
+            headings.push((level, line[level..].trim().to_string()));
+            current.headings = headings.iter().map(|(_, h)| h.clone()).collect();
+        }
+        current.text.push_str(line);
+        current.text.push('\n');
+        current.end_line = i + 1;
+    }
+    sections.push(current);
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "---\n\
+title: Release Plan\n\
+tags: [planning, \"q3\"]\n\
+status: draft\n\
+priority: 2\n\
+namespace: bob\n\
+aliases:\n  - Plan\n  - Roadmap\n\
+---\n\
+# Release Plan\n\
+Intro linking [[Team Notes|the team]].\n\
+\n\
+## Dates\n\
+Ship after [[Testing#Checklist]] #milestone\n\
+```\n\
+# not a heading\n\
+```\n";
+
+    #[test]
+    fn test_chunk_note() {
+        let paths = [PathBuf::from("vault/Release Plan.md"), PathBuf::from("vault/Team Notes.md")];
+        let vault = Vault {
+            notes: paths.iter().map(|p| (note_name(p).to_lowercase(), p.clone())).collect(),
+            backlinks: HashMap::from([(paths[0].clone(), 3)]),
+        };
+        let mut chunk_id = 0;
+        let chunks = vault.chunk_note(&paths[0], NOTE, &mut chunk_id);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].text, "Release Plan > Release Plan\n\n# Release Plan\nIntro linking the team.");
+        assert_eq!(chunks[1].metadata["heading"], "Release Plan > Dates");
+        assert!(chunks[1].text.contains("Ship after Testing > Checklist #milestone"));
+        assert!(chunks[1].text.contains("# not a heading"));
+
+        let meta = &chunks[0].metadata;
+        assert_eq!(meta["title"], "Release Plan");
+        assert_eq!(meta["tags"], "milestone, planning, q3");
+        assert_eq!(meta["links"], "Team Notes, Testing");
+        assert_eq!(meta["backlinks"], 3);
+        assert_eq!(meta["status"], "draft");
+        assert_eq!(meta["priority"], 2);
+        assert_eq!(meta["aliases"], "Plan, Roadmap");
+        assert!(meta.get("namespace").is_none());
+        // Line numbers are within the whole file, frontmatter included
+        assert_eq!(meta["start_line"], 11);
+        assert_eq!(chunks[1].metadata["start_line"], 14);
+        assert_eq!(chunks[1].metadata["end_line"], 18);
+    }
+
+    #[test]
+    fn test_note_names_and_links() {
+        assert_eq!(note_name(Path::new("export/Roadmap 0123456789abcdef0123456789abcdef.md")), "Roadmap");
+        assert_eq!(note_name(Path::new("vault/Daily 2024.md")), "Daily 2024");
+        assert_eq!(
+            link_names("See [[A]], ![[img.png]], [B](B%20c.md) and [web](https://x.io/a.md)"),
+            vec!["A", "img.png", "B%20c.md"]
+        );
+        assert_eq!(percent_decode("B%20c.md"), "B c.md");
+        assert_eq!(resolve_links("[[A|alias]] [[#Local]] [[B]]"), "alias Local B");
+    }
+}
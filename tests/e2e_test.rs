@@ -0,0 +1,780 @@
+//! End-to-end tests: build indexes from the fixture documents with the mock
+//! embedding provider, then search, update, prune, and serve them
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A scratch directory with a copy of the fixture documents, for running
+/// leann commands against
+struct Workspace {
+    dir: PathBuf,
+}
+
+impl Workspace {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("leann-e2e-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let docs = dir.join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/docs");
+        for entry in std::fs::read_dir(fixtures).unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), docs.join(entry.file_name())).unwrap();
+        }
+        Self { dir }
+    }
+
+    fn docs(&self) -> PathBuf {
+        self.dir.join("docs")
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_leann"));
+        cmd.args(args)
+            .current_dir(&self.dir)
+            .env("HOME", &self.dir)
+            .env("XDG_CONFIG_HOME", self.dir.join(".config"))
+            .env("LEANN_NO_DAEMON", "1")
+            .env("LEANN_OFFLINE", "1")
+            .env("RUST_BACKTRACE", "0");
+        cmd
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().expect("Failed to run leann")
+    }
+
+    /// Run a command that must succeed
+    fn ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "leann {} failed:\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    fn build(&self, index: &str, extra: &[&str]) {
+        let docs = self.docs();
+        let mut args = vec![
+            "build",
+            index,
+            "--docs",
+            docs.to_str().unwrap(),
+            "--embedding-mode",
+            "mock",
+            "--embedding-model",
+            "mock",
+        ];
+        args.extend_from_slice(extra);
+        self.ok(&args);
+    }
+
+    /// Sources of the results of a JSON search, best first
+    fn search(&self, index: &str, query: &str, extra: &[&str]) -> Vec<String> {
+        let mut args = vec!["search", query, "--index", index, "--format", "json"];
+        args.extend_from_slice(extra);
+        let results: serde_json::Value = serde_json::from_str(&self.ok(&args)).unwrap();
+        sources(&results)
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn sources(results: &serde_json::Value) -> Vec<String> {
+    results
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["metadata"]["source"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_build_and_search() {
+    let ws = Workspace::new("search");
+    ws.build("docs", &[]);
+
+    let sources = ws.search("docs", "sourdough starter bread dough", &[]);
+    assert!(sources[0].ends_with("sourdough.md"), "{:?}", sources);
+
+    let sources = ws.search("docs", "kubernetes pods deployment replicas", &["--top-k", "2"]);
+    assert!(sources[0].ends_with("kubernetes.md"), "{:?}", sources);
+    assert!(sources.len() <= 2);
+
+    let sources = ws.search("docs", "tokenize expression", &["--filter", "source:*.rs"]);
+    assert!(!sources.is_empty());
+    assert!(sources.iter().all(|s| s.ends_with("tokenizer.rs")), "{:?}", sources);
+}
+
+#[test]
+fn test_update_adds_new_documents() {
+    let ws = Workspace::new("update");
+    ws.build("docs", &[]);
+
+    std::fs::write(
+        ws.docs().join("gardening.md"),
+        "# Growing Tomatoes\n\nTomato seedlings need full sun, compost, and deep watering. \
+         Stake the tomato plants and prune suckers for a bigger harvest.\n",
+    )
+    .unwrap();
+    ws.ok(&["update", "docs", "--yes"]);
+
+    let sources = ws.search("docs", "tomato seedlings compost harvest", &[]);
+    assert!(sources[0].ends_with("gardening.md"), "{:?}", sources);
+}
+
+#[test]
+fn test_update_from_relative_docs_root() {
+    let ws = Workspace::new("update-relative");
+    // Built with --docs relative to the working directory; update falls back
+    // to the recorded (absolute) root
+    ws.ok(&["build", "docs", "--docs", "docs", "--embedding-mode", "mock", "--embedding-model", "mock"]);
+
+    std::fs::write(
+        ws.docs().join("sourdough.md"),
+        "# Sourdough\n\nFeed the sourdough starter twice a day and proof the bread dough overnight.\n",
+    )
+    .unwrap();
+    let output = ws.ok(&["update", "docs", "--yes"]);
+    assert!(output.contains("Modified files:   1"), "{}", output);
+    assert!(output.contains("New files:        0"), "{}", output);
+
+    let results: serde_json::Value = serde_json::from_str(&ws.ok(&[
+        "search", "sourdough starter bread dough", "--index", "docs", "--top-k", "20", "--format", "json",
+    ]))
+    .unwrap();
+    let sourdough: Vec<&serde_json::Value> = results
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|r| r["metadata"]["source"].as_str().unwrap().ends_with("sourdough.md"))
+        .collect();
+    assert_eq!(sourdough.len(), 1, "{:#}", results);
+    assert!(sourdough[0]["text"].as_str().unwrap().contains("proof the bread dough overnight"));
+
+    let meta: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(ws.dir.join(".leann/indexes/docs/documents.leann.meta.json")).unwrap(),
+    )
+    .unwrap();
+    let files = meta["provenance"]["files"].as_array().unwrap();
+    assert_eq!(files.len(), 4);
+    assert_eq!(files.iter().filter(|f| f["path"].as_str().unwrap().ends_with("sourdough.md")).count(), 1);
+}
+
+#[test]
+fn test_update_replaces_modified_passages() {
+    let ws = Workspace::new("update-replace");
+    ws.ok(&["build", "docs", "--docs", "docs", "--embedding-mode", "mock", "--embedding-model", "mock"]);
+    let index_dir = ws.dir.join(".leann/indexes/docs");
+    let read_ids = || -> Vec<String> {
+        std::fs::read_to_string(index_dir.join("documents.ids.txt")).unwrap().lines().map(String::from).collect()
+    };
+    let search = || -> Vec<(String, String)> {
+        let results: serde_json::Value = serde_json::from_str(&ws.ok(&[
+            "search", "sourdough starter bread dough", "--index", "docs", "--top-k", "20", "--format", "json",
+        ]))
+        .unwrap();
+        results
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|r| r["metadata"]["source"].as_str().unwrap().ends_with("sourdough.md"))
+            .map(|r| (r["id"].as_str().unwrap().to_string(), r["text"].as_str().unwrap().to_string()))
+            .collect()
+    };
+    let before = search();
+    assert_eq!(before.len(), 1);
+    let old_id = before[0].0.clone();
+    let keys = read_ids().len();
+
+    std::fs::write(ws.docs().join("sourdough.md"), "# Sourdough\n\nBake the sourdough bread dough in a hot dutch oven.\n")
+        .unwrap();
+    let output = ws.ok(&["update", "docs", "--yes"]);
+    assert!(output.contains("Passages to remove: 1"), "{}", output);
+
+    // The old passage is tombstoned and its vector's key taken over by the new one
+    let after = search();
+    assert_eq!(after.len(), 1, "{:?}", after);
+    assert_ne!(after[0].0, old_id);
+    assert!(after[0].1.contains("dutch oven"));
+    let ids = read_ids();
+    assert_eq!(ids.len(), keys);
+    assert!(!ids.contains(&old_id) && ids.contains(&after[0].0));
+}
+
+#[test]
+fn test_paginated_expanded_query_is_stable() {
+    let ws = Workspace::new("pages");
+    ws.build("docs", &[]);
+
+    // A short query is expanded with terms from BM25 matches on every page
+    let page = |offset: &str| -> Vec<String> {
+        let output = ws.ok(&[
+            "search", "bread dough", "--index", "docs", "--page-size", "2", "--offset", offset, "--format", "json",
+        ]);
+        let results: serde_json::Value = serde_json::from_str(&output).unwrap();
+        results.as_array().unwrap().iter().map(|r| r["id"].as_str().unwrap().to_string()).collect()
+    };
+    let (first, second) = (page("0"), page("2"));
+    assert_eq!(page("0"), first);
+    assert_eq!(page("2"), second);
+    assert_eq!(first.len(), 2);
+    assert!(second.iter().all(|id| !first.contains(id)), "{:?} {:?}", first, second);
+}
+
+#[test]
+fn test_auto_chunk_size_is_recorded() {
+    let ws = Workspace::new("chunk-size");
+    let chunking = |index: &str| -> serde_json::Value {
+        let path = ws.dir.join(format!(".leann/indexes/{}/documents.leann.meta.json", index));
+        let meta: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        meta["chunking"].clone()
+    };
+
+    ws.build("fitted", &["--max-chunk-tokens", "100", "--auto-chunk-size"]);
+    let fitted = chunking("fitted");
+    assert_eq!((fitted["chunk_size"].as_u64(), fitted["chunk_overlap"].as_u64()), (Some(100), Some(50)));
+    assert_eq!(fitted["max_chunk_tokens"], 100);
+
+    // Without --auto-chunk-size the requested size is kept
+    ws.build("kept", &["--max-chunk-tokens", "100"]);
+    let kept = chunking("kept");
+    assert_eq!((kept["chunk_size"].as_u64(), kept["chunk_overlap"].as_u64()), (Some(256), Some(128)));
+}
+
+#[test]
+fn test_relevance_feedback() {
+    let ws = Workspace::new("feedback");
+    ws.build("docs", &[]);
+
+    let query = "sourdough starter bread dough";
+    let ids = |extra: &[&str]| -> Vec<String> {
+        let mut args = vec!["search", query, "--index", "docs", "--top-k", "10", "--format", "json"];
+        args.extend_from_slice(extra);
+        let results: serde_json::Value = serde_json::from_str(&ws.ok(&args)).unwrap();
+        results.as_array().unwrap().iter().map(|r| r["id"].as_str().unwrap().to_string()).collect()
+    };
+    let before = ids(&[]);
+    let (best, worst) = (before[0].clone(), before.last().unwrap().clone());
+
+    ws.ok(&["feedback", "docs", "--query", query, "--good", &worst, "--bad", &best]);
+    assert!(ws.ok(&["feedback", "docs", "--list"]).contains(&format!("+ {}", worst)));
+
+    // The good passage is pinned to the top and the bad one dropped
+    let after = ids(&[]);
+    assert_eq!(after[0], worst);
+    assert!(!after.contains(&best));
+    assert_eq!(ids(&["--no-feedback"])[0], best);
+
+    let output = ws.run(&["feedback", "docs", "--query", query, "--good", "no-such-passage"]);
+    assert!(!output.status.success());
+
+    ws.ok(&["feedback", "docs", "--clear"]);
+    assert_eq!(ids(&[])[0], best);
+}
+
+#[test]
+fn test_synonyms() {
+    let ws = Workspace::new("synonyms");
+    ws.build("docs", &[]);
+    std::fs::write(
+        ws.dir.join(".leann/indexes/docs.synonyms.toml"),
+        "infra = \"kubernetes, pods, deployment, replicas\"\n",
+    )
+    .unwrap();
+
+    let sources = ws.search("docs", "infra", &[]);
+    assert!(sources[0].ends_with("kubernetes.md"), "{:?}", sources);
+    let sources = ws.search("docs", "infra", &["--no-synonyms"]);
+    assert!(!sources.first().is_some_and(|s| s.ends_with("kubernetes.md")), "{:?}", sources);
+}
+
+#[test]
+fn test_spelling_correction() {
+    let ws = Workspace::new("spelling");
+    ws.build("docs", &[]);
+
+    let typo = "kubernetse pdos deploymnet replcia";
+    let sources = ws.search("docs", typo, &["--top-k", "2"]);
+    assert!(sources[0].ends_with("kubernetes.md"), "{:?}", sources);
+
+    let output = ws.ok(&["search", typo, "--index", "docs", "--top-k", "1"]);
+    assert!(
+        output.contains("Showing results for 'kubernetes pods deployment replica' instead of"),
+        "{}",
+        output
+    );
+    let output = ws.ok(&["search", typo, "--index", "docs", "--top-k", "1", "--no-correct"]);
+    assert!(output.contains("Search results for 'kubernetse pdos deploymnet replcia'"), "{}", output);
+}
+
+#[test]
+fn test_embedding_instructions() {
+    let ws = Workspace::new("instructions");
+    std::fs::create_dir_all(ws.dir.join(".config/leann")).unwrap();
+    std::fs::write(
+        ws.dir.join(".config/leann/config.toml"),
+        "[embedding.instructions]\ncode = \"code: \"\nmarkdown = \"passage: \"\n",
+    )
+    .unwrap();
+    ws.build("docs", &["--recompute"]);
+    ws.ok(&["prune", "docs", "--yes"]);
+
+    // Recomputed embeddings use the instruction each passage was built with
+    let results: serde_json::Value = serde_json::from_str(&ws.ok(&[
+        "search", "ownership borrow checker references", "--index", "docs", "--top-k", "10", "--format", "json",
+    ]))
+    .unwrap();
+    let results = results.as_array().unwrap();
+    assert!(results[0]["metadata"]["source"].as_str().unwrap().ends_with("ownership.md"));
+    for result in results {
+        let source = result["metadata"]["source"].as_str().unwrap();
+        let expected = if source.ends_with(".rs") { "code: " } else { "passage: " };
+        assert_eq!(result["metadata"]["embedding_instruction"], expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_prune_and_recompute() {
+    let ws = Workspace::new("prune");
+    ws.build("docs", &["--recompute"]);
+    ws.ok(&["prune", "docs", "--yes"]);
+
+    // Pruned indexes recompute embeddings at search time
+    let sources = ws.search("docs", "ownership borrow checker references", &[]);
+    assert!(sources[0].ends_with("ownership.md"), "{:?}", sources);
+}
+
+#[test]
+fn test_summarize_stores_summaries() {
+    let ws = Workspace::new("summarize");
+    ws.build("docs", &[]);
+
+    let args = ["summarize", "docs", "--llm", "simulated", "--model", "sim", "--format", "json"];
+    let output: serde_json::Value = serde_json::from_str(&ws.ok(&args)).unwrap();
+    assert_eq!(output["files"].as_array().unwrap().len(), 4);
+    assert!(output["corpus"].is_string());
+    assert_eq!(output["stored"], 5);
+
+    let results: serde_json::Value = serde_json::from_str(&ws.ok(&[
+        "search", "summary", "--index", "docs", "--filter", "type=summary", "--top-k", "10", "--format", "json",
+    ]))
+    .unwrap();
+    assert_eq!(results.as_array().unwrap().len(), 5);
+
+    // Unchanged summaries keep their passages
+    let output: serde_json::Value = serde_json::from_str(&ws.ok(&args)).unwrap();
+    assert_eq!(output["stored"], 0);
+}
+
+#[test]
+fn test_raptor_summary_tree() {
+    let ws = Workspace::new("raptor");
+    ws.build("docs", &["--raptor", "--raptor-llm", "simulated", "--raptor-model", "sim", "--raptor-cluster-size", "2"]);
+
+    let results: serde_json::Value = serde_json::from_str(&ws.ok(&[
+        "search", "overview", "--index", "docs", "--filter", "summary_of=cluster", "--top-k", "10", "--format", "json",
+    ]))
+    .unwrap();
+    let mut levels: Vec<u64> = results
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["metadata"]["level"].as_u64().unwrap())
+        .collect();
+    levels.sort();
+    // Four chunks, two clusters of two, one root
+    assert_eq!(levels, [1, 1, 2]);
+    let root = results.as_array().unwrap().iter().find(|r| r["metadata"]["level"] == 2).unwrap();
+    assert_eq!(root["metadata"]["children"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_code_graph_query() {
+    let ws = Workspace::new("graph");
+    std::fs::write(
+        ws.docs().join("calc.rs"),
+        "/// Evaluate an arithmetic expression\npub fn evaluate(input: &str) -> usize {\n    tokenize_expression(input).len()\n}\n",
+    )
+    .unwrap();
+    ws.build("docs", &["--graph", "code"]);
+
+    let output: serde_json::Value =
+        serde_json::from_str(&ws.ok(&["graph", "query", "docs", "tokenize", "--format", "json"])).unwrap();
+    assert_eq!(output["entity"], "tokenize_expression");
+    assert_eq!(
+        output["relations"],
+        serde_json::json!([{ "from": "evaluate", "relation": "calls", "to": "tokenize_expression" }])
+    );
+    let passages: Vec<&str> = output["passages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["metadata"]["source"].as_str().unwrap())
+        .collect();
+    assert!(passages[0].ends_with("tokenizer.rs") && passages[1].ends_with("calc.rs"), "{:?}", passages);
+
+    // Indexes built without --graph say how to get one
+    ws.build("plain", &[]);
+    let output = ws.run(&["graph", "query", "plain", "evaluate"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--graph code"));
+}
+
+#[test]
+fn test_include_definitions() {
+    let ws = Workspace::new("definitions");
+    std::fs::write(
+        ws.docs().join("calc.rs"),
+        "/// Evaluate an arithmetic expression\npub fn evaluate(input: &str) -> usize {\n    tokenize_expression(input).len()\n}\n",
+    )
+    .unwrap();
+    ws.build("docs", &[]);
+
+    let results: serde_json::Value = serde_json::from_str(&ws.ok(&[
+        "search", "evaluate arithmetic", "--index", "docs", "--filter", "source:*calc.rs", "--include-definitions",
+        "--format", "json",
+    ]))
+    .unwrap();
+    let results = results.as_array().unwrap();
+    assert_eq!(results[0]["metadata"]["calls"], serde_json::json!(["tokenize_expression", "len"]));
+    assert!(results[1]["metadata"]["source"].as_str().unwrap().ends_with("tokenizer.rs"), "{:?}", results);
+    assert_eq!(results[1]["metadata"]["definition_of"], "tokenize_expression");
+
+    // Without the flag only the hit is returned
+    assert_eq!(ws.search("docs", "evaluate arithmetic", &["--filter", "source:*calc.rs"]).len(), 1);
+}
+
+#[test]
+fn test_react_sessions() {
+    let ws = Workspace::new("sessions");
+    ws.build("docs", &[]);
+
+    let react = |question: &str| {
+        ws.ok(&["react", "docs", question, "--llm", "simulated", "--model", "sim", "--max-steps", "1", "--session", "bread"])
+    };
+    react("How is sourdough made?");
+    assert!(react("How long does it rise?").contains("Resuming session 'bread' (1 earlier questions)"));
+
+    let sessions: serde_json::Value = serde_json::from_str(&ws.ok(&["sessions", "list", "--format", "json"])).unwrap();
+    assert_eq!(sessions[0]["name"], "bread");
+    assert_eq!(sessions[0]["index"], "docs");
+    assert_eq!(sessions[0]["turns"], 2);
+
+    let session: serde_json::Value = serde_json::from_str(&ws.ok(&["sessions", "show", "bread", "--format", "json"])).unwrap();
+    let turns = session["turns"].as_array().unwrap();
+    assert_eq!(turns[1]["question"], "How long does it rise?");
+    // One step, then the forced final answer
+    assert_eq!(turns[1]["steps"].as_array().unwrap().len(), 2);
+    assert!(ws.ok(&["sessions", "show", "bread"]).contains("=== Final Answer ==="));
+
+    // A session stays on its index
+    ws.build("other", &[]);
+    let output = ws.run(&["react", "other", "Why?", "--llm", "simulated", "--model", "sim", "--session", "bread"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("searches index 'docs'"));
+
+    ws.ok(&["sessions", "remove", "bread"]);
+    assert!(ws.ok(&["sessions"]).contains("No sessions"));
+}
+
+#[test]
+fn test_react_reflection_and_budget() {
+    let ws = Workspace::new("react-limits");
+    ws.build("docs", &[]);
+    let steps = |session: &str, extra: &[&str]| -> Vec<serde_json::Value> {
+        let mut args = vec!["react", "docs", "How is sourdough made?", "--llm", "simulated", "--model", "sim", "--session", session];
+        args.extend_from_slice(extra);
+        ws.ok(&args);
+        let session: serde_json::Value =
+            serde_json::from_str(&ws.ok(&["sessions", "show", session, "--format", "json"])).unwrap();
+        session["turns"][0]["steps"].as_array().unwrap().clone()
+    };
+
+    // The simulated LLM never takes an action, so two steps stall and it reflects
+    let trace = steps("stalled", &["--max-steps", "2"]);
+    let reflections: Vec<bool> = trace.iter().map(|s| s["reflection"].as_bool().unwrap_or(false)).collect();
+    assert_eq!(reflections, [false, false, true, false]);
+
+    // Once the budget is spent the agent stops and gives its final answer
+    assert_eq!(steps("budget", &["--token-budget", "1", "--max-steps", "3"]).len(), 2);
+    assert_eq!(steps("unlimited", &["--max-steps", "3", "--reflect-after", "0"]).len(), 4);
+}
+
+#[test]
+fn test_react_interactive() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let ws = Workspace::new("react-interactive");
+    ws.build("docs", &[]);
+    let mut child = ws
+        .command(&[
+            "react", "docs", "How is sourdough made?", "--interactive", "--llm", "simulated", "--model", "sim",
+            "--max-steps", "1", "--reflect-after", "0", "--session", "bread",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = "/tools\nHow long does it rise?\n/trace\n/reset\n/trace\n/quit\n";
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("search(query)"));
+    // The trace covers both questions until the reset
+    assert!(stdout.contains("=== Question 2 ===\nHow long does it rise?"));
+    assert!(stdout.contains("Scratchpad cleared."));
+    assert!(stdout.contains("No questions since the last reset."));
+
+    let session: serde_json::Value = serde_json::from_str(&ws.ok(&["sessions", "show", "bread", "--format", "json"])).unwrap();
+    assert_eq!(session["turns"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_ask_history_index() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let ws = Workspace::new("ask-history");
+    ws.build("docs", &[]);
+    std::fs::create_dir_all(ws.dir.join(".config/leann")).unwrap();
+    std::fs::write(ws.dir.join(".config/leann/config.toml"), "[analytics]\nask_history = true\n").unwrap();
+
+    let ask = |questions: &str| {
+        let mut child = ws
+            .command(&["ask", "--index", "docs", "--interactive", "--llm", "simulated", "--model", "sim"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(questions.as_bytes()).unwrap();
+        assert!(child.wait().unwrap().success());
+    };
+    // The first answer creates the index, later ones are appended
+    ask("How do I keep a sourdough starter alive?\n/quit\n");
+    ask("How do pods get scheduled?\nWhat does the borrow checker enforce?\n/quit\n");
+
+    let results: serde_json::Value = serde_json::from_str(&ws.ok(&[
+        "search", "sourdough starter", "--index", "_history", "--top-k", "5", "--format", "json",
+    ]))
+    .unwrap();
+    let results = results.as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["metadata"]["question"], "How do I keep a sourdough starter alive?");
+    assert_eq!(results[0]["metadata"]["source"], "docs");
+}
+
+#[test]
+fn test_ask_schema() {
+    let ws = Workspace::new("ask-schema");
+    ws.build("docs", &[]);
+    let schema = ws.dir.join("schema.json");
+    std::fs::write(
+        &schema,
+        r#"{"type": "object", "properties": {"steps": {"type": "array", "items": {"type": "string"}}}, "required": ["steps"]}"#,
+    )
+    .unwrap();
+
+    let stdout = ws.ok(&[
+        "ask", "How is sourdough made?", "--index", "docs", "--llm", "simulated", "--model", "sim",
+        "--schema", schema.to_str().unwrap(),
+    ]);
+    let (_, answer) = stdout.split_once("Answer:\n").unwrap();
+    let (answer, _) = answer.split_once("\n\nUsage:").unwrap();
+    let answer: serde_json::Value = serde_json::from_str(answer).unwrap();
+    assert_eq!(answer["steps"], serde_json::json!(["simulated"]));
+
+    std::fs::write(&schema, r#"{"type": "sometimes"}"#).unwrap();
+    let output = ws.run(&["ask", "anything", "--index", "docs", "--llm", "simulated", "--schema", schema.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid JSON schema"));
+}
+
+#[test]
+fn test_missing_index_error_envelope() {
+    let ws = Workspace::new("errors");
+    let output = ws.run(&["search", "anything", "--index", "missing", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(3));
+    let envelope: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(envelope["error"]["code"], "index_not_found");
+    assert_eq!(envelope["error"]["details"]["index"], "missing");
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn test_serve_search() {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::time::Duration;
+
+    fn request(port: u16, method: &str, path: &str, body: &str) -> Option<(u16, String)> {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).ok()?;
+        stream.set_read_timeout(Some(Duration::from_secs(30))).ok()?;
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        let status = response.split_whitespace().nth(1)?.parse().ok()?;
+        let (_, body) = response.split_once("\r\n\r\n")?;
+        Some((status, body.to_string()))
+    }
+
+    let ws = Workspace::new("serve");
+    ws.build("docs", &[]);
+
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut server = ws
+        .command(&["serve", "docs", "--port", &port.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let healthy = (0..100).any(|_| {
+        std::thread::sleep(Duration::from_millis(100));
+        matches!(request(port, "GET", "/health", ""), Some((200, _)))
+    });
+    let response = healthy.then(|| request(port, "POST", "/search", r#"{"query": "sourdough starter bread"}"#));
+    let _ = server.kill();
+    let _ = server.wait();
+
+    assert!(healthy, "server didn't become healthy");
+    let (status, body) = response.flatten().expect("no response from /search");
+    assert_eq!(status, 200, "{}", body);
+    let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response["query"], "sourdough starter bread");
+    let sources = sources(&response["results"]);
+    assert!(sources[0].ends_with("sourdough.md"), "{:?}", sources);
+}
+
+#[cfg(feature = "mcp")]
+#[test]
+fn test_mcp_sse_transport() {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::time::Duration;
+
+    /// POST a JSON-RPC message; returns the response headers and the JSON of its last event
+    fn post(port: u16, session: Option<&str>, body: &str) -> Option<(String, serde_json::Value)> {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).ok()?;
+        stream.set_read_timeout(Some(Duration::from_secs(30))).ok()?;
+        let session = session.map(|id| format!("Mcp-Session-Id: {}\r\n", id)).unwrap_or_default();
+        write!(
+            stream,
+            "POST /mcp HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Accept: application/json, text/event-stream\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            session,
+            body.len(),
+            body
+        )
+        .ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        let (headers, events) = response.split_once("\r\n\r\n")?;
+        let data = events.lines().filter_map(|l| l.strip_prefix("data: ")).rfind(|d| d.starts_with('{'))?;
+        Some((headers.to_string(), serde_json::from_str(data).ok()?))
+    }
+
+    let ws = Workspace::new("mcp-sse");
+    ws.build("docs", &[]);
+
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut server = ws
+        .command(&["mcp", "--index", "docs", "--transport", "sse", "--port", &port.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let initialize = r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2025-03-26", "capabilities": {}, "clientInfo": {"name": "e2e", "version": "1"}}}"#;
+    let initialized = (0..100).find_map(|_| {
+        std::thread::sleep(Duration::from_millis(100));
+        post(port, None, initialize)
+    });
+    let search = initialized.as_ref().and_then(|(headers, _)| {
+        let session = headers.lines().find_map(|l| l.to_lowercase().strip_prefix("mcp-session-id: ").map(String::from))?;
+        post(port, Some(&session), r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#);
+        let call = r#"{"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "search", "arguments": {"query": "sourdough starter bread", "top_k": 1}}}"#;
+        post(port, Some(&session), call)
+    });
+    let _ = server.kill();
+    let _ = server.wait();
+
+    let (_, initialized) = initialized.expect("MCP server didn't answer initialize");
+    assert_eq!(initialized["result"]["serverInfo"]["name"], "leann-mcp");
+    assert!(initialized["result"]["capabilities"]["prompts"].is_object());
+    let (_, search) = search.expect("no response to the search call");
+    let text = search["result"]["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("sourdough.md"), "{}", text);
+}
+
+#[cfg(feature = "grpc")]
+#[tokio::test]
+async fn test_grpc_search() {
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    mod proto {
+        tonic::include_proto!("leann.v1");
+    }
+    use proto::leann_client::LeannClient;
+
+    let ws = Workspace::new("grpc");
+    ws.build("docs", &[]);
+
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let grpc_port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut server = ws
+        .command(&["serve", "docs", "--port", &port.to_string(), "--grpc-port", &grpc_port.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut client = None;
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        if let Ok(connected) = LeannClient::connect(format!("http://127.0.0.1:{}", grpc_port)).await {
+            client = Some(connected);
+            break;
+        }
+    }
+    let response = match client.as_mut() {
+        Some(client) => Some(
+            client
+                .search(proto::SearchRequest {
+                    query: "sourdough starter bread".to_string(),
+                    top_k: Some(2),
+                    ..Default::default()
+                })
+                .await,
+        ),
+        None => None,
+    };
+    let _ = server.kill();
+    let _ = server.wait();
+
+    let response = response.expect("gRPC server didn't accept connections").unwrap().into_inner();
+    assert_eq!(response.results.len(), 2);
+    let metadata: serde_json::Value = serde_json::from_str(&response.results[0].metadata_json).unwrap();
+    let source = metadata["source"].as_str().unwrap();
+    assert!(source.ends_with("sourdough.md"), "{}", source);
+}
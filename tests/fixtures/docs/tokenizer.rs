@@ -0,0 +1,31 @@
+//! A small tokenizer for arithmetic expressions
+
+/// A token of an arithmetic expression
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    Number(f64),
+    Operator(char),
+}
+
+/// Split an arithmetic expression into number and operator tokens
+pub fn tokenize_expression(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        if !number.is_empty() {
+            tokens.push(Token::Number(number.parse().unwrap()));
+            number.clear();
+        }
+        if "+-*/".contains(c) {
+            tokens.push(Token::Operator(c));
+        }
+    }
+    if !number.is_empty() {
+        tokens.push(Token::Number(number.parse().unwrap()));
+    }
+    tokens
+}
@@ -40,6 +40,17 @@ fn test_build_help() {
     assert!(stdout.contains("--docs"));
     assert!(stdout.contains("--embedding-mode"));
     assert!(stdout.contains("--backend-name"));
+    assert!(stdout.contains("--ask-template"));
+    assert!(stdout.contains("--query-prompt-template"));
+    assert!(stdout.contains("--no-normalize"));
+    assert!(stdout.contains("--embedding-dims"));
+    assert!(stdout.contains("--embedding-precision"));
+    assert!(stdout.contains("--sparse-model"));
+    assert!(stdout.contains("--source"));
+    assert!(stdout.contains("--transcription-model"));
+    assert!(stdout.contains("--multi-vector"));
+    assert!(stdout.contains("--bm25-tokenizer"));
+    assert!(stdout.contains("--bm25-stemmer"));
 }
 
 #[test]
@@ -49,7 +60,22 @@ fn test_search_help() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("--top-k"));
     assert!(stdout.contains("--filter"));
+    assert!(stdout.contains("--filter-mode"));
+    assert!(stdout.contains("--modality"));
+    assert!(stdout.contains("--language"));
     assert!(stdout.contains("--hybrid"));
+    assert!(stdout.contains("--mmr"));
+    assert!(stdout.contains("--group-by"));
+    assert!(stdout.contains("--min-score"));
+    assert!(stdout.contains("--page-size"));
+    assert!(stdout.contains("--no-color"));
+    assert!(stdout.contains("--hyde"));
+    assert!(stdout.contains("--hyde-weight"));
+    assert!(stdout.contains("--multi-query"));
+    assert!(stdout.contains("--sparse"));
+    assert!(stdout.contains("--sparse-alpha"));
+    assert!(stdout.contains("--multi-vector"));
+    assert!(stdout.contains("files"));
 }
 
 #[test]
@@ -60,6 +86,14 @@ fn test_ask_help() {
     assert!(stdout.contains("--llm"));
     assert!(stdout.contains("--model"));
     assert!(stdout.contains("--interactive"));
+    assert!(stdout.contains("--prompt-template"));
+    assert!(stdout.contains("--system-prompt"));
+    assert!(stdout.contains("--context-budget"));
+    assert!(stdout.contains("--hyde"));
+    assert!(stdout.contains("--multi-query"));
+    assert!(stdout.contains("gemini"));
+    assert!(stdout.contains("--temperature"));
+    assert!(stdout.contains("--max-tokens"));
 }
 
 #[test]
@@ -69,6 +103,8 @@ fn test_react_help() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("--max-steps"));
     assert!(stdout.contains("--verbose"));
+    assert!(stdout.contains("--prompt-template"));
+    assert!(stdout.contains("--stop"));
 }
 
 #[test]
@@ -78,4 +114,31 @@ fn test_serve_help() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("--port"));
     assert!(stdout.contains("--cors"));
+    assert!(stdout.contains("--llm"));
+}
+
+#[test]
+fn test_info_help() {
+    let output = cargo_run(&["info", "--help"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--format"));
+}
+
+#[test]
+fn test_update_help() {
+    let output = cargo_run(&["update", "--help"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--yes"));
+    assert!(stdout.contains("--confirm-above"));
+}
+
+#[test]
+fn test_search_all_help() {
+    let output = cargo_run(&["search-all", "--help"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--top-k"));
+    assert!(stdout.contains("--merge"));
 }